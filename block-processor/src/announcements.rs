@@ -0,0 +1,60 @@
+use std::{io::Error as IoError, time::Duration};
+
+use futures::{sync::oneshot, Future};
+use futures_timer::Delay;
+
+// Announcements are only relied on up to this long, in case one got lost.
+const ANNOUNCEMENT_TIMEOUT: Duration = Duration::from_secs(60);
+
+// New blocks announced by the node, which a poller waits for instead of polling while it is
+// subscribed to them.
+pub struct Announcements {
+    subscribed: bool,
+    waiting: Option<oneshot::Sender<()>>,
+}
+
+impl Announcements {
+    pub fn new() -> Self {
+        Announcements {
+            subscribed: false,
+            waiting: None,
+        }
+    }
+
+    pub fn is_subscribed(&self) -> bool {
+        self.subscribed
+    }
+
+    // A poller waiting for an announcement goes back to polling when the subscription is lost.
+    pub fn set_subscribed(&mut self, subscribed: bool) {
+        self.subscribed = subscribed;
+
+        if !subscribed {
+            self.announce();
+        }
+    }
+
+    pub fn announce(&mut self) {
+        if let Some(waiting) = self.waiting.take() {
+            let _ = waiting.send(());
+        }
+    }
+
+    // Resolves at the next announcement, or `None` when not subscribed.
+    pub fn next(&mut self) -> Option<Box<Future<Item = (), Error = IoError>>> {
+        if !self.subscribed {
+            return None;
+        }
+
+        let (sender, receiver) = oneshot::channel();
+        self.waiting = Some(sender);
+
+        let next = receiver
+            .then(|_| Ok(()))
+            .select(Delay::new(ANNOUNCEMENT_TIMEOUT))
+            .map(|_| ())
+            .map_err(|(e, _)| e);
+
+        Some(Box::new(next))
+    }
+}
@@ -17,6 +17,8 @@ pub enum Error {
     BlockchainApiClientError(#[cause] BlockchainApiClientError),
     #[fail(display = "{}", _0)]
     IoError(#[cause] IoError),
+    #[fail(display = "invalid output amount: {}", _0)]
+    InvalidAmount(String),
 }
 
 impl From<ModelError> for Error {
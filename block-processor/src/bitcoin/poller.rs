@@ -1,9 +1,10 @@
-use std::time::Duration;
+use std::{io::Error as IoError, time::Duration};
 
 use actix::prelude::*;
 use futures::{future, stream, Future, Stream};
 use futures_timer::Delay;
 
+use announcements::Announcements;
 use bitcoin::{
     processor::{ProcessBlock, ProcessorAddr},
     Error,
@@ -12,11 +13,12 @@ use blockchain_api_client::{
     bitcoin::{BlockchainApiClientAddr, GetBlockByNumber, GetBlockCount},
     errors::Error as BlockchainApiClientError,
 };
+use catch_up::Progress;
 use core::{
-    bitcoin::{BlockchainStatus, BlockchainStatusPayload},
+    bitcoin::{Block, BlockchainStatus, BlockchainStatusPayload},
     db::postgres::PgExecutorAddr,
 };
-use types::{bitcoin::Network, U128};
+use types::{bitcoin::ChainParams, U128};
 
 const RETRY_LIMIT: usize = 10;
 
@@ -24,7 +26,10 @@ pub struct Poller {
     processor: ProcessorAddr,
     postgres: PgExecutorAddr,
     blockchain_api_client: BlockchainApiClientAddr,
-    network: Network,
+    chain: ChainParams,
+    catch_up_concurrency: usize,
+    announcements: Announcements,
+    announced_block: Option<Block>,
 }
 
 impl Poller {
@@ -32,13 +37,17 @@ impl Poller {
         processor: ProcessorAddr,
         postgres: PgExecutorAddr,
         blockchain_api_client: BlockchainApiClientAddr,
-        network: Network,
+        chain: ChainParams,
+        catch_up_concurrency: usize,
     ) -> Self {
         Poller {
             processor,
             postgres,
             blockchain_api_client,
-            network,
+            chain,
+            catch_up_concurrency,
+            announcements: Announcements::new(),
+            announced_block: None,
         }
     }
 }
@@ -129,22 +138,24 @@ impl Handler<Bootstrap> for Poller {
         let processor = self.processor.clone();
         let blockchain_api_client = self.blockchain_api_client.clone();
         let postgres = self.postgres.clone();
-        let network = self.network;
+        let chain = self.chain;
+        let catch_up_concurrency = self.catch_up_concurrency;
 
         let bootstrap_process = blockchain_api_client
             .send(GetBlockCount)
             .from_err::<Error>()
             .and_then(move |res| res.map_err(|e| Error::from(e)))
             .and_then(move |current_block_number| {
-                BlockchainStatus::find(network, &postgres)
+                BlockchainStatus::find(chain.crypto, chain.network, &postgres)
                     .from_err()
                     .or_else(
                         move |e| -> Box<Future<Item = BlockchainStatus, Error = Error>> {
                             match e {
                                 Error::ModelError(_) => {
                                     let payload = BlockchainStatusPayload {
-                                        network: Some(network),
+                                        network: Some(chain.network),
                                         block_height: Some(current_block_number),
+                                        crypto: Some(chain.crypto),
                                     };
 
                                     Box::new(
@@ -176,10 +187,14 @@ impl Handler<Bootstrap> for Poller {
                         current_block_number
                     );
 
+                    let mut progress =
+                        Progress::new(block_height + U128::from(1), current_block_number);
+
+                    // Blocks are fetched ahead, but processed one by one in order.
                     Box::new(
                         stream::unfold(block_height + U128::from(1), move |block_number| {
                             if block_number <= current_block_number {
-                                return Some(future::ok::<_, _>((
+                                return Some(future::ok::<_, Error>((
                                     block_number,
                                     block_number + U128::from(1),
                                 )));
@@ -187,20 +202,24 @@ impl Handler<Bootstrap> for Poller {
 
                             None
                         })
-                        .for_each(move |block_number| {
-                            let processor = processor.clone();
-                            let blockchain_api_client = blockchain_api_client.clone();
-
+                        .map(move |block_number| {
                             blockchain_api_client
                                 .send(GetBlockByNumber(block_number))
                                 .from_err()
                                 .and_then(move |res| res.map_err(|e| Error::from(e)))
-                                .and_then(move |block| {
-                                    processor
-                                        .send(ProcessBlock(block))
-                                        .from_err()
-                                        .and_then(|res| res.map_err(|e| Error::from(e)))
-                                })
+                                .map(move |block| (block_number, block))
+                        })
+                        .buffered(catch_up_concurrency.max(1))
+                        .and_then(move |(block_number, block)| {
+                            processor
+                                .send(ProcessBlock(block))
+                                .from_err()
+                                .and_then(|res| res.map_err(|e| Error::from(e)))
+                                .map(move |_| block_number)
+                        })
+                        .for_each(move |block_number| {
+                            progress.processed(block_number);
+                            Ok(())
                         })
                         .and_then(move |_| {
                             address
@@ -216,6 +235,44 @@ impl Handler<Bootstrap> for Poller {
     }
 }
 
+// Sent by the subscriber to the node's notifications.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct SetSubscribed(pub bool);
+
+impl Handler<SetSubscribed> for Poller {
+    type Result = ();
+
+    fn handle(&mut self, SetSubscribed(subscribed): SetSubscribed, _: &mut Self::Context) {
+        if subscribed != self.announcements.is_subscribed() {
+            info!(
+                "{} new blocks",
+                if subscribed {
+                    "Subscribed to"
+                } else {
+                    "Polling for"
+                }
+            );
+        }
+
+        self.announcements.set_subscribed(subscribed);
+    }
+}
+
+// Sent with the block itself when the subscriber could decode it.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct BlockAnnounced(pub Option<Block>);
+
+impl Handler<BlockAnnounced> for Poller {
+    type Result = ();
+
+    fn handle(&mut self, BlockAnnounced(block): BlockAnnounced, _: &mut Self::Context) {
+        self.announced_block = block;
+        self.announcements.announce();
+    }
+}
+
 #[derive(Message)]
 #[rtype(result = "Result<(), Error>")]
 pub struct Poll {
@@ -242,36 +299,55 @@ impl Handler<Poll> for Poller {
             return Box::new(future::err(Error::RetryLimitError(retry_count)));
         }
 
-        let polling = blockchain_api_client
-            .send(GetBlockByNumber(block_number))
-            .from_err()
-            .and_then(move |res| res.map_err(|e| Error::from(e)))
+        let announced = self.announcements.next();
+
+        // The announced block spares fetching the next one, unless the node moved on since.
+        let block: Box<Future<Item = Block, Error = Error>> = match self.announced_block.take() {
+            Some(ref block) if block.height == Some(block_number) => {
+                Box::new(future::ok(block.clone()))
+            }
+            _ => Box::new(
+                blockchain_api_client
+                    .send(GetBlockByNumber(block_number))
+                    .from_err()
+                    .and_then(move |res| res.map_err(|e| Error::from(e))),
+            ),
+        };
+
+        let polling = block
             .and_then(move |block| {
                 processor
                     .send(ProcessBlock(block))
                     .from_err()
                     .and_then(|res| res.map_err(|e| Error::from(e)))
-                    .map(move |_| (block_number + U128::from(1), 0))
+                    .map(move |_| (block_number + U128::from(1), 0, true))
             })
             .or_else(move |e| match e {
                 Error::BlockchainApiClientError(e) => match e {
-                    BlockchainApiClientError::EmptyResponseError => future::ok((block_number, 0)),
-                    _ => future::ok((block_number, retry_count + 1)),
+                    BlockchainApiClientError::EmptyResponseError => {
+                        future::ok((block_number, 0, false))
+                    }
+                    _ => future::ok((block_number, retry_count + 1, false)),
                 },
                 _ => future::err(e),
             })
-            .and_then(move |(block_number, retry_count)| {
-                Delay::new(Duration::from_secs(3))
-                    .from_err::<Error>()
-                    .and_then(move |_| {
-                        address
-                            .send(Poll {
-                                block_number: block_number,
-                                retry_count,
-                            })
-                            .from_err::<Error>()
-                            .and_then(|res| res.map_err(|e| Error::from(e)))
-                    })
+            .and_then(move |(block_number, retry_count, processed)| {
+                let wait: Box<Future<Item = (), Error = IoError>> = match announced {
+                    // The next block may be out already.
+                    Some(_) if processed => Box::new(future::ok(())),
+                    Some(announced) if retry_count == 0 => announced,
+                    _ => Box::new(Delay::new(Duration::from_secs(3))),
+                };
+
+                wait.from_err::<Error>().and_then(move |_| {
+                    address
+                        .send(Poll {
+                            block_number: block_number,
+                            retry_count,
+                        })
+                        .from_err::<Error>()
+                        .and_then(|res| res.map_err(|e| Error::from(e)))
+                })
             })
             .map(|_| ());
 
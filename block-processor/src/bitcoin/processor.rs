@@ -6,21 +6,27 @@ use chrono::prelude::*;
 use futures::{future, stream, Future, Stream};
 
 use bitcoin::Error;
+use blockchain_api_client::bitcoin::Psbt;
 use core::{
     bitcoin::{
         Block, BlockchainStatus, BlockchainStatusPayload, SignedTransactionOutput, Transaction,
     },
     db::postgres::PgExecutorAddr,
     payment::{Payment, PaymentPayload},
-    payout::Payout,
+    payout::{Payout, PayoutPayload},
+};
+use mailer::{Notification, NotifierAddr, Notify};
+use types::{
+    bitcoin::{cashaddr, ChainParams},
+    PaymentStatus, PayoutAction, PayoutStatus, H256, U128,
 };
-use types::{bitcoin::Network, currency::Crypto, PaymentStatus, H256, U128};
 
 pub type ProcessorAddr = Addr<Processor>;
 
 pub struct Processor {
-    pub network: Network,
+    pub chain: ChainParams,
     pub postgres: PgExecutorAddr,
+    pub notifier: NotifierAddr,
 }
 
 impl Actor for Processor {
@@ -51,6 +57,8 @@ impl Handler<ProcessMempoolTransactions> for Processor {
         _: &mut Self::Context,
     ) -> Self::Result {
         let postgres = self.postgres.clone();
+        let chain = self.chain;
+        let crypto = chain.crypto;
 
         let process = stream::iter_ok(pooled_transactions.clone())
             .and_then(move |transaction| {
@@ -62,10 +70,11 @@ impl Handler<ProcessMempoolTransactions> for Processor {
                               output|
                               -> ProcessedBlockStream {
                             let output_addresses = output.clone().script.addresses.unwrap();
+                            let address = cashaddr::legacy_address(&chain, &output_addresses[0]);
 
-                            addresses.push(output_addresses[0].clone());
-                            txids.insert(output_addresses[0].clone(), transaction.txid.clone());
-                            outputs.insert(output_addresses[0].clone(), output.clone());
+                            addresses.push(address.clone());
+                            txids.insert(address.clone(), transaction.txid.clone());
+                            outputs.insert(address, output.clone());
 
                             Box::new(future::ok((addresses, txids, outputs)))
                         },
@@ -81,7 +90,7 @@ impl Handler<ProcessMempoolTransactions> for Processor {
                 },
             )
             .and_then(move |(addresses, txids, outputs)| {
-                Payment::find_all_by_address(addresses, Crypto::Btc, &postgres)
+                Payment::find_all_by_address(addresses, crypto, &postgres)
                     .from_err()
                     .map(move |payments| stream::iter_ok(payments))
                     .flatten_stream()
@@ -135,6 +144,225 @@ impl Handler<ProcessMempoolTransactions> for Processor {
     }
 }
 
+// The outputs of the transactions paying an address, with the address in legacy format and the
+// transaction they belong to.
+fn outputs(
+    chain: &ChainParams,
+    transactions: &[Transaction],
+) -> Vec<(String, H256, SignedTransactionOutput)> {
+    transactions
+        .iter()
+        .flat_map(|transaction| {
+            transaction.vout.iter().filter_map(move |output| {
+                let addresses = output.script.addresses.as_ref()?;
+                let address = cashaddr::legacy_address(chain, &addresses[0]);
+
+                Some((address, transaction.txid, output.clone()))
+            })
+        })
+        .collect()
+}
+
+impl Processor {
+    // A rescanned block leaves the processed tip alone. Outputs are credited once either way.
+    fn process_block(&self, block: Block, rescan: bool) -> Box<Future<Item = (), Error = Error>> {
+        info!("Processing block: {}", block.height.unwrap());
+        let postgres = self.postgres.clone();
+        let notifier = self.notifier.clone();
+        let chain = self.chain;
+        let crypto = chain.crypto;
+
+        let cosigned = complete_cosigned_payouts(
+            chain,
+            block.transactions.clone().unwrap(),
+            self.postgres.clone(),
+            self.notifier.clone(),
+        );
+
+        let block_number = block.height.unwrap();
+        let transactions = block.transactions.unwrap();
+        let outputs = outputs(&chain, &transactions);
+        let addresses = outputs
+            .iter()
+            .map(|&(ref address, _, _)| address.clone())
+            .collect();
+        let _postgres = postgres.clone();
+
+        let process = Payment::find_all_by_address(addresses, crypto, &postgres)
+            .from_err()
+            .map(move |payments| {
+                // An address can be paid by several outputs, even within one transaction, and
+                // each is credited on its own.
+                let credits: Vec<(Payment, H256, SignedTransactionOutput)> = payments
+                    .into_iter()
+                    .flat_map(|payment| {
+                        outputs
+                            .iter()
+                            .filter(|&&(ref address, _, _)| *address == payment.address)
+                            .map(|&(_, txid, ref output)| (payment.clone(), txid, output.clone()))
+                            .collect::<Vec<_>>()
+                    })
+                    .collect();
+
+                stream::iter_ok(credits)
+            })
+            .flatten_stream()
+            // Outputs credited by an earlier run over the block are skipped by the insert,
+            // whichever transaction the payment was last paid by.
+            .and_then(move |(payment, txid, output)| {
+                let transaction = transactions.iter().find(|tx| tx.txid == txid).unwrap();
+
+                let amount_paid = BigDecimal::from_str(&format!("{}", output.value))
+                    .expect("failed to parse transaction amount");
+
+                // Block height required = transaction's block number + required number of confirmations - 1.
+                let block_height_required =
+                    block_number + U128::from(payment.confirmations_required) - U128::from(1);
+
+                let insufficient_amount = match payment.status {
+                    PaymentStatus::Pending
+                    | PaymentStatus::Paid
+                    | PaymentStatus::InsufficientAmount => amount_paid < payment.charge,
+                    _ => false,
+                };
+                let notifier = notifier.clone();
+
+                Payout::insert_btc_payout(
+                    amount_paid,
+                    block_height_required,
+                    payment,
+                    transaction.to_owned(),
+                    output.n,
+                    &postgres,
+                )
+                .from_err()
+                .map(move |payout| {
+                    // Already credited by an earlier run over the block.
+                    let payout = match payout {
+                        Some(payout) => payout,
+                        None => return,
+                    };
+
+                    // Payments are notified by the payouter once they reached their
+                    // confirmations.
+                    if payout.action == PayoutAction::Refund && insufficient_amount {
+                        notifier.do_send(Notify(Notification::InsufficientAmount(payout)))
+                    }
+                })
+            })
+            .for_each(move |_| future::ok(()))
+            .and_then(move |_| -> Box<Future<Item = (), Error = Error>> {
+                if rescan {
+                    return Box::new(future::ok(()));
+                }
+
+                let payload = BlockchainStatusPayload {
+                    network: None,
+                    block_height: Some(block_number),
+                    crypto: None,
+                };
+
+                Box::new(
+                    BlockchainStatus::update(chain.crypto, chain.network, payload, &_postgres)
+                        .from_err()
+                        .map(|_| ()),
+                )
+            })
+            .and_then(move |_| cosigned);
+
+        Box::new(process)
+    }
+}
+
+// Completes the multisig payouts whose payment output a transaction of the block spends, their
+// cosigners having signed and broadcast it. The fee is what the spent outputs leave over the
+// outputs of the transaction.
+fn complete_cosigned_payouts(
+    chain: ChainParams,
+    transactions: Vec<Transaction>,
+    postgres: PgExecutorAddr,
+    notifier: NotifierAddr,
+) -> impl Future<Item = (), Error = Error> {
+    let _postgres = postgres.clone();
+
+    future::lazy(move || Payout::find_all_awaiting_cosigners(chain.crypto, &_postgres))
+        .from_err()
+        .and_then(move |payouts| {
+            let cosigned: Vec<_> = payouts
+                .into_iter()
+                .filter_map(|(payout, payout_psbt)| {
+                    let psbt = match Psbt::from_base64(&payout_psbt.psbt) {
+                        Ok(psbt) => psbt,
+                        Err(e) => {
+                            error!("Invalid PSBT of payout {}: {:?}", payout.id, e);
+                            return None;
+                        }
+                    };
+                    let inputs = &psbt.transaction().inputs;
+
+                    let transaction = transactions.iter().find(|transaction| {
+                        transaction.vin.iter().any(|input| {
+                            inputs.iter().any(|spent| {
+                                input.txid == Some(spent.outpoint.hash)
+                                    && input.vout == Some(spent.outpoint.index)
+                            })
+                        })
+                    })?;
+
+                    let spent: u64 = inputs.iter().map(|input| input.value).sum();
+                    let sent: u64 = transaction
+                        .vout
+                        .iter()
+                        .map(|output| (output.value * (100_000_000 as f64)).round() as u64)
+                        .sum();
+
+                    Some((payout, transaction.clone(), spent.saturating_sub(sent)))
+                })
+                .collect();
+
+            future::join_all(cosigned.into_iter().map(move |(payout, transaction, fee)| {
+                let notifier = notifier.clone();
+                let hash = transaction.txid;
+                let destination = transaction
+                    .vout
+                    .first()
+                    .and_then(|output| output.script.addresses.as_ref())
+                    .and_then(|addresses| addresses.first().cloned());
+
+                let mut payout_payload = PayoutPayload::from(payout);
+                payout_payload.transaction_hash = Some(Some(hash));
+                payout_payload.status = Some(PayoutStatus::PaidOut);
+
+                let mut payment_payload = PaymentPayload::new();
+                payment_payload.status = Some(PaymentStatus::Completed);
+
+                Payout::pay_out(
+                    payout.id,
+                    payout_payload,
+                    payment_payload,
+                    BigDecimal::from_str(&format!("{}", fee)).unwrap()
+                        / BigDecimal::from_str("100000000").unwrap(),
+                    destination,
+                    &postgres,
+                )
+                .map(move |payout| {
+                    info!("Cosigners paid out payout {} in {}", payout.id, hash);
+
+                    notifier.do_send(Notify(Notification::PayoutSent {
+                        payout,
+                        transaction_hash: hash,
+                        explorer_url: chain.explorer_transaction_url(&hash),
+                    }))
+                })
+                .or_else(move |e| -> Result<(), Error> {
+                    error!("Failed to complete cosigned payout {}: {:?}", payout.id, e);
+                    Ok(())
+                })
+            }))
+        })
+        .map(|_| ())
+}
+
 #[derive(Message)]
 #[rtype(result = "Result<(), Error>")]
 pub struct ProcessBlock(pub Block);
@@ -143,94 +371,371 @@ impl Handler<ProcessBlock> for Processor {
     type Result = Box<Future<Item = (), Error = Error>>;
 
     fn handle(&mut self, ProcessBlock(block): ProcessBlock, _: &mut Self::Context) -> Self::Result {
-        info!("Processing block: {}", block.height.unwrap());
-        let postgres = self.postgres.clone();
-        let network = self.network;
+        self.process_block(block, false)
+    }
+}
 
-        let process = stream::iter_ok(block.transactions.clone().unwrap())
-            .and_then(move |transaction| {
-                stream::iter_ok(transaction.clone().vout)
-                    .filter(|output| output.script.addresses.is_some())
-                    .fold(
-                        (Vec::new(), HashMap::new(), HashMap::new()),
-                        move |(mut addresses, mut txids, mut outputs),
-                              output|
-                              -> ProcessedBlockStream {
-                            let output_addresses = output.clone().script.addresses.unwrap();
+// Processes a block again, e.g. one skipped with `--skip-missed-blocks`.
+#[derive(Message)]
+#[rtype(result = "Result<(), Error>")]
+pub struct RescanBlock(pub Block);
 
-                            addresses.push(output_addresses[0].clone());
-                            txids.insert(output_addresses[0].clone(), transaction.txid.clone());
-                            outputs.insert(output_addresses[0].clone(), output.clone());
+impl Handler<RescanBlock> for Processor {
+    type Result = Box<Future<Item = (), Error = Error>>;
 
-                            Box::new(future::ok((addresses, txids, outputs)))
-                        },
-                    )
-            })
-            .fold(
-                (Vec::new(), HashMap::new(), HashMap::new()),
-                |(mut addresses, mut txids, mut outputs),
-                 (ad, tx, op)|
-                 -> Box<
-                    Future<
-                        Item = (
-                            Vec<String>,
-                            HashMap<String, H256>,
-                            HashMap<String, SignedTransactionOutput>,
-                        ),
-                        Error = Error,
-                    >,
-                > {
-                    addresses.append(&mut ad.clone());
-                    txids.extend(tx);
-                    outputs.extend(op);
-                    Box::new(future::ok((addresses, txids, outputs)))
-                },
-            )
-            .and_then(move |(addresses, txids, outputs)| {
-                let block_number = block.height.unwrap();
-                let transactions = block.transactions.unwrap();
-                let _postgres = postgres.clone();
+    fn handle(&mut self, RescanBlock(block): RescanBlock, _: &mut Self::Context) -> Self::Result {
+        self.process_block(block, true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+    use uuid::Uuid;
+
+    use blockchain_api_client::bitcoin::UnsignedTransaction;
+    use core::ledger_entry::LedgerEntry;
+    use testing;
+    use types::{
+        bitcoin::Network,
+        currency::{Crypto, Fiat},
+        LedgerAccount, LedgerEvent,
+    };
+
+    fn payment_payload(store_id: Uuid, address: String) -> PaymentPayload {
+        let mut payload = PaymentPayload::new();
+        payload.status = Some(PaymentStatus::Pending);
+        payload.store_id = Some(store_id);
+        payload.index = Some(0);
+        payload.created_by = Some(store_id);
+        payload.set_created_at();
+        payload.fiat = Some(Fiat::Usd);
+        payload.price = Some(BigDecimal::from_str("10").unwrap());
+        payload.crypto = Some(Crypto::Btc);
+        payload.address = Some(address);
+        payload.charge = Some(BigDecimal::from_str("0.001").unwrap());
+        payload.confirmations_required = Some(1);
+        payload.btc_network = Some(Network::Test);
+        payload
+    }
 
-                Payment::find_all_by_address(addresses, Crypto::Btc, &postgres)
+    // A block paying the address with an output of a transaction, as bitcoind returns it.
+    fn block(address: &str, height: u64) -> Block {
+        let txid = testing::hash();
+
+        serde_json::from_value(json!({
+            "hash": testing::hash(),
+            "height": height,
+            "version": 1,
+            "merkleroot": testing::hash(),
+            "tx": [{
+                "txid": txid,
+                "hex": "",
+                "hash": txid,
+                "size": 0,
+                "vsize": 0,
+                "version": 1,
+                "locktime": 0,
+                "vin": [],
+                "vout": [{
+                    "value": 0.001,
+                    "n": 1,
+                    "scriptPubKey": {
+                        "asm": "",
+                        "hex": "",
+                        "reqSigs": 1,
+                        "type": "pubkeyhash",
+                        "addresses": [address],
+                    },
+                }],
+            }],
+            "time": 0,
+            "nonce": 0,
+        }))
+        .unwrap()
+    }
+
+    // A block whose transaction spends the output to the destination, less a fee of 0.0001.
+    fn spending_block(txid: H256, vout: u32, destination: &str, height: u64) -> Block {
+        let mut block = block(destination, height);
+        {
+            let transaction = &mut block.transactions.as_mut().unwrap()[0];
+            transaction.vin = serde_json::from_value(json!([{
+                "txid": txid,
+                "vout": vout,
+                "sequence": 0xffffffffu32,
+            }]))
+            .unwrap();
+            transaction.vout[0].value = 0.0009;
+        }
+        block
+    }
+
+    // Runs the blocks through a processor, each rescanned if flagged, and returns the payment of
+    // the address with its payouts and the ledger entries of its store.
+    fn process(
+        address: String,
+        blocks: Vec<(Block, bool)>,
+    ) -> (Payment, Vec<Payout>, Vec<LedgerEntry>) {
+        let chain = ChainParams::new(Crypto::Btc, Network::Test).unwrap();
+        let store_id = Uuid::new_v4();
+
+        testing::run(move |postgres, notifier| {
+            let processor = Processor {
+                chain,
+                postgres: postgres.clone(),
+                notifier,
+            }
+            .start();
+            let _postgres = postgres.clone();
+            let __postgres = postgres.clone();
+            let ___postgres = postgres.clone();
+
+            // The processor moves the tip of the chain, which has to exist.
+            let status = BlockchainStatusPayload {
+                network: Some(chain.network),
+                block_height: Some(U128::from(0)),
+                crypto: Some(chain.crypto),
+            };
+
+            Box::new(
+                BlockchainStatus::find(chain.crypto, chain.network, &postgres)
+                    .or_else(move |_| BlockchainStatus::insert(status, &_postgres))
+                    .and_then(move |_| {
+                        Payment::insert(payment_payload(store_id, address), &__postgres)
+                    })
                     .from_err()
-                    .map(move |payments| stream::iter_ok(payments))
-                    .flatten_stream()
                     .and_then(move |payment| {
-                        let txid = txids.get(&payment.clone().address).unwrap();
-                        let transaction = transactions.iter().find(|tx| tx.txid == *txid).unwrap();
-
-                        let amount_paid = BigDecimal::from_str(&format!(
-                            "{}",
-                            outputs.get(&payment.address).unwrap().value
-                        ))
-                        .expect("failed to parse transaction amount");
-
-                        // Block height required = transaction's block number + required number of confirmations - 1.
-                        let block_height_required = block_number
-                            + U128::from(payment.confirmations_required)
-                            - U128::from(1);
-
-                        Payout::insert_btc_payout(
-                            amount_paid,
-                            block_height_required,
-                            payment,
-                            transaction.to_owned(),
-                            &postgres,
-                        )
-                        .from_err()
+                        stream::iter_ok(blocks)
+                            .for_each(
+                                move |(block, rescan)| -> Box<Future<Item = (), Error = Error>> {
+                                    if rescan {
+                                        Box::new(
+                                            processor
+                                                .send(RescanBlock(block))
+                                                .from_err()
+                                                .and_then(|res| res),
+                                        )
+                                    } else {
+                                        Box::new(
+                                            processor
+                                                .send(ProcessBlock(block))
+                                                .from_err()
+                                                .and_then(|res| res),
+                                        )
+                                    }
+                                },
+                            )
+                            .map(move |_| payment)
                     })
-                    .for_each(move |_| future::ok(()))
-                    .and_then(move |_| {
-                        let payload = BlockchainStatusPayload {
-                            network: None,
-                            block_height: Some(block_number),
-                        };
+                    .and_then(move |payment| {
+                        let postgres = ___postgres.clone();
+
+                        Payment::find_by_id(payment.id, &___postgres)
+                            .join(Payout::find_all_by_payments(vec![payment.id], &postgres))
+                            .join(LedgerEntry::find_by_store(store_id, 10, 0, &postgres))
+                            .map(|((payment, payouts), entries)| (payment, payouts, entries))
+                            .from_err()
+                    }),
+            ) as Box<Future<Item = _, Error = Error>>
+        })
+        .unwrap()
+    }
 
-                        BlockchainStatus::update(network, payload, &_postgres).from_err()
-                    })
-                    .map(|_| ())
-            });
+    #[test]
+    fn processing_a_block_again_credits_its_outputs_once() {
+        let address = format!("test-{}", Uuid::new_v4().simple());
+        let block = block(&address, 100);
+
+        let (payment, payouts, entries) =
+            process(address, vec![(block.clone(), false), (block, false)]);
+
+        assert_eq!(payment.status, PaymentStatus::Confirmed);
+        assert_eq!(
+            payment.amount_paid,
+            Some(BigDecimal::from_str("0.001").unwrap())
+        );
+        assert_eq!(payment.block_height_required, Some(U128::from(100)));
+
+        assert_eq!(payouts.len(), 1);
+        assert_eq!(payouts[0].status, PayoutStatus::Pending);
+        assert_eq!(payouts[0].action, PayoutAction::Payout);
+        assert_eq!(payouts[0].output_index, Some(1));
+
+        assert_eq!(entries.len(), 2);
+        assert!(entries
+            .iter()
+            .all(|entry| entry.payout_id == payouts[0].id && entry.event == LedgerEvent::Credit));
+    }
 
-        Box::new(process)
+    #[test]
+    fn every_output_paying_an_address_in_a_block_is_credited() {
+        let address = format!("test-{}", Uuid::new_v4().simple());
+        let mut block = block(&address, 100);
+        {
+            // A second output of the transaction, and another transaction, pay the address too.
+            let transactions = block.transactions.as_mut().unwrap();
+            let mut output = transactions[0].vout[0].clone();
+            output.n = 2;
+            output.value = 0.0005;
+            transactions[0].vout.push(output);
+
+            let mut transaction = transactions[0].clone();
+            transaction.txid = testing::hash();
+            transaction.hash = transaction.txid;
+            transactions.push(transaction);
+        }
+        let transactions = block.transactions.clone().unwrap();
+
+        let (_, payouts, entries) = process(address, vec![(block, false)]);
+
+        let mut outputs: Vec<(Option<H256>, Option<i32>)> = payouts
+            .iter()
+            .map(|payout| (payout.payment_transaction_hash, payout.output_index))
+            .collect();
+        outputs.sort_by_key(|&(hash, index)| (hash.map(|hash| hash.to_string()), index));
+
+        let mut expected = vec![
+            (Some(transactions[0].hash), Some(1)),
+            (Some(transactions[0].hash), Some(2)),
+            (Some(transactions[1].hash), Some(1)),
+            (Some(transactions[1].hash), Some(2)),
+        ];
+        expected.sort_by_key(|&(hash, index)| (hash.map(|hash| hash.to_string()), index));
+
+        assert_eq!(outputs, expected);
+        assert_eq!(entries.len(), 8);
+    }
+
+    #[test]
+    fn rescanning_a_block_skips_outputs_credited_before_a_later_payment() {
+        let address = format!("test-{}", Uuid::new_v4().simple());
+        let first = block(&address, 100);
+        let second = block(&address, 101);
+        let second_hash = second.transactions.as_ref().unwrap()[0].hash;
+
+        // The payment was last paid by the transaction of the second block when the first one is
+        // rescanned.
+        let (payment, payouts, entries) = process(
+            address,
+            vec![(first.clone(), false), (second, false), (first, true)],
+        );
+
+        assert_eq!(payment.status, PaymentStatus::Confirmed);
+        assert_eq!(payment.transaction_hash, Some(second_hash));
+
+        assert_eq!(payouts.len(), 2);
+        assert_eq!(
+            payouts
+                .iter()
+                .filter(|payout| payout.action == PayoutAction::Payout)
+                .count(),
+            1
+        );
+        assert_eq!(
+            payouts
+                .iter()
+                .filter(|payout| payout.action == PayoutAction::Refund)
+                .count(),
+            1
+        );
+
+        assert_eq!(entries.len(), 4);
+        assert!(entries
+            .iter()
+            .all(|entry| entry.event == LedgerEvent::Credit));
+    }
+
+    #[test]
+    fn spending_a_payment_output_completes_the_payout_awaiting_its_cosigners() {
+        let chain = ChainParams::new(Crypto::Btc, Network::Test).unwrap();
+        let store_id = Uuid::new_v4();
+        let address = format!("test-{}", Uuid::new_v4().simple());
+        let destination = format!("test-{}", Uuid::new_v4().simple());
+        let paid = block(&address, 100);
+        let paid_hash = paid.transactions.as_ref().unwrap()[0].txid;
+        let spending = spending_block(paid_hash, 1, &destination, 101);
+        let spending_hash = spending.transactions.as_ref().unwrap()[0].txid;
+
+        // The unsigned transaction of the cosigners, sweeping the output with a fee of 0.0001.
+        let mut raw_transaction = vec![1, 0, 0, 0, 1];
+        raw_transaction.extend(paid_hash.iter().rev());
+        raw_transaction.extend_from_slice(&[1, 0, 0, 0, 0, 0xff, 0xff, 0xff, 0xff, 1]);
+        raw_transaction.extend_from_slice(&[0x90, 0x5f, 0x01, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        let mut transaction = UnsignedTransaction::deserialize(&raw_transaction).unwrap();
+        transaction.inputs[0].value = 100_000;
+        let psbt = Psbt::new(transaction, vec![Vec::new()]).to_base64();
+
+        let (payment, payout, entries) = testing::run(move |postgres, notifier| {
+            let processor = Processor {
+                chain,
+                postgres: postgres.clone(),
+                notifier,
+            }
+            .start();
+            let _processor = processor.clone();
+            let _postgres = postgres.clone();
+            let __postgres = postgres.clone();
+            let ___postgres = postgres.clone();
+
+            let status = BlockchainStatusPayload {
+                network: Some(chain.network),
+                block_height: Some(U128::from(0)),
+                crypto: Some(chain.crypto),
+            };
+
+            Box::new(
+                BlockchainStatus::find(chain.crypto, chain.network, &postgres)
+                    .or_else(move |_| BlockchainStatus::insert(status, &_postgres))
+                    .and_then(move |_| {
+                        Payment::insert(payment_payload(store_id, address), &__postgres)
+                    })
+                    .from_err()
+                    .and_then(move |payment| {
+                        processor
+                            .send(ProcessBlock(paid))
+                            .from_err()
+                            .and_then(|res| res)
+                            .map(move |_| payment)
+                    })
+                    .and_then(move |payment| {
+                        let postgres = ___postgres.clone();
+
+                        Payout::find_all_by_payments(vec![payment.id], &___postgres)
+                            .and_then(move |payouts| {
+                                Payout::await_cosigners(payouts[0].id, psbt, &postgres)
+                            })
+                            .from_err()
+                            .and_then(move |payout| {
+                                _processor
+                                    .send(ProcessBlock(spending))
+                                    .from_err()
+                                    .and_then(|res| res)
+                                    .map(move |_| payout)
+                            })
+                    })
+                    .and_then(move |payout| {
+                        Payment::find_by_id(payout.payment_id, &postgres)
+                            .join(Payout::find_by_id(payout.id, &postgres))
+                            .join(LedgerEntry::find_by_store(store_id, 10, 0, &postgres))
+                            .map(|((payment, payout), entries)| (payment, payout, entries))
+                            .from_err()
+                    }),
+            ) as Box<Future<Item = _, Error = Error>>
+        })
+        .unwrap();
+
+        assert_eq!(payout.status, PayoutStatus::PaidOut);
+        assert_eq!(payout.transaction_hash, Some(spending_hash));
+        assert_eq!(payment.status, PaymentStatus::Completed);
+
+        let fee = entries
+            .iter()
+            .find(|entry| entry.account == LedgerAccount::NetworkFees)
+            .unwrap();
+        assert_eq!(fee.event, LedgerEvent::Payout);
+        assert_eq!(fee.amount, BigDecimal::from_str("0.0001").unwrap());
+        assert_eq!(fee.destination, Some(destination));
     }
 }
@@ -0,0 +1,276 @@
+use std::{str::FromStr, time::Duration};
+
+use actix::{
+    fut::{self, wrap_future},
+    prelude::*,
+};
+use bigdecimal::BigDecimal;
+use chrono::{prelude::*, Duration as ChronoDuration};
+use futures::{future, stream, Future, Stream};
+
+use bitcoin::Error;
+use blockchain_api_client::bitcoin::{
+    BlockchainApiClientAddr, GetRawTransaction, ScanTxOutSet, Unspent,
+};
+use config::ReconciliationConfig;
+use core::{
+    bitcoin::BlockchainStatus, db::postgres::PgExecutorAddr, payment::Payment, payout::Payout,
+};
+use discrepancy;
+use mailer::{Notification, NotifierAddr, Notify};
+use types::{
+    bitcoin::{cashaddr, ChainParams},
+    PaymentStatus, PayoutAction, U128,
+};
+
+// Looks up the addresses of recent unpaid payments in the UTXO set, and flags or credits the
+// ones which received coins in blocks the processor has already gone through.
+pub struct Reconciler {
+    chain: ChainParams,
+    postgres: PgExecutorAddr,
+    blockchain_api_client: BlockchainApiClientAddr,
+    notifier: NotifierAddr,
+    config: ReconciliationConfig,
+    running: bool,
+}
+
+impl Reconciler {
+    pub fn new(
+        chain: ChainParams,
+        postgres: PgExecutorAddr,
+        blockchain_api_client: BlockchainApiClientAddr,
+        notifier: NotifierAddr,
+        config: ReconciliationConfig,
+    ) -> Self {
+        Reconciler {
+            chain,
+            postgres,
+            blockchain_api_client,
+            notifier,
+            config,
+            running: false,
+        }
+    }
+}
+
+impl Actor for Reconciler {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Context<Self>) {
+        let interval = Duration::from_secs(self.config.interval_secs);
+
+        ctx.run_interval(interval, move |reconciler, ctx| {
+            // A scan of the UTXO set can outlast the interval.
+            if reconciler.running {
+                return;
+            }
+            reconciler.running = true;
+
+            let process = wrap_future(reconciler.reconcile())
+                .map_err(|e: Error, _: &mut Reconciler, _| error!("{:?}", e))
+                .then(|_, reconciler: &mut Reconciler, _| {
+                    reconciler.running = false;
+                    fut::ok(())
+                });
+
+            ctx.spawn(process);
+        });
+    }
+}
+
+impl Reconciler {
+    fn reconcile(&self) -> Box<Future<Item = (), Error = Error>> {
+        let postgres = self.postgres.clone();
+        let blockchain_api_client = self.blockchain_api_client.clone();
+        let notifier = self.notifier.clone();
+        let chain = self.chain;
+        let auto_credit = self.config.auto_credit;
+        let since = Utc::now() - ChronoDuration::hours(self.config.lookback_hours);
+
+        let process = Payment::find_all_unpaid_since(chain.crypto, since, &postgres)
+            .join(BlockchainStatus::find(
+                chain.crypto,
+                chain.network,
+                &postgres,
+            ))
+            .from_err()
+            .and_then(
+                move |(payments, status)| -> Box<Future<Item = (), Error = Error>> {
+                    // Flagged payments are credited once auto-crediting is turned on.
+                    let payments: Vec<Payment> = payments
+                        .into_iter()
+                        .filter(|payment| auto_credit || payment.discrepancy_flagged_at.is_none())
+                        .collect();
+
+                    if payments.is_empty() {
+                        return Box::new(future::ok(()));
+                    }
+
+                    let addresses = payments
+                        .iter()
+                        .map(|payment| payment.address.clone())
+                        .collect();
+                    let block_height = status.block_height;
+
+                    let process = blockchain_api_client
+                        .send(ScanTxOutSet(addresses))
+                        .from_err()
+                        .and_then(|res| res.map_err(|e| Error::from(e)))
+                        .and_then(move |scan| {
+                            stream::iter_ok(payments)
+                                .filter_map(move |payment| {
+                                    // Coins in blocks the processor hasn't reached yet are still
+                                    // on their way to it.
+                                    let unspents: Vec<Unspent> = scan
+                                        .unspents
+                                        .iter()
+                                        .filter(|unspent| {
+                                            let address = unspent.address().map(|address| {
+                                                cashaddr::legacy_address(&chain, address)
+                                            });
+
+                                            address.as_ref() == Some(&payment.address)
+                                                && unspent.height <= block_height
+                                        })
+                                        .cloned()
+                                        .collect();
+
+                                    if unspents.is_empty() {
+                                        None
+                                    } else {
+                                        Some((payment, unspents))
+                                    }
+                                })
+                                .and_then(move |(payment, unspents)| {
+                                    reconcile_payment(
+                                        payment,
+                                        unspents,
+                                        auto_credit,
+                                        &blockchain_api_client,
+                                        &notifier,
+                                        &postgres,
+                                    )
+                                })
+                                .for_each(|_| future::ok(()))
+                        });
+
+                    Box::new(process)
+                },
+            );
+
+        Box::new(process)
+    }
+}
+
+fn reconcile_payment(
+    payment: Payment,
+    unspents: Vec<Unspent>,
+    auto_credit: bool,
+    blockchain_api_client: &BlockchainApiClientAddr,
+    notifier: &NotifierAddr,
+    postgres: &PgExecutorAddr,
+) -> Box<Future<Item = (), Error = Error>> {
+    // A payment with an output we can't make sense of is left for the next run, the others
+    // still being reconciled.
+    let amounts: Result<Vec<BigDecimal>, Error> = unspents.iter().map(amount).collect();
+    let amounts = match amounts {
+        Ok(amounts) => amounts,
+        Err(e) => {
+            error!("Skipping payment {}: {:?}", payment.id, e);
+            return Box::new(future::ok(()));
+        }
+    };
+
+    if auto_credit {
+        return Box::new(credit(
+            payment,
+            unspents.into_iter().zip(amounts).collect(),
+            blockchain_api_client,
+            notifier,
+            postgres,
+        ));
+    }
+
+    let found = json!({
+        "address": payment.address,
+        "amount": amounts
+            .into_iter()
+            .fold(BigDecimal::from_str("0").unwrap(), |sum, amount| sum + amount)
+            .to_string(),
+        "transactions": unspents
+            .iter()
+            .map(|unspent| format!("{}", unspent.txid))
+            .collect::<Vec<String>>(),
+    });
+
+    Box::new(discrepancy::flag(payment, found, postgres).from_err())
+}
+
+fn amount(unspent: &Unspent) -> Result<BigDecimal, Error> {
+    let amount = format!("{}", unspent.amount);
+
+    BigDecimal::from_str(&amount).map_err(|_| Error::InvalidAmount(amount))
+}
+
+// Credits the payment the same way the processor would have, with a payout for each output
+// found, the earliest first.
+fn credit(
+    payment: Payment,
+    mut unspents: Vec<(Unspent, BigDecimal)>,
+    blockchain_api_client: &BlockchainApiClientAddr,
+    notifier: &NotifierAddr,
+    postgres: &PgExecutorAddr,
+) -> impl Future<Item = (), Error = Error> {
+    let postgres = postgres.clone();
+    let notifier = notifier.clone();
+    let blockchain_api_client = blockchain_api_client.clone();
+
+    // Heights only have a partial order, though every two of them compare.
+    unspents.sort_by(|&(ref a, _), &(ref b, _)| a.height.partial_cmp(&b.height).unwrap());
+
+    stream::iter_ok(unspents).for_each(move |(unspent, amount_paid)| {
+        info!(
+            "Crediting payment {} with missed output {}:{}",
+            payment.id, unspent.txid, unspent.vout
+        );
+
+        let payment = payment.clone();
+        let postgres = postgres.clone();
+        let notifier = notifier.clone();
+
+        blockchain_api_client
+            .send(GetRawTransaction(unspent.txid))
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+            .and_then(move |transaction| {
+                // Block height required = transaction's block number + required number of confirmations - 1.
+                let block_height_required =
+                    unspent.height + U128::from(payment.confirmations_required) - U128::from(1);
+
+                let insufficient_amount = match payment.status {
+                    PaymentStatus::Pending => amount_paid < payment.charge,
+                    _ => false,
+                };
+
+                Payout::insert_btc_payout(
+                    amount_paid,
+                    block_height_required,
+                    payment,
+                    transaction,
+                    unspent.vout,
+                    &postgres,
+                )
+                .from_err()
+                .map(move |payout| {
+                    let payout = match payout {
+                        Some(payout) => payout,
+                        None => return,
+                    };
+
+                    if payout.action == PayoutAction::Refund && insufficient_amount {
+                        notifier.do_send(Notify(Notification::InsufficientAmount(payout)))
+                    }
+                })
+            })
+    })
+}
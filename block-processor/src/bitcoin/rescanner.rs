@@ -0,0 +1,184 @@
+use std::time::Duration;
+
+use actix::{
+    fut::{self, wrap_future},
+    prelude::*,
+};
+use futures::{future, stream, Future, Stream};
+use uuid::Uuid;
+
+use bitcoin::{
+    processor::{ProcessorAddr, RescanBlock},
+    Error,
+};
+use blockchain_api_client::bitcoin::{BlockchainApiClientAddr, GetBlockByNumber};
+use core::{
+    bitcoin::BlockchainStatus,
+    block_rescan::{BlockRescan, BlockRescanPayload},
+    db::postgres::PgExecutorAddr,
+    ModelError,
+};
+use types::{bitcoin::ChainParams, RescanStatus, U128};
+
+// Works through the rescans requested for the chain, one at a time and oldest first.
+pub struct Rescanner {
+    chain: ChainParams,
+    processor: ProcessorAddr,
+    postgres: PgExecutorAddr,
+    blockchain_api_client: BlockchainApiClientAddr,
+    running: bool,
+}
+
+impl Rescanner {
+    pub fn new(
+        chain: ChainParams,
+        processor: ProcessorAddr,
+        postgres: PgExecutorAddr,
+        blockchain_api_client: BlockchainApiClientAddr,
+    ) -> Self {
+        Rescanner {
+            chain,
+            processor,
+            postgres,
+            blockchain_api_client,
+            running: false,
+        }
+    }
+}
+
+impl Actor for Rescanner {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Context<Self>) {
+        ctx.run_interval(Duration::new(10, 0), move |rescanner, ctx| {
+            if rescanner.running {
+                return;
+            }
+            rescanner.running = true;
+
+            let process = wrap_future(rescanner.rescan_next())
+                .map_err(|e: Error, _: &mut Rescanner, _| error!("{:?}", e))
+                .then(|_, rescanner: &mut Rescanner, _| {
+                    rescanner.running = false;
+                    fut::ok(())
+                });
+
+            ctx.spawn(process);
+        });
+    }
+}
+
+impl Rescanner {
+    fn rescan_next(&self) -> Box<Future<Item = (), Error = Error>> {
+        let postgres = self.postgres.clone();
+        let processor = self.processor.clone();
+        let blockchain_api_client = self.blockchain_api_client.clone();
+        let chain = self.chain;
+
+        let process = BlockRescan::find_next(chain.crypto, None, &postgres)
+            .join(BlockchainStatus::find(
+                chain.crypto,
+                chain.network,
+                &postgres,
+            ))
+            .from_err()
+            .and_then(
+                move |(rescan, status)| -> Box<Future<Item = (), Error = Error>> {
+                    let rescan = match rescan {
+                        Some(rescan) => rescan,
+                        None => return Box::new(future::ok(())),
+                    };
+
+                    // The poller is still on its way to these blocks.
+                    if rescan.to_block > status.block_height {
+                        return Box::new(
+                            finish(
+                                rescan.id,
+                                Some(format!(
+                                    "block {} hasn't been processed yet",
+                                    rescan.to_block
+                                )),
+                                &postgres,
+                            )
+                            .from_err(),
+                        );
+                    }
+
+                    info!(
+                        "Rescanning blocks {} ~ {} of {}",
+                        rescan.next_block, rescan.to_block, chain.crypto
+                    );
+
+                    let id = rescan.id;
+                    let to_block = rescan.to_block;
+                    let _postgres = postgres.clone();
+
+                    let mut payload = BlockRescanPayload::new();
+                    payload.status = Some(RescanStatus::Running);
+
+                    let process = BlockRescan::update(id, payload, &postgres)
+                        .from_err()
+                        .and_then(move |rescan| {
+                            stream::unfold(rescan.next_block, move |block_number| {
+                                if block_number <= to_block {
+                                    return Some(future::ok::<_, Error>((
+                                        block_number,
+                                        block_number + U128::from(1),
+                                    )));
+                                }
+
+                                None
+                            })
+                            .for_each(move |block_number| {
+                                let processor = processor.clone();
+                                let postgres = postgres.clone();
+
+                                blockchain_api_client
+                                    .send(GetBlockByNumber(block_number))
+                                    .from_err()
+                                    .and_then(|res| res.map_err(|e| Error::from(e)))
+                                    .and_then(move |block| {
+                                        processor
+                                            .send(RescanBlock(block))
+                                            .from_err()
+                                            .and_then(|res| res.map_err(|e| Error::from(e)))
+                                    })
+                                    // Progress is kept, so that an interrupted rescan resumes.
+                                    .and_then(move |_| {
+                                        let mut payload = BlockRescanPayload::new();
+                                        payload.next_block = Some(block_number + U128::from(1));
+
+                                        BlockRescan::update(id, payload, &postgres)
+                                            .from_err()
+                                            .map(|_| ())
+                                    })
+                            })
+                        })
+                        .then(move |res| {
+                            let error = res.err().map(|e| format!("{}", e));
+
+                            finish(id, error, &_postgres).from_err()
+                        });
+
+                    Box::new(process)
+                },
+            );
+
+        Box::new(process)
+    }
+}
+
+fn finish(
+    id: Uuid,
+    error: Option<String>,
+    postgres: &PgExecutorAddr,
+) -> impl Future<Item = (), Error = ModelError> {
+    let mut payload = BlockRescanPayload::new();
+    payload.status = Some(match error {
+        Some(_) => RescanStatus::Failed,
+        None => RescanStatus::Completed,
+    });
+    payload.error = Some(error);
+
+    BlockRescan::update(id, payload, postgres).map(|_| ())
+}
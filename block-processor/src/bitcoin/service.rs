@@ -4,28 +4,42 @@ use bitcoin::{
     pb_poller::{Poller as PendingBlocksPoller, StartPolling as StartPollingPendings},
     poller::{Poller, StartPolling},
     processor::Processor,
+    reconciler::Reconciler,
+    rescanner::Rescanner,
+    subscriber::Subscriber,
 };
 use blockchain_api_client::bitcoin::BlockchainApiClientAddr;
+use config::ReconciliationConfig;
 use core::db::postgres;
-use types::bitcoin::Network;
+use mailer::NotifierAddr;
+use types::bitcoin::ChainParams;
 
 pub fn run(
     postgres: postgres::PgExecutorAddr,
     blockchain_api_client: BlockchainApiClientAddr,
-    network: Network,
+    notifier: NotifierAddr,
+    chain: ChainParams,
     skip_missed_blocks: bool,
+    catch_up_concurrency: usize,
 ) -> (Addr<Processor>, Addr<Poller>, Addr<PendingBlocksPoller>) {
     let pg = postgres.clone();
     let block_processor = Arbiter::start(move |_| Processor {
-        network,
+        chain,
         postgres: pg,
+        notifier,
     });
 
     let _block_processor = block_processor.clone();
     let _postgres = postgres.clone();
     let _blockchain_api_client = blockchain_api_client.clone();
     let poller = Supervisor::start(move |_| {
-        Poller::new(_block_processor, _postgres, _blockchain_api_client, network)
+        Poller::new(
+            _block_processor,
+            _postgres,
+            _blockchain_api_client,
+            chain,
+            catch_up_concurrency,
+        )
     });
     poller.do_send(StartPolling { skip_missed_blocks });
 
@@ -37,3 +51,34 @@ pub fn run(
 
     (block_processor, poller, pb_poller)
 }
+
+pub fn run_reconciler(
+    postgres: postgres::PgExecutorAddr,
+    blockchain_api_client: BlockchainApiClientAddr,
+    notifier: NotifierAddr,
+    chain: ChainParams,
+    config: ReconciliationConfig,
+) -> Addr<Reconciler> {
+    Arbiter::start(move |_| {
+        Reconciler::new(chain, postgres, blockchain_api_client, notifier, config)
+    })
+}
+
+pub fn run_rescanner(
+    processor: Addr<Processor>,
+    postgres: postgres::PgExecutorAddr,
+    blockchain_api_client: BlockchainApiClientAddr,
+    chain: ChainParams,
+) -> Addr<Rescanner> {
+    Arbiter::start(move |_| Rescanner::new(chain, processor, postgres, blockchain_api_client))
+}
+
+pub fn run_subscriber(
+    chain: ChainParams,
+    zmq_url: String,
+    processor: Addr<Processor>,
+    poller: Addr<Poller>,
+    pb_poller: Addr<PendingBlocksPoller>,
+) -> Addr<Subscriber> {
+    Arbiter::start(move |_| Subscriber::new(chain, zmq_url, processor, poller, pb_poller))
+}
@@ -0,0 +1,190 @@
+use std::{cmp, thread, time::Duration};
+
+use actix::{fut::wrap_future, prelude::*};
+use futures::Future;
+use zmq;
+
+use bitcoin::{
+    pb_poller::{Poller as PendingBlocksPoller, SetSubscribed as SetSubscribedPendings},
+    poller::{BlockAnnounced, Poller, SetSubscribed},
+    processor::{ProcessMempoolTransactions, ProcessorAddr},
+    Error,
+};
+use blockchain_api_client::bitcoin::{decode_block, decode_transaction};
+use types::bitcoin::ChainParams;
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(60);
+
+// Receives the node's ZMQ notifications, so that the pollers only poll while it's unreachable.
+pub struct Subscriber {
+    chain: ChainParams,
+    zmq_url: String,
+    processor: ProcessorAddr,
+    poller: Addr<Poller>,
+    pb_poller: Addr<PendingBlocksPoller>,
+}
+
+impl Subscriber {
+    pub fn new(
+        chain: ChainParams,
+        zmq_url: String,
+        processor: ProcessorAddr,
+        poller: Addr<Poller>,
+        pb_poller: Addr<PendingBlocksPoller>,
+    ) -> Self {
+        Subscriber {
+            chain,
+            zmq_url,
+            processor,
+            poller,
+            pb_poller,
+        }
+    }
+}
+
+impl Actor for Subscriber {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Context<Self>) {
+        let address = ctx.address();
+        let zmq_url = self.zmq_url.clone();
+        let monitor_url = format!("inproc://{}-zmq-monitor", self.chain.crypto);
+
+        // ZMQ sockets block, so they get a thread of their own. Sockets reconnect by themselves,
+        // the subscription is set up again when it failed altogether.
+        thread::spawn(move || {
+            let mut delay = RECONNECT_DELAY;
+
+            loop {
+                if let Err(e) = listen(&zmq_url, &monitor_url, &address, &mut delay) {
+                    error!("Failed to subscribe to {}: {}", zmq_url, e);
+                    address.do_send(Notification::Disconnected);
+                }
+
+                thread::sleep(delay);
+                delay = cmp::min(delay * 2, MAX_RECONNECT_DELAY);
+            }
+        });
+    }
+}
+
+fn listen(
+    zmq_url: &str,
+    monitor_url: &str,
+    subscriber: &Addr<Subscriber>,
+    delay: &mut Duration,
+) -> Result<(), zmq::Error> {
+    let context = zmq::Context::new();
+
+    let socket = context.socket(zmq::SUB)?;
+    socket.monitor(monitor_url, zmq::SocketEvent::ALL.to_raw() as i32)?;
+    socket.set_subscribe(b"rawblock")?;
+    socket.set_subscribe(b"rawtx")?;
+
+    let monitor = context.socket(zmq::PAIR)?;
+    monitor.connect(monitor_url)?;
+
+    socket.connect(zmq_url)?;
+
+    loop {
+        let mut items = [
+            socket.as_poll_item(zmq::POLLIN),
+            monitor.as_poll_item(zmq::POLLIN),
+        ];
+        zmq::poll(&mut items, -1)?;
+
+        // Monitor events start with the event id, as a little-endian u16.
+        if items[1].is_readable() {
+            let event = monitor.recv_multipart(0)?;
+
+            if event.len() > 0 && event[0].len() >= 2 {
+                let id = u16::from(event[0][0]) | u16::from(event[0][1]) << 8;
+
+                match zmq::SocketEvent::from_raw(id) {
+                    zmq::SocketEvent::CONNECTED => {
+                        *delay = RECONNECT_DELAY;
+                        subscriber.do_send(Notification::Connected)
+                    }
+                    zmq::SocketEvent::DISCONNECTED => {
+                        subscriber.do_send(Notification::Disconnected)
+                    }
+                    _ => (),
+                }
+            }
+        }
+
+        // Notifications are the topic, the serialized block or transaction and a sequence number.
+        if items[0].is_readable() {
+            let mut message = socket.recv_multipart(0)?;
+
+            if message.len() < 2 {
+                continue;
+            }
+
+            let body = message.swap_remove(1);
+            match message[0].as_slice() {
+                b"rawblock" => subscriber.do_send(Notification::Block(body)),
+                b"rawtx" => subscriber.do_send(Notification::Transaction(body)),
+                _ => (),
+            }
+        }
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "()")]
+pub enum Notification {
+    Connected,
+    Disconnected,
+    Block(Vec<u8>),
+    Transaction(Vec<u8>),
+}
+
+impl Handler<Notification> for Subscriber {
+    type Result = ();
+
+    fn handle(&mut self, notification: Notification, ctx: &mut Self::Context) -> Self::Result {
+        match notification {
+            Notification::Connected => {
+                info!("Subscribed to {} notifications", self.chain.crypto);
+                self.poller.do_send(SetSubscribed(true));
+                self.pb_poller.do_send(SetSubscribedPendings(true));
+            }
+            Notification::Disconnected => {
+                warn!("Lost the {} notifications subscription", self.chain.crypto);
+                self.poller.do_send(SetSubscribed(false));
+                self.pb_poller.do_send(SetSubscribedPendings(false));
+            }
+            // The poller still processes blocks in order, the announced one spares fetching it.
+            Notification::Block(raw_block) => match decode_block(&raw_block, &self.chain) {
+                Ok(block) => self.poller.do_send(BlockAnnounced(Some(block))),
+                Err(e) => {
+                    warn!("Failed to decode an announced block: {:?}", e);
+                    self.poller.do_send(BlockAnnounced(None));
+                }
+            },
+            // `rawtx` announces the transactions of new blocks too. Processing them as pooled
+            // ones only marks their pending payments paid, crediting stays the block processor's.
+            Notification::Transaction(raw_transaction) => {
+                let transaction = match decode_transaction(&raw_transaction, &self.chain) {
+                    Ok(transaction) => transaction,
+                    Err(e) => {
+                        warn!("Failed to decode an announced transaction: {:?}", e);
+                        return;
+                    }
+                };
+                let hash = transaction.txid;
+
+                let process = self
+                    .processor
+                    .send(ProcessMempoolTransactions(vec![transaction]))
+                    .from_err::<Error>()
+                    .and_then(|res| res.map_err(|e| Error::from(e)))
+                    .map_err(move |e| error!("Failed to process transaction {}: {:?}", hash, e));
+
+                ctx.spawn(wrap_future(process));
+            }
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use std::time::{Duration, Instant};
+
+use types::U128;
+
+const REPORT_INTERVAL: Duration = Duration::from_secs(30);
+
+// Logs how far catching up on missed blocks has come, at most once per interval and once done.
+pub struct Progress {
+    from: U128,
+    to: U128,
+    started_at: Instant,
+    reported_at: Instant,
+}
+
+impl Progress {
+    pub fn new(from: U128, to: U128) -> Self {
+        let now = Instant::now();
+
+        Progress {
+            from,
+            to,
+            started_at: now,
+            reported_at: now,
+        }
+    }
+
+    pub fn processed(&mut self, block_number: U128) {
+        if block_number < self.to && self.reported_at.elapsed() < REPORT_INTERVAL {
+            return;
+        }
+        self.reported_at = Instant::now();
+
+        let processed = (block_number - self.from).as_u64() + 1;
+        let total = (self.to - self.from).as_u64() + 1;
+        let elapsed = self.started_at.elapsed().as_secs();
+        let rate = processed as f64 / elapsed.max(1) as f64;
+        let remaining = ((total - processed) as f64 / rate) as u64;
+
+        info!(
+            "Caught up to block {} ({}/{} missed blocks, {:.1} blocks/s, {}s remaining)",
+            block_number, processed, total, rate, remaining
+        );
+    }
+}
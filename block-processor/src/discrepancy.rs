@@ -0,0 +1,104 @@
+use chrono::prelude::*;
+use futures::Future;
+use serde_json::Value;
+
+use core::{
+    audit_event::{AuditEvent, AuditEventPayload},
+    db::{payments, postgres::PgExecutorAddr},
+    payment::{Payment, PaymentPayload},
+    ModelError,
+};
+use types::AuditAction;
+
+// Records coins found on chain for an unpaid payment in the store's audit log, for the merchant
+// to settle with the customer. The payment is marked along with it, so that it is reported once.
+pub fn flag(
+    payment: Payment,
+    found: Value,
+    postgres: &PgExecutorAddr,
+) -> impl Future<Item = (), Error = ModelError> {
+    warn!(
+        "Payment {} to {} was not credited: {}",
+        payment.id, payment.address, found
+    );
+
+    let id = payment.id;
+
+    let mut event = AuditEventPayload::new(AuditAction::PaymentDiscrepancyDetected, None, None);
+    event.store_id = Some(payment.store_id);
+    event.old_values = Some(payment.export());
+    event.new_values = Some(found);
+
+    let mut payload = PaymentPayload::new();
+    payload.discrepancy_flagged_at = Some(Utc::now());
+
+    AuditEvent::audited(
+        move |conn| payments::update(id, payload, conn),
+        move |_| event,
+        postgres,
+    )
+    .map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bigdecimal::BigDecimal;
+    use std::str::FromStr;
+    use uuid::Uuid;
+
+    use core::{audit_event::AuditEvent, payment::Payment};
+    use testing;
+    use types::{
+        currency::{Crypto, Fiat},
+        ethereum::Network,
+        PaymentStatus,
+    };
+
+    #[test]
+    fn flagging_a_payment_marks_it_and_records_the_discrepancy() {
+        let store_id = Uuid::new_v4();
+
+        let mut payload = PaymentPayload::new();
+        payload.status = Some(PaymentStatus::Expired);
+        payload.store_id = Some(store_id);
+        payload.index = Some(0);
+        payload.created_by = Some(store_id);
+        payload.set_created_at();
+        payload.set_expires_at();
+        payload.fiat = Some(Fiat::Usd);
+        payload.price = Some(BigDecimal::from_str("10").unwrap());
+        payload.crypto = Some(Crypto::Eth);
+        payload.address = Some(testing::eth_address().hex());
+        payload.charge = Some(BigDecimal::from_str("0.5").unwrap());
+        payload.confirmations_required = Some(1);
+        payload.eth_network = Some(Network(1337));
+
+        let (payment, events) = testing::run(move |postgres, _| {
+            let _postgres = postgres.clone();
+            let __postgres = postgres.clone();
+            let ___postgres = postgres.clone();
+
+            Box::new(
+                Payment::insert(payload, &postgres)
+                    .and_then(move |payment| {
+                        let id = payment.id;
+                        let found = json!({ "amount": "0.5" });
+
+                        flag(payment, found, &_postgres).map(move |_| id)
+                    })
+                    .and_then(move |id| Payment::find_by_id(id, &__postgres))
+                    .and_then(move |payment| {
+                        AuditEvent::find_by_store(store_id, 10, 0, &___postgres)
+                            .map(|events| (payment, events))
+                    }),
+            )
+        })
+        .unwrap();
+
+        assert!(payment.discrepancy_flagged_at.is_some());
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].action, AuditAction::PaymentDiscrepancyDetected);
+        assert_eq!(events[0].new_values, Some(json!({ "amount": "0.5" })));
+    }
+}
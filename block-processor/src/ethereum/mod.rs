@@ -4,4 +4,7 @@ pub use self::errors::Error;
 pub mod pb_poller;
 pub mod poller;
 pub mod processor;
+pub mod reconciler;
+pub mod rescanner;
 pub mod service;
+pub mod subscriber;
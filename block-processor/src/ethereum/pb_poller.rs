@@ -6,7 +6,7 @@ use futures_timer::Delay;
 
 use blockchain_api_client::{
     errors::Error as BlockchainApiClientError,
-    ethereum::{GetPendingBlock, BlockchainApiClientAddr},
+    ethereum::{BlockchainApiClientAddr, GetPendingBlock},
 };
 use core::ethereum::Transaction;
 use ethereum::{
@@ -19,6 +19,8 @@ const RETRY_LIMIT: usize = 10;
 pub struct Poller {
     processor: ProcessorAddr,
     blockchain_api_client: BlockchainApiClientAddr,
+    // Whether the subscriber receives new transactions from the node.
+    subscribed: bool,
 }
 
 impl Poller {
@@ -26,6 +28,7 @@ impl Poller {
         Poller {
             processor,
             blockchain_api_client,
+            subscribed: false,
         }
     }
 }
@@ -83,6 +86,19 @@ impl Handler<StartPolling> for Poller {
     }
 }
 
+// Sent by the subscriber to the node's notifications.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct SetSubscribed(pub bool);
+
+impl Handler<SetSubscribed> for Poller {
+    type Result = ();
+
+    fn handle(&mut self, SetSubscribed(subscribed): SetSubscribed, _: &mut Self::Context) {
+        self.subscribed = subscribed;
+    }
+}
+
 #[derive(Message)]
 #[rtype(result = "Result<(), Error>")]
 pub struct Poll {
@@ -109,6 +125,23 @@ impl Handler<Poll> for Poller {
             return Box::new(future::err(Error::RetryLimitError(retry_count)));
         }
 
+        // Polling resumes from where it was when the subscription is lost.
+        if self.subscribed {
+            let idle = Delay::new(Duration::from_secs(3))
+                .from_err::<Error>()
+                .and_then(move |_| {
+                    address
+                        .send(Poll {
+                            previous,
+                            retry_count,
+                        })
+                        .from_err::<Error>()
+                        .and_then(|res| res.map_err(|e| Error::from(e)))
+                });
+
+            return Box::new(idle);
+        }
+
         let polling = blockchain_api_client
             .send(GetPendingBlock)
             .from_err::<Error>()
@@ -1,16 +1,22 @@
-use std::time::Duration;
+use std::{io::Error as IoError, time::Duration};
 
 use actix::prelude::*;
 use futures::{future, stream, Future, Stream};
 use futures_timer::Delay;
 
+use announcements::Announcements;
 use blockchain_api_client::{
     errors::Error as BlockchainApiClientError,
-    ethereum::{BlockchainApiClientAddr, GetBlockByNumber, GetBlockNumber},
+    ethereum::{
+        BlockchainApiClientAddr, DebugTraceBlock, GetBlockByNumber, GetBlockNumber,
+        InternalTransfer, TraceBlock,
+    },
 };
+use catch_up::Progress;
+use config::EthTraceMethod;
 use core::{
     db::postgres::PgExecutorAddr,
-    ethereum::{BlockchainStatus, BlockchainStatusPayload},
+    ethereum::{Block, BlockchainStatus, BlockchainStatusPayload},
 };
 use ethereum::{
     errors::Error,
@@ -25,6 +31,9 @@ pub struct Poller {
     postgres: PgExecutorAddr,
     blockchain_api_client: BlockchainApiClientAddr,
     network: Network,
+    trace_method: Option<EthTraceMethod>,
+    catch_up_concurrency: usize,
+    announcements: Announcements,
 }
 
 impl Poller {
@@ -33,16 +42,63 @@ impl Poller {
         postgres: PgExecutorAddr,
         blockchain_api_client: BlockchainApiClientAddr,
         network: Network,
+        trace_method: Option<EthTraceMethod>,
+        catch_up_concurrency: usize,
     ) -> Self {
         Poller {
             processor,
             postgres,
             blockchain_api_client,
             network,
+            trace_method,
+            catch_up_concurrency,
+            announcements: Announcements::new(),
         }
     }
 }
 
+// Fetches a block, and the value transfers made by contract calls within it when tracing is
+// configured.
+pub fn get_block(
+    blockchain_api_client: BlockchainApiClientAddr,
+    block_number: U128,
+    trace_method: Option<EthTraceMethod>,
+) -> Box<Future<Item = (Block, Vec<InternalTransfer>), Error = Error>> {
+    let process = blockchain_api_client
+        .send(GetBlockByNumber(block_number))
+        .from_err()
+        .and_then(|res| res.map_err(|e| Error::from(e)))
+        .and_then(move |block| {
+            let internal_transfers: Box<Future<Item = Vec<InternalTransfer>, Error = Error>> =
+                match trace_method {
+                    Some(EthTraceMethod::TraceBlock) => Box::new(
+                        blockchain_api_client
+                            .send(TraceBlock(block_number))
+                            .from_err()
+                            .and_then(|res| res.map_err(|e| Error::from(e))),
+                    ),
+                    Some(EthTraceMethod::DebugTraceBlock) => Box::new(
+                        blockchain_api_client
+                            .send(DebugTraceBlock {
+                                block_number,
+                                transaction_hashes: block
+                                    .transactions
+                                    .iter()
+                                    .map(|transaction| transaction.hash)
+                                    .collect(),
+                            })
+                            .from_err()
+                            .and_then(|res| res.map_err(|e| Error::from(e))),
+                    ),
+                    None => Box::new(future::ok(Vec::new())),
+                };
+
+            internal_transfers.map(move |internal_transfers| (block, internal_transfers))
+        });
+
+    Box::new(process)
+}
+
 impl Actor for Poller {
     type Context = Context<Self>;
 }
@@ -129,6 +185,8 @@ impl Handler<Bootstrap> for Poller {
         let blockchain_api_client = self.blockchain_api_client.clone();
         let postgres = self.postgres.clone();
         let network = self.network;
+        let trace_method = self.trace_method;
+        let catch_up_concurrency = self.catch_up_concurrency;
 
         let bootstrap_process = blockchain_api_client
             .send(GetBlockNumber)
@@ -175,10 +233,14 @@ impl Handler<Bootstrap> for Poller {
                         current_block_number
                     );
 
+                    let mut progress =
+                        Progress::new(block_height + U128::from(1), current_block_number);
+
+                    // Blocks are fetched ahead, but processed one by one in order.
                     Box::new(
                         stream::unfold(block_height + U128::from(1), move |block_number| {
                             if block_number <= current_block_number {
-                                return Some(future::ok((
+                                return Some(future::ok::<_, Error>((
                                     block_number,
                                     block_number + U128::from(1),
                                 )));
@@ -186,20 +248,24 @@ impl Handler<Bootstrap> for Poller {
 
                             None
                         })
-                        .for_each(move |block_number| {
-                            let processor = processor.clone();
-
-                            blockchain_api_client
-                                .send(GetBlockByNumber(block_number))
-                                .from_err()
-                                .and_then(move |res| res.map_err(|e| Error::from(e)))
-                                .and_then(move |block| {
-                                    processor
-                                        .send(ProcessBlock(block))
-                                        .from_err()
-                                        .and_then(|res| res.map_err(|e| Error::from(e)))
+                        .map(move |block_number| {
+                            get_block(blockchain_api_client.clone(), block_number, trace_method)
+                                .map(move |(block, internal_transfers)| {
+                                    (block_number, block, internal_transfers)
                                 })
                         })
+                        .buffered(catch_up_concurrency.max(1))
+                        .and_then(move |(block_number, block, internal_transfers)| {
+                            processor
+                                .send(ProcessBlock(block, internal_transfers))
+                                .from_err()
+                                .and_then(|res| res.map_err(|e| Error::from(e)))
+                                .map(move |_| block_number)
+                        })
+                        .for_each(move |block_number| {
+                            progress.processed(block_number);
+                            Ok(())
+                        })
                         .and_then(move |_| {
                             address
                                 .send(Bootstrap { skip_missed_blocks })
@@ -214,6 +280,42 @@ impl Handler<Bootstrap> for Poller {
     }
 }
 
+// Sent by the subscriber to the node's notifications.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct SetSubscribed(pub bool);
+
+impl Handler<SetSubscribed> for Poller {
+    type Result = ();
+
+    fn handle(&mut self, SetSubscribed(subscribed): SetSubscribed, _: &mut Self::Context) {
+        if subscribed != self.announcements.is_subscribed() {
+            info!(
+                "{} new blocks",
+                if subscribed {
+                    "Subscribed to"
+                } else {
+                    "Polling for"
+                }
+            );
+        }
+
+        self.announcements.set_subscribed(subscribed);
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct BlockAnnounced;
+
+impl Handler<BlockAnnounced> for Poller {
+    type Result = ();
+
+    fn handle(&mut self, _: BlockAnnounced, _: &mut Self::Context) {
+        self.announcements.announce();
+    }
+}
+
 #[derive(Message)]
 #[rtype(result = "Result<(), Error>")]
 pub struct Poll {
@@ -235,42 +337,48 @@ impl Handler<Poll> for Poller {
         let address = ctx.address();
         let processor = self.processor.clone();
         let blockchain_api_client = self.blockchain_api_client.clone();
+        let trace_method = self.trace_method;
 
         if retry_count == RETRY_LIMIT {
             return Box::new(future::err(Error::RetryLimitError(retry_count)));
         }
 
-        let polling = blockchain_api_client
-            .send(GetBlockByNumber(block_number))
-            .from_err::<Error>()
-            .and_then(move |res| res.map_err(|e| Error::from(e)))
-            .from_err::<Error>()
-            .and_then(move |block| {
+        let announced = self.announcements.next();
+
+        let polling = get_block(blockchain_api_client, block_number, trace_method)
+            .and_then(move |(block, internal_transfers)| {
                 processor
-                    .send(ProcessBlock(block))
+                    .send(ProcessBlock(block, internal_transfers))
                     .from_err::<Error>()
                     .and_then(|res| res.map_err(|e| Error::from(e)))
-                    .map(move |_| (block_number + U128::from(1), 0))
+                    .map(move |_| (block_number + U128::from(1), 0, true))
             })
             .or_else(move |e| match e {
                 Error::BlockchainApiClientError(e) => match e {
-                    BlockchainApiClientError::EmptyResponseError => future::ok((block_number, 0)),
-                    _ => future::ok((block_number, retry_count + 1)),
+                    BlockchainApiClientError::EmptyResponseError => {
+                        future::ok((block_number, 0, false))
+                    }
+                    _ => future::ok((block_number, retry_count + 1, false)),
                 },
                 _ => future::err(e),
             })
-            .and_then(move |(block_number, retry_count)| {
-                Delay::new(Duration::from_secs(3))
-                    .from_err::<Error>()
-                    .and_then(move |_| {
-                        address
-                            .send(Poll {
-                                block_number: block_number,
-                                retry_count,
-                            })
-                            .from_err::<Error>()
-                            .and_then(|res| res.map_err(|e| Error::from(e)))
-                    })
+            .and_then(move |(block_number, retry_count, processed)| {
+                let wait: Box<Future<Item = (), Error = IoError>> = match announced {
+                    // The next block may be out already.
+                    Some(_) if processed => Box::new(future::ok(())),
+                    Some(announced) if retry_count == 0 => announced,
+                    _ => Box::new(Delay::new(Duration::from_secs(3))),
+                };
+
+                wait.from_err::<Error>().and_then(move |_| {
+                    address
+                        .send(Poll {
+                            block_number: block_number,
+                            retry_count,
+                        })
+                        .from_err::<Error>()
+                        .and_then(|res| res.map_err(|e| Error::from(e)))
+                })
             })
             .map(|_| ());
 
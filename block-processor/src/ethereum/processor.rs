@@ -4,20 +4,23 @@ use actix::prelude::*;
 use bigdecimal::BigDecimal;
 use futures::{future, stream, Future, Stream};
 
+use blockchain_api_client::ethereum::InternalTransfer;
 use core::{
     db::postgres::PgExecutorAddr,
-    ethereum::{Block, BlockchainStatus, BlockchainStatusPayload, Transaction},
+    ethereum::{Block, BlockchainStatus, BlockchainStatusPayload, Transaction, Transfer},
     payment::{Payment, PaymentPayload},
     payout::Payout,
 };
 use ethereum::errors::Error;
-use types::{currency::Crypto, ethereum::Network, PaymentStatus, H256, U128};
+use mailer::{Notification, NotifierAddr, Notify};
+use types::{ethereum::Network, PaymentStatus, PayoutAction, H256, U128};
 
 pub type ProcessorAddr = Addr<Processor>;
 
 pub struct Processor {
     pub network: Network,
     pub postgres: PgExecutorAddr,
+    pub notifier: NotifierAddr,
 }
 
 impl Actor for Processor {
@@ -26,86 +29,190 @@ impl Actor for Processor {
 
 type ProcessedBlockStream = Box<Future<Item = (Vec<String>, HashMap<String, H256>), Error = Error>>;
 
-#[derive(Message)]
-#[rtype(result = "Result<(), Error>")]
-pub struct ProcessBlock(pub Block);
+// The ether received in the block: the value of its transactions, then the transfers made by
+// contract calls within them, numbered in trace order after their transaction.
+fn transfers(
+    transactions: &[Transaction],
+    internal_transfers: Vec<InternalTransfer>,
+) -> Vec<Transfer> {
+    let mut transfers: Vec<Transfer> = transactions
+        .iter()
+        .filter_map(Transfer::from_transaction)
+        .collect();
+    let mut output_indexes: HashMap<H256, i32> = HashMap::new();
 
-impl Handler<ProcessBlock> for Processor {
-    type Result = Box<Future<Item = (), Error = Error>>;
+    for transfer in internal_transfers {
+        if !transactions
+            .iter()
+            .any(|tx| tx.hash == transfer.transaction_hash)
+        {
+            continue;
+        }
+
+        let output_index = output_indexes.entry(transfer.transaction_hash).or_insert(0);
+        *output_index += 1;
+
+        transfers.push(Transfer {
+            transaction_hash: transfer.transaction_hash,
+            output_index: *output_index,
+            from_address: transfer.from,
+            to_address: transfer.to,
+            value: transfer.value,
+        });
+    }
 
-    fn handle(&mut self, ProcessBlock(block): ProcessBlock, _: &mut Self::Context) -> Self::Result {
+    transfers
+}
+
+impl Processor {
+    // A rescanned block leaves the processed tip alone. Outputs are credited once either way.
+    fn process_block(
+        &self,
+        block: Block,
+        internal_transfers: Vec<InternalTransfer>,
+        rescan: bool,
+    ) -> Box<Future<Item = (), Error = Error>> {
         info!("Processing block: {}", block.number.unwrap());
         let postgres = self.postgres.clone();
+        let notifier = self.notifier.clone();
         let network = self.network;
         let block_number = block.number;
         let _postgres = postgres.clone();
+        let transactions = block.transactions.clone();
+        let transfers = transfers(&transactions, internal_transfers);
+        let addresses = transfers
+            .iter()
+            .map(|transfer| format!("0x{}", transfer.to_address))
+            .collect();
 
-        let process = stream::iter_ok(block.transactions.clone())
-            .filter(|transaction| transaction.to_address.is_some())
-            .fold(
-                (Vec::new(), HashMap::new()),
-                move |(mut addresses, mut tx_hashes), transaction| -> ProcessedBlockStream {
-                    let to = transaction.to_address.unwrap();
-                    addresses.push(format!("0x{}", to));
-                    tx_hashes.insert(format!("0x{}", to), transaction.hash.clone());
-
-                    Box::new(future::ok((addresses, tx_hashes)))
-                },
-            )
-            .and_then(move |(addresses, tx_hashes)| {
-                Payment::find_all_by_address(addresses, Crypto::Eth, &postgres)
-                    .from_err()
-                    .map(move |payments| stream::iter_ok(payments))
-                    .flatten_stream()
-                    .and_then(move |payment| {
-                        let hash = tx_hashes.get(&payment.clone().address).unwrap();
-                        let transaction = block
-                            .transactions
+        let process = Payment::find_all_by_eth_address(addresses, network, &postgres)
+            .from_err()
+            .map(move |payments| {
+                // An address can receive several transfers, even within one transaction, and
+                // each is credited on its own.
+                let credits: Vec<(Payment, Transfer)> = payments
+                    .into_iter()
+                    .flat_map(|payment| {
+                        transfers
                             .iter()
-                            .find(|tx| tx.hash == *hash)
-                            .unwrap();
+                            .filter(|transfer| {
+                                format!("0x{}", transfer.to_address) == payment.address
+                            })
+                            .map(|transfer| (payment.clone(), transfer.clone()))
+                            .collect::<Vec<_>>()
+                    })
+                    .collect();
 
-                        let amount_paid =
-                            match BigDecimal::from_str(&format!("{}", transaction.value)) {
-                                Ok(value) => {
-                                    value / BigDecimal::from_str("1000000000000000000").unwrap()
-                                }
-                                Err(_) => {
-                                    // TODO: Handle error.
-                                    panic!("failed to parse transaction amount");
-                                }
-                            };
+                stream::iter_ok(credits)
+            })
+            .flatten_stream()
+            // Transfers credited by an earlier run over the block are skipped by the insert,
+            // whichever transaction the payment was last paid by.
+            .and_then(move |(payment, transfer)| {
+                let transaction = transactions
+                    .iter()
+                    .find(|tx| tx.hash == transfer.transaction_hash)
+                    .unwrap();
 
-                        // Block height required = transaction's block number + required number of confirmations - 1.
-                        let block_height_required = block.number.unwrap()
-                            + U128::from(payment.confirmations_required)
-                            - U128::from(1);
-
-                        Payout::insert_eth_payout(
-                            amount_paid,
-                            block_height_required,
-                            payment,
-                            transaction.to_owned(),
-                            &postgres,
-                        )
-                        .from_err()
-                    })
-                    .for_each(move |_| future::ok(()))
-                    .and_then(move |_| {
-                        let payload = BlockchainStatusPayload {
-                            network: None,
-                            block_height: block_number,
-                        };
+                let amount_paid = match BigDecimal::from_str(&format!("{}", transfer.value)) {
+                    Ok(value) => value / BigDecimal::from_str("1000000000000000000").unwrap(),
+                    Err(_) => {
+                        // TODO: Handle error.
+                        panic!("failed to parse transaction amount");
+                    }
+                };
 
-                        BlockchainStatus::update(network, payload, &_postgres).from_err()
-                    })
-                    .map(|_| ())
+                // Block height required = transaction's block number + required number of confirmations - 1.
+                let block_height_required = block.number.unwrap()
+                    + U128::from(payment.confirmations_required)
+                    - U128::from(1);
+
+                let insufficient_amount = match payment.status {
+                    PaymentStatus::Pending
+                    | PaymentStatus::Paid
+                    | PaymentStatus::InsufficientAmount => amount_paid < payment.charge,
+                    _ => false,
+                };
+                let notifier = notifier.clone();
+
+                Payout::insert_eth_payout(
+                    amount_paid,
+                    block_height_required,
+                    payment,
+                    transaction.to_owned(),
+                    transfer,
+                    &postgres,
+                )
+                .from_err()
+                .map(move |payout| {
+                    // Already credited by an earlier run over the block.
+                    let payout = match payout {
+                        Some(payout) => payout,
+                        None => return,
+                    };
+
+                    // Payments are notified by the payouter once they reached their
+                    // confirmations.
+                    if payout.action == PayoutAction::Refund && insufficient_amount {
+                        notifier.do_send(Notify(Notification::InsufficientAmount(payout)))
+                    }
+                })
+            })
+            .for_each(move |_| future::ok(()))
+            .and_then(move |_| -> Box<Future<Item = (), Error = Error>> {
+                if rescan {
+                    return Box::new(future::ok(()));
+                }
+
+                let payload = BlockchainStatusPayload {
+                    network: None,
+                    block_height: block_number,
+                };
+
+                Box::new(
+                    BlockchainStatus::update(network, payload, &_postgres)
+                        .from_err()
+                        .map(|_| ()),
+                )
             });
 
         Box::new(process)
     }
 }
 
+#[derive(Message)]
+#[rtype(result = "Result<(), Error>")]
+pub struct ProcessBlock(pub Block, pub Vec<InternalTransfer>);
+
+impl Handler<ProcessBlock> for Processor {
+    type Result = Box<Future<Item = (), Error = Error>>;
+
+    fn handle(
+        &mut self,
+        ProcessBlock(block, internal_transfers): ProcessBlock,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        self.process_block(block, internal_transfers, false)
+    }
+}
+
+// Processes a block again, e.g. one skipped with `--skip-missed-blocks`.
+#[derive(Message)]
+#[rtype(result = "Result<(), Error>")]
+pub struct RescanBlock(pub Block, pub Vec<InternalTransfer>);
+
+impl Handler<RescanBlock> for Processor {
+    type Result = Box<Future<Item = (), Error = Error>>;
+
+    fn handle(
+        &mut self,
+        RescanBlock(block, internal_transfers): RescanBlock,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        self.process_block(block, internal_transfers, true)
+    }
+}
+
 #[derive(Message)]
 #[rtype(result = "Result<(), Error>")]
 pub struct ProcessPendingTransactions(pub Vec<Transaction>);
@@ -119,6 +226,7 @@ impl Handler<ProcessPendingTransactions> for Processor {
         _: &mut Self::Context,
     ) -> Self::Result {
         let postgres = self.postgres.clone();
+        let network = self.network;
 
         let process = stream::iter_ok(pending_transactions.clone())
             .filter(|transaction| transaction.to_address.is_some())
@@ -133,7 +241,7 @@ impl Handler<ProcessPendingTransactions> for Processor {
                 },
             )
             .and_then(move |(addresses, tx_hashes)| {
-                Payment::find_all_by_address(addresses, Crypto::Eth, &postgres)
+                Payment::find_all_by_eth_address(addresses, network, &postgres)
                     .from_err()
                     .map(move |payments| stream::iter_ok(payments))
                     .flatten_stream()
@@ -184,3 +292,167 @@ impl Handler<ProcessPendingTransactions> for Processor {
         Box::new(process)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    use core::ledger_entry::LedgerEntry;
+    use testing;
+    use types::{
+        currency::{Crypto, Fiat},
+        LedgerEvent, H160, U256,
+    };
+
+    // A private chain, so as not to touch the tip of a public one.
+    const NETWORK: Network = Network(1337);
+
+    fn payment_payload(store_id: Uuid, address: String) -> PaymentPayload {
+        let mut payload = PaymentPayload::new();
+        payload.status = Some(PaymentStatus::Pending);
+        payload.store_id = Some(store_id);
+        payload.index = Some(0);
+        payload.created_by = Some(store_id);
+        payload.set_created_at();
+        payload.fiat = Some(Fiat::Usd);
+        payload.price = Some(BigDecimal::from_str("10").unwrap());
+        payload.crypto = Some(Crypto::Eth);
+        payload.address = Some(address);
+        payload.charge = Some(BigDecimal::from_str("0.5").unwrap());
+        payload.confirmations_required = Some(1);
+        payload.eth_network = Some(NETWORK);
+        payload
+    }
+
+    fn half_an_ether() -> U256 {
+        U256::from_str("6f05b59d3b20000").unwrap()
+    }
+
+    // A block paying the address half an ether with a transaction, and another half with a
+    // contract call the transaction makes.
+    fn block(address: H160) -> (Block, Vec<InternalTransfer>) {
+        let hash = testing::hash();
+        let from = testing::eth_address();
+
+        let transaction = Transaction {
+            hash,
+            nonce: U256::from(0),
+            block_hash: None,
+            block_number: Some(U256::from(100)),
+            transaction_index: None,
+            from_address: from,
+            to_address: Some(address),
+            value: half_an_ether(),
+            gas_price: U256::from(0),
+            gas: U256::from(21000),
+            input: String::from("0x"),
+        };
+
+        let block = Block {
+            hash: Some(testing::hash()),
+            parent_hash: testing::hash(),
+            uncles_hash: testing::hash(),
+            author: None,
+            state_root: testing::hash(),
+            transactions_root: testing::hash(),
+            receipts_root: testing::hash(),
+            number: Some(U128::from(100)),
+            gas_used: U256::from(0),
+            gas_limit: U256::from(0),
+            extra_data: String::from("0x"),
+            logs_bloom: String::from("0x"),
+            timestamp: U256::from(0),
+            difficulty: U256::from(0),
+            total_difficulty: None,
+            uncles: Vec::new(),
+            transactions: vec![transaction],
+            size: None,
+        };
+
+        let internal_transfers = vec![InternalTransfer {
+            transaction_hash: hash,
+            from,
+            to: address,
+            value: half_an_ether(),
+        }];
+
+        (block, internal_transfers)
+    }
+
+    #[test]
+    fn processing_a_block_again_credits_its_transfers_once() {
+        let store_id = Uuid::new_v4();
+        let address = testing::eth_address();
+        let (block, internal_transfers) = block(address);
+
+        let (payment, payouts, entries) = testing::run(move |postgres, notifier| {
+            let processor = Processor {
+                network: NETWORK,
+                postgres: postgres.clone(),
+                notifier,
+            }
+            .start();
+            let _processor = processor.clone();
+            let _block = block.clone();
+            let _internal_transfers = internal_transfers.clone();
+            let _postgres = postgres.clone();
+            let __postgres = postgres.clone();
+            let ___postgres = postgres.clone();
+
+            // The processor moves the tip of the chain, which has to exist.
+            let status = BlockchainStatusPayload {
+                network: Some(NETWORK),
+                block_height: Some(U128::from(0)),
+            };
+
+            Box::new(
+                BlockchainStatus::find(NETWORK, &postgres)
+                    .or_else(move |_| BlockchainStatus::insert(status, &_postgres))
+                    .and_then(move |_| {
+                        let address = format!("0x{}", address);
+
+                        Payment::insert(payment_payload(store_id, address), &__postgres)
+                    })
+                    .from_err()
+                    .and_then(move |payment| {
+                        processor
+                            .send(ProcessBlock(block, internal_transfers))
+                            .from_err()
+                            .and_then(|res| res)
+                            .map(move |_| payment)
+                    })
+                    .and_then(move |payment| {
+                        _processor
+                            .send(ProcessBlock(_block, _internal_transfers))
+                            .from_err()
+                            .and_then(|res| res)
+                            .map(move |_| payment)
+                    })
+                    .and_then(move |payment| {
+                        let postgres = ___postgres.clone();
+
+                        Payment::find_by_id(payment.id, &___postgres)
+                            .join(Payout::find_all_by_payments(vec![payment.id], &postgres))
+                            .join(LedgerEntry::find_by_store(store_id, 10, 0, &postgres))
+                            .map(|((payment, payouts), entries)| (payment, payouts, entries))
+                            .from_err()
+                    }),
+            ) as Box<Future<Item = _, Error = Error>>
+        })
+        .unwrap();
+
+        assert_eq!(payment.status, PaymentStatus::Confirmed);
+        assert_eq!(payment.block_height_required, Some(U128::from(100)));
+
+        let mut output_indexes: Vec<Option<i32>> =
+            payouts.iter().map(|payout| payout.output_index).collect();
+        output_indexes.sort();
+        assert_eq!(output_indexes, vec![Some(0), Some(1)]);
+
+        assert_eq!(entries.len(), 4);
+        assert!(entries
+            .iter()
+            .all(|entry| entry.event == LedgerEvent::Credit));
+    }
+}
@@ -0,0 +1,129 @@
+use std::{str::FromStr, time::Duration};
+
+use actix::{
+    fut::{self, wrap_future},
+    prelude::*,
+};
+use bigdecimal::BigDecimal;
+use chrono::{prelude::*, Duration as ChronoDuration};
+use futures::{future, stream, Future, Stream};
+
+use blockchain_api_client::ethereum::{BlockchainApiClientAddr, GetBalanceAt};
+use config::ReconciliationConfig;
+use core::{db::postgres::PgExecutorAddr, ethereum::BlockchainStatus, payment::Payment};
+use discrepancy;
+use ethereum::Error;
+use types::{currency::Crypto, ethereum::Network, H160, U256};
+
+// Checks the balances of the addresses of recent unpaid payments as of the last processed block,
+// and flags the ones which received ether. A balance doesn't tell which transaction paid it, so
+// these payments can't be credited automatically.
+pub struct Reconciler {
+    network: Network,
+    postgres: PgExecutorAddr,
+    blockchain_api_client: BlockchainApiClientAddr,
+    config: ReconciliationConfig,
+    running: bool,
+}
+
+impl Reconciler {
+    pub fn new(
+        network: Network,
+        postgres: PgExecutorAddr,
+        blockchain_api_client: BlockchainApiClientAddr,
+        config: ReconciliationConfig,
+    ) -> Self {
+        Reconciler {
+            network,
+            postgres,
+            blockchain_api_client,
+            config,
+            running: false,
+        }
+    }
+}
+
+impl Actor for Reconciler {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Context<Self>) {
+        let interval = Duration::from_secs(self.config.interval_secs);
+
+        ctx.run_interval(interval, move |reconciler, ctx| {
+            if reconciler.running {
+                return;
+            }
+            reconciler.running = true;
+
+            let process = wrap_future(reconciler.reconcile())
+                .map_err(|e: Error, _: &mut Reconciler, _| error!("{:?}", e))
+                .then(|_, reconciler: &mut Reconciler, _| {
+                    reconciler.running = false;
+                    fut::ok(())
+                });
+
+            ctx.spawn(process);
+        });
+    }
+}
+
+impl Reconciler {
+    fn reconcile(&self) -> Box<Future<Item = (), Error = Error>> {
+        let postgres = self.postgres.clone();
+        let blockchain_api_client = self.blockchain_api_client.clone();
+        let network = self.network;
+        let since = Utc::now() - ChronoDuration::hours(self.config.lookback_hours);
+
+        let process = Payment::find_all_unpaid_since(Crypto::Eth, since, &postgres)
+            .join(BlockchainStatus::find(network, &postgres))
+            .from_err()
+            .and_then(move |(payments, status)| {
+                let block_height = status.block_height;
+
+                stream::iter_ok(payments)
+                    .filter(move |payment| {
+                        payment.eth_network == Some(network)
+                            && payment.discrepancy_flagged_at.is_none()
+                    })
+                    .filter_map(|payment| match H160::from_hex(&payment.address) {
+                        Ok(address) => Some((payment, address)),
+                        Err(e) => {
+                            error!("Payment {} has an invalid address: {}", payment.id, e);
+                            None
+                        }
+                    })
+                    .and_then(move |(payment, address)| {
+                        let postgres = postgres.clone();
+
+                        blockchain_api_client
+                            .send(GetBalanceAt {
+                                account: address,
+                                block_number: block_height,
+                            })
+                            .from_err()
+                            .and_then(|res| res.map_err(|e| Error::from(e)))
+                            .and_then(move |balance| -> Box<Future<Item = (), Error = Error>> {
+                                if balance == U256::from(0) {
+                                    return Box::new(future::ok(()));
+                                }
+
+                                let found = json!({
+                                    "address": payment.address,
+                                    "amount": ether(balance).to_string(),
+                                    "block_height": format!("{}", block_height),
+                                });
+
+                                Box::new(discrepancy::flag(payment, found, &postgres).from_err())
+                            })
+                    })
+                    .for_each(|_| future::ok(()))
+            });
+
+        Box::new(process)
+    }
+}
+
+fn ether(wei: U256) -> BigDecimal {
+    BigDecimal::from_str(&format!("{}", wei)).unwrap()
+        / BigDecimal::from_str("1000000000000000000").unwrap()
+}
@@ -0,0 +1,183 @@
+use std::time::Duration;
+
+use actix::{
+    fut::{self, wrap_future},
+    prelude::*,
+};
+use futures::{future, stream, Future, Stream};
+use uuid::Uuid;
+
+use blockchain_api_client::ethereum::BlockchainApiClientAddr;
+use config::EthTraceMethod;
+use core::{
+    block_rescan::{BlockRescan, BlockRescanPayload},
+    db::postgres::PgExecutorAddr,
+    ethereum::BlockchainStatus,
+    ModelError,
+};
+use ethereum::{
+    poller::get_block,
+    processor::{ProcessorAddr, RescanBlock},
+    Error,
+};
+use types::{currency::Crypto, ethereum::Network, RescanStatus, U128};
+
+// Works through the rescans requested for the network, one at a time and oldest first.
+pub struct Rescanner {
+    network: Network,
+    processor: ProcessorAddr,
+    postgres: PgExecutorAddr,
+    blockchain_api_client: BlockchainApiClientAddr,
+    trace_method: Option<EthTraceMethod>,
+    running: bool,
+}
+
+impl Rescanner {
+    pub fn new(
+        network: Network,
+        processor: ProcessorAddr,
+        postgres: PgExecutorAddr,
+        blockchain_api_client: BlockchainApiClientAddr,
+        trace_method: Option<EthTraceMethod>,
+    ) -> Self {
+        Rescanner {
+            network,
+            processor,
+            postgres,
+            blockchain_api_client,
+            trace_method,
+            running: false,
+        }
+    }
+}
+
+impl Actor for Rescanner {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Context<Self>) {
+        ctx.run_interval(Duration::new(10, 0), move |rescanner, ctx| {
+            if rescanner.running {
+                return;
+            }
+            rescanner.running = true;
+
+            let process = wrap_future(rescanner.rescan_next())
+                .map_err(|e: Error, _: &mut Rescanner, _| error!("{:?}", e))
+                .then(|_, rescanner: &mut Rescanner, _| {
+                    rescanner.running = false;
+                    fut::ok(())
+                });
+
+            ctx.spawn(process);
+        });
+    }
+}
+
+impl Rescanner {
+    fn rescan_next(&self) -> Box<Future<Item = (), Error = Error>> {
+        let postgres = self.postgres.clone();
+        let processor = self.processor.clone();
+        let blockchain_api_client = self.blockchain_api_client.clone();
+        let network = self.network;
+        let trace_method = self.trace_method;
+
+        let process = BlockRescan::find_next(Crypto::Eth, Some(network), &postgres)
+            .join(BlockchainStatus::find(network, &postgres))
+            .from_err()
+            .and_then(
+                move |(rescan, status)| -> Box<Future<Item = (), Error = Error>> {
+                    let rescan = match rescan {
+                        Some(rescan) => rescan,
+                        None => return Box::new(future::ok(())),
+                    };
+
+                    // The poller is still on its way to these blocks.
+                    if rescan.to_block > status.block_height {
+                        return Box::new(
+                            finish(
+                                rescan.id,
+                                Some(format!(
+                                    "block {} hasn't been processed yet",
+                                    rescan.to_block
+                                )),
+                                &postgres,
+                            )
+                            .from_err(),
+                        );
+                    }
+
+                    info!(
+                        "Rescanning blocks {} ~ {} of network {}",
+                        rescan.next_block, rescan.to_block, network
+                    );
+
+                    let id = rescan.id;
+                    let to_block = rescan.to_block;
+                    let _postgres = postgres.clone();
+
+                    let mut payload = BlockRescanPayload::new();
+                    payload.status = Some(RescanStatus::Running);
+
+                    let process = BlockRescan::update(id, payload, &postgres)
+                        .from_err()
+                        .and_then(move |rescan| {
+                            stream::unfold(rescan.next_block, move |block_number| {
+                                if block_number <= to_block {
+                                    return Some(future::ok::<_, Error>((
+                                        block_number,
+                                        block_number + U128::from(1),
+                                    )));
+                                }
+
+                                None
+                            })
+                            .for_each(move |block_number| {
+                                let processor = processor.clone();
+                                let postgres = postgres.clone();
+
+                                get_block(blockchain_api_client.clone(), block_number, trace_method)
+                                    .and_then(move |(block, internal_transfers)| {
+                                        processor
+                                            .send(RescanBlock(block, internal_transfers))
+                                            .from_err()
+                                            .and_then(|res| res.map_err(|e| Error::from(e)))
+                                    })
+                                    // Progress is kept, so that an interrupted rescan resumes.
+                                    .and_then(move |_| {
+                                        let mut payload = BlockRescanPayload::new();
+                                        payload.next_block = Some(block_number + U128::from(1));
+
+                                        BlockRescan::update(id, payload, &postgres)
+                                            .from_err()
+                                            .map(|_| ())
+                                    })
+                            })
+                        })
+                        .then(move |res| {
+                            let error = res.err().map(|e| format!("{}", e));
+
+                            finish(id, error, &_postgres).from_err()
+                        });
+
+                    Box::new(process)
+                },
+            );
+
+        Box::new(process)
+    }
+}
+
+fn finish(
+    id: Uuid,
+    error: Option<String>,
+    postgres: &PgExecutorAddr,
+) -> impl Future<Item = (), Error = ModelError> {
+    let mut payload = BlockRescanPayload::new();
+    payload.status = Some(match error {
+        Some(_) => RescanStatus::Failed,
+        None => RescanStatus::Completed,
+    });
+    payload.error = Some(error);
+
+    BlockRescan::update(id, payload, postgres).map(|_| ())
+}
@@ -1,31 +1,47 @@
 use actix::prelude::*;
 
 use blockchain_api_client::ethereum::BlockchainApiClientAddr;
+use config::{EthTraceMethod, ReconciliationConfig};
 use core::db::postgres;
 use ethereum::{
     pb_poller::{Poller as PendingBlocksPoller, StartPolling as StartPollingPendings},
     poller::{Poller, StartPolling},
     processor::Processor,
+    reconciler::Reconciler,
+    rescanner::Rescanner,
+    subscriber::Subscriber,
 };
+use mailer::NotifierAddr;
 use types::ethereum::Network;
 
 pub fn run(
     postgres: postgres::PgExecutorAddr,
     blockchain_api_client: BlockchainApiClientAddr,
+    notifier: NotifierAddr,
     network: Network,
+    trace_method: Option<EthTraceMethod>,
     skip_missed_blocks: bool,
+    catch_up_concurrency: usize,
 ) -> (Addr<Processor>, Addr<Poller>, Addr<PendingBlocksPoller>) {
     let pg = postgres.clone();
     let block_processor = Arbiter::start(move |_| Processor {
         network,
         postgres: pg,
+        notifier,
     });
 
     let _block_processor = block_processor.clone();
     let _postgres = postgres.clone();
     let _blockchain_api_client = blockchain_api_client.clone();
     let poller = Supervisor::start(move |_| {
-        Poller::new(_block_processor, _postgres, _blockchain_api_client, network)
+        Poller::new(
+            _block_processor,
+            _postgres,
+            _blockchain_api_client,
+            network,
+            trace_method,
+            catch_up_concurrency,
+        )
     });
     poller.do_send(StartPolling { skip_missed_blocks });
 
@@ -37,3 +53,50 @@ pub fn run(
 
     (block_processor, poller, pb_poller)
 }
+
+pub fn run_reconciler(
+    postgres: postgres::PgExecutorAddr,
+    blockchain_api_client: BlockchainApiClientAddr,
+    network: Network,
+    config: ReconciliationConfig,
+) -> Addr<Reconciler> {
+    Arbiter::start(move |_| Reconciler::new(network, postgres, blockchain_api_client, config))
+}
+
+pub fn run_rescanner(
+    processor: Addr<Processor>,
+    postgres: postgres::PgExecutorAddr,
+    blockchain_api_client: BlockchainApiClientAddr,
+    network: Network,
+    trace_method: Option<EthTraceMethod>,
+) -> Addr<Rescanner> {
+    Arbiter::start(move |_| {
+        Rescanner::new(
+            network,
+            processor,
+            postgres,
+            blockchain_api_client,
+            trace_method,
+        )
+    })
+}
+
+pub fn run_subscriber(
+    network: Network,
+    ws_url: String,
+    processor: Addr<Processor>,
+    poller: Addr<Poller>,
+    pb_poller: Addr<PendingBlocksPoller>,
+    blockchain_api_client: BlockchainApiClientAddr,
+) -> Addr<Subscriber> {
+    Arbiter::start(move |_| {
+        Subscriber::new(
+            network,
+            ws_url,
+            processor,
+            poller,
+            pb_poller,
+            blockchain_api_client,
+        )
+    })
+}
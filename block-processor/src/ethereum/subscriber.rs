@@ -0,0 +1,242 @@
+use std::{collections::HashMap, time::Duration};
+
+use actix::{fut::wrap_future, prelude::*};
+use actix_web::ws::{Client, ClientWriter, Message as WsMessage, ProtocolError};
+use futures::Future;
+use serde_json::{self, Value};
+
+use blockchain_api_client::ethereum::{BlockchainApiClientAddr, GetTransactionByHash};
+use ethereum::{
+    pb_poller::{Poller as PendingBlocksPoller, SetSubscribed as SetSubscribedPendings},
+    poller::{BlockAnnounced, Poller, SetSubscribed},
+    processor::{ProcessPendingTransactions, ProcessorAddr},
+    Error,
+};
+use types::{ethereum::Network, H256};
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Subscription {
+    NewHeads,
+    NewPendingTransactions,
+}
+
+impl Subscription {
+    fn request_id(&self) -> u64 {
+        match *self {
+            Subscription::NewHeads => 1,
+            Subscription::NewPendingTransactions => 2,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match *self {
+            Subscription::NewHeads => "newHeads",
+            Subscription::NewPendingTransactions => "newPendingTransactions",
+        }
+    }
+}
+
+// Receives new heads and pending transactions over `eth_subscribe`, so that the pollers only poll
+// while the websocket is down.
+pub struct Subscriber {
+    network: Network,
+    ws_url: String,
+    processor: ProcessorAddr,
+    poller: Addr<Poller>,
+    pb_poller: Addr<PendingBlocksPoller>,
+    blockchain_api_client: BlockchainApiClientAddr,
+    writer: Option<ClientWriter>,
+    // Subscription ids given by the node.
+    subscriptions: HashMap<String, Subscription>,
+}
+
+impl Subscriber {
+    pub fn new(
+        network: Network,
+        ws_url: String,
+        processor: ProcessorAddr,
+        poller: Addr<Poller>,
+        pb_poller: Addr<PendingBlocksPoller>,
+        blockchain_api_client: BlockchainApiClientAddr,
+    ) -> Self {
+        Subscriber {
+            network,
+            ws_url,
+            processor,
+            poller,
+            pb_poller,
+            blockchain_api_client,
+            writer: None,
+            subscriptions: HashMap::new(),
+        }
+    }
+
+    fn connect(&mut self, ctx: &mut Context<Self>) {
+        let connecting = wrap_future(Client::new(self.ws_url.as_str()).connect())
+            .map(|(reader, writer), subscriber: &mut Subscriber, ctx| {
+                ctx.add_stream(reader);
+                subscriber.writer = Some(writer);
+                subscriber.subscribe(Subscription::NewHeads);
+                subscriber.subscribe(Subscription::NewPendingTransactions);
+            })
+            .map_err(|e, subscriber: &mut Subscriber, ctx| {
+                error!(
+                    "Failed to connect to {} of network {}: {}",
+                    subscriber.ws_url, subscriber.network, e
+                );
+                subscriber.reconnect_later(ctx);
+            });
+
+        ctx.spawn(connecting);
+    }
+
+    fn subscribe(&mut self, subscription: Subscription) {
+        if let Some(ref mut writer) = self.writer {
+            writer.text(
+                json!({
+                    "jsonrpc": "2.0",
+                    "method": "eth_subscribe",
+                    "params": [subscription.name()],
+                    "id": subscription.request_id(),
+                })
+                .to_string(),
+            );
+        }
+    }
+
+    fn reconnect_later(&mut self, ctx: &mut Context<Self>) {
+        self.writer = None;
+        self.subscriptions.clear();
+        self.poller.do_send(SetSubscribed(false));
+        self.pb_poller.do_send(SetSubscribedPendings(false));
+
+        ctx.run_later(RECONNECT_DELAY, |subscriber, ctx| subscriber.connect(ctx));
+    }
+
+    fn subscribed(&mut self, subscription: Subscription, id: String) {
+        info!(
+            "Subscribed to {} of network {}",
+            subscription.name(),
+            self.network
+        );
+
+        match subscription {
+            Subscription::NewHeads => self.poller.do_send(SetSubscribed(true)),
+            Subscription::NewPendingTransactions => {
+                self.pb_poller.do_send(SetSubscribedPendings(true))
+            }
+        };
+
+        self.subscriptions.insert(id, subscription);
+    }
+
+    fn process_pending_transaction(&self, hash: H256, ctx: &mut Context<Self>) {
+        let processor = self.processor.clone();
+
+        let process = self
+            .blockchain_api_client
+            .send(GetTransactionByHash(hash))
+            .from_err::<Error>()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+            .and_then(move |transaction| {
+                processor
+                    .send(ProcessPendingTransactions(vec![transaction]))
+                    .from_err()
+                    .and_then(|res| res.map_err(|e| Error::from(e)))
+            })
+            // Transactions can be dropped from the pool before they're looked up.
+            .map_err(move |e| debug!("Failed to process transaction {}: {:?}", hash, e));
+
+        ctx.spawn(wrap_future(process));
+    }
+}
+
+impl Actor for Subscriber {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Context<Self>) {
+        self.connect(ctx);
+    }
+}
+
+impl StreamHandler<WsMessage, ProtocolError> for Subscriber {
+    fn handle(&mut self, message: WsMessage, ctx: &mut Context<Self>) {
+        let text = match message {
+            WsMessage::Text(text) => text,
+            WsMessage::Ping(ping) => {
+                if let Some(ref mut writer) = self.writer {
+                    writer.pong(&ping);
+                }
+                return;
+            }
+            _ => return,
+        };
+
+        let message: Value = match serde_json::from_str(&text) {
+            Ok(message) => message,
+            Err(e) => return error!("Failed to parse a websocket message: {}", e),
+        };
+
+        // Response to a subscription request. Nodes may not serve `newPendingTransactions`, in
+        // which case the pending block keeps being polled.
+        if let Some(request_id) = message.get("id").and_then(|id| id.as_u64()) {
+            let subscription = match request_id {
+                1 => Subscription::NewHeads,
+                2 => Subscription::NewPendingTransactions,
+                _ => return,
+            };
+
+            match message.get("result").and_then(|id| id.as_str()) {
+                Some(id) => self.subscribed(subscription, id.to_owned()),
+                None => warn!(
+                    "Failed to subscribe to {} of network {}: {}",
+                    subscription.name(),
+                    self.network,
+                    message.get("error").unwrap_or(&Value::Null)
+                ),
+            };
+
+            return;
+        }
+
+        let params = match message.get("params") {
+            Some(params) => params,
+            None => return,
+        };
+
+        let subscription = match params
+            .get("subscription")
+            .and_then(|id| id.as_str())
+            .and_then(|id| self.subscriptions.get(id))
+        {
+            Some(subscription) => *subscription,
+            None => return,
+        };
+
+        match subscription {
+            Subscription::NewHeads => self.poller.do_send(BlockAnnounced),
+            Subscription::NewPendingTransactions => {
+                match params
+                    .get("result")
+                    .and_then(|hash| hash.as_str())
+                    .and_then(|hash| hash.trim_start_matches("0x").parse::<H256>().ok())
+                {
+                    Some(hash) => self.process_pending_transaction(hash, ctx),
+                    None => (),
+                }
+            }
+        };
+    }
+
+    fn error(&mut self, e: ProtocolError, _: &mut Context<Self>) -> Running {
+        error!("Websocket error on network {}: {}", self.network, e);
+        Running::Continue
+    }
+
+    fn finished(&mut self, ctx: &mut Context<Self>) {
+        warn!("Lost the websocket connection to network {}", self.network);
+        self.reconnect_later(ctx);
+    }
+}
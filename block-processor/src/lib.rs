@@ -12,12 +12,24 @@ extern crate futures_timer;
 extern crate log;
 extern crate env_logger;
 extern crate serde;
+#[macro_use]
 extern crate serde_json;
 extern crate tokio;
+extern crate uuid;
+extern crate zmq;
 
 extern crate blockchain_api_client;
+extern crate config;
 extern crate core;
+extern crate lightning_api_client;
+extern crate mailer;
 extern crate types;
 
 pub mod bitcoin;
+mod announcements;
+mod catch_up;
+mod discrepancy;
 pub mod ethereum;
+pub mod lightning;
+#[cfg(test)]
+mod testing;
@@ -0,0 +1,32 @@
+use actix::MailboxError;
+use core::ModelError;
+
+use lightning_api_client::Error as LightningApiClientError;
+
+#[derive(Debug, Fail)]
+pub enum Error {
+    #[fail(display = "{}", _0)]
+    ModelError(#[cause] ModelError),
+    #[fail(display = "{}", _0)]
+    MailboxError(#[cause] MailboxError),
+    #[fail(display = "{}", _0)]
+    LightningApiClientError(#[cause] LightningApiClientError),
+}
+
+impl From<ModelError> for Error {
+    fn from(e: ModelError) -> Error {
+        Error::ModelError(e)
+    }
+}
+
+impl From<MailboxError> for Error {
+    fn from(e: MailboxError) -> Error {
+        Error::MailboxError(e)
+    }
+}
+
+impl From<LightningApiClientError> for Error {
+    fn from(e: LightningApiClientError) -> Error {
+        Error::LightningApiClientError(e)
+    }
+}
@@ -0,0 +1,5 @@
+mod errors;
+
+pub use self::errors::Error;
+pub mod service;
+pub mod watcher;
@@ -0,0 +1,15 @@
+use actix::prelude::*;
+
+use core::db::postgres;
+use lightning::watcher::Watcher;
+use lightning_api_client::LightningClientAddr;
+
+pub fn run(
+    postgres: postgres::PgExecutorAddr,
+    lightning_client: LightningClientAddr,
+) -> Addr<Watcher> {
+    Arbiter::start(move |_| Watcher {
+        postgres,
+        lightning_client,
+    })
+}
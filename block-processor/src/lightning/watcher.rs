@@ -0,0 +1,196 @@
+use std::{str::FromStr, time::Duration};
+
+use actix::{fut::wrap_future, prelude::*};
+use bigdecimal::BigDecimal;
+use chrono::prelude::*;
+use futures::{future, stream, Future, Stream};
+
+use core::{
+    db::postgres::PgExecutorAddr,
+    payment::{Payment, PaymentPayload},
+};
+use lightning::Error;
+use lightning_api_client::{InvoiceState, LightningClientAddr, LookupInvoice};
+use types::{bitcoin::MSAT_PER_BTC, currency::Crypto, PaymentStatus};
+
+// Polls the Lightning backend for the invoices of pending payments and marks the payments
+// confirmed once their invoice is settled.
+pub struct Watcher {
+    pub postgres: PgExecutorAddr,
+    pub lightning_client: LightningClientAddr,
+}
+
+impl Actor for Watcher {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Context<Self>) {
+        ctx.run_interval(Duration::new(10, 0), move |watcher, ctx| {
+            let process = wrap_future(watcher.check_invoices())
+                .map_err(|e: Error, _: &mut Watcher, _| error!("{:?}", e));
+
+            ctx.spawn(process);
+        });
+    }
+}
+
+impl Watcher {
+    fn check_invoices(&self) -> impl Future<Item = (), Error = Error> {
+        let postgres = self.postgres.clone();
+        let lightning_client = self.lightning_client.clone();
+
+        Payment::find_all_pending(Crypto::BtcLightning, &postgres)
+            .from_err()
+            .map(move |payments| stream::iter_ok(payments))
+            .flatten_stream()
+            .filter(|payment| payment.ln_payment_hash.is_some())
+            .for_each(move |payment| {
+                let postgres = postgres.clone();
+
+                lightning_client
+                    .send(LookupInvoice(payment.ln_payment_hash.clone().unwrap()))
+                    .from_err()
+                    .and_then(|res| res.map_err(|e| Error::from(e)))
+                    .and_then(move |state| update_payment(payment, state, &postgres))
+                    // One failing lookup shouldn't hold back the other payments.
+                    .then(|res| {
+                        if let Err(e) = res {
+                            error!("{:?}", e);
+                        }
+
+                        future::ok(())
+                    })
+            })
+    }
+}
+
+fn update_payment(
+    payment: Payment,
+    state: InvoiceState,
+    postgres: &PgExecutorAddr,
+) -> Box<Future<Item = (), Error = Error>> {
+    let mut payload = PaymentPayload::new();
+
+    match state {
+        InvoiceState::Settled { amount_paid_msat } => {
+            info!("Lightning invoice of payment {} settled", payment.id);
+
+            let amount_paid = BigDecimal::from_str(&amount_paid_msat.to_string()).unwrap()
+                / BigDecimal::from_str(MSAT_PER_BTC).unwrap();
+
+            // Invoices can be settled for less than the charge when they don't carry an amount.
+            payload.status = Some(if amount_paid >= payment.charge {
+                PaymentStatus::Confirmed
+            } else {
+                PaymentStatus::InsufficientAmount
+            });
+            payload.amount_paid = Some(amount_paid);
+            payload.set_paid_at();
+        }
+        InvoiceState::Canceled => payload.status = Some(PaymentStatus::Expired),
+        // The node refuses to settle expired invoices, even if it still reports them as open.
+        InvoiceState::Open if payment.expires_at < Utc::now() => {
+            payload.status = Some(PaymentStatus::Expired)
+        }
+        InvoiceState::Open => return Box::new(future::ok(())),
+    };
+
+    Box::new(
+        Payment::update(payment.id, payload, postgres)
+            .from_err()
+            .map(|_| ()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration as ChronoDuration;
+    use uuid::Uuid;
+
+    use testing;
+    use types::{bitcoin::Network, currency::Fiat};
+
+    fn payment_payload(expires_at: DateTime<Utc>) -> PaymentPayload {
+        let store_id = Uuid::new_v4();
+
+        let mut payload = PaymentPayload::new();
+        payload.status = Some(PaymentStatus::Pending);
+        payload.store_id = Some(store_id);
+        payload.index = Some(0);
+        payload.created_by = Some(store_id);
+        payload.set_created_at();
+        payload.expires_at = Some(expires_at);
+        payload.fiat = Some(Fiat::Usd);
+        payload.price = Some(BigDecimal::from_str("1").unwrap());
+        payload.crypto = Some(Crypto::BtcLightning);
+        payload.address = Some(format!("lntest-{}", Uuid::new_v4().simple()));
+        payload.ln_payment_hash = Some(testing::hash().to_string());
+        payload.charge = Some(BigDecimal::from_str("0.0000015").unwrap());
+        payload.confirmations_required = Some(0);
+        payload.btc_network = Some(Network::Test);
+        payload
+    }
+
+    // Updates a new payment expiring at `expires_at` with the state of its invoice.
+    fn update(expires_at: DateTime<Utc>, state: InvoiceState) -> Payment {
+        testing::run(move |postgres, _| {
+            let _postgres = postgres.clone();
+            let __postgres = postgres.clone();
+
+            Box::new(
+                Payment::insert(payment_payload(expires_at), &postgres)
+                    .from_err()
+                    .and_then(move |payment| {
+                        let id = payment.id;
+
+                        update_payment(payment, state, &_postgres).map(move |_| id)
+                    })
+                    .and_then(move |id| Payment::find_by_id(id, &__postgres).from_err()),
+            )
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn settled_invoices_confirm_payments_paid_in_full() {
+        let payment = update(
+            Utc::now() + ChronoDuration::minutes(15),
+            InvoiceState::Settled {
+                amount_paid_msat: 150_000,
+            },
+        );
+
+        assert_eq!(payment.status, PaymentStatus::Confirmed);
+        assert_eq!(
+            payment.amount_paid,
+            Some(BigDecimal::from_str("0.0000015").unwrap())
+        );
+        assert!(payment.paid_at.is_some());
+    }
+
+    #[test]
+    fn invoices_settled_for_less_than_the_charge_are_insufficient() {
+        let payment = update(
+            Utc::now() + ChronoDuration::minutes(15),
+            InvoiceState::Settled {
+                amount_paid_msat: 149_999,
+            },
+        );
+
+        assert_eq!(payment.status, PaymentStatus::InsufficientAmount);
+    }
+
+    #[test]
+    fn open_invoices_expire_with_their_payment() {
+        let open = update(Utc::now() + ChronoDuration::minutes(15), InvoiceState::Open);
+        let expired = update(Utc::now() - ChronoDuration::minutes(1), InvoiceState::Open);
+        let canceled = update(
+            Utc::now() + ChronoDuration::minutes(15),
+            InvoiceState::Canceled,
+        );
+
+        assert_eq!(open.status, PaymentStatus::Pending);
+        assert_eq!(expired.status, PaymentStatus::Expired);
+        assert_eq!(canceled.status, PaymentStatus::Expired);
+    }
+}
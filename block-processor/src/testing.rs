@@ -0,0 +1,69 @@
+use std::{cell::RefCell, env, rc::Rc, str::FromStr};
+
+use actix::prelude::*;
+use futures::Future;
+use uuid::Uuid;
+
+use config::{MailConfig, MailTransport};
+use core::db::postgres::{self, PgExecutor, PgExecutorAddr};
+use mailer::{self, Mailer, Notifier, NotifierAddr};
+use types::{H160, H256};
+
+// Runs the future made by `f` on a system of its own, with an executor on the migrated database
+// in `DATABASE_URL`, which has to be set, and a notifier printing its mails.
+pub fn run<F, T, E>(f: F) -> Result<T, E>
+where
+    F: FnOnce(PgExecutorAddr, NotifierAddr) -> Box<Future<Item = T, Error = E>>,
+    T: 'static,
+    E: 'static,
+{
+    let url = env::var("DATABASE_URL").expect("DATABASE_URL must point to a migrated database");
+
+    let system = System::new("test");
+
+    let pg_pool = postgres::init_pool(&url);
+    let postgres = SyncArbiter::start(1, move || PgExecutor(pg_pool.clone()));
+
+    let mailer = SyncArbiter::start(1, || {
+        let mail_config = MailConfig {
+            transport: MailTransport::Stdout,
+            dir: None,
+        };
+
+        Mailer(mailer::init_transport(&mail_config, None))
+    });
+
+    let _postgres = postgres.clone();
+    let notifier = Arbiter::start(move |_| {
+        Notifier::new(mailer, _postgres, String::from("finch@example.com"))
+    });
+
+    let result = Rc::new(RefCell::new(None));
+    let _result = result.clone();
+
+    Arbiter::spawn(f(postgres, notifier).then(move |res| {
+        *_result.borrow_mut() = Some(res);
+
+        System::current().stop();
+        Ok(())
+    }));
+
+    system.run();
+
+    let result = result.borrow_mut().take();
+    result.expect("the system stopped before the future resolved")
+}
+
+// Rows of the tests are never cleaned up, so the hashes and addresses they use are fresh ones.
+pub fn hash() -> H256 {
+    H256::from_str(&format!(
+        "{}{}",
+        Uuid::new_v4().simple(),
+        Uuid::new_v4().simple()
+    ))
+    .unwrap()
+}
+
+pub fn eth_address() -> H160 {
+    H160::from_str(&format!("{}{}", Uuid::new_v4().simple(), "00000000")).unwrap()
+}
@@ -10,6 +10,7 @@ use futures::{
 use rustc_hex::ToHex;
 use serde_json::{self, Value};
 
+use bitcoin::TxOutSetScan;
 use core::bitcoin::{Block, Transaction};
 use errors::Error;
 use types::{H256, U128};
@@ -326,6 +327,60 @@ impl BlockchainApiClient {
                     }
                 };
 
+                err(Error::CustomError(format!(
+                    "{}",
+                    body.get("error")
+                        .unwrap()
+                        .get("message")
+                        .unwrap()
+                        .as_str()
+                        .unwrap()
+                )))
+            })
+        }))
+    }
+    // Unspent outputs of the given addresses, looked up in the node's UTXO set without needing
+    // them in a wallet. Only one scan can run on a node at a time, and a scan takes minutes on
+    // mainnet.
+    pub fn scan_tx_out_set(
+        &self,
+        addresses: Vec<String>,
+    ) -> Box<Future<Item = TxOutSetScan, Error = Error>> {
+        let descriptors: Vec<String> = addresses
+            .iter()
+            .map(|address| format!("addr({})", address))
+            .collect();
+
+        let req = match client::ClientRequest::post(&self.url)
+            .header("Authorization", format!("{}", self.basic_auth))
+            .timeout(Duration::from_secs(600))
+            .content_type("application/json")
+            .json(json!({
+                "jsonrpc": "1.0",
+                "method": "scantxoutset",
+                "params": ("start", descriptors),
+                "id": "1"
+            })) {
+            Ok(req) => req,
+            Err(e) => return Box::new(err(Error::CustomError(format!("{}", e)))),
+        };
+
+        Box::new(req.send().from_err().and_then(move |resp| {
+            resp.body().limit(4194304).from_err().and_then(move |body| {
+                let body: Value = match serde_json::from_slice(&body) {
+                    Ok(body) => body,
+                    Err(e) => return err(Error::from(e)),
+                };
+
+                if let Some(result) = body.get("result") {
+                    if !result.is_null() {
+                        match serde_json::from_value::<TxOutSetScan>(result.clone()) {
+                            Ok(scan) => return ok(scan),
+                            Err(e) => return err(Error::from(e)),
+                        }
+                    }
+                };
+
                 err(Error::CustomError(format!(
                     "{}",
                     body.get("error")
@@ -461,3 +516,19 @@ impl Handler<GetRawMempool> for BlockchainApiClient {
         self.get_raw_mempool()
     }
 }
+
+#[derive(Message)]
+#[rtype(result = "Result<TxOutSetScan, Error>")]
+pub struct ScanTxOutSet(pub Vec<String>);
+
+impl Handler<ScanTxOutSet> for BlockchainApiClient {
+    type Result = Box<Future<Item = TxOutSetScan, Error = Error>>;
+
+    fn handle(
+        &mut self,
+        ScanTxOutSet(addresses): ScanTxOutSet,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        self.scan_tx_out_set(addresses)
+    }
+}
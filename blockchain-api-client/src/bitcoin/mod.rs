@@ -1,8 +1,15 @@
 mod api_client;
+mod psbt;
+mod raw;
 mod transaction;
+mod utxo_set;
 
 pub use self::api_client::{
     EstimateSmartFee, GetBlock, GetBlockByNumber, GetBlockCount, GetBlockHash, GetRawMempool,
-    GetRawTransaction, BlockchainApiClient, BlockchainApiClientAddr, SendRawTransaction,
+    GetRawTransaction, BlockchainApiClient, BlockchainApiClientAddr, ScanTxOutSet,
+    SendRawTransaction,
 };
-pub use self::transaction::UnsignedTransaction;
+pub use self::psbt::Psbt;
+pub use self::raw::{decode_block, decode_transaction};
+pub use self::transaction::{OutPoint, Script, UnsignedTransaction};
+pub use self::utxo_set::{TxOutSetScan, Unspent};
@@ -0,0 +1,369 @@
+// Partially signed transactions (BIP174), handed to the cosigners of multisig addresses to add
+// their signatures, finalize and broadcast, or to reviewers of payouts awaiting approval.
+
+use base64;
+use byteorder::{LittleEndian, WriteBytesExt};
+use rustc_hex::FromHex;
+use secp256k1::{key::PublicKey, RecoverableSignature, Secp256k1, Signature};
+
+use super::transaction::{Reader, Script, UnsignedTransaction};
+use core::bitcoin::Transaction;
+use errors::Error;
+use types::{bitcoin::VarInt, H256};
+
+const MAGIC: &[u8] = b"psbt\xff";
+const SEPARATOR: u8 = 0x00;
+
+const PSBT_GLOBAL_UNSIGNED_TX: u8 = 0x00;
+const PSBT_IN_NON_WITNESS_UTXO: u8 = 0x00;
+const PSBT_IN_WITNESS_UTXO: u8 = 0x01;
+const PSBT_IN_PARTIAL_SIG: u8 = 0x02;
+const PSBT_IN_SIGHASH_TYPE: u8 = 0x03;
+const PSBT_IN_WITNESS_SCRIPT: u8 = 0x05;
+
+const SIGHASH_ALL: u32 = 0x01;
+
+// What an input spends.
+#[derive(Debug, Clone)]
+enum Spent {
+    // A legacy output, described by the raw transaction it belongs to.
+    NonWitness(Vec<u8>),
+    // A P2WSH output, with its witness script.
+    Witness(Vec<u8>),
+}
+
+#[derive(Debug, Clone)]
+pub struct Psbt {
+    transaction: UnsignedTransaction,
+    spent: Vec<Spent>,
+    partial_signatures: Vec<Vec<(PublicKey, Signature)>>,
+}
+
+impl Psbt {
+    // Every input of `transaction` spends a P2WSH output of the witness script at its index.
+    pub fn new(transaction: UnsignedTransaction, witness_scripts: Vec<Vec<u8>>) -> Self {
+        let spent = witness_scripts.into_iter().map(Spent::Witness).collect();
+
+        Psbt::with_spent(transaction, spent)
+    }
+
+    // Every input of `transaction` spends a legacy output of the previous transaction at its
+    // index.
+    pub fn legacy(transaction: UnsignedTransaction, previous_transactions: &[Transaction]) -> Self {
+        let spent = previous_transactions
+            .iter()
+            .map(|previous| Spent::NonWitness(previous.hex.from_hex().unwrap()))
+            .collect();
+
+        Psbt::with_spent(transaction, spent)
+    }
+
+    fn with_spent(transaction: UnsignedTransaction, spent: Vec<Spent>) -> Self {
+        let partial_signatures = spent.iter().map(|_| Vec::new()).collect();
+
+        Psbt {
+            transaction,
+            spent,
+            partial_signatures,
+        }
+    }
+
+    // Reads a PSBT serialized by `to_base64`, with what its inputs spend. Partial signatures and
+    // other keys are skipped.
+    pub fn from_base64(psbt: &str) -> Result<Self, Error> {
+        let bytes = base64::decode(psbt).map_err(|_| Error::InvalidTransaction)?;
+        if !bytes.starts_with(MAGIC) {
+            return Err(Error::InvalidTransaction);
+        }
+
+        let mut reader = Reader::new(&bytes[MAGIC.len()..]);
+
+        let mut transaction = None;
+        while let Some((key, value)) = read_pair(&mut reader)? {
+            if key == &[PSBT_GLOBAL_UNSIGNED_TX][..] {
+                transaction = Some(UnsignedTransaction::deserialize(value)?);
+            }
+        }
+        let mut transaction = transaction.ok_or(Error::InvalidTransaction)?;
+
+        let mut spent = Vec::new();
+        for input in transaction.inputs.iter_mut() {
+            let mut previous_transaction = None;
+            let mut witness_script = None;
+
+            while let Some((key, value)) = read_pair(&mut reader)? {
+                match key {
+                    [PSBT_IN_NON_WITNESS_UTXO] => previous_transaction = Some(value),
+                    [PSBT_IN_WITNESS_SCRIPT] => witness_script = Some(value),
+                    [PSBT_IN_WITNESS_UTXO] => {
+                        let mut witness_utxo = Reader::new(value);
+                        input.value = witness_utxo.read_u64()?;
+                        input.script_sig = Script(witness_utxo.read_bytes()?.to_vec());
+                    }
+                    _ => (),
+                }
+            }
+
+            // Inputs are signed with the script of the output they spend in place of theirs.
+            match (previous_transaction, witness_script) {
+                (_, Some(witness_script)) => spent.push(Spent::Witness(witness_script.to_vec())),
+                (Some(previous_transaction), None) => {
+                    let previous = UnsignedTransaction::deserialize(previous_transaction)?;
+                    let output = previous
+                        .outputs
+                        .get(input.outpoint.index as usize)
+                        .ok_or(Error::InvalidTransaction)?;
+
+                    input.value = output.value;
+                    input.script_sig = output.script_pubkey.clone();
+                    spent.push(Spent::NonWitness(previous_transaction.to_vec()));
+                }
+                (None, None) => return Err(Error::InvalidTransaction),
+            }
+        }
+
+        for _ in transaction.outputs.iter() {
+            while let Some(_) = read_pair(&mut reader)? {}
+        }
+
+        Ok(Psbt::with_spent(transaction, spent))
+    }
+
+    pub fn transaction(&self) -> &UnsignedTransaction {
+        &self.transaction
+    }
+
+    // Signs every P2WSH input for `pkey` through `sign`, which gets each digest to sign with its
+    // key.
+    pub fn sign<F, E>(&mut self, pkey: PublicKey, mut sign: F) -> Result<(), E>
+    where
+        F: FnMut(&H256) -> Result<RecoverableSignature, E>,
+    {
+        let secp = Secp256k1::new();
+
+        for (idx, spent) in self.spent.iter().enumerate() {
+            let witness_script = match *spent {
+                Spent::Witness(ref witness_script) => witness_script,
+                Spent::NonWitness(_) => continue,
+            };
+
+            let hash = self
+                .transaction
+                .witness_signature_hash(idx, witness_script, SIGHASH_ALL);
+            let signature = sign(&hash)?.to_standard(&secp);
+
+            self.partial_signatures[idx].push((pkey, signature));
+        }
+
+        Ok(())
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        let secp = Secp256k1::new();
+
+        // The unsigned transaction carries empty scripts, the previous outputs being below.
+        let mut transaction = self.transaction.clone();
+        for input in transaction.inputs.iter_mut() {
+            input.script_sig = Script::default();
+        }
+
+        let mut stream = MAGIC.to_vec();
+
+        write_pair(
+            &mut stream,
+            &[PSBT_GLOBAL_UNSIGNED_TX],
+            &transaction.into_raw_transaction(),
+        );
+        stream.push(SEPARATOR);
+
+        for (idx, input) in self.transaction.inputs.iter().enumerate() {
+            match self.spent[idx] {
+                Spent::NonWitness(ref previous_transaction) => {
+                    write_pair(
+                        &mut stream,
+                        &[PSBT_IN_NON_WITNESS_UTXO],
+                        previous_transaction,
+                    );
+                }
+                Spent::Witness(_) => {
+                    let mut witness_utxo = Vec::new();
+                    witness_utxo.write_u64::<LittleEndian>(input.value).unwrap();
+                    VarInt::from(input.script_sig.len()).serialize(&mut witness_utxo);
+                    witness_utxo.extend_from_slice(&input.script_sig);
+                    write_pair(&mut stream, &[PSBT_IN_WITNESS_UTXO], &witness_utxo);
+                }
+            }
+
+            for (pkey, signature) in self.partial_signatures[idx].iter() {
+                let mut key = vec![PSBT_IN_PARTIAL_SIG];
+                key.extend_from_slice(&pkey.serialize());
+
+                let mut der_signature = signature.serialize_der(&secp);
+                der_signature.push(SIGHASH_ALL as u8);
+
+                write_pair(&mut stream, &key, &der_signature);
+            }
+
+            let mut sighash_type = Vec::new();
+            sighash_type.write_u32::<LittleEndian>(SIGHASH_ALL).unwrap();
+            write_pair(&mut stream, &[PSBT_IN_SIGHASH_TYPE], &sighash_type);
+
+            if let Spent::Witness(ref witness_script) = self.spent[idx] {
+                write_pair(&mut stream, &[PSBT_IN_WITNESS_SCRIPT], witness_script);
+            }
+            stream.push(SEPARATOR);
+        }
+
+        for _ in self.transaction.outputs.iter() {
+            stream.push(SEPARATOR);
+        }
+
+        stream
+    }
+
+    pub fn to_base64(&self) -> String {
+        base64::encode(&self.serialize())
+    }
+}
+
+fn write_pair(stream: &mut Vec<u8>, key: &[u8], value: &[u8]) {
+    VarInt::from(key.len()).serialize(stream);
+    stream.extend_from_slice(key);
+    VarInt::from(value.len()).serialize(stream);
+    stream.extend_from_slice(value);
+}
+
+// Yields `None` at the separator ending a map.
+fn read_pair<'a>(reader: &mut Reader<'a>) -> Result<Option<(&'a [u8], &'a [u8])>, Error> {
+    let key = reader.read_bytes()?;
+    if key.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some((key, reader.read_bytes()?)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secp256k1::Message;
+
+    // BIP174 test vectors. One P2PKH input, with empty outputs:
+    const LEGACY_PSBT: &str = "70736274ff0100750200000001268171371edff285e937adeea4b37b78000c05\
+                               66cbb3ad64641713ca42171bf60000000000feffffff02d3dff5050000000019\
+                               76a914d0c59903c5bac2868760e90fd521a4665aa7652088ac00e1f505000000\
+                               0017a9143545e6e33b832c47050f24d3eeb93c9c03948bc787b32e1300000100\
+                               fda5010100000000010289a3c71eab4d20e0371bbba4cc698fa295c9463afa2e\
+                               397f8533ccb62f9567e50100000017160014be18d152a9b012039daf3da7de4f\
+                               53349eecb985ffffffff86f8aa43a71dff1448893a530a7237ef6b4608bbb2dd\
+                               2d0171e63aec6a4890b40100000017160014fe3e9ef1a745e974d902c4355943\
+                               abcb34bd5353ffffffff0200c2eb0b000000001976a91485cff1097fd9e008bb\
+                               34af709c62197b38978a4888ac72fef84e2c00000017a914339725ba21efd62a\
+                               c753a9bcd067d6c7a6a39d05870247304402202712be22e0270f394f568311dc\
+                               7ca9a68970b8025fdd3b240229f07f8a5f3a240220018b38d7dcd314e734c927\
+                               6bd6fb40f673325bc4baa144c800d2f2f02db2765c012103d2e15674941bad4a\
+                               996372cb87e1856d3652606d98562fe39c5e9e7e413f210502483045022100d1\
+                               2b852d85dcd961d2f5f4ab660654df6eedcc794c0c33ce5cc309ffb5fce58d02\
+                               2067338a8e0e1725c197fb1a88af59f51e44e4255b20167c8684031c05d1f259\
+                               2a01210223b72beef0965d10be0778efecd61fcac6f79a4ea169393380734464\
+                               f84f2ab300000000000000";
+
+    // The same with the sighash type of the input:
+    const SIGHASH_PSBT: &str = "70736274ff0100750200000001268171371edff285e937adeea4b37b78000c05\
+                                66cbb3ad64641713ca42171bf60000000000feffffff02d3dff5050000000019\
+                                76a914d0c59903c5bac2868760e90fd521a4665aa7652088ac00e1f505000000\
+                                0017a9143545e6e33b832c47050f24d3eeb93c9c03948bc787b32e1300000100\
+                                fda5010100000000010289a3c71eab4d20e0371bbba4cc698fa295c9463afa2e\
+                                397f8533ccb62f9567e50100000017160014be18d152a9b012039daf3da7de4f\
+                                53349eecb985ffffffff86f8aa43a71dff1448893a530a7237ef6b4608bbb2dd\
+                                2d0171e63aec6a4890b40100000017160014fe3e9ef1a745e974d902c4355943\
+                                abcb34bd5353ffffffff0200c2eb0b000000001976a91485cff1097fd9e008bb\
+                                34af709c62197b38978a4888ac72fef84e2c00000017a914339725ba21efd62a\
+                                c753a9bcd067d6c7a6a39d05870247304402202712be22e0270f394f568311dc\
+                                7ca9a68970b8025fdd3b240229f07f8a5f3a240220018b38d7dcd314e734c927\
+                                6bd6fb40f673325bc4baa144c800d2f2f02db2765c012103d2e15674941bad4a\
+                                996372cb87e1856d3652606d98562fe39c5e9e7e413f210502483045022100d1\
+                                2b852d85dcd961d2f5f4ab660654df6eedcc794c0c33ce5cc309ffb5fce58d02\
+                                2067338a8e0e1725c197fb1a88af59f51e44e4255b20167c8684031c05d1f259\
+                                2a01210223b72beef0965d10be0778efecd61fcac6f79a4ea169393380734464\
+                                f84f2ab30000000001030401000000000000";
+
+    // One P2SH-P2WSH input of a 2-of-2 multisig, signed with its first key:
+    const WITNESS_PSBT: &str = "70736274ff0100550200000001279a2323a5dfb51fc45f220fa58b0fc13e1e33\
+                                42792a85d7e36cd6333b5cbc390000000000ffffffff01a05aea0b0000000019\
+                                76a914ffe9c0061097cc3b636f2cb0460fa4fc427d2b4588ac00000000000101\
+                                20955eea0b0000000017a9146345200f68d189e1adc0df1c4d16ea8f14c0dbeb\
+                                87220203b1341ccba7683b6af4f1238cd6e97e7167d569fac47f1e48d4754184\
+                                4355bd4646304302200424b58effaaa694e1559ea5c93bbfd4a89064224055cd\
+                                f070b6771469442d07021f5c8eb0fea6516d60b8acb33ad64ede60e8785bfb3a\
+                                a94b99bdf86151db9a9a010104220020771fd18ad459666dd49f3d564e3dbc42\
+                                f4c84774e360ada16816a8ed488d5681010547522103b1341ccba7683b6af4f1\
+                                238cd6e97e7167d569fac47f1e48d47541844355bd462103de55d1e1dac805e3\
+                                f8a58c1fbf9b94c02f3dbaafe127fefca4995f26f82083bd52ae220603b1341c\
+                                cba7683b6af4f1238cd6e97e7167d569fac47f1e48d47541844355bd4610b4a6\
+                                ba67000000800000008004000080220603de55d1e1dac805e3f8a58c1fbf9b94\
+                                c02f3dbaafe127fefca4995f26f82083bd10b4a6ba6700000080000000800500\
+                                00800000";
+
+    fn from_hex(psbt: &str) -> Psbt {
+        Psbt::from_base64(&base64::encode(&psbt.from_hex::<Vec<u8>>().unwrap())).unwrap()
+    }
+
+    #[test]
+    fn read_legacy_input() {
+        let psbt = from_hex(LEGACY_PSBT);
+        let transaction = psbt.transaction();
+
+        assert_eq!(transaction.inputs.len(), 1);
+        assert_eq!(transaction.outputs.len(), 2);
+        assert_eq!(transaction.inputs[0].value, 200_000_000);
+        assert_eq!(
+            transaction.inputs[0].script_sig.to_vec(),
+            "76a91485cff1097fd9e008bb34af709c62197b38978a4888ac"
+                .from_hex::<Vec<u8>>()
+                .unwrap()
+        );
+
+        assert_eq!(
+            psbt.serialize(),
+            SIGHASH_PSBT.from_hex::<Vec<u8>>().unwrap()
+        );
+    }
+
+    #[test]
+    fn verify_partial_signature() {
+        let psbt = from_hex(WITNESS_PSBT);
+        let witness_script = match psbt.spent[0] {
+            Spent::Witness(ref witness_script) => witness_script.clone(),
+            Spent::NonWitness(_) => panic!("the input spends a witness output"),
+        };
+
+        assert_eq!(psbt.transaction().inputs[0].value, 199_909_013);
+
+        let hash = psbt
+            .transaction()
+            .witness_signature_hash(0, &witness_script, SIGHASH_ALL);
+        assert_eq!(
+            hash.to_vec(),
+            "768adbe5e70db1200ef6c6275b3006fda0577f83905854cf3669ff3ea3137848"
+                .from_hex::<Vec<u8>>()
+                .unwrap()
+        );
+
+        let secp = Secp256k1::new();
+        let pkey = "03b1341ccba7683b6af4f1238cd6e97e7167d569fac47f1e48d47541844355bd46"
+            .from_hex::<Vec<u8>>()
+            .unwrap();
+        let signature = "304302200424b58effaaa694e1559ea5c93bbfd4a89064224055cdf070b67714\
+                         69442d07021f5c8eb0fea6516d60b8acb33ad64ede60e8785bfb3aa94b99bdf8\
+                         6151db9a9a"
+            .from_hex::<Vec<u8>>()
+            .unwrap();
+
+        let pkey = PublicKey::from_slice(&secp, &pkey).unwrap();
+        let signature = Signature::from_der(&secp, &signature).unwrap();
+        let message = Message::from_slice(&hash).unwrap();
+
+        assert!(secp.verify(&message, &signature, &pkey).is_ok());
+    }
+}
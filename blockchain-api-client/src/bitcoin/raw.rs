@@ -0,0 +1,365 @@
+use rust_base58::ToBase58;
+use rustc_hex::ToHex;
+
+use super::transaction::{Reader, UnsignedTransaction};
+use core::bitcoin::{
+    Block, ScriptType, SignedTransactionInput, SignedTransactionOutput, Transaction,
+    TransactionInputScript, TransactionOutputScript,
+};
+use errors::Error;
+use types::{
+    bitcoin::{bech32, ChainParams},
+    currency::Crypto,
+    H256, U128,
+};
+
+const SATOSHIS_PER_COIN: f64 = 100_000_000.0;
+const HEADER_LENGTH: usize = 80;
+// Merge-mined Dogecoin blocks carry the proof of work of their parent chain after the header.
+const AUX_POW_VERSION: u32 = 0x100;
+
+// Decodes a raw transaction, e.g. one of the node's `rawtx` notifications, the way
+// `getrawtransaction` returns it verbosely. Scripts aren't disassembled.
+pub fn decode_transaction(
+    raw_transaction: &[u8],
+    chain: &ChainParams,
+) -> Result<Transaction, Error> {
+    let mut reader = Reader::new(raw_transaction);
+    let transaction = UnsignedTransaction::read(&mut reader)?;
+
+    if !reader.is_empty() {
+        return Err(Error::InvalidTransaction);
+    }
+
+    Ok(verbose_transaction(&transaction, raw_transaction, chain))
+}
+
+// Decodes a raw block, e.g. one of the node's `rawblock` notifications. Its height is read from
+// the coinbase (BIP34), and is `None` for blocks from before it.
+pub fn decode_block(raw_block: &[u8], chain: &ChainParams) -> Result<Block, Error> {
+    let mut reader = Reader::new(raw_block);
+
+    let header = reader.take(HEADER_LENGTH)?;
+    let mut header_reader = Reader::new(header);
+    let version = header_reader.read_u32()?;
+    let previous_block_hash = reverse(header_reader.take(32)?);
+    let merkleroot = reverse(header_reader.take(32)?);
+    let time = header_reader.read_u32()?;
+    let _bits = header_reader.read_u32()?;
+    let nonce = header_reader.read_u32()?;
+
+    let mut hash = H256::from_data(header);
+    hash.reverse();
+
+    if chain.crypto == Crypto::Doge && version & AUX_POW_VERSION != 0 {
+        skip_aux_pow(&mut reader)?;
+    }
+
+    let mut height = None;
+    let mut transactions = Vec::new();
+    for i in 0..reader.read_var_int()? {
+        let remaining = reader.remaining();
+        let transaction = UnsignedTransaction::read(&mut reader)?;
+        let raw_transaction = &remaining[..remaining.len() - reader.remaining().len()];
+
+        if i == 0 && version >= 2 {
+            height = transaction
+                .inputs
+                .first()
+                .and_then(|coinbase| coinbase_height(&coinbase.script_sig));
+        }
+
+        let mut transaction = verbose_transaction(&transaction, raw_transaction, chain);
+        transaction.blockhash = Some(hash);
+        transaction.time = Some(time);
+        transaction.blocktime = Some(time);
+        transactions.push(transaction);
+    }
+
+    if !reader.is_empty() {
+        return Err(Error::InvalidTransaction);
+    }
+
+    Ok(Block {
+        hash,
+        height,
+        version,
+        merkleroot,
+        transactions: Some(transactions),
+        time,
+        nonce,
+        previousblockhash: Some(previous_block_hash),
+    })
+}
+
+fn verbose_transaction(
+    transaction: &UnsignedTransaction,
+    raw_transaction: &[u8],
+    chain: &ChainParams,
+) -> Transaction {
+    // The txid leaves the witnesses out, the hash doesn't.
+    let stripped = transaction.into_raw_transaction();
+    let mut txid = H256::from_data(&stripped);
+    txid.reverse();
+    let mut hash = H256::from_data(raw_transaction);
+    hash.reverse();
+    let weight = stripped.len() * 3 + raw_transaction.len();
+
+    let segwit = transaction
+        .inputs
+        .iter()
+        .any(|input| !input.script_witness.is_empty());
+
+    let vin = transaction
+        .inputs
+        .iter()
+        .map(|input| {
+            let txinwitness = if segwit {
+                Some(
+                    input
+                        .script_witness
+                        .iter()
+                        .map(|item| item.to_hex())
+                        .collect(),
+                )
+            } else {
+                None
+            };
+
+            if input.outpoint.hash == H256::new() && input.outpoint.index == u32::max_value() {
+                return SignedTransactionInput {
+                    txid: None,
+                    vout: None,
+                    script_sig: None,
+                    sequence: input.sequence,
+                    txinwitness,
+                    coinbase: Some(input.script_sig.to_hex()),
+                };
+            }
+
+            SignedTransactionInput {
+                txid: Some(input.outpoint.hash),
+                vout: Some(input.outpoint.index),
+                script_sig: Some(TransactionInputScript {
+                    asm: String::new(),
+                    hex: input.script_sig.to_hex(),
+                }),
+                sequence: input.sequence,
+                txinwitness,
+                coinbase: None,
+            }
+        })
+        .collect();
+
+    let vout = transaction
+        .outputs
+        .iter()
+        .enumerate()
+        .map(|(n, output)| {
+            let (script_type, address) = classify(&output.script_pubkey, chain);
+
+            SignedTransactionOutput {
+                value: output.value as f64 / SATOSHIS_PER_COIN,
+                n: n as u32,
+                script: TransactionOutputScript {
+                    asm: String::new(),
+                    hex: output.script_pubkey.to_hex(),
+                    req_sigs: address.as_ref().map(|_| 1),
+                    script_type,
+                    addresses: address.map(|address| vec![address]),
+                },
+            }
+        })
+        .collect();
+
+    Transaction {
+        txid,
+        hex: raw_transaction.to_hex(),
+        hash,
+        size: raw_transaction.len(),
+        vsize: (weight + 3) / 4,
+        version: transaction.version,
+        locktime: transaction.lock_time,
+        vin,
+        vout,
+        blockhash: None,
+        confirmations: None,
+        time: None,
+        blocktime: None,
+    }
+}
+
+// The standard output scripts payments are made to, with their address.
+fn classify(script: &[u8], chain: &ChainParams) -> (ScriptType, Option<String>) {
+    let segwit_address = |program: &[u8]| {
+        chain
+            .bech32_hrp
+            .map(|hrp| bech32::encode_segwit_address(hrp, program))
+    };
+
+    match script.len() {
+        // OP_DUP OP_HASH160 <20 bytes> OP_EQUALVERIFY OP_CHECKSIG
+        25 if script[..3] == [0x76, 0xa9, 0x14] && script[23..] == [0x88, 0xac] => (
+            ScriptType::PubKeyHash,
+            Some(base58_address(chain.p2pkh_version, &script[3..23])),
+        ),
+        // OP_HASH160 <20 bytes> OP_EQUAL
+        23 if script[..2] == [0xa9, 0x14] && script[22] == 0x87 => (
+            ScriptType::ScriptHash,
+            Some(base58_address(chain.p2sh_version, &script[2..22])),
+        ),
+        // OP_0 <20 bytes>
+        22 if script[..2] == [0x00, 0x14] => (ScriptType::WitnessKey, segwit_address(&script[2..])),
+        // OP_0 <32 bytes>
+        34 if script[..2] == [0x00, 0x20] => {
+            (ScriptType::WitnessScript, segwit_address(&script[2..]))
+        }
+        // OP_RETURN ...
+        _ if script.first() == Some(&0x6a) => (ScriptType::NullData, None),
+        _ => (ScriptType::NonStandard, None),
+    }
+}
+
+fn base58_address(version: u8, hash: &[u8]) -> String {
+    let mut address = vec![version];
+    address.extend_from_slice(hash);
+    let checksum = H256::from_data(&address);
+    address.extend_from_slice(&checksum[0..4]);
+    address.to_base58()
+}
+
+// The height pushed first by the coinbase script.
+fn coinbase_height(script: &[u8]) -> Option<U128> {
+    match script.first() {
+        Some(&0x00) => Some(U128::from(0)),
+        Some(&op) if op >= 0x51 && op <= 0x60 => Some(U128::from(u64::from(op - 0x50))),
+        Some(&len) if len >= 1 && len <= 8 && script.len() > len as usize => Some(U128::from(
+            script[1..=len as usize]
+                .iter()
+                .rev()
+                .fold(0u64, |height, byte| height << 8 | u64::from(*byte)),
+        )),
+        _ => None,
+    }
+}
+
+// The parent coinbase, the parent block hash, both merkle branches and the parent header.
+fn skip_aux_pow(reader: &mut Reader) -> Result<(), Error> {
+    UnsignedTransaction::read(reader)?;
+    reader.take(32)?;
+
+    for _ in 0..2 {
+        let branch_length = reader.read_var_int()?;
+        for _ in 0..branch_length {
+            reader.take(32)?;
+        }
+        reader.read_u32()?;
+    }
+
+    reader.take(HEADER_LENGTH)?;
+    Ok(())
+}
+
+fn reverse(bytes: &[u8]) -> H256 {
+    let mut hash = H256::from_slice(bytes);
+    hash.reverse();
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustc_hex::FromHex;
+    use types::bitcoin::Network;
+
+    const GENESIS_BLOCK: &str = "0100000000000000000000000000000000000000000000000000000000000000\
+                                 000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa\
+                                 4b1e5e4a29ab5f49ffff001d1dac2b7c01010000000100000000000000000000\
+                                 00000000000000000000000000000000000000000000ffffffff4d04ffff001d\
+                                 0104455468652054696d65732030332f4a616e2f32303039204368616e63656c\
+                                 6c6f72206f6e206272696e6b206f66207365636f6e64206261696c6f75742066\
+                                 6f722062616e6b73ffffffff0100f2052a01000000434104678afdb0fe554827\
+                                 1967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4\
+                                 f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac00000000";
+
+    #[test]
+    fn decode_genesis_block() {
+        let chain = ChainParams::new(Crypto::Btc, Network::Mainnet).unwrap();
+        let block = decode_block(&GENESIS_BLOCK.from_hex().unwrap(), &chain).unwrap();
+
+        assert_eq!(
+            block.hash,
+            "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f"
+                .parse()
+                .unwrap()
+        );
+        assert_eq!(block.previousblockhash, Some(H256::new()));
+        assert_eq!(block.height, None);
+        assert_eq!(block.time, 1231006505);
+
+        let transactions = block.transactions.unwrap();
+        assert_eq!(transactions.len(), 1);
+        assert_eq!(transactions[0].txid, block.merkleroot);
+        assert_eq!(
+            transactions[0].txid,
+            "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b"
+                .parse()
+                .unwrap()
+        );
+        assert_eq!(transactions[0].hash, transactions[0].txid);
+        assert_eq!(transactions[0].size, 204);
+        assert_eq!(transactions[0].blockhash, Some(block.hash));
+        assert!(transactions[0].vin[0].coinbase.is_some());
+        assert_eq!(format!("{}", transactions[0].vout[0].value), "50");
+        assert_eq!(transactions[0].vout[0].script.addresses, None);
+    }
+
+    #[test]
+    fn decode_output_addresses() {
+        let hash = "751e76e8199196d454941c45d1b3a323f1433bd6";
+        let outputs = vec![
+            format!("76a914{}88ac", hash),
+            format!("a914{}87", hash),
+            format!("0014{}", hash),
+            String::from("6a0401020304"),
+        ];
+
+        let addresses = |chain: ChainParams| {
+            outputs
+                .iter()
+                .map(|script| classify(&script.from_hex().unwrap(), &chain).1)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            addresses(ChainParams::new(Crypto::Btc, Network::Mainnet).unwrap()),
+            vec![
+                Some(String::from("1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH")),
+                Some(String::from("3CNHUhP3uyB9EUtRLsmvFUmvGdjGdkTxJw")),
+                Some(String::from("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4")),
+                None,
+            ]
+        );
+        assert_eq!(
+            addresses(ChainParams::new(Crypto::Btc, Network::Test).unwrap()),
+            vec![
+                Some(String::from("mrCDrCybB6J1vRfbwM5hemdJz73FwDBC8r")),
+                Some(String::from("2N3vVYSK5XRgVSGWy21PnsRmBUywSQNdCsf")),
+                Some(String::from("tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx")),
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn read_coinbase_heights() {
+        // The coinbase of block 227836, the first one with a BIP34 height.
+        assert_eq!(
+            coinbase_height(&"03fc790304".from_hex().unwrap()),
+            Some(U128::from(227_836))
+        );
+        assert_eq!(coinbase_height(&[0x51]), Some(U128::from(1)));
+        assert_eq!(coinbase_height(&[0x03, 0x01]), None);
+        assert_eq!(coinbase_height(&[]), None);
+    }
+}
@@ -1,15 +1,16 @@
 use std::ops::Deref;
 
-use byteorder::{LittleEndian, WriteBytesExt};
+use byteorder::{ByteOrder, LittleEndian, WriteBytesExt};
 use rust_base58::FromBase58;
 use rustc_hex::FromHex;
-use secp256k1::{
-    key::{PublicKey, SecretKey},
-    Message, Secp256k1, Signature,
-};
+use secp256k1::{key::PublicKey, RecoverableSignature, Secp256k1, Signature};
 
 use core::bitcoin::Transaction;
-use types::{bitcoin::VarInt, H256};
+use errors::Error;
+use types::{
+    bitcoin::{ChainParams, VarInt},
+    H256,
+};
 
 #[derive(Default, Debug, Clone)]
 pub struct Script(pub Vec<u8>);
@@ -29,11 +30,11 @@ impl Script {
         Script(script)
     }
 
-    pub fn script_sig(sig: Signature, pkey: PublicKey) -> Self {
+    pub fn script_sig(sig: Signature, pkey: PublicKey, sighash_type: u32) -> Self {
         let secp = Secp256k1::new();
 
         let mut der_sig = sig.serialize_der(&secp);
-        der_sig.push(0x01); //SIGHASH ALL
+        der_sig.push(sighash_type as u8);
 
         let mut script = Vec::new();
         script.write_u8(der_sig.len() as u8).unwrap();
@@ -43,9 +44,9 @@ impl Script {
         Script(script)
     }
 
-    pub fn from_hex(hex: String) -> Self {
-        let bytes = hex.from_hex().unwrap();
-        Script(bytes)
+    pub fn from_hex(hex: &str) -> Result<Self, Error> {
+        let bytes = hex.from_hex().map_err(|_| Error::InvalidScript)?;
+        Ok(Script(bytes))
     }
 }
 
@@ -62,15 +63,84 @@ const OP_EQUALVERIFY: u8 = 0x88;
 const OP_CHECKSIG: u8 = 0xac;
 const OP_PUSHBYTES_20: u8 = 0x14;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct OutPoint {
     pub hash: H256,
     pub index: u32,
 }
 
+impl OutPoint {
+    pub fn serialize(&self, stream: &mut Vec<u8>) {
+        let mut hash = self.hash.clone();
+        hash.reverse();
+        stream.extend_from_slice(&hash);
+        stream.write_u32::<LittleEndian>(self.index).unwrap();
+    }
+}
+
+// Reads the fields of serialized transactions and PSBTs in order.
+pub struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Reader(bytes)
+    }
+
+    // The bytes which are left to read.
+    pub fn remaining(&self) -> &'a [u8] {
+        self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn take(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        if self.0.len() < len {
+            return Err(Error::InvalidTransaction);
+        }
+
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, Error> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn read_u32(&mut self) -> Result<u32, Error> {
+        Ok(LittleEndian::read_u32(self.take(4)?))
+    }
+
+    pub fn read_u64(&mut self) -> Result<u64, Error> {
+        Ok(LittleEndian::read_u64(self.take(8)?))
+    }
+
+    pub fn read_var_int(&mut self) -> Result<u64, Error> {
+        match self.read_u8()? {
+            0xfd => Ok(u64::from(LittleEndian::read_u16(self.take(2)?))),
+            0xfe => Ok(u64::from(self.read_u32()?)),
+            0xff => self.read_u64(),
+            n => Ok(u64::from(n)),
+        }
+    }
+
+    // Bytes prefixed with their length.
+    pub fn read_bytes(&mut self) -> Result<&'a [u8], Error> {
+        let len = self.read_var_int()?;
+        if len > self.0.len() as u64 {
+            return Err(Error::InvalidTransaction);
+        }
+
+        self.take(len as usize)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Input {
     pub outpoint: OutPoint,
+    pub value: u64,
     pub script_sig: Script,
     pub sequence: u32,
     pub script_witness: Vec<Vec<u8>>,
@@ -82,6 +152,15 @@ pub struct Output {
     pub script_pubkey: Script,
 }
 
+impl Output {
+    pub fn serialize(&self, stream: &mut Vec<u8>) {
+        stream.write_u64::<LittleEndian>(self.value).unwrap();
+        let script_length = VarInt::from(self.script_pubkey.len());
+        script_length.serialize(stream);
+        stream.extend_from_slice(&self.script_pubkey);
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct UnsignedTransaction {
     pub version: i32,
@@ -91,7 +170,10 @@ pub struct UnsignedTransaction {
 }
 
 impl UnsignedTransaction {
-    pub fn new(inputs: Vec<(Transaction, u32)>, outputs: Vec<(String, u64)>) -> Self {
+    pub fn new(
+        inputs: Vec<(Transaction, u32)>,
+        outputs: Vec<(Script, u64)>,
+    ) -> Result<Self, Error> {
         let mut tx = UnsignedTransaction {
             version: 1,
             inputs: Vec::new(),
@@ -100,14 +182,19 @@ impl UnsignedTransaction {
         };
 
         for (utxo, index) in inputs {
-            let hex_script = utxo.vout[index as usize].script.hex.clone();
-            let previous_script_pubkey = Script::from_hex(hex_script);
+            let previous_output = utxo
+                .vout
+                .get(index as usize)
+                .cloned()
+                .ok_or(Error::InvalidTransaction)?;
+            let previous_script_pubkey = Script::from_hex(&previous_output.script.hex)?;
 
             let input = Input {
                 outpoint: OutPoint {
                     hash: utxo.txid,
                     index: index,
                 },
+                value: (previous_output.value * (100_000_000 as f64)).round() as u64,
                 script_sig: previous_script_pubkey,
                 sequence: 0xFFFFFFFF,
                 script_witness: Vec::new(),
@@ -116,29 +203,65 @@ impl UnsignedTransaction {
             tx.inputs.push(input);
         }
 
-        for (address, amount) in outputs {
+        for (script_pubkey, amount) in outputs {
             let output = Output {
                 value: amount,
-                script_pubkey: Script::p2pkh(address),
+                script_pubkey,
             };
             tx.outputs.push(output);
         }
 
-        tx
+        Ok(tx)
     }
 
-    pub fn sign(&mut self, skey: SecretKey, pkey: PublicKey) {
-        for (idx, _) in self.inputs.clone().iter().enumerate() {
-            let hash = self.signature_hash(idx);
-
-            let secp = Secp256k1::new();
-            let signature = secp.sign(&Message::from(hash.0), &skey);
-            self.inputs[idx].script_sig = Script::script_sig(signature, pkey);
+    // Signs every input for `pkey` through `sign`, which gets each digest to sign with its key.
+    pub fn sign<F, E>(&mut self, pkey: PublicKey, chain: &ChainParams, mut sign: F) -> Result<(), E>
+    where
+        F: FnMut(&H256) -> Result<RecoverableSignature, E>,
+    {
+        for idx in 0..self.inputs.len() {
+            self.sign_input(idx, pkey, chain, &mut sign)?;
         }
+
+        Ok(())
+    }
+
+    // Signs input `idx` alone, for transactions spending the outputs of several keys. Inputs can be
+    // signed in any order, as the digest of an input doesn't commit to the script_sig of others.
+    pub fn sign_input<F, E>(
+        &mut self,
+        idx: usize,
+        pkey: PublicKey,
+        chain: &ChainParams,
+        sign: F,
+    ) -> Result<(), E>
+    where
+        F: FnOnce(&H256) -> Result<RecoverableSignature, E>,
+    {
+        let secp = Secp256k1::new();
+        let sighash_type = chain.sighash_type();
+
+        let hash = match chain.fork_id {
+            Some(_) => self.forkid_signature_hash(idx, sighash_type),
+            None => self.signature_hash(idx),
+        };
+
+        let signature = sign(&hash)?.to_standard(&secp);
+        self.inputs[idx].script_sig = Script::script_sig(signature, pkey, sighash_type);
+
+        Ok(())
     }
 
-    pub fn signature_hash(&self, _: usize) -> H256 {
-        let tx = self.clone();
+    // Legacy digest of input `idx`, for which only that input keeps the script of the output it
+    // spends.
+    pub fn signature_hash(&self, idx: usize) -> H256 {
+        let mut tx = self.clone();
+
+        for (i, input) in tx.inputs.iter_mut().enumerate() {
+            if i != idx {
+                input.script_sig = Script(Vec::new());
+            }
+        }
 
         let mut serialized = Vec::new();
         tx.serialize(&mut serialized);
@@ -146,6 +269,55 @@ impl UnsignedTransaction {
         H256::from_data(&serialized)
     }
 
+    // BIP143 style digest used by chains with SIGHASH_FORKID, which commits to the spent amount.
+    pub fn forkid_signature_hash(&self, idx: usize, sighash_type: u32) -> H256 {
+        self.witness_signature_hash(idx, &self.inputs[idx].script_sig, sighash_type)
+    }
+
+    // BIP143 digest of input `idx`, with the script it's spent through as `script_code`.
+    pub fn witness_signature_hash(
+        &self,
+        idx: usize,
+        script_code: &[u8],
+        sighash_type: u32,
+    ) -> H256 {
+        let mut prevouts = Vec::new();
+        let mut sequences = Vec::new();
+
+        for input in self.inputs.iter() {
+            input.outpoint.serialize(&mut prevouts);
+            sequences.write_u32::<LittleEndian>(input.sequence).unwrap();
+        }
+
+        let mut outputs = Vec::new();
+
+        for output in self.outputs.iter() {
+            output.serialize(&mut outputs);
+        }
+
+        let input = &self.inputs[idx];
+
+        let mut serialized = Vec::new();
+        serialized
+            .write_u32::<LittleEndian>(self.version as u32)
+            .unwrap();
+        serialized.extend_from_slice(&H256::from_data(&prevouts));
+        serialized.extend_from_slice(&H256::from_data(&sequences));
+        input.outpoint.serialize(&mut serialized);
+        VarInt::from(script_code.len()).serialize(&mut serialized);
+        serialized.extend_from_slice(script_code);
+        serialized.write_u64::<LittleEndian>(input.value).unwrap();
+        serialized
+            .write_u32::<LittleEndian>(input.sequence)
+            .unwrap();
+        serialized.extend_from_slice(&H256::from_data(&outputs));
+        serialized
+            .write_u32::<LittleEndian>(self.lock_time)
+            .unwrap();
+        serialized.write_u32::<LittleEndian>(sighash_type).unwrap();
+        H256::from_data(&serialized)
+    }
+
     pub fn serialize(&self, stream: &mut Vec<u8>) {
         stream
             .write_u32::<LittleEndian>(self.version as u32)
@@ -153,12 +325,8 @@ impl UnsignedTransaction {
 
         VarInt::from(self.inputs.len()).serialize(stream);
 
-        for mut input in self.inputs.clone() {
-            input.outpoint.hash.reverse();
-            stream.extend_from_slice(&input.outpoint.hash);
-            stream
-                .write_u32::<LittleEndian>(input.outpoint.index)
-                .unwrap();
+        for input in self.inputs.clone() {
+            input.outpoint.serialize(stream);
 
             let script_length = VarInt::from(input.script_sig.len());
             script_length.serialize(stream);
@@ -170,18 +338,150 @@ impl UnsignedTransaction {
         VarInt::from(self.outputs.len()).serialize(stream);
 
         for output in self.outputs.clone() {
-            stream.write_u64::<LittleEndian>(output.value).unwrap();
-            let script_length = VarInt::from(output.script_pubkey.len());
-            script_length.serialize(stream);
-            stream.extend_from_slice(&output.script_pubkey);
+            output.serialize(stream);
         }
 
         stream.write_u32::<LittleEndian>(self.lock_time).unwrap();
     }
 
+    // Reads a raw transaction, e.g. one signed outside of Finch, with or without witnesses. The
+    // spent amounts aren't part of it and are left at zero.
+    pub fn deserialize(raw_transaction: &[u8]) -> Result<Self, Error> {
+        let mut reader = Reader::new(raw_transaction);
+        let transaction = UnsignedTransaction::read(&mut reader)?;
+
+        if !reader.is_empty() {
+            return Err(Error::InvalidTransaction);
+        }
+
+        Ok(transaction)
+    }
+
+    // Reads the transaction at the start of the reader, e.g. one of the transactions of a block.
+    pub fn read(reader: &mut Reader) -> Result<Self, Error> {
+        let version = reader.read_u32()? as i32;
+        let mut input_count = reader.read_var_int()?;
+
+        // BIP144 marker and flag.
+        let segwit = input_count == 0;
+        if segwit {
+            if reader.read_u8()? != 1 {
+                return Err(Error::InvalidTransaction);
+            }
+            input_count = reader.read_var_int()?;
+        }
+
+        let mut inputs = Vec::new();
+        for _ in 0..input_count {
+            let mut hash = H256::from_slice(reader.take(32)?);
+            hash.reverse();
+
+            inputs.push(Input {
+                outpoint: OutPoint {
+                    hash,
+                    index: reader.read_u32()?,
+                },
+                value: 0,
+                script_sig: Script(reader.read_bytes()?.to_vec()),
+                sequence: reader.read_u32()?,
+                script_witness: Vec::new(),
+            });
+        }
+
+        let mut outputs = Vec::new();
+        for _ in 0..reader.read_var_int()? {
+            outputs.push(Output {
+                value: reader.read_u64()?,
+                script_pubkey: Script(reader.read_bytes()?.to_vec()),
+            });
+        }
+
+        if segwit {
+            for input in inputs.iter_mut() {
+                for _ in 0..reader.read_var_int()? {
+                    input.script_witness.push(reader.read_bytes()?.to_vec());
+                }
+            }
+        }
+
+        let lock_time = reader.read_u32()?;
+
+        Ok(UnsignedTransaction {
+            version,
+            inputs,
+            outputs,
+            lock_time,
+        })
+    }
+
     pub fn into_raw_transaction(&self) -> Vec<u8> {
         let mut s = Vec::new();
         self.serialize(&mut s);
         s
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // BIP143 test vectors, spending a native P2WPKH output and one nested in P2SH.
+    const NATIVE_TRANSACTION: &str = "0100000002fff7f7881a8099afa6940d42d1e7f6362bec38171ea3ed\
+                                      f433541db4e4ad969f0000000000eeffffffef51e1b804cc89d182d2\
+                                      79655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ff\
+                                      ffffff02202cb206000000001976a9148280b37df378db99f66f85c9\
+                                      5a783a76ac7a6d5988ac9093510d000000001976a9143bde42dbee7e\
+                                      4dbe6a21b2d50ce2f0167faa815988ac11000000";
+
+    const NESTED_TRANSACTION: &str = "0100000001db6b1b20aa0fd7b23880be2ecbd4a98130974cf4748fb6\
+                                      6092ac4d3ceb1a54770100000000feffffff02b8b4eb0b0000000019\
+                                      76a914a457b684d7f0d539a46a45bbc043f35b59d0d96388ac0008af\
+                                      2f000000001976a914fd270b1ee6abcaea97fea7ad0402e8bd8ad6d7\
+                                      7c88ac92040000";
+
+    fn witness_signature_hash(
+        transaction: &str,
+        idx: usize,
+        value: u64,
+        script_code: &str,
+    ) -> Vec<u8> {
+        let raw_transaction = transaction.from_hex::<Vec<u8>>().unwrap();
+        let mut transaction = UnsignedTransaction::deserialize(&raw_transaction).unwrap();
+        transaction.inputs[idx].value = value;
+
+        let script_code = script_code.from_hex::<Vec<u8>>().unwrap();
+        transaction
+            .witness_signature_hash(idx, &script_code, 0x01)
+            .to_vec()
+    }
+
+    #[test]
+    fn native_p2wpkh_signature_hash() {
+        assert_eq!(
+            witness_signature_hash(
+                NATIVE_TRANSACTION,
+                1,
+                600_000_000,
+                "76a9141d0f172a0ecb48aee1be1f2687d2963ae33f71a188ac",
+            ),
+            "c37af31116d1b27caf68aae9e3ac82f1477929014d5b917657d0eb49478cb670"
+                .from_hex::<Vec<u8>>()
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn p2sh_p2wpkh_signature_hash() {
+        assert_eq!(
+            witness_signature_hash(
+                NESTED_TRANSACTION,
+                0,
+                1_000_000_000,
+                "76a91479091972186c449eb1ded22b78e40d009bdf008988ac",
+            ),
+            "64f3b0f4dd2bb3aa1ce8566d220cc74dda9df97d8490cc81d89d735c92e59fb6"
+                .from_hex::<Vec<u8>>()
+                .unwrap()
+        );
+    }
+}
@@ -0,0 +1,29 @@
+use types::{H256, U128};
+
+// Result of `scantxoutset start`.
+#[derive(Debug, Deserialize)]
+pub struct TxOutSetScan {
+    pub unspents: Vec<Unspent>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Unspent {
+    pub txid: H256,
+    pub vout: u32,
+    // Descriptor of the output's script, e.g. `addr(bc1q...)#checksum`.
+    pub desc: String,
+    pub amount: f64,
+    pub height: U128,
+}
+
+impl Unspent {
+    pub fn address(&self) -> Option<&str> {
+        let desc = self.desc.split('#').next().unwrap_or("");
+
+        if desc.starts_with("addr(") && desc.ends_with(')') {
+            Some(&desc[5..desc.len() - 1])
+        } else {
+            None
+        }
+    }
+}
@@ -16,6 +16,10 @@ pub enum Error {
     PayloadError(#[cause] PayloadError),
     #[fail(display = "{}", _0)]
     Secp256k1Error(#[cause] Secp256k1Error),
+    #[fail(display = "invalid transaction")]
+    InvalidTransaction,
+    #[fail(display = "invalid script")]
+    InvalidScript,
     #[fail(display = "{}", _0)]
     CustomError(String),
 }
@@ -5,9 +5,9 @@ use actix_web::{client, HttpMessage};
 use futures::future::{err, ok, Future};
 use serde_json::{self, Value};
 
-use core::ethereum::Block;
+use core::ethereum::{Block, Transaction};
 use errors::Error;
-use ethereum::SignedTransaction;
+use ethereum::{trace, FeeHistory, InternalTransfer, SignedTransaction, TransactionReceipt};
 use types::{H160, H256, U128, U256};
 
 pub type BlockchainApiClientAddr = Addr<BlockchainApiClient>;
@@ -27,12 +27,28 @@ impl BlockchainApiClient {
     }
 
     pub fn get_balance(&self, account: H160) -> Box<Future<Item = U256, Error = Error>> {
+        self.get_balance_in(account, String::from("pending"))
+    }
+
+    pub fn get_balance_at(
+        &self,
+        account: H160,
+        block_number: U128,
+    ) -> Box<Future<Item = U256, Error = Error>> {
+        self.get_balance_in(account, block_number.hex())
+    }
+
+    fn get_balance_in(
+        &self,
+        account: H160,
+        block: String,
+    ) -> Box<Future<Item = U256, Error = Error>> {
         let req = match client::ClientRequest::post(&self.url)
             .content_type("application/json")
             .json(json!({
                 "jsonrpc": "2.0",
                 "method": "eth_getBalance",
-                "params": (account.hex(), "pending"),
+                "params": (account.hex(), block),
                 "id": 1
             })) {
             Ok(req) => req,
@@ -207,6 +223,211 @@ impl BlockchainApiClient {
         }))
     }
 
+    pub fn get_transaction_by_hash(
+        &self,
+        hash: H256,
+    ) -> Box<Future<Item = Transaction, Error = Error>> {
+        let req = match client::ClientRequest::post(&self.url)
+            .timeout(Duration::from_secs(20))
+            .content_type("application/json")
+            .json(json!({
+                "jsonrpc": "2.0",
+                "method": "eth_getTransactionByHash",
+                "params": [hash.hex()],
+                "id": 1
+            })) {
+            Ok(req) => req,
+            Err(e) => return Box::new(err(Error::CustomError(format!("{}", e)))),
+        };
+
+        Box::new(req.send().from_err().and_then(move |resp| {
+            resp.body().from_err().and_then(move |body| {
+                let body: Value = match serde_json::from_slice(&body) {
+                    Ok(body) => body,
+                    Err(e) => return err(Error::from(e)),
+                };
+
+                if let Some(result) = body.get("result") {
+                    // Dropped from the pool since it was announced.
+                    if result.is_null() {
+                        return err(Error::EmptyResponseError);
+                    }
+
+                    match serde_json::from_str::<Transaction>(&format!("{}", result)) {
+                        Ok(transaction) => return ok(transaction),
+                        Err(e) => return err(Error::from(e)),
+                    }
+                };
+
+                err(Error::CustomError(format!(
+                    "{}",
+                    body.get("error")
+                        .unwrap()
+                        .get("message")
+                        .unwrap()
+                        .as_str()
+                        .unwrap()
+                )))
+            })
+        }))
+    }
+
+    pub fn get_transaction_receipt(
+        &self,
+        hash: H256,
+    ) -> Box<Future<Item = TransactionReceipt, Error = Error>> {
+        let req = match client::ClientRequest::post(&self.url)
+            .timeout(Duration::from_secs(20))
+            .content_type("application/json")
+            .json(json!({
+                "jsonrpc": "2.0",
+                "method": "eth_getTransactionReceipt",
+                "params": [hash.hex()],
+                "id": 1
+            })) {
+            Ok(req) => req,
+            Err(e) => return Box::new(err(Error::CustomError(format!("{}", e)))),
+        };
+
+        Box::new(req.send().from_err().and_then(move |resp| {
+            resp.body().from_err().and_then(move |body| {
+                let body: Value = match serde_json::from_slice(&body) {
+                    Ok(body) => body,
+                    Err(e) => return err(Error::from(e)),
+                };
+
+                if let Some(result) = body.get("result") {
+                    // Not mined yet.
+                    if result.is_null() {
+                        return err(Error::EmptyResponseError);
+                    }
+
+                    match serde_json::from_str::<TransactionReceipt>(&format!("{}", result)) {
+                        Ok(receipt) => return ok(receipt),
+                        Err(e) => return err(Error::from(e)),
+                    }
+                };
+
+                err(Error::CustomError(format!(
+                    "{}",
+                    body.get("error")
+                        .unwrap()
+                        .get("message")
+                        .unwrap()
+                        .as_str()
+                        .unwrap()
+                )))
+            })
+        }))
+    }
+
+    // Value transfers made by nested contract calls, from Parity style `trace_block`.
+    pub fn trace_block(
+        &self,
+        block_number: U128,
+    ) -> Box<Future<Item = Vec<InternalTransfer>, Error = Error>> {
+        let req = match client::ClientRequest::post(&self.url)
+            .timeout(Duration::from_secs(20))
+            .content_type("application/json")
+            .json(json!({
+                "jsonrpc": "2.0",
+                "method": "trace_block",
+                "params": vec![block_number.hex()],
+                "id": 1
+            })) {
+            Ok(req) => req,
+            Err(e) => return Box::new(err(Error::CustomError(format!("{}", e)))),
+        };
+
+        Box::new(req.send().from_err().and_then(move |resp| {
+            resp.body()
+                .limit(16777216)
+                .from_err()
+                .and_then(move |body| {
+                    let body: Value = match serde_json::from_slice(&body) {
+                        Ok(body) => body,
+                        Err(e) => return err(Error::from(e)),
+                    };
+
+                    if let Some(result) = body.get("result") {
+                        if result.is_null() {
+                            return err(Error::EmptyResponseError);
+                        }
+
+                        match trace::from_trace_block(result.clone()) {
+                            Ok(transfers) => return ok(transfers),
+                            Err(e) => return err(e),
+                        }
+                    };
+
+                    err(Error::CustomError(format!(
+                        "{}",
+                        body.get("error")
+                            .unwrap()
+                            .get("message")
+                            .unwrap()
+                            .as_str()
+                            .unwrap()
+                    )))
+                })
+        }))
+    }
+
+    // Value transfers made by nested contract calls, from Geth `debug_traceBlockByNumber` with
+    // the built-in call tracer. The block's transaction hashes are used to match results with
+    // transactions on nodes which don't report them.
+    pub fn debug_trace_block(
+        &self,
+        block_number: U128,
+        transaction_hashes: Vec<H256>,
+    ) -> Box<Future<Item = Vec<InternalTransfer>, Error = Error>> {
+        let req = match client::ClientRequest::post(&self.url)
+            .timeout(Duration::from_secs(20))
+            .content_type("application/json")
+            .json(json!({
+                "jsonrpc": "2.0",
+                "method": "debug_traceBlockByNumber",
+                "params": (block_number.hex(), json!({ "tracer": "callTracer" })),
+                "id": 1
+            })) {
+            Ok(req) => req,
+            Err(e) => return Box::new(err(Error::CustomError(format!("{}", e)))),
+        };
+
+        Box::new(req.send().from_err().and_then(move |resp| {
+            resp.body()
+                .limit(16777216)
+                .from_err()
+                .and_then(move |body| {
+                    let body: Value = match serde_json::from_slice(&body) {
+                        Ok(body) => body,
+                        Err(e) => return err(Error::from(e)),
+                    };
+
+                    if let Some(result) = body.get("result") {
+                        if result.is_null() {
+                            return err(Error::EmptyResponseError);
+                        }
+
+                        match trace::from_call_tracer(result.clone(), &transaction_hashes) {
+                            Ok(transfers) => return ok(transfers),
+                            Err(e) => return err(e),
+                        }
+                    };
+
+                    err(Error::CustomError(format!(
+                        "{}",
+                        body.get("error")
+                            .unwrap()
+                            .get("message")
+                            .unwrap()
+                            .as_str()
+                            .unwrap()
+                    )))
+                })
+        }))
+    }
+
     pub fn get_gas_price(&self) -> Box<Future<Item = U256, Error = Error>> {
         let req = match client::ClientRequest::post(&self.url)
             .content_type("application/json")
@@ -253,6 +474,111 @@ impl BlockchainApiClient {
         }))
     }
 
+    // Base fees of the last `block_count` blocks, and the given percentile of the priority fees
+    // paid in each of them.
+    pub fn get_fee_history(
+        &self,
+        block_count: u64,
+        reward_percentile: f64,
+    ) -> Box<Future<Item = FeeHistory, Error = Error>> {
+        let req = match client::ClientRequest::post(&self.url)
+            .content_type("application/json")
+            .json(json!({
+                "jsonrpc": "2.0",
+                "method": "eth_feeHistory",
+                "params": (format!("0x{:x}", block_count), "latest", vec![reward_percentile]),
+                "id": 1
+            })) {
+            Ok(req) => req,
+            Err(e) => return Box::new(err(Error::CustomError(format!("{}", e)))),
+        };
+
+        Box::new(req.send().from_err().and_then(move |resp| {
+            resp.body().from_err().and_then(move |body| {
+                let body: Value = match serde_json::from_slice(&body) {
+                    Ok(body) => body,
+                    Err(e) => return err(Error::from(e)),
+                };
+
+                if let Some(result) = body.get("result") {
+                    if result.is_null() {
+                        return err(Error::EmptyResponseError);
+                    }
+
+                    match serde_json::from_value::<FeeHistory>(result.clone()) {
+                        Ok(fee_history) => return ok(fee_history),
+                        Err(e) => return err(Error::from(e)),
+                    }
+                };
+
+                err(Error::CustomError(format!(
+                    "{}",
+                    body.get("error")
+                        .unwrap()
+                        .get("message")
+                        .unwrap()
+                        .as_str()
+                        .unwrap()
+                )))
+            })
+        }))
+    }
+
+    // Gas needed to send `value` from `from` to `to`, which is more than a plain transfer when `to`
+    // is a contract wallet.
+    pub fn estimate_gas(
+        &self,
+        from: H160,
+        to: H160,
+        value: U256,
+    ) -> Box<Future<Item = U256, Error = Error>> {
+        let req = match client::ClientRequest::post(&self.url)
+            .content_type("application/json")
+            .json(json!({
+                "jsonrpc": "2.0",
+                "method": "eth_estimateGas",
+                "params": [{
+                    "from": from.hex(),
+                    "to": to.hex(),
+                    "value": value.hex(),
+                }],
+                "id": 1
+            })) {
+            Ok(req) => req,
+            Err(e) => return Box::new(err(Error::CustomError(format!("{}", e)))),
+        };
+
+        Box::new(req.send().from_err().and_then(move |resp| {
+            resp.body().from_err().and_then(move |body| {
+                let body: Value = match serde_json::from_slice(&body) {
+                    Ok(body) => body,
+                    Err(e) => return err(Error::from(e)),
+                };
+
+                if let Some(result) = body.get("result") {
+                    if result.is_null() {
+                        return err(Error::EmptyResponseError);
+                    }
+
+                    match serde_json::from_str::<U256>(&format!("{}", result)) {
+                        Ok(gas) => return ok(gas),
+                        Err(e) => return err(Error::from(e)),
+                    }
+                };
+
+                err(Error::CustomError(format!(
+                    "{}",
+                    body.get("error")
+                        .unwrap()
+                        .get("message")
+                        .unwrap()
+                        .as_str()
+                        .unwrap()
+                )))
+            })
+        }))
+    }
+
     pub fn get_transaction_count(&self, account: H160) -> Box<Future<Item = U128, Error = Error>> {
         let req = match client::ClientRequest::post(&self.url)
             .content_type("application/json")
@@ -297,12 +623,8 @@ impl BlockchainApiClient {
         }))
     }
 
-    pub fn send_raw_transaction(
-        &self,
-        signed_transaction: SignedTransaction,
-    ) -> Box<Future<Item = H256, Error = Error>> {
-        let rlp = signed_transaction.rlp_encode();
-
+    // Broadcasts a hex encoded signed transaction.
+    pub fn send_raw_transaction(&self, rlp: String) -> Box<Future<Item = H256, Error = Error>> {
         let req = match client::ClientRequest::post(&self.url).json(json!({
             "jsonrpc": "2.0",
             "method": "eth_sendRawTransaction",
@@ -357,6 +679,29 @@ impl Handler<GetBalance> for BlockchainApiClient {
     }
 }
 
+// Balance of the account as of the given block.
+#[derive(Message)]
+#[rtype(result = "Result<U256, Error>")]
+pub struct GetBalanceAt {
+    pub account: H160,
+    pub block_number: U128,
+}
+
+impl Handler<GetBalanceAt> for BlockchainApiClient {
+    type Result = Box<Future<Item = U256, Error = Error>>;
+
+    fn handle(
+        &mut self,
+        GetBalanceAt {
+            account,
+            block_number,
+        }: GetBalanceAt,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        self.get_balance_at(account, block_number)
+    }
+}
+
 #[derive(Message)]
 #[rtype(result = "Result<U128, Error>")]
 pub struct GetBlockNumber;
@@ -397,6 +742,76 @@ impl Handler<GetBlockByNumber> for BlockchainApiClient {
     }
 }
 
+#[derive(Message)]
+#[rtype(result = "Result<Transaction, Error>")]
+pub struct GetTransactionByHash(pub H256);
+
+impl Handler<GetTransactionByHash> for BlockchainApiClient {
+    type Result = Box<Future<Item = Transaction, Error = Error>>;
+
+    fn handle(
+        &mut self,
+        GetTransactionByHash(hash): GetTransactionByHash,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        self.get_transaction_by_hash(hash)
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<TransactionReceipt, Error>")]
+pub struct GetTransactionReceipt(pub H256);
+
+impl Handler<GetTransactionReceipt> for BlockchainApiClient {
+    type Result = Box<Future<Item = TransactionReceipt, Error = Error>>;
+
+    fn handle(
+        &mut self,
+        GetTransactionReceipt(hash): GetTransactionReceipt,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        self.get_transaction_receipt(hash)
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<Vec<InternalTransfer>, Error>")]
+pub struct TraceBlock(pub U128);
+
+impl Handler<TraceBlock> for BlockchainApiClient {
+    type Result = Box<Future<Item = Vec<InternalTransfer>, Error = Error>>;
+
+    fn handle(
+        &mut self,
+        TraceBlock(block_number): TraceBlock,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        self.trace_block(block_number)
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<Vec<InternalTransfer>, Error>")]
+pub struct DebugTraceBlock {
+    pub block_number: U128,
+    pub transaction_hashes: Vec<H256>,
+}
+
+impl Handler<DebugTraceBlock> for BlockchainApiClient {
+    type Result = Box<Future<Item = Vec<InternalTransfer>, Error = Error>>;
+
+    fn handle(
+        &mut self,
+        DebugTraceBlock {
+            block_number,
+            transaction_hashes,
+        }: DebugTraceBlock,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        self.debug_trace_block(block_number, transaction_hashes)
+    }
+}
+
 #[derive(Message)]
 #[rtype(result = "Result<U256, Error>")]
 pub struct GetGasPrice;
@@ -409,6 +824,48 @@ impl Handler<GetGasPrice> for BlockchainApiClient {
     }
 }
 
+#[derive(Message)]
+#[rtype(result = "Result<FeeHistory, Error>")]
+pub struct GetFeeHistory {
+    pub block_count: u64,
+    pub reward_percentile: f64,
+}
+
+impl Handler<GetFeeHistory> for BlockchainApiClient {
+    type Result = Box<Future<Item = FeeHistory, Error = Error>>;
+
+    fn handle(
+        &mut self,
+        GetFeeHistory {
+            block_count,
+            reward_percentile,
+        }: GetFeeHistory,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        self.get_fee_history(block_count, reward_percentile)
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<U256, Error>")]
+pub struct EstimateGas {
+    pub from: H160,
+    pub to: H160,
+    pub value: U256,
+}
+
+impl Handler<EstimateGas> for BlockchainApiClient {
+    type Result = Box<Future<Item = U256, Error = Error>>;
+
+    fn handle(
+        &mut self,
+        EstimateGas { from, to, value }: EstimateGas,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        self.estimate_gas(from, to, value)
+    }
+}
+
 #[derive(Message)]
 #[rtype(result = "Result<U128, Error>")]
 pub struct GetTransactionCount(pub H160);
@@ -437,6 +894,23 @@ impl Handler<SendRawTransaction> for BlockchainApiClient {
         SendRawTransaction(signed_transaction): SendRawTransaction,
         _: &mut Self::Context,
     ) -> Self::Result {
-        self.send_raw_transaction(signed_transaction)
+        self.send_raw_transaction(signed_transaction.rlp_encode())
+    }
+}
+
+// A transaction signed outside of Finch, hex encoded.
+#[derive(Message)]
+#[rtype(result = "Result<H256, Error>")]
+pub struct SendRawTransactionHex(pub String);
+
+impl Handler<SendRawTransactionHex> for BlockchainApiClient {
+    type Result = Box<Future<Item = H256, Error = Error>>;
+
+    fn handle(
+        &mut self,
+        SendRawTransactionHex(rlp): SendRawTransactionHex,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        self.send_raw_transaction(rlp)
     }
 }
@@ -0,0 +1,98 @@
+use errors::Error;
+use ethereum::Fee;
+use types::U256;
+
+// Result of `eth_feeHistory`, requested with a single reward percentile.
+#[derive(Debug, Deserialize)]
+pub struct FeeHistory {
+    // Contains one more entry than the number of blocks: the base fee of the next block.
+    #[serde(rename = "baseFeePerGas")]
+    pub base_fee_per_gas: Vec<U256>,
+    #[serde(default)]
+    pub reward: Vec<Vec<U256>>,
+}
+
+impl FeeHistory {
+    // The tip is the average reward paid in recent blocks, and the fee cap leaves room for the
+    // base fee to double before the transaction stops being includable.
+    pub fn suggest_fee(&self) -> Result<Fee, Error> {
+        let base_fee = match self.base_fee_per_gas.last() {
+            Some(base_fee) => *base_fee,
+            None => return Err(Error::EmptyResponseError),
+        };
+
+        let rewards: Vec<U256> = self
+            .reward
+            .iter()
+            .filter_map(|rewards| rewards.first().cloned())
+            .collect();
+
+        let max_priority_fee_per_gas = if rewards.is_empty() {
+            U256::from(0)
+        } else {
+            rewards
+                .iter()
+                .fold(U256::from(0), |sum, reward| sum + *reward)
+                / U256::from(rewards.len() as u64)
+        };
+
+        Ok(Fee::DynamicFee {
+            max_fee_per_gas: base_fee * U256::from(2) + max_priority_fee_per_gas,
+            max_priority_fee_per_gas,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    fn fee_history(json: &str) -> FeeHistory {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn suggest_fee_from_the_next_base_fee_and_average_reward() {
+        // As returned by `eth_feeHistory` for 2 blocks and the 50th reward percentile.
+        let history = fee_history(
+            r#"{
+                "oldestBlock": "0x1295a04",
+                "baseFeePerGas": ["0x3da8e7618", "0x3e1ba3b1b", "0x3dfd72b90"],
+                "gasUsedRatio": [0.5227, 0.4837],
+                "reward": [["0x59682f00"], ["0x3b9aca00"]]
+            }"#,
+        );
+
+        match history.suggest_fee().unwrap() {
+            Fee::DynamicFee {
+                max_fee_per_gas,
+                max_priority_fee_per_gas,
+            } => {
+                assert_eq!(max_priority_fee_per_gas, U256::from(1_250_000_000u64));
+                assert_eq!(max_fee_per_gas, U256::from(34_530_644_896u64));
+            }
+            Fee::Legacy { .. } => panic!("the fee is a dynamic one"),
+        }
+    }
+
+    #[test]
+    fn suggest_fee_without_rewards() {
+        let history = fee_history(r#"{"baseFeePerGas": ["0x7", "0x8"]}"#);
+
+        match history.suggest_fee().unwrap() {
+            Fee::DynamicFee {
+                max_fee_per_gas,
+                max_priority_fee_per_gas,
+            } => {
+                assert_eq!(max_priority_fee_per_gas, U256::from(0u64));
+                assert_eq!(max_fee_per_gas, U256::from(16u64));
+            }
+            Fee::Legacy { .. } => panic!("the fee is a dynamic one"),
+        }
+
+        assert!(fee_history(r#"{"baseFeePerGas": []}"#)
+            .suggest_fee()
+            .is_err());
+    }
+}
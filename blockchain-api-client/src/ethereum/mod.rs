@@ -1,10 +1,18 @@
 mod api_client;
+mod fee_history;
+mod receipt;
 mod signature;
+mod trace;
 mod transaction;
 
 pub use self::api_client::{
-    GetBalance, GetBlockByNumber, GetBlockNumber, GetGasPrice, GetPendingBlock,
-    GetTransactionCount, BlockchainApiClient, BlockchainApiClientAddr, SendRawTransaction,
+    BlockchainApiClient, BlockchainApiClientAddr, DebugTraceBlock, EstimateGas, GetBalance,
+    GetBalanceAt, GetBlockByNumber, GetBlockNumber, GetFeeHistory, GetGasPrice, GetPendingBlock,
+    GetTransactionByHash, GetTransactionCount, GetTransactionReceipt, SendRawTransaction,
+    SendRawTransactionHex, TraceBlock,
 };
+pub use self::fee_history::FeeHistory;
+pub use self::receipt::TransactionReceipt;
 pub use self::signature::Signature;
-pub use self::transaction::{SignedTransaction, UnsignedTransaction};
+pub use self::trace::InternalTransfer;
+pub use self::transaction::{Fee, SignedTransaction, UnsignedTransaction};
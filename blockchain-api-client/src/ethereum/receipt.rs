@@ -0,0 +1,20 @@
+use types::{U128, U256};
+
+// Result of `eth_getTransactionReceipt` for a mined transaction.
+#[derive(Debug, Deserialize)]
+pub struct TransactionReceipt {
+    #[serde(rename = "blockNumber")]
+    pub block_number: U128,
+    #[serde(rename = "gasUsed")]
+    pub gas_used: U256,
+    // The base fee of the block plus the tip paid, at most the fee cap of the transaction.
+    #[serde(rename = "effectiveGasPrice")]
+    pub effective_gas_price: U256,
+}
+
+impl TransactionReceipt {
+    // What the transaction spent on fees, in wei.
+    pub fn fee(&self) -> U256 {
+        self.gas_used * self.effective_gas_price
+    }
+}
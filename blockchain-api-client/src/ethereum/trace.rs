@@ -0,0 +1,227 @@
+use serde_json::{self, Value};
+
+use errors::Error;
+use types::{H160, H256, U256};
+
+// Ether moved by a contract call nested inside a transaction, e.g. a smart-contract wallet
+// forwarding funds to an invoice address.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InternalTransfer {
+    pub transaction_hash: H256,
+    pub from: H160,
+    pub to: H160,
+    pub value: U256,
+}
+
+#[derive(Debug, Deserialize)]
+struct Trace {
+    #[serde(rename = "type")]
+    typ: String,
+    action: TraceAction,
+    #[serde(rename = "transactionHash")]
+    transaction_hash: Option<H256>,
+    #[serde(rename = "traceAddress")]
+    trace_address: Vec<u64>,
+    error: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TraceAction {
+    #[serde(rename = "callType")]
+    call_type: Option<String>,
+    from: Option<H160>,
+    to: Option<H160>,
+    value: Option<U256>,
+}
+
+// Parses the result of Parity / OpenEthereum / Erigon `trace_block`.
+pub fn from_trace_block(result: Value) -> Result<Vec<InternalTransfer>, Error> {
+    let traces = serde_json::from_value::<Vec<Trace>>(result)?;
+
+    // A failed call reverts the calls nested in it, but parity only reports the error on the
+    // failed call itself.
+    let reverted: Vec<(Option<H256>, Vec<u64>)> = traces
+        .iter()
+        .filter(|trace| trace.error.is_some())
+        .map(|trace| (trace.transaction_hash, trace.trace_address.clone()))
+        .collect();
+
+    let transfers = traces
+        .into_iter()
+        // Top level calls are the block's transactions themselves.
+        .filter(|trace| trace.typ == "call" && !trace.trace_address.is_empty())
+        .filter(|trace| {
+            !reverted.iter().any(|(transaction_hash, trace_address)| {
+                *transaction_hash == trace.transaction_hash
+                    && trace.trace_address.starts_with(trace_address)
+            })
+        })
+        .filter(|trace| trace.action.call_type.as_ref().map(|t| t.as_str()) == Some("call"))
+        .filter_map(|trace| {
+            match (
+                trace.transaction_hash,
+                trace.action.from,
+                trace.action.to,
+                trace.action.value,
+            ) {
+                (Some(transaction_hash), Some(from), Some(to), Some(value))
+                    if value > U256::from(0) =>
+                {
+                    Some(InternalTransfer {
+                        transaction_hash,
+                        from,
+                        to,
+                        value,
+                    })
+                }
+                _ => None,
+            }
+        })
+        .collect();
+
+    Ok(transfers)
+}
+
+#[derive(Debug, Deserialize)]
+struct TransactionTrace {
+    #[serde(rename = "txHash")]
+    transaction_hash: Option<H256>,
+    result: CallFrame,
+}
+
+#[derive(Debug, Deserialize)]
+struct CallFrame {
+    #[serde(rename = "type")]
+    typ: String,
+    from: H160,
+    to: Option<H160>,
+    value: Option<U256>,
+    error: Option<String>,
+    #[serde(default)]
+    calls: Vec<CallFrame>,
+}
+
+// Parses the result of Geth `debug_traceBlockByNumber` run with the `callTracer`.
+pub fn from_call_tracer(
+    result: Value,
+    transaction_hashes: &[H256],
+) -> Result<Vec<InternalTransfer>, Error> {
+    let traces = serde_json::from_value::<Vec<TransactionTrace>>(result)?;
+    let mut transfers = Vec::new();
+
+    // Older Geth versions don't include `txHash`, in which case results are in block order.
+    for (index, trace) in traces.into_iter().enumerate() {
+        let transaction_hash = match trace
+            .transaction_hash
+            .or_else(|| transaction_hashes.get(index).cloned())
+        {
+            Some(transaction_hash) => transaction_hash,
+            None => continue,
+        };
+
+        if trace.result.error.is_some() {
+            continue;
+        }
+
+        for call in trace.result.calls.iter() {
+            collect_calls(call, transaction_hash, &mut transfers);
+        }
+    }
+
+    Ok(transfers)
+}
+
+fn collect_calls(frame: &CallFrame, transaction_hash: H256, transfers: &mut Vec<InternalTransfer>) {
+    // Nothing under a reverted frame took effect.
+    if frame.error.is_some() {
+        return;
+    }
+
+    if frame.typ == "CALL" {
+        if let (Some(to), Some(value)) = (frame.to, frame.value) {
+            if value > U256::from(0) {
+                transfers.push(InternalTransfer {
+                    transaction_hash,
+                    from: frame.from,
+                    to,
+                    value,
+                });
+            }
+        }
+    }
+
+    for call in frame.calls.iter() {
+        collect_calls(call, transaction_hash, transfers);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hash(hex: &str) -> H256 {
+        H256::from_hex(hex).unwrap()
+    }
+
+    fn address(hex: &str) -> H160 {
+        H160::from_hex(hex).unwrap()
+    }
+
+    // Both fixtures trace the same block: a wallet contract forwarding two payments to an invoice
+    // address, a transfer nested in a reverted call, and one nested in a reverted transaction.
+    fn expected_transfers() -> Vec<InternalTransfer> {
+        let transaction_hash =
+            hash("0x8f1a6c42d4e41fd4c8a0e4d1ab7f3c58b5e6a1f7e09f3b2c6a74e9d0c3b2a1f0");
+        let from = address("0x34cfac646f301356faa8b21e94227e3583fe3f5f");
+        let to = address("0x3d9819210a31b4961b30ef54be2aed79b9c9cd3b");
+
+        vec![
+            InternalTransfer {
+                transaction_hash,
+                from,
+                to,
+                value: U256::from(1_000_000_000_000_000_000u64),
+            },
+            InternalTransfer {
+                transaction_hash,
+                from,
+                to,
+                value: U256::from(500_000_000_000_000_000u64),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_from_trace_block() {
+        let result = serde_json::from_str(include_str!("fixtures/trace_block.json")).unwrap();
+
+        assert_eq!(from_trace_block(result).unwrap(), expected_transfers());
+    }
+
+    #[test]
+    fn test_from_call_tracer() {
+        let result = serde_json::from_str(include_str!("fixtures/call_tracer.json")).unwrap();
+
+        assert_eq!(from_call_tracer(result, &[]).unwrap(), expected_transfers());
+    }
+
+    #[test]
+    fn test_from_call_tracer_without_transaction_hashes() {
+        let mut result: Value =
+            serde_json::from_str(include_str!("fixtures/call_tracer.json")).unwrap();
+        for trace in result.as_array_mut().unwrap() {
+            trace.as_object_mut().unwrap().remove("txHash");
+        }
+
+        let transaction_hashes = [
+            hash("0x8f1a6c42d4e41fd4c8a0e4d1ab7f3c58b5e6a1f7e09f3b2c6a74e9d0c3b2a1f0"),
+            hash("0x2b7e0f1c9a3d4e5f60718293a4b5c6d7e8f90a1b2c3d4e5f60718293a4b5c6d7"),
+            hash("0xc4d5e6f708192a3b4c5d6e7f8091a2b3c4d5e6f708192a3b4c5d6e7f8091a2b3"),
+        ];
+
+        assert_eq!(
+            from_call_tracer(result, &transaction_hashes).unwrap(),
+            expected_transfers()
+        );
+    }
+}
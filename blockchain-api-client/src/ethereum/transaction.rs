@@ -1,6 +1,6 @@
-use rlp::RlpStream;
+use rlp::{RlpStream, UntrustedRlp};
 use rustc_hex::ToHex;
-use secp256k1::{Message, Secp256k1, key::SecretKey};
+use secp256k1::{Message, RecoverableSignature, RecoveryId, Secp256k1};
 use tiny_keccak::keccak256;
 
 use errors::Error;
@@ -8,10 +8,38 @@ use ethereum::Signature;
 
 use types::{H160, H256, U128, U256};
 
+// EIP-2718 type of EIP-1559 transactions.
+const DYNAMIC_FEE_TRANSACTION_TYPE: u8 = 0x02;
+
+#[derive(Debug, Clone, Copy)]
+pub enum Fee {
+    // EIP-155 transaction priced with `eth_gasPrice`.
+    Legacy {
+        gas_price: U256,
+    },
+    // EIP-1559 type-2 transaction.
+    DynamicFee {
+        max_fee_per_gas: U256,
+        max_priority_fee_per_gas: U256,
+    },
+}
+
+impl Fee {
+    // The most the sender can be charged per unit of gas.
+    pub fn max_gas_price(&self) -> U256 {
+        match *self {
+            Fee::Legacy { gas_price } => gas_price,
+            Fee::DynamicFee {
+                max_fee_per_gas, ..
+            } => max_fee_per_gas,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct UnsignedTransaction {
     pub nonce: U128,
-    pub gas_price: U256,
+    pub fee: Fee,
     pub gas: U256,
     pub to: H160,
     pub value: U256,
@@ -19,62 +47,423 @@ pub struct UnsignedTransaction {
 }
 
 impl UnsignedTransaction {
-    pub fn sign(self, secret_key: SecretKey, chain_id: u64) -> Result<SignedTransaction, Error> {
-        let mut stream = RlpStream::new();
-
-        stream.begin_list(9);
-        stream.append(&self.nonce);
-        stream.append(&self.gas_price);
-        stream.append(&self.gas);
-        stream.append(&self.to);
-        stream.append(&self.value);
-        stream.append(&self.data);
-        stream.append(&chain_id);
-        stream.append(&0u8);
-        stream.append(&0u8);
-
-        let hash = H256::from_hash(keccak256(stream.as_raw()));
+    // Signs the transaction through `sign`, which gets the digest to sign with the sender's key.
+    pub fn sign<F, E>(self, chain_id: u64, sign: F) -> Result<SignedTransaction, E>
+    where
+        F: FnOnce(&H256) -> Result<RecoverableSignature, E>,
+    {
+        let hash = H256::from_hash(keccak256(&self.signing_payload(chain_id)));
 
         let secp = Secp256k1::new();
-        let s = secp.sign_recoverable(&Message::from_slice(&hash[..])?, &secret_key);
-        let (rec_id, data) = s.serialize_compact(&secp);
+        let (rec_id, data) = sign(&hash)?.serialize_compact(&secp);
         let mut data_arr = [0; 65];
 
         data_arr[0..64].copy_from_slice(&data[0..64]);
         data_arr[64] = rec_id.to_i32() as u8;
         let signature = Signature::new(data_arr);
 
+        // Typed transactions carry the bare y parity instead of an EIP-155 v.
+        let v = match self.fee {
+            Fee::Legacy { .. } => signature.v() as u64 + (35 + chain_id * 2),
+            Fee::DynamicFee { .. } => signature.v() as u64,
+        };
+
         Ok(SignedTransaction {
             transaction: self,
-            v: signature.v() as u64 + (35 + chain_id * 2),
+            chain_id,
+            v,
             r: signature.r().into(),
             s: signature.s().into(),
         })
     }
+
+    // Hex encoded payload signed for the transaction, which is how wallets and hardware signers
+    // take unsigned transactions.
+    pub fn unsigned_rlp_encode(&self, chain_id: u64) -> String {
+        self.signing_payload(chain_id).to_hex()
+    }
+
+    // Reads a payload made by `unsigned_rlp_encode`, with the chain id it's for.
+    pub fn unsigned_rlp_decode(payload: &[u8]) -> Result<(Self, u64), Error> {
+        match payload.first() {
+            Some(&DYNAMIC_FEE_TRANSACTION_TYPE) => {
+                let rlp = UntrustedRlp::new(&payload[1..]);
+                if !rlp.is_list() || rlp.at(9).is_ok() {
+                    return Err(Error::InvalidTransaction);
+                }
+
+                UnsignedTransaction::decode_dynamic_fee_fields(&rlp)
+            }
+            _ => {
+                let rlp = UntrustedRlp::new(payload);
+                if !rlp.is_list() || rlp.at(9).is_ok() || !rlp_bytes(&rlp, 7)?.is_empty() {
+                    return Err(Error::InvalidTransaction);
+                }
+
+                Ok((
+                    UnsignedTransaction::decode_legacy_fields(&rlp)?,
+                    rlp_u64(&rlp, 6)?,
+                ))
+            }
+        }
+    }
+
+    // The six fields of legacy transactions, from nonce to data.
+    fn decode_legacy_fields(rlp: &UntrustedRlp) -> Result<Self, Error> {
+        Ok(UnsignedTransaction {
+            nonce: U128::from(rlp_u64(rlp, 0)?),
+            fee: Fee::Legacy {
+                gas_price: rlp_u256(rlp, 1)?,
+            },
+            gas: rlp_u256(rlp, 2)?,
+            to: rlp_h160(rlp, 3)?,
+            value: rlp_u256(rlp, 4)?,
+            data: rlp_bytes(rlp, 5)?,
+        })
+    }
+
+    // The fields written by `append_dynamic_fee_fields`. Access lists aren't supported.
+    fn decode_dynamic_fee_fields(rlp: &UntrustedRlp) -> Result<(Self, u64), Error> {
+        let access_list = rlp.at(8).map_err(|_| Error::InvalidTransaction)?;
+        if !access_list.is_list() || access_list.at(0).is_ok() {
+            return Err(Error::InvalidTransaction);
+        }
+
+        let transaction = UnsignedTransaction {
+            nonce: U128::from(rlp_u64(rlp, 1)?),
+            fee: Fee::DynamicFee {
+                max_fee_per_gas: rlp_u256(rlp, 3)?,
+                max_priority_fee_per_gas: rlp_u256(rlp, 2)?,
+            },
+            gas: rlp_u256(rlp, 4)?,
+            to: rlp_h160(rlp, 5)?,
+            value: rlp_u256(rlp, 6)?,
+            data: rlp_bytes(rlp, 7)?,
+        };
+
+        Ok((transaction, rlp_u64(rlp, 0)?))
+    }
+
+    fn signing_payload(&self, chain_id: u64) -> Vec<u8> {
+        let mut stream = RlpStream::new();
+
+        match self.fee {
+            Fee::Legacy { gas_price } => {
+                stream.begin_list(9);
+                stream.append(&self.nonce);
+                stream.append(&gas_price);
+                stream.append(&self.gas);
+                stream.append(&self.to);
+                stream.append(&self.value);
+                stream.append(&self.data);
+                stream.append(&chain_id);
+                stream.append(&0u8);
+                stream.append(&0u8);
+
+                stream.drain().into_vec()
+            }
+            Fee::DynamicFee {
+                max_fee_per_gas,
+                max_priority_fee_per_gas,
+            } => {
+                stream.begin_list(9);
+                self.append_dynamic_fee_fields(
+                    &mut stream,
+                    chain_id,
+                    max_fee_per_gas,
+                    max_priority_fee_per_gas,
+                );
+
+                let mut payload = vec![DYNAMIC_FEE_TRANSACTION_TYPE];
+                payload.extend_from_slice(&stream.drain());
+                payload
+            }
+        }
+    }
+
+    // chain_id, nonce, max_priority_fee_per_gas, max_fee_per_gas, gas, to, value, data, access_list
+    fn append_dynamic_fee_fields(
+        &self,
+        stream: &mut RlpStream,
+        chain_id: u64,
+        max_fee_per_gas: U256,
+        max_priority_fee_per_gas: U256,
+    ) {
+        stream.append(&chain_id);
+        stream.append(&self.nonce);
+        stream.append(&max_priority_fee_per_gas);
+        stream.append(&max_fee_per_gas);
+        stream.append(&self.gas);
+        stream.append(&self.to);
+        stream.append(&self.value);
+        stream.append(&self.data);
+        stream.begin_list(0);
+    }
 }
 
 #[derive(Debug)]
 pub struct SignedTransaction {
     transaction: UnsignedTransaction,
+    chain_id: u64,
     v: u64,
     r: U256,
     s: U256,
 }
 
 impl SignedTransaction {
+    // Reads a raw EIP-155 or EIP-1559 transaction, e.g. one signed outside of Finch.
+    pub fn rlp_decode(raw_transaction: &[u8]) -> Result<Self, Error> {
+        match raw_transaction.first() {
+            Some(&DYNAMIC_FEE_TRANSACTION_TYPE) => {
+                let rlp = UntrustedRlp::new(&raw_transaction[1..]);
+                if !rlp.is_list() || rlp.at(12).is_ok() {
+                    return Err(Error::InvalidTransaction);
+                }
+
+                let (transaction, chain_id) = UnsignedTransaction::decode_dynamic_fee_fields(&rlp)?;
+
+                Ok(SignedTransaction {
+                    transaction,
+                    chain_id,
+                    v: rlp_u64(&rlp, 9)?,
+                    r: rlp_u256(&rlp, 10)?,
+                    s: rlp_u256(&rlp, 11)?,
+                })
+            }
+            _ => {
+                let rlp = UntrustedRlp::new(raw_transaction);
+                if !rlp.is_list() || rlp.at(9).is_ok() {
+                    return Err(Error::InvalidTransaction);
+                }
+
+                let v = rlp_u64(&rlp, 6)?;
+                // Transactions without replay protection could be broadcast on any chain.
+                if v < 35 {
+                    return Err(Error::InvalidTransaction);
+                }
+
+                Ok(SignedTransaction {
+                    transaction: UnsignedTransaction::decode_legacy_fields(&rlp)?,
+                    chain_id: (v - 35) / 2,
+                    v,
+                    r: rlp_u256(&rlp, 7)?,
+                    s: rlp_u256(&rlp, 8)?,
+                })
+            }
+        }
+    }
+
+    pub fn transaction(&self) -> &UnsignedTransaction {
+        &self.transaction
+    }
+
+    pub fn chain_id(&self) -> u64 {
+        self.chain_id
+    }
+
+    // The address which signed the transaction.
+    pub fn sender(&self) -> Result<H160, Error> {
+        let hash = keccak256(&self.transaction.signing_payload(self.chain_id));
+
+        let recovery_id = match self.transaction.fee {
+            Fee::Legacy { .. } => self.v - (35 + self.chain_id * 2),
+            Fee::DynamicFee { .. } => self.v,
+        };
+
+        let mut signature = [0; 64];
+        self.r.to_big_endian(&mut signature[0..32]);
+        self.s.to_big_endian(&mut signature[32..64]);
+
+        let secp = Secp256k1::new();
+        let signature = RecoverableSignature::from_compact(
+            &secp,
+            &signature,
+            RecoveryId::from_i32(recovery_id as i32)?,
+        )?;
+        let public_key = secp.recover(&Message::from(hash), &signature)?;
+
+        Ok(H160::from_slice(
+            &keccak256(&public_key.serialize_uncompressed()[1..])[12..],
+        ))
+    }
+
     pub fn rlp_encode(&self) -> String {
         let mut s = RlpStream::new();
-        s.begin_list(9);
-        s.append(&self.transaction.nonce);
-        s.append(&self.transaction.gas_price);
-        s.append(&self.transaction.gas);
-        s.append(&self.transaction.to);
-        s.append(&self.transaction.value);
-        s.append(&self.transaction.data);
-        s.append(&self.v);
-        s.append(&self.r);
-        s.append(&self.s);
-
-        s.drain().into_vec().to_hex()
+
+        match self.transaction.fee {
+            Fee::Legacy { gas_price } => {
+                s.begin_list(9);
+                s.append(&self.transaction.nonce);
+                s.append(&gas_price);
+                s.append(&self.transaction.gas);
+                s.append(&self.transaction.to);
+                s.append(&self.transaction.value);
+                s.append(&self.transaction.data);
+                s.append(&self.v);
+                s.append(&self.r);
+                s.append(&self.s);
+
+                s.drain().into_vec().to_hex()
+            }
+            Fee::DynamicFee {
+                max_fee_per_gas,
+                max_priority_fee_per_gas,
+            } => {
+                s.begin_list(12);
+                self.transaction.append_dynamic_fee_fields(
+                    &mut s,
+                    self.chain_id,
+                    max_fee_per_gas,
+                    max_priority_fee_per_gas,
+                );
+                s.append(&self.v);
+                s.append(&self.r);
+                s.append(&self.s);
+
+                let mut envelope = vec![DYNAMIC_FEE_TRANSACTION_TYPE];
+                envelope.extend_from_slice(&s.drain());
+
+                envelope.to_hex()
+            }
+        }
+    }
+}
+
+fn rlp_bytes(rlp: &UntrustedRlp, index: usize) -> Result<Vec<u8>, Error> {
+    rlp.at(index)
+        .and_then(|item| item.data().map(|data| data.to_vec()))
+        .map_err(|_| Error::InvalidTransaction)
+}
+
+fn rlp_u64(rlp: &UntrustedRlp, index: usize) -> Result<u64, Error> {
+    let bytes = rlp_bytes(rlp, index)?;
+    if bytes.len() > 8 {
+        return Err(Error::InvalidTransaction);
+    }
+
+    Ok(bytes
+        .iter()
+        .fold(0, |value, byte| (value << 8) | u64::from(*byte)))
+}
+
+fn rlp_u256(rlp: &UntrustedRlp, index: usize) -> Result<U256, Error> {
+    let bytes = rlp_bytes(rlp, index)?;
+    if bytes.len() > 32 {
+        return Err(Error::InvalidTransaction);
+    }
+
+    Ok(U256::from(&bytes[..]))
+}
+
+fn rlp_h160(rlp: &UntrustedRlp, index: usize) -> Result<H160, Error> {
+    let bytes = rlp_bytes(rlp, index)?;
+    if bytes.len() != 20 {
+        return Err(Error::InvalidTransaction);
+    }
+
+    Ok(H160::from_slice(&bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustc_hex::FromHex;
+
+    // The EIP-1559 transaction 0x0ec0b6a2df4d87424e5f6ad2a654e27aaeb7dac20ae9e8385cc09087ad532ee0
+    // of mainnet.
+    const UNSIGNED_TRANSACTION: &str = "02f86d0142843b9aca008504a817c80082ad62946069a6c32cf691f598\
+                                        2febae4faf8a6f3ab2f0f680b844a22cb4650000000000000000000000\
+                                        005eee75727d804a2b13038928d36f8b188945a57a0000000000000000\
+                                        000000000000000000000000000000000000000000000000c0";
+    const RAW_TRANSACTION: &str = "02f8b00142843b9aca008504a817c80082ad62946069a6c32cf691f5982feb\
+                                   ae4faf8a6f3ab2f0f680b844a22cb4650000000000000000000000005eee75\
+                                   727d804a2b13038928d36f8b188945a57a0000000000000000000000000000\
+                                   000000000000000000000000000000000000c080a0840cfc572845f5786e70\
+                                   2984c2a582528cad4b49b2a10b9db1be7fca90058565a025e7109ceb98168d\
+                                   95b09b18bbf6b685130e0562f233877d492b94eee0c5b6d1";
+
+    fn dynamic_fee_transaction() -> UnsignedTransaction {
+        UnsignedTransaction {
+            nonce: U128::from(0x42u64),
+            fee: Fee::DynamicFee {
+                max_fee_per_gas: U256::from(0x4_a817_c800u64),
+                max_priority_fee_per_gas: U256::from(0x3b9a_ca00u64),
+            },
+            gas: U256::from(44_386u64),
+            to: H160::from_hex("6069a6c32cf691f5982febae4faf8a6f3ab2f0f6").unwrap(),
+            value: U256::from(0u64),
+            data: "a22cb4650000000000000000000000005eee75727d804a2b13038928d36f8b188945a57a\
+                   0000000000000000000000000000000000000000000000000000000000000000"
+                .from_hex()
+                .unwrap(),
+        }
+    }
+
+    #[test]
+    fn dynamic_fee_signing_hash() {
+        let transaction = dynamic_fee_transaction();
+
+        assert_eq!(transaction.unsigned_rlp_encode(1), UNSIGNED_TRANSACTION);
+        assert_eq!(
+            H256::from_hash(keccak256(&transaction.signing_payload(1))),
+            H256::from_hex("0d5688ac3897124635b6cf1bc0e29d6dfebceebdc10a54d74f2ef8b56535b682")
+                .unwrap()
+        );
+
+        let payload = UNSIGNED_TRANSACTION.from_hex::<Vec<u8>>().unwrap();
+        let (decoded, chain_id) = UnsignedTransaction::unsigned_rlp_decode(&payload).unwrap();
+        assert_eq!(chain_id, 1);
+        assert_eq!(decoded.unsigned_rlp_encode(chain_id), UNSIGNED_TRANSACTION);
+    }
+
+    #[test]
+    fn dynamic_fee_envelope() {
+        let signed_transaction = SignedTransaction {
+            transaction: dynamic_fee_transaction(),
+            chain_id: 1,
+            v: 0,
+            r: U256::from(
+                &"840cfc572845f5786e702984c2a582528cad4b49b2a10b9db1be7fca90058565"
+                    .from_hex::<Vec<u8>>()
+                    .unwrap()[..],
+            ),
+            s: U256::from(
+                &"25e7109ceb98168d95b09b18bbf6b685130e0562f233877d492b94eee0c5b6d1"
+                    .from_hex::<Vec<u8>>()
+                    .unwrap()[..],
+            ),
+        };
+
+        assert_eq!(signed_transaction.rlp_encode(), RAW_TRANSACTION);
+
+        // The hash of a typed transaction is the one of its envelope, type included.
+        let raw_transaction = RAW_TRANSACTION.from_hex::<Vec<u8>>().unwrap();
+        assert_eq!(
+            H256::from_hash(keccak256(&raw_transaction)),
+            H256::from_hex("0ec0b6a2df4d87424e5f6ad2a654e27aaeb7dac20ae9e8385cc09087ad532ee0")
+                .unwrap()
+        );
+
+        let decoded = SignedTransaction::rlp_decode(&raw_transaction).unwrap();
+        assert_eq!(decoded.chain_id(), 1);
+        assert_eq!(decoded.rlp_encode(), RAW_TRANSACTION);
+        assert_eq!(
+            decoded.sender().unwrap(),
+            H160::from_hex("dd6b8b3dc6b7ad97db52f08a275ff4483e024cea").unwrap()
+        );
+    }
+
+    #[test]
+    fn dynamic_fees_reserve_the_fee_cap_and_keep_their_tip() {
+        let fee = dynamic_fee_transaction().fee;
+
+        assert_eq!(fee.max_gas_price(), U256::from(0x4_a817_c800u64));
+        match fee {
+            Fee::DynamicFee {
+                max_priority_fee_per_gas,
+                ..
+            } => assert_eq!(max_priority_fee_per_gas, U256::from(0x3b9a_ca00u64)),
+            Fee::Legacy { .. } => panic!("the fee is a dynamic one"),
+        }
     }
 }
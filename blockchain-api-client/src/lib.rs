@@ -13,6 +13,8 @@ extern crate rustc_hex;
 extern crate secp256k1;
 extern crate serde;
 #[macro_use]
+extern crate serde_derive;
+#[macro_use]
 extern crate serde_json;
 extern crate tiny_keccak;
 
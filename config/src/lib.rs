@@ -5,16 +5,59 @@ extern crate bigdecimal;
 extern crate currency_api_client;
 extern crate types;
 
+use std::net::IpAddr;
+
 use currency_api_client::Api as CurrencyApi;
-use types::{bitcoin::Network as BtcNetwork, ethereum::Network as EthNetwork};
+use types::{
+    bitcoin::{ChainParams, Network as BtcNetwork},
+    currency::Crypto,
+    ethereum::Network as EthNetwork,
+    H256,
+};
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct Config {
     pub postgres: String,
     pub server: ServerConfig,
-    pub smtp: SmtpConfig,
+    #[serde(default)]
+    pub mail: MailConfig,
+    pub smtp: Option<SmtpConfig>,
     pub bitcoin: Option<BtcConfig>,
+    pub litecoin: Option<BtcConfig>,
+    // Cashaddr outputs of Bitcoin Cash nodes are mapped to the legacy addresses of payments.
+    pub bitcoin_cash: Option<BtcConfig>,
+    pub dogecoin: Option<BtcConfig>,
     pub ethereum: Option<EthConfig>,
+    // Further EVM networks (Polygon, BSC, Arbitrum...), each running its own processor and payouter.
+    #[serde(default)]
+    pub evm: Vec<EthConfig>,
+    pub lightning: Option<LightningConfig>,
+    // Periodically checks invoice addresses on chain for payments the processors missed.
+    pub reconciliation: Option<ReconciliationConfig>,
+    // Where payouts get signed. Unless set, the seeds of new stores are kept in the database and
+    // keys are derived in the payouters, which is only meant for development.
+    pub signer: Option<SignerConfig>,
+}
+
+impl Config {
+    pub fn evm_networks(&self) -> Vec<EthConfig> {
+        self.ethereum
+            .iter()
+            .chain(self.evm.iter())
+            .cloned()
+            .collect()
+    }
+
+    // Configuration of a chain served by the bitcoind JSON-RPC stack.
+    pub fn bitcoin_like(&self, crypto: Crypto) -> Option<BtcConfig> {
+        match crypto {
+            Crypto::Btc => self.bitcoin.clone(),
+            Crypto::Ltc => self.litecoin.clone(),
+            Crypto::Bch => self.bitcoin_cash.clone(),
+            Crypto::Doge => self.dogecoin.clone(),
+            Crypto::Eth | Crypto::BtcLightning => None,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -27,6 +70,189 @@ pub struct ServerConfig {
     pub web_client_url: String,
     pub currency_api: CurrencyApi,
     pub currency_api_key: String,
+    pub payout_address_time_lock_hours: Option<i64>,
+    #[serde(default)]
+    pub rate_limit: RateLimitConfig,
+    // Bearer token for operator endpoints such as block rescans, which are disabled without it.
+    pub operator_token: Option<String>,
+    // Where emailed exports are generated, and served from until their link expires.
+    #[serde(default = "default_export_dir")]
+    pub export_dir: String,
+    // Reverse proxies whose `X-Forwarded-For` header tells the address of clients, e.g.
+    // `["127.0.0.1"]`. The header is ignored from anyone else.
+    #[serde(default)]
+    pub trusted_proxies: Vec<IpAddr>,
+}
+
+impl ServerConfig {
+    // Settings which depend on each other, checked at startup rather than when first used.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.rate_limit.backend != RateLimitBackend::Redis {
+            return Ok(());
+        }
+
+        let redis_url = self
+            .rate_limit
+            .redis_url
+            .as_ref()
+            .ok_or_else(|| String::from("no rate_limit.redis_url for the redis backend"))?;
+
+        if !["redis://", "rediss://", "unix://"]
+            .iter()
+            .any(|scheme| redis_url.starts_with(scheme))
+        {
+            return Err(format!("invalid rate_limit.redis_url {}", redis_url));
+        }
+
+        Ok(())
+    }
+}
+
+fn default_export_dir() -> String {
+    String::from("exports")
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitBackend {
+    Memory,
+    Redis,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct RateLimitRule {
+    pub path: String,
+    pub per_ip: Option<u64>,
+    pub per_token: Option<u64>,
+    pub window_secs: u64,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct RateLimitConfig {
+    pub backend: RateLimitBackend,
+    pub redis_url: Option<String>,
+    pub rules: Vec<RateLimitRule>,
+    pub max_failed_logins: u64,
+    pub lockout_secs: u64,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        RateLimitConfig {
+            backend: RateLimitBackend::Memory,
+            redis_url: None,
+            rules: vec![
+                RateLimitRule {
+                    path: String::from("/login"),
+                    per_ip: Some(10),
+                    per_token: None,
+                    window_secs: 60,
+                },
+                RateLimitRule {
+                    path: String::from("/registration"),
+                    per_ip: Some(10),
+                    per_token: None,
+                    window_secs: 3600,
+                },
+                RateLimitRule {
+                    path: String::from("/reset_password"),
+                    per_ip: Some(5),
+                    per_token: None,
+                    window_secs: 3600,
+                },
+                RateLimitRule {
+                    path: String::from("/payments"),
+                    per_ip: Some(30),
+                    per_token: Some(120),
+                    window_secs: 60,
+                },
+            ],
+            max_failed_logins: 5,
+            lockout_secs: 900,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ReconciliationConfig {
+    #[serde(default = "default_reconciliation_interval_secs")]
+    pub interval_secs: u64,
+    // How far back unpaid and expired payments are checked.
+    #[serde(default = "default_reconciliation_lookback_hours")]
+    pub lookback_hours: i64,
+    // Credit missed payments instead of only flagging them in the store's audit log. Only
+    // Bitcoin-like chains can be credited, as the paying transaction is unknown on Ethereum.
+    #[serde(default)]
+    pub auto_credit: bool,
+}
+
+fn default_reconciliation_interval_secs() -> u64 {
+    600
+}
+
+fn default_reconciliation_lookback_hours() -> i64 {
+    72
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct SignerConfig {
+    // `unix:/path/to/socket` or loopback `host:port` of a process speaking the hd-keyring signer
+    // protocol.
+    pub address: String,
+    // Shared with the signer process, which refuses requests not authenticated with it.
+    pub secret: String,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum MailTransport {
+    Smtp,
+    File,
+    Stdout,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct MailConfig {
+    pub transport: MailTransport,
+    // Directory the file transport writes emails to.
+    pub dir: Option<String>,
+}
+
+impl Default for MailConfig {
+    fn default() -> Self {
+        MailConfig {
+            transport: MailTransport::Smtp,
+            dir: None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SmtpSecurity {
+    // Upgrades a plain connection with STARTTLS, usually on port 587.
+    Starttls,
+    // Implicit TLS from the start of the connection, usually on port 465.
+    Tls,
+}
+
+impl Default for SmtpSecurity {
+    fn default() -> Self {
+        SmtpSecurity::Starttls
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SmtpAuthMechanism {
+    Plain,
+    Login,
+}
+
+impl Default for SmtpAuthMechanism {
+    fn default() -> Self {
+        SmtpAuthMechanism::Plain
+    }
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -35,6 +261,10 @@ pub struct SmtpConfig {
     pub port: u16,
     pub user: String,
     pub pass: String,
+    #[serde(default)]
+    pub security: SmtpSecurity,
+    #[serde(default)]
+    pub auth_mechanism: SmtpAuthMechanism,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -44,11 +274,109 @@ pub struct BtcConfig {
     pub rpc_user: String,
     pub rpc_pass: String,
     pub min_charge: Option<bigdecimal::BigDecimal>,
+    // Number of missed blocks fetched at once when catching up after downtime.
+    #[serde(default = "default_catch_up_concurrency")]
+    pub catch_up_concurrency: usize,
+    // ZMQ endpoint the node publishes `rawblock` and `rawtx` to, e.g. "tcp://127.0.0.1:28332".
+    // Blocks and transactions are polled for while it's unreachable.
+    pub zmq_url: Option<String>,
+}
+
+fn default_catch_up_concurrency() -> usize {
+    4
+}
+
+impl BtcConfig {
+    pub fn chain(&self, crypto: Crypto) -> ChainParams {
+        ChainParams::new(crypto, self.network).expect("not a bitcoin-like chain")
+    }
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct EthConfig {
+    // Chain id, or "mainnet" / "ropsten".
     pub network: EthNetwork,
     pub rpc_url: String,
+    // Symbol of the native currency, used for exchange rates.
+    #[serde(default = "default_eth_symbol")]
+    pub symbol: String,
+    // Payments on this network wait for at least this many confirmations.
+    #[serde(default)]
+    pub confirmations_required: i32,
+    pub explorer_url: Option<String>,
+    pub min_charge: Option<bigdecimal::BigDecimal>,
+    #[serde(default)]
+    pub transaction_type: EthTransactionType,
+    // Also credit payments sent by contract calls, e.g. from smart-contract wallets. Needs a
+    // node with the matching tracing API enabled.
+    pub internal_transactions: Option<EthTraceMethod>,
+    // Number of missed blocks fetched at once when catching up after downtime.
+    #[serde(default = "default_catch_up_concurrency")]
+    pub catch_up_concurrency: usize,
+    // Websocket endpoint for `eth_subscribe`. Blocks and transactions are polled for while it's
+    // unreachable.
+    pub ws_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum EthTransactionType {
+    // EIP-155 transactions priced from `eth_gasPrice`.
+    Legacy,
+    // Type-2 transactions priced from `eth_feeHistory`. Needs a network with the London fork.
+    Eip1559,
+}
+
+impl Default for EthTransactionType {
+    fn default() -> Self {
+        EthTransactionType::Legacy
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum EthTraceMethod {
+    // `trace_block`, served by Erigon, Nethermind and OpenEthereum.
+    TraceBlock,
+    // `debug_traceBlockByNumber` with the call tracer, served by Geth.
+    DebugTraceBlock,
+}
+
+fn default_eth_symbol() -> String {
+    String::from("ETH")
+}
+
+impl EthConfig {
+    pub fn explorer_transaction_url(&self, hash: &H256) -> String {
+        let explorer_url = match self.explorer_url {
+            Some(ref explorer_url) => explorer_url.as_str(),
+            None => self.network.default_explorer_url().unwrap_or(""),
+        };
+
+        self.network.explorer_transaction_url(explorer_url, hash)
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum LightningBackend {
+    Lnd,
+    CoreLightning,
+    // Keeps invoices in memory, for local development.
+    Stub,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct LightningConfig {
+    pub backend: LightningBackend,
+    pub network: BtcNetwork,
+    // REST endpoint of LND, or of the Core Lightning `clnrest` plugin.
+    pub url: Option<String>,
+    // Hex encoded LND invoice macaroon.
+    pub macaroon: Option<String>,
+    // Core Lightning rune allowed to call `invoice` and `listinvoices`.
+    pub rune: Option<String>,
+    #[serde(default)]
+    pub stub_auto_settle: bool,
     pub min_charge: Option<bigdecimal::BigDecimal>,
 }
@@ -0,0 +1,116 @@
+use actix::prelude::*;
+use diesel::prelude::*;
+use uuid::Uuid;
+
+use db::{
+    postgres::{PgExecutor, PooledConnection},
+    Error,
+};
+use models::audit_event::{AuditEvent, AuditEventPayload};
+
+pub fn insert(payload: AuditEventPayload, conn: &PooledConnection) -> Result<AuditEvent, Error> {
+    use diesel::insert_into;
+    use schema::audit_events::dsl;
+
+    insert_into(dsl::audit_events)
+        .values(&payload)
+        .get_result(conn)
+        .map_err(|e| Error::from(e))
+}
+
+pub fn find_by_store(
+    store_id: Uuid,
+    limit: i64,
+    offset: i64,
+    conn: &PooledConnection,
+) -> Result<Vec<AuditEvent>, Error> {
+    use schema::audit_events::dsl;
+
+    dsl::audit_events
+        .filter(dsl::store_id.eq(store_id))
+        .order(dsl::created_at.desc())
+        .limit(limit)
+        .offset(offset)
+        .load::<AuditEvent>(conn)
+        .map_err(|e| Error::from(e))
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<AuditEvent, Error>")]
+pub struct Insert(pub AuditEventPayload);
+
+impl Handler<Insert> for PgExecutor {
+    type Result = Result<AuditEvent, Error>;
+
+    fn handle(&mut self, Insert(payload): Insert, _: &mut Self::Context) -> Self::Result {
+        let conn = &self.get()?;
+
+        insert(payload, &conn)
+    }
+}
+
+// Runs `F`, a mutation, and records the audit event `E` makes of its result in the same
+// transaction, so that neither is kept without the other.
+pub struct Audited<F, E>(pub F, pub E);
+
+impl<F, E, T> Message for Audited<F, E>
+where
+    F: FnOnce(&PooledConnection) -> Result<T, Error> + Send + 'static,
+    E: FnOnce(&T) -> AuditEventPayload + Send + 'static,
+    T: Send + 'static,
+{
+    type Result = Result<T, Error>;
+}
+
+impl<F, E, T> Handler<Audited<F, E>> for PgExecutor
+where
+    F: FnOnce(&PooledConnection) -> Result<T, Error> + Send + 'static,
+    E: FnOnce(&T) -> AuditEventPayload + Send + 'static,
+    T: Send + 'static,
+{
+    type Result = Result<T, Error>;
+
+    fn handle(
+        &mut self,
+        Audited(mutate, event): Audited<F, E>,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        let conn = &self.get()?;
+
+        conn.transaction::<_, Error, _>(|| {
+            let result = mutate(conn)?;
+
+            let mut payload = event(&result);
+            payload.set_created_at();
+            insert(payload, conn)?;
+
+            Ok(result)
+        })
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<Vec<AuditEvent>, Error>")]
+pub struct FindByStore {
+    pub store_id: Uuid,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+impl Handler<FindByStore> for PgExecutor {
+    type Result = Result<Vec<AuditEvent>, Error>;
+
+    fn handle(
+        &mut self,
+        FindByStore {
+            store_id,
+            limit,
+            offset,
+        }: FindByStore,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        let conn = &self.get()?;
+
+        find_by_store(store_id, limit, offset, &conn)
+    }
+}
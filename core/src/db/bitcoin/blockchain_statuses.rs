@@ -6,7 +6,7 @@ use db::{
     Error,
 };
 use models::bitcoin::{BlockchainStatus, BlockchainStatusPayload};
-use types::bitcoin::Network;
+use types::{bitcoin::Network, currency::Crypto};
 
 pub fn insert(
     payload: BlockchainStatusPayload,
@@ -22,6 +22,7 @@ pub fn insert(
 }
 
 pub fn update(
+    crypto: Crypto,
     network: Network,
     payload: BlockchainStatusPayload,
     conn: &PooledConnection,
@@ -29,20 +30,23 @@ pub fn update(
     use diesel::update;
     use schema::btc_blockchain_statuses::dsl;
 
-    update(dsl::btc_blockchain_statuses.filter(dsl::network.eq(network)))
-        .set(&payload)
-        .get_result(conn)
-        .map_err(|e| Error::from(e))
+    update(
+        dsl::btc_blockchain_statuses.filter(dsl::crypto.eq(crypto).and(dsl::network.eq(network))),
+    )
+    .set(&payload)
+    .get_result(conn)
+    .map_err(|e| Error::from(e))
 }
 
-pub fn find_by_network(
+pub fn find_by_chain(
+    crypto: Crypto,
     network: Network,
     conn: &PooledConnection,
 ) -> Result<BlockchainStatus, Error> {
     use schema::btc_blockchain_statuses::dsl;
 
     dsl::btc_blockchain_statuses
-        .filter(dsl::network.eq(network))
+        .filter(dsl::crypto.eq(crypto).and(dsl::network.eq(network)))
         .first::<BlockchainStatus>(conn)
         .map_err(|e| Error::from(e))
 }
@@ -64,6 +68,7 @@ impl Handler<Insert> for PgExecutor {
 #[derive(Message)]
 #[rtype(result = "Result<BlockchainStatus, Error>")]
 pub struct Update {
+    pub crypto: Crypto,
     pub network: Network,
     pub payload: BlockchainStatusPayload,
 }
@@ -72,29 +77,36 @@ impl Handler<Update> for PgExecutor {
 
     fn handle(
         &mut self,
-        Update { network, payload }: Update,
+        Update {
+            crypto,
+            network,
+            payload,
+        }: Update,
         _: &mut Self::Context,
     ) -> Self::Result {
         let conn = &self.get()?;
 
-        update(network, payload, &conn)
+        update(crypto, network, payload, &conn)
     }
 }
 
 #[derive(Message)]
 #[rtype(result = "Result<BlockchainStatus, Error>")]
-pub struct FindByNetwork(pub Network);
+pub struct FindByChain {
+    pub crypto: Crypto,
+    pub network: Network,
+}
 
-impl Handler<FindByNetwork> for PgExecutor {
+impl Handler<FindByChain> for PgExecutor {
     type Result = Result<BlockchainStatus, Error>;
 
     fn handle(
         &mut self,
-        FindByNetwork(network): FindByNetwork,
+        FindByChain { crypto, network }: FindByChain,
         _: &mut Self::Context,
     ) -> Self::Result {
         let conn = &self.get()?;
 
-        find_by_network(network, &conn)
+        find_by_chain(crypto, network, &conn)
     }
 }
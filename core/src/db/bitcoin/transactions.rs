@@ -34,6 +34,25 @@ pub fn insert(payload: Transaction, conn: &PooledConnection) -> Result<Transacti
         .map_err(|e| Error::from(e))
 }
 
+// Stores the transaction unless it already is, e.g. when it pays several payments.
+pub fn upsert(payload: Transaction, conn: &PooledConnection) -> Result<(), Error> {
+    use diesel::insert_into;
+    use schema::btc_transactions::dsl;
+
+    let tx = BtcTransaction {
+        hash: payload.hash,
+        data: json!(payload),
+    };
+
+    insert_into(dsl::btc_transactions)
+        .values(&tx)
+        .on_conflict(dsl::hash)
+        .do_nothing()
+        .execute(conn)
+        .map(|_| ())
+        .map_err(|e| Error::from(e))
+}
+
 pub fn find_by_hash(hash: H256, conn: &PooledConnection) -> Result<Transaction, Error> {
     use schema::btc_transactions::dsl;
 
@@ -0,0 +1,136 @@
+use actix::prelude::*;
+use diesel::prelude::*;
+use uuid::Uuid;
+
+use db::{
+    postgres::{PgExecutor, PooledConnection},
+    Error,
+};
+use models::block_rescan::{BlockRescan, BlockRescanPayload};
+use types::{currency::Crypto, ethereum::Network as EthNetwork, RescanStatus};
+
+pub fn insert(payload: BlockRescanPayload, conn: &PooledConnection) -> Result<BlockRescan, Error> {
+    use diesel::insert_into;
+    use schema::block_rescans::dsl;
+
+    insert_into(dsl::block_rescans)
+        .values(&payload)
+        .get_result(conn)
+        .map_err(|e| Error::from(e))
+}
+
+pub fn update(
+    id: Uuid,
+    payload: BlockRescanPayload,
+    conn: &PooledConnection,
+) -> Result<BlockRescan, Error> {
+    use diesel::update;
+    use schema::block_rescans::dsl;
+
+    update(dsl::block_rescans.filter(dsl::id.eq(id)))
+        .set(&payload)
+        .get_result(conn)
+        .map_err(|e| Error::from(e))
+}
+
+pub fn find_by_id(id: Uuid, conn: &PooledConnection) -> Result<BlockRescan, Error> {
+    use schema::block_rescans::dsl;
+
+    dsl::block_rescans
+        .filter(dsl::id.eq(id))
+        .first::<BlockRescan>(conn)
+        .map_err(|e| Error::from(e))
+}
+
+pub fn find_next(
+    crypto: Crypto,
+    eth_network: Option<EthNetwork>,
+    conn: &PooledConnection,
+) -> Result<Option<BlockRescan>, Error> {
+    use schema::block_rescans::dsl;
+
+    let query = dsl::block_rescans
+        .filter(
+            dsl::crypto
+                .eq(crypto)
+                .and(dsl::status.eq_any(vec![RescanStatus::Pending, RescanStatus::Running])),
+        )
+        .order(dsl::created_at.asc())
+        .into_boxed();
+
+    let query = match eth_network {
+        Some(eth_network) => query.filter(dsl::eth_network.eq(eth_network)),
+        None => query.filter(dsl::eth_network.is_null()),
+    };
+
+    query
+        .first::<BlockRescan>(conn)
+        .optional()
+        .map_err(|e| Error::from(e))
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<BlockRescan, Error>")]
+pub struct Insert(pub BlockRescanPayload);
+
+impl Handler<Insert> for PgExecutor {
+    type Result = Result<BlockRescan, Error>;
+
+    fn handle(&mut self, Insert(payload): Insert, _: &mut Self::Context) -> Self::Result {
+        let conn = &self.get()?;
+
+        insert(payload, &conn)
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<BlockRescan, Error>")]
+pub struct Update(pub Uuid, pub BlockRescanPayload);
+
+impl Handler<Update> for PgExecutor {
+    type Result = Result<BlockRescan, Error>;
+
+    fn handle(&mut self, Update(id, payload): Update, _: &mut Self::Context) -> Self::Result {
+        let conn = &self.get()?;
+
+        update(id, payload, &conn)
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<BlockRescan, Error>")]
+pub struct FindById(pub Uuid);
+
+impl Handler<FindById> for PgExecutor {
+    type Result = Result<BlockRescan, Error>;
+
+    fn handle(&mut self, FindById(id): FindById, _: &mut Self::Context) -> Self::Result {
+        let conn = &self.get()?;
+
+        find_by_id(id, &conn)
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<Option<BlockRescan>, Error>")]
+pub struct FindNext {
+    pub crypto: Crypto,
+    pub eth_network: Option<EthNetwork>,
+}
+
+impl Handler<FindNext> for PgExecutor {
+    type Result = Result<Option<BlockRescan>, Error>;
+
+    fn handle(
+        &mut self,
+        FindNext {
+            crypto,
+            eth_network,
+        }: FindNext,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        let conn = &self.get()?;
+
+        find_next(crypto, eth_network, &conn)
+    }
+}
@@ -1,3 +1,4 @@
+use _redis::RedisError;
 use diesel::result::Error as DieselError;
 use r2d2::Error as PoolError;
 use serde_json::Error as SerdeJsonError;
@@ -10,6 +11,8 @@ pub enum Error {
     PoolError(#[cause] PoolError),
     #[fail(display = "{}", _0)]
     SerdeJsonError(#[cause] SerdeJsonError),
+    #[fail(display = "{}", _0)]
+    RedisError(#[cause] RedisError),
 }
 
 impl From<DieselError> for Error {
@@ -29,3 +32,9 @@ impl From<SerdeJsonError> for Error {
         Error::SerdeJsonError(e)
     }
 }
+
+impl From<RedisError> for Error {
+    fn from(e: RedisError) -> Error {
+        Error::RedisError(e)
+    }
+}
@@ -1,2 +1,3 @@
 pub mod transactions;
+pub mod transfers;
 pub mod blockchain_statuses;
\ No newline at end of file
@@ -18,6 +18,20 @@ pub fn insert(payload: Transaction, conn: &PooledConnection) -> Result<Transacti
         .map_err(|e| Error::from(e))
 }
 
+// Stores the transaction unless it already is, e.g. when it pays several payments.
+pub fn upsert(payload: Transaction, conn: &PooledConnection) -> Result<(), Error> {
+    use diesel::insert_into;
+    use schema::eth_transactions::dsl::*;
+
+    insert_into(eth_transactions)
+        .values(&payload)
+        .on_conflict(hash)
+        .do_nothing()
+        .execute(conn)
+        .map(|_| ())
+        .map_err(|e| Error::from(e))
+}
+
 pub fn find_by_hash(transaction_hash: H256, conn: &PooledConnection) -> Result<Transaction, Error> {
     use schema::eth_transactions::dsl::*;
 
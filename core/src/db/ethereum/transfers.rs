@@ -0,0 +1,51 @@
+use actix::prelude::*;
+use diesel::prelude::*;
+
+use db::{
+    postgres::{PgExecutor, PooledConnection},
+    Error,
+};
+use models::ethereum::Transfer;
+use types::H256;
+
+// Stores the transfer unless it already is, e.g. when a block is processed again.
+pub fn upsert(payload: Transfer, conn: &PooledConnection) -> Result<(), Error> {
+    use diesel::insert_into;
+    use schema::eth_transfers::dsl::*;
+
+    insert_into(eth_transfers)
+        .values(&payload)
+        .on_conflict((transaction_hash, output_index))
+        .do_nothing()
+        .execute(conn)
+        .map(|_| ())
+        .map_err(|e| Error::from(e))
+}
+
+pub fn find_by_output(hash: H256, index: i32, conn: &PooledConnection) -> Result<Transfer, Error> {
+    use schema::eth_transfers::dsl::*;
+
+    eth_transfers
+        .filter(transaction_hash.eq(hash))
+        .filter(output_index.eq(index))
+        .first::<Transfer>(conn)
+        .map_err(|e| Error::from(e))
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<Transfer, Error>")]
+pub struct FindByOutput(pub H256, pub i32);
+
+impl Handler<FindByOutput> for PgExecutor {
+    type Result = Result<Transfer, Error>;
+
+    fn handle(
+        &mut self,
+        FindByOutput(transaction_hash, output_index): FindByOutput,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        let conn = &self.get()?;
+
+        find_by_output(transaction_hash, output_index, &conn)
+    }
+}
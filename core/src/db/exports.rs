@@ -0,0 +1,142 @@
+use actix::prelude::*;
+use chrono::prelude::*;
+use diesel::prelude::*;
+use uuid::Uuid;
+
+use db::{
+    postgres::{PgExecutor, PooledConnection},
+    Error,
+};
+use models::export::{Export, ExportPayload};
+
+pub fn insert(payload: ExportPayload, conn: &PooledConnection) -> Result<Export, Error> {
+    use diesel::insert_into;
+    use schema::exports::dsl;
+
+    insert_into(dsl::exports)
+        .values(&payload)
+        .get_result(conn)
+        .map_err(|e| Error::from(e))
+}
+
+pub fn update(id: Uuid, payload: ExportPayload, conn: &PooledConnection) -> Result<Export, Error> {
+    use diesel::update;
+    use schema::exports::dsl;
+
+    update(dsl::exports.filter(dsl::id.eq(id)))
+        .set(&payload)
+        .get_result(conn)
+        .map_err(|e| Error::from(e))
+}
+
+pub fn find_by_id(id: Uuid, conn: &PooledConnection) -> Result<Export, Error> {
+    use schema::exports::dsl;
+
+    dsl::exports
+        .filter(dsl::id.eq(id))
+        .first::<Export>(conn)
+        .map_err(|e| Error::from(e))
+}
+
+pub fn fail_stale(
+    started_before: DateTime<Utc>,
+    conn: &PooledConnection,
+) -> Result<Vec<Export>, Error> {
+    use diesel::update;
+    use schema::exports::dsl;
+
+    update(
+        dsl::exports.filter(
+            dsl::created_at
+                .lt(started_before)
+                .and(dsl::completed_at.is_null())
+                .and(dsl::failed_at.is_null()),
+        ),
+    )
+    .set(dsl::failed_at.eq(Utc::now()))
+    .get_results(conn)
+    .map_err(|e| Error::from(e))
+}
+
+pub fn delete_expired(conn: &PooledConnection) -> Result<Vec<Export>, Error> {
+    use diesel::delete;
+    use schema::exports::dsl;
+
+    delete(dsl::exports.filter(dsl::expires_at.lt(Utc::now())))
+        .get_results(conn)
+        .map_err(|e| Error::from(e))
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<Export, Error>")]
+pub struct Insert(pub ExportPayload);
+
+impl Handler<Insert> for PgExecutor {
+    type Result = Result<Export, Error>;
+
+    fn handle(&mut self, Insert(payload): Insert, _: &mut Self::Context) -> Self::Result {
+        let conn = &self.get()?;
+
+        insert(payload, &conn)
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<Export, Error>")]
+pub struct Update(pub Uuid, pub ExportPayload);
+
+impl Handler<Update> for PgExecutor {
+    type Result = Result<Export, Error>;
+
+    fn handle(&mut self, Update(id, payload): Update, _: &mut Self::Context) -> Self::Result {
+        let conn = &self.get()?;
+
+        update(id, payload, &conn)
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<Export, Error>")]
+pub struct FindById(pub Uuid);
+
+impl Handler<FindById> for PgExecutor {
+    type Result = Result<Export, Error>;
+
+    fn handle(&mut self, FindById(id): FindById, _: &mut Self::Context) -> Self::Result {
+        let conn = &self.get()?;
+
+        find_by_id(id, &conn)
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<Vec<Export>, Error>")]
+pub struct FailStale(pub DateTime<Utc>);
+
+impl Handler<FailStale> for PgExecutor {
+    type Result = Result<Vec<Export>, Error>;
+
+    fn handle(
+        &mut self,
+        FailStale(started_before): FailStale,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        let conn = &self.get()?;
+
+        fail_stale(started_before, &conn)
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<Vec<Export>, Error>")]
+pub struct DeleteExpired;
+
+impl Handler<DeleteExpired> for PgExecutor {
+    type Result = Result<Vec<Export>, Error>;
+
+    fn handle(&mut self, _: DeleteExpired, _: &mut Self::Context) -> Self::Result {
+        let conn = &self.get()?;
+
+        delete_expired(&conn)
+    }
+}
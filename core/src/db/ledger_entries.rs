@@ -0,0 +1,440 @@
+use std::collections::{HashMap, HashSet};
+
+use actix::prelude::*;
+use bigdecimal::{BigDecimal, Zero};
+use chrono::prelude::*;
+use diesel::prelude::*;
+use uuid::Uuid;
+
+use db::{
+    postgres::{PgExecutor, PooledConnection},
+    Error,
+};
+use models::{
+    ledger_entry::{Balance, Discrepancy, LedgerEntry, LedgerEntryPayload},
+    payout::Payout,
+};
+use types::{currency::Crypto, LedgerAccount, LedgerEvent, PayoutStatus};
+
+fn post(
+    payout: &Payout,
+    event: LedgerEvent,
+    destination: Option<String>,
+    postings: Vec<(LedgerAccount, BigDecimal)>,
+    conn: &PooledConnection,
+) -> Result<Vec<LedgerEntry>, Error> {
+    use diesel::insert_into;
+    use schema::ledger_entries::dsl;
+
+    let created_at = Utc::now();
+    let payloads: Vec<LedgerEntryPayload> = postings
+        .into_iter()
+        .map(|(account, amount)| LedgerEntryPayload {
+            store_id: payout.store_id,
+            typ: payout.typ,
+            payment_id: payout.payment_id,
+            payout_id: payout.id,
+            event,
+            account,
+            amount,
+            created_at,
+            destination: destination.clone(),
+        })
+        .collect();
+
+    insert_into(dsl::ledger_entries)
+        .values(&payloads)
+        .get_results(conn)
+        .map_err(|e| Error::from(e))
+}
+
+// Posts the amount of a credited payment output as pending.
+pub fn credit(
+    payout: &Payout,
+    amount: BigDecimal,
+    conn: &PooledConnection,
+) -> Result<Vec<LedgerEntry>, Error> {
+    post(
+        payout,
+        LedgerEvent::Credit,
+        None,
+        vec![
+            (LedgerAccount::Pending, amount.clone()),
+            (LedgerAccount::Payments, -amount),
+        ],
+        conn,
+    )
+}
+
+// Moves what is pending for a payout to where it was sent, less the network fee.
+pub fn settle(
+    payout: &Payout,
+    event: LedgerEvent,
+    fee: BigDecimal,
+    destination: Option<String>,
+    conn: &PooledConnection,
+) -> Result<Vec<LedgerEntry>, Error> {
+    use schema::ledger_entries::dsl;
+
+    let pending = dsl::ledger_entries
+        .filter(
+            dsl::payout_id
+                .eq(payout.id)
+                .and(dsl::account.eq(LedgerAccount::Pending)),
+        )
+        .load::<LedgerEntry>(conn)?
+        .into_iter()
+        .fold(BigDecimal::zero(), |pending, entry| pending + entry.amount);
+
+    // Nothing was credited for payouts of payments without an amount, which `check` reports.
+    if pending.is_zero() {
+        return Ok(Vec::new());
+    }
+
+    let account = match event {
+        LedgerEvent::Refund => LedgerAccount::Refunded,
+        _ => LedgerAccount::Settled,
+    };
+
+    post(
+        payout,
+        event,
+        destination,
+        vec![
+            (LedgerAccount::Pending, -pending.clone()),
+            (account, pending - fee.clone()),
+            (LedgerAccount::NetworkFees, fee),
+        ],
+        conn,
+    )
+}
+
+// Moves the part of the fee posted for a sent payout which its transaction didn't spend to what is
+// left on Finch's addresses, once. Ethereum transactions are signed with a fee cap, and only pay
+// the fee of the gas they used at the price of their block.
+pub fn adjust_fee(
+    payout: &Payout,
+    fee: BigDecimal,
+    conn: &PooledConnection,
+) -> Result<Vec<LedgerEntry>, Error> {
+    use schema::ledger_entries::dsl;
+
+    let entries = dsl::ledger_entries
+        .filter(dsl::payout_id.eq(payout.id))
+        .load::<LedgerEntry>(conn)?;
+
+    if entries
+        .iter()
+        .any(|entry| entry.event == LedgerEvent::FeeAdjustment)
+    {
+        return Ok(Vec::new());
+    }
+
+    let posted = entries
+        .iter()
+        .filter(|entry| entry.account == LedgerAccount::NetworkFees)
+        .fold(BigDecimal::zero(), |posted, entry| {
+            posted + entry.amount.clone()
+        });
+    let unspent = posted - fee;
+
+    // Postings of zero record that the fee cap was spent, so the payout isn't looked at again.
+    post(
+        payout,
+        LedgerEvent::FeeAdjustment,
+        None,
+        vec![
+            (LedgerAccount::NetworkFees, -unspent.clone()),
+            (LedgerAccount::Unspent, unspent),
+        ],
+        conn,
+    )
+}
+
+pub fn find_by_store(
+    store_id: Uuid,
+    limit: i64,
+    offset: i64,
+    conn: &PooledConnection,
+) -> Result<Vec<LedgerEntry>, Error> {
+    use schema::ledger_entries::dsl;
+
+    dsl::ledger_entries
+        .filter(dsl::store_id.eq(store_id))
+        .order(dsl::created_at.desc())
+        .limit(limit)
+        .offset(offset)
+        .load::<LedgerEntry>(conn)
+        .map_err(|e| Error::from(e))
+}
+
+// The postings of payouts and refunds of the given payouts.
+pub fn find_settlements(
+    payout_ids: Vec<Uuid>,
+    conn: &PooledConnection,
+) -> Result<Vec<LedgerEntry>, Error> {
+    use schema::ledger_entries::dsl;
+
+    dsl::ledger_entries
+        .filter(
+            dsl::payout_id
+                .eq_any(payout_ids)
+                .and(dsl::event.ne(LedgerEvent::Credit)),
+        )
+        .load::<LedgerEntry>(conn)
+        .map_err(|e| Error::from(e))
+}
+
+pub fn find_balances_by_store(
+    store_id: Uuid,
+    conn: &PooledConnection,
+) -> Result<Vec<Balance>, Error> {
+    use diesel::dsl::sum;
+    use schema::ledger_entries::dsl;
+
+    let totals = dsl::ledger_entries
+        .filter(dsl::store_id.eq(store_id))
+        .group_by((dsl::typ, dsl::account))
+        .select((dsl::typ, dsl::account, sum(dsl::amount)))
+        .load::<(Crypto, LedgerAccount, Option<BigDecimal>)>(conn)?;
+
+    let mut balances: Vec<Balance> = Vec::new();
+
+    for (typ, account, total) in totals {
+        let total = total.unwrap_or_else(BigDecimal::zero);
+        let position = match balances.iter().position(|balance| balance.typ == typ) {
+            Some(position) => position,
+            None => {
+                balances.push(Balance::new(typ));
+                balances.len() - 1
+            }
+        };
+        let balance = &mut balances[position];
+
+        match account {
+            // Payments are credits, so earnings grow as the account goes negative.
+            LedgerAccount::Payments => balance.earned = -total,
+            LedgerAccount::Pending => balance.pending = total,
+            LedgerAccount::Settled => balance.settled = total,
+            LedgerAccount::Refunded => balance.refunded = total,
+            LedgerAccount::NetworkFees => balance.network_fees = total,
+            LedgerAccount::Unspent => balance.unspent = total,
+        }
+    }
+
+    Ok(balances)
+}
+
+// Checks that the postings of every event balance, and that what is credited and pending for the
+// store's payouts agrees with their payments and statuses. Postings are summed by the database, so
+// a payout is looked at once however many postings it has.
+pub fn check(store_id: Uuid, conn: &PooledConnection) -> Result<Vec<Discrepancy>, Error> {
+    use diesel::dsl::sum;
+    use schema::ledger_entries::dsl;
+    use schema::payments::dsl as payments_dsl;
+    use schema::payouts::dsl as payouts_dsl;
+
+    let unbalanced: HashSet<Uuid> = dsl::ledger_entries
+        .filter(dsl::store_id.eq(store_id))
+        .group_by((dsl::payout_id, dsl::event))
+        .select((dsl::payout_id, sum(dsl::amount)))
+        .load::<(Uuid, Option<BigDecimal>)>(conn)?
+        .into_iter()
+        .filter(|(_, total)| total.as_ref().map_or(false, |total| !total.is_zero()))
+        .map(|(payout_id, _)| payout_id)
+        .collect();
+
+    let pending: HashMap<Uuid, BigDecimal> = dsl::ledger_entries
+        .filter(
+            dsl::store_id
+                .eq(store_id)
+                .and(dsl::account.eq(LedgerAccount::Pending)),
+        )
+        .group_by(dsl::payout_id)
+        .select((dsl::payout_id, sum(dsl::amount)))
+        .load::<(Uuid, Option<BigDecimal>)>(conn)?
+        .into_iter()
+        .map(|(payout_id, total)| (payout_id, total.unwrap_or_else(BigDecimal::zero)))
+        .collect();
+
+    let credited: HashMap<Uuid, BigDecimal> = dsl::ledger_entries
+        .filter(
+            dsl::store_id.eq(store_id).and(
+                dsl::event
+                    .eq(LedgerEvent::Credit)
+                    .and(dsl::account.eq(LedgerAccount::Pending)),
+            ),
+        )
+        .select((dsl::payout_id, dsl::amount))
+        .load::<(Uuid, BigDecimal)>(conn)?
+        .into_iter()
+        .collect();
+
+    // Latest first, the amount paid of a payment being the one of its latest credited output.
+    let payouts = payouts_dsl::payouts
+        .filter(payouts_dsl::store_id.eq(store_id))
+        .order(payouts_dsl::created_at.desc())
+        .select((
+            payouts_dsl::id,
+            payouts_dsl::payment_id,
+            payouts_dsl::status,
+        ))
+        .load::<(Uuid, Uuid, PayoutStatus)>(conn)?;
+
+    let amounts_paid: HashMap<Uuid, BigDecimal> = payments_dsl::payments
+        .filter(
+            payments_dsl::store_id
+                .eq(store_id)
+                .and(payments_dsl::amount_paid.is_not_null()),
+        )
+        .select((payments_dsl::id, payments_dsl::amount_paid))
+        .load::<(Uuid, Option<BigDecimal>)>(conn)?
+        .into_iter()
+        .filter_map(|(payment_id, amount_paid)| amount_paid.map(|amount| (payment_id, amount)))
+        .collect();
+
+    let mut discrepancies = Vec::new();
+    let mut checked_payments = HashSet::new();
+
+    for (payout_id, payment_id, status) in payouts {
+        let mut discrepancy = |description| {
+            discrepancies.push(Discrepancy {
+                payout_id,
+                payment_id,
+                description,
+            })
+        };
+
+        if unbalanced.contains(&payout_id) {
+            discrepancy("postings don't balance");
+        }
+
+        let credit = credited.get(&payout_id);
+
+        match (credit, amounts_paid.get(&payment_id)) {
+            (None, Some(_)) => discrepancy("payout isn't credited"),
+            (Some(credit), Some(amount_paid))
+                if !checked_payments.contains(&payment_id) && credit != amount_paid =>
+            {
+                discrepancy("credited amount doesn't match the payment")
+            }
+            _ => (),
+        }
+
+        checked_payments.insert(payment_id);
+
+        let is_pending = pending
+            .get(&payout_id)
+            .map_or(false, |pending| !pending.is_zero());
+
+        match status {
+            PayoutStatus::PaidOut | PayoutStatus::Refunded if is_pending => {
+                discrepancy("sent payout is still pending")
+            }
+            PayoutStatus::PaidOut | PayoutStatus::Refunded => (),
+            _ if credit.is_some() && !is_pending => {
+                discrepancy("payout isn't sent but nothing is pending")
+            }
+            _ => (),
+        }
+    }
+
+    Ok(discrepancies)
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<Vec<LedgerEntry>, Error>")]
+pub struct FindByStore {
+    pub store_id: Uuid,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+impl Handler<FindByStore> for PgExecutor {
+    type Result = Result<Vec<LedgerEntry>, Error>;
+
+    fn handle(
+        &mut self,
+        FindByStore {
+            store_id,
+            limit,
+            offset,
+        }: FindByStore,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        let conn = &self.get()?;
+
+        find_by_store(store_id, limit, offset, &conn)
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<Vec<LedgerEntry>, Error>")]
+pub struct FindSettlements(pub Vec<Uuid>);
+
+impl Handler<FindSettlements> for PgExecutor {
+    type Result = Result<Vec<LedgerEntry>, Error>;
+
+    fn handle(
+        &mut self,
+        FindSettlements(payout_ids): FindSettlements,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        let conn = &self.get()?;
+
+        find_settlements(payout_ids, &conn)
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<Vec<LedgerEntry>, Error>")]
+pub struct AdjustFee {
+    pub payout: Payout,
+    pub fee: BigDecimal,
+}
+
+impl Handler<AdjustFee> for PgExecutor {
+    type Result = Result<Vec<LedgerEntry>, Error>;
+
+    fn handle(
+        &mut self,
+        AdjustFee { payout, fee }: AdjustFee,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        let conn = &self.get()?;
+
+        conn.transaction::<_, Error, _>(|| adjust_fee(&payout, fee, &conn))
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<Vec<Balance>, Error>")]
+pub struct FindBalancesByStore(pub Uuid);
+
+impl Handler<FindBalancesByStore> for PgExecutor {
+    type Result = Result<Vec<Balance>, Error>;
+
+    fn handle(
+        &mut self,
+        FindBalancesByStore(store_id): FindBalancesByStore,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        let conn = &self.get()?;
+
+        find_balances_by_store(store_id, &conn)
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<Vec<Discrepancy>, Error>")]
+pub struct Check(pub Uuid);
+
+impl Handler<Check> for PgExecutor {
+    type Result = Result<Vec<Discrepancy>, Error>;
+
+    fn handle(&mut self, Check(store_id): Check, _: &mut Self::Context) -> Self::Result {
+        let conn = &self.get()?;
+
+        check(store_id, &conn)
+    }
+}
@@ -4,10 +4,17 @@ pub use self::errors::Error;
 pub mod postgres;
 pub mod redis;
 
+pub mod audit_events;
+pub mod block_rescans;
 pub mod client_tokens;
 pub mod ethereum;
+pub mod exports;
+pub mod ledger_entries;
+pub mod notification_preferences;
 pub mod payments;
+pub mod payout_address_changes;
 pub mod payouts;
+pub mod sessions;
 pub mod stores;
 pub mod bitcoin;
 pub mod users;
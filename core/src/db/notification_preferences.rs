@@ -0,0 +1,75 @@
+use actix::prelude::*;
+use diesel::prelude::*;
+use uuid::Uuid;
+
+use db::{
+    postgres::{PgExecutor, PooledConnection},
+    Error,
+};
+use models::notification_preference::{NotificationPreference, NotificationPreferencePayload};
+
+pub fn upsert(
+    payload: NotificationPreferencePayload,
+    conn: &PooledConnection,
+) -> Result<NotificationPreference, Error> {
+    use diesel::insert_into;
+    use schema::notification_preferences::dsl;
+
+    let changes = payload.clone();
+
+    let mut payload = payload;
+    payload.created_at = payload.updated_at;
+
+    insert_into(dsl::notification_preferences)
+        .values(&payload)
+        .on_conflict(dsl::store_id)
+        .do_update()
+        .set(&changes)
+        .get_result(conn)
+        .map_err(|e| Error::from(e))
+}
+
+pub fn find_by_store(
+    store_id: Uuid,
+    conn: &PooledConnection,
+) -> Result<Option<NotificationPreference>, Error> {
+    use schema::notification_preferences::dsl;
+
+    dsl::notification_preferences
+        .filter(dsl::store_id.eq(store_id))
+        .first::<NotificationPreference>(conn)
+        .optional()
+        .map_err(|e| Error::from(e))
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<NotificationPreference, Error>")]
+pub struct Upsert(pub NotificationPreferencePayload);
+
+impl Handler<Upsert> for PgExecutor {
+    type Result = Result<NotificationPreference, Error>;
+
+    fn handle(&mut self, Upsert(payload): Upsert, _: &mut Self::Context) -> Self::Result {
+        let conn = &self.get()?;
+
+        upsert(payload, &conn)
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<Option<NotificationPreference>, Error>")]
+pub struct FindByStore(pub Uuid);
+
+impl Handler<FindByStore> for PgExecutor {
+    type Result = Result<Option<NotificationPreference>, Error>;
+
+    fn handle(
+        &mut self,
+        FindByStore(store_id): FindByStore,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        let conn = &self.get()?;
+
+        find_by_store(store_id, &conn)
+    }
+}
@@ -1,4 +1,5 @@
 use actix::prelude::*;
+use chrono::prelude::*;
 use diesel::prelude::*;
 
 use db::{
@@ -8,7 +9,7 @@ use db::{
 use models::payment::{Payment, PaymentPayload};
 use uuid::Uuid;
 
-use types::currency::Crypto;
+use types::{currency::Crypto, ethereum::Network as EthNetwork, PaymentStatus};
 
 pub fn insert(payload: PaymentPayload, conn: &PooledConnection) -> Result<Payment, Error> {
     use diesel::insert_into;
@@ -57,6 +58,118 @@ pub fn find_all_by_addresses(
         .map_err(|e| Error::from(e))
 }
 
+pub fn find_all_by_eth_addresses(
+    addresses: Vec<String>,
+    network: EthNetwork,
+    conn: &PooledConnection,
+) -> Result<Vec<Payment>, Error> {
+    use diesel::pg::expression::dsl::any;
+    use schema::payments::dsl;
+
+    dsl::payments
+        .filter(
+            dsl::address
+                .eq(any(addresses))
+                .and(dsl::crypto.eq(Crypto::Eth))
+                .and(dsl::eth_network.eq(network)),
+        )
+        .load::<Payment>(conn)
+        .map_err(|e| Error::from(e))
+}
+
+pub fn find_all_pending_by_crypto(
+    crypto: Crypto,
+    conn: &PooledConnection,
+) -> Result<Vec<Payment>, Error> {
+    use schema::payments::dsl;
+
+    dsl::payments
+        .filter(
+            dsl::status
+                .eq(PaymentStatus::Pending)
+                .and(dsl::crypto.eq(crypto)),
+        )
+        .load::<Payment>(conn)
+        .map_err(|e| Error::from(e))
+}
+
+pub fn find_all_unpaid_since(
+    crypto: Crypto,
+    since: DateTime<Utc>,
+    conn: &PooledConnection,
+) -> Result<Vec<Payment>, Error> {
+    use schema::payments::dsl;
+
+    dsl::payments
+        .filter(
+            dsl::status
+                .eq_any(vec![PaymentStatus::Pending, PaymentStatus::Expired])
+                .and(dsl::crypto.eq(crypto))
+                .and(dsl::created_at.ge(since))
+                .and(dsl::transaction_hash.is_null())
+                .and(dsl::amount_paid.is_null()),
+        )
+        .load::<Payment>(conn)
+        .map_err(|e| Error::from(e))
+}
+
+// Payments paid to an address derived from the store's mnemonic.
+pub fn find_all_with_address_by_store(
+    store_id: Uuid,
+    conn: &PooledConnection,
+) -> Result<Vec<Payment>, Error> {
+    use schema::payments::dsl;
+
+    dsl::payments
+        .filter(
+            dsl::store_id
+                .eq(store_id)
+                .and(dsl::crypto.ne(Crypto::BtcLightning)),
+        )
+        .order(dsl::created_at.asc())
+        .load::<Payment>(conn)
+        .map_err(|e| Error::from(e))
+}
+
+// A page of the store's payments created in the range, oldest first, following the one whose
+// creation time and id are `after`.
+pub fn find_by_store_in_range(
+    store_id: Uuid,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    limit: i64,
+    after: Option<(DateTime<Utc>, Uuid)>,
+    conn: &PooledConnection,
+) -> Result<Vec<Payment>, Error> {
+    use schema::payments::dsl;
+
+    let mut query = dsl::payments
+        .filter(dsl::store_id.eq(store_id))
+        .order((dsl::created_at.asc(), dsl::id.asc()))
+        .into_boxed();
+
+    if let Some(from) = from {
+        query = query.filter(dsl::created_at.ge(from));
+    }
+
+    if let Some(to) = to {
+        query = query.filter(dsl::created_at.lt(to));
+    }
+
+    if let Some((created_at, id)) = after {
+        query = query.filter(
+            dsl::created_at
+                .gt(created_at)
+                .or(dsl::created_at.eq(created_at).and(dsl::id.gt(id))),
+        );
+    }
+
+    query
+        .limit(limit)
+        .load::<Payment>(conn)
+        .map_err(|e| Error::from(e))
+}
+
 #[derive(Message)]
 #[rtype(result = "Result<Payment, Error>")]
 pub struct Insert(pub PaymentPayload);
@@ -119,3 +232,111 @@ impl Handler<FindAllByAddress> for PgExecutor {
         find_all_by_addresses(addresses, crypto, &conn)
     }
 }
+
+#[derive(Message)]
+#[rtype(result = "Result<Vec<Payment>, Error>")]
+pub struct FindAllByEthAddress {
+    pub addresses: Vec<String>,
+    pub network: EthNetwork,
+}
+
+impl Handler<FindAllByEthAddress> for PgExecutor {
+    type Result = Result<Vec<Payment>, Error>;
+
+    fn handle(
+        &mut self,
+        FindAllByEthAddress { addresses, network }: FindAllByEthAddress,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        let conn = &self.get()?;
+
+        find_all_by_eth_addresses(addresses, network, &conn)
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<Vec<Payment>, Error>")]
+pub struct FindAllPendingByCrypto(pub Crypto);
+
+impl Handler<FindAllPendingByCrypto> for PgExecutor {
+    type Result = Result<Vec<Payment>, Error>;
+
+    fn handle(
+        &mut self,
+        FindAllPendingByCrypto(crypto): FindAllPendingByCrypto,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        let conn = &self.get()?;
+
+        find_all_pending_by_crypto(crypto, &conn)
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<Vec<Payment>, Error>")]
+pub struct FindAllUnpaidSince {
+    pub crypto: Crypto,
+    pub since: DateTime<Utc>,
+}
+
+impl Handler<FindAllUnpaidSince> for PgExecutor {
+    type Result = Result<Vec<Payment>, Error>;
+
+    fn handle(
+        &mut self,
+        FindAllUnpaidSince { crypto, since }: FindAllUnpaidSince,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        let conn = &self.get()?;
+
+        find_all_unpaid_since(crypto, since, &conn)
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<Vec<Payment>, Error>")]
+pub struct FindAllWithAddressByStore(pub Uuid);
+
+impl Handler<FindAllWithAddressByStore> for PgExecutor {
+    type Result = Result<Vec<Payment>, Error>;
+
+    fn handle(
+        &mut self,
+        FindAllWithAddressByStore(store_id): FindAllWithAddressByStore,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        let conn = &self.get()?;
+
+        find_all_with_address_by_store(store_id, &conn)
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<Vec<Payment>, Error>")]
+pub struct FindByStoreInRange {
+    pub store_id: Uuid,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub limit: i64,
+    pub after: Option<(DateTime<Utc>, Uuid)>,
+}
+
+impl Handler<FindByStoreInRange> for PgExecutor {
+    type Result = Result<Vec<Payment>, Error>;
+
+    fn handle(
+        &mut self,
+        FindByStoreInRange {
+            store_id,
+            from,
+            to,
+            limit,
+            after,
+        }: FindByStoreInRange,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        let conn = &self.get()?;
+
+        find_by_store_in_range(store_id, from, to, limit, after, &conn)
+    }
+}
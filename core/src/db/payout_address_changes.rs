@@ -0,0 +1,444 @@
+use actix::prelude::*;
+use chrono::{prelude::*, Duration};
+use diesel::prelude::*;
+use uuid::Uuid;
+
+use db::{
+    postgres::{PgExecutor, PooledConnection},
+    stores, Error,
+};
+use models::{
+    payout_address_change::{PayoutAddressChange, PayoutAddressChangePayload},
+    store::StorePayload,
+};
+
+pub fn insert(
+    payload: PayoutAddressChangePayload,
+    conn: &PooledConnection,
+) -> Result<PayoutAddressChange, Error> {
+    use diesel::insert_into;
+    use schema::payout_address_changes::dsl;
+
+    if let Some(store_id) = payload.store_id {
+        cancel_pending_by_store(store_id, conn)?;
+    }
+
+    insert_into(dsl::payout_address_changes)
+        .values(&payload)
+        .get_result(conn)
+        .map_err(|e| Error::from(e))
+}
+
+pub fn find_by_id(id: Uuid, conn: &PooledConnection) -> Result<PayoutAddressChange, Error> {
+    use schema::payout_address_changes::dsl;
+
+    dsl::payout_address_changes
+        .filter(dsl::id.eq(id))
+        .first::<PayoutAddressChange>(conn)
+        .map_err(|e| Error::from(e))
+}
+
+pub fn find_by_store(
+    store_id: Uuid,
+    limit: i64,
+    offset: i64,
+    conn: &PooledConnection,
+) -> Result<Vec<PayoutAddressChange>, Error> {
+    use schema::payout_address_changes::dsl;
+
+    dsl::payout_address_changes
+        .filter(dsl::store_id.eq(store_id))
+        .order(dsl::created_at.desc())
+        .limit(limit)
+        .offset(offset)
+        .load::<PayoutAddressChange>(conn)
+        .map_err(|e| Error::from(e))
+}
+
+pub fn confirm(token: Uuid, conn: &PooledConnection) -> Result<PayoutAddressChange, Error> {
+    use diesel::update;
+    use schema::payout_address_changes::dsl;
+
+    let now = Utc::now();
+
+    let mut payload = PayoutAddressChangePayload::new();
+    payload.confirmed_at = Some(Some(now));
+
+    update(
+        dsl::payout_address_changes.filter(
+            dsl::confirmation_token
+                .eq(token)
+                .and(dsl::confirmation_token_expires_at.gt(now))
+                .and(dsl::confirmed_at.is_null())
+                .and(dsl::cancelled_at.is_null()),
+        ),
+    )
+    .set(&payload)
+    .get_result(conn)
+    .map_err(|e| Error::from(e))
+}
+
+// A confirmed change has no effective time until it's scheduled, so that `apply_due` leaves it
+// alone meanwhile.
+pub fn schedule(
+    id: Uuid,
+    time_lock: Duration,
+    conn: &PooledConnection,
+) -> Result<PayoutAddressChange, Error> {
+    use diesel::update;
+    use schema::payout_address_changes::dsl;
+
+    let mut payload = PayoutAddressChangePayload::new();
+    payload.effective_at = Some(Some(Utc::now() + time_lock));
+
+    let change = update(
+        dsl::payout_address_changes.filter(
+            dsl::id
+                .eq(id)
+                .and(dsl::confirmed_at.is_not_null())
+                .and(dsl::effective_at.is_null())
+                .and(dsl::cancelled_at.is_null()),
+        ),
+    )
+    .set(&payload)
+    .get_result::<PayoutAddressChange>(conn)?;
+
+    if time_lock > Duration::zero() {
+        return Ok(change);
+    }
+
+    apply(change, conn)
+}
+
+pub fn cancel(id: Uuid, conn: &PooledConnection) -> Result<PayoutAddressChange, Error> {
+    use diesel::update;
+    use schema::payout_address_changes::dsl;
+
+    let mut payload = PayoutAddressChangePayload::new();
+    payload.cancelled_at = Some(Some(Utc::now()));
+
+    update(
+        dsl::payout_address_changes.filter(
+            dsl::id
+                .eq(id)
+                .and(dsl::applied_at.is_null())
+                .and(dsl::cancelled_at.is_null()),
+        ),
+    )
+    .set(&payload)
+    .get_result(conn)
+    .map_err(|e| Error::from(e))
+}
+
+pub fn cancel_by_token(token: Uuid, conn: &PooledConnection) -> Result<PayoutAddressChange, Error> {
+    use diesel::update;
+    use schema::payout_address_changes::dsl;
+
+    let mut payload = PayoutAddressChangePayload::new();
+    payload.cancelled_at = Some(Some(Utc::now()));
+
+    update(
+        dsl::payout_address_changes.filter(
+            dsl::cancellation_token
+                .eq(token)
+                .and(dsl::applied_at.is_null())
+                .and(dsl::cancelled_at.is_null()),
+        ),
+    )
+    .set(&payload)
+    .get_result(conn)
+    .map_err(|e| Error::from(e))
+}
+
+pub fn cancel_pending_by_store(store_id: Uuid, conn: &PooledConnection) -> Result<usize, Error> {
+    use diesel::update;
+    use schema::payout_address_changes::dsl;
+
+    let mut payload = PayoutAddressChangePayload::new();
+    payload.cancelled_at = Some(Some(Utc::now()));
+
+    update(
+        dsl::payout_address_changes.filter(
+            dsl::store_id
+                .eq(store_id)
+                .and(dsl::applied_at.is_null())
+                .and(dsl::cancelled_at.is_null()),
+        ),
+    )
+    .set(&payload)
+    .execute(conn)
+    .map_err(|e| Error::from(e))
+}
+
+pub fn apply(
+    change: PayoutAddressChange,
+    conn: &PooledConnection,
+) -> Result<PayoutAddressChange, Error> {
+    use diesel::update;
+    use schema::payout_address_changes::dsl;
+
+    let mut store_payload = StorePayload::new();
+    store_payload.set_updated_at();
+
+    if let Some(eth_payout_addresses) = change.eth_payout_addresses {
+        store_payload.eth_payout_addresses = Some(Some(eth_payout_addresses));
+    }
+
+    // A multisig wallet and payout addresses replace each other as the BTC destination.
+    if let Some(btc_payout_addresses) = change.btc_payout_addresses {
+        store_payload.btc_payout_addresses = Some(Some(btc_payout_addresses));
+        store_payload.btc_payout_multisig_threshold = Some(None);
+        store_payload.btc_payout_multisig_public_keys = Some(None);
+    }
+
+    if let (Some(threshold), Some(public_keys)) = (
+        change.btc_payout_multisig_threshold,
+        change.btc_payout_multisig_public_keys,
+    ) {
+        store_payload.btc_payout_addresses = Some(None);
+        store_payload.btc_payout_multisig_threshold = Some(Some(threshold));
+        store_payload.btc_payout_multisig_public_keys = Some(Some(public_keys));
+    }
+
+    if let (Some(threshold), Some(xpubs)) = (
+        change.btc_invoice_multisig_threshold,
+        change.btc_invoice_multisig_xpubs,
+    ) {
+        store_payload.btc_invoice_multisig_threshold = Some(Some(threshold));
+        store_payload.btc_invoice_multisig_xpubs = Some(Some(xpubs));
+    }
+
+    if let Some(ltc_payout_addresses) = change.ltc_payout_addresses {
+        store_payload.ltc_payout_addresses = Some(Some(ltc_payout_addresses));
+    }
+
+    if let Some(bch_payout_addresses) = change.bch_payout_addresses {
+        store_payload.bch_payout_addresses = Some(Some(bch_payout_addresses));
+    }
+
+    if let Some(doge_payout_addresses) = change.doge_payout_addresses {
+        store_payload.doge_payout_addresses = Some(Some(doge_payout_addresses));
+    }
+
+    if let Some(manual_payout_approval) = change.manual_payout_approval {
+        store_payload.manual_payout_approval = Some(manual_payout_approval);
+    }
+
+    stores::update(change.store_id, store_payload, conn)?;
+
+    let mut payload = PayoutAddressChangePayload::new();
+    payload.applied_at = Some(Some(Utc::now()));
+
+    update(dsl::payout_address_changes.filter(dsl::id.eq(change.id)))
+        .set(&payload)
+        .get_result(conn)
+        .map_err(|e| Error::from(e))
+}
+
+pub fn apply_due(conn: &PooledConnection) -> Result<Vec<PayoutAddressChange>, Error> {
+    use schema::payout_address_changes::dsl;
+
+    let due = dsl::payout_address_changes
+        .filter(
+            dsl::confirmed_at
+                .is_not_null()
+                .and(dsl::effective_at.le(Utc::now()))
+                .and(dsl::applied_at.is_null())
+                .and(dsl::cancelled_at.is_null()),
+        )
+        .for_update()
+        .load::<PayoutAddressChange>(conn)?;
+
+    due.into_iter().map(|change| apply(change, conn)).collect()
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<PayoutAddressChange, Error>")]
+pub struct Insert(pub PayoutAddressChangePayload);
+
+impl Handler<Insert> for PgExecutor {
+    type Result = Result<PayoutAddressChange, Error>;
+
+    fn handle(&mut self, Insert(payload): Insert, _: &mut Self::Context) -> Self::Result {
+        let conn = &self.get()?;
+
+        conn.transaction::<_, Error, _>(|| insert(payload, &conn))
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<PayoutAddressChange, Error>")]
+pub struct FindById(pub Uuid);
+
+impl Handler<FindById> for PgExecutor {
+    type Result = Result<PayoutAddressChange, Error>;
+
+    fn handle(&mut self, FindById(id): FindById, _: &mut Self::Context) -> Self::Result {
+        let conn = &self.get()?;
+
+        find_by_id(id, &conn)
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<Vec<PayoutAddressChange>, Error>")]
+pub struct FindByStore {
+    pub store_id: Uuid,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+impl Handler<FindByStore> for PgExecutor {
+    type Result = Result<Vec<PayoutAddressChange>, Error>;
+
+    fn handle(
+        &mut self,
+        FindByStore {
+            store_id,
+            limit,
+            offset,
+        }: FindByStore,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        let conn = &self.get()?;
+
+        find_by_store(store_id, limit, offset, &conn)
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<PayoutAddressChange, Error>")]
+pub struct Confirm(pub Uuid);
+
+impl Handler<Confirm> for PgExecutor {
+    type Result = Result<PayoutAddressChange, Error>;
+
+    fn handle(&mut self, Confirm(token): Confirm, _: &mut Self::Context) -> Self::Result {
+        let conn = &self.get()?;
+
+        confirm(token, &conn)
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<PayoutAddressChange, Error>")]
+pub struct Schedule {
+    pub id: Uuid,
+    pub time_lock: Duration,
+}
+
+impl Handler<Schedule> for PgExecutor {
+    type Result = Result<PayoutAddressChange, Error>;
+
+    fn handle(
+        &mut self,
+        Schedule { id, time_lock }: Schedule,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        let conn = &self.get()?;
+
+        conn.transaction::<_, Error, _>(|| schedule(id, time_lock, &conn))
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<PayoutAddressChange, Error>")]
+pub struct Cancel(pub Uuid);
+
+impl Handler<Cancel> for PgExecutor {
+    type Result = Result<PayoutAddressChange, Error>;
+
+    fn handle(&mut self, Cancel(id): Cancel, _: &mut Self::Context) -> Self::Result {
+        let conn = &self.get()?;
+
+        cancel(id, &conn)
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<PayoutAddressChange, Error>")]
+pub struct CancelByToken(pub Uuid);
+
+impl Handler<CancelByToken> for PgExecutor {
+    type Result = Result<PayoutAddressChange, Error>;
+
+    fn handle(
+        &mut self,
+        CancelByToken(token): CancelByToken,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        let conn = &self.get()?;
+
+        cancel_by_token(token, &conn)
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<Vec<PayoutAddressChange>, Error>")]
+pub struct ApplyDue;
+
+impl Handler<ApplyDue> for PgExecutor {
+    type Result = Result<Vec<PayoutAddressChange>, Error>;
+
+    fn handle(&mut self, _: ApplyDue, _: &mut Self::Context) -> Self::Result {
+        let conn = &self.get()?;
+
+        conn.transaction::<_, Error, _>(|| apply_due(&conn))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+    use db::postgres;
+
+    // These run against the migrated database in `DATABASE_URL`, which has to be set.
+    fn connection() -> PooledConnection {
+        let url = env::var("DATABASE_URL").expect("DATABASE_URL must point to a migrated database");
+
+        postgres::init_pool(&url)
+            .get()
+            .expect("failed to connect to the database")
+    }
+
+    fn change(conn: &PooledConnection) -> PayoutAddressChange {
+        let mut payload = PayoutAddressChangePayload::new();
+        payload.store_id = Some(Uuid::new_v4());
+        payload.set_created_at();
+        payload.set_tokens();
+
+        insert(payload, conn).unwrap()
+    }
+
+    #[test]
+    fn confirming_leaves_the_change_unscheduled() {
+        let conn = connection();
+        let change = change(&conn);
+
+        let confirmed = confirm(change.confirmation_token, &conn).unwrap();
+        assert!(confirmed.confirmed_at.is_some());
+        assert!(confirmed.effective_at.is_none());
+        assert!(confirm(change.confirmation_token, &conn).is_err());
+
+        let scheduled = schedule(change.id, Duration::hours(1), &conn).unwrap();
+        assert!(scheduled.effective_at.unwrap() > Utc::now());
+        assert!(scheduled.applied_at.is_none());
+        assert!(schedule(change.id, Duration::hours(1), &conn).is_err());
+    }
+
+    #[test]
+    fn cancelling_by_token_works_until_applied() {
+        let conn = connection();
+        let change = change(&conn);
+
+        // Only the cancellation token cancels the change, even before it's confirmed.
+        assert!(cancel_by_token(change.confirmation_token, &conn).is_err());
+
+        let cancelled = cancel_by_token(change.cancellation_token, &conn).unwrap();
+        assert!(cancelled.cancelled_at.is_some());
+        assert!(confirm(change.confirmation_token, &conn).is_err());
+        assert!(cancel_by_token(change.cancellation_token, &conn).is_err());
+    }
+}
@@ -1,57 +1,132 @@
 use actix::prelude::*;
+use bigdecimal::BigDecimal;
+use chrono::prelude::*;
 use diesel::prelude::*;
 use uuid::Uuid;
 
 use db::{
     bitcoin::transactions as btc_transactions,
-    ethereum::transactions as eth_transactions,
-    payments,
+    ethereum::{transactions as eth_transactions, transfers as eth_transfers},
+    ledger_entries, payments,
     postgres::{PgExecutor, PooledConnection},
     Error,
 };
 use models::{
     bitcoin::Transaction as BtcTransaction,
-    ethereum::Transaction as EthTransaction,
+    ethereum::{Transaction as EthTransaction, Transfer as EthTransfer},
     payment::PaymentPayload,
-    payout::{Payout, PayoutPayload},
+    payout::{Payout, PayoutApproval, PayoutPayload, PayoutPsbt},
+};
+use types::{
+    currency::Crypto, ethereum::Network as EthNetwork, LedgerAccount, LedgerEvent, PayoutStatus,
+    H256, U128,
 };
-use types::{currency::Crypto, PayoutStatus, U128};
 
+// Credits a payment output once: `None` is returned without touching the payment when the output
+// already has a payout.
 pub fn insert_btc(
     payout_payload: PayoutPayload,
     payment_payload: PaymentPayload,
     transaction_payload: BtcTransaction,
     conn: &PooledConnection,
-) -> Result<Payout, Error> {
-    use diesel::insert_into;
-    use schema::payouts::dsl;
+) -> Result<Option<Payout>, Error> {
+    let payout = match insert_once(payout_payload, conn)? {
+        Some(payout) => payout,
+        None => return Ok(None),
+    };
 
-    payments::update(payout_payload.payment_id.unwrap(), payment_payload, conn)?;
+    if let Some(ref amount_paid) = payment_payload.amount_paid {
+        ledger_entries::credit(&payout, amount_paid.clone(), conn)?;
+    }
 
-    btc_transactions::insert(transaction_payload, conn)?;
+    payments::update(payout.payment_id, payment_payload, conn)?;
 
-    insert_into(dsl::payouts)
-        .values(&payout_payload)
-        .get_result(conn)
-        .map_err(|e| Error::from(e))
+    btc_transactions::upsert(transaction_payload, conn)?;
+
+    Ok(Some(payout))
 }
 
 pub fn insert_eth(
     payout_payload: PayoutPayload,
     payment_payload: PaymentPayload,
     transaction_payload: EthTransaction,
+    transfer_payload: EthTransfer,
     conn: &PooledConnection,
-) -> Result<Payout, Error> {
-    use diesel::insert_into;
-    use schema::payouts::dsl;
+) -> Result<Option<Payout>, Error> {
+    let payout = match insert_once(payout_payload, conn)? {
+        Some(payout) => payout,
+        None => return Ok(None),
+    };
 
-    payments::update(payout_payload.payment_id.unwrap(), payment_payload, conn)?;
+    if let Some(ref amount_paid) = payment_payload.amount_paid {
+        ledger_entries::credit(&payout, amount_paid.clone(), conn)?;
+    }
 
-    eth_transactions::insert(transaction_payload, conn)?;
+    payments::update(payout.payment_id, payment_payload, conn)?;
 
+    // A single transaction can pay several payments through internal transfers.
+    eth_transactions::upsert(transaction_payload, conn)?;
+    eth_transfers::upsert(transfer_payload, conn)?;
+
+    Ok(Some(payout))
+}
+
+fn insert_once(payload: PayoutPayload, conn: &PooledConnection) -> Result<Option<Payout>, Error> {
+    use diesel::{insert_into, pg::upsert::on_constraint};
+    use schema::payouts::dsl;
+
+    if let (Some(payment_id), Some(Some(transaction_hash)), Some(Some(output_index))) = (
+        payload.payment_id,
+        payload.payment_transaction_hash,
+        payload.output_index,
+    ) {
+        if find_by_payment_output(payment_id, transaction_hash, output_index, conn)?.is_some() {
+            return Ok(None);
+        }
+    }
+
+    // The constraint settles a race with a concurrent insert.
     insert_into(dsl::payouts)
-        .values(&payout_payload)
+        .values(&payload)
+        .on_conflict(on_constraint("payouts_payment_transaction_output_key"))
+        .do_nothing()
         .get_result(conn)
+        .optional()
+        .map_err(|e| Error::from(e))
+}
+
+// Payouts created before outputs were recorded only have the transaction hash, and match any
+// output of it.
+pub fn find_by_payment_output(
+    payment_id: Uuid,
+    transaction_hash: H256,
+    output_index: i32,
+    conn: &PooledConnection,
+) -> Result<Option<Payout>, Error> {
+    use schema::payouts::dsl;
+
+    dsl::payouts
+        .filter(
+            dsl::payment_id
+                .eq(payment_id)
+                .and(dsl::payment_transaction_hash.eq(transaction_hash))
+                .and(
+                    dsl::output_index
+                        .eq(output_index)
+                        .or(dsl::output_index.is_null()),
+                ),
+        )
+        .first::<Payout>(conn)
+        .optional()
+        .map_err(|e| Error::from(e))
+}
+
+pub fn find_by_id(id: Uuid, conn: &PooledConnection) -> Result<Payout, Error> {
+    use schema::payouts::dsl;
+
+    dsl::payouts
+        .filter(dsl::id.eq(id))
+        .first::<Payout>(conn)
         .map_err(|e| Error::from(e))
 }
 
@@ -92,6 +167,89 @@ pub fn update_with_payment(
         .map_err(|e| Error::from(e))
 }
 
+// Records a sent payout, posting what reached the store and the network fee to the ledger.
+pub fn pay_out(
+    id: Uuid,
+    payout_payload: PayoutPayload,
+    payment_payload: PaymentPayload,
+    fee: BigDecimal,
+    destination: Option<String>,
+    conn: &PooledConnection,
+) -> Result<Payout, Error> {
+    let payout = update_with_payment(id, payout_payload, payment_payload, conn)?;
+
+    ledger_entries::settle(&payout, LedgerEvent::Payout, fee, destination, conn)?;
+
+    Ok(payout)
+}
+
+pub fn refund(
+    id: Uuid,
+    payload: PayoutPayload,
+    fee: BigDecimal,
+    destination: Option<String>,
+    conn: &PooledConnection,
+) -> Result<Payout, Error> {
+    let payout = update(id, payload, conn)?;
+
+    ledger_entries::settle(&payout, LedgerEvent::Refund, fee, destination, conn)?;
+
+    Ok(payout)
+}
+
+// A page of the store's payouts created in the range, oldest first, following the one whose
+// creation time and id are `after`.
+pub fn find_by_store_in_range(
+    store_id: Uuid,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    limit: i64,
+    after: Option<(DateTime<Utc>, Uuid)>,
+    conn: &PooledConnection,
+) -> Result<Vec<Payout>, Error> {
+    use schema::payouts::dsl;
+
+    let mut query = dsl::payouts
+        .filter(dsl::store_id.eq(store_id))
+        .order((dsl::created_at.asc(), dsl::id.asc()))
+        .into_boxed();
+
+    if let Some(from) = from {
+        query = query.filter(dsl::created_at.ge(from));
+    }
+
+    if let Some(to) = to {
+        query = query.filter(dsl::created_at.lt(to));
+    }
+
+    if let Some((created_at, id)) = after {
+        query = query.filter(
+            dsl::created_at
+                .gt(created_at)
+                .or(dsl::created_at.eq(created_at).and(dsl::id.gt(id))),
+        );
+    }
+
+    query
+        .limit(limit)
+        .load::<Payout>(conn)
+        .map_err(|e| Error::from(e))
+}
+
+// The payouts crediting the given payments, oldest first.
+pub fn find_all_by_payments(
+    payment_ids: Vec<Uuid>,
+    conn: &PooledConnection,
+) -> Result<Vec<Payout>, Error> {
+    use schema::payouts::dsl;
+
+    dsl::payouts
+        .filter(dsl::payment_id.eq_any(payment_ids))
+        .order((dsl::created_at.asc(), dsl::id.asc()))
+        .load::<Payout>(conn)
+        .map_err(|e| Error::from(e))
+}
+
 pub fn find_all_confirmed(
     block_height: U128,
     typ: Crypto,
@@ -111,8 +269,277 @@ pub fn find_all_confirmed(
         .map_err(|e| Error::from(e))
 }
 
+pub fn find_all_confirmed_eth(
+    block_height: U128,
+    network: EthNetwork,
+    conn: &PooledConnection,
+) -> Result<Vec<Payout>, Error> {
+    use schema::payments::dsl as payments_dsl;
+    use schema::payouts::dsl;
+
+    let payment_ids = payments_dsl::payments
+        .select(payments_dsl::id)
+        .filter(payments_dsl::eth_network.eq(network));
+
+    dsl::payouts
+        .filter(
+            dsl::status.eq(PayoutStatus::Pending).and(
+                dsl::block_height_required
+                    .le(block_height)
+                    .and(dsl::typ.eq(Crypto::Eth))
+                    .and(dsl::payment_id.eq_any(payment_ids)),
+            ),
+        )
+        .load::<Payout>(conn)
+        .map_err(|e| Error::from(e))
+}
+
+// Sent payouts whose network fee was posted as the fee cap of their transaction, and not yet
+// adjusted to what the transaction spent.
+pub fn find_all_unadjusted_fees_eth(
+    network: EthNetwork,
+    conn: &PooledConnection,
+) -> Result<Vec<Payout>, Error> {
+    use schema::ledger_entries::dsl as ledger_entries_dsl;
+    use schema::payments::dsl as payments_dsl;
+    use schema::payouts::dsl;
+
+    let payment_ids = payments_dsl::payments
+        .select(payments_dsl::id)
+        .filter(payments_dsl::eth_network.eq(network));
+    let posted_ids = ledger_entries_dsl::ledger_entries
+        .select(ledger_entries_dsl::payout_id)
+        .filter(ledger_entries_dsl::account.eq(LedgerAccount::NetworkFees));
+    let adjusted_ids = ledger_entries_dsl::ledger_entries
+        .select(ledger_entries_dsl::payout_id)
+        .filter(ledger_entries_dsl::event.eq(LedgerEvent::FeeAdjustment));
+
+    dsl::payouts
+        .filter(
+            dsl::status
+                .eq_any(vec![PayoutStatus::PaidOut, PayoutStatus::Refunded])
+                .and(dsl::typ.eq(Crypto::Eth))
+                .and(dsl::transaction_hash.is_not_null())
+                .and(dsl::payment_id.eq_any(payment_ids))
+                .and(dsl::id.eq_any(posted_ids))
+                .and(dsl::id.ne_all(adjusted_ids)),
+        )
+        .load::<Payout>(conn)
+        .map_err(|e| Error::from(e))
+}
+
+// Marks the merchant as notified of the confirmation of payouts, yielding those which weren't yet
+// so that each payout is notified once.
+pub fn mark_confirmation_notified(
+    ids: Vec<Uuid>,
+    conn: &PooledConnection,
+) -> Result<Vec<Payout>, Error> {
+    use diesel::update;
+    use schema::payouts::dsl;
+
+    update(
+        dsl::payouts.filter(
+            dsl::id
+                .eq_any(ids)
+                .and(dsl::confirmation_notified_at.is_null()),
+        ),
+    )
+    .set(dsl::confirmation_notified_at.eq(Utc::now()))
+    .get_results(conn)
+    .map_err(|e| Error::from(e))
+}
+
+// Keeps the partially signed transaction of a multisig payout, which is no longer Finch's to
+// broadcast.
+pub fn await_cosigners(psbt: PayoutPsbt, conn: &PooledConnection) -> Result<Payout, Error> {
+    use diesel::{insert_into, update};
+    use schema::{payout_psbts::dsl as payout_psbts_dsl, payouts::dsl};
+
+    insert_into(payout_psbts_dsl::payout_psbts)
+        .values(&psbt)
+        .execute(conn)?;
+
+    update(dsl::payouts.filter(dsl::id.eq(psbt.payout_id)))
+        .set(dsl::status.eq(PayoutStatus::AwaitingCosigners))
+        .get_result(conn)
+        .map_err(|e| Error::from(e))
+}
+
+pub fn find_all_awaiting_cosigners_by_store(
+    store_id: Uuid,
+    conn: &PooledConnection,
+) -> Result<Vec<(Payout, PayoutPsbt)>, Error> {
+    use schema::payouts::dsl;
+
+    let payouts = dsl::payouts
+        .filter(
+            dsl::store_id
+                .eq(store_id)
+                .and(dsl::status.eq(PayoutStatus::AwaitingCosigners)),
+        )
+        .order(dsl::created_at.desc())
+        .load::<Payout>(conn)?;
+
+    with_psbts(payouts, conn)
+}
+
+// Multisig payouts in the currency, for the block processor to tell when their cosigners spent
+// the payment output.
+pub fn find_all_awaiting_cosigners(
+    typ: Crypto,
+    conn: &PooledConnection,
+) -> Result<Vec<(Payout, PayoutPsbt)>, Error> {
+    use schema::payouts::dsl;
+
+    let payouts = dsl::payouts
+        .filter(
+            dsl::typ
+                .eq(typ)
+                .and(dsl::status.eq(PayoutStatus::AwaitingCosigners)),
+        )
+        .load::<Payout>(conn)?;
+
+    with_psbts(payouts, conn)
+}
+
+fn with_psbts(
+    payouts: Vec<Payout>,
+    conn: &PooledConnection,
+) -> Result<Vec<(Payout, PayoutPsbt)>, Error> {
+    use schema::payout_psbts::dsl as payout_psbts_dsl;
+
+    let mut psbts = payout_psbts_dsl::payout_psbts
+        .filter(payout_psbts_dsl::payout_id.eq_any(payouts.iter().map(|payout| payout.id)))
+        .load::<PayoutPsbt>(conn)?;
+
+    Ok(payouts
+        .into_iter()
+        .filter_map(|payout| {
+            psbts
+                .iter()
+                .position(|psbt| psbt.payout_id == payout.id)
+                .map(|i| (payout, psbts.swap_remove(i)))
+        })
+        .collect())
+}
+
+pub fn await_approval(approval: PayoutApproval, conn: &PooledConnection) -> Result<Payout, Error> {
+    use diesel::{insert_into, update};
+    use schema::{payout_approvals::dsl as payout_approvals_dsl, payouts::dsl};
+
+    // A payout whose approved transaction no longer applies is left for review again.
+    insert_into(payout_approvals_dsl::payout_approvals)
+        .values(&approval)
+        .on_conflict(payout_approvals_dsl::payout_id)
+        .do_update()
+        .set((
+            payout_approvals_dsl::unsigned_transaction.eq(&approval.unsigned_transaction),
+            payout_approvals_dsl::signed_transaction.eq(None::<String>),
+            payout_approvals_dsl::approved_by.eq(None::<Uuid>),
+            payout_approvals_dsl::approved_at.eq(None::<DateTime<Utc>>),
+            payout_approvals_dsl::created_at.eq(approval.created_at),
+        ))
+        .execute(conn)?;
+
+    update(dsl::payouts.filter(dsl::id.eq(approval.payout_id)))
+        .set(dsl::status.eq(PayoutStatus::AwaitingApproval))
+        .get_result(conn)
+        .map_err(|e| Error::from(e))
+}
+
+pub fn find_approval(id: Uuid, conn: &PooledConnection) -> Result<Option<PayoutApproval>, Error> {
+    use schema::payout_approvals::dsl;
+
+    dsl::payout_approvals
+        .filter(dsl::payout_id.eq(id))
+        .first::<PayoutApproval>(conn)
+        .optional()
+        .map_err(|e| Error::from(e))
+}
+
+pub fn find_all_awaiting_approval_by_store(
+    store_id: Uuid,
+    conn: &PooledConnection,
+) -> Result<Vec<(Payout, PayoutApproval)>, Error> {
+    use schema::{payout_approvals::dsl as payout_approvals_dsl, payouts::dsl};
+
+    let payouts = dsl::payouts
+        .filter(
+            dsl::store_id
+                .eq(store_id)
+                .and(dsl::status.eq(PayoutStatus::AwaitingApproval)),
+        )
+        .order(dsl::created_at.desc())
+        .load::<Payout>(conn)?;
+
+    let mut approvals = payout_approvals_dsl::payout_approvals
+        .filter(payout_approvals_dsl::payout_id.eq_any(payouts.iter().map(|payout| payout.id)))
+        .load::<PayoutApproval>(conn)?;
+
+    Ok(payouts
+        .into_iter()
+        .filter_map(|payout| {
+            approvals
+                .iter()
+                .position(|approval| approval.payout_id == payout.id)
+                .map(|i| (payout, approvals.swap_remove(i)))
+        })
+        .collect())
+}
+
+// Only payouts still awaiting approval can be approved, so it happens once.
+pub fn approve(
+    id: Uuid,
+    approved_by: Uuid,
+    signed_transaction: Option<String>,
+    conn: &PooledConnection,
+) -> Result<Payout, Error> {
+    use diesel::update;
+    use schema::{payout_approvals::dsl as payout_approvals_dsl, payouts::dsl};
+
+    let payout = update(
+        dsl::payouts.filter(
+            dsl::id
+                .eq(id)
+                .and(dsl::status.eq(PayoutStatus::AwaitingApproval)),
+        ),
+    )
+    .set(dsl::status.eq(PayoutStatus::Pending))
+    .get_result::<Payout>(conn)?;
+
+    update(payout_approvals_dsl::payout_approvals.filter(payout_approvals_dsl::payout_id.eq(id)))
+        .set((
+            payout_approvals_dsl::signed_transaction.eq(signed_transaction),
+            payout_approvals_dsl::approved_by.eq(Some(approved_by)),
+            payout_approvals_dsl::approved_at.eq(Some(Utc::now())),
+        ))
+        .execute(conn)?;
+
+    Ok(payout)
+}
+
+// Puts an approved payout back up for approval, e.g. when its uploaded transaction couldn't be
+// broadcast, so that it can be approved again.
+pub fn reopen_approval(id: Uuid, conn: &PooledConnection) -> Result<Payout, Error> {
+    use diesel::update;
+    use schema::{payout_approvals::dsl as payout_approvals_dsl, payouts::dsl};
+
+    update(payout_approvals_dsl::payout_approvals.filter(payout_approvals_dsl::payout_id.eq(id)))
+        .set((
+            payout_approvals_dsl::signed_transaction.eq(None::<String>),
+            payout_approvals_dsl::approved_by.eq(None::<Uuid>),
+            payout_approvals_dsl::approved_at.eq(None::<DateTime<Utc>>),
+        ))
+        .execute(conn)?;
+
+    update(dsl::payouts.filter(dsl::id.eq(id).and(dsl::status.eq(PayoutStatus::Pending))))
+        .set(dsl::status.eq(PayoutStatus::AwaitingApproval))
+        .get_result(conn)
+        .map_err(|e| Error::from(e))
+}
+
 #[derive(Message)]
-#[rtype(result = "Result<Payout, Error>")]
+#[rtype(result = "Result<Option<Payout>, Error>")]
 pub struct InsertBtc {
     pub payout_payload: PayoutPayload,
     pub payment_payload: PaymentPayload,
@@ -120,7 +547,7 @@ pub struct InsertBtc {
 }
 
 impl Handler<InsertBtc> for PgExecutor {
-    type Result = Result<Payout, Error>;
+    type Result = Result<Option<Payout>, Error>;
 
     fn handle(
         &mut self,
@@ -140,15 +567,16 @@ impl Handler<InsertBtc> for PgExecutor {
 }
 
 #[derive(Message)]
-#[rtype(result = "Result<Payout, Error>")]
+#[rtype(result = "Result<Option<Payout>, Error>")]
 pub struct InsertEth {
     pub payout_payload: PayoutPayload,
     pub payment_payload: PaymentPayload,
     pub transaction_payload: EthTransaction,
+    pub transfer_payload: EthTransfer,
 }
 
 impl Handler<InsertEth> for PgExecutor {
-    type Result = Result<Payout, Error>;
+    type Result = Result<Option<Payout>, Error>;
 
     fn handle(
         &mut self,
@@ -156,17 +584,68 @@ impl Handler<InsertEth> for PgExecutor {
             payout_payload,
             payment_payload,
             transaction_payload,
+            transfer_payload,
         }: InsertEth,
         _: &mut Self::Context,
     ) -> Self::Result {
         let conn = &self.get()?;
 
         conn.transaction::<_, Error, _>(|| {
-            insert_eth(payout_payload, payment_payload, transaction_payload, &conn)
+            insert_eth(
+                payout_payload,
+                payment_payload,
+                transaction_payload,
+                transfer_payload,
+                &conn,
+            )
         })
     }
 }
 
+#[derive(Message)]
+#[rtype(result = "Result<Payout, Error>")]
+pub struct FindById(pub Uuid);
+
+impl Handler<FindById> for PgExecutor {
+    type Result = Result<Payout, Error>;
+
+    fn handle(&mut self, FindById(id): FindById, _: &mut Self::Context) -> Self::Result {
+        let conn = &self.get()?;
+
+        find_by_id(id, &conn)
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<Vec<Payout>, Error>")]
+pub struct FindByStoreInRange {
+    pub store_id: Uuid,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub limit: i64,
+    pub after: Option<(DateTime<Utc>, Uuid)>,
+}
+
+impl Handler<FindByStoreInRange> for PgExecutor {
+    type Result = Result<Vec<Payout>, Error>;
+
+    fn handle(
+        &mut self,
+        FindByStoreInRange {
+            store_id,
+            from,
+            to,
+            limit,
+            after,
+        }: FindByStoreInRange,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        let conn = &self.get()?;
+
+        find_by_store_in_range(store_id, from, to, limit, after, &conn)
+    }
+}
+
 #[derive(Message)]
 #[rtype(result = "Result<Payout, Error>")]
 pub struct Update(pub Uuid, pub PayoutPayload);
@@ -183,32 +662,82 @@ impl Handler<Update> for PgExecutor {
 
 #[derive(Message)]
 #[rtype(result = "Result<Payout, Error>")]
-pub struct UpdateWithPayment {
+pub struct PayOut {
     pub id: Uuid,
     pub payout_payload: PayoutPayload,
     pub payment_payload: PaymentPayload,
+    pub fee: BigDecimal,
+    pub destination: Option<String>,
 }
 
-impl Handler<UpdateWithPayment> for PgExecutor {
+impl Handler<PayOut> for PgExecutor {
     type Result = Result<Payout, Error>;
 
     fn handle(
         &mut self,
-        UpdateWithPayment {
+        PayOut {
             id,
             payout_payload,
             payment_payload,
-        }: UpdateWithPayment,
+            fee,
+            destination,
+        }: PayOut,
         _: &mut Self::Context,
     ) -> Self::Result {
         let conn = &self.get()?;
 
         conn.transaction::<_, Error, _>(|| {
-            update_with_payment(id, payout_payload, payment_payload, &conn)
+            pay_out(id, payout_payload, payment_payload, fee, destination, &conn)
         })
     }
 }
 
+#[derive(Message)]
+#[rtype(result = "Result<Payout, Error>")]
+pub struct Refund {
+    pub id: Uuid,
+    pub payload: PayoutPayload,
+    pub fee: BigDecimal,
+    pub destination: Option<String>,
+}
+
+impl Handler<Refund> for PgExecutor {
+    type Result = Result<Payout, Error>;
+
+    fn handle(
+        &mut self,
+        Refund {
+            id,
+            payload,
+            fee,
+            destination,
+        }: Refund,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        let conn = &self.get()?;
+
+        conn.transaction::<_, Error, _>(|| refund(id, payload, fee, destination, &conn))
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<Vec<Payout>, Error>")]
+pub struct FindAllByPayments(pub Vec<Uuid>);
+
+impl Handler<FindAllByPayments> for PgExecutor {
+    type Result = Result<Vec<Payout>, Error>;
+
+    fn handle(
+        &mut self,
+        FindAllByPayments(payment_ids): FindAllByPayments,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        let conn = &self.get()?;
+
+        find_all_by_payments(payment_ids, &conn)
+    }
+}
+
 #[derive(Message)]
 #[rtype(result = "Result<Vec<Payout>, Error>")]
 pub struct FindAllConfirmed {
@@ -229,3 +758,569 @@ impl Handler<FindAllConfirmed> for PgExecutor {
         find_all_confirmed(block_height, typ, &conn)
     }
 }
+
+#[derive(Message)]
+#[rtype(result = "Result<Vec<Payout>, Error>")]
+pub struct MarkConfirmationNotified(pub Vec<Uuid>);
+
+impl Handler<MarkConfirmationNotified> for PgExecutor {
+    type Result = Result<Vec<Payout>, Error>;
+
+    fn handle(
+        &mut self,
+        MarkConfirmationNotified(ids): MarkConfirmationNotified,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        let conn = &self.get()?;
+
+        mark_confirmation_notified(ids, &conn)
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<Vec<Payout>, Error>")]
+pub struct FindAllConfirmedEth {
+    pub block_height: U128,
+    pub network: EthNetwork,
+}
+
+impl Handler<FindAllConfirmedEth> for PgExecutor {
+    type Result = Result<Vec<Payout>, Error>;
+
+    fn handle(
+        &mut self,
+        FindAllConfirmedEth {
+            block_height,
+            network,
+        }: FindAllConfirmedEth,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        let conn = &self.get()?;
+
+        find_all_confirmed_eth(block_height, network, &conn)
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<Vec<Payout>, Error>")]
+pub struct FindAllUnadjustedFeesEth(pub EthNetwork);
+
+impl Handler<FindAllUnadjustedFeesEth> for PgExecutor {
+    type Result = Result<Vec<Payout>, Error>;
+
+    fn handle(
+        &mut self,
+        FindAllUnadjustedFeesEth(network): FindAllUnadjustedFeesEth,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        let conn = &self.get()?;
+
+        find_all_unadjusted_fees_eth(network, &conn)
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<Payout, Error>")]
+pub struct AwaitCosigners(pub PayoutPsbt);
+
+impl Handler<AwaitCosigners> for PgExecutor {
+    type Result = Result<Payout, Error>;
+
+    fn handle(
+        &mut self,
+        AwaitCosigners(psbt): AwaitCosigners,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        let conn = &self.get()?;
+
+        conn.transaction::<_, Error, _>(|| await_cosigners(psbt, &conn))
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<Vec<(Payout, PayoutPsbt)>, Error>")]
+pub struct FindAllAwaitingCosignersByStore(pub Uuid);
+
+impl Handler<FindAllAwaitingCosignersByStore> for PgExecutor {
+    type Result = Result<Vec<(Payout, PayoutPsbt)>, Error>;
+
+    fn handle(
+        &mut self,
+        FindAllAwaitingCosignersByStore(store_id): FindAllAwaitingCosignersByStore,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        let conn = &self.get()?;
+
+        find_all_awaiting_cosigners_by_store(store_id, &conn)
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<Vec<(Payout, PayoutPsbt)>, Error>")]
+pub struct FindAllAwaitingCosigners(pub Crypto);
+
+impl Handler<FindAllAwaitingCosigners> for PgExecutor {
+    type Result = Result<Vec<(Payout, PayoutPsbt)>, Error>;
+
+    fn handle(
+        &mut self,
+        FindAllAwaitingCosigners(typ): FindAllAwaitingCosigners,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        let conn = &self.get()?;
+
+        find_all_awaiting_cosigners(typ, &conn)
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<Payout, Error>")]
+pub struct AwaitApproval(pub PayoutApproval);
+
+impl Handler<AwaitApproval> for PgExecutor {
+    type Result = Result<Payout, Error>;
+
+    fn handle(
+        &mut self,
+        AwaitApproval(approval): AwaitApproval,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        let conn = &self.get()?;
+
+        conn.transaction::<_, Error, _>(|| await_approval(approval, &conn))
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<Option<PayoutApproval>, Error>")]
+pub struct FindApproval(pub Uuid);
+
+impl Handler<FindApproval> for PgExecutor {
+    type Result = Result<Option<PayoutApproval>, Error>;
+
+    fn handle(&mut self, FindApproval(id): FindApproval, _: &mut Self::Context) -> Self::Result {
+        let conn = &self.get()?;
+
+        find_approval(id, &conn)
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<Vec<(Payout, PayoutApproval)>, Error>")]
+pub struct FindAllAwaitingApprovalByStore(pub Uuid);
+
+impl Handler<FindAllAwaitingApprovalByStore> for PgExecutor {
+    type Result = Result<Vec<(Payout, PayoutApproval)>, Error>;
+
+    fn handle(
+        &mut self,
+        FindAllAwaitingApprovalByStore(store_id): FindAllAwaitingApprovalByStore,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        let conn = &self.get()?;
+
+        find_all_awaiting_approval_by_store(store_id, &conn)
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<Payout, Error>")]
+pub struct Approve {
+    pub id: Uuid,
+    pub approved_by: Uuid,
+    pub signed_transaction: Option<String>,
+}
+
+impl Handler<Approve> for PgExecutor {
+    type Result = Result<Payout, Error>;
+
+    fn handle(
+        &mut self,
+        Approve {
+            id,
+            approved_by,
+            signed_transaction,
+        }: Approve,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        let conn = &self.get()?;
+
+        conn.transaction::<_, Error, _>(|| approve(id, approved_by, signed_transaction, &conn))
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<Payout, Error>")]
+pub struct ReopenApproval(pub Uuid);
+
+impl Handler<ReopenApproval> for PgExecutor {
+    type Result = Result<Payout, Error>;
+
+    fn handle(
+        &mut self,
+        ReopenApproval(id): ReopenApproval,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        let conn = &self.get()?;
+
+        conn.transaction::<_, Error, _>(|| reopen_approval(id, &conn))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, str::FromStr};
+
+    use diesel::{sql_query, sql_types};
+
+    use super::*;
+    use db::postgres;
+    use types::{PaymentStatus, PayoutAction};
+
+    const TXID: &str = "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b";
+
+    // These run against the migrated database in `DATABASE_URL`, which has to be set.
+    fn connection() -> PooledConnection {
+        let url = env::var("DATABASE_URL").expect("DATABASE_URL must point to a migrated database");
+
+        postgres::init_pool(&url)
+            .get()
+            .expect("failed to connect to the database")
+    }
+
+    fn insert_payment(conn: &PooledConnection) -> Uuid {
+        let id = Uuid::new_v4();
+
+        sql_query(
+            "INSERT INTO payments (id, status, store_id, index, created_by, created_at, \
+             expires_at, fiat, price, crypto, address, charge, confirmations_required, \
+             btc_network) VALUES ($1, 'pending', $1, 0, $1, now(), now() + interval '1 hour', \
+             'usd', 10, 'btc', '1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2', 0.001, 1, 'mainnet')",
+        )
+        .bind::<sql_types::Uuid, _>(id)
+        .execute(conn)
+        .unwrap();
+
+        id
+    }
+
+    fn transaction() -> BtcTransaction {
+        let hash = H256::from_str(TXID).unwrap();
+
+        BtcTransaction {
+            txid: hash,
+            hex: String::new(),
+            hash,
+            size: 0,
+            vsize: 0,
+            version: 1,
+            locktime: 0,
+            vin: Vec::new(),
+            vout: Vec::new(),
+            blockhash: None,
+            confirmations: None,
+            time: None,
+            blocktime: None,
+        }
+    }
+
+    // What the block processor inserts when crediting an output.
+    fn payloads(payment_id: Uuid, output_index: i32) -> (PayoutPayload, PaymentPayload) {
+        let hash = H256::from_str(TXID).unwrap();
+
+        let mut payout_payload = PayoutPayload::new();
+        payout_payload.status = Some(PayoutStatus::Pending);
+        payout_payload.action = Some(PayoutAction::Payout);
+        payout_payload.store_id = Some(payment_id);
+        payout_payload.payment_id = Some(payment_id);
+        payout_payload.typ = Some(Crypto::Btc);
+        payout_payload.block_height_required = Some(U128::from(100));
+        payout_payload.payment_transaction_hash = Some(Some(hash));
+        payout_payload.output_index = Some(Some(output_index));
+        payout_payload.set_created_at();
+
+        let mut payment_payload = PaymentPayload::new();
+        payment_payload.status = Some(PaymentStatus::Confirmed);
+        payment_payload.transaction_hash = Some(hash);
+        payment_payload.block_height_required = Some(U128::from(100));
+
+        (payout_payload, payment_payload)
+    }
+
+    fn count_payouts(payment_id: Uuid, conn: &PooledConnection) -> i64 {
+        use schema::payouts::dsl;
+
+        dsl::payouts
+            .filter(dsl::payment_id.eq(payment_id))
+            .count()
+            .get_result(conn)
+            .unwrap()
+    }
+
+    #[test]
+    fn processing_an_output_again_is_a_no_op() {
+        let conn = connection();
+
+        conn.test_transaction::<_, Error, _>(|| {
+            let payment_id = insert_payment(&conn);
+
+            let (payout_payload, payment_payload) = payloads(payment_id, 0);
+            assert!(insert_btc(payout_payload, payment_payload, transaction(), &conn)?.is_some());
+
+            let (payout_payload, payment_payload) = payloads(payment_id, 0);
+            assert!(insert_btc(payout_payload, payment_payload, transaction(), &conn)?.is_none());
+
+            assert_eq!(count_payouts(payment_id, &conn), 1);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn another_output_of_the_transaction_is_credited() {
+        let conn = connection();
+
+        conn.test_transaction::<_, Error, _>(|| {
+            let payment_id = insert_payment(&conn);
+
+            let (payout_payload, payment_payload) = payloads(payment_id, 0);
+            insert_btc(payout_payload, payment_payload, transaction(), &conn)?;
+
+            let (payout_payload, payment_payload) = payloads(payment_id, 1);
+            assert!(insert_btc(payout_payload, payment_payload, transaction(), &conn)?.is_some());
+
+            assert_eq!(count_payouts(payment_id, &conn), 2);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn paying_out_settles_the_ledger() {
+        let conn = connection();
+
+        conn.test_transaction::<_, Error, _>(|| {
+            let payment_id = insert_payment(&conn);
+
+            let (payout_payload, mut payment_payload) = payloads(payment_id, 0);
+            payment_payload.amount_paid = Some(BigDecimal::from_str("0.001").unwrap());
+            let payout =
+                insert_btc(payout_payload, payment_payload, transaction(), &conn)?.unwrap();
+
+            let mut payout_payload = PayoutPayload::from(payout);
+            payout_payload.status = Some(PayoutStatus::PaidOut);
+            let mut payment_payload = PaymentPayload::new();
+            payment_payload.status = Some(PaymentStatus::Completed);
+            let fee = BigDecimal::from_str("0.0001").unwrap();
+            let destination = Some(String::from("1BoatSLRHtKNngkdXEeobR76b53LETtpyT"));
+            pay_out(
+                payout.id,
+                payout_payload,
+                payment_payload,
+                fee,
+                destination.clone(),
+                &conn,
+            )?;
+
+            let balances = ledger_entries::find_balances_by_store(payment_id, &conn)?;
+            assert_eq!(balances.len(), 1);
+            assert_eq!(balances[0].earned, BigDecimal::from_str("0.001").unwrap());
+            assert_eq!(balances[0].pending, BigDecimal::from_str("0").unwrap());
+            assert_eq!(balances[0].settled, BigDecimal::from_str("0.0009").unwrap());
+            assert_eq!(
+                balances[0].network_fees,
+                BigDecimal::from_str("0.0001").unwrap()
+            );
+
+            assert!(ledger_entries::check(payment_id, &conn)?.is_empty());
+
+            let settlements = ledger_entries::find_settlements(vec![payout.id], &conn)?;
+            assert_eq!(settlements.len(), 3);
+            assert!(settlements
+                .iter()
+                .all(|entry| entry.destination == destination));
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn refunding_settles_the_ledger() {
+        let conn = connection();
+
+        conn.test_transaction::<_, Error, _>(|| {
+            let payment_id = insert_payment(&conn);
+
+            let (payout_payload, mut payment_payload) = payloads(payment_id, 0);
+            payment_payload.amount_paid = Some(BigDecimal::from_str("0.001").unwrap());
+            let payout =
+                insert_btc(payout_payload, payment_payload, transaction(), &conn)?.unwrap();
+
+            let id = payout.id;
+            let mut payload = PayoutPayload::from(payout);
+            payload.action = Some(PayoutAction::Refund);
+            payload.status = Some(PayoutStatus::Refunded);
+            refund(
+                id,
+                payload,
+                BigDecimal::from_str("0.0001").unwrap(),
+                None,
+                &conn,
+            )?;
+
+            let balances = ledger_entries::find_balances_by_store(payment_id, &conn)?;
+            assert_eq!(balances.len(), 1);
+            assert_eq!(balances[0].earned, BigDecimal::from_str("0.001").unwrap());
+            assert_eq!(balances[0].pending, BigDecimal::from_str("0").unwrap());
+            assert_eq!(balances[0].settled, BigDecimal::from_str("0").unwrap());
+            assert_eq!(
+                balances[0].refunded,
+                BigDecimal::from_str("0.0009").unwrap()
+            );
+
+            assert!(ledger_entries::check(payment_id, &conn)?.is_empty());
+
+            let settlements = ledger_entries::find_settlements(vec![id], &conn)?;
+            assert!(settlements
+                .iter()
+                .all(|entry| entry.event == LedgerEvent::Refund));
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn checking_the_ledger_reports_discrepancies() {
+        let conn = connection();
+
+        conn.test_transaction::<_, Error, _>(|| {
+            let payment_id = insert_payment(&conn);
+
+            let (payout_payload, mut payment_payload) = payloads(payment_id, 0);
+            payment_payload.amount_paid = Some(BigDecimal::from_str("0.001").unwrap());
+            let payout =
+                insert_btc(payout_payload, payment_payload, transaction(), &conn)?.unwrap();
+
+            // Marked as sent without settling, and paid more than what was credited.
+            let id = payout.id;
+            let mut payload = PayoutPayload::from(payout);
+            payload.status = Some(PayoutStatus::PaidOut);
+            update(id, payload, &conn)?;
+
+            sql_query("UPDATE payments SET amount_paid = 0.002 WHERE id = $1")
+                .bind::<sql_types::Uuid, _>(payment_id)
+                .execute(&conn)?;
+
+            let descriptions: Vec<&str> = ledger_entries::check(payment_id, &conn)?
+                .into_iter()
+                .map(|discrepancy| discrepancy.description)
+                .collect();
+            assert_eq!(
+                descriptions,
+                vec![
+                    "credited amount doesn't match the payment",
+                    "sent payout is still pending",
+                ]
+            );
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn adjusting_the_fee_keeps_the_unspent_fee_cap() {
+        let conn = connection();
+
+        conn.test_transaction::<_, Error, _>(|| {
+            let payment_id = insert_payment(&conn);
+
+            let (payout_payload, mut payment_payload) = payloads(payment_id, 0);
+            payment_payload.amount_paid = Some(BigDecimal::from_str("0.001").unwrap());
+            let payout =
+                insert_btc(payout_payload, payment_payload, transaction(), &conn)?.unwrap();
+
+            let id = payout.id;
+            let mut payout_payload = PayoutPayload::from(payout);
+            payout_payload.status = Some(PayoutStatus::PaidOut);
+            let payout = pay_out(
+                id,
+                payout_payload,
+                PaymentPayload::new(),
+                BigDecimal::from_str("0.0001").unwrap(),
+                None,
+                &conn,
+            )?;
+
+            let fee = BigDecimal::from_str("0.00004").unwrap();
+            assert_eq!(
+                ledger_entries::adjust_fee(&payout, fee.clone(), &conn)?.len(),
+                2
+            );
+            assert!(ledger_entries::adjust_fee(&payout, fee, &conn)?.is_empty());
+
+            let balances = ledger_entries::find_balances_by_store(payment_id, &conn)?;
+            assert_eq!(
+                balances[0].network_fees,
+                BigDecimal::from_str("0.00004").unwrap()
+            );
+            assert_eq!(
+                balances[0].unspent,
+                BigDecimal::from_str("0.00006").unwrap()
+            );
+
+            assert!(ledger_entries::check(payment_id, &conn)?.is_empty());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn payouts_without_an_output_match_any_output() {
+        let conn = connection();
+
+        conn.test_transaction::<_, Error, _>(|| {
+            let payment_id = insert_payment(&conn);
+
+            // Recorded before outputs were.
+            let (mut payout_payload, _) = payloads(payment_id, 0);
+            payout_payload.output_index = Some(None);
+            insert(payout_payload, &conn)?;
+
+            let (payout_payload, payment_payload) = payloads(payment_id, 3);
+            assert!(insert_btc(payout_payload, payment_payload, transaction(), &conn)?.is_none());
+
+            assert_eq!(count_payouts(payment_id, &conn), 1);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn pages_follow_the_last_payout_of_the_previous_one() {
+        let conn = connection();
+
+        conn.test_transaction::<_, Error, _>(|| {
+            let payment_id = insert_payment(&conn);
+
+            // Created at the same time, so that only ids tell them apart.
+            let (first, _) = payloads(payment_id, 0);
+            let (mut second, _) = payloads(payment_id, 1);
+            second.created_at = first.created_at;
+            insert(first, &conn)?;
+            insert(second, &conn)?;
+
+            let page = find_by_store_in_range(payment_id, None, None, 1, None, &conn)?;
+            assert_eq!(page.len(), 1);
+
+            let after = Some((page[0].created_at, page[0].id));
+            let next = find_by_store_in_range(payment_id, None, None, 1, after, &conn)?;
+            assert_eq!(next.len(), 1);
+            assert!(next[0].id > page[0].id);
+
+            let after = Some((next[0].created_at, next[0].id));
+            assert!(find_by_store_in_range(payment_id, None, None, 1, after, &conn)?.is_empty());
+
+            Ok(())
+        });
+    }
+}
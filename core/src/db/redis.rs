@@ -46,6 +46,67 @@ impl Handler<Publish> for RedisExecutor {
     }
 }
 
+// Increments a counter, starting its expiry window when the key is created. The key is created
+// along with its expiry so that it can't outlive the window if the increment fails.
+#[derive(Message)]
+#[rtype(result = "Result<u64, Error>")]
+pub struct Incr {
+    pub key: String,
+    pub expires_in: u64,
+}
+
+impl Handler<Incr> for RedisExecutor {
+    type Result = Result<u64, Error>;
+
+    fn handle(&mut self, Incr { key, expires_in }: Incr, _: &mut Self::Context) -> Self::Result {
+        let redis_conn = &self.get()?;
+
+        _redis::cmd("SET")
+            .arg(&key)
+            .arg(0)
+            .arg("EX")
+            .arg(expires_in)
+            .arg("NX")
+            .query::<()>(&**redis_conn)?;
+
+        let count: u64 = _redis::cmd("INCR").arg(&key).query(&**redis_conn)?;
+
+        Ok(count)
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<u64, Error>")]
+pub struct GetCount(pub String);
+
+impl Handler<GetCount> for RedisExecutor {
+    type Result = Result<u64, Error>;
+
+    fn handle(&mut self, GetCount(key): GetCount, _: &mut Self::Context) -> Self::Result {
+        let redis_conn = &self.get()?;
+
+        let count: Option<u64> = _redis::cmd("GET").arg(&key).query(&**redis_conn)?;
+
+        Ok(count.unwrap_or(0))
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<(), Error>")]
+pub struct Delete(pub String);
+
+impl Handler<Delete> for RedisExecutor {
+    type Result = Result<(), Error>;
+
+    fn handle(&mut self, Delete(key): Delete, _: &mut Self::Context) -> Self::Result {
+        let redis_conn = &self.get()?;
+
+        _redis::cmd("DEL").arg(&key).query::<()>(&**redis_conn)?;
+
+        Ok(())
+    }
+}
+
 impl Deref for RedisExecutor {
     type Target = RedisPool;
     fn deref(&self) -> &Self::Target {
@@ -0,0 +1,369 @@
+use actix::prelude::*;
+use chrono::prelude::*;
+use diesel::prelude::*;
+use uuid::Uuid;
+
+use db::{
+    postgres::{PgExecutor, PooledConnection},
+    Error,
+};
+use models::session::{Session, SessionPayload};
+
+pub fn insert(payload: SessionPayload, conn: &PooledConnection) -> Result<Session, Error> {
+    use diesel::insert_into;
+    use schema::sessions::dsl;
+
+    insert_into(dsl::sessions)
+        .values(&payload)
+        .get_result(conn)
+        .map_err(|e| Error::from(e))
+}
+
+pub fn find_active_by_refresh_token_hash(
+    refresh_token_hash: String,
+    conn: &PooledConnection,
+) -> Result<Session, Error> {
+    use schema::sessions::dsl;
+
+    dsl::sessions
+        .filter(
+            dsl::refresh_token_hash
+                .eq(refresh_token_hash)
+                .and(dsl::revoked_at.is_null())
+                .and(dsl::expires_at.gt(Utc::now())),
+        )
+        .first::<Session>(conn)
+        .map_err(|e| Error::from(e))
+}
+
+pub fn find_active_by_user(
+    user_id: Uuid,
+    limit: i64,
+    offset: i64,
+    conn: &PooledConnection,
+) -> Result<Vec<Session>, Error> {
+    use schema::sessions::dsl;
+
+    dsl::sessions
+        .filter(
+            dsl::user_id
+                .eq(user_id)
+                .and(dsl::revoked_at.is_null())
+                .and(dsl::expires_at.gt(Utc::now())),
+        )
+        .order(dsl::last_used_at.desc())
+        .limit(limit)
+        .offset(offset)
+        .load::<Session>(conn)
+        .map_err(|e| Error::from(e))
+}
+
+// Replaces the refresh token of a session only if it is still the one which was presented. When
+// it was already replaced, e.g. by a concurrent refresh, the token was used twice and the session
+// is revoked, yielding `None`. The replaced token is kept, to tell when it's presented again.
+pub fn rotate(
+    id: Uuid,
+    old_refresh_token_hash: String,
+    refresh_token_hash: String,
+    conn: &PooledConnection,
+) -> Result<Option<Session>, Error> {
+    use diesel::update;
+    use schema::sessions::dsl;
+
+    let mut payload = SessionPayload::new();
+    payload.refresh_token_hash = Some(refresh_token_hash);
+    payload.last_used_at = Some(Utc::now());
+
+    let session = update(
+        dsl::sessions.filter(
+            dsl::id
+                .eq(id)
+                .and(dsl::refresh_token_hash.eq(old_refresh_token_hash.clone()))
+                .and(dsl::revoked_at.is_null())
+                .and(dsl::expires_at.gt(Utc::now())),
+        ),
+    )
+    .set(&payload)
+    .get_result(conn)
+    .optional()?;
+
+    match session {
+        Some(_) => {
+            use diesel::insert_into;
+            use schema::superseded_refresh_tokens;
+
+            insert_into(superseded_refresh_tokens::table)
+                .values((
+                    superseded_refresh_tokens::refresh_token_hash.eq(old_refresh_token_hash),
+                    superseded_refresh_tokens::session_id.eq(id),
+                ))
+                .execute(conn)?;
+        }
+        None => {
+            let mut payload = SessionPayload::new();
+            payload.set_revoked();
+
+            update(dsl::sessions.filter(dsl::id.eq(id).and(dsl::revoked_at.is_null())))
+                .set(&payload)
+                .execute(conn)?;
+        }
+    }
+
+    Ok(session)
+}
+
+// Revokes the session a refresh token was replaced in. Whoever presents it again holds a copy of
+// the session's tokens, so neither the copy nor the session can be trusted anymore. Yields the
+// number of sessions revoked, none when the token was never replaced.
+pub fn revoke_by_superseded_refresh_token_hash(
+    refresh_token_hash: String,
+    conn: &PooledConnection,
+) -> Result<usize, Error> {
+    use diesel::update;
+    use schema::{sessions::dsl, superseded_refresh_tokens};
+
+    let session_id = superseded_refresh_tokens::table
+        .select(superseded_refresh_tokens::session_id)
+        .filter(superseded_refresh_tokens::refresh_token_hash.eq(refresh_token_hash))
+        .first::<Uuid>(conn)
+        .optional()?;
+
+    let session_id = match session_id {
+        Some(session_id) => session_id,
+        None => return Ok(0),
+    };
+
+    let mut payload = SessionPayload::new();
+    payload.set_revoked();
+
+    update(dsl::sessions.filter(dsl::id.eq(session_id).and(dsl::revoked_at.is_null())))
+        .set(&payload)
+        .execute(conn)
+        .map_err(|e| Error::from(e))
+}
+
+pub fn revoke(id: Uuid, user_id: Uuid, conn: &PooledConnection) -> Result<usize, Error> {
+    use diesel::update;
+    use schema::sessions::dsl;
+
+    let mut payload = SessionPayload::new();
+    payload.set_revoked();
+
+    update(
+        dsl::sessions.filter(
+            dsl::id
+                .eq(id)
+                .and(dsl::user_id.eq(user_id))
+                .and(dsl::revoked_at.is_null()),
+        ),
+    )
+    .set(&payload)
+    .execute(conn)
+    .map_err(|e| Error::from(e))
+}
+
+pub fn revoke_by_user(user_id: Uuid, conn: &PooledConnection) -> Result<usize, Error> {
+    use diesel::update;
+    use schema::sessions::dsl;
+
+    let mut payload = SessionPayload::new();
+    payload.set_revoked();
+
+    update(dsl::sessions.filter(dsl::user_id.eq(user_id).and(dsl::revoked_at.is_null())))
+        .set(&payload)
+        .execute(conn)
+        .map_err(|e| Error::from(e))
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<Session, Error>")]
+pub struct Insert(pub SessionPayload);
+
+impl Handler<Insert> for PgExecutor {
+    type Result = Result<Session, Error>;
+
+    fn handle(&mut self, Insert(payload): Insert, _: &mut Self::Context) -> Self::Result {
+        let conn = &self.get()?;
+
+        insert(payload, &conn)
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<Session, Error>")]
+pub struct FindActiveByRefreshTokenHash(pub String);
+
+impl Handler<FindActiveByRefreshTokenHash> for PgExecutor {
+    type Result = Result<Session, Error>;
+
+    fn handle(
+        &mut self,
+        FindActiveByRefreshTokenHash(refresh_token_hash): FindActiveByRefreshTokenHash,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        let conn = &self.get()?;
+
+        find_active_by_refresh_token_hash(refresh_token_hash, &conn)
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<Vec<Session>, Error>")]
+pub struct FindActiveByUser {
+    pub user_id: Uuid,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+impl Handler<FindActiveByUser> for PgExecutor {
+    type Result = Result<Vec<Session>, Error>;
+
+    fn handle(
+        &mut self,
+        FindActiveByUser {
+            user_id,
+            limit,
+            offset,
+        }: FindActiveByUser,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        let conn = &self.get()?;
+
+        find_active_by_user(user_id, limit, offset, &conn)
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<Option<Session>, Error>")]
+pub struct Rotate {
+    pub id: Uuid,
+    pub old_refresh_token_hash: String,
+    pub refresh_token_hash: String,
+}
+
+impl Handler<Rotate> for PgExecutor {
+    type Result = Result<Option<Session>, Error>;
+
+    fn handle(
+        &mut self,
+        Rotate {
+            id,
+            old_refresh_token_hash,
+            refresh_token_hash,
+        }: Rotate,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        let conn = &self.get()?;
+
+        conn.transaction::<_, Error, _>(|| {
+            rotate(id, old_refresh_token_hash, refresh_token_hash, &conn)
+        })
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<usize, Error>")]
+pub struct RevokeBySupersededRefreshTokenHash(pub String);
+
+impl Handler<RevokeBySupersededRefreshTokenHash> for PgExecutor {
+    type Result = Result<usize, Error>;
+
+    fn handle(
+        &mut self,
+        RevokeBySupersededRefreshTokenHash(refresh_token_hash): RevokeBySupersededRefreshTokenHash,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        let conn = &self.get()?;
+
+        revoke_by_superseded_refresh_token_hash(refresh_token_hash, &conn)
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<usize, Error>")]
+pub struct Revoke {
+    pub id: Uuid,
+    pub user_id: Uuid,
+}
+
+impl Handler<Revoke> for PgExecutor {
+    type Result = Result<usize, Error>;
+
+    fn handle(&mut self, Revoke { id, user_id }: Revoke, _: &mut Self::Context) -> Self::Result {
+        let conn = &self.get()?;
+
+        revoke(id, user_id, &conn)
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<usize, Error>")]
+pub struct RevokeByUser(pub Uuid);
+
+impl Handler<RevokeByUser> for PgExecutor {
+    type Result = Result<usize, Error>;
+
+    fn handle(
+        &mut self,
+        RevokeByUser(user_id): RevokeByUser,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        let conn = &self.get()?;
+
+        revoke_by_user(user_id, &conn)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use chrono::Duration;
+
+    use super::*;
+    use db::postgres;
+
+    // These run against the migrated database in `DATABASE_URL`, which has to be set.
+    fn connection() -> PooledConnection {
+        let url = env::var("DATABASE_URL").expect("DATABASE_URL must point to a migrated database");
+
+        postgres::init_pool(&url)
+            .get()
+            .expect("failed to connect to the database")
+    }
+
+    fn insert_session(refresh_token_hash: &str, conn: &PooledConnection) -> Session {
+        let mut payload = SessionPayload::new();
+        payload.user_id = Some(Uuid::new_v4());
+        payload.refresh_token_hash = Some(refresh_token_hash.to_owned());
+        payload.set_created_at();
+        payload.expires_at = Some(Utc::now() + Duration::days(1));
+
+        insert(payload, conn).unwrap()
+    }
+
+    #[test]
+    fn replaying_a_superseded_refresh_token_revokes_the_session() {
+        let conn = connection();
+        let old_hash = Uuid::new_v4().to_string();
+        let new_hash = Uuid::new_v4().to_string();
+        let session = insert_session(&old_hash, &conn);
+
+        let rotated = rotate(session.id, old_hash.clone(), new_hash.clone(), &conn).unwrap();
+        assert!(rotated.is_some());
+        assert!(find_active_by_refresh_token_hash(old_hash.clone(), &conn).is_err());
+        assert!(find_active_by_refresh_token_hash(new_hash.clone(), &conn).is_ok());
+
+        let revoked = revoke_by_superseded_refresh_token_hash(old_hash, &conn);
+        assert_eq!(revoked.unwrap(), 1);
+        assert!(find_active_by_refresh_token_hash(new_hash, &conn).is_err());
+    }
+
+    #[test]
+    fn unknown_refresh_tokens_revoke_nothing() {
+        let conn = connection();
+
+        let revoked = revoke_by_superseded_refresh_token_hash(Uuid::new_v4().to_string(), &conn);
+        assert_eq!(revoked.unwrap(), 0);
+    }
+}
@@ -2,7 +2,7 @@ use actix::prelude::*;
 use diesel::prelude::*;
 
 use db::{
-    client_tokens,
+    client_tokens, payout_address_changes,
     {
         postgres::{PgExecutor, PooledConnection},
         Error,
@@ -11,14 +11,21 @@ use db::{
 use models::store::{Store, StorePayload};
 use uuid::Uuid;
 
-pub fn insert(payload: StorePayload, conn: &PooledConnection) -> Result<Store, Error> {
+pub fn insert(
+    payload: StorePayload,
+    xpubs: Vec<(String, String)>,
+    conn: &PooledConnection,
+) -> Result<Store, Error> {
     use diesel::insert_into;
     use schema::stores::dsl;
 
-    insert_into(dsl::stores)
-        .values(&payload)
-        .get_result(conn)
-        .map_err(|e| Error::from(e))
+    let store: Store = insert_into(dsl::stores).values(&payload).get_result(conn)?;
+
+    for (hd_path, xpub) in xpubs {
+        insert_xpub(store.id, hd_path, xpub, conn)?;
+    }
+
+    Ok(store)
 }
 
 pub fn update(id: Uuid, payload: StorePayload, conn: &PooledConnection) -> Result<Store, Error> {
@@ -87,6 +94,7 @@ pub fn soft_delete(id: Uuid, conn: &PooledConnection) -> Result<usize, Error> {
         .map_err(|e| Error::from(e))?;
 
     client_tokens::delete_by_store_id(id, conn)?;
+    payout_address_changes::cancel_pending_by_store(id, conn)?;
 
     Ok(1)
 }
@@ -105,22 +113,136 @@ pub fn soft_delete_by_owner_id(owner_id: Uuid, conn: &PooledConnection) -> Resul
 
     for store in deleted_stores {
         client_tokens::delete_by_store_id(store.id, conn)?;
+        payout_address_changes::cancel_pending_by_store(store.id, conn)?;
     }
 
     Ok(1)
 }
 
+// Hands out the next address index of the store's account at `hd_path`, starting at 1. The
+// counter is bumped by a single upsert, so concurrent payments never share an index.
+pub fn next_address_index(
+    store_id: Uuid,
+    hd_path: String,
+    conn: &PooledConnection,
+) -> Result<i32, Error> {
+    use diesel::insert_into;
+    use schema::store_address_indexes::dsl;
+
+    insert_into(dsl::store_address_indexes)
+        .values((
+            dsl::store_id.eq(store_id),
+            dsl::hd_path.eq(hd_path),
+            dsl::next_index.eq(2),
+        ))
+        .on_conflict((dsl::store_id, dsl::hd_path))
+        .do_update()
+        .set(dsl::next_index.eq(dsl::next_index + 1))
+        .returning(dsl::next_index)
+        .get_result::<i32>(conn)
+        .map(|next_index| next_index - 1)
+        .map_err(|e| Error::from(e))
+}
+
+// Keeps the first xpub of an account, those of stores created before xpubs were recorded being
+// added as invoices are created.
+pub fn insert_xpub(
+    store_id: Uuid,
+    hd_path: String,
+    xpub: String,
+    conn: &PooledConnection,
+) -> Result<(), Error> {
+    use diesel::insert_into;
+    use schema::store_xpubs::dsl;
+
+    insert_into(dsl::store_xpubs)
+        .values((
+            dsl::store_id.eq(store_id),
+            dsl::hd_path.eq(hd_path),
+            dsl::xpub.eq(xpub),
+        ))
+        .on_conflict_do_nothing()
+        .execute(conn)
+        .map(|_| ())
+        .map_err(|e| Error::from(e))
+}
+
+pub fn find_xpub(
+    store_id: Uuid,
+    hd_path: String,
+    conn: &PooledConnection,
+) -> Result<Option<String>, Error> {
+    use schema::store_xpubs::dsl;
+
+    dsl::store_xpubs
+        .filter(dsl::store_id.eq(store_id).and(dsl::hd_path.eq(hd_path)))
+        .select(dsl::xpub)
+        .first::<String>(conn)
+        .optional()
+        .map_err(|e| Error::from(e))
+}
+
 #[derive(Message)]
 #[rtype(result = "Result<Store, Error>")]
-pub struct Insert(pub StorePayload);
+pub struct Insert {
+    pub payload: StorePayload,
+    pub xpubs: Vec<(String, String)>,
+}
 
 impl Handler<Insert> for PgExecutor {
     type Result = Result<Store, Error>;
 
-    fn handle(&mut self, Insert(payload): Insert, _: &mut Self::Context) -> Self::Result {
+    fn handle(&mut self, Insert { payload, xpubs }: Insert, _: &mut Self::Context) -> Self::Result {
+        let conn = &self.get()?;
+
+        conn.transaction::<_, Error, _>(|| insert(payload, xpubs, &conn))
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<(), Error>")]
+pub struct InsertXpub {
+    pub store_id: Uuid,
+    pub hd_path: String,
+    pub xpub: String,
+}
+
+impl Handler<InsertXpub> for PgExecutor {
+    type Result = Result<(), Error>;
+
+    fn handle(
+        &mut self,
+        InsertXpub {
+            store_id,
+            hd_path,
+            xpub,
+        }: InsertXpub,
+        _: &mut Self::Context,
+    ) -> Self::Result {
         let conn = &self.get()?;
 
-        insert(payload, &conn)
+        insert_xpub(store_id, hd_path, xpub, &conn)
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<Option<String>, Error>")]
+pub struct FindXpub {
+    pub store_id: Uuid,
+    pub hd_path: String,
+}
+
+impl Handler<FindXpub> for PgExecutor {
+    type Result = Result<Option<String>, Error>;
+
+    fn handle(
+        &mut self,
+        FindXpub { store_id, hd_path }: FindXpub,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        let conn = &self.get()?;
+
+        find_xpub(store_id, hd_path, &conn)
     }
 }
 
@@ -199,6 +321,27 @@ impl Handler<FindByOwner> for PgExecutor {
     }
 }
 
+#[derive(Message)]
+#[rtype(result = "Result<i32, Error>")]
+pub struct NextAddressIndex {
+    pub store_id: Uuid,
+    pub hd_path: String,
+}
+
+impl Handler<NextAddressIndex> for PgExecutor {
+    type Result = Result<i32, Error>;
+
+    fn handle(
+        &mut self,
+        NextAddressIndex { store_id, hd_path }: NextAddressIndex,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        let conn = &self.get()?;
+
+        next_address_index(store_id, hd_path, &conn)
+    }
+}
+
 #[derive(Message)]
 #[rtype(result = "Result<usize, Error>")]
 pub struct SoftDelete(pub Uuid);
@@ -212,3 +355,106 @@ impl Handler<SoftDelete> for PgExecutor {
         conn.transaction::<_, Error, _>(|| soft_delete(id, &conn))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use bigdecimal::BigDecimal;
+    use chrono::{prelude::*, Duration};
+    use diesel::{sql_query, sql_types};
+
+    use super::*;
+    use db::{payments, postgres};
+    use models::payment::{Payment, PaymentPayload};
+    use types::{
+        currency::{Crypto, Fiat},
+        PaymentStatus,
+    };
+
+    const HD_PATH: &str = "m/44'/60'/0'/0";
+
+    // These run against the migrated database in `DATABASE_URL`, which has to be set.
+    fn connection() -> PooledConnection {
+        let url = env::var("DATABASE_URL").expect("DATABASE_URL must point to a migrated database");
+
+        postgres::init_pool(&url)
+            .get()
+            .expect("failed to connect to the database")
+    }
+
+    fn insert_store(conn: &PooledConnection) -> Uuid {
+        let id = Uuid::new_v4();
+
+        sql_query(
+            "INSERT INTO stores (id, name, description, owner_id, private_key, public_key, \
+             created_at, updated_at, hd_path) VALUES ($1, 'store', '', $1, '', '', now(), now(), \
+             'm/44''/60''/0''/0')",
+        )
+        .bind::<sql_types::Uuid, _>(id)
+        .execute(conn)
+        .unwrap();
+
+        id
+    }
+
+    // An invoice of the store at `index` that expired unpaid.
+    fn insert_payment(
+        store_id: Uuid,
+        index: i32,
+        conn: &PooledConnection,
+    ) -> Result<Payment, Error> {
+        let mut payload = PaymentPayload::new();
+        payload.status = Some(PaymentStatus::Pending);
+        payload.store_id = Some(store_id);
+        payload.index = Some(index);
+        payload.created_by = Some(store_id);
+        payload.set_created_at();
+        payload.expires_at = Some(Utc::now() - Duration::hours(1));
+        payload.fiat = Some(Fiat::Usd);
+        payload.price = Some(BigDecimal::from(10));
+        payload.crypto = Some(Crypto::Eth);
+        payload.address = Some(format!("{}/{}", store_id, index));
+        payload.charge = Some(BigDecimal::from(1));
+        payload.confirmations_required = Some(1);
+        payload.hd_path = Some(HD_PATH.to_owned());
+
+        payments::insert(payload, conn)
+    }
+
+    #[test]
+    fn address_indexes_count_up_per_account() {
+        let conn = connection();
+        let store_id = insert_store(&conn);
+
+        assert_eq!(
+            next_address_index(store_id, HD_PATH.to_owned(), &conn).unwrap(),
+            1
+        );
+        assert_eq!(
+            next_address_index(store_id, HD_PATH.to_owned(), &conn).unwrap(),
+            2
+        );
+        assert_eq!(
+            next_address_index(store_id, "m/44'/0'/0'/0".to_owned(), &conn).unwrap(),
+            1
+        );
+    }
+
+    #[test]
+    fn expired_invoices_keep_their_address_indexes() {
+        let conn = connection();
+        let store_id = insert_store(&conn);
+
+        for _ in 0..30 {
+            let index = next_address_index(store_id, HD_PATH.to_owned(), &conn).unwrap();
+            insert_payment(store_id, index, &conn).unwrap();
+        }
+
+        assert_eq!(
+            next_address_index(store_id, HD_PATH.to_owned(), &conn).unwrap(),
+            31
+        );
+        assert!(insert_payment(store_id, 30, &conn).is_err());
+    }
+}
@@ -3,7 +3,7 @@ use chrono::prelude::*;
 use diesel::prelude::*;
 use uuid::Uuid;
 
-use db::{Error, stores, postgres::{PgExecutor, PooledConnection}};
+use db::{Error, sessions, stores, postgres::{PgExecutor, PooledConnection}};
 use models::user::{User, UserPayload};
 
 pub fn insert(payload: UserPayload, conn: &PooledConnection) -> Result<User, Error> {
@@ -86,6 +86,7 @@ pub fn delete(id: Uuid, conn: &PooledConnection) -> Result<usize, Error> {
         .map_err(|e| Error::from(e))?;
 
     stores::soft_delete_by_owner_id(id, &conn)?;
+    sessions::revoke_by_user(id, &conn)?;
 
     Ok(1)
 }
@@ -32,5 +32,7 @@ pub mod db;
 mod models;
 
 pub use models::{
-    bitcoin, client_token, ethereum, payment, payout, store, user, voucher, Error as ModelError,
+    audit_event, bitcoin, block_rescan, client_token, ethereum, export, ledger_entry,
+    notification_preference, payment, payout, payout_address_change, session, store, user, voucher,
+    Error as ModelError,
 };
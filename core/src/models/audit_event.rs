@@ -0,0 +1,116 @@
+use chrono::prelude::*;
+use futures::Future;
+use serde_json::Value;
+use uuid::Uuid;
+
+use db::{
+    audit_events::{Audited, FindByStore, Insert},
+    postgres::{PgExecutorAddr, PooledConnection},
+    Error as DbError,
+};
+use models::Error;
+use schema::audit_events;
+use types::AuditAction;
+
+#[derive(Debug, Insertable, Clone)]
+#[table_name = "audit_events"]
+pub struct AuditEventPayload {
+    pub store_id: Option<Uuid>,
+    pub actor_id: Option<Uuid>,
+    pub ip_address: Option<String>,
+    pub action: AuditAction,
+    pub old_values: Option<Value>,
+    pub new_values: Option<Value>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+impl AuditEventPayload {
+    pub fn new(action: AuditAction, actor_id: Option<Uuid>, ip_address: Option<String>) -> Self {
+        AuditEventPayload {
+            store_id: None,
+            actor_id,
+            ip_address,
+            action,
+            old_values: None,
+            new_values: None,
+            created_at: None,
+        }
+    }
+
+    pub fn set_created_at(&mut self) {
+        self.created_at = Some(Utc::now());
+    }
+}
+
+#[derive(Debug, Identifiable, Queryable, Serialize, Clone)]
+pub struct AuditEvent {
+    pub id: Uuid,
+    pub store_id: Option<Uuid>,
+    pub actor_id: Option<Uuid>,
+    pub ip_address: Option<String>,
+    pub action: AuditAction,
+    pub old_values: Option<Value>,
+    pub new_values: Option<Value>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl AuditEvent {
+    pub fn insert(
+        mut payload: AuditEventPayload,
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = AuditEvent, Error = Error> {
+        payload.set_created_at();
+
+        (*postgres)
+            .send(Insert(payload))
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+    }
+
+    pub fn find_by_store(
+        store_id: Uuid,
+        limit: i64,
+        offset: i64,
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = Vec<AuditEvent>, Error = Error> {
+        (*postgres)
+            .send(FindByStore {
+                store_id,
+                limit,
+                offset,
+            })
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+    }
+
+    // Runs the mutation and records the event about it in one transaction. See
+    // `db::audit_events::Audited`.
+    pub fn audited<F, E, T>(
+        mutate: F,
+        event: E,
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = T, Error = Error>
+    where
+        F: FnOnce(&PooledConnection) -> Result<T, DbError> + Send + 'static,
+        E: FnOnce(&T) -> AuditEventPayload + Send + 'static,
+        T: Send + 'static,
+    {
+        (*postgres)
+            .send(Audited(mutate, event))
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+    }
+
+    pub fn export(&self) -> Value {
+        json!({
+            "id": self.id,
+            "store_id": self.store_id,
+            "actor_id": self.actor_id,
+            "ip_address": self.ip_address,
+            "action": self.action,
+            "old_values": self.old_values,
+            "new_values": self.new_values,
+            "created_at": self.created_at.timestamp(),
+        })
+    }
+}
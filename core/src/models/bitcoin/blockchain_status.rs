@@ -1,24 +1,26 @@
 use futures::Future;
 
 use db::{
-    bitcoin::blockchain_statuses::{FindByNetwork, Insert, Update},
+    bitcoin::blockchain_statuses::{FindByChain, Insert, Update},
     postgres::PgExecutorAddr,
 };
 use models::Error;
 use schema::btc_blockchain_statuses;
-use types::{bitcoin::Network, U128};
+use types::{bitcoin::Network, currency::Crypto, U128};
 
 #[derive(Insertable, AsChangeset, Deserialize)]
 #[table_name = "btc_blockchain_statuses"]
 pub struct BlockchainStatusPayload {
     pub network: Option<Network>,
     pub block_height: Option<U128>,
+    pub crypto: Option<Crypto>,
 }
 
 #[derive(Queryable, Serialize)]
 pub struct BlockchainStatus {
     pub network: Network,
     pub block_height: U128,
+    pub crypto: Crypto,
 }
 
 impl BlockchainStatus {
@@ -33,22 +35,28 @@ impl BlockchainStatus {
     }
 
     pub fn find(
+        crypto: Crypto,
         network: Network,
         postgres: &PgExecutorAddr,
     ) -> impl Future<Item = BlockchainStatus, Error = Error> {
         (*postgres)
-            .send(FindByNetwork(network))
+            .send(FindByChain { crypto, network })
             .from_err()
             .and_then(|res| res.map_err(|e| Error::from(e)))
     }
 
     pub fn update(
+        crypto: Crypto,
         network: Network,
         payload: BlockchainStatusPayload,
         postgres: &PgExecutorAddr,
     ) -> impl Future<Item = BlockchainStatus, Error = Error> {
         (*postgres)
-            .send(Update { network, payload })
+            .send(Update {
+                crypto,
+                network,
+                payload,
+            })
             .from_err()
             .and_then(|res| res.map_err(|e| Error::from(e)))
     }
@@ -4,4 +4,7 @@ mod transaction;
 
 pub use self::block::Block;
 pub use self::blockchain_status::{BlockchainStatus, BlockchainStatusPayload};
-pub use self::transaction::{ScriptType, SignedTransactionOutput, Transaction};
+pub use self::transaction::{
+    ScriptType, SignedTransactionInput, SignedTransactionOutput, Transaction,
+    TransactionInputScript, TransactionOutputScript,
+};
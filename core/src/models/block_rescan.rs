@@ -0,0 +1,136 @@
+use chrono::prelude::*;
+use futures::Future;
+use serde_json::Value;
+use uuid::Uuid;
+
+use db::{
+    block_rescans::{FindById, FindNext, Insert, Update},
+    postgres::PgExecutorAddr,
+};
+use models::Error;
+use schema::block_rescans;
+use types::{currency::Crypto, ethereum::Network as EthNetwork, RescanStatus, U128};
+
+#[derive(Debug, Insertable, AsChangeset)]
+#[table_name = "block_rescans"]
+pub struct BlockRescanPayload {
+    pub crypto: Option<Crypto>,
+    pub eth_network: Option<Option<EthNetwork>>,
+    pub from_block: Option<U128>,
+    pub to_block: Option<U128>,
+    pub next_block: Option<U128>,
+    pub status: Option<RescanStatus>,
+    pub error: Option<Option<String>>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl BlockRescanPayload {
+    pub fn new() -> Self {
+        BlockRescanPayload {
+            crypto: None,
+            eth_network: None,
+            from_block: None,
+            to_block: None,
+            next_block: None,
+            status: None,
+            error: None,
+            created_at: None,
+            updated_at: None,
+        }
+    }
+
+    pub fn set_created_at(&mut self) {
+        self.created_at = Some(Utc::now());
+    }
+
+    pub fn set_updated_at(&mut self) {
+        self.updated_at = Some(Utc::now());
+    }
+}
+
+// A range of blocks to process again, picked up by the rescanner of its chain. Blocks are
+// replayed without moving the chain's processed tip.
+#[derive(Debug, Identifiable, Queryable, Clone)]
+pub struct BlockRescan {
+    pub id: Uuid,
+    pub crypto: Crypto,
+    pub eth_network: Option<EthNetwork>,
+    pub from_block: U128,
+    pub to_block: U128,
+    pub next_block: U128,
+    pub status: RescanStatus,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl BlockRescan {
+    pub fn insert(
+        mut payload: BlockRescanPayload,
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = BlockRescan, Error = Error> {
+        payload.next_block = payload.from_block;
+        payload.status = Some(RescanStatus::Pending);
+        payload.set_created_at();
+        payload.set_updated_at();
+
+        (*postgres)
+            .send(Insert(payload))
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+    }
+
+    pub fn update(
+        id: Uuid,
+        mut payload: BlockRescanPayload,
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = BlockRescan, Error = Error> {
+        payload.set_updated_at();
+
+        (*postgres)
+            .send(Update(id, payload))
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+    }
+
+    pub fn find_by_id(
+        id: Uuid,
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = BlockRescan, Error = Error> {
+        (*postgres)
+            .send(FindById(id))
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+    }
+
+    // The oldest unfinished rescan of the chain, an interrupted one included.
+    pub fn find_next(
+        crypto: Crypto,
+        eth_network: Option<EthNetwork>,
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = Option<BlockRescan>, Error = Error> {
+        (*postgres)
+            .send(FindNext {
+                crypto,
+                eth_network,
+            })
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+    }
+
+    pub fn export(&self) -> Value {
+        json!({
+            "id": self.id,
+            "crypto": self.crypto,
+            "chain_id": self.eth_network.map(|network| network.0),
+            "from_block": format!("{}", self.from_block),
+            "to_block": format!("{}", self.to_block),
+            "next_block": format!("{}", self.next_block),
+            "status": self.status,
+            "error": self.error,
+            "created_at": self.created_at,
+            "updated_at": self.updated_at,
+        })
+    }
+}
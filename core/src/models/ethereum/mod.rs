@@ -1,7 +1,9 @@
 mod block;
 mod blockchain_status;
 mod transaction;
+mod transfer;
 
 pub use self::block::Block;
 pub use self::blockchain_status::{BlockchainStatus, BlockchainStatusPayload};
 pub use self::transaction::Transaction;
+pub use self::transfer::Transfer;
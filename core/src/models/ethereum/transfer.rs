@@ -0,0 +1,42 @@
+use futures::Future;
+
+use db::{ethereum::transfers::FindByOutput, postgres::PgExecutorAddr};
+use models::{ethereum::Transaction, Error};
+use schema::eth_transfers;
+use types::{H160, H256, U256};
+
+// Ether received by an address in a transaction. Besides the value of the transaction itself,
+// contract calls nested in it can make several transfers, each paying out on its own.
+#[derive(Debug, Insertable, Queryable, Clone, Eq, PartialEq, Hash)]
+#[table_name = "eth_transfers"]
+pub struct Transfer {
+    pub transaction_hash: H256,
+    pub output_index: i32,
+    pub from_address: H160,
+    pub to_address: H160,
+    pub value: U256,
+}
+
+impl Transfer {
+    // The value of the transaction, unless it creates a contract.
+    pub fn from_transaction(transaction: &Transaction) -> Option<Transfer> {
+        transaction.to_address.map(|to_address| Transfer {
+            transaction_hash: transaction.hash,
+            output_index: 0,
+            from_address: transaction.from_address,
+            to_address,
+            value: transaction.value,
+        })
+    }
+
+    pub fn find_by_output(
+        transaction_hash: H256,
+        output_index: i32,
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = Transfer, Error = Error> {
+        (*postgres)
+            .send(FindByOutput(transaction_hash, output_index))
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+    }
+}
@@ -0,0 +1,175 @@
+use chrono::{prelude::*, Duration};
+use futures::Future;
+use serde_json::Value;
+use uuid::Uuid;
+
+use db::{
+    exports::{DeleteExpired, FailStale, FindById, Insert, Update},
+    postgres::PgExecutorAddr,
+};
+use models::Error;
+use schema::exports;
+use types::{ExportFormat, ExportKind};
+
+// Days a generated export can be downloaded for.
+const EXPORT_TTL_DAYS: i64 = 7;
+
+#[derive(Debug, Insertable, AsChangeset)]
+#[table_name = "exports"]
+pub struct ExportPayload {
+    pub store_id: Option<Uuid>,
+    pub requested_by: Option<Uuid>,
+    pub kind: Option<ExportKind>,
+    pub format: Option<ExportFormat>,
+    pub from_at: Option<Option<DateTime<Utc>>>,
+    pub to_at: Option<Option<DateTime<Utc>>>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub completed_at: Option<Option<DateTime<Utc>>>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub failed_at: Option<Option<DateTime<Utc>>>,
+}
+
+impl ExportPayload {
+    pub fn new() -> Self {
+        ExportPayload {
+            store_id: None,
+            requested_by: None,
+            kind: None,
+            format: None,
+            from_at: None,
+            to_at: None,
+            created_at: None,
+            completed_at: None,
+            expires_at: None,
+            failed_at: None,
+        }
+    }
+
+    pub fn set_created_at(&mut self) {
+        self.created_at = Some(Utc::now());
+    }
+
+    pub fn set_expires_at(&mut self) {
+        self.expires_at = Some(Utc::now() + Duration::days(EXPORT_TTL_DAYS));
+    }
+}
+
+// Name of the file an export made at `created_at` is downloaded as.
+pub fn file_name(kind: ExportKind, format: ExportFormat, created_at: DateTime<Utc>) -> String {
+    format!(
+        "{}-{}.{}",
+        kind.to_str(),
+        created_at.format("%Y%m%d%H%M%S"),
+        format.to_str()
+    )
+}
+
+// A payments or payouts export generated in the background into a file named after its id, whose
+// download link is emailed to the user who asked for it.
+#[derive(Debug, Identifiable, Queryable, Clone)]
+pub struct Export {
+    pub id: Uuid,
+    pub store_id: Uuid,
+    pub requested_by: Uuid,
+    pub kind: ExportKind,
+    pub format: ExportFormat,
+    pub from_at: Option<DateTime<Utc>>,
+    pub to_at: Option<DateTime<Utc>>,
+    pub download_token: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub expires_at: DateTime<Utc>,
+    pub failed_at: Option<DateTime<Utc>>,
+}
+
+impl Export {
+    pub fn insert(
+        mut payload: ExportPayload,
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = Export, Error = Error> {
+        payload.set_created_at();
+        payload.set_expires_at();
+
+        (*postgres)
+            .send(Insert(payload))
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+    }
+
+    pub fn complete(
+        id: Uuid,
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = Export, Error = Error> {
+        let mut payload = ExportPayload::new();
+        payload.completed_at = Some(Some(Utc::now()));
+        payload.set_expires_at();
+
+        (*postgres)
+            .send(Update(id, payload))
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+    }
+
+    pub fn fail(id: Uuid, postgres: &PgExecutorAddr) -> impl Future<Item = Export, Error = Error> {
+        let mut payload = ExportPayload::new();
+        payload.failed_at = Some(Some(Utc::now()));
+
+        (*postgres)
+            .send(Update(id, payload))
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+    }
+
+    // Marks the exports still generating since before `started_before` as failed, yielding them.
+    pub fn fail_stale(
+        started_before: DateTime<Utc>,
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = Vec<Export>, Error = Error> {
+        (*postgres)
+            .send(FailStale(started_before))
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+    }
+
+    // Yields the deleted exports, whose files are left to be removed.
+    pub fn delete_expired(
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = Vec<Export>, Error = Error> {
+        (*postgres)
+            .send(DeleteExpired)
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+    }
+
+    pub fn find_by_id(
+        id: Uuid,
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = Export, Error = Error> {
+        (*postgres)
+            .send(FindById(id))
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at < Utc::now()
+    }
+
+    pub fn file_name(&self) -> String {
+        file_name(self.kind, self.format, self.created_at)
+    }
+
+    pub fn export(&self) -> Value {
+        json!({
+            "id": self.id,
+            "kind": self.kind,
+            "format": self.format,
+            "from": self.from_at.map(|from_at| from_at.timestamp()),
+            "to": self.to_at.map(|to_at| to_at.timestamp()),
+            "created_at": self.created_at.timestamp(),
+            "completed_at": self.completed_at.map(|completed_at| completed_at.timestamp()),
+            "expires_at": self.expires_at.timestamp(),
+            "failed_at": self.failed_at.map(|failed_at| failed_at.timestamp()),
+        })
+    }
+}
@@ -0,0 +1,151 @@
+use bigdecimal::{BigDecimal, Zero};
+use chrono::prelude::*;
+use futures::Future;
+use serde_json::Value;
+use uuid::Uuid;
+
+use db::{
+    ledger_entries::{AdjustFee, Check, FindBalancesByStore, FindByStore, FindSettlements},
+    postgres::PgExecutorAddr,
+};
+use models::{payout::Payout, Error};
+use schema::ledger_entries;
+use types::{currency::Crypto, LedgerAccount, LedgerEvent};
+
+#[derive(Debug, Insertable)]
+#[table_name = "ledger_entries"]
+pub struct LedgerEntryPayload {
+    pub store_id: Uuid,
+    pub typ: Crypto,
+    pub payment_id: Uuid,
+    pub payout_id: Uuid,
+    pub event: LedgerEvent,
+    pub account: LedgerAccount,
+    pub amount: BigDecimal,
+    pub created_at: DateTime<Utc>,
+    pub destination: Option<String>,
+}
+
+// A posting of a store's double-entry ledger, made along with the payout it is about.
+#[derive(Debug, Identifiable, Queryable, Serialize, Clone)]
+#[table_name = "ledger_entries"]
+pub struct LedgerEntry {
+    pub id: Uuid,
+    pub store_id: Uuid,
+    pub typ: Crypto,
+    pub payment_id: Uuid,
+    pub payout_id: Uuid,
+    pub event: LedgerEvent,
+    pub account: LedgerAccount,
+    pub amount: BigDecimal,
+    pub created_at: DateTime<Utc>,
+    pub destination: Option<String>,
+}
+
+// The balances of a store's ledger accounts in a currency, earnings being what customers paid.
+#[derive(Debug, Serialize)]
+pub struct Balance {
+    pub typ: Crypto,
+    pub earned: BigDecimal,
+    pub pending: BigDecimal,
+    pub settled: BigDecimal,
+    pub refunded: BigDecimal,
+    pub network_fees: BigDecimal,
+    pub unspent: BigDecimal,
+}
+
+impl Balance {
+    pub fn new(typ: Crypto) -> Self {
+        Balance {
+            typ,
+            earned: BigDecimal::zero(),
+            pending: BigDecimal::zero(),
+            settled: BigDecimal::zero(),
+            refunded: BigDecimal::zero(),
+            network_fees: BigDecimal::zero(),
+            unspent: BigDecimal::zero(),
+        }
+    }
+}
+
+// A payout whose postings don't agree with `payments` and `payouts`.
+#[derive(Debug, Serialize)]
+pub struct Discrepancy {
+    pub payout_id: Uuid,
+    pub payment_id: Uuid,
+    pub description: &'static str,
+}
+
+impl LedgerEntry {
+    pub fn find_by_store(
+        store_id: Uuid,
+        limit: i64,
+        offset: i64,
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = Vec<LedgerEntry>, Error = Error> {
+        (*postgres)
+            .send(FindByStore {
+                store_id,
+                limit,
+                offset,
+            })
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+    }
+
+    pub fn find_settlements(
+        payout_ids: Vec<Uuid>,
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = Vec<LedgerEntry>, Error = Error> {
+        (*postgres)
+            .send(FindSettlements(payout_ids))
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+    }
+
+    // The fee is in units of the currency, as spent by the payout's transaction.
+    pub fn adjust_fee(
+        payout: Payout,
+        fee: BigDecimal,
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = Vec<LedgerEntry>, Error = Error> {
+        (*postgres)
+            .send(AdjustFee { payout, fee })
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+    }
+
+    pub fn find_balances_by_store(
+        store_id: Uuid,
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = Vec<Balance>, Error = Error> {
+        (*postgres)
+            .send(FindBalancesByStore(store_id))
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+    }
+
+    pub fn check(
+        store_id: Uuid,
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = Vec<Discrepancy>, Error = Error> {
+        (*postgres)
+            .send(Check(store_id))
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+    }
+
+    pub fn export(&self) -> Value {
+        json!({
+            "id": self.id,
+            "currency": self.typ,
+            "payment_id": self.payment_id,
+            "payout_id": self.payout_id,
+            "event": self.event,
+            "account": self.account,
+            "amount": self.amount,
+            "created_at": self.created_at.timestamp(),
+            "destination": self.destination,
+        })
+    }
+}
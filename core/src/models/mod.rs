@@ -2,11 +2,18 @@ mod errors;
 
 pub use self::errors::Error;
 
+pub mod audit_event;
 pub mod bitcoin;
+pub mod block_rescan;
 pub mod client_token;
 pub mod ethereum;
+pub mod export;
+pub mod ledger_entry;
+pub mod notification_preference;
 pub mod payment;
 pub mod payout;
+pub mod payout_address_change;
+pub mod session;
 pub mod store;
 pub mod user;
 pub mod voucher;
@@ -0,0 +1,116 @@
+use chrono::prelude::*;
+use futures::Future;
+use serde_json::Value;
+use uuid::Uuid;
+
+use db::{
+    notification_preferences::{FindByStore, Upsert},
+    postgres::PgExecutorAddr,
+};
+use models::{store::Store, Error};
+use schema::notification_preferences;
+
+#[derive(Debug, Insertable, AsChangeset, Deserialize, Clone)]
+#[table_name = "notification_preferences"]
+pub struct NotificationPreferencePayload {
+    pub store_id: Option<Uuid>,
+    pub email: Option<Option<String>>,
+    pub payment_confirmed: Option<bool>,
+    pub insufficient_amount: Option<bool>,
+    pub payout_sent: Option<bool>,
+    pub payout_failed: Option<bool>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl NotificationPreferencePayload {
+    pub fn new() -> Self {
+        NotificationPreferencePayload {
+            store_id: None,
+            email: None,
+            payment_confirmed: None,
+            insufficient_amount: None,
+            payout_sent: None,
+            payout_failed: None,
+            created_at: None,
+            updated_at: None,
+        }
+    }
+
+    pub fn set_created_at(&mut self) {
+        self.created_at = Some(Utc::now());
+    }
+
+    pub fn set_updated_at(&mut self) {
+        self.updated_at = Some(Utc::now());
+    }
+}
+
+#[derive(Debug, Identifiable, Queryable, Associations, Serialize, Clone)]
+#[belongs_to(Store, foreign_key = "store_id")]
+#[primary_key(store_id)]
+pub struct NotificationPreference {
+    pub store_id: Uuid,
+    pub email: Option<String>,
+    pub payment_confirmed: bool,
+    pub insufficient_amount: bool,
+    pub payout_sent: bool,
+    pub payout_failed: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl NotificationPreference {
+    // Stores which never saved their preferences get every notification, sent to the owner.
+    pub fn default_for(store_id: Uuid) -> Self {
+        NotificationPreference {
+            store_id,
+            email: None,
+            payment_confirmed: true,
+            insufficient_amount: true,
+            payout_sent: true,
+            payout_failed: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    pub fn upsert(
+        store_id: Uuid,
+        mut payload: NotificationPreferencePayload,
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = NotificationPreference, Error = Error> {
+        payload.store_id = Some(store_id);
+        payload.set_updated_at();
+
+        (*postgres)
+            .send(Upsert(payload))
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+    }
+
+    pub fn find_by_store(
+        store_id: Uuid,
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = NotificationPreference, Error = Error> {
+        (*postgres)
+            .send(FindByStore(store_id))
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+            .map(move |preference| {
+                preference.unwrap_or_else(|| NotificationPreference::default_for(store_id))
+            })
+    }
+
+    pub fn export(&self) -> Value {
+        json!({
+            "store_id": self.store_id,
+            "email": self.email,
+            "payment_confirmed": self.payment_confirmed,
+            "insufficient_amount": self.insufficient_amount,
+            "payout_sent": self.payout_sent,
+            "payout_failed": self.payout_failed,
+            "updated_at": self.updated_at.timestamp(),
+        })
+    }
+}
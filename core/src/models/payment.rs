@@ -7,7 +7,10 @@ use serde_json::Value;
 use uuid::Uuid;
 
 use db::{
-    payments::{FindAllByAddress, FindById, Insert, Update},
+    payments::{
+        FindAllByAddress, FindAllByEthAddress, FindAllPendingByCrypto, FindAllUnpaidSince,
+        FindAllWithAddressByStore, FindById, FindByStoreInRange, Insert, Update,
+    },
     postgres::PgExecutorAddr,
 };
 use models::{store::Store, Error};
@@ -19,6 +22,9 @@ use types::{
     PaymentStatus, H256, U128,
 };
 
+// Seconds a payment can be paid in.
+pub const EXPIRES_IN: i64 = 3600;
+
 #[derive(Debug, Insertable, AsChangeset, Serialize, Clone)]
 #[table_name = "payments"]
 pub struct PaymentPayload {
@@ -41,6 +47,10 @@ pub struct PaymentPayload {
     pub btc_network: Option<BtcNetwork>,
     pub eth_network: Option<EthNetwork>,
     pub identifier: Option<String>,
+    pub ln_payment_hash: Option<String>,
+    pub hd_path: Option<String>,
+    pub witness_script: Option<String>,
+    pub discrepancy_flagged_at: Option<DateTime<Utc>>,
 }
 
 impl PaymentPayload {
@@ -65,6 +75,10 @@ impl PaymentPayload {
             btc_network: None,
             eth_network: None,
             identifier: None,
+            ln_payment_hash: None,
+            hd_path: None,
+            witness_script: None,
+            discrepancy_flagged_at: None,
         }
     }
 
@@ -77,7 +91,7 @@ impl PaymentPayload {
     }
 
     pub fn set_expires_at(&mut self) {
-        self.expires_at = Some(Utc::now() + Duration::seconds(3600))
+        self.expires_at = Some(Utc::now() + Duration::seconds(EXPIRES_IN))
     }
 }
 
@@ -103,6 +117,10 @@ impl From<Payment> for PaymentPayload {
             btc_network: payment.btc_network,
             eth_network: payment.eth_network,
             identifier: payment.identifier,
+            ln_payment_hash: payment.ln_payment_hash,
+            hd_path: payment.hd_path,
+            witness_script: payment.witness_script,
+            discrepancy_flagged_at: payment.discrepancy_flagged_at,
         }
     }
 }
@@ -141,9 +159,33 @@ pub struct Payment {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub eth_network: Option<EthNetwork>,
     pub identifier: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ln_payment_hash: Option<String>,
+    #[serde(skip_serializing)]
+    pub hd_path: Option<String>,
+    // Hex encoded script of multisig addresses, for cosigners to check.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub witness_script: Option<String>,
+    // Set once coins found on chain for the unpaid payment were recorded in the audit log.
+    #[serde(skip_serializing)]
+    pub discrepancy_flagged_at: Option<DateTime<Utc>>,
 }
 
 impl Payment {
+    // Path of the account the payment's address is derived from. Payments created before
+    // sequential indexes used a path made of `legacy_hd_path` and their creation time.
+    pub fn derivation_path(&self, legacy_hd_path: &str) -> String {
+        match self.hd_path {
+            Some(ref hd_path) => hd_path.clone(),
+            None => format!(
+                "{}/{}/{}",
+                legacy_hd_path,
+                self.created_at.timestamp(),
+                self.created_at.timestamp_subsec_micros()
+            ),
+        }
+    }
+
     pub fn insert(
         mut payload: PaymentPayload,
         postgres: &PgExecutorAddr,
@@ -196,7 +238,125 @@ impl Payment {
             .and_then(|res| res.map_err(|e| Error::from(e)))
     }
 
+    // The same address can receive funds on every EVM network, so lookups are scoped to one.
+    pub fn find_all_by_eth_address(
+        addresses: Vec<String>,
+        network: EthNetwork,
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = Vec<Payment>, Error = Error> {
+        (*postgres)
+            .send(FindAllByEthAddress { addresses, network })
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+    }
+
+    pub fn find_all_pending(
+        crypto: Crypto,
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = Vec<Payment>, Error = Error> {
+        (*postgres)
+            .send(FindAllPendingByCrypto(crypto))
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+    }
+
+    // Payments created since the given time which no transaction was seen for, expired ones
+    // included.
+    pub fn find_all_unpaid_since(
+        crypto: Crypto,
+        since: DateTime<Utc>,
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = Vec<Payment>, Error = Error> {
+        (*postgres)
+            .send(FindAllUnpaidSince { crypto, since })
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+    }
+
+    pub fn find_all_with_address_by_store(
+        store_id: Uuid,
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = Vec<Payment>, Error = Error> {
+        (*postgres)
+            .send(FindAllWithAddressByStore(store_id))
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+    }
+
+    pub fn find_by_store_in_range(
+        store_id: Uuid,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+        limit: i64,
+        after: Option<(DateTime<Utc>, Uuid)>,
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = Vec<Payment>, Error = Error> {
+        (*postgres)
+            .send(FindByStoreInRange {
+                store_id,
+                from,
+                to,
+                limit,
+                after,
+            })
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+    }
+
     pub fn export(&self) -> Value {
         serde_json::to_value(self).unwrap()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payment(hd_path: Option<String>) -> Payment {
+        let created_at = Utc.timestamp(1_561_000_000, 123_456_000);
+
+        Payment {
+            id: Uuid::new_v4(),
+            status: PaymentStatus::Pending,
+            store_id: Uuid::new_v4(),
+            index: 3,
+            created_by: Uuid::new_v4(),
+            created_at,
+            expires_at: created_at + Duration::seconds(EXPIRES_IN),
+            paid_at: None,
+            amount_paid: None,
+            transaction_hash: None,
+            fiat: Fiat::Usd,
+            price: BigDecimal::from(10),
+            crypto: Crypto::Eth,
+            address: "0x3535353535353535353535353535353535353535".to_owned(),
+            charge: BigDecimal::from(1),
+            confirmations_required: 1,
+            block_height_required: None,
+            btc_network: None,
+            eth_network: Some(EthNetwork(1)),
+            identifier: None,
+            ln_payment_hash: None,
+            hd_path,
+            witness_script: None,
+            discrepancy_flagged_at: None,
+        }
+    }
+
+    #[test]
+    fn derivation_path_of_indexed_payments() {
+        let payment = payment(Some("m/44'/60'/0'/0".to_owned()));
+
+        assert_eq!(payment.derivation_path("m/44'/60'/0'"), "m/44'/60'/0'/0");
+    }
+
+    #[test]
+    fn derivation_path_of_legacy_payments() {
+        let payment = payment(None);
+
+        assert_eq!(
+            payment.derivation_path("m/44'/60'/0'"),
+            "m/44'/60'/0'/1561000000/123456"
+        );
+    }
+}
@@ -6,15 +6,28 @@ use futures::Future;
 use uuid::Uuid;
 
 use db::{
-    payouts::{FindAllConfirmed, InsertBtc, InsertEth, Update, UpdateWithPayment},
+    payouts::{
+        Approve, AwaitApproval, AwaitCosigners, FindAllAwaitingApprovalByStore,
+        FindAllAwaitingCosigners, FindAllAwaitingCosignersByStore, FindAllByPayments,
+        FindAllConfirmed, FindAllConfirmedEth, FindAllUnadjustedFeesEth, FindApproval, FindById,
+        FindByStoreInRange, InsertBtc, InsertEth, MarkConfirmationNotified, PayOut, Refund,
+        ReopenApproval, Update,
+    },
     postgres::PgExecutorAddr,
 };
 use models::{
-    bitcoin::Transaction as BtcTransaction, ethereum::Transaction as EthTransaction,
-    payment::Payment, payment::PaymentPayload, store::Store, Error,
+    bitcoin::Transaction as BtcTransaction,
+    ethereum::{Transaction as EthTransaction, Transfer as EthTransfer},
+    payment::Payment,
+    payment::PaymentPayload,
+    store::Store,
+    Error,
+};
+use schema::{payout_approvals, payout_psbts, payouts};
+use types::{
+    currency::Crypto, ethereum::Network as EthNetwork, PaymentStatus, PayoutAction, PayoutStatus,
+    H256, U128,
 };
-use schema::payouts;
-use types::{currency::Crypto, PaymentStatus, PayoutAction, PayoutStatus, H256, U128};
 
 #[derive(Debug, Insertable, AsChangeset, Serialize)]
 #[table_name = "payouts"]
@@ -27,6 +40,9 @@ pub struct PayoutPayload {
     pub block_height_required: Option<U128>,
     pub transaction_hash: Option<Option<H256>>,
     pub created_at: Option<DateTime<Utc>>,
+    pub payment_transaction_hash: Option<Option<H256>>,
+    pub output_index: Option<Option<i32>>,
+    pub confirmation_notified_at: Option<Option<DateTime<Utc>>>,
 }
 
 impl PayoutPayload {
@@ -40,6 +56,9 @@ impl PayoutPayload {
             block_height_required: None,
             transaction_hash: None,
             created_at: None,
+            payment_transaction_hash: None,
+            output_index: None,
+            confirmation_notified_at: None,
         }
     }
 
@@ -59,6 +78,9 @@ impl From<Payout> for PayoutPayload {
             block_height_required: Some(payout.block_height_required),
             transaction_hash: Some(payout.transaction_hash),
             created_at: Some(payout.created_at),
+            payment_transaction_hash: Some(payout.payment_transaction_hash),
+            output_index: Some(payout.output_index),
+            confirmation_notified_at: Some(payout.confirmation_notified_at),
         }
     }
 }
@@ -76,6 +98,33 @@ pub struct Payout {
     pub block_height_required: U128,
     pub transaction_hash: Option<H256>,
     pub created_at: DateTime<Utc>,
+    // The transaction output which paid the payment, a payment being credited once per output.
+    // Ethereum transactions have a single one, at index 0.
+    pub payment_transaction_hash: Option<H256>,
+    pub output_index: Option<i32>,
+    pub confirmation_notified_at: Option<DateTime<Utc>>,
+}
+
+// A multisig payout Finch signed its part of, for the store's cosigners to complete.
+#[derive(Debug, Insertable, Queryable, Serialize)]
+#[table_name = "payout_psbts"]
+pub struct PayoutPsbt {
+    pub payout_id: Uuid,
+    pub psbt: String,
+    pub created_at: DateTime<Utc>,
+}
+
+// A payout of a store with manual approval, left for review until an authorized user approves it
+// or uploads a transaction signed elsewhere.
+#[derive(Debug, Insertable, Queryable, Serialize)]
+#[table_name = "payout_approvals"]
+pub struct PayoutApproval {
+    pub payout_id: Uuid,
+    pub unsigned_transaction: String,
+    pub signed_transaction: Option<String>,
+    pub approved_by: Option<Uuid>,
+    pub approved_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
 }
 
 impl Payout {
@@ -87,13 +136,15 @@ impl Payout {
         Payment::find_by_id(self.payment_id, postgres)
     }
 
+    // Yields `None` when the output was already credited, e.g. when a block is processed again.
     pub fn insert_btc_payout(
         amount_paid: BigDecimal,
         block_height_required: U128,
         payment: Payment,
         transaction: BtcTransaction,
+        output_index: u32,
         postgres: &PgExecutorAddr,
-    ) -> impl Future<Item = Payout, Error = Error> {
+    ) -> impl Future<Item = Option<Payout>, Error = Error> {
         let mut payment_payload = PaymentPayload::from(payment.clone());
         payment_payload.transaction_hash = Some(transaction.hash);
         payment_payload.block_height_required = Some(block_height_required);
@@ -104,8 +155,10 @@ impl Payout {
         payout_payload.status = Some(PayoutStatus::Pending);
         payout_payload.store_id = Some(payment.store_id);
         payout_payload.payment_id = Some(payment.id);
-        payout_payload.typ = Some(Crypto::Btc);
+        payout_payload.typ = Some(payment.crypto);
         payout_payload.block_height_required = Some(block_height_required);
+        payout_payload.payment_transaction_hash = Some(Some(transaction.hash));
+        payout_payload.output_index = Some(Some(output_index as i32));
         payout_payload.set_created_at();
 
         let charge = payment.charge;
@@ -122,7 +175,6 @@ impl Payout {
                     payment_payload.status = Some(PaymentStatus::InsufficientAmount);
                     payout_payload.action = Some(PayoutAction::Refund);
                 }
-
             }
             _ => payout_payload.action = Some(PayoutAction::Refund),
         };
@@ -137,13 +189,16 @@ impl Payout {
             .and_then(|res| res.map_err(|e| Error::from(e)))
     }
 
+    // Yields `None` when the transaction was already credited, e.g. when a block is processed
+    // again.
     pub fn insert_eth_payout(
         amount_paid: BigDecimal,
         block_height_required: U128,
         payment: Payment,
         transaction: EthTransaction,
+        transfer: EthTransfer,
         postgres: &PgExecutorAddr,
-    ) -> impl Future<Item = Payout, Error = Error> {
+    ) -> impl Future<Item = Option<Payout>, Error = Error> {
         let mut payment_payload = PaymentPayload::from(payment.clone());
         payment_payload.transaction_hash = Some(transaction.hash);
         payment_payload.block_height_required = Some(block_height_required);
@@ -156,6 +211,8 @@ impl Payout {
         payout_payload.payment_id = Some(payment.id);
         payout_payload.typ = Some(Crypto::Eth);
         payout_payload.block_height_required = Some(block_height_required);
+        payout_payload.payment_transaction_hash = Some(Some(transaction.hash));
+        payout_payload.output_index = Some(Some(transfer.output_index));
         payout_payload.set_created_at();
 
         let charge = payment.charge;
@@ -172,7 +229,6 @@ impl Payout {
                     payment_payload.status = Some(PaymentStatus::InsufficientAmount);
                     payout_payload.action = Some(PayoutAction::Refund);
                 }
-
             }
             _ => payout_payload.action = Some(PayoutAction::Refund),
         };
@@ -182,11 +238,52 @@ impl Payout {
                 payout_payload,
                 payment_payload,
                 transaction_payload: transaction,
+                transfer_payload: transfer,
             })
             .from_err()
             .and_then(|res| res.map_err(|e| Error::from(e)))
     }
 
+    pub fn find_by_id(
+        id: Uuid,
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = Payout, Error = Error> {
+        (*postgres)
+            .send(FindById(id))
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+    }
+
+    pub fn find_by_store_in_range(
+        store_id: Uuid,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+        limit: i64,
+        after: Option<(DateTime<Utc>, Uuid)>,
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = Vec<Payout>, Error = Error> {
+        (*postgres)
+            .send(FindByStoreInRange {
+                store_id,
+                from,
+                to,
+                limit,
+                after,
+            })
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+    }
+
+    pub fn find_all_by_payments(
+        payment_ids: Vec<Uuid>,
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = Vec<Payout>, Error = Error> {
+        (*postgres)
+            .send(FindAllByPayments(payment_ids))
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+    }
+
     pub fn find_all_confirmed(
         block_height: U128,
         typ: Crypto,
@@ -198,6 +295,41 @@ impl Payout {
             .and_then(|res| res.map_err(|e| Error::from(e)))
     }
 
+    pub fn find_all_confirmed_eth(
+        block_height: U128,
+        network: EthNetwork,
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = Vec<Payout>, Error = Error> {
+        (*postgres)
+            .send(FindAllConfirmedEth {
+                block_height,
+                network,
+            })
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+    }
+
+    pub fn find_all_unadjusted_fees_eth(
+        network: EthNetwork,
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = Vec<Payout>, Error = Error> {
+        (*postgres)
+            .send(FindAllUnadjustedFeesEth(network))
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+    }
+
+    // Yields the payouts whose merchant wasn't notified of the confirmation yet.
+    pub fn mark_confirmation_notified(
+        ids: Vec<Uuid>,
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = Vec<Payout>, Error = Error> {
+        (*postgres)
+            .send(MarkConfirmationNotified(ids))
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+    }
+
     pub fn update(
         id: Uuid,
         payload: PayoutPayload,
@@ -209,19 +341,146 @@ impl Payout {
             .and_then(|res| res.map_err(|e| Error::from(e)))
     }
 
-    pub fn update_with_payment(
+    // The network fee is in units of the currency, and posted to the store's ledger.
+    pub fn pay_out(
         id: Uuid,
         payout_payload: PayoutPayload,
         payment_payload: PaymentPayload,
+        fee: BigDecimal,
+        destination: Option<String>,
         postgres: &PgExecutorAddr,
     ) -> impl Future<Item = Payout, Error = Error> {
         (*postgres)
-            .send(UpdateWithPayment {
+            .send(PayOut {
                 id,
                 payout_payload,
                 payment_payload,
+                fee,
+                destination,
+            })
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+    }
+
+    pub fn refund(
+        id: Uuid,
+        payload: PayoutPayload,
+        fee: BigDecimal,
+        destination: Option<String>,
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = Payout, Error = Error> {
+        (*postgres)
+            .send(Refund {
+                id,
+                payload,
+                fee,
+                destination,
+            })
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+    }
+
+    pub fn await_cosigners(
+        id: Uuid,
+        psbt: String,
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = Payout, Error = Error> {
+        let psbt = PayoutPsbt {
+            payout_id: id,
+            psbt,
+            created_at: Utc::now(),
+        };
+
+        (*postgres)
+            .send(AwaitCosigners(psbt))
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+    }
+
+    pub fn find_all_awaiting_cosigners_by_store(
+        store_id: Uuid,
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = Vec<(Payout, PayoutPsbt)>, Error = Error> {
+        (*postgres)
+            .send(FindAllAwaitingCosignersByStore(store_id))
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+    }
+
+    pub fn find_all_awaiting_cosigners(
+        typ: Crypto,
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = Vec<(Payout, PayoutPsbt)>, Error = Error> {
+        (*postgres)
+            .send(FindAllAwaitingCosigners(typ))
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+    }
+
+    pub fn await_approval(
+        id: Uuid,
+        unsigned_transaction: String,
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = Payout, Error = Error> {
+        let approval = PayoutApproval {
+            payout_id: id,
+            unsigned_transaction,
+            signed_transaction: None,
+            approved_by: None,
+            approved_at: None,
+            created_at: Utc::now(),
+        };
+
+        (*postgres)
+            .send(AwaitApproval(approval))
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+    }
+
+    pub fn find_approval(
+        id: Uuid,
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = Option<PayoutApproval>, Error = Error> {
+        (*postgres)
+            .send(FindApproval(id))
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+    }
+
+    pub fn find_all_awaiting_approval_by_store(
+        store_id: Uuid,
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = Vec<(Payout, PayoutApproval)>, Error = Error> {
+        (*postgres)
+            .send(FindAllAwaitingApprovalByStore(store_id))
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+    }
+
+    // Hands the payout back to the payouter, which broadcasts `signed_transaction` when given.
+    pub fn approve(
+        id: Uuid,
+        approved_by: Uuid,
+        signed_transaction: Option<String>,
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = Payout, Error = Error> {
+        (*postgres)
+            .send(Approve {
+                id,
+                approved_by,
+                signed_transaction,
             })
             .from_err()
             .and_then(|res| res.map_err(|e| Error::from(e)))
     }
+
+    pub fn reopen_approval(
+        id: Uuid,
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = Payout, Error = Error> {
+        (*postgres)
+            .send(ReopenApproval(id))
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+    }
 }
@@ -0,0 +1,233 @@
+use chrono::{prelude::*, Duration};
+use futures::Future;
+use serde_json::Value;
+use uuid::Uuid;
+
+use db::{
+    payout_address_changes::{
+        ApplyDue, Cancel, CancelByToken, Confirm, FindById, FindByStore, Insert, Schedule,
+    },
+    postgres::PgExecutorAddr,
+};
+use models::{store::Store, Error};
+use schema::payout_address_changes;
+use types::{bitcoin::Address as BtcAddress, H160};
+
+#[derive(Debug, Insertable, AsChangeset)]
+#[table_name = "payout_address_changes"]
+pub struct PayoutAddressChangePayload {
+    pub store_id: Option<Uuid>,
+    pub eth_payout_addresses: Option<Option<Vec<H160>>>,
+    pub btc_payout_addresses: Option<Option<Vec<BtcAddress>>>,
+    pub confirmation_token: Option<Uuid>,
+    pub confirmation_token_expires_at: Option<DateTime<Utc>>,
+    pub confirmed_at: Option<Option<DateTime<Utc>>>,
+    pub effective_at: Option<Option<DateTime<Utc>>>,
+    pub applied_at: Option<Option<DateTime<Utc>>>,
+    pub cancelled_at: Option<Option<DateTime<Utc>>>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub ltc_payout_addresses: Option<Option<Vec<BtcAddress>>>,
+    pub bch_payout_addresses: Option<Option<Vec<BtcAddress>>>,
+    pub doge_payout_addresses: Option<Option<Vec<BtcAddress>>>,
+    pub btc_payout_multisig_threshold: Option<Option<i32>>,
+    pub btc_payout_multisig_public_keys: Option<Option<Vec<String>>>,
+    pub btc_invoice_multisig_threshold: Option<Option<i32>>,
+    pub btc_invoice_multisig_xpubs: Option<Option<Vec<String>>>,
+    pub manual_payout_approval: Option<Option<bool>>,
+    pub cancellation_token: Option<Uuid>,
+}
+
+impl PayoutAddressChangePayload {
+    pub fn new() -> Self {
+        PayoutAddressChangePayload {
+            store_id: None,
+            eth_payout_addresses: None,
+            btc_payout_addresses: None,
+            confirmation_token: None,
+            confirmation_token_expires_at: None,
+            confirmed_at: None,
+            effective_at: None,
+            applied_at: None,
+            cancelled_at: None,
+            created_at: None,
+            ltc_payout_addresses: None,
+            bch_payout_addresses: None,
+            doge_payout_addresses: None,
+            btc_payout_multisig_threshold: None,
+            btc_payout_multisig_public_keys: None,
+            btc_invoice_multisig_threshold: None,
+            btc_invoice_multisig_xpubs: None,
+            manual_payout_approval: None,
+            cancellation_token: None,
+        }
+    }
+
+    pub fn set_created_at(&mut self) {
+        self.created_at = Some(Utc::now());
+    }
+
+    // The cancellation token doesn't expire, the change being cancellable until applied.
+    pub fn set_tokens(&mut self) {
+        self.confirmation_token = Some(Uuid::new_v4());
+        self.confirmation_token_expires_at = Some(Utc::now() + Duration::days(1));
+        self.cancellation_token = Some(Uuid::new_v4());
+    }
+}
+
+#[derive(Debug, Identifiable, Queryable, Associations, Serialize, Clone)]
+#[belongs_to(Store, foreign_key = "store_id")]
+pub struct PayoutAddressChange {
+    pub id: Uuid,
+    pub store_id: Uuid,
+    pub eth_payout_addresses: Option<Vec<H160>>,
+    pub btc_payout_addresses: Option<Vec<BtcAddress>>,
+    pub confirmation_token: Uuid,
+    pub confirmation_token_expires_at: DateTime<Utc>,
+    pub confirmed_at: Option<DateTime<Utc>>,
+    pub effective_at: Option<DateTime<Utc>>,
+    pub applied_at: Option<DateTime<Utc>>,
+    pub cancelled_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub ltc_payout_addresses: Option<Vec<BtcAddress>>,
+    pub bch_payout_addresses: Option<Vec<BtcAddress>>,
+    pub doge_payout_addresses: Option<Vec<BtcAddress>>,
+    pub btc_payout_multisig_threshold: Option<i32>,
+    pub btc_payout_multisig_public_keys: Option<Vec<String>>,
+    pub btc_invoice_multisig_threshold: Option<i32>,
+    pub btc_invoice_multisig_xpubs: Option<Vec<String>>,
+    pub manual_payout_approval: Option<bool>,
+    pub cancellation_token: Uuid,
+}
+
+impl PayoutAddressChange {
+    pub fn status(&self) -> &str {
+        if self.cancelled_at.is_some() {
+            return "cancelled";
+        }
+
+        if self.applied_at.is_some() {
+            return "applied";
+        }
+
+        if self.confirmed_at.is_some() {
+            return "time_locked";
+        }
+
+        "pending_confirmation"
+    }
+
+    // Any change still waiting for confirmation or its time-lock for the same store is cancelled.
+    pub fn insert(
+        mut payload: PayoutAddressChangePayload,
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = PayoutAddressChange, Error = Error> {
+        payload.set_created_at();
+        payload.set_tokens();
+
+        (*postgres)
+            .send(Insert(payload))
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+    }
+
+    pub fn find_by_id(
+        id: Uuid,
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = PayoutAddressChange, Error = Error> {
+        (*postgres)
+            .send(FindById(id))
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+    }
+
+    pub fn find_by_store(
+        store_id: Uuid,
+        limit: i64,
+        offset: i64,
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = Vec<PayoutAddressChange>, Error = Error> {
+        (*postgres)
+            .send(FindByStore {
+                store_id,
+                limit,
+                offset,
+            })
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+    }
+
+    // Its time-lock only starts with `schedule`, once the owner was told about the confirmation.
+    pub fn confirm(
+        token: Uuid,
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = PayoutAddressChange, Error = Error> {
+        (*postgres)
+            .send(Confirm(token))
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+    }
+
+    // Starts the time-lock of the confirmed change, applying it right away when there's none.
+    pub fn schedule(
+        id: Uuid,
+        time_lock: Duration,
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = PayoutAddressChange, Error = Error> {
+        (*postgres)
+            .send(Schedule { id, time_lock })
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+    }
+
+    pub fn cancel(
+        id: Uuid,
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = PayoutAddressChange, Error = Error> {
+        (*postgres)
+            .send(Cancel(id))
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+    }
+
+    pub fn cancel_by_token(
+        token: Uuid,
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = PayoutAddressChange, Error = Error> {
+        (*postgres)
+            .send(CancelByToken(token))
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+    }
+
+    pub fn apply_due(
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = Vec<PayoutAddressChange>, Error = Error> {
+        (*postgres)
+            .send(ApplyDue)
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+    }
+
+    pub fn export(&self) -> Value {
+        json!({
+            "id": self.id,
+            "store_id": self.store_id,
+            "eth_payout_addresses": self.eth_payout_addresses,
+            "btc_payout_addresses": self.btc_payout_addresses,
+            "ltc_payout_addresses": self.ltc_payout_addresses,
+            "bch_payout_addresses": self.bch_payout_addresses,
+            "doge_payout_addresses": self.doge_payout_addresses,
+            "btc_payout_multisig_threshold": self.btc_payout_multisig_threshold,
+            "btc_payout_multisig_public_keys": self.btc_payout_multisig_public_keys,
+            "btc_invoice_multisig_threshold": self.btc_invoice_multisig_threshold,
+            "btc_invoice_multisig_xpubs": self.btc_invoice_multisig_xpubs,
+            "manual_payout_approval": self.manual_payout_approval,
+            "status": self.status(),
+            "confirmed_at": self.confirmed_at.map(|confirmed_at| confirmed_at.timestamp()),
+            "effective_at": self.effective_at.map(|effective_at| effective_at.timestamp()),
+            "applied_at": self.applied_at.map(|applied_at| applied_at.timestamp()),
+            "cancelled_at": self.cancelled_at.map(|cancelled_at| cancelled_at.timestamp()),
+            "created_at": self.created_at.timestamp(),
+        })
+    }
+}
@@ -0,0 +1,166 @@
+use chrono::prelude::*;
+use futures::Future;
+use serde_json::Value;
+use uuid::Uuid;
+
+use db::{
+    postgres::PgExecutorAddr,
+    sessions::{
+        FindActiveByRefreshTokenHash, FindActiveByUser, Insert, Revoke,
+        RevokeBySupersededRefreshTokenHash, RevokeByUser, Rotate,
+    },
+};
+use models::{user::User, Error};
+use schema::sessions;
+
+#[derive(Debug, Insertable, AsChangeset)]
+#[table_name = "sessions"]
+pub struct SessionPayload {
+    pub user_id: Option<Uuid>,
+    pub refresh_token_hash: Option<String>,
+    pub ip_address: Option<Option<String>>,
+    pub user_agent: Option<Option<String>>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<Option<DateTime<Utc>>>,
+}
+
+impl SessionPayload {
+    pub fn new() -> Self {
+        SessionPayload {
+            user_id: None,
+            refresh_token_hash: None,
+            ip_address: None,
+            user_agent: None,
+            created_at: None,
+            last_used_at: None,
+            expires_at: None,
+            revoked_at: None,
+        }
+    }
+
+    pub fn set_created_at(&mut self) {
+        self.created_at = Some(Utc::now());
+        self.last_used_at = Some(Utc::now());
+    }
+
+    pub fn set_revoked(&mut self) {
+        self.revoked_at = Some(Some(Utc::now()));
+    }
+}
+
+#[derive(Debug, Identifiable, Queryable, Associations, Clone)]
+#[belongs_to(User, foreign_key = "user_id")]
+pub struct Session {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub refresh_token_hash: String,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl Session {
+    pub fn insert(
+        mut payload: SessionPayload,
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = Session, Error = Error> {
+        payload.set_created_at();
+
+        (*postgres)
+            .send(Insert(payload))
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+    }
+
+    pub fn find_active_by_refresh_token_hash(
+        refresh_token_hash: String,
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = Session, Error = Error> {
+        (*postgres)
+            .send(FindActiveByRefreshTokenHash(refresh_token_hash))
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+    }
+
+    pub fn find_active_by_user(
+        user_id: Uuid,
+        limit: i64,
+        offset: i64,
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = Vec<Session>, Error = Error> {
+        (*postgres)
+            .send(FindActiveByUser {
+                user_id,
+                limit,
+                offset,
+            })
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+    }
+
+    // Replaces the refresh token of an active session, so each refresh token can only be used once.
+    // Yields `None` when the old token was already used, the session being revoked then.
+    pub fn rotate(
+        id: Uuid,
+        old_refresh_token_hash: String,
+        refresh_token_hash: String,
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = Option<Session>, Error = Error> {
+        (*postgres)
+            .send(Rotate {
+                id,
+                old_refresh_token_hash,
+                refresh_token_hash,
+            })
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+    }
+
+    // Revokes the session a refresh token was rotated out of, yielding how many were revoked.
+    pub fn revoke_by_superseded_refresh_token_hash(
+        refresh_token_hash: String,
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = usize, Error = Error> {
+        (*postgres)
+            .send(RevokeBySupersededRefreshTokenHash(refresh_token_hash))
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+    }
+
+    pub fn revoke(
+        id: Uuid,
+        user_id: Uuid,
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = usize, Error = Error> {
+        (*postgres)
+            .send(Revoke { id, user_id })
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+    }
+
+    pub fn revoke_by_user(
+        user_id: Uuid,
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = usize, Error = Error> {
+        (*postgres)
+            .send(RevokeByUser(user_id))
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+    }
+
+    pub fn export(&self) -> Value {
+        json!({
+            "id": self.id,
+            "ip_address": self.ip_address,
+            "user_agent": self.user_agent,
+            "created_at": self.created_at.timestamp(),
+            "last_used_at": self.last_used_at.timestamp(),
+            "expires_at": self.expires_at.timestamp(),
+        })
+    }
+}
@@ -1,5 +1,6 @@
 use std::convert::From;
 
+use bigdecimal::BigDecimal;
 use chrono::prelude::*;
 use futures::Future;
 use serde_json::Value;
@@ -7,11 +8,17 @@ use uuid::Uuid;
 
 use db::{
     postgres::PgExecutorAddr,
-    stores::{FindById, FindByIdWithDeleted, FindByOwner, Insert, SoftDelete, Update},
+    stores::{
+        FindById, FindByIdWithDeleted, FindByOwner, FindXpub, Insert, InsertXpub, NextAddressIndex,
+        SoftDelete, Update,
+    },
 };
 use models::{user::User, Error};
 use schema::stores;
-use types::{bitcoin::Address as BtcAddress, currency::Crypto, PrivateKey, PublicKey, H160};
+use types::{
+    bitcoin::Address as BtcAddress, currency::Crypto, PrivateKey, PublicKey, SettlementInterval,
+    H160,
+};
 
 #[derive(Debug, Insertable, AsChangeset, Deserialize)]
 #[table_name = "stores"]
@@ -28,9 +35,30 @@ pub struct StorePayload {
     pub eth_confirmations_required: Option<Option<i32>>,
     pub btc_payout_addresses: Option<Option<Vec<BtcAddress>>>,
     pub btc_confirmations_required: Option<Option<i32>>,
-    pub mnemonic: Option<String>,
+    pub mnemonic: Option<Option<String>>,
     pub hd_path: Option<String>,
     pub deleted_at: Option<Option<DateTime<Utc>>>,
+    pub ltc_payout_addresses: Option<Option<Vec<BtcAddress>>>,
+    pub ltc_confirmations_required: Option<Option<i32>>,
+    pub bch_payout_addresses: Option<Option<Vec<BtcAddress>>>,
+    pub bch_confirmations_required: Option<Option<i32>>,
+    pub doge_payout_addresses: Option<Option<Vec<BtcAddress>>>,
+    pub doge_confirmations_required: Option<Option<i32>>,
+    pub mnemonic_passphrase: Option<Option<String>>,
+    pub btc_payout_multisig_threshold: Option<Option<i32>>,
+    pub btc_payout_multisig_public_keys: Option<Option<Vec<String>>>,
+    pub btc_invoice_multisig_threshold: Option<Option<i32>>,
+    pub btc_invoice_multisig_xpubs: Option<Option<Vec<String>>>,
+    pub manual_payout_approval: Option<bool>,
+    pub btc_payout_minimum: Option<Option<BigDecimal>>,
+    pub ltc_payout_minimum: Option<Option<BigDecimal>>,
+    pub bch_payout_minimum: Option<Option<BigDecimal>>,
+    pub doge_payout_minimum: Option<Option<BigDecimal>>,
+    pub eth_payout_minimum: Option<Option<BigDecimal>>,
+    pub settlement_interval: Option<Option<SettlementInterval>>,
+    pub settlement_weekday: Option<i32>,
+    pub settlement_hour: Option<i32>,
+    pub btc_lightning_enabled: Option<bool>,
 }
 
 impl StorePayload {
@@ -51,6 +79,27 @@ impl StorePayload {
             mnemonic: None,
             hd_path: None,
             deleted_at: None,
+            ltc_payout_addresses: None,
+            ltc_confirmations_required: None,
+            bch_payout_addresses: None,
+            bch_confirmations_required: None,
+            doge_payout_addresses: None,
+            doge_confirmations_required: None,
+            mnemonic_passphrase: None,
+            btc_payout_multisig_threshold: None,
+            btc_payout_multisig_public_keys: None,
+            btc_invoice_multisig_threshold: None,
+            btc_invoice_multisig_xpubs: None,
+            manual_payout_approval: None,
+            btc_payout_minimum: None,
+            ltc_payout_minimum: None,
+            bch_payout_minimum: None,
+            doge_payout_minimum: None,
+            eth_payout_minimum: None,
+            settlement_interval: None,
+            settlement_weekday: None,
+            settlement_hour: None,
+            btc_lightning_enabled: None,
         }
     }
 
@@ -89,6 +138,27 @@ impl From<Store> for StorePayload {
             mnemonic: Some(store.mnemonic),
             hd_path: Some(store.hd_path),
             deleted_at: Some(store.deleted_at),
+            ltc_payout_addresses: Some(store.ltc_payout_addresses),
+            ltc_confirmations_required: Some(store.ltc_confirmations_required),
+            bch_payout_addresses: Some(store.bch_payout_addresses),
+            bch_confirmations_required: Some(store.bch_confirmations_required),
+            doge_payout_addresses: Some(store.doge_payout_addresses),
+            doge_confirmations_required: Some(store.doge_confirmations_required),
+            mnemonic_passphrase: Some(store.mnemonic_passphrase),
+            btc_payout_multisig_threshold: Some(store.btc_payout_multisig_threshold),
+            btc_payout_multisig_public_keys: Some(store.btc_payout_multisig_public_keys),
+            btc_invoice_multisig_threshold: Some(store.btc_invoice_multisig_threshold),
+            btc_invoice_multisig_xpubs: Some(store.btc_invoice_multisig_xpubs),
+            manual_payout_approval: Some(store.manual_payout_approval),
+            btc_payout_minimum: Some(store.btc_payout_minimum),
+            ltc_payout_minimum: Some(store.ltc_payout_minimum),
+            bch_payout_minimum: Some(store.bch_payout_minimum),
+            doge_payout_minimum: Some(store.doge_payout_minimum),
+            eth_payout_minimum: Some(store.eth_payout_minimum),
+            settlement_interval: Some(store.settlement_interval),
+            settlement_weekday: Some(store.settlement_weekday),
+            settlement_hour: Some(store.settlement_hour),
+            btc_lightning_enabled: Some(store.btc_lightning_enabled),
         }
     }
 }
@@ -108,32 +178,125 @@ pub struct Store {
     pub eth_confirmations_required: Option<i32>,
     pub btc_payout_addresses: Option<Vec<BtcAddress>>,
     pub btc_confirmations_required: Option<i32>,
-    pub mnemonic: String,
+    // Unset for stores whose seed is only held by the signer.
+    #[serde(skip_serializing)]
+    pub mnemonic: Option<String>,
     pub hd_path: String,
     pub deleted_at: Option<DateTime<Utc>>,
+    pub ltc_payout_addresses: Option<Vec<BtcAddress>>,
+    pub ltc_confirmations_required: Option<i32>,
+    pub bch_payout_addresses: Option<Vec<BtcAddress>>,
+    pub bch_confirmations_required: Option<i32>,
+    pub doge_payout_addresses: Option<Vec<BtcAddress>>,
+    pub doge_confirmations_required: Option<i32>,
+    #[serde(skip_serializing)]
+    pub mnemonic_passphrase: Option<String>,
+    pub btc_payout_multisig_threshold: Option<i32>,
+    pub btc_payout_multisig_public_keys: Option<Vec<String>>,
+    pub btc_invoice_multisig_threshold: Option<i32>,
+    pub btc_invoice_multisig_xpubs: Option<Vec<String>>,
+    // Payouts are left for review instead of being broadcast.
+    pub manual_payout_approval: bool,
+    // Confirmed payouts of a currency are held until their total reaches the minimum.
+    pub btc_payout_minimum: Option<BigDecimal>,
+    pub ltc_payout_minimum: Option<BigDecimal>,
+    pub bch_payout_minimum: Option<BigDecimal>,
+    pub doge_payout_minimum: Option<BigDecimal>,
+    pub eth_payout_minimum: Option<BigDecimal>,
+    // Payouts are sent at `settlement_hour` UTC every day or every week on `settlement_weekday`,
+    // from 0 for Monday, instead of as soon as they are confirmed.
+    pub settlement_interval: Option<SettlementInterval>,
+    pub settlement_weekday: i32,
+    pub settlement_hour: i32,
+    // Accepts Lightning invoices, which are settled on the operator's node.
+    pub btc_lightning_enabled: bool,
 }
 
 impl Store {
     pub fn can_accept(&self, crypto: &Crypto) -> bool {
         match crypto {
-            Crypto::Btc => {
-                self.btc_payout_addresses.is_some() && self.btc_confirmations_required.is_some()
-            }
             Crypto::Eth => {
                 self.eth_payout_addresses.is_some() && self.eth_confirmations_required.is_some()
             }
+            Crypto::BtcLightning => self.btc_lightning_enabled,
+            Crypto::Btc => {
+                (self.btc_payout_addresses.is_some() || self.btc_payout_multisig().is_some())
+                    && self.btc_confirmations_required.is_some()
+            }
+            _ => {
+                self.bitcoin_like_payout_addresses(crypto).is_some()
+                    && self.confirmations_required(crypto).is_some()
+            }
+        }
+    }
+
+    // The threshold and hex encoded public keys of the wallet BTC payouts are swept to, when
+    // set instead of payout addresses.
+    pub fn btc_payout_multisig(&self) -> Option<(usize, Vec<String>)> {
+        match (
+            self.btc_payout_multisig_threshold,
+            &self.btc_payout_multisig_public_keys,
+        ) {
+            (Some(threshold), Some(public_keys)) => Some((threshold as usize, public_keys.clone())),
+            _ => None,
+        }
+    }
+
+    // The threshold and cosigner xpubs of BTC invoice addresses, Finch holding one more key.
+    pub fn btc_invoice_multisig(&self) -> Option<(usize, Vec<String>)> {
+        match (
+            self.btc_invoice_multisig_threshold,
+            &self.btc_invoice_multisig_xpubs,
+        ) {
+            (Some(threshold), Some(xpubs)) => Some((threshold as usize, xpubs.clone())),
+            _ => None,
         }
     }
 
+    pub fn bitcoin_like_payout_addresses(&self, crypto: &Crypto) -> Option<Vec<BtcAddress>> {
+        match crypto {
+            Crypto::Btc => self.btc_payout_addresses.clone(),
+            Crypto::Ltc => self.ltc_payout_addresses.clone(),
+            Crypto::Bch => self.bch_payout_addresses.clone(),
+            Crypto::Doge => self.doge_payout_addresses.clone(),
+            Crypto::Eth | Crypto::BtcLightning => None,
+        }
+    }
+
+    pub fn confirmations_required(&self, crypto: &Crypto) -> Option<i32> {
+        match crypto {
+            Crypto::Btc => self.btc_confirmations_required,
+            Crypto::Eth => self.eth_confirmations_required,
+            Crypto::Ltc => self.ltc_confirmations_required,
+            Crypto::Bch => self.bch_confirmations_required,
+            Crypto::Doge => self.doge_confirmations_required,
+            // Settled invoices are final.
+            Crypto::BtcLightning => Some(0),
+        }
+    }
+
+    pub fn payout_minimum(&self, crypto: &Crypto) -> Option<BigDecimal> {
+        match crypto {
+            Crypto::Btc => self.btc_payout_minimum.clone(),
+            Crypto::Eth => self.eth_payout_minimum.clone(),
+            Crypto::Ltc => self.ltc_payout_minimum.clone(),
+            Crypto::Bch => self.bch_payout_minimum.clone(),
+            Crypto::Doge => self.doge_payout_minimum.clone(),
+            Crypto::BtcLightning => None,
+        }
+    }
+
+    // `xpubs` are the extended public keys of the store's accounts, by hd path.
     pub fn insert(
         mut payload: StorePayload,
+        xpubs: Vec<(String, String)>,
         postgres: &PgExecutorAddr,
     ) -> impl Future<Item = Store, Error = Error> {
         payload.set_created_at();
         payload.set_updated_at();
 
         (*postgres)
-            .send(Insert(payload))
+            .send(Insert { payload, xpubs })
             .from_err()
             .and_then(|res| res.map_err(|e| Error::from(e)))
     }
@@ -187,6 +350,44 @@ impl Store {
             .and_then(|res| res.map_err(|e| Error::from(e)))
     }
 
+    pub fn find_xpub(
+        store_id: Uuid,
+        hd_path: String,
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = Option<String>, Error = Error> {
+        (*postgres)
+            .send(FindXpub { store_id, hd_path })
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+    }
+
+    pub fn insert_xpub(
+        store_id: Uuid,
+        hd_path: String,
+        xpub: String,
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = (), Error = Error> {
+        (*postgres)
+            .send(InsertXpub {
+                store_id,
+                hd_path,
+                xpub,
+            })
+            .from_err()
+            .and_then(|res| res.map_err(|e| Error::from(e)))
+    }
+
+    pub fn next_address_index(
+        store_id: Uuid,
+        hd_path: String,
+        postgres: &PgExecutorAddr,
+    ) -> impl Future<Item = u32, Error = Error> {
+        (*postgres)
+            .send(NextAddressIndex { store_id, hd_path })
+            .from_err()
+            .and_then(|res| res.map(|index| index as u32).map_err(|e| Error::from(e)))
+    }
+
     pub fn soft_delete(
         id: Uuid,
         postgres: &PgExecutorAddr,
@@ -207,12 +408,67 @@ impl Store {
             "eth_payout_addresses": self.eth_payout_addresses,
             "eth_confirmations_required": self.eth_confirmations_required,
             "btc_payout_addresses": self.btc_payout_addresses,
+            "btc_payout_multisig_threshold": self.btc_payout_multisig_threshold,
+            "btc_payout_multisig_public_keys": self.btc_payout_multisig_public_keys,
+            "btc_invoice_multisig_threshold": self.btc_invoice_multisig_threshold,
+            "btc_invoice_multisig_xpubs": self.btc_invoice_multisig_xpubs,
             "btc_confirmations_required": self.btc_confirmations_required,
+            "ltc_payout_addresses": self.ltc_payout_addresses,
+            "ltc_confirmations_required": self.ltc_confirmations_required,
+            "bch_payout_addresses": self.bch_payout_addresses,
+            "bch_confirmations_required": self.bch_confirmations_required,
+            "doge_payout_addresses": self.doge_payout_addresses,
+            "doge_confirmations_required": self.doge_confirmations_required,
+            "manual_payout_approval": self.manual_payout_approval,
+            "btc_payout_minimum": self.btc_payout_minimum,
+            "ltc_payout_minimum": self.ltc_payout_minimum,
+            "bch_payout_minimum": self.bch_payout_minimum,
+            "doge_payout_minimum": self.doge_payout_minimum,
+            "eth_payout_minimum": self.eth_payout_minimum,
+            "settlement_interval": self.settlement_interval,
+            "settlement_weekday": self.settlement_weekday,
+            "settlement_hour": self.settlement_hour,
+            "btc_lightning_enabled": self.btc_lightning_enabled,
             "public_key": String::from_utf8_lossy(&self.public_key),
             "can_accept_eth": self.can_accept(&Crypto::Eth),
             "can_accept_btc": self.can_accept(&Crypto::Btc),
+            "can_accept_ltc": self.can_accept(&Crypto::Ltc),
+            "can_accept_bch": self.can_accept(&Crypto::Bch),
+            "can_accept_doge": self.can_accept(&Crypto::Doge),
+            "can_accept_btc_lightning": self.can_accept(&Crypto::BtcLightning),
             "created_at": self.created_at.timestamp(),
             "updated_at": self.updated_at.timestamp(),
         })
     }
+
+    pub fn export_settings(&self) -> Value {
+        json!({
+            "name": self.name,
+            "description": self.description,
+            "eth_payout_addresses": self.eth_payout_addresses,
+            "eth_confirmations_required": self.eth_confirmations_required,
+            "btc_payout_addresses": self.btc_payout_addresses,
+            "btc_payout_multisig_threshold": self.btc_payout_multisig_threshold,
+            "btc_payout_multisig_public_keys": self.btc_payout_multisig_public_keys,
+            "btc_invoice_multisig_threshold": self.btc_invoice_multisig_threshold,
+            "btc_invoice_multisig_xpubs": self.btc_invoice_multisig_xpubs,
+            "btc_confirmations_required": self.btc_confirmations_required,
+            "ltc_payout_addresses": self.ltc_payout_addresses,
+            "ltc_confirmations_required": self.ltc_confirmations_required,
+            "bch_payout_addresses": self.bch_payout_addresses,
+            "bch_confirmations_required": self.bch_confirmations_required,
+            "doge_payout_addresses": self.doge_payout_addresses,
+            "doge_confirmations_required": self.doge_confirmations_required,
+            "manual_payout_approval": self.manual_payout_approval,
+            "btc_payout_minimum": self.btc_payout_minimum,
+            "ltc_payout_minimum": self.ltc_payout_minimum,
+            "bch_payout_minimum": self.bch_payout_minimum,
+            "doge_payout_minimum": self.doge_payout_minimum,
+            "eth_payout_minimum": self.eth_payout_minimum,
+            "settlement_interval": self.settlement_interval,
+            "settlement_weekday": self.settlement_weekday,
+            "settlement_hour": self.settlement_hour,
+            "btc_lightning_enabled": self.btc_lightning_enabled,
+        })
+    }
 }
@@ -13,6 +13,7 @@ use db::{
 };
 use models::Error;
 use schema::users;
+use types::Locale;
 
 #[derive(Insertable, AsChangeset, Deserialize, Clone)]
 #[table_name = "users"]
@@ -27,6 +28,7 @@ pub struct UserPayload {
     pub verification_token_expires_at: Option<DateTime<Utc>>,
     pub reset_token: Option<Option<Uuid>>,
     pub reset_token_expires_at: Option<Option<DateTime<Utc>>>,
+    pub locale: Option<Locale>,
 }
 
 impl UserPayload {
@@ -42,6 +44,7 @@ impl UserPayload {
             verification_token_expires_at: None,
             reset_token: None,
             reset_token_expires_at: None,
+            locale: None,
         }
     }
 
@@ -78,6 +81,7 @@ impl From<User> for UserPayload {
             verification_token_expires_at: Some(user.verification_token_expires_at),
             reset_token: Some(user.reset_token),
             reset_token_expires_at: Some(user.reset_token_expires_at),
+            locale: Some(user.locale),
         }
     }
 }
@@ -95,6 +99,7 @@ pub struct User {
     pub verification_token_expires_at: DateTime<Utc>,
     pub reset_token: Option<Uuid>,
     pub reset_token_expires_at: Option<DateTime<Utc>>,
+    pub locale: Locale,
 }
 
 impl User {
@@ -186,6 +191,7 @@ impl User {
         json!({
             "id": self.id,
             "email": self.email,
+            "locale": self.locale,
             "created_at": self.created_at.timestamp(),
             "updated_at": self.updated_at.timestamp(),
         })
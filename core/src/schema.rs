@@ -1,7 +1,36 @@
 table! {
-    btc_blockchain_statuses (network) {
+    audit_events (id) {
+        id -> Uuid,
+        store_id -> Nullable<Uuid>,
+        actor_id -> Nullable<Uuid>,
+        ip_address -> Nullable<Varchar>,
+        action -> Varchar,
+        old_values -> Nullable<Json>,
+        new_values -> Nullable<Json>,
+        created_at -> Timestamptz,
+    }
+}
+
+table! {
+    block_rescans (id) {
+        id -> Uuid,
+        crypto -> Varchar,
+        eth_network -> Nullable<Varchar>,
+        from_block -> Numeric,
+        to_block -> Numeric,
+        next_block -> Numeric,
+        status -> Varchar,
+        error -> Nullable<Varchar>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+table! {
+    btc_blockchain_statuses (crypto, network) {
         network -> Varchar,
         block_height -> Numeric,
+        crypto -> Varchar,
     }
 }
 
@@ -47,6 +76,61 @@ table! {
     }
 }
 
+table! {
+    eth_transfers (transaction_hash, output_index) {
+        transaction_hash -> Varchar,
+        output_index -> Int4,
+        from_address -> Varchar,
+        to_address -> Varchar,
+        value -> Numeric,
+    }
+}
+
+table! {
+    exports (id) {
+        id -> Uuid,
+        store_id -> Uuid,
+        requested_by -> Uuid,
+        kind -> Varchar,
+        format -> Varchar,
+        from_at -> Nullable<Timestamptz>,
+        to_at -> Nullable<Timestamptz>,
+        download_token -> Uuid,
+        created_at -> Timestamptz,
+        completed_at -> Nullable<Timestamptz>,
+        expires_at -> Timestamptz,
+        failed_at -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    ledger_entries (id) {
+        id -> Uuid,
+        store_id -> Uuid,
+        typ -> Varchar,
+        payment_id -> Uuid,
+        payout_id -> Uuid,
+        event -> Varchar,
+        account -> Varchar,
+        amount -> Numeric,
+        created_at -> Timestamptz,
+        destination -> Nullable<Varchar>,
+    }
+}
+
+table! {
+    notification_preferences (store_id) {
+        store_id -> Uuid,
+        email -> Nullable<Varchar>,
+        payment_confirmed -> Bool,
+        insufficient_amount -> Bool,
+        payout_sent -> Bool,
+        payout_failed -> Bool,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
 table! {
     payments (id) {
         id -> Uuid,
@@ -69,6 +153,54 @@ table! {
         btc_network -> Nullable<Varchar>,
         eth_network -> Nullable<Varchar>,
         identifier -> Nullable<Varchar>,
+        ln_payment_hash -> Nullable<Varchar>,
+        hd_path -> Nullable<Varchar>,
+        witness_script -> Nullable<Varchar>,
+        discrepancy_flagged_at -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    payout_address_changes (id) {
+        id -> Uuid,
+        store_id -> Uuid,
+        eth_payout_addresses -> Nullable<Array<Text>>,
+        btc_payout_addresses -> Nullable<Array<Text>>,
+        confirmation_token -> Uuid,
+        confirmation_token_expires_at -> Timestamptz,
+        confirmed_at -> Nullable<Timestamptz>,
+        effective_at -> Nullable<Timestamptz>,
+        applied_at -> Nullable<Timestamptz>,
+        cancelled_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+        ltc_payout_addresses -> Nullable<Array<Text>>,
+        bch_payout_addresses -> Nullable<Array<Text>>,
+        doge_payout_addresses -> Nullable<Array<Text>>,
+        btc_payout_multisig_threshold -> Nullable<Int4>,
+        btc_payout_multisig_public_keys -> Nullable<Array<Text>>,
+        btc_invoice_multisig_threshold -> Nullable<Int4>,
+        btc_invoice_multisig_xpubs -> Nullable<Array<Text>>,
+        manual_payout_approval -> Nullable<Bool>,
+        cancellation_token -> Uuid,
+    }
+}
+
+table! {
+    payout_approvals (payout_id) {
+        payout_id -> Uuid,
+        unsigned_transaction -> Text,
+        signed_transaction -> Nullable<Text>,
+        approved_by -> Nullable<Uuid>,
+        approved_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+    }
+}
+
+table! {
+    payout_psbts (payout_id) {
+        payout_id -> Uuid,
+        psbt -> Text,
+        created_at -> Timestamptz,
     }
 }
 
@@ -83,6 +215,39 @@ table! {
         block_height_required -> Numeric,
         transaction_hash -> Nullable<Varchar>,
         created_at -> Timestamptz,
+        payment_transaction_hash -> Nullable<Varchar>,
+        output_index -> Nullable<Int4>,
+        confirmation_notified_at -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    sessions (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        refresh_token_hash -> Varchar,
+        ip_address -> Nullable<Varchar>,
+        user_agent -> Nullable<Varchar>,
+        created_at -> Timestamptz,
+        last_used_at -> Timestamptz,
+        expires_at -> Timestamptz,
+        revoked_at -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    store_address_indexes (store_id, hd_path) {
+        store_id -> Uuid,
+        hd_path -> Varchar,
+        next_index -> Int4,
+    }
+}
+
+table! {
+    store_xpubs (store_id, hd_path) {
+        store_id -> Uuid,
+        hd_path -> Varchar,
+        xpub -> Varchar,
     }
 }
 
@@ -100,9 +265,38 @@ table! {
         eth_confirmations_required -> Nullable<Int4>,
         btc_payout_addresses -> Nullable<Array<Text>>,
         btc_confirmations_required -> Nullable<Int4>,
-        mnemonic -> Varchar,
+        mnemonic -> Nullable<Varchar>,
         hd_path -> Varchar,
         deleted_at -> Nullable<Timestamptz>,
+        ltc_payout_addresses -> Nullable<Array<Text>>,
+        ltc_confirmations_required -> Nullable<Int4>,
+        bch_payout_addresses -> Nullable<Array<Text>>,
+        bch_confirmations_required -> Nullable<Int4>,
+        doge_payout_addresses -> Nullable<Array<Text>>,
+        doge_confirmations_required -> Nullable<Int4>,
+        mnemonic_passphrase -> Nullable<Varchar>,
+        btc_payout_multisig_threshold -> Nullable<Int4>,
+        btc_payout_multisig_public_keys -> Nullable<Array<Text>>,
+        btc_invoice_multisig_threshold -> Nullable<Int4>,
+        btc_invoice_multisig_xpubs -> Nullable<Array<Text>>,
+        manual_payout_approval -> Bool,
+        btc_payout_minimum -> Nullable<Numeric>,
+        ltc_payout_minimum -> Nullable<Numeric>,
+        bch_payout_minimum -> Nullable<Numeric>,
+        doge_payout_minimum -> Nullable<Numeric>,
+        eth_payout_minimum -> Nullable<Numeric>,
+        settlement_interval -> Nullable<Varchar>,
+        settlement_weekday -> Int4,
+        settlement_hour -> Int4,
+        btc_lightning_enabled -> Bool,
+    }
+}
+
+table! {
+    superseded_refresh_tokens (refresh_token_hash) {
+        refresh_token_hash -> Varchar,
+        session_id -> Uuid,
+        created_at -> Timestamptz,
     }
 }
 
@@ -119,17 +313,31 @@ table! {
         verification_token_expires_at -> Timestamptz,
         reset_token -> Nullable<Uuid>,
         reset_token_expires_at -> Nullable<Timestamptz>,
+        locale -> Varchar,
     }
 }
 
 allow_tables_to_appear_in_same_query!(
+    audit_events,
+    block_rescans,
     btc_blockchain_statuses,
     btc_transactions,
     client_tokens,
     eth_blockchain_statuses,
     eth_transactions,
+    eth_transfers,
+    exports,
+    ledger_entries,
+    notification_preferences,
     payments,
+    payout_address_changes,
+    payout_approvals,
+    payout_psbts,
     payouts,
+    sessions,
+    store_address_indexes,
+    store_xpubs,
     stores,
+    superseded_refresh_tokens,
     users,
 );
@@ -13,7 +13,7 @@ use serde_json::{self, Value};
 use url::Url;
 
 use errors::Error as ApiClientError;
-use types::currency::{Crypto, Fiat};
+use types::currency::Fiat;
 
 #[derive(FromSqlRow, AsExpression, Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Hash)]
 #[serde(rename_all = "lowercase")]
@@ -37,7 +37,7 @@ impl Api {
         }
     }
 
-    fn rate_endpoint(&self, from: Fiat, to: Crypto) -> Url {
+    fn rate_endpoint(&self, from: Fiat, to: &str) -> Url {
         let mut url = self.base_url();
 
         match self {
@@ -45,7 +45,7 @@ impl Api {
                 url.set_path(&format!(
                     "/v1/exchangerate/{}/{}",
                     format!("{}", from.to_string().to_uppercase()),
-                    format!("{}", to.to_string().to_uppercase())
+                    format!("{}", to.to_uppercase())
                 ));
             }
             Api::CryptoCompare => {
@@ -53,16 +53,16 @@ impl Api {
                 url.query_pairs_mut()
                     .append_pair("fsym", &from.to_string().to_uppercase());
                 url.query_pairs_mut()
-                    .append_pair("tsyms", &to.to_string().to_uppercase());
+                    .append_pair("tsyms", &to.to_uppercase());
             }
         };
         url
     }
 
-    fn rate_field(&self, _: Fiat, to: Crypto) -> String {
+    fn rate_field(&self, _: Fiat, to: &str) -> String {
         match self {
             Api::CoinApi => "rate".to_owned(),
-            Api::CryptoCompare => to.to_string().to_uppercase(),
+            Api::CryptoCompare => to.to_uppercase(),
         }
     }
 
@@ -76,7 +76,7 @@ impl Api {
     pub fn get_rate(
         &self,
         from: Fiat,
-        to: Crypto,
+        to: &str,
         key: &str,
     ) -> Box<Future<Item = BigDecimal, Error = ApiClientError>> {
         let url = self.rate_endpoint(from, to);
@@ -4,7 +4,7 @@ use futures::future::Future;
 
 use api::Api;
 use errors::Error;
-use types::currency::{Crypto, Fiat};
+use types::currency::Fiat;
 
 pub type CurrencyApiClientAddr = Addr<Client>;
 
@@ -31,13 +31,14 @@ impl Actor for Client {
 #[rtype(result = "Result<BigDecimal, Error>")]
 pub struct GetRate {
     pub from: Fiat,
-    pub to: Crypto,
+    // Currency symbol, e.g. "BTC" or the native symbol of an EVM network.
+    pub to: String,
 }
 
 impl Handler<GetRate> for Client {
     type Result = Box<Future<Item = BigDecimal, Error = Error>>;
 
     fn handle(&mut self, GetRate { from, to }: GetRate, _: &mut Self::Context) -> Self::Result {
-        self.api.get_rate(from, to, &self.key)
+        self.api.get_rate(from, &to, &self.key)
     }
 }
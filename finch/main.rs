@@ -2,6 +2,7 @@ extern crate actix;
 #[macro_use]
 extern crate clap;
 extern crate env_logger;
+extern crate futures;
 extern crate openssl;
 
 extern crate blockchain_api_client;
@@ -10,22 +11,30 @@ extern crate toml;
 extern crate block_processor;
 extern crate config;
 extern crate core;
+extern crate lightning_api_client;
+extern crate mailer;
 extern crate payouter;
 extern crate server;
 extern crate types;
 
 use actix::prelude::*;
-use clap::App;
+use clap::{App, ArgMatches};
+use futures::Future;
 use openssl::rsa::Rsa;
-use std::{env, fs::File, io::prelude::*, path::Path};
+use std::{env, fmt::Display, fs::File, io::prelude::*, path::Path, process};
 
 use blockchain_api_client::{
     bitcoin::BlockchainApiClient as BtcBlockchainApiClient,
     ethereum::BlockchainApiClient as EthBlockchainApiClient,
 };
 use config::Config;
-use core::db::postgres;
-use types::currency::Crypto;
+use core::{
+    block_rescan::{BlockRescan, BlockRescanPayload},
+    db::postgres,
+};
+use lightning_api_client::Client as LightningClient;
+use mailer::{Mailer, Notifier};
+use types::{currency::Crypto, ethereum::Network as EthNetwork, U128};
 
 fn main() {
     env::set_var(
@@ -48,6 +57,15 @@ fn main() {
     .unwrap();
 
     let config: Config = toml::from_str(&settings).unwrap();
+    config
+        .server
+        .validate()
+        .unwrap_or_else(|e| exit_with_config_error(e));
+
+    if let Some(matches) = matches.subcommand_matches("rescan") {
+        rescan(matches, &config);
+        return;
+    }
 
     if !Path::new(&config.server.private_key_path).exists()
         || !Path::new(&config.server.private_key_path).exists()
@@ -87,20 +105,57 @@ fn main() {
     let pg_pool = postgres::init_pool(&postgres_url);
     let postgres = SyncArbiter::start(4, move || postgres::PgExecutor(pg_pool.clone()));
 
+    let mail_config = config.mail.clone();
+    let smtp_config = config.smtp.clone();
+    let mailer = SyncArbiter::start(4, move || {
+        Mailer(mailer::init_transport(&mail_config, smtp_config.as_ref()))
+    });
+
+    let _mailer = mailer.clone();
+    let _postgres = postgres.clone();
+    let mail_sender = config.server.mail_sender.clone();
+    let notifier = Arbiter::start(move |_| Notifier::new(_mailer, _postgres, mail_sender));
+
+    // Shared by the server creating invoices and the watcher settling them, so invoices are only
+    // issued while `btc_lightning` is among the currencies.
+    let lightning_client = if currencies.contains(&Crypto::BtcLightning) {
+        let lightning_config = config
+            .lightning
+            .as_ref()
+            .unwrap_or_else(|| exit_with_config_error("no lightning configuration"));
+        let backend = lightning_api_client::init_backend(lightning_config)
+            .unwrap_or_else(|e| exit_with_config_error(e));
+
+        Some(Arbiter::start(move |_| LightningClient::new(backend)))
+    } else {
+        None
+    };
+
     let skip_missed_blocks = matches.is_present("skip_missed_blocks");
 
-    let mut _btc_block_processor;
-    let mut _eth_block_processor;
+    let mut _bitcoin_like_block_processors = Vec::new();
+    let mut _eth_block_processors = Vec::new();
+    let mut _lightning_watcher = None;
+    let mut _bitcoin_like_reconcilers = Vec::new();
+    let mut _eth_reconcilers = Vec::new();
+    let mut _bitcoin_like_rescanners = Vec::new();
+    let mut _eth_rescanners = Vec::new();
+    let mut _bitcoin_like_subscribers = Vec::new();
+    let mut _eth_subscribers = Vec::new();
 
     for c in currencies {
         match c {
-            Crypto::Btc => {
+            Crypto::Btc | Crypto::Ltc | Crypto::Bch | Crypto::Doge => {
                 use block_processor::bitcoin::service as block_processor;
                 use payouter::bitcoin::service as payouter;
 
-                let btc_config = config.bitcoin.clone().expect("no bitcoin configuration");
+                let btc_config = config
+                    .bitcoin_like(c)
+                    .unwrap_or_else(|| panic!("no {} configuration", c));
 
-                let network = btc_config.network;
+                let chain = btc_config.chain(c);
+                let catch_up_concurrency = btc_config.catch_up_concurrency;
+                let zmq_url = btc_config.zmq_url.clone();
 
                 let blockchain_api_client = Arbiter::start(move |_| {
                     BtcBlockchainApiClient::new(
@@ -110,36 +165,194 @@ fn main() {
                     )
                 });
 
-                _btc_block_processor = block_processor::run(
+                let (processor, poller, pb_poller) = block_processor::run(
                     postgres.clone(),
                     blockchain_api_client.clone(),
-                    network,
+                    notifier.clone(),
+                    chain,
                     skip_missed_blocks,
+                    catch_up_concurrency,
                 );
-                payouter::run(postgres.clone(), blockchain_api_client.clone(), network);
+                _bitcoin_like_rescanners.push(block_processor::run_rescanner(
+                    processor.clone(),
+                    postgres.clone(),
+                    blockchain_api_client.clone(),
+                    chain,
+                ));
+                if let Some(zmq_url) = zmq_url {
+                    _bitcoin_like_subscribers.push(block_processor::run_subscriber(
+                        chain,
+                        zmq_url,
+                        processor.clone(),
+                        poller.clone(),
+                        pb_poller.clone(),
+                    ));
+                }
+                _bitcoin_like_block_processors.push((processor, poller, pb_poller));
+                if let Some(ref reconciliation_config) = config.reconciliation {
+                    _bitcoin_like_reconcilers.push(block_processor::run_reconciler(
+                        postgres.clone(),
+                        blockchain_api_client.clone(),
+                        notifier.clone(),
+                        chain,
+                        reconciliation_config.clone(),
+                    ));
+                }
+                payouter::run(
+                    postgres.clone(),
+                    blockchain_api_client.clone(),
+                    notifier.clone(),
+                    chain,
+                    config.signer.clone(),
+                )
+                .unwrap_or_else(|e| exit_with_config_error(e));
             }
             Crypto::Eth => {
                 use block_processor::ethereum::service as block_processor;
                 use payouter::ethereum::service as payouter;
 
-                let eth_config = config.clone().ethereum.expect("no ethereum configuration");
+                let evm_networks = config.evm_networks();
+                if evm_networks.is_empty() {
+                    panic!("no ethereum configuration");
+                }
 
-                let network = eth_config.network;
-                let blockchain_api_client =
-                    Arbiter::start(move |_| EthBlockchainApiClient::new(eth_config.rpc_url));
+                for eth_config in evm_networks {
+                    let network = eth_config.network;
+                    let rpc_url = eth_config.rpc_url.clone();
+                    let blockchain_api_client =
+                        Arbiter::start(move |_| EthBlockchainApiClient::new(rpc_url));
 
-                _eth_block_processor = block_processor::run(
-                    postgres.clone(),
-                    blockchain_api_client.clone(),
-                    network,
-                    skip_missed_blocks,
-                );
-                payouter::run(postgres.clone(), blockchain_api_client.clone(), network);
+                    let (processor, poller, pb_poller) = block_processor::run(
+                        postgres.clone(),
+                        blockchain_api_client.clone(),
+                        notifier.clone(),
+                        network,
+                        eth_config.internal_transactions,
+                        skip_missed_blocks,
+                        eth_config.catch_up_concurrency,
+                    );
+                    _eth_rescanners.push(block_processor::run_rescanner(
+                        processor.clone(),
+                        postgres.clone(),
+                        blockchain_api_client.clone(),
+                        network,
+                        eth_config.internal_transactions,
+                    ));
+                    if let Some(ref ws_url) = eth_config.ws_url {
+                        _eth_subscribers.push(block_processor::run_subscriber(
+                            network,
+                            ws_url.clone(),
+                            processor.clone(),
+                            poller.clone(),
+                            pb_poller.clone(),
+                            blockchain_api_client.clone(),
+                        ));
+                    }
+                    _eth_block_processors.push((processor, poller, pb_poller));
+                    if let Some(ref reconciliation_config) = config.reconciliation {
+                        _eth_reconcilers.push(block_processor::run_reconciler(
+                            postgres.clone(),
+                            blockchain_api_client.clone(),
+                            network,
+                            reconciliation_config.clone(),
+                        ));
+                    }
+                    payouter::run(
+                        postgres.clone(),
+                        blockchain_api_client.clone(),
+                        notifier.clone(),
+                        eth_config,
+                        config.signer.clone(),
+                    )
+                    .unwrap_or_else(|e| exit_with_config_error(e));
+                }
+            }
+            Crypto::BtcLightning => {
+                use block_processor::lightning::service as watcher;
+
+                if let Some(ref lightning_client) = lightning_client {
+                    _lightning_watcher =
+                        Some(watcher::run(postgres.clone(), lightning_client.clone()));
+                }
             }
         }
     }
 
-    server::run(postgres, config);
+    server::run(postgres, mailer, lightning_client, config);
+
+    system.run();
+}
+
+fn exit_with_config_error<E: Display>(e: E) -> ! {
+    eprintln!("invalid configuration: {}", e);
+    process::exit(1);
+}
+
+// Queues a rescan of a block range for the running block processors, and exits.
+fn rescan(matches: &ArgMatches, config: &Config) {
+    let crypto = value_t!(matches, "currency", Crypto).unwrap_or_else(|e| e.exit());
+    let from_block = value_t!(matches, "from", u64).unwrap_or_else(|e| e.exit());
+    let to_block = value_t!(matches, "to", u64).unwrap_or_else(|e| e.exit());
+
+    if from_block > to_block {
+        clap::Error::with_description(
+            "--from must not be greater than --to",
+            clap::ErrorKind::InvalidValue,
+        )
+        .exit();
+    }
+
+    let mut payload = BlockRescanPayload::new();
+    payload.crypto = Some(crypto);
+    payload.from_block = Some(U128::from(from_block));
+    payload.to_block = Some(U128::from(to_block));
+
+    match crypto {
+        Crypto::Eth => {
+            let evm_networks = config.evm_networks();
+            let eth_config = match matches.value_of("chain_id") {
+                Some(_) => {
+                    let network =
+                        EthNetwork(value_t!(matches, "chain_id", u64).unwrap_or_else(|e| e.exit()));
+                    evm_networks
+                        .into_iter()
+                        .find(|eth_config| eth_config.network == network)
+                }
+                None => evm_networks.into_iter().next(),
+            };
+
+            payload.eth_network = Some(Some(
+                eth_config
+                    .unwrap_or_else(|| exit_with_config_error("no configuration for the network"))
+                    .network,
+            ));
+        }
+        Crypto::BtcLightning => clap::Error::with_description(
+            "lightning payments aren't processed from blocks",
+            clap::ErrorKind::InvalidValue,
+        )
+        .exit(),
+        _ => {
+            config
+                .bitcoin_like(crypto)
+                .unwrap_or_else(|| exit_with_config_error(format!("no {} configuration", crypto)));
+        }
+    }
+
+    let system = System::new("finch-rescan");
+
+    let pg_pool = postgres::init_pool(&config.postgres);
+    let postgres = SyncArbiter::start(1, move || postgres::PgExecutor(pg_pool.clone()));
+
+    Arbiter::spawn(BlockRescan::insert(payload, &postgres).then(|res| {
+        match res {
+            Ok(rescan) => println!("{}", rescan.export()),
+            Err(e) => eprintln!("failed to queue the rescan: {}", e),
+        };
+
+        System::current().stop();
+        Ok(())
+    }));
 
     system.run();
 }
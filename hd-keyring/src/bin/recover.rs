@@ -0,0 +1,111 @@
+// Recovers the keys of a store's payments from a wallet export, and sweeps bitcoin-like funds
+// without a running Finch.
+//
+//     finch-recover keys <export.json>
+//     finch-recover sweep <export.json> <crypto> <network> <utxos.json> <address> <satoshi per byte>
+//
+// UTXOs are a JSON array of `{"txid", "vout", "address", "value"}`, with values in satoshi.
+
+extern crate hd_keyring;
+extern crate serde_json;
+extern crate types;
+
+use std::{env, fs::File, process, str::FromStr};
+
+use hd_keyring::recovery::{self, RecoveredKey, Utxo, WalletExport};
+use types::bitcoin::{Address as BtcAddress, ChainParams};
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+
+    let res = match args.get(1).map(|command| command.as_str()) {
+        Some("keys") if args.len() == 3 => keys(&args[2]),
+        Some("sweep") if args.len() == 8 => sweep(&args[2..]),
+        _ => Err(String::from(
+            "usage: finch-recover keys <export.json>\n       finch-recover sweep <export.json> \
+             <crypto> <network> <utxos.json> <address> <satoshi per byte>",
+        )),
+    };
+
+    if let Err(e) = res {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
+
+fn read_export(path: &str) -> Result<WalletExport, String> {
+    let file = File::open(path).map_err(|e| format!("{}: {}", path, e))?;
+
+    serde_json::from_reader(file).map_err(|e| format!("{}: {}", path, e))
+}
+
+// Recovers what keys it can, reporting the payments whose address doesn't match on stderr.
+fn recover_keys(export: &WalletExport) -> Result<Vec<RecoveredKey>, String> {
+    let recovery = recovery::recover_keys(export).map_err(|e| e.to_string())?;
+
+    for payment in recovery.mismatches {
+        eprintln!(
+            "skipping {}: derived key doesn't match address {}",
+            payment.id, payment.address
+        );
+    }
+
+    Ok(recovery.keys)
+}
+
+fn keys(export: &str) -> Result<(), String> {
+    let export = read_export(export)?;
+    let keys = recover_keys(&export)?;
+
+    for account in export.accounts.iter() {
+        if account.gap_limit > recovery::GAP_LIMIT {
+            eprintln!(
+                "restore {} with a gap limit of at least {} to find every payment",
+                account.hd_path, account.gap_limit
+            );
+        }
+    }
+
+    for key in keys {
+        let private_key: String = key.wallet.secret_key[..]
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+
+        println!(
+            "{} {} {} {}",
+            key.payment.id, key.payment.crypto, key.payment.address, private_key
+        );
+    }
+
+    Ok(())
+}
+
+fn sweep(args: &[String]) -> Result<(), String> {
+    let export = read_export(&args[0])?;
+    let chain = ChainParams::new(args[1].parse()?, args[2].parse()?)
+        .ok_or_else(|| String::from("only bitcoin-like chains can be swept"))?;
+
+    let file = File::open(&args[3]).map_err(|e| format!("{}: {}", args[3], e))?;
+    let utxos: Vec<Utxo> =
+        serde_json::from_reader(file).map_err(|e| format!("{}: {}", args[3], e))?;
+
+    let to = BtcAddress::from_str(&args[4])?;
+    let fee_per_byte = args[5]
+        .parse::<u64>()
+        .map_err(|_| String::from("invalid fee"))?;
+
+    let keys = recover_keys(&export)?;
+    let transaction =
+        recovery::sweep(&chain, &keys, &utxos, &to, fee_per_byte).map_err(|e| e.to_string())?;
+
+    println!(
+        "{}",
+        transaction
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,113 @@
+// A signer process holding store seeds away from Finch, for development and tests.
+//
+//     finch-signer <keys.json> <unix:/path/to/socket | host:port>
+//
+// Keys are `{"network": "mainnet", "secret": "...", "stores": {"<store id>": {"mnemonic",
+// "passphrase"}}}`, requests being authenticated with the secret shared with the payouters.
+// Stores created while a signer is configured show their mnemonic once, to be added here.
+
+extern crate hd_keyring;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+extern crate types;
+
+use std::{
+    collections::HashMap,
+    env,
+    fs::{self, File},
+    net::TcpListener,
+    os::unix::net::UnixListener,
+    process,
+    str::FromStr,
+    sync::Arc,
+    thread,
+};
+
+use hd_keyring::signer::{self, InMemorySigner, SignerAddress};
+use types::bitcoin::Network as BtcNetwork;
+
+#[derive(Deserialize)]
+struct Keys {
+    network: BtcNetwork,
+    secret: String,
+    stores: HashMap<String, StoreKeys>,
+}
+
+#[derive(Deserialize)]
+struct StoreKeys {
+    mnemonic: String,
+    #[serde(default)]
+    passphrase: String,
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+
+    let res = if args.len() == 3 {
+        run(&args[1], &args[2])
+    } else {
+        Err(String::from(
+            "usage: finch-signer <keys.json> <unix:/path/to/socket | host:port>",
+        ))
+    };
+
+    if let Err(e) = res {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
+
+fn run(keys: &str, address: &str) -> Result<(), String> {
+    let file = File::open(keys).map_err(|e| format!("{}: {}", keys, e))?;
+    let keys: Keys = serde_json::from_reader(file).map_err(|e| format!("{}: {}", keys, e))?;
+
+    let mut signer = InMemorySigner::new(keys.network);
+
+    for (store_id, store_keys) in keys.stores {
+        signer
+            .add_store(&store_id, &store_keys.mnemonic, &store_keys.passphrase)
+            .map_err(|e| format!("{}: {}", store_id, e))?;
+    }
+
+    let signer = Arc::new(signer);
+    let secret = Arc::new(keys.secret);
+
+    match SignerAddress::from_str(address).map_err(|e| e.to_string())? {
+        SignerAddress::Unix(path) => {
+            // A socket left behind by a previous run would fail the bind.
+            let _ = fs::remove_file(&path);
+            let listener = UnixListener::bind(&path).map_err(|e| e.to_string())?;
+
+            for stream in listener.incoming() {
+                let signer = signer.clone();
+                let secret = secret.clone();
+                let stream = stream.map_err(|e| e.to_string())?;
+
+                thread::spawn(move || {
+                    if let Err(e) = signer::serve(&*signer, &secret, stream) {
+                        eprintln!("{}", e);
+                    }
+                });
+            }
+        }
+        SignerAddress::Tcp(address) => {
+            let listener = TcpListener::bind(address.as_str()).map_err(|e| e.to_string())?;
+
+            for stream in listener.incoming() {
+                let signer = signer.clone();
+                let secret = secret.clone();
+                let stream = stream.map_err(|e| e.to_string())?;
+
+                thread::spawn(move || {
+                    if let Err(e) = signer::serve(&*signer, &secret, stream) {
+                        eprintln!("{}", e);
+                    }
+                });
+            }
+        }
+    }
+
+    Ok(())
+}
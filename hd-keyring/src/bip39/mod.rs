@@ -6,6 +6,7 @@ use bit_vec::BitVec;
 use rand::{thread_rng, Rng};
 use ring::{digest, pbkdf2};
 use sha2::{Digest, Sha256};
+use unicode_normalization::UnicodeNormalization;
 
 pub use self::errors::Error;
 
@@ -23,24 +24,75 @@ lazy_static! {
             .split_whitespace()
             .collect()
     };
-    static ref ENGLISH_WORDMAP: HashMap<&'static str, u16> = {
-        ENGLISH_WORDLIST
-            .iter()
-            .enumerate()
-            .map(|(i, word)| (*word, i as u16))
+    static ref JAPANESE_WORDLIST: Vec<&'static str> = {
+        include_str!("wordlist/japanese.txt")
+            .split_whitespace()
+            .collect()
+    };
+    static ref SPANISH_WORDLIST: Vec<&'static str> = {
+        include_str!("wordlist/spanish.txt")
+            .split_whitespace()
+            .collect()
+    };
+    static ref CHINESE_SIMPLIFIED_WORDLIST: Vec<&'static str> = {
+        include_str!("wordlist/chinese_simplified.txt")
+            .split_whitespace()
+            .collect()
+    };
+    static ref CHINESE_TRADITIONAL_WORDLIST: Vec<&'static str> = {
+        include_str!("wordlist/chinese_traditional.txt")
+            .split_whitespace()
             .collect()
     };
-    static ref FRENCH_WORDMAP: HashMap<&'static str, u16> = {
-        FRENCH_WORDLIST
-            .iter()
-            .enumerate()
-            .map(|(i, word)| (*word, i as u16))
+    static ref ITALIAN_WORDLIST: Vec<&'static str> = {
+        include_str!("wordlist/italian.txt")
+            .split_whitespace()
+            .collect()
+    };
+    static ref KOREAN_WORDLIST: Vec<&'static str> = {
+        include_str!("wordlist/korean.txt")
+            .split_whitespace()
+            .collect()
+    };
+    static ref CZECH_WORDLIST: Vec<&'static str> = {
+        include_str!("wordlist/czech.txt")
+            .split_whitespace()
+            .collect()
+    };
+    static ref PORTUGUESE_WORDLIST: Vec<&'static str> = {
+        include_str!("wordlist/portuguese.txt")
+            .split_whitespace()
             .collect()
     };
+    static ref ENGLISH_WORDMAP: HashMap<String, u16> = wordmap(&ENGLISH_WORDLIST);
+    static ref FRENCH_WORDMAP: HashMap<String, u16> = wordmap(&FRENCH_WORDLIST);
+    static ref JAPANESE_WORDMAP: HashMap<String, u16> = wordmap(&JAPANESE_WORDLIST);
+    static ref SPANISH_WORDMAP: HashMap<String, u16> = wordmap(&SPANISH_WORDLIST);
+    static ref CHINESE_SIMPLIFIED_WORDMAP: HashMap<String, u16> =
+        wordmap(&CHINESE_SIMPLIFIED_WORDLIST);
+    static ref CHINESE_TRADITIONAL_WORDMAP: HashMap<String, u16> =
+        wordmap(&CHINESE_TRADITIONAL_WORDLIST);
+    static ref ITALIAN_WORDMAP: HashMap<String, u16> = wordmap(&ITALIAN_WORDLIST);
+    static ref KOREAN_WORDMAP: HashMap<String, u16> = wordmap(&KOREAN_WORDLIST);
+    static ref CZECH_WORDMAP: HashMap<String, u16> = wordmap(&CZECH_WORDLIST);
+    static ref PORTUGUESE_WORDMAP: HashMap<String, u16> = wordmap(&PORTUGUESE_WORDLIST);
 }
 
+// Words are looked up normalized, so that phrases typed with composed accents still match.
+fn wordmap(wordlist: &[&'static str]) -> HashMap<String, u16> {
+    wordlist
+        .iter()
+        .enumerate()
+        .map(|(i, word)| (word.nfkd().collect(), i as u16))
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum MnemonicType {
     Words12,
+    Words15,
+    Words18,
+    Words21,
     Words24,
 }
 
@@ -48,6 +100,9 @@ impl MnemonicType {
     pub fn from_word_count(count: usize) -> Result<Self, Error> {
         let typ = match count {
             12 => MnemonicType::Words12,
+            15 => MnemonicType::Words15,
+            18 => MnemonicType::Words18,
+            21 => MnemonicType::Words21,
             24 => MnemonicType::Words24,
             _ => Err(Error::InvalidWordLength(count))?,
         };
@@ -58,43 +113,90 @@ impl MnemonicType {
     fn entropy_length(&self) -> usize {
         match *self {
             MnemonicType::Words12 => 128,
+            MnemonicType::Words15 => 160,
+            MnemonicType::Words18 => 192,
+            MnemonicType::Words21 => 224,
             MnemonicType::Words24 => 256,
         }
     }
 
     fn checksum_length(&self) -> usize {
-        match *self {
-            MnemonicType::Words12 => 4,
-            MnemonicType::Words24 => 8,
-        }
+        self.entropy_length() / 32
     }
 
     fn word_count(&self) -> usize {
-        match *self {
-            MnemonicType::Words12 => 12,
-            MnemonicType::Words24 => 24,
-        }
+        (self.entropy_length() + self.checksum_length()) / 11
     }
 }
 
-#[derive(Debug)]
+// Official BIP39 wordlists bundled in `wordlist/`.
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Language {
     English,
     French,
+    Japanese,
+    Spanish,
+    ChineseSimplified,
+    ChineseTraditional,
+    Italian,
+    Korean,
+    Czech,
+    Portuguese,
 }
 
 impl Language {
+    // Some phrases are valid in both Chinese wordlists, which share many characters. They are
+    // parsed as simplified Chinese.
+    pub fn all() -> Vec<Language> {
+        vec![
+            Language::English,
+            Language::French,
+            Language::Japanese,
+            Language::Spanish,
+            Language::ChineseSimplified,
+            Language::ChineseTraditional,
+            Language::Italian,
+            Language::Korean,
+            Language::Czech,
+            Language::Portuguese,
+        ]
+    }
+
+    // Japanese words are separated by ideographic spaces, which NFKD maps to regular ones.
+    fn separator(&self) -> &'static str {
+        match *self {
+            Language::Japanese => "\u{3000}",
+            _ => " ",
+        }
+    }
+
     fn get_wordlist(&self) -> &'static Vec<&'static str> {
         match *self {
             Language::English => &*ENGLISH_WORDLIST,
             Language::French => &*FRENCH_WORDLIST,
+            Language::Japanese => &*JAPANESE_WORDLIST,
+            Language::Spanish => &*SPANISH_WORDLIST,
+            Language::ChineseSimplified => &*CHINESE_SIMPLIFIED_WORDLIST,
+            Language::ChineseTraditional => &*CHINESE_TRADITIONAL_WORDLIST,
+            Language::Italian => &*ITALIAN_WORDLIST,
+            Language::Korean => &*KOREAN_WORDLIST,
+            Language::Czech => &*CZECH_WORDLIST,
+            Language::Portuguese => &*PORTUGUESE_WORDLIST,
         }
     }
 
-    fn get_wordmap(&self) -> &'static HashMap<&'static str, u16> {
+    fn get_wordmap(&self) -> &'static HashMap<String, u16> {
         match *self {
             Language::English => &*ENGLISH_WORDMAP,
             Language::French => &*FRENCH_WORDMAP,
+            Language::Japanese => &*JAPANESE_WORDMAP,
+            Language::Spanish => &*SPANISH_WORDMAP,
+            Language::ChineseSimplified => &*CHINESE_SIMPLIFIED_WORDMAP,
+            Language::ChineseTraditional => &*CHINESE_TRADITIONAL_WORDMAP,
+            Language::Italian => &*ITALIAN_WORDMAP,
+            Language::Korean => &*KOREAN_WORDMAP,
+            Language::Czech => &*CZECH_WORDMAP,
+            Language::Portuguese => &*PORTUGUESE_WORDMAP,
         }
     }
 }
@@ -105,8 +207,10 @@ pub struct Seed {
 }
 
 impl Seed {
-    pub fn new(entropy: &[u8], pass: String) -> Self {
-        let salt = format!("mnemonic{}", pass);
+    // Both the phrase and the passphrase are NFKD normalized, as BIP39 requires.
+    pub fn new(phrase: &str, pass: &str) -> Self {
+        let phrase: String = phrase.nfkd().collect();
+        let salt = format!("mnemonic{}", pass.nfkd().collect::<String>());
 
         let mut bytes = vec![0u8; PBKDF2_BYTES];
 
@@ -116,7 +220,7 @@ impl Seed {
             DIGEST_ALG,
             PBKDF2_ROUNDS,
             salt.as_bytes(),
-            entropy,
+            phrase.as_bytes(),
             &mut bytes,
         );
 
@@ -177,10 +281,11 @@ impl Mnemonic {
             }
         }
 
-        let seed = Seed::new(&phrase.join(" ").as_bytes(), pass);
+        let phrase = phrase.join(lang.separator());
+        let seed = Seed::new(&phrase, &pass);
 
         Ok(Mnemonic {
-            phrase: phrase.join(" "),
+            phrase,
             lang,
             entropy,
             seed,
@@ -192,15 +297,15 @@ impl Mnemonic {
         T: Into<String>,
     {
         let pass = pass.into();
-        let phrase = phrase.into();
+        let phrase = phrase
+            .into()
+            .nfkd()
+            .collect::<String>()
+            .split_whitespace()
+            .collect::<Vec<&str>>()
+            .join(" ");
 
-        let typ = MnemonicType::from_word_count(
-            phrase
-                .split(" ")
-                .map(|s| s.to_owned())
-                .collect::<Vec<String>>()
-                .len(),
-        )?;
+        let typ = MnemonicType::from_word_count(phrase.split(" ").count())?;
 
         let checksum_length = typ.checksum_length();
 
@@ -209,7 +314,7 @@ impl Mnemonic {
         let mut indexes = Vec::new();
 
         for word in phrase.split(" ") {
-            match wordmap.get(&word) {
+            match wordmap.get(word) {
                 Some(index) => indexes.push(index),
                 None => return Err(Error::InvalidWord(word.to_owned())),
             }
@@ -240,16 +345,34 @@ impl Mnemonic {
             return Err(Error::InvalidChecksum);
         }
 
-        let seed = Seed::new(&phrase.as_bytes(), pass);
+        let seed = Seed::new(&phrase, &pass);
 
         Ok(Mnemonic {
-            phrase: phrase.to_owned(),
+            phrase,
             lang,
             entropy,
             seed,
         })
     }
 
+    // Parses a phrase of any bundled language. Errors of a language which knows every word of
+    // the phrase are the ones reported.
+    pub fn from_phrase(phrase: &str, pass: &str) -> Result<Self, Error> {
+        let mut error = None;
+
+        for lang in Language::all() {
+            match Mnemonic::from_string(phrase, lang, pass) {
+                Ok(mnemonic) => return Ok(mnemonic),
+                Err(e) => match error {
+                    None | Some(Error::InvalidWord(_)) => error = Some(e),
+                    _ => (),
+                },
+            }
+        }
+
+        Err(error.unwrap())
+    }
+
     pub fn phrase(&self) -> String {
         self.phrase.to_owned()
     }
@@ -258,3 +381,235 @@ impl Mnemonic {
         self.seed.to_owned()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bip32::XKeyPair;
+    use types::bitcoin::Network as BtcNetwork;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    // Test vectors of the BIP39 spec, all with the passphrase "TREZOR".
+    const VECTORS: &[(&str, &str, &str, &str)] = &[
+        (
+            "00000000000000000000000000000000",
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+            "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04",
+            "xprv9s21ZrQH143K3h3fDYiay8mocZ3afhfULfb5GX8kCBdno77K4HiA15Tg23wpbeF1pLfs1c5SPmYHrEpTuuRhxMwvKDwqdKiGJS9XFKzUsAF",
+        ),
+        (
+            "7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f",
+            "legal winner thank year wave sausage worth useful legal winner thank yellow",
+            "2e8905819b8723fe2c1d161860e5ee1830318dbf49a83bd451cfb8440c28bd6fa457fe1296106559a3c80937a1c1069be3a3a5bd381ee6260e8d9739fce1f607",
+            "xprv9s21ZrQH143K2gA81bYFHqU68xz1cX2APaSq5tt6MFSLeXnCKV1RVUJt9FWNTbrrryem4ZckN8k4Ls1H6nwdvDTvnV7zEXs2HgPezuVccsq",
+        ),
+        (
+            "80808080808080808080808080808080",
+            "letter advice cage absurd amount doctor acoustic avoid letter advice cage above",
+            "d71de856f81a8acc65e6fc851a38d4d7ec216fd0796d0a6827a3ad6ed5511a30fa280f12eb2e47ed2ac03b5c462a0358d18d69fe4f985ec81778c1b370b652a8",
+            "xprv9s21ZrQH143K2shfP28KM3nr5Ap1SXjz8gc2rAqqMEynmjt6o1qboCDpxckqXavCwdnYds6yBHZGKHv7ef2eTXy461PXUjBFQg6PrwY4Gzq",
+        ),
+        (
+            "ffffffffffffffffffffffffffffffff",
+            "zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo wrong",
+            "ac27495480225222079d7be181583751e86f571027b0497b5b5d11218e0a8a13332572917f0f8e5a589620c6f15b11c61dee327651a14c34e18231052e48c069",
+            "xprv9s21ZrQH143K2V4oox4M8Zmhi2Fjx5XK4Lf7GKRvPSgydU3mjZuKGCTg7UPiBUD7ydVPvSLtg9hjp7MQTYsW67rZHAXeccqYqrsx8LcXnyd",
+        ),
+        (
+            "000000000000000000000000000000000000000000000000",
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon agent",
+            "035895f2f481b1b0f01fcf8c289c794660b289981a78f8106447707fdd9666ca06da5a9a565181599b79f53b844d8a71dd9f439c52a3d7b3e8a79c906ac845fa",
+            "xprv9s21ZrQH143K3mEDrypcZ2usWqFgzKB6jBBx9B6GfC7fu26X6hPRzVjzkqkPvDqp6g5eypdk6cyhGnBngbjeHTe4LsuLG1cCmKJka5SMkmU",
+        ),
+        (
+            "0000000000000000000000000000000000000000000000000000000000000000",
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon art",
+            "bda85446c68413707090a52022edd26a1c9462295029f2e60cd7c4f2bbd3097170af7a4d73245cafa9c3cca8d561a7c3de6f5d4a10be8ed2a5e608d68f92fcc8",
+            "xprv9s21ZrQH143K32qBagUJAMU2LsHg3ka7jqMcV98Y7gVeVyNStwYS3U7yVVoDZ4btbRNf4h6ibWpY22iRmXq35qgLs79f312g2kj5539ebPM",
+        ),
+        (
+            "9e885d952ad362caeb4efe34a8e91bd2",
+            "ozone drill grab fiber curtain grace pudding thank cruise elder eight picnic",
+            "274ddc525802f7c828d8ef7ddbcdc5304e87ac3535913611fbbfa986d0c9e5476c91689f9c8a54fd55bd38606aa6a8595ad213d4c9c9f9aca3fb217069a41028",
+            "xprv9s21ZrQH143K2oZ9stBYpoaZ2ktHj7jLz7iMqpgg1En8kKFTXJHsjxry1JbKH19YrDTicVwKPehFKTbmaxgVEc5TpHdS1aYhB2s9aFJBeJH",
+        ),
+    ];
+
+    // Test vectors of the Japanese wordlist referenced by the BIP39 spec, from
+    // https://github.com/bip32JP/bip32JP.github.io/blob/master/test_JP_BIP39.json
+    const JAPANESE_VECTORS: &[(&str, &str, &str, &str)] = &[
+        (
+            "00000000000000000000000000000000",
+            "あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　あおぞら",
+            "㍍ガバヴァぱばぐゞちぢ十人十色",
+            "a262d6fb6122ecf45be09c50492b31f92e9beb7d9a845987a02cefda57a15f9c467a17872029a9e92299b5cbdf306e3a0ee620245cbd508959b6cb7ca637bd55",
+        ),
+        (
+            "808080808080808080808080808080808080808080808080",
+            "そとづら　あまど　おおう　あこがれる　いくぶん　けいけん　あたえる　いよく　そとづら　あまど　おおう　あこがれる　いくぶん　けいけん　あたえる　いよく　そとづら　いきなり",
+            "㍍ガバヴァぱばぐゞちぢ十人十色",
+            "aec0f8d3167a10683374c222e6e632f2940c0826587ea0a73ac5d0493b6a632590179a6538287641a9fc9df8e6f24e01bf1be548e1f74fd7407ccd72ecebe425",
+        ),
+        (
+            "ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+            "われる　われる　われる　われる　われる　われる　われる　われる　われる　われる　われる　われる　われる　われる　われる　われる　われる　われる　われる　われる　われる　われる　われる　らいう",
+            "㍍ガバヴァぱばぐゞちぢ十人十色",
+            "a44ba7054ac2f9226929d56505a51e13acdaa8a9097923ca07ea465c4c7e294c038f3f4e7e4b373726ba0057191aced6e48ac8d183f3a11569c426f0de414623",
+        ),
+    ];
+
+    // The spec only has vectors for English and Japanese. These are the entropy of the last English
+    // vector in the other wordlists, with the passphrase "TREZOR".
+    const WORDLIST_VECTORS: &[(Language, &str, &str)] = &[
+        (
+            Language::Spanish,
+            "obra diadema gorila farmacia colgar gorra pausa talar cocina duda dragón optar",
+            "fcf6ebfc7d9eebab56ca868cbd2d5d05a6f2142ba903c52855dad4ab8c0c2cf6b4e047a2dd97cf382ae717dc18d155a45fc798e6f0a0b89971a4224e2a285701",
+        ),
+        (
+            Language::ChineseSimplified,
+            "蒙 台 脱 纪 构 硫 浆 霉 感 仅 鱼 汤",
+            "decd71d2824a1bbadf8c3942f43504a648a8db5f1cac0ae1d0f787728353002a12644b1a6b725147c91682e7f33aec13493b9a779a7dd8ee15a5d10ab21d49e5",
+        ),
+        (
+            Language::ChineseTraditional,
+            "蒙 台 脫 紀 構 硫 漿 黴 感 僅 魚 湯",
+            "27ca577f0318b6c6067acce7aefacd12bc9fbbc8e365fdc16bfc0ffd76379b0768dc56877f19eee4c1222dfb5a94a5516c5707e6a6ad070af9a0fe7f7799ac5e",
+        ),
+        (
+            Language::Italian,
+            "pesista educare imballo formica curvo imbevuto raddoppio sussurro croce eppure epilogo poligono",
+            "4ffd8b7879c0c6d7eee14682a26465d6429b8b921d6ea3299fb8a448d84d19b47ead5b23fd14449539cbd358abd19a23560dbd8c4bf6c153d98ea0fce7f474de",
+        ),
+        (
+            Language::Korean,
+            "원고 물질 생일 부산 마요네즈 생활 일찍 큰절 동화책 반성 반드시 의식",
+            "8d148c7f8ed529d7a88fe2bc8bff574b56406f9928ab5426df793f4d3a5121c7c6974c856ad20f66ecf04fbecd3bc025912b3e41d500f1e5be896505e01d08d6",
+        ),
+        (
+            Language::Czech,
+            "pokoj jogurt malovat kroupa holub malvice rachot uznat hnout kasa karamel potupa",
+            "f3922b8086d559436ba2d04bc2aae4174e6504d7d4d451f7282d0b41a1b8cc958b45a896985e0b9316ad09c62f7d62dac85bc3d3e2e2423bcad3336412fd33f8",
+        ),
+        (
+            Language::Portuguese,
+            "mexicano crosta farpa empolgar chatice fartura olaria sogro centeio defesa dedal multar",
+            "1f0397e6d2aaf8d6867d648e9bc27b12a4ee1b61a47fb63c6676c153c472d708f02344ac56fd1a8e135e18cce4eef711e7e88529bd6c54b90715e9b3d9fb8467",
+        ),
+    ];
+
+    #[test]
+    fn spec_vectors() {
+        for &(entropy, phrase, seed, xprv) in VECTORS {
+            let mnemonic = Mnemonic::from_string(phrase, Language::English, "TREZOR").unwrap();
+
+            assert_eq!(entropy, hex(&mnemonic.entropy));
+            assert_eq!(seed, hex(mnemonic.seed().as_bytes()));
+            assert_eq!(
+                xprv,
+                XKeyPair::from_seed(mnemonic.seed(), BtcNetwork::Mainnet)
+                    .unwrap()
+                    .xprv()
+                    .to_string()
+            );
+        }
+    }
+
+    #[test]
+    fn all_lengths() {
+        for &count in &[12, 15, 18, 21, 24] {
+            let typ = MnemonicType::from_word_count(count).unwrap();
+            let mnemonic = Mnemonic::new(typ, Language::English, "").unwrap();
+
+            assert_eq!(count, mnemonic.phrase().split(" ").count());
+            assert_eq!(typ.entropy_length() / 8, mnemonic.entropy.len());
+
+            let parsed =
+                Mnemonic::from_string(mnemonic.phrase(), Language::English, String::new()).unwrap();
+            assert_eq!(mnemonic.entropy, parsed.entropy);
+        }
+
+        assert!(MnemonicType::from_word_count(13).is_err());
+    }
+
+    #[test]
+    fn invalid_checksum() {
+        match Mnemonic::from_phrase("zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo", "") {
+            Err(Error::InvalidChecksum) => (),
+            res => panic!("unexpected result {:?}", res),
+        }
+    }
+
+    #[test]
+    fn passphrase_changes_seed() {
+        let phrase = VECTORS[0].1;
+
+        let with_passphrase = Mnemonic::from_phrase(phrase, "TREZOR").unwrap();
+        let without_passphrase = Mnemonic::from_phrase(phrase, "").unwrap();
+
+        assert_eq!(VECTORS[0].2, hex(with_passphrase.seed().as_bytes()));
+        assert_ne!(
+            with_passphrase.seed().as_bytes(),
+            without_passphrase.seed().as_bytes()
+        );
+    }
+
+    #[test]
+    fn detects_language_and_normalizes() {
+        let seed = "d322acd69a849cce8719674eeb7cd76520de01ea35210012a44a5dcc19faf285202c3fb3c749a46d338ad54ddd398029ee308ee352a89f65180dbd3ff750dd50";
+
+        // Composed and decomposed accents, and extra whitespace.
+        let composed = "monument d\u{e9}penser f\u{e9}roce entasser com\u{e9}die ferveur optique \
+                        sonnette codifier discuter dioxyde nerveux";
+        let decomposed = "monument  de\u{301}penser fe\u{301}roce entasser come\u{301}die ferveur \
+                          optique sonnette codifier discuter dioxyde nerveux ";
+
+        for phrase in &[composed, decomposed] {
+            let mnemonic = Mnemonic::from_phrase(phrase, "TREZOR").unwrap();
+
+            assert_eq!(Language::French, mnemonic.lang);
+            assert_eq!("9e885d952ad362caeb4efe34a8e91bd2", hex(&mnemonic.entropy));
+            assert_eq!(seed, hex(mnemonic.seed().as_bytes()));
+        }
+    }
+
+    #[test]
+    fn wordlists() {
+        for lang in Language::all() {
+            assert_eq!(2048, lang.get_wordlist().len());
+            assert_eq!(2048, lang.get_wordmap().len());
+        }
+    }
+
+    #[test]
+    fn japanese_spec_vectors() {
+        for &(entropy, phrase, pass, seed) in JAPANESE_VECTORS {
+            let mnemonic = Mnemonic::from_phrase(phrase, pass).unwrap();
+
+            assert_eq!(Language::Japanese, mnemonic.lang);
+            assert_eq!(entropy, hex(&mnemonic.entropy));
+            assert_eq!(seed, hex(mnemonic.seed().as_bytes()));
+        }
+
+        let mnemonic = Mnemonic::new(MnemonicType::Words12, Language::Japanese, "").unwrap();
+        let parsed = Mnemonic::from_phrase(&mnemonic.phrase(), "").unwrap();
+
+        assert_eq!(12, mnemonic.phrase().split('\u{3000}').count());
+        assert_eq!(mnemonic.entropy, parsed.entropy);
+        assert_eq!(mnemonic.seed().as_bytes(), parsed.seed().as_bytes());
+    }
+
+    #[test]
+    fn wordlist_vectors() {
+        for &(lang, phrase, seed) in WORDLIST_VECTORS {
+            let mnemonic = Mnemonic::from_phrase(phrase, "TREZOR").unwrap();
+
+            assert_eq!(lang, mnemonic.lang);
+            assert_eq!(VECTORS[6].0, hex(&mnemonic.entropy));
+            assert_eq!(seed, hex(mnemonic.seed().as_bytes()));
+        }
+    }
+}
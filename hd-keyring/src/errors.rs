@@ -1,5 +1,6 @@
 use bip39::Error as Bip39Error;
 use secp256k1::Error as Secp256k1Error;
+use serde_json::Error as JsonError;
 use std::io::Error as IoError;
 
 #[derive(Debug, Fail)]
@@ -22,6 +23,28 @@ pub enum Error {
     IoError(#[cause] IoError),
     #[fail(display = "invalid network")]
     InvalidNetwork,
+    #[fail(display = "invalid address {}", _0)]
+    InvalidAddress(String),
+    #[fail(display = "no payment was paid to address {}", _0)]
+    UnknownAddress(String),
+    #[fail(display = "insufficient funds")]
+    InsufficientFunds,
+    #[fail(display = "unknown store {}", _0)]
+    UnknownStore(String),
+    #[fail(display = "invalid signer address {}", _0)]
+    InvalidSignerAddress(String),
+    #[fail(display = "invalid signer response")]
+    InvalidSignerResponse,
+    #[fail(display = "signer error: {}", _0)]
+    SignerError(String),
+    #[fail(display = "unauthenticated signer request")]
+    UnauthenticatedSignerRequest,
+    #[fail(display = "{}", _0)]
+    JsonError(#[cause] JsonError),
+    #[fail(display = "invalid multisig")]
+    InvalidMultisig,
+    #[fail(display = "chain doesn't support segwit")]
+    SegwitUnsupported,
 }
 
 impl From<Bip39Error> for Error {
@@ -41,3 +64,9 @@ impl From<IoError> for Error {
         Error::IoError(e)
     }
 }
+
+impl From<JsonError> for Error {
+    fn from(e: JsonError) -> Error {
+        Error::JsonError(e)
+    }
+}
@@ -1,12 +1,13 @@
 use std::str::FromStr;
 
 use bip39::{Language, Mnemonic, MnemonicType};
+use secp256k1::key::PublicKey;
 
-use bip32::{DerivationPath, Index, XKeyPair};
+use bip32::{DerivationPath, Index, XKeyPair, Xpub};
 use errors::Error;
-use wallet::Wallet;
+use wallet::{self, Wallet};
 
-use types::bitcoin::Network as BtcNetwork;
+use types::{bitcoin::Network as BtcNetwork, currency::Crypto};
 
 #[derive(Debug)]
 pub struct HdKeyring {
@@ -21,27 +22,31 @@ pub struct HdKeyring {
 impl HdKeyring {
     pub fn new(
         path: &str,
+        passphrase: &str,
         number_of_accounts: u32,
         btc_network: BtcNetwork,
     ) -> Result<Self, Error> {
         let path = DerivationPath::from_str(path)?;
 
-        let mnemonic = Mnemonic::new(MnemonicType::Words12, Language::English, String::from(""))?;
+        let mnemonic = Mnemonic::new(MnemonicType::Words12, Language::English, passphrase)?;
 
         let mut keyring = HdKeyring::init_from_mnemonic(mnemonic, &path, btc_network)?;
         keyring.load_wallets(number_of_accounts)?;
         Ok(keyring)
     }
 
+    // `mnemonic` can be in any bundled language, and `passphrase` is empty unless the wallet
+    // was created with one.
     pub fn from_mnemonic(
         path: &str,
         mnemonic: &str,
+        passphrase: &str,
         number_of_accounts: u32,
         btc_network: BtcNetwork,
     ) -> Result<Self, Error> {
         let path = DerivationPath::from_str(path)?;
 
-        let mnemonic = Mnemonic::from_string(mnemonic, Language::English, "")?;
+        let mnemonic = Mnemonic::from_phrase(mnemonic, passphrase)?;
         let mut keyring = HdKeyring::init_from_mnemonic(mnemonic, &path, btc_network)?;
         keyring.load_wallets(number_of_accounts)?;
         Ok(keyring)
@@ -75,12 +80,53 @@ impl HdKeyring {
         Ok(())
     }
 
+    // Extended private key of the account at `hd_path`.
+    pub fn xprv(&self) -> String {
+        self.root.xprv().to_string()
+    }
+
+    // Extended public key of the account at `hd_path`, which its addresses can be derived from.
+    pub fn xpub(&self) -> String {
+        self.root.xpub().to_string()
+    }
+
     pub fn get_wallet_by_index(&self, index: u32) -> Result<Wallet, Error> {
         let key_pair = self.root.derive(&Index::Soft(index - 1))?;
         Wallet::from_secret_key(*key_pair.xprv().as_raw(), self.btc_network)
     }
 }
 
+// An account known by its extended public key only, deriving the same addresses as an `HdKeyring`
+// at its path without holding the seed.
+#[derive(Debug)]
+pub struct XpubAccount {
+    xpub: Xpub,
+    btc_network: BtcNetwork,
+}
+
+impl XpubAccount {
+    pub fn from_xpub(xpub: &str, btc_network: BtcNetwork) -> Result<Self, Error> {
+        Ok(XpubAccount {
+            xpub: Xpub::from_str(xpub)?,
+            btc_network,
+        })
+    }
+
+    // Indexes are 1-based, as in `HdKeyring::get_wallet_by_index`.
+    pub fn public_key(&self, index: u32) -> Result<PublicKey, Error> {
+        if index == 0 {
+            return Err(Error::InvalidDerivation);
+        }
+
+        Ok(*self.xpub.ckd_pub(&Index::Soft(index - 1))?.as_raw())
+    }
+
+    pub fn address(&self, index: u32, currency: &Crypto) -> Result<String, Error> {
+        let public_key = self.public_key(index)?;
+        Ok(wallet::address(&public_key, currency, self.btc_network))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -88,7 +134,7 @@ mod tests {
 
     #[test]
     fn create_new_keyring() {
-        HdKeyring::new("m/44'/60'/0'/0", 1, BtcNetwork::Mainnet).unwrap();
+        HdKeyring::new("m/44'/60'/0'/0", "", 1, BtcNetwork::Mainnet).unwrap();
     }
 
     #[test]
@@ -98,6 +144,7 @@ mod tests {
         let keyring = HdKeyring::from_mnemonic(
             "m/44'/60'/0'/0",
             "addict else general weird gospel excite void debate north include exercise liberty",
+            "",
             1,
             BtcNetwork::Mainnet,
         )
@@ -115,6 +162,7 @@ mod tests {
         let keyring = HdKeyring::from_mnemonic(
             "m/44'/0'/0'/0",
             "addict else general weird gospel excite void debate north include exercise liberty",
+            "",
             1,
             BtcNetwork::Mainnet,
         )
@@ -125,6 +173,33 @@ mod tests {
         }
     }
 
+    #[test]
+    fn bitcoin_like_wallet_from_mnemonic() {
+        let keyring = HdKeyring::from_mnemonic(
+            "m/44'/0'/0'/0",
+            "addict else general weird gospel excite void debate north include exercise liberty",
+            "",
+            1,
+            BtcNetwork::Mainnet,
+        )
+        .unwrap();
+
+        let wallet = &keyring.wallets[0];
+
+        assert_eq!(
+            "LTJ96tme8dX4pKU3BU8e3Np2ofiSjZtHMv",
+            wallet.get_address(&Crypto::Ltc)
+        );
+        assert_eq!(
+            "195BqgTp3yH1ZWmt1L9LmMkGbTMAc1vGPN",
+            wallet.get_address(&Crypto::Bch)
+        );
+        assert_eq!(
+            "DDDHNwQTMPBJ6WxUjv8uK7usUb5Tx9Ajhy",
+            wallet.get_address(&Crypto::Doge)
+        );
+    }
+
     #[test]
     fn get_wallet_at_specific_index() {
         let index = 100;
@@ -133,6 +208,7 @@ mod tests {
         let keyring = HdKeyring::from_mnemonic(
             "m/44'/60'/0'/0",
             "addict else general weird gospel excite void debate north include exercise liberty",
+            "",
             0,
             BtcNetwork::Mainnet,
         )
@@ -142,4 +218,34 @@ mod tests {
 
         assert_eq!(address, wallet.get_eth_address());
     }
+
+    #[test]
+    fn xpub_account_derives_keyring_addresses() {
+        let keyring = HdKeyring::from_mnemonic(
+            "m/44'/0'/0'/0",
+            "addict else general weird gospel excite void debate north include exercise liberty",
+            "",
+            0,
+            BtcNetwork::Mainnet,
+        )
+        .unwrap();
+        let account = XpubAccount::from_xpub(&keyring.xpub(), BtcNetwork::Mainnet).unwrap();
+
+        assert_eq!(
+            "195BqgTp3yH1ZWmt1L9LmMkGbTMAc1vGPN",
+            account.address(1, &Crypto::Btc).unwrap()
+        );
+
+        for index in &[1, 100] {
+            let wallet = keyring.get_wallet_by_index(*index).unwrap();
+
+            assert_eq!(wallet.public_key, account.public_key(*index).unwrap());
+            assert_eq!(
+                wallet.get_address(&Crypto::Eth),
+                account.address(*index, &Crypto::Eth).unwrap()
+            );
+        }
+
+        assert!(account.public_key(0).is_err());
+    }
 }
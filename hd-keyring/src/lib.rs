@@ -9,14 +9,20 @@ extern crate rand;
 extern crate regex;
 extern crate ring;
 extern crate ripemd160;
+extern crate rustc_hex;
 extern crate secp256k1;
 extern crate sha2;
 extern crate tiny_keccak;
+extern crate unicode_normalization;
 #[macro_use]
 extern crate failure;
 #[macro_use]
 extern crate lazy_static;
 extern crate rust_base58;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
 
 extern crate types;
 
@@ -24,9 +30,12 @@ mod bip32;
 mod bip39;
 mod errors;
 mod keyring;
+pub mod multisig;
+pub mod recovery;
+pub mod signer;
 mod wallet;
 
 pub use bip32::{DerivationPath, Index, XKeyPair, Xprv, Xpub};
 pub use errors::Error;
-pub use keyring::HdKeyring;
+pub use keyring::{HdKeyring, XpubAccount};
 pub use wallet::Wallet;
@@ -0,0 +1,141 @@
+// M-of-N multisig wallets, spent through P2WSH with their keys sorted as in BIP67.
+
+use std::str::FromStr;
+
+use rustc_hex::FromHex;
+use secp256k1::{key::PublicKey, Secp256k1};
+use sha2::{Digest, Sha256};
+
+use bip32::{Index, Xpub};
+use errors::Error;
+use types::bitcoin::{bech32, ChainParams};
+
+const OP_0: u8 = 0x00;
+const OP_PUSHBYTES_32: u8 = 0x20;
+const OP_PUSHBYTES_33: u8 = 0x21;
+// OP_1 to OP_16 push their own number.
+const OP_RESERVED: u8 = 0x50;
+const OP_CHECKMULTISIG: u8 = 0xae;
+
+pub const MAX_KEYS: usize = 15;
+
+// Sizes used to price spending a multisig output: outpoint, empty script_sig and sequence, then
+// the witness (counted a quarter) of signatures and the script.
+const P2WSH_INPUT_BASE_SIZE: usize = 41;
+const SIGNATURE_SIZE: usize = 73;
+
+pub fn witness_script(threshold: usize, public_keys: &[PublicKey]) -> Result<Vec<u8>, Error> {
+    if threshold == 0 || threshold > public_keys.len() || public_keys.len() > MAX_KEYS {
+        return Err(Error::InvalidMultisig);
+    }
+
+    let mut keys: Vec<[u8; 33]> = public_keys.iter().map(|key| key.serialize()).collect();
+    keys.sort();
+    keys.dedup();
+
+    if keys.len() != public_keys.len() {
+        return Err(Error::InvalidMultisig);
+    }
+
+    let mut script = vec![OP_RESERVED + threshold as u8];
+
+    for key in keys.iter() {
+        script.push(OP_PUSHBYTES_33);
+        script.extend_from_slice(&key[..]);
+    }
+
+    script.push(OP_RESERVED + keys.len() as u8);
+    script.push(OP_CHECKMULTISIG);
+
+    Ok(script)
+}
+
+// Signatures needed to spend a script built by `witness_script`.
+pub fn threshold(witness_script: &[u8]) -> usize {
+    (witness_script[0] - OP_RESERVED) as usize
+}
+
+pub fn p2wsh_script_pubkey(witness_script: &[u8]) -> Vec<u8> {
+    let mut script = vec![OP_0, OP_PUSHBYTES_32];
+    script.extend_from_slice(&Sha256::digest(witness_script));
+    script
+}
+
+pub fn p2wsh_address(chain: &ChainParams, witness_script: &[u8]) -> Result<String, Error> {
+    let hrp = chain.bech32_hrp.ok_or(Error::SegwitUnsupported)?;
+
+    Ok(bech32::encode_segwit_address(
+        hrp,
+        &Sha256::digest(witness_script),
+    ))
+}
+
+// Virtual size of an input spending `witness_script` with all the signatures it needs.
+pub fn input_vsize(witness_script: &[u8]) -> usize {
+    let witness_size =
+        1 + 1 + threshold(witness_script) * SIGNATURE_SIZE + 1 + witness_script.len();
+
+    P2WSH_INPUT_BASE_SIZE + (witness_size + 3) / 4
+}
+
+pub fn parse_public_key(hex: &str) -> Result<PublicKey, Error> {
+    let bytes = hex.from_hex().map_err(|_| Error::InvalidKeyLength)?;
+
+    let secp = Secp256k1::new();
+    Ok(PublicKey::from_slice(&secp, &bytes)?)
+}
+
+// Keys of cosigners at a payment's 1-based index, from the xpubs of their receive chains.
+pub fn derive_public_keys(xpubs: &[String], index: u32) -> Result<Vec<PublicKey>, Error> {
+    if index == 0 {
+        return Err(Error::InvalidDerivation);
+    }
+
+    xpubs
+        .iter()
+        .map(|xpub| {
+            let child = Xpub::from_str(xpub)?.ckd_pub(&Index::Soft(index - 1))?;
+            Ok(*child.as_raw())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustc_hex::ToHex;
+    use types::{bitcoin::Network as BtcNetwork, currency::Crypto};
+
+    const KEY_1: &str = "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+    const KEY_2: &str = "02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5";
+
+    #[test]
+    fn build_sorted_multisig_scripts() {
+        let keys = vec![
+            parse_public_key(KEY_2).unwrap(),
+            parse_public_key(KEY_1).unwrap(),
+        ];
+        let script = witness_script(2, &keys).unwrap();
+
+        assert_eq!(format!("5221{}21{}52ae", KEY_1, KEY_2), script.to_hex());
+        assert_eq!(2, threshold(&script));
+
+        let chain = ChainParams::new(Crypto::Btc, BtcNetwork::Mainnet).unwrap();
+        assert_eq!(
+            "bc1qnwvyc7aw8m7acw3lpgs0lqdlaz0drls8luf72cs5nmn9f0kcghdse7d78q",
+            p2wsh_address(&chain, &script).unwrap()
+        );
+
+        let chain = ChainParams::new(Crypto::Doge, BtcNetwork::Mainnet).unwrap();
+        assert!(p2wsh_address(&chain, &script).is_err());
+    }
+
+    #[test]
+    fn reject_invalid_multisig() {
+        let key = parse_public_key(KEY_1).unwrap();
+
+        assert!(witness_script(0, &[key]).is_err());
+        assert!(witness_script(2, &[key]).is_err());
+        assert!(witness_script(1, &[key, key]).is_err());
+    }
+}
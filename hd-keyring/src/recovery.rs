@@ -0,0 +1,407 @@
+use std::{cmp, str::FromStr};
+
+use byteorder::{LittleEndian, WriteBytesExt};
+use rust_base58::FromBase58;
+use secp256k1::{Message, Secp256k1};
+
+use bip32::{DerivationPath, Index, XKeyPair};
+use bip39::Mnemonic;
+use errors::Error;
+use types::{
+    bitcoin::{Address as BtcAddress, ChainParams, Network as BtcNetwork, VarInt},
+    currency::Crypto,
+    H256,
+};
+use wallet::Wallet;
+
+const OP_DUP: u8 = 0x76;
+const OP_HASH160: u8 = 0xa9;
+const OP_EQUALVERIFY: u8 = 0x88;
+const OP_CHECKSIG: u8 = 0xac;
+const OP_PUSHBYTES_20: u8 = 0x14;
+
+// Wallets restoring an account stop looking for payments after this many unused addresses in a
+// row (BIP44).
+pub const GAP_LIMIT: u32 = 20;
+
+// Sizes of a P2PKH transaction with a single output, used to price the sweep.
+const TX_OVERHEAD_SIZE: u64 = 10;
+const P2PKH_INPUT_SIZE: u64 = 148;
+const P2PKH_OUTPUT_SIZE: u64 = 34;
+
+// What a store's wallet can be recovered from without Finch: the mnemonic, and where each
+// payment's key was derived.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WalletExport {
+    pub mnemonic: String,
+    #[serde(default)]
+    pub passphrase: String,
+    pub accounts: Vec<ExportedAccount>,
+    pub payments: Vec<ExportedPayment>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportedAccount {
+    pub hd_path: String,
+    pub xprv: String,
+    // What wallets restoring the account have to look past, invoices expiring unpaid leaving
+    // their addresses unused.
+    #[serde(default = "default_gap_limit")]
+    pub gap_limit: u32,
+}
+
+fn default_gap_limit() -> u32 {
+    GAP_LIMIT
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportedPayment {
+    pub id: String,
+    pub crypto: Crypto,
+    pub btc_network: BtcNetwork,
+    pub address: String,
+    pub hd_path: String,
+    pub index: u32,
+}
+
+#[derive(Debug)]
+pub struct RecoveredKey {
+    pub payment: ExportedPayment,
+    pub wallet: Wallet,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Utxo {
+    pub txid: H256,
+    pub vout: u32,
+    pub address: String,
+    // In satoshi.
+    pub value: u64,
+}
+
+// The gap limit that finds every paid address of an account, given their indexes.
+pub fn gap_limit(paid_indexes: &[u32]) -> u32 {
+    let mut indexes = paid_indexes.to_vec();
+    indexes.sort();
+    indexes.dedup();
+
+    let mut last = 0;
+    let mut limit = GAP_LIMIT;
+
+    for index in indexes {
+        limit = cmp::max(limit, index - last);
+        last = index;
+    }
+
+    limit
+}
+
+// Keys recovered from an export, and the payments whose address the derived key doesn't match,
+// such as those paid to multisig addresses.
+#[derive(Debug)]
+pub struct Recovery {
+    pub keys: Vec<RecoveredKey>,
+    pub mismatches: Vec<ExportedPayment>,
+}
+
+// Derives the key of every payment again, and checks it against the address the payment was
+// paid to.
+pub fn recover_keys(export: &WalletExport) -> Result<Recovery, Error> {
+    let mnemonic = Mnemonic::from_phrase(&export.mnemonic, &export.passphrase)?;
+    let mut recovery = Recovery {
+        keys: Vec::new(),
+        mismatches: Vec::new(),
+    };
+
+    for payment in export
+        .payments
+        .iter()
+        .filter(|payment| payment.crypto != Crypto::BtcLightning)
+    {
+        let path = DerivationPath::from_str(&payment.hd_path)?;
+
+        let key_pair = XKeyPair::from_seed(mnemonic.seed(), payment.btc_network)?
+            .from_path(&path)?
+            .derive(&Index::Soft(payment.index - 1))?;
+        let wallet = Wallet::from_secret_key(*key_pair.xprv().as_raw(), payment.btc_network)?;
+
+        if wallet
+            .get_address(&payment.crypto)
+            .eq_ignore_ascii_case(&payment.address)
+        {
+            recovery.keys.push(RecoveredKey {
+                payment: payment.clone(),
+                wallet,
+            });
+        } else {
+            recovery.mismatches.push(payment.clone());
+        }
+    }
+
+    Ok(recovery)
+}
+
+// Builds a signed transaction spending every given output of the chain to `to`.
+pub fn sweep(
+    chain: &ChainParams,
+    keys: &[RecoveredKey],
+    utxos: &[Utxo],
+    to: &BtcAddress,
+    fee_per_byte: u64,
+) -> Result<Vec<u8>, Error> {
+    if !to.is_valid_for(chain) {
+        return Err(Error::InvalidAddress(to.to_string()));
+    }
+
+    let mut inputs = Vec::new();
+
+    for utxo in utxos {
+        let key = keys
+            .iter()
+            .find(|key| {
+                key.payment.crypto == chain.crypto
+                    && key.payment.btc_network == chain.network
+                    && key.payment.address == utxo.address
+            })
+            .ok_or_else(|| Error::UnknownAddress(utxo.address.clone()))?;
+
+        inputs.push((utxo, &key.wallet, p2pkh_script(&utxo.address)?));
+    }
+
+    let total: u64 = utxos.iter().map(|utxo| utxo.value).sum();
+    let fee = fee_per_byte
+        * (TX_OVERHEAD_SIZE + P2PKH_INPUT_SIZE * inputs.len() as u64 + P2PKH_OUTPUT_SIZE);
+
+    if inputs.is_empty() || total <= fee {
+        return Err(Error::InsufficientFunds);
+    }
+
+    let output_script = p2pkh_script(to)?;
+    let mut output = Vec::new();
+    output.write_u64::<LittleEndian>(total - fee).unwrap();
+    VarInt::from(output_script.len()).serialize(&mut output);
+    output.extend_from_slice(&output_script);
+
+    let sighash_type = chain.sighash_type();
+    let secp = Secp256k1::new();
+
+    let mut script_sigs = Vec::new();
+
+    for idx in 0..inputs.len() {
+        let hash = match chain.fork_id {
+            Some(_) => forkid_signature_hash(&inputs, &output, idx, sighash_type),
+            // Only the signed input carries its previous script while hashing.
+            None => {
+                let mut serialized = serialize(&inputs, &output, |i| {
+                    if i == idx {
+                        inputs[i].2.clone()
+                    } else {
+                        Vec::new()
+                    }
+                });
+                serialized.write_u32::<LittleEndian>(sighash_type).unwrap();
+                H256::from_data(&serialized)
+            }
+        };
+
+        let wallet = inputs[idx].1;
+        let signature = secp.sign(&Message::from(hash.0), &wallet.secret_key);
+
+        let mut der_signature = signature.serialize_der(&secp);
+        der_signature.push(sighash_type as u8);
+        let public_key = wallet.public_key.serialize();
+
+        let mut script_sig = Vec::new();
+        script_sig.push(der_signature.len() as u8);
+        script_sig.extend_from_slice(&der_signature);
+        script_sig.push(public_key.len() as u8);
+        script_sig.extend_from_slice(&public_key);
+
+        script_sigs.push(script_sig);
+    }
+
+    Ok(serialize(&inputs, &output, |i| script_sigs[i].clone()))
+}
+
+fn p2pkh_script(address: &str) -> Result<Vec<u8>, Error> {
+    let decoded = address
+        .from_base58()
+        .map_err(|_| Error::InvalidAddress(address.to_owned()))?;
+
+    if decoded.len() != 25 {
+        return Err(Error::InvalidAddress(address.to_owned()));
+    }
+
+    let mut script = vec![OP_DUP, OP_HASH160, OP_PUSHBYTES_20];
+    script.extend_from_slice(&decoded[1..21]);
+    script.push(OP_EQUALVERIFY);
+    script.push(OP_CHECKSIG);
+
+    Ok(script)
+}
+
+fn serialize_outpoint(utxo: &Utxo, stream: &mut Vec<u8>) {
+    let mut hash = utxo.txid;
+    hash.reverse();
+    stream.extend_from_slice(&hash);
+    stream.write_u32::<LittleEndian>(utxo.vout).unwrap();
+}
+
+fn serialize<F>(inputs: &[(&Utxo, &Wallet, Vec<u8>)], output: &[u8], script_sig: F) -> Vec<u8>
+where
+    F: Fn(usize) -> Vec<u8>,
+{
+    let mut stream = Vec::new();
+    stream.write_u32::<LittleEndian>(1).unwrap();
+
+    VarInt::from(inputs.len()).serialize(&mut stream);
+
+    for (i, (utxo, _, _)) in inputs.iter().enumerate() {
+        serialize_outpoint(utxo, &mut stream);
+
+        let script = script_sig(i);
+        VarInt::from(script.len()).serialize(&mut stream);
+        stream.extend_from_slice(&script);
+        stream.write_u32::<LittleEndian>(0xFFFFFFFF).unwrap();
+    }
+
+    VarInt::from(1).serialize(&mut stream);
+    stream.extend_from_slice(output);
+
+    stream.write_u32::<LittleEndian>(0).unwrap();
+    stream
+}
+
+// BIP143 style digest used by chains with SIGHASH_FORKID.
+fn forkid_signature_hash(
+    inputs: &[(&Utxo, &Wallet, Vec<u8>)],
+    output: &[u8],
+    idx: usize,
+    sighash_type: u32,
+) -> H256 {
+    let mut prevouts = Vec::new();
+    let mut sequences = Vec::new();
+
+    for (utxo, _, _) in inputs.iter() {
+        serialize_outpoint(utxo, &mut prevouts);
+        sequences.write_u32::<LittleEndian>(0xFFFFFFFF).unwrap();
+    }
+
+    let (utxo, _, ref script_code) = inputs[idx];
+
+    let mut serialized = Vec::new();
+    serialized.write_u32::<LittleEndian>(1).unwrap();
+    serialized.extend_from_slice(&H256::from_data(&prevouts));
+    serialized.extend_from_slice(&H256::from_data(&sequences));
+    serialize_outpoint(utxo, &mut serialized);
+    VarInt::from(script_code.len()).serialize(&mut serialized);
+    serialized.extend_from_slice(script_code);
+    serialized.write_u64::<LittleEndian>(utxo.value).unwrap();
+    serialized.write_u32::<LittleEndian>(0xFFFFFFFF).unwrap();
+    serialized.extend_from_slice(&H256::from_data(output));
+    serialized.write_u32::<LittleEndian>(0).unwrap();
+    serialized.write_u32::<LittleEndian>(sighash_type).unwrap();
+    H256::from_data(&serialized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MNEMONIC: &str =
+        "addict else general weird gospel excite void debate north include exercise liberty";
+
+    fn export(payments: Vec<ExportedPayment>) -> WalletExport {
+        WalletExport {
+            mnemonic: String::from(MNEMONIC),
+            passphrase: String::new(),
+            accounts: Vec::new(),
+            payments,
+        }
+    }
+
+    fn btc_payment() -> ExportedPayment {
+        ExportedPayment {
+            id: String::from("btc"),
+            crypto: Crypto::Btc,
+            btc_network: BtcNetwork::Mainnet,
+            address: String::from("195BqgTp3yH1ZWmt1L9LmMkGbTMAc1vGPN"),
+            hd_path: String::from("m/44'/0'/0'/0"),
+            index: 1,
+        }
+    }
+
+    #[test]
+    fn recover_keys_of_payments() {
+        let eth_payment = ExportedPayment {
+            id: String::from("eth"),
+            crypto: Crypto::Eth,
+            btc_network: BtcNetwork::Mainnet,
+            address: String::from("0x41cf7938a02b9b27795a8d28c2de028aa86e8ecb"),
+            hd_path: String::from("m/44'/60'/0'/0"),
+            index: 100,
+        };
+
+        let recovery = recover_keys(&export(vec![btc_payment(), eth_payment])).unwrap();
+
+        assert_eq!(2, recovery.keys.len());
+        assert_eq!("btc", recovery.keys[0].payment.id);
+        assert_eq!("eth", recovery.keys[1].payment.id);
+        assert!(recovery.mismatches.is_empty());
+    }
+
+    #[test]
+    fn recover_keys_skips_mismatched_addresses() {
+        let mut mismatched = btc_payment();
+        mismatched.id = String::from("mismatched");
+        mismatched.index = 2;
+
+        let recovery = recover_keys(&export(vec![mismatched, btc_payment()])).unwrap();
+
+        assert_eq!(1, recovery.keys.len());
+        assert_eq!("btc", recovery.keys[0].payment.id);
+        assert_eq!(1, recovery.mismatches.len());
+        assert_eq!("mismatched", recovery.mismatches[0].id);
+    }
+
+    #[test]
+    fn gap_limit_covers_runs_of_unpaid_addresses() {
+        assert_eq!(GAP_LIMIT, gap_limit(&[]));
+        assert_eq!(GAP_LIMIT, gap_limit(&[1, 2, 21]));
+        assert_eq!(25, gap_limit(&[25]));
+        assert_eq!(31, gap_limit(&[40, 3, 9, 40]));
+    }
+
+    #[test]
+    fn sweep_payments() {
+        let chain = ChainParams::new(Crypto::Btc, BtcNetwork::Mainnet).unwrap();
+        let keys = recover_keys(&export(vec![btc_payment()])).unwrap().keys;
+        let to = BtcAddress::from_str("1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2").unwrap();
+
+        let utxos = vec![Utxo {
+            txid: H256::from_str(
+                "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b",
+            )
+            .unwrap(),
+            vout: 0,
+            address: String::from("195BqgTp3yH1ZWmt1L9LmMkGbTMAc1vGPN"),
+            value: 100_000,
+        }];
+
+        let transaction = sweep(&chain, &keys, &utxos, &to, 10).unwrap();
+
+        // Version, then a single input.
+        assert_eq!(&[1, 0, 0, 0, 1], &transaction[0..5]);
+
+        // The only output gets everything but the fee, before the lock time.
+        let output = &transaction[transaction.len() - 4 - 25 - 1 - 8..];
+        let mut value = Vec::new();
+        value.write_u64::<LittleEndian>(100_000 - 10 * 192).unwrap();
+        assert_eq!(&value[..], &output[0..8]);
+
+        match sweep(&chain, &keys, &utxos, &to, 1_000) {
+            Err(Error::InsufficientFunds) => (),
+            res => panic!("unexpected result {:?}", res),
+        }
+    }
+}
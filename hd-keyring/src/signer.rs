@@ -0,0 +1,448 @@
+// Payout keys are only ever used through a `Signer`, so that they can live in a separate hardened
+// process instead of being derived next to the payouters.
+//
+// The remote protocol is one JSON object per line, over a Unix socket or a loopback TCP port:
+//
+//     {"body": "<request>", "mac": "<HMAC-SHA256 of body with the shared secret, hex>"}
+//
+// where the request is
+//
+//     {"timestamp": <unix seconds>, "request": {"method": "public_key", "key": {...}}}
+//     {"timestamp": <unix seconds>, "request": {"method": "sign", "key": {...}, "hash": "0x..."}}
+//
+// with keys `{"store_id", "hd_path", "index"}`, answered by `{"public_key": "<33 bytes hex>"}`,
+// `{"signature": "<r, s and recovery id hex>"}` or `{"error": "..."}`. Requests with a bad MAC or
+// a timestamp further than `REMOTE_SIGNER_TIMEOUT_SECS` from the signer's clock are refused. An HSM
+// backed process only has to speak the same protocol.
+
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader, Read, Write},
+    net::{IpAddr, TcpStream},
+    os::unix::net::UnixStream,
+    path::PathBuf,
+    str::FromStr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use hmac::{Hmac, Mac};
+
+use rustc_hex::{FromHex, ToHex};
+use secp256k1::{
+    key::{PublicKey, SecretKey},
+    Message, RecoverableSignature, RecoveryId, Secp256k1,
+};
+use serde_json;
+use sha2::Sha256;
+
+use bip32::{DerivationPath, Index, XKeyPair};
+use bip39::Mnemonic;
+use errors::Error;
+use types::{bitcoin::Network as BtcNetwork, H256};
+
+const REMOTE_SIGNER_TIMEOUT_SECS: u64 = 30;
+
+// A payment key: the store seed it's derived from, the account path and the 1-based index in it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KeyId {
+    pub store_id: String,
+    pub hd_path: String,
+    pub index: u32,
+}
+
+pub trait Signer {
+    fn public_key(&self, key: &KeyId) -> Result<PublicKey, Error>;
+
+    fn sign(&self, key: &KeyId, hash: &H256) -> Result<RecoverableSignature, Error>;
+}
+
+// Derives keys in-process from the seeds of the stores it was given.
+pub struct InMemorySigner {
+    btc_network: BtcNetwork,
+    seeds: HashMap<String, Mnemonic>,
+}
+
+impl InMemorySigner {
+    pub fn new(btc_network: BtcNetwork) -> Self {
+        InMemorySigner {
+            btc_network,
+            seeds: HashMap::new(),
+        }
+    }
+
+    pub fn add_store(
+        &mut self,
+        store_id: &str,
+        mnemonic: &str,
+        passphrase: &str,
+    ) -> Result<(), Error> {
+        let mnemonic = Mnemonic::from_phrase(mnemonic, passphrase)?;
+        self.seeds.insert(store_id.to_owned(), mnemonic);
+        Ok(())
+    }
+
+    fn secret_key(&self, key: &KeyId) -> Result<SecretKey, Error> {
+        let mnemonic = self
+            .seeds
+            .get(&key.store_id)
+            .ok_or_else(|| Error::UnknownStore(key.store_id.clone()))?;
+
+        if key.index == 0 {
+            return Err(Error::InvalidDerivation);
+        }
+
+        let path = DerivationPath::from_str(&key.hd_path)?;
+        let key_pair = XKeyPair::from_seed(mnemonic.seed(), self.btc_network)?
+            .from_path(&path)?
+            .derive(&Index::Soft(key.index - 1))?;
+
+        Ok(*key_pair.xprv().as_raw())
+    }
+}
+
+impl Signer for InMemorySigner {
+    fn public_key(&self, key: &KeyId) -> Result<PublicKey, Error> {
+        let secp = Secp256k1::new();
+        Ok(PublicKey::from_secret_key(&secp, &self.secret_key(key)?))
+    }
+
+    fn sign(&self, key: &KeyId, hash: &H256) -> Result<RecoverableSignature, Error> {
+        let secp = Secp256k1::new();
+        Ok(secp.sign_recoverable(&Message::from(hash.0), &self.secret_key(key)?))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SignerAddress {
+    Unix(PathBuf),
+    Tcp(String),
+}
+
+impl FromStr for SignerAddress {
+    type Err = Error;
+
+    // `unix:/path/to/socket`, or `host:port` of a loopback address, as keys are never to be
+    // requested over the network.
+    fn from_str(s: &str) -> Result<Self, Error> {
+        if s.starts_with("unix:") {
+            Ok(SignerAddress::Unix(PathBuf::from(&s[5..])))
+        } else if is_loopback(s) {
+            Ok(SignerAddress::Tcp(s.to_owned()))
+        } else {
+            Err(Error::InvalidSignerAddress(s.to_owned()))
+        }
+    }
+}
+
+fn is_loopback(address: &str) -> bool {
+    let host = match address.rfind(':') {
+        Some(i) => address[..i].trim_start_matches('[').trim_end_matches(']'),
+        None => return false,
+    };
+
+    host == "localhost" || IpAddr::from_str(host).map_or(false, |ip| ip.is_loopback())
+}
+
+// Asks a signer process for public keys and signatures, opening a connection per request.
+#[derive(Clone)]
+pub struct RemoteSigner {
+    address: SignerAddress,
+    secret: Vec<u8>,
+}
+
+impl RemoteSigner {
+    pub fn new(address: SignerAddress, secret: &str) -> Self {
+        RemoteSigner {
+            address,
+            secret: secret.as_bytes().to_vec(),
+        }
+    }
+
+    fn call(&self, request: Request) -> Result<Response, Error> {
+        let body = serde_json::to_string(&Body {
+            timestamp: now(),
+            request,
+        })?;
+        let mac = mac(&self.secret, &body).result().code().to_hex();
+
+        let mut line = serde_json::to_vec(&Envelope { body, mac })?;
+        line.push(b'\n');
+
+        let timeout = Some(Duration::from_secs(REMOTE_SIGNER_TIMEOUT_SECS));
+
+        let response = match self.address {
+            SignerAddress::Unix(ref path) => {
+                let stream = UnixStream::connect(path)?;
+                stream.set_read_timeout(timeout)?;
+                exchange(stream, &line)?
+            }
+            SignerAddress::Tcp(ref address) => {
+                let stream = TcpStream::connect(address.as_str())?;
+                stream.set_read_timeout(timeout)?;
+                exchange(stream, &line)?
+            }
+        };
+
+        match response.error {
+            Some(e) => Err(Error::SignerError(e)),
+            None => Ok(response),
+        }
+    }
+}
+
+impl Signer for RemoteSigner {
+    fn public_key(&self, key: &KeyId) -> Result<PublicKey, Error> {
+        let response = self.call(Request::PublicKey { key: key.clone() })?;
+        let bytes = decode_hex(response.public_key)?;
+
+        let secp = Secp256k1::new();
+        Ok(PublicKey::from_slice(&secp, &bytes)?)
+    }
+
+    fn sign(&self, key: &KeyId, hash: &H256) -> Result<RecoverableSignature, Error> {
+        let response = self.call(Request::Sign {
+            key: key.clone(),
+            hash: *hash,
+        })?;
+        let bytes = decode_hex(response.signature)?;
+
+        if bytes.len() != 65 {
+            return Err(Error::InvalidSignerResponse);
+        }
+
+        let secp = Secp256k1::new();
+        let recovery_id = RecoveryId::from_i32(i32::from(bytes[64]))?;
+        Ok(RecoverableSignature::from_compact(
+            &secp,
+            &bytes[0..64],
+            recovery_id,
+        )?)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Envelope {
+    body: String,
+    mac: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Body {
+    timestamp: u64,
+    request: Request,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "method", rename_all = "snake_case")]
+enum Request {
+    PublicKey { key: KeyId },
+    Sign { key: KeyId, hash: H256 },
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Response {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    public_key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    signature: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+fn exchange<S: Read + Write>(mut stream: S, request: &[u8]) -> Result<Response, Error> {
+    stream.write_all(request)?;
+
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line)?;
+
+    Ok(serde_json::from_str(&line)?)
+}
+
+fn mac(secret: &[u8], body: &str) -> Hmac<Sha256> {
+    // HMAC takes keys of any length.
+    let mut mac = Hmac::<Sha256>::new_varkey(secret).unwrap();
+    mac.input(body.as_bytes());
+    mac
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0)
+}
+
+// The request of an envelope, as long as it was sent recently by a holder of the secret.
+fn authenticate(secret: &[u8], envelope: Envelope) -> Result<Request, Error> {
+    let code: Vec<u8> = envelope
+        .mac
+        .from_hex()
+        .map_err(|_| Error::UnauthenticatedSignerRequest)?;
+
+    mac(secret, &envelope.body)
+        .verify(&code)
+        .map_err(|_| Error::UnauthenticatedSignerRequest)?;
+
+    let body: Body = serde_json::from_str(&envelope.body)?;
+    let now = now();
+    let skew = if body.timestamp > now {
+        body.timestamp - now
+    } else {
+        now - body.timestamp
+    };
+
+    if skew > REMOTE_SIGNER_TIMEOUT_SECS {
+        return Err(Error::UnauthenticatedSignerRequest);
+    }
+
+    Ok(body.request)
+}
+
+fn decode_hex(hex: Option<String>) -> Result<Vec<u8>, Error> {
+    hex.ok_or(Error::InvalidSignerResponse)?
+        .from_hex()
+        .map_err(|_| Error::InvalidSignerResponse)
+}
+
+// Answers the requests of one connection to a signer process with `signer`, those authenticated
+// with `secret` that is.
+pub fn serve<S: Read + Write>(signer: &Signer, secret: &str, stream: S) -> Result<(), Error> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+
+    while reader.read_line(&mut line)? > 0 {
+        let response = serde_json::from_str(&line)
+            .map_err(|e| Error::from(e))
+            .and_then(|envelope| authenticate(secret.as_bytes(), envelope))
+            .and_then(|request| handle(signer, request))
+            .unwrap_or_else(|e| Response {
+                error: Some(e.to_string()),
+                ..Default::default()
+            });
+
+        let mut out = serde_json::to_vec(&response)?;
+        out.push(b'\n');
+        reader.get_mut().write_all(&out)?;
+
+        line.clear();
+    }
+
+    Ok(())
+}
+
+fn handle(signer: &Signer, request: Request) -> Result<Response, Error> {
+    match request {
+        Request::PublicKey { key } => Ok(Response {
+            public_key: Some(signer.public_key(&key)?.serialize().to_hex()),
+            ..Default::default()
+        }),
+        Request::Sign { key, hash } => {
+            let secp = Secp256k1::new();
+            let (recovery_id, data) = signer.sign(&key, &hash)?.serialize_compact(&secp);
+
+            let mut signature = data.to_vec();
+            signature.push(recovery_id.to_i32() as u8);
+
+            Ok(Response {
+                signature: Some(signature.to_hex()),
+                ..Default::default()
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{net::TcpListener, thread};
+
+    const MNEMONIC: &str =
+        "addict else general weird gospel excite void debate north include exercise liberty";
+    const SECRET: &str = "signer secret";
+
+    fn in_memory_signer() -> InMemorySigner {
+        let mut signer = InMemorySigner::new(BtcNetwork::Mainnet);
+        signer.add_store("store", MNEMONIC, "").unwrap();
+        signer
+    }
+
+    fn key(store_id: &str) -> KeyId {
+        KeyId {
+            store_id: String::from(store_id),
+            hd_path: String::from("m/44'/0'/0'/0"),
+            index: 1,
+        }
+    }
+
+    // Serves `connections` connections on a local port, returning its address.
+    fn run_signer(connections: usize) -> SignerAddress {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+
+        thread::spawn(move || {
+            let signer = in_memory_signer();
+
+            for stream in listener.incoming().take(connections) {
+                serve(&signer, SECRET, stream.unwrap()).unwrap();
+            }
+        });
+
+        SignerAddress::Tcp(address)
+    }
+
+    #[test]
+    fn remote_signer_signs_like_in_memory_signer() {
+        let remote = RemoteSigner::new(run_signer(2), SECRET);
+        let local = in_memory_signer();
+        let hash = H256::from_data(b"payout");
+
+        let public_key = remote.public_key(&key("store")).unwrap();
+        assert_eq!(local.public_key(&key("store")).unwrap(), public_key);
+
+        let signature = remote.sign(&key("store"), &hash).unwrap();
+        let secp = Secp256k1::new();
+        assert_eq!(
+            public_key,
+            secp.recover(&Message::from(hash.0), &signature).unwrap()
+        );
+    }
+
+    #[test]
+    fn remote_signer_reports_errors() {
+        let remote = RemoteSigner::new(run_signer(1), SECRET);
+
+        match remote.public_key(&key("unknown")) {
+            Err(Error::SignerError(e)) => assert_eq!("unknown store unknown", e),
+            res => panic!("unexpected result {:?}", res),
+        }
+    }
+
+    #[test]
+    fn remote_signer_refuses_unauthenticated_requests() {
+        let remote = RemoteSigner::new(run_signer(1), "another secret");
+
+        match remote.public_key(&key("store")) {
+            Err(Error::SignerError(e)) => assert_eq!("unauthenticated signer request", e),
+            res => panic!("unexpected result {:?}", res),
+        }
+    }
+
+    #[test]
+    fn parse_signer_addresses() {
+        assert_eq!(
+            SignerAddress::Unix(PathBuf::from("/run/finch/signer.sock")),
+            SignerAddress::from_str("unix:/run/finch/signer.sock").unwrap()
+        );
+        assert_eq!(
+            SignerAddress::Tcp(String::from("127.0.0.1:7070")),
+            SignerAddress::from_str("127.0.0.1:7070").unwrap()
+        );
+        assert_eq!(
+            SignerAddress::Tcp(String::from("[::1]:7070")),
+            SignerAddress::from_str("[::1]:7070").unwrap()
+        );
+        assert!(SignerAddress::from_str("signer").is_err());
+        assert!(SignerAddress::from_str("10.0.0.2:7070").is_err());
+        assert!(SignerAddress::from_str("signer.example.com:7070").is_err());
+    }
+}
@@ -8,7 +8,11 @@ use secp256k1::{
 use tiny_keccak::keccak256;
 
 use errors::Error;
-use types::{bitcoin::Network as BtcNetwork, currency::Crypto, H160, H256};
+use types::{
+    bitcoin::{ChainParams, Network as BtcNetwork},
+    currency::Crypto,
+    H160, H256,
+};
 
 #[derive(Debug)]
 pub struct Wallet {
@@ -30,49 +34,66 @@ impl Wallet {
     }
 
     pub fn get_address(&self, currency: &Crypto) -> String {
-        match currency {
-            Crypto::Btc => self.get_btc_address(),
-            Crypto::Eth => format!("0x{}", self.get_eth_address()),
-        }
+        address(&self.public_key, currency, self.btc_network)
     }
 
     pub fn get_eth_address(&self) -> H160 {
-        let key_hash = keccak256(&self.public_key.serialize_uncompressed()[1..]); // Ignoring prefix 0x04.
+        eth_address(&self.public_key)
+    }
 
-        let mut address = String::new();
-        address.push_str(
-            &key_hash[key_hash.len() - 20..]
-                .iter()
-                .map(|b| format!("{:02X}", b))
-                .collect::<String>(),
-        );
+    pub fn get_btc_address(&self) -> String {
+        self.get_bitcoin_like_address(&ChainParams::new(Crypto::Btc, self.btc_network).unwrap())
+    }
 
-        H160::from_str(&address).unwrap()
+    pub fn get_bitcoin_like_address(&self, chain: &ChainParams) -> String {
+        bitcoin_like_address(&self.public_key, chain)
     }
+}
 
-    pub fn get_btc_address(&self) -> String {
-        // h160 on public key.
-        let h160 = H160::from_data(&self.public_key.serialize()[..]);
+// Address of a public key on the chain of `currency`, for keys known without their secret.
+pub fn address(public_key: &PublicKey, currency: &Crypto, btc_network: BtcNetwork) -> String {
+    match currency {
+        Crypto::Eth => format!("0x{}", eth_address(public_key)),
+        _ => bitcoin_like_address(
+            public_key,
+            &ChainParams::new(*currency, btc_network).expect("not a bitcoin-like chain"),
+        ),
+    }
+}
+
+fn eth_address(public_key: &PublicKey) -> H160 {
+    let key_hash = keccak256(&public_key.serialize_uncompressed()[1..]); // Ignoring prefix 0x04.
 
-        // Add version prefix.
-        let mut prefixed = [0; 21];
+    let mut address = String::new();
+    address.push_str(
+        &key_hash[key_hash.len() - 20..]
+            .iter()
+            .map(|b| format!("{:02X}", b))
+            .collect::<String>(),
+    );
 
-        prefixed[0] = match self.btc_network {
-            BtcNetwork::Mainnet => 0,
-            BtcNetwork::Test => 111,
-        };
+    H160::from_str(&address).unwrap()
+}
 
-        prefixed[1..].copy_from_slice(&h160[..]);
+fn bitcoin_like_address(public_key: &PublicKey, chain: &ChainParams) -> String {
+    // h160 on public key.
+    let h160 = H160::from_data(&public_key.serialize()[..]);
 
-        // h256 on prefixed h160.
-        let h256 = H256::from_data(&prefixed);
+    // Add version prefix.
+    let mut prefixed = [0; 21];
 
-        // 25 byte binary Bitcoin Address.
-        let mut address = [0; 25];
-        address[0..21].copy_from_slice(&prefixed);
-        address[21..].copy_from_slice(&h256[0..4]);
+    prefixed[0] = chain.p2pkh_version;
 
-        // Base58 string of the address.
-        address.to_base58()
-    }
+    prefixed[1..].copy_from_slice(&h160[..]);
+
+    // h256 on prefixed h160.
+    let h256 = H256::from_data(&prefixed);
+
+    // 25 byte binary Bitcoin Address.
+    let mut address = [0; 25];
+    address[0..21].copy_from_slice(&prefixed);
+    address[21..].copy_from_slice(&h256[0..4]);
+
+    // Base58 string of the address.
+    address.to_base58()
 }
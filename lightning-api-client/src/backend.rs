@@ -0,0 +1,94 @@
+use futures::Future;
+
+use config::{LightningBackend, LightningConfig};
+use core_lightning::CoreLightningBackend;
+use errors::Error;
+use lnd::LndBackend;
+use stub::StubBackend;
+
+#[derive(Debug, Clone)]
+pub struct Invoice {
+    // Hex encoded.
+    pub payment_hash: String,
+    // BOLT11 encoded invoice which is shown to the customer.
+    pub payment_request: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum InvoiceState {
+    Open,
+    Settled { amount_paid_msat: u64 },
+    // Cancelled or expired. It can't be paid any more.
+    Canceled,
+}
+
+// Backends are created on the main thread and moved to the client's arbiter.
+pub trait Backend: Send {
+    // `label` has to be unique among the invoices of the node.
+    fn create_invoice(
+        &mut self,
+        label: &str,
+        amount_msat: u64,
+        description: &str,
+        expiry: u64,
+    ) -> Box<Future<Item = Invoice, Error = Error>>;
+
+    fn lookup_invoice(
+        &mut self,
+        payment_hash: &str,
+    ) -> Box<Future<Item = InvoiceState, Error = Error>>;
+}
+
+pub fn init_backend(config: &LightningConfig) -> Result<Box<Backend>, Error> {
+    let url = || config.url.as_ref().ok_or(Error::MissingSetting("url"));
+
+    Ok(match config.backend {
+        LightningBackend::Lnd => Box::new(LndBackend::new(
+            url()?,
+            config
+                .macaroon
+                .as_ref()
+                .ok_or(Error::MissingSetting("macaroon"))?,
+        )),
+        LightningBackend::CoreLightning => Box::new(CoreLightningBackend::new(
+            url()?,
+            config.rune.as_ref().ok_or(Error::MissingSetting("rune"))?,
+        )),
+        LightningBackend::Stub => Box::new(StubBackend::new(config.stub_auto_settle)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    // The settings of the `[lightning]` table, apart from the backend's credentials.
+    fn config(backend: &str) -> LightningConfig {
+        serde_json::from_value(json!({
+            "backend": backend,
+            "network": "test",
+            "url": "https://localhost:8080",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn backends_require_their_credentials() {
+        let mut lnd = config("lnd");
+        assert!(init_backend(&lnd).is_err());
+        lnd.macaroon = Some(String::from("0201036c6e64"));
+        assert!(init_backend(&lnd).is_ok());
+        lnd.url = None;
+        assert!(init_backend(&lnd).is_err());
+
+        let mut core_lightning = config("core_lightning");
+        assert!(init_backend(&core_lightning).is_err());
+        core_lightning.rune = Some(String::from("rune"));
+        assert!(init_backend(&core_lightning).is_ok());
+
+        let mut stub = config("stub");
+        stub.url = None;
+        assert!(init_backend(&stub).is_ok());
+    }
+}
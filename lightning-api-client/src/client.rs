@@ -0,0 +1,65 @@
+use actix::prelude::*;
+use futures::future::Future;
+
+use backend::{Backend, Invoice, InvoiceState};
+use errors::Error;
+
+pub type LightningClientAddr = Addr<Client>;
+
+pub struct Client {
+    backend: Box<Backend>,
+}
+
+impl Client {
+    pub fn new(backend: Box<Backend>) -> Self {
+        Client { backend }
+    }
+}
+
+impl Actor for Client {
+    type Context = Context<Self>;
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<Invoice, Error>")]
+pub struct CreateInvoice {
+    pub label: String,
+    pub amount_msat: u64,
+    pub description: String,
+    // Seconds.
+    pub expiry: u64,
+}
+
+impl Handler<CreateInvoice> for Client {
+    type Result = Box<Future<Item = Invoice, Error = Error>>;
+
+    fn handle(
+        &mut self,
+        CreateInvoice {
+            label,
+            amount_msat,
+            description,
+            expiry,
+        }: CreateInvoice,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        self.backend
+            .create_invoice(&label, amount_msat, &description, expiry)
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<InvoiceState, Error>")]
+pub struct LookupInvoice(pub String);
+
+impl Handler<LookupInvoice> for Client {
+    type Result = Box<Future<Item = InvoiceState, Error = Error>>;
+
+    fn handle(
+        &mut self,
+        LookupInvoice(payment_hash): LookupInvoice,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        self.backend.lookup_invoice(&payment_hash)
+    }
+}
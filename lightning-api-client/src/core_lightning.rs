@@ -0,0 +1,159 @@
+use actix_web::{client, HttpMessage};
+use futures::future::{err, ok, Future};
+use serde_json::{self, Value};
+
+use backend::{Backend, Invoice, InvoiceState};
+use errors::Error;
+
+// Core Lightning through its `clnrest` plugin, authenticated with a rune.
+pub struct CoreLightningBackend {
+    url: String,
+    rune: String,
+}
+
+impl CoreLightningBackend {
+    pub fn new(url: &str, rune: &str) -> Self {
+        CoreLightningBackend {
+            url: url.trim_end_matches('/').to_owned(),
+            rune: rune.to_owned(),
+        }
+    }
+
+    fn call(&self, method: &str, params: Value) -> Box<Future<Item = Value, Error = Error>> {
+        let req = match client::ClientRequest::post(&format!("{}/v1/{}", self.url, method))
+            .header("Rune", self.rune.as_str())
+            .json(params)
+        {
+            Ok(req) => req,
+            Err(e) => return Box::new(err(Error::CustomError(format!("{}", e)))),
+        };
+
+        Box::new(req.send().from_err().and_then(move |resp| {
+            resp.body().from_err().and_then(move |body| {
+                let body: Value = match serde_json::from_slice(&body) {
+                    Ok(body) => body,
+                    Err(e) => return err(Error::from(e)),
+                };
+
+                if let Some(error) = body.get("error") {
+                    return err(Error::CustomError(format!("{}", error)));
+                }
+
+                ok(body)
+            })
+        }))
+    }
+}
+
+// Newer versions return amounts as integers, older ones as strings like "1000msat".
+fn parse_msat(value: Option<&Value>) -> u64 {
+    match value {
+        Some(Value::Number(n)) => n.as_u64().unwrap_or(0),
+        Some(Value::String(s)) => s.trim_end_matches("msat").parse().unwrap_or(0),
+        _ => 0,
+    }
+}
+
+// The state of the only invoice of a `listinvoices` response.
+fn invoice_state(body: &Value) -> Result<InvoiceState, Error> {
+    let invoice = body
+        .get("invoices")
+        .and_then(|invoices| invoices.as_array())
+        .and_then(|invoices| invoices.first())
+        .ok_or(Error::InvoiceNotFound)?;
+
+    match invoice.get("status").and_then(|status| status.as_str()) {
+        Some("paid") => Ok(InvoiceState::Settled {
+            amount_paid_msat: parse_msat(invoice.get("amount_received_msat")),
+        }),
+        Some("expired") => Ok(InvoiceState::Canceled),
+        Some(_) => Ok(InvoiceState::Open),
+        None => Err(Error::ResponseError),
+    }
+}
+
+impl Backend for CoreLightningBackend {
+    fn create_invoice(
+        &mut self,
+        label: &str,
+        amount_msat: u64,
+        description: &str,
+        expiry: u64,
+    ) -> Box<Future<Item = Invoice, Error = Error>> {
+        Box::new(
+            self.call(
+                "invoice",
+                json!({
+                    "amount_msat": amount_msat,
+                    "label": label,
+                    "description": description,
+                    "expiry": expiry,
+                }),
+            )
+            .and_then(|body| {
+                let payment_hash = body.get("payment_hash").and_then(|hash| hash.as_str());
+                let payment_request = body.get("bolt11").and_then(|bolt11| bolt11.as_str());
+
+                match (payment_hash, payment_request) {
+                    (Some(payment_hash), Some(payment_request)) => ok(Invoice {
+                        payment_hash: payment_hash.to_owned(),
+                        payment_request: payment_request.to_owned(),
+                    }),
+                    _ => err(Error::ResponseError),
+                }
+            }),
+        )
+    }
+
+    fn lookup_invoice(
+        &mut self,
+        payment_hash: &str,
+    ) -> Box<Future<Item = InvoiceState, Error = Error>> {
+        Box::new(
+            self.call("listinvoices", json!({ "payment_hash": payment_hash }))
+                .and_then(|body| match invoice_state(&body) {
+                    Ok(state) => ok(state),
+                    Err(e) => err(e),
+                }),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_invoice_state() {
+        let paid = json!({
+            "invoices": [{
+                "label": "a5b8b0cb-3c1c-4a5c-9a4e-0f4c9e2f3a61",
+                "payment_hash": "e8f7a21072850268576654ceb2fa7c0dc3c4debcf1dd067c570e8bdf945d9b4b",
+                "status": "paid",
+                "amount_msat": 150000,
+                "amount_received_msat": 150000,
+            }],
+        });
+        let paid_by_old_node = json!({
+            "invoices": [{ "status": "paid", "amount_received_msat": "150000msat" }],
+        });
+        let unpaid = json!({ "invoices": [{ "status": "unpaid" }] });
+        let expired = json!({ "invoices": [{ "status": "expired" }] });
+
+        for body in &[paid, paid_by_old_node] {
+            assert_eq!(
+                invoice_state(body).unwrap(),
+                InvoiceState::Settled {
+                    amount_paid_msat: 150_000
+                }
+            );
+        }
+        assert_eq!(invoice_state(&unpaid).unwrap(), InvoiceState::Open);
+        assert_eq!(invoice_state(&expired).unwrap(), InvoiceState::Canceled);
+
+        match invoice_state(&json!({ "invoices": [] })) {
+            Err(Error::InvoiceNotFound) => (),
+            state => panic!("unexpected {:?}", state),
+        }
+    }
+}
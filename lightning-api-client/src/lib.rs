@@ -0,0 +1,30 @@
+#![allow(proc_macro_derive_resolution_fallback)]
+
+extern crate actix;
+extern crate actix_web;
+extern crate base64;
+#[macro_use]
+extern crate failure;
+extern crate futures;
+extern crate rustc_hex;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate serde_json;
+
+extern crate config;
+
+mod backend;
+mod client;
+mod core_lightning;
+mod errors;
+mod lnd;
+mod stub;
+
+pub use self::backend::{init_backend, Backend, Invoice, InvoiceState};
+pub use self::client::{Client, CreateInvoice, LightningClientAddr, LookupInvoice};
+pub use self::core_lightning::CoreLightningBackend;
+pub use self::errors::Error;
+pub use self::lnd::LndBackend;
+pub use self::stub::StubBackend;
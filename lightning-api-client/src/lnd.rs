@@ -0,0 +1,164 @@
+use actix_web::{client, HttpMessage};
+use futures::future::{err, ok, Future};
+use rustc_hex::ToHex;
+use serde_json::{self, Value};
+
+use backend::{Backend, Invoice, InvoiceState};
+use errors::Error;
+
+const MACAROON_HEADER: &str = "Grpc-Metadata-macaroon";
+
+// LND through its REST proxy. The node's TLS certificate has to be trusted by the system.
+pub struct LndBackend {
+    url: String,
+    // Hex encoded invoice macaroon.
+    macaroon: String,
+}
+
+impl LndBackend {
+    pub fn new(url: &str, macaroon: &str) -> Self {
+        LndBackend {
+            url: url.trim_end_matches('/').to_owned(),
+            macaroon: macaroon.to_owned(),
+        }
+    }
+}
+
+fn parse_body(body: &[u8]) -> Result<Value, Error> {
+    let body: Value = serde_json::from_slice(body)?;
+
+    if let Some(error) = body.get("error") {
+        return Err(Error::CustomError(format!("{}", error)));
+    }
+
+    Ok(body)
+}
+
+// LND encodes 64 bit integers as strings.
+fn parse_u64(value: Option<&Value>) -> u64 {
+    match value {
+        Some(Value::String(s)) => s.parse().unwrap_or(0),
+        Some(Value::Number(n)) => n.as_u64().unwrap_or(0),
+        _ => 0,
+    }
+}
+
+fn invoice_state(invoice: &Value) -> Result<InvoiceState, Error> {
+    match invoice.get("state").and_then(|state| state.as_str()) {
+        Some("SETTLED") => Ok(InvoiceState::Settled {
+            amount_paid_msat: parse_u64(invoice.get("amt_paid_msat")),
+        }),
+        Some("CANCELED") => Ok(InvoiceState::Canceled),
+        // ACCEPTED is only used by hold invoices, which are never created.
+        Some(_) => Ok(InvoiceState::Open),
+        None => Err(Error::ResponseError),
+    }
+}
+
+impl Backend for LndBackend {
+    fn create_invoice(
+        &mut self,
+        _: &str,
+        amount_msat: u64,
+        description: &str,
+        expiry: u64,
+    ) -> Box<Future<Item = Invoice, Error = Error>> {
+        let req = match client::ClientRequest::post(&format!("{}/v1/invoices", self.url))
+            .header(MACAROON_HEADER, self.macaroon.as_str())
+            .json(json!({
+                "memo": description,
+                "value_msat": amount_msat.to_string(),
+                "expiry": expiry.to_string(),
+            })) {
+            Ok(req) => req,
+            Err(e) => return Box::new(err(Error::CustomError(format!("{}", e)))),
+        };
+
+        Box::new(req.send().from_err().and_then(move |resp| {
+            resp.body().from_err().and_then(move |body| {
+                let body = match parse_body(&body) {
+                    Ok(body) => body,
+                    Err(e) => return err(e),
+                };
+
+                let payment_hash = match body
+                    .get("r_hash")
+                    .and_then(|r_hash| r_hash.as_str())
+                    .and_then(|r_hash| base64::decode(r_hash).ok())
+                {
+                    Some(r_hash) => r_hash.to_hex(),
+                    None => return err(Error::ResponseError),
+                };
+
+                match body.get("payment_request").and_then(|pr| pr.as_str()) {
+                    Some(payment_request) => ok(Invoice {
+                        payment_hash,
+                        payment_request: payment_request.to_owned(),
+                    }),
+                    None => err(Error::ResponseError),
+                }
+            })
+        }))
+    }
+
+    fn lookup_invoice(
+        &mut self,
+        payment_hash: &str,
+    ) -> Box<Future<Item = InvoiceState, Error = Error>> {
+        let req =
+            match client::ClientRequest::get(&format!("{}/v1/invoice/{}", self.url, payment_hash))
+                .header(MACAROON_HEADER, self.macaroon.as_str())
+                .finish()
+            {
+                Ok(req) => req,
+                Err(e) => return Box::new(err(Error::CustomError(format!("{}", e)))),
+            };
+
+        Box::new(req.send().from_err().and_then(move |resp| {
+            resp.body().from_err().and_then(move |body| {
+                let body = match parse_body(&body) {
+                    Ok(body) => body,
+                    Err(e) => return err(e),
+                };
+
+                match invoice_state(&body) {
+                    Ok(state) => ok(state),
+                    Err(e) => err(e),
+                }
+            })
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_invoice_state() {
+        let settled = parse_body(
+            br#"{"r_hash":"6PeiEHKFAmhXZlTOsvp8DcPE3rzx3QZ8Vw6L35Rdm0s=","value_msat":"150000",
+            "settled":true,"state":"SETTLED","amt_paid_sat":"150","amt_paid_msat":"150000"}"#,
+        )
+        .unwrap();
+        let open = json!({ "state": "OPEN", "amt_paid_msat": "0" });
+        let canceled = json!({ "state": "CANCELED", "amt_paid_msat": "0" });
+
+        assert_eq!(
+            invoice_state(&settled).unwrap(),
+            InvoiceState::Settled {
+                amount_paid_msat: 150_000
+            }
+        );
+        assert_eq!(invoice_state(&open).unwrap(), InvoiceState::Open);
+        assert_eq!(invoice_state(&canceled).unwrap(), InvoiceState::Canceled);
+        assert!(invoice_state(&json!({})).is_err());
+    }
+
+    #[test]
+    fn parse_error_body() {
+        let body = br#"{"error":"unable to locate invoice","code":5}"#;
+
+        assert!(parse_body(body).is_err());
+    }
+}
@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+
+use futures::future::{err, ok, Future};
+
+use backend::{Backend, Invoice, InvoiceState};
+use errors::Error;
+
+// Keeps invoices in memory instead of talking to a node, for local development and tests.
+pub struct StubBackend {
+    // Reports every invoice as paid in full on its first lookup.
+    auto_settle: bool,
+    invoices: HashMap<String, (u64, InvoiceState)>,
+    created: u64,
+}
+
+impl StubBackend {
+    pub fn new(auto_settle: bool) -> Self {
+        StubBackend {
+            auto_settle,
+            invoices: HashMap::new(),
+            created: 0,
+        }
+    }
+
+    pub fn settle(&mut self, payment_hash: &str) -> Result<(), Error> {
+        match self.invoices.get_mut(payment_hash) {
+            Some(&mut (amount_msat, ref mut state)) => {
+                *state = InvoiceState::Settled {
+                    amount_paid_msat: amount_msat,
+                };
+                Ok(())
+            }
+            None => Err(Error::InvoiceNotFound),
+        }
+    }
+
+    pub fn cancel(&mut self, payment_hash: &str) -> Result<(), Error> {
+        match self.invoices.get_mut(payment_hash) {
+            Some(&mut (_, ref mut state)) => {
+                *state = InvoiceState::Canceled;
+                Ok(())
+            }
+            None => Err(Error::InvoiceNotFound),
+        }
+    }
+}
+
+impl Backend for StubBackend {
+    fn create_invoice(
+        &mut self,
+        _: &str,
+        amount_msat: u64,
+        _: &str,
+        _: u64,
+    ) -> Box<Future<Item = Invoice, Error = Error>> {
+        self.created += 1;
+
+        let payment_hash = format!("{:064x}", self.created);
+        let payment_request = format!("lnstub{}m1{}", amount_msat, payment_hash);
+
+        self.invoices
+            .insert(payment_hash.clone(), (amount_msat, InvoiceState::Open));
+
+        Box::new(ok(Invoice {
+            payment_hash,
+            payment_request,
+        }))
+    }
+
+    fn lookup_invoice(
+        &mut self,
+        payment_hash: &str,
+    ) -> Box<Future<Item = InvoiceState, Error = Error>> {
+        if self.auto_settle {
+            if let Err(e) = self.settle(payment_hash) {
+                return Box::new(err(e));
+            }
+        }
+
+        match self.invoices.get(payment_hash) {
+            Some(&(_, ref state)) => Box::new(ok(state.clone())),
+            None => Box::new(err(Error::InvoiceNotFound)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settle_invoice() {
+        let mut backend = StubBackend::new(false);
+
+        let invoice = backend
+            .create_invoice("label", 150_000, "description", 3600)
+            .wait()
+            .unwrap();
+
+        assert_eq!(
+            InvoiceState::Open,
+            backend
+                .lookup_invoice(&invoice.payment_hash)
+                .wait()
+                .unwrap()
+        );
+
+        backend.settle(&invoice.payment_hash).unwrap();
+
+        assert_eq!(
+            InvoiceState::Settled {
+                amount_paid_msat: 150_000
+            },
+            backend
+                .lookup_invoice(&invoice.payment_hash)
+                .wait()
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn auto_settle_invoice() {
+        let mut backend = StubBackend::new(true);
+
+        let invoice = backend
+            .create_invoice("label", 150_000, "description", 3600)
+            .wait()
+            .unwrap();
+
+        assert_eq!(
+            InvoiceState::Settled {
+                amount_paid_msat: 150_000
+            },
+            backend
+                .lookup_invoice(&invoice.payment_hash)
+                .wait()
+                .unwrap()
+        );
+        assert!(backend.lookup_invoice("unknown").wait().is_err());
+    }
+}
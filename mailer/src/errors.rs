@@ -0,0 +1,50 @@
+use std::io::Error as IoError;
+
+use actix::MailboxError;
+use core::ModelError;
+use lettre::smtp::error::Error as LettreError;
+use lettre_email::error::Error as EmailError;
+
+#[derive(Debug, Fail)]
+pub enum Error {
+    #[fail(display = "{}", _0)]
+    LettreError(#[cause] LettreError),
+    #[fail(display = "{}", _0)]
+    EmailError(#[cause] EmailError),
+    #[fail(display = "{}", _0)]
+    IoError(#[cause] IoError),
+    #[fail(display = "{}", _0)]
+    ModelError(#[cause] ModelError),
+    #[fail(display = "{}", _0)]
+    MailboxError(#[cause] MailboxError),
+}
+
+impl From<LettreError> for Error {
+    fn from(e: LettreError) -> Error {
+        Error::LettreError(e)
+    }
+}
+
+impl From<EmailError> for Error {
+    fn from(e: EmailError) -> Error {
+        Error::EmailError(e)
+    }
+}
+
+impl From<IoError> for Error {
+    fn from(e: IoError) -> Error {
+        Error::IoError(e)
+    }
+}
+
+impl From<ModelError> for Error {
+    fn from(e: ModelError) -> Error {
+        Error::ModelError(e)
+    }
+}
+
+impl From<MailboxError> for Error {
+    fn from(e: MailboxError) -> Error {
+        Error::MailboxError(e)
+    }
+}
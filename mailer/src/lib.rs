@@ -0,0 +1,74 @@
+extern crate actix;
+extern crate chrono;
+#[macro_use]
+extern crate failure;
+extern crate futures;
+extern crate lettre;
+extern crate lettre_email;
+#[macro_use]
+extern crate log;
+extern crate native_tls;
+
+extern crate config;
+extern crate core;
+extern crate types;
+
+mod errors;
+pub mod notifier;
+pub mod templates;
+pub mod transport;
+
+pub use self::errors::Error;
+pub use self::notifier::{Notification, Notifier, NotifierAddr, Notify};
+pub use self::templates::Template;
+pub use self::transport::{
+    init_transport, FileTransport, SmtpTransport, StdoutTransport, Transport,
+};
+
+use actix::prelude::*;
+
+use types::Locale;
+
+pub type MailerAddr = Addr<Mailer>;
+
+pub struct Mailer(pub Box<Transport>);
+
+impl Actor for Mailer {
+    type Context = SyncContext<Self>;
+
+    fn stopped(&mut self, _: &mut Self::Context) {
+        self.0.close();
+    }
+}
+
+#[derive(Message, Debug, Clone)]
+#[rtype(result = "Result<(), Error>")]
+pub struct SendMail {
+    pub subject: String,
+    pub from: String,
+    pub to: String,
+    pub html: String,
+    pub text: String,
+}
+
+impl SendMail {
+    pub fn from_template(template: &Template, locale: Locale, from: String, to: String) -> Self {
+        let (subject, html, text) = template.render(locale);
+
+        SendMail {
+            subject,
+            from,
+            to,
+            html,
+            text,
+        }
+    }
+}
+
+impl Handler<SendMail> for Mailer {
+    type Result = Result<(), Error>;
+
+    fn handle(&mut self, mail: SendMail, _: &mut Self::Context) -> Self::Result {
+        self.0.send(&mail)
+    }
+}
@@ -0,0 +1,162 @@
+use actix::{fut::wrap_future, prelude::*};
+use futures::{future, Future};
+
+use core::{
+    db::postgres::PgExecutorAddr, notification_preference::NotificationPreference,
+    payment::Payment, payout::Payout, store::Store, user::User,
+};
+use errors::Error;
+use templates::Template;
+use types::H256;
+use {MailerAddr, SendMail};
+
+pub type NotifierAddr = Addr<Notifier>;
+
+#[derive(Debug, Clone)]
+pub enum Notification {
+    PaymentConfirmed(Payout),
+    InsufficientAmount(Payout),
+    PayoutSent {
+        payout: Payout,
+        transaction_hash: H256,
+        explorer_url: String,
+    },
+    PayoutFailed(Payout),
+}
+
+impl Notification {
+    fn payout(&self) -> Payout {
+        match *self {
+            Notification::PaymentConfirmed(payout)
+            | Notification::InsufficientAmount(payout)
+            | Notification::PayoutFailed(payout) => payout,
+            Notification::PayoutSent { payout, .. } => payout,
+        }
+    }
+
+    fn is_enabled(&self, preference: &NotificationPreference) -> bool {
+        match self {
+            Notification::PaymentConfirmed(_) => preference.payment_confirmed,
+            Notification::InsufficientAmount(_) => preference.insufficient_amount,
+            Notification::PayoutSent { .. } => preference.payout_sent,
+            Notification::PayoutFailed(_) => preference.payout_failed,
+        }
+    }
+
+    fn template(&self, store: &Store, payment: &Payment) -> Template {
+        let store_name = store.name.clone();
+        let payment_id = payment.id.to_string();
+        let crypto = payment.crypto.to_str().to_uppercase();
+        let amount = payment
+            .amount_paid
+            .as_ref()
+            .map(|amount_paid| amount_paid.to_string())
+            .unwrap_or_default();
+
+        match self {
+            Notification::PaymentConfirmed(_) => Template::PaymentReceived {
+                store_name,
+                payment_id,
+                amount,
+                crypto,
+            },
+            Notification::InsufficientAmount(_) => Template::InsufficientAmount {
+                store_name,
+                payment_id,
+                amount,
+                charge: payment.charge.to_string(),
+                crypto,
+            },
+            Notification::PayoutSent {
+                transaction_hash,
+                explorer_url,
+                ..
+            } => Template::PayoutSent {
+                store_name,
+                payment_id,
+                crypto,
+                transaction_hash: transaction_hash.to_string(),
+                explorer_url: explorer_url.clone(),
+            },
+            Notification::PayoutFailed(_) => Template::PayoutFailed {
+                store_name,
+                payment_id,
+                crypto,
+            },
+        }
+    }
+}
+
+// Emails merchants about payment and payout events, following the notification preferences of
+// their stores. Failures are only logged so that they never hold up payment processing.
+pub struct Notifier {
+    pub mailer: MailerAddr,
+    pub postgres: PgExecutorAddr,
+    pub mail_sender: String,
+}
+
+impl Notifier {
+    pub fn new(mailer: MailerAddr, postgres: PgExecutorAddr, mail_sender: String) -> Self {
+        Notifier {
+            mailer,
+            postgres,
+            mail_sender,
+        }
+    }
+
+    fn notify(&self, notification: Notification) -> impl Future<Item = (), Error = Error> {
+        let mailer = self.mailer.clone();
+        let postgres = self.postgres.clone();
+        let mail_sender = self.mail_sender.clone();
+        let payout = notification.payout();
+
+        NotificationPreference::find_by_store(payout.store_id, &postgres)
+            .from_err()
+            .and_then(move |preference| -> Box<Future<Item = (), Error = Error>> {
+                if !notification.is_enabled(&preference) {
+                    return Box::new(future::ok(()));
+                }
+
+                let store = payout.store(&postgres).from_err();
+                let payment = payout.payment(&postgres).from_err();
+
+                Box::new(store.join(payment).and_then(move |(store, payment)| {
+                    User::find_by_id(store.owner_id, &postgres)
+                        .from_err()
+                        .and_then(move |owner| {
+                            let template = notification.template(&store, &payment);
+                            let to = preference.email.unwrap_or(owner.email);
+
+                            mailer
+                                .send(SendMail::from_template(
+                                    &template,
+                                    owner.locale,
+                                    mail_sender,
+                                    to,
+                                ))
+                                .from_err()
+                                .and_then(|res| res)
+                        })
+                }))
+            })
+    }
+}
+
+impl Actor for Notifier {
+    type Context = Context<Self>;
+}
+
+#[derive(Message)]
+pub struct Notify(pub Notification);
+
+impl Handler<Notify> for Notifier {
+    type Result = ();
+
+    fn handle(&mut self, Notify(notification): Notify, ctx: &mut Self::Context) -> Self::Result {
+        let process = self
+            .notify(notification)
+            .map_err(|e| error!("failed to send notification: {:?}", e));
+
+        ctx.spawn(wrap_future(process));
+    }
+}
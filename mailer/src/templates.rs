@@ -0,0 +1,242 @@
+use types::Locale;
+
+macro_rules! bodies {
+    ($locale:tt, $name:tt) => {
+        (
+            include_str!(concat!("../templates/", $locale, "/", $name, ".html")),
+            include_str!(concat!("../templates/", $locale, "/", $name, ".txt")),
+        )
+    };
+}
+
+#[derive(Debug, Clone)]
+pub enum Template {
+    Activation {
+        url: String,
+    },
+    ResetPassword {
+        url: String,
+    },
+    PayoutAddressChange {
+        store_name: String,
+        url: String,
+        cancel_url: String,
+    },
+    PayoutAddressChangeConfirmed {
+        store_name: String,
+        cancel_url: String,
+    },
+    ExportReady {
+        store_name: String,
+        url: String,
+    },
+    PaymentReceived {
+        store_name: String,
+        payment_id: String,
+        amount: String,
+        crypto: String,
+    },
+    InsufficientAmount {
+        store_name: String,
+        payment_id: String,
+        amount: String,
+        charge: String,
+        crypto: String,
+    },
+    PayoutSent {
+        store_name: String,
+        payment_id: String,
+        crypto: String,
+        transaction_hash: String,
+        explorer_url: String,
+    },
+    PayoutFailed {
+        store_name: String,
+        payment_id: String,
+        crypto: String,
+    },
+}
+
+impl Template {
+    fn subject(&self, locale: Locale) -> &'static str {
+        match (self, locale) {
+            (Template::Activation { .. }, Locale::En) => "Please activate your account.",
+            (Template::Activation { .. }, Locale::Ja) => "アカウントを有効化してください。",
+            (Template::ResetPassword { .. }, Locale::En) => "Please reset your password.",
+            (Template::ResetPassword { .. }, Locale::Ja) => "パスワードを再設定してください。",
+            (Template::PayoutAddressChange { .. }, Locale::En) => {
+                "Please confirm your new payout addresses."
+            }
+            (Template::PayoutAddressChange { .. }, Locale::Ja) => {
+                "新しい送金先アドレスを確認してください。"
+            }
+            (Template::PayoutAddressChangeConfirmed { .. }, Locale::En) => {
+                "Your new payout addresses were confirmed."
+            }
+            (Template::PayoutAddressChangeConfirmed { .. }, Locale::Ja) => {
+                "新しい送金先アドレスが確認されました。"
+            }
+            (Template::ExportReady { .. }, Locale::En) => "Your export is ready.",
+            (Template::ExportReady { .. }, Locale::Ja) => "エクスポートの準備ができました。",
+            (Template::PaymentReceived { .. }, Locale::En) => "You received a new payment.",
+            (Template::PaymentReceived { .. }, Locale::Ja) => "新しい支払いを受け取りました。",
+            (Template::InsufficientAmount { .. }, Locale::En) => {
+                "A payment was made with an insufficient amount."
+            }
+            (Template::InsufficientAmount { .. }, Locale::Ja) => "支払い金額が不足しています。",
+            (Template::PayoutSent { .. }, Locale::En) => "Your payout has been sent.",
+            (Template::PayoutSent { .. }, Locale::Ja) => "送金が完了しました。",
+            (Template::PayoutFailed { .. }, Locale::En) => "Your payout failed.",
+            (Template::PayoutFailed { .. }, Locale::Ja) => "送金に失敗しました。",
+        }
+    }
+
+    fn bodies(&self, locale: Locale) -> (&'static str, &'static str) {
+        match (self, locale) {
+            (Template::Activation { .. }, Locale::En) => bodies!("en", "activation"),
+            (Template::Activation { .. }, Locale::Ja) => bodies!("ja", "activation"),
+            (Template::ResetPassword { .. }, Locale::En) => bodies!("en", "reset_password"),
+            (Template::ResetPassword { .. }, Locale::Ja) => bodies!("ja", "reset_password"),
+            (Template::PayoutAddressChange { .. }, Locale::En) => {
+                bodies!("en", "payout_address_change")
+            }
+            (Template::PayoutAddressChange { .. }, Locale::Ja) => {
+                bodies!("ja", "payout_address_change")
+            }
+            (Template::PayoutAddressChangeConfirmed { .. }, Locale::En) => {
+                bodies!("en", "payout_address_change_confirmed")
+            }
+            (Template::PayoutAddressChangeConfirmed { .. }, Locale::Ja) => {
+                bodies!("ja", "payout_address_change_confirmed")
+            }
+            (Template::ExportReady { .. }, Locale::En) => bodies!("en", "export_ready"),
+            (Template::ExportReady { .. }, Locale::Ja) => bodies!("ja", "export_ready"),
+            (Template::PaymentReceived { .. }, Locale::En) => bodies!("en", "payment_received"),
+            (Template::PaymentReceived { .. }, Locale::Ja) => bodies!("ja", "payment_received"),
+            (Template::InsufficientAmount { .. }, Locale::En) => {
+                bodies!("en", "insufficient_amount")
+            }
+            (Template::InsufficientAmount { .. }, Locale::Ja) => {
+                bodies!("ja", "insufficient_amount")
+            }
+            (Template::PayoutSent { .. }, Locale::En) => bodies!("en", "payout_sent"),
+            (Template::PayoutSent { .. }, Locale::Ja) => bodies!("ja", "payout_sent"),
+            (Template::PayoutFailed { .. }, Locale::En) => bodies!("en", "payout_failed"),
+            (Template::PayoutFailed { .. }, Locale::Ja) => bodies!("ja", "payout_failed"),
+        }
+    }
+
+    fn variables(&self) -> Vec<(&'static str, &str)> {
+        match self {
+            Template::Activation { url } | Template::ResetPassword { url } => {
+                vec![("url", &url[..])]
+            }
+            Template::PayoutAddressChange {
+                store_name,
+                url,
+                cancel_url,
+            } => vec![
+                ("store_name", &store_name[..]),
+                ("url", &url[..]),
+                ("cancel_url", &cancel_url[..]),
+            ],
+            Template::PayoutAddressChangeConfirmed {
+                store_name,
+                cancel_url,
+            } => vec![
+                ("store_name", &store_name[..]),
+                ("cancel_url", &cancel_url[..]),
+            ],
+            Template::ExportReady { store_name, url } => {
+                vec![("store_name", &store_name[..]), ("url", &url[..])]
+            }
+            Template::PaymentReceived {
+                store_name,
+                payment_id,
+                amount,
+                crypto,
+            } => vec![
+                ("store_name", &store_name[..]),
+                ("payment_id", &payment_id[..]),
+                ("amount", &amount[..]),
+                ("crypto", &crypto[..]),
+            ],
+            Template::InsufficientAmount {
+                store_name,
+                payment_id,
+                amount,
+                charge,
+                crypto,
+            } => vec![
+                ("store_name", &store_name[..]),
+                ("payment_id", &payment_id[..]),
+                ("amount", &amount[..]),
+                ("charge", &charge[..]),
+                ("crypto", &crypto[..]),
+            ],
+            Template::PayoutSent {
+                store_name,
+                payment_id,
+                crypto,
+                transaction_hash,
+                explorer_url,
+            } => vec![
+                ("store_name", &store_name[..]),
+                ("payment_id", &payment_id[..]),
+                ("crypto", &crypto[..]),
+                ("transaction_hash", &transaction_hash[..]),
+                ("explorer_url", &explorer_url[..]),
+            ],
+            Template::PayoutFailed {
+                store_name,
+                payment_id,
+                crypto,
+            } => vec![
+                ("store_name", &store_name[..]),
+                ("payment_id", &payment_id[..]),
+                ("crypto", &crypto[..]),
+            ],
+        }
+    }
+
+    // Returns the subject, the html body and the text body of the email.
+    pub fn render(&self, locale: Locale) -> (String, String, String) {
+        let (html, text) = self.bodies(locale);
+        let variables = self.variables();
+
+        (
+            String::from(self.subject(locale)),
+            substitute(html, &variables, true),
+            substitute(text, &variables, false),
+        )
+    }
+}
+
+fn escape_html(s: &str) -> String {
+    s.chars()
+        .fold(String::with_capacity(s.len()), |mut acc, c| {
+            match c {
+                '&' => acc.push_str("&amp;"),
+                '<' => acc.push_str("&lt;"),
+                '>' => acc.push_str("&gt;"),
+                '"' => acc.push_str("&quot;"),
+                '\'' => acc.push_str("&#39;"),
+                c => acc.push(c),
+            }
+            acc
+        })
+}
+
+fn substitute(body: &str, variables: &[(&'static str, &str)], html: bool) -> String {
+    variables
+        .iter()
+        .fold(String::from(body), |acc, &(key, value)| {
+            let value = if html {
+                escape_html(value)
+            } else {
+                String::from(value)
+            };
+
+            acc.replace(&format!("{{{{{}}}}}", key), &value)
+        })
+}
@@ -0,0 +1,152 @@
+use std::{
+    fs::{self, File},
+    io::Write,
+    path::PathBuf,
+    time::Duration,
+};
+
+use chrono::prelude::*;
+use lettre::{
+    smtp::authentication::{Credentials, Mechanism},
+    smtp::ConnectionReuseParameters,
+    smtp::SmtpTransportBuilder,
+    ClientSecurity, ClientTlsParameters, EmailTransport, SmtpTransport as LettreSmtpTransport,
+};
+use lettre_email::EmailBuilder;
+use native_tls::{Protocol, TlsConnector};
+
+use config::{MailConfig, MailTransport, SmtpAuthMechanism, SmtpConfig, SmtpSecurity};
+use errors::Error;
+use SendMail;
+
+pub trait Transport {
+    fn send(&mut self, mail: &SendMail) -> Result<(), Error>;
+
+    fn close(&mut self) {}
+}
+
+pub struct SmtpTransport(LettreSmtpTransport);
+
+impl SmtpTransport {
+    pub fn new(config: &SmtpConfig) -> Self {
+        let mut tls_builder = TlsConnector::builder().unwrap();
+
+        tls_builder
+            .supported_protocols(&[Protocol::Tlsv12])
+            .unwrap();
+
+        let tls_parameters =
+            ClientTlsParameters::new(config.host.clone(), tls_builder.build().unwrap());
+
+        let security = match config.security {
+            SmtpSecurity::Starttls => ClientSecurity::Required(tls_parameters),
+            SmtpSecurity::Tls => ClientSecurity::Wrapper(tls_parameters),
+        };
+
+        let mechanism = match config.auth_mechanism {
+            SmtpAuthMechanism::Plain => Mechanism::Plain,
+            SmtpAuthMechanism::Login => Mechanism::Login,
+        };
+
+        let transport = SmtpTransportBuilder::new((&config.host[..], config.port), security)
+            .expect("failed to create transport")
+            .authentication_mechanism(mechanism)
+            .credentials(Credentials::new(config.user.clone(), config.pass.clone()))
+            .connection_reuse(ConnectionReuseParameters::NoReuse)
+            .timeout(Some(Duration::new(15, 0)))
+            .build();
+
+        SmtpTransport(transport)
+    }
+}
+
+impl Transport for SmtpTransport {
+    fn send(&mut self, mail: &SendMail) -> Result<(), Error> {
+        let email = EmailBuilder::new()
+            .to(&mail.to[..])
+            .from((&mail.from[..], "Finch"))
+            .subject(&mail.subject[..])
+            .alternative(&mail.html[..], &mail.text[..])
+            .build()?;
+
+        self.0.send(&email)?;
+
+        Ok(())
+    }
+
+    fn close(&mut self) {
+        self.0.close();
+    }
+}
+
+fn format_mail(mail: &SendMail) -> String {
+    format!(
+        "From: {}\nTo: {}\nSubject: {}\n\n{}\n\n--- html ---\n{}\n",
+        mail.from, mail.to, mail.subject, mail.text, mail.html
+    )
+}
+
+// Writes every email into a directory instead of sending it, for local development and tests.
+pub struct FileTransport {
+    dir: PathBuf,
+    sent: u64,
+}
+
+impl FileTransport {
+    pub fn new(dir: &str) -> Self {
+        fs::create_dir_all(dir).expect("failed to create the mail directory");
+
+        FileTransport {
+            dir: PathBuf::from(dir),
+            sent: 0,
+        }
+    }
+}
+
+impl Transport for FileTransport {
+    fn send(&mut self, mail: &SendMail) -> Result<(), Error> {
+        self.sent += 1;
+
+        let path = self.dir.join(format!(
+            "{}-{}.eml",
+            Utc::now().format("%Y%m%d%H%M%S%.6f"),
+            self.sent
+        ));
+
+        let mut file = File::create(&path)?;
+        file.write_all(format_mail(mail).as_bytes())?;
+
+        info!("Wrote email to {}", path.display());
+
+        Ok(())
+    }
+}
+
+pub struct StdoutTransport;
+
+impl Transport for StdoutTransport {
+    fn send(&mut self, mail: &SendMail) -> Result<(), Error> {
+        println!("{}", format_mail(mail));
+
+        Ok(())
+    }
+}
+
+pub fn init_transport(
+    mail_config: &MailConfig,
+    smtp_config: Option<&SmtpConfig>,
+) -> Box<Transport> {
+    match mail_config.transport {
+        MailTransport::Smtp => Box::new(SmtpTransport::new(
+            smtp_config.expect("no smtp configuration"),
+        )),
+        MailTransport::File => Box::new(FileTransport::new(
+            mail_config
+                .dir
+                .as_ref()
+                .map(|dir| &dir[..])
+                .unwrap_or("mails"),
+        )),
+        MailTransport::Stdout => Box::new(StdoutTransport),
+    }
+}
@@ -0,0 +1,24 @@
+-- Your SQL goes here
+CREATE TABLE audit_events
+(
+    id uuid PRIMARY KEY NOT NULL DEFAULT uuid_generate_v4(),
+    store_id uuid,
+    actor_id uuid,
+    ip_address VARCHAR,
+    action VARCHAR NOT NULL,
+    old_values JSON,
+    new_values JSON,
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX audit_events_store_id_created_at_idx ON audit_events (store_id, created_at DESC);
+
+-- Audit events are append-only.
+CREATE OR REPLACE FUNCTION audit_events_reject_modification() RETURNS trigger AS $$
+BEGIN
+    RAISE EXCEPTION 'audit_events is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER audit_events_append_only BEFORE UPDATE OR DELETE ON audit_events
+    FOR EACH ROW EXECUTE PROCEDURE audit_events_reject_modification();
@@ -0,0 +1,18 @@
+-- Your SQL goes here
+CREATE TABLE payout_address_changes
+(
+    id uuid PRIMARY KEY NOT NULL DEFAULT uuid_generate_v4(),
+    store_id uuid NOT NULL,
+    eth_payout_addresses TEXT[],
+    btc_payout_addresses TEXT[],
+    confirmation_token uuid NOT NULL,
+    confirmation_token_expires_at TIMESTAMPTZ NOT NULL,
+    confirmed_at TIMESTAMPTZ,
+    effective_at TIMESTAMPTZ,
+    applied_at TIMESTAMPTZ,
+    cancelled_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE UNIQUE INDEX payout_address_changes_confirmation_token_idx ON payout_address_changes (confirmation_token);
+CREATE INDEX payout_address_changes_store_id_idx ON payout_address_changes (store_id);
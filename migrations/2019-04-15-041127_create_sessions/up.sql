@@ -0,0 +1,16 @@
+-- Your SQL goes here
+CREATE TABLE sessions
+(
+    id uuid PRIMARY KEY NOT NULL DEFAULT uuid_generate_v4(),
+    user_id uuid NOT NULL,
+    refresh_token_hash VARCHAR NOT NULL,
+    ip_address VARCHAR,
+    user_agent VARCHAR,
+    created_at TIMESTAMPTZ NOT NULL,
+    last_used_at TIMESTAMPTZ NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    revoked_at TIMESTAMPTZ
+);
+
+CREATE UNIQUE INDEX sessions_refresh_token_hash_idx ON sessions (refresh_token_hash);
+CREATE INDEX sessions_user_id_idx ON sessions (user_id);
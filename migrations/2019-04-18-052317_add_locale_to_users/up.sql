@@ -0,0 +1,2 @@
+-- Your SQL goes here
+ALTER TABLE users ADD COLUMN locale VARCHAR NOT NULL DEFAULT 'en';
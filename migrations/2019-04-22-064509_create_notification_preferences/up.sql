@@ -0,0 +1,12 @@
+-- Your SQL goes here
+CREATE TABLE notification_preferences
+(
+    store_id uuid PRIMARY KEY NOT NULL,
+    email VARCHAR,
+    payment_confirmed BOOLEAN NOT NULL DEFAULT TRUE,
+    insufficient_amount BOOLEAN NOT NULL DEFAULT TRUE,
+    payout_sent BOOLEAN NOT NULL DEFAULT TRUE,
+    payout_failed BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMPTZ NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL
+);
@@ -0,0 +1,16 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE payout_address_changes DROP COLUMN doge_payout_addresses;
+ALTER TABLE payout_address_changes DROP COLUMN bch_payout_addresses;
+ALTER TABLE payout_address_changes DROP COLUMN ltc_payout_addresses;
+
+ALTER TABLE stores DROP COLUMN doge_confirmations_required;
+ALTER TABLE stores DROP COLUMN doge_payout_addresses;
+ALTER TABLE stores DROP COLUMN bch_confirmations_required;
+ALTER TABLE stores DROP COLUMN bch_payout_addresses;
+ALTER TABLE stores DROP COLUMN ltc_confirmations_required;
+ALTER TABLE stores DROP COLUMN ltc_payout_addresses;
+
+DELETE FROM btc_blockchain_statuses WHERE crypto <> 'btc';
+ALTER TABLE btc_blockchain_statuses DROP CONSTRAINT btc_blockchain_statuses_pkey;
+ALTER TABLE btc_blockchain_statuses ADD PRIMARY KEY (network);
+ALTER TABLE btc_blockchain_statuses DROP COLUMN crypto;
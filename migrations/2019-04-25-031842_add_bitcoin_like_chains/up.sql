@@ -0,0 +1,15 @@
+-- Your SQL goes here
+ALTER TABLE btc_blockchain_statuses ADD COLUMN crypto VARCHAR NOT NULL DEFAULT 'btc';
+ALTER TABLE btc_blockchain_statuses DROP CONSTRAINT btc_blockchain_statuses_pkey;
+ALTER TABLE btc_blockchain_statuses ADD PRIMARY KEY (crypto, network);
+
+ALTER TABLE stores ADD COLUMN ltc_payout_addresses TEXT[];
+ALTER TABLE stores ADD COLUMN ltc_confirmations_required INTEGER;
+ALTER TABLE stores ADD COLUMN bch_payout_addresses TEXT[];
+ALTER TABLE stores ADD COLUMN bch_confirmations_required INTEGER;
+ALTER TABLE stores ADD COLUMN doge_payout_addresses TEXT[];
+ALTER TABLE stores ADD COLUMN doge_confirmations_required INTEGER;
+
+ALTER TABLE payout_address_changes ADD COLUMN ltc_payout_addresses TEXT[];
+ALTER TABLE payout_address_changes ADD COLUMN bch_payout_addresses TEXT[];
+ALTER TABLE payout_address_changes ADD COLUMN doge_payout_addresses TEXT[];
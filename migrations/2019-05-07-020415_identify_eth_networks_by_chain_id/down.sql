@@ -0,0 +1,8 @@
+-- This file should undo anything in `up.sql`
+DELETE FROM eth_blockchain_statuses WHERE network NOT IN ('1', '3');
+
+UPDATE eth_blockchain_statuses SET network = 'mainnet' WHERE network = '1';
+UPDATE eth_blockchain_statuses SET network = 'ropsten' WHERE network = '3';
+
+UPDATE payments SET eth_network = 'mainnet' WHERE eth_network = '1';
+UPDATE payments SET eth_network = 'ropsten' WHERE eth_network = '3';
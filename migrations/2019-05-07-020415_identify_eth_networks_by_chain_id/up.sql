@@ -0,0 +1,6 @@
+-- Your SQL goes here
+UPDATE payments SET eth_network = '1' WHERE eth_network = 'mainnet';
+UPDATE payments SET eth_network = '3' WHERE eth_network = 'ropsten';
+
+UPDATE eth_blockchain_statuses SET network = '1' WHERE network = 'mainnet';
+UPDATE eth_blockchain_statuses SET network = '3' WHERE network = 'ropsten';
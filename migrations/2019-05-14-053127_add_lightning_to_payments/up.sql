@@ -0,0 +1,2 @@
+-- Your SQL goes here
+ALTER TABLE payments ADD COLUMN ln_payment_hash VARCHAR;
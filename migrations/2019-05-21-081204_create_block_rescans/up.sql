@@ -0,0 +1,16 @@
+-- Your SQL goes here
+CREATE TABLE block_rescans
+(
+    id uuid PRIMARY KEY NOT NULL DEFAULT uuid_generate_v4(),
+    crypto VARCHAR NOT NULL,
+    eth_network VARCHAR,
+    from_block NUMERIC NOT NULL,
+    to_block NUMERIC NOT NULL,
+    next_block NUMERIC NOT NULL,
+    status VARCHAR NOT NULL,
+    error VARCHAR,
+    created_at TIMESTAMPTZ NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX block_rescans_status_created_at_idx ON block_rescans (status, created_at);
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE payouts DROP CONSTRAINT payouts_payment_transaction_output_key;
+ALTER TABLE payouts DROP COLUMN output_index;
+ALTER TABLE payouts DROP COLUMN payment_transaction_hash;
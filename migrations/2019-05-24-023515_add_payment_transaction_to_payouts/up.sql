@@ -0,0 +1,16 @@
+-- Your SQL goes here
+ALTER TABLE payouts ADD COLUMN payment_transaction_hash VARCHAR;
+ALTER TABLE payouts ADD COLUMN output_index INTEGER;
+
+-- The output of older payouts isn't known. Only the earliest payout of a payment is linked to its
+-- transaction, so that duplicates created before the constraint don't violate it.
+UPDATE payouts
+SET payment_transaction_hash = payments.transaction_hash
+FROM payments
+WHERE payouts.payment_id = payments.id
+AND payouts.id IN (
+    SELECT DISTINCT ON (payment_id) id FROM payouts ORDER BY payment_id, created_at
+);
+
+ALTER TABLE payouts ADD CONSTRAINT payouts_payment_transaction_output_key
+    UNIQUE (payment_id, payment_transaction_hash, output_index);
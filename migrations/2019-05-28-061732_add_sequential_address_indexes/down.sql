@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX payments_store_id_hd_path_index_key;
+
+ALTER TABLE payments DROP COLUMN hd_path;
+
+DROP TABLE store_address_indexes;
@@ -0,0 +1,13 @@
+-- Your SQL goes here
+CREATE TABLE store_address_indexes
+(
+    store_id uuid NOT NULL REFERENCES stores (id),
+    hd_path VARCHAR NOT NULL,
+    next_index INTEGER NOT NULL,
+    PRIMARY KEY (store_id, hd_path)
+);
+
+-- Payments created before this were derived under their creation time and keep a NULL path.
+ALTER TABLE payments ADD COLUMN hd_path VARCHAR;
+
+CREATE UNIQUE INDEX payments_store_id_hd_path_index_key ON payments (store_id, hd_path, index);
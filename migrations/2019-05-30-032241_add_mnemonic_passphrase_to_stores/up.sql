@@ -0,0 +1,3 @@
+-- Your SQL goes here
+-- BIP39 passphrase of the store's mnemonic. Empty for wallets created without one.
+ALTER TABLE stores ADD COLUMN mnemonic_passphrase VARCHAR NOT NULL DEFAULT '';
@@ -0,0 +1,14 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE payout_psbts;
+
+ALTER TABLE payments DROP COLUMN witness_script;
+
+ALTER TABLE payout_address_changes DROP COLUMN btc_invoice_multisig_xpubs;
+ALTER TABLE payout_address_changes DROP COLUMN btc_invoice_multisig_threshold;
+ALTER TABLE payout_address_changes DROP COLUMN btc_payout_multisig_public_keys;
+ALTER TABLE payout_address_changes DROP COLUMN btc_payout_multisig_threshold;
+
+ALTER TABLE stores DROP COLUMN btc_invoice_multisig_xpubs;
+ALTER TABLE stores DROP COLUMN btc_invoice_multisig_threshold;
+ALTER TABLE stores DROP COLUMN btc_payout_multisig_public_keys;
+ALTER TABLE stores DROP COLUMN btc_payout_multisig_threshold;
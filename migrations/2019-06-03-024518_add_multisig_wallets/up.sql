@@ -0,0 +1,23 @@
+-- Your SQL goes here
+-- M-of-N P2WSH wallet payouts are swept to, from hex encoded public keys.
+ALTER TABLE stores ADD COLUMN btc_payout_multisig_threshold INTEGER;
+ALTER TABLE stores ADD COLUMN btc_payout_multisig_public_keys TEXT[];
+-- Cosigners of invoice addresses, from the xpubs of their receive chains.
+ALTER TABLE stores ADD COLUMN btc_invoice_multisig_threshold INTEGER;
+ALTER TABLE stores ADD COLUMN btc_invoice_multisig_xpubs TEXT[];
+
+ALTER TABLE payout_address_changes ADD COLUMN btc_payout_multisig_threshold INTEGER;
+ALTER TABLE payout_address_changes ADD COLUMN btc_payout_multisig_public_keys TEXT[];
+ALTER TABLE payout_address_changes ADD COLUMN btc_invoice_multisig_threshold INTEGER;
+ALTER TABLE payout_address_changes ADD COLUMN btc_invoice_multisig_xpubs TEXT[];
+
+-- Hex encoded witness script of payments made to a multisig address.
+ALTER TABLE payments ADD COLUMN witness_script VARCHAR;
+
+-- Base64 encoded PSBTs of payouts waiting for cosigners.
+CREATE TABLE payout_psbts
+(
+    payout_id uuid PRIMARY KEY NOT NULL REFERENCES payouts (id),
+    psbt TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL
+);
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE payout_approvals;
+
+ALTER TABLE stores DROP COLUMN manual_payout_approval;
@@ -0,0 +1,15 @@
+-- Your SQL goes here
+-- Payouts of stores with manual approval wait for an authorized user before being broadcast.
+ALTER TABLE stores ADD COLUMN manual_payout_approval BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE TABLE payout_approvals
+(
+    payout_id uuid PRIMARY KEY NOT NULL REFERENCES payouts (id),
+    -- Base64 encoded PSBT on bitcoin-like chains, hex encoded unsigned transaction on Ethereum.
+    unsigned_transaction TEXT NOT NULL,
+    -- Hex encoded transaction signed outside of Finch, broadcast as is once approved.
+    signed_transaction TEXT,
+    approved_by uuid REFERENCES users (id),
+    approved_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL
+);
@@ -0,0 +1,9 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE stores DROP COLUMN settlement_hour;
+ALTER TABLE stores DROP COLUMN settlement_weekday;
+ALTER TABLE stores DROP COLUMN settlement_interval;
+ALTER TABLE stores DROP COLUMN eth_payout_minimum;
+ALTER TABLE stores DROP COLUMN doge_payout_minimum;
+ALTER TABLE stores DROP COLUMN bch_payout_minimum;
+ALTER TABLE stores DROP COLUMN ltc_payout_minimum;
+ALTER TABLE stores DROP COLUMN btc_payout_minimum;
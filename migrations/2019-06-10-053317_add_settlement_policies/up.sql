@@ -0,0 +1,13 @@
+-- Your SQL goes here
+-- Confirmed payouts of a currency are held until their total reaches the store's minimum.
+ALTER TABLE stores ADD COLUMN btc_payout_minimum NUMERIC;
+ALTER TABLE stores ADD COLUMN ltc_payout_minimum NUMERIC;
+ALTER TABLE stores ADD COLUMN bch_payout_minimum NUMERIC;
+ALTER TABLE stores ADD COLUMN doge_payout_minimum NUMERIC;
+ALTER TABLE stores ADD COLUMN eth_payout_minimum NUMERIC;
+-- 'daily' or 'weekly', payouts being sent as soon as they are confirmed without one.
+ALTER TABLE stores ADD COLUMN settlement_interval VARCHAR;
+-- Day of the week of weekly settlements, from 0 for Monday.
+ALTER TABLE stores ADD COLUMN settlement_weekday INTEGER NOT NULL DEFAULT 0;
+-- Hour of the day settlements happen at, in UTC.
+ALTER TABLE stores ADD COLUMN settlement_hour INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP TRIGGER ledger_entries_append_only ON ledger_entries;
+DROP FUNCTION ledger_entries_reject_modification();
+DROP TABLE ledger_entries;
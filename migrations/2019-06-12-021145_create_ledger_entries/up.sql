@@ -0,0 +1,53 @@
+-- Your SQL goes here
+CREATE TABLE ledger_entries
+(
+    id uuid PRIMARY KEY NOT NULL DEFAULT uuid_generate_v4(),
+    store_id uuid NOT NULL,
+    typ VARCHAR NOT NULL,
+    payment_id uuid NOT NULL,
+    payout_id uuid NOT NULL REFERENCES payouts (id),
+    -- 'credit', 'payout' or 'refund'. The postings of an event of a payout sum to zero.
+    event VARCHAR NOT NULL,
+    -- 'payments', 'pending', 'settled', 'refunded' or 'network_fees'.
+    account VARCHAR NOT NULL,
+    -- Positive for debits and negative for credits, in units of the currency.
+    amount NUMERIC NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX ledger_entries_store_id_created_at_idx ON ledger_entries (store_id, created_at DESC);
+CREATE INDEX ledger_entries_payout_id_idx ON ledger_entries (payout_id);
+
+-- The ledger is append-only.
+CREATE OR REPLACE FUNCTION ledger_entries_reject_modification() RETURNS trigger AS $$
+BEGIN
+    RAISE EXCEPTION 'ledger_entries is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER ledger_entries_append_only BEFORE UPDATE OR DELETE ON ledger_entries
+    FOR EACH ROW EXECUTE PROCEDURE ledger_entries_reject_modification();
+
+-- Payouts credited before the ledger are posted with the amount paid of their payment. Fees of
+-- the ones already sent aren't known, so their whole amount is posted as settled or refunded.
+INSERT INTO ledger_entries (store_id, typ, payment_id, payout_id, event, account, amount, created_at)
+SELECT payouts.store_id, payouts.typ, payouts.payment_id, payouts.id, 'credit', postings.account,
+    postings.sign * payments.amount_paid, payouts.created_at
+FROM payouts
+JOIN payments ON payments.id = payouts.payment_id
+CROSS JOIN (VALUES ('pending', 1), ('payments', -1)) AS postings (account, sign)
+WHERE payments.amount_paid IS NOT NULL;
+
+INSERT INTO ledger_entries (store_id, typ, payment_id, payout_id, event, account, amount, created_at)
+SELECT payouts.store_id, payouts.typ, payouts.payment_id, payouts.id, payouts.action,
+    CASE
+        WHEN postings.sign = -1 THEN 'pending'
+        WHEN payouts.status = 'paid_out' THEN 'settled'
+        ELSE 'refunded'
+    END,
+    postings.sign * payments.amount_paid, payouts.created_at
+FROM payouts
+JOIN payments ON payments.id = payouts.payment_id
+CROSS JOIN (VALUES (1), (-1)) AS postings (sign)
+WHERE payments.amount_paid IS NOT NULL
+AND payouts.status IN ('paid_out', 'refunded');
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE exports;
+ALTER TABLE ledger_entries DROP COLUMN destination;
@@ -0,0 +1,27 @@
+-- Your SQL goes here
+-- Address a payout or refund was sent to, on the postings of its event. It isn't known for
+-- transactions uploaded on approval, nor for the ones sent before it was recorded.
+ALTER TABLE ledger_entries ADD COLUMN destination VARCHAR;
+
+CREATE TABLE exports
+(
+    id uuid PRIMARY KEY NOT NULL DEFAULT uuid_generate_v4(),
+    store_id uuid NOT NULL REFERENCES stores (id),
+    requested_by uuid NOT NULL REFERENCES users (id),
+    -- 'payments' or 'payouts'.
+    kind VARCHAR NOT NULL,
+    -- 'csv' or 'json'.
+    format VARCHAR NOT NULL,
+    -- Range of creation times of the exported payments or payouts, unbounded when NULL.
+    from_at TIMESTAMPTZ,
+    to_at TIMESTAMPTZ,
+    -- The generated file, NULL until generation completes.
+    content TEXT,
+    -- Secret of the emailed download link.
+    download_token uuid NOT NULL DEFAULT uuid_generate_v4(),
+    created_at TIMESTAMPTZ NOT NULL,
+    completed_at TIMESTAMPTZ,
+    expires_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX exports_store_id_idx ON exports (store_id);
@@ -0,0 +1,18 @@
+-- Your SQL goes here
+-- Ether received by an address in a transaction: the value of the transaction itself, or a
+-- transfer made by a contract call nested in it.
+CREATE TABLE eth_transfers
+(
+    transaction_hash VARCHAR NOT NULL,
+    -- 0 for the value of the transaction, then its internal transfers in trace order.
+    output_index INTEGER NOT NULL,
+    from_address VARCHAR NOT NULL,
+    to_address VARCHAR NOT NULL,
+    value NUMERIC NOT NULL,
+    PRIMARY KEY (transaction_hash, output_index)
+);
+
+-- Transactions already credited were stored with the transfer to the first payment they paid.
+INSERT INTO eth_transfers (transaction_hash, output_index, from_address, to_address, value)
+SELECT hash, 0, from_address, to_address, value FROM eth_transactions
+WHERE to_address IS NOT NULL;
@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE payout_address_changes DROP COLUMN manual_payout_approval;
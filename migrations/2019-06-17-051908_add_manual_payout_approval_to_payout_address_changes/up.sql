@@ -0,0 +1,3 @@
+-- Your SQL goes here
+-- Turning off manual payout approval is confirmed by email like a payout address change.
+ALTER TABLE payout_address_changes ADD COLUMN manual_payout_approval BOOLEAN;
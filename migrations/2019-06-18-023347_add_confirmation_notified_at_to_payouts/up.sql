@@ -0,0 +1,6 @@
+-- Your SQL goes here
+-- When the merchant was emailed that the payment of a payout reached its confirmations.
+ALTER TABLE payouts ADD COLUMN confirmation_notified_at TIMESTAMPTZ;
+
+-- Existing payouts were notified as they were created.
+UPDATE payouts SET confirmation_notified_at = created_at WHERE action = 'payout';
@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE stores ALTER COLUMN mnemonic_passphrase SET DEFAULT '';
+ALTER TABLE stores ALTER COLUMN mnemonic_passphrase SET NOT NULL;
+ALTER TABLE stores ALTER COLUMN mnemonic SET NOT NULL;
+
+DROP TABLE store_xpubs;
@@ -0,0 +1,15 @@
+-- Your SQL goes here
+-- Extended public keys of the accounts invoice addresses are derived from, so that the server never
+-- needs a store's seed.
+CREATE TABLE store_xpubs
+(
+    store_id uuid NOT NULL REFERENCES stores (id),
+    hd_path VARCHAR NOT NULL,
+    xpub VARCHAR NOT NULL,
+    PRIMARY KEY (store_id, hd_path)
+);
+
+-- Seeds of stores created with a signer configured are only held by the signer.
+ALTER TABLE stores ALTER COLUMN mnemonic DROP NOT NULL;
+ALTER TABLE stores ALTER COLUMN mnemonic_passphrase DROP NOT NULL;
+ALTER TABLE stores ALTER COLUMN mnemonic_passphrase DROP DEFAULT;
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX payouts_store_id_created_at_id_idx;
+DROP INDEX payments_store_id_created_at_id_idx;
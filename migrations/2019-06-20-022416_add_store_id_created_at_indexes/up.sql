@@ -0,0 +1,4 @@
+-- Your SQL goes here
+-- Exports page through a store's payments and payouts by creation time and id.
+CREATE INDEX payments_store_id_created_at_id_idx ON payments (store_id, created_at, id);
+CREATE INDEX payouts_store_id_created_at_id_idx ON payouts (store_id, created_at, id);
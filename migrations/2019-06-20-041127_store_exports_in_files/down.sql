@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX exports_expires_at_idx;
+
+ALTER TABLE exports DROP COLUMN failed_at;
+
+ALTER TABLE exports ADD COLUMN content TEXT;
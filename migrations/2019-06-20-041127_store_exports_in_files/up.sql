@@ -0,0 +1,10 @@
+-- Your SQL goes here
+-- Generated exports are written to a file named after their id in the server's export directory.
+-- The ones generated into the database so far are dropped along with the column.
+DELETE FROM exports WHERE content IS NOT NULL;
+ALTER TABLE exports DROP COLUMN content;
+
+-- Set when generation failed, or was abandoned by a server which stopped meanwhile.
+ALTER TABLE exports ADD COLUMN failed_at TIMESTAMPTZ;
+
+CREATE INDEX exports_expires_at_idx ON exports (expires_at);
@@ -0,0 +1,3 @@
+-- Your SQL goes here
+-- Settled invoices stay on the operator's node instead of being paid out, so stores opt in.
+ALTER TABLE stores ADD COLUMN btc_lightning_enabled BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,4 @@
+-- Your SQL goes here
+-- Set once coins found on chain for an unpaid payment are recorded in the audit log, so that the
+-- reconcilers report each payment once.
+ALTER TABLE payments ADD COLUMN discrepancy_flagged_at TIMESTAMPTZ;
@@ -0,0 +1,5 @@
+-- Your SQL goes here
+-- Emailed along with the change, so that it can be cancelled without signing in until applied.
+ALTER TABLE payout_address_changes ADD COLUMN cancellation_token uuid NOT NULL DEFAULT uuid_generate_v4();
+
+CREATE UNIQUE INDEX payout_address_changes_cancellation_token_idx ON payout_address_changes (cancellation_token);
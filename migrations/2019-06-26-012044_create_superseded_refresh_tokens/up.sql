@@ -0,0 +1,8 @@
+-- Your SQL goes here
+-- Refresh tokens replaced by rotation, so that a session whose old token is presented again can be revoked.
+CREATE TABLE superseded_refresh_tokens
+(
+    refresh_token_hash VARCHAR PRIMARY KEY NOT NULL,
+    session_id uuid NOT NULL REFERENCES sessions (id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
@@ -6,25 +6,37 @@ use actix::{
 };
 use futures::{future, stream, Future, Stream};
 
-use super::payouter::{PayouterAddr, ProcessPayout};
-use core::{bitcoin::BlockchainStatus, db::postgres::PgExecutorAddr, payout::Payout};
-use types::{bitcoin::Network, currency::Crypto, U128};
+use super::payouter::{PayouterAddr, ProcessSettlement};
+use core::{
+    bitcoin::BlockchainStatus, db::postgres::PgExecutorAddr, payout::Payout,
+    payout_address_change::PayoutAddressChange,
+};
+use types::{bitcoin::ChainParams, PayoutAction, U128};
 
 use errors::Error;
+use mailer::{Notification, NotifierAddr, Notify};
+use settlement;
 
 pub struct Monitor {
     pub payouter: PayouterAddr,
-    pub network: Network,
+    pub chain: ChainParams,
     pub postgres: PgExecutorAddr,
+    pub notifier: NotifierAddr,
     pub previous_block: Option<U128>,
 }
 
 impl Monitor {
-    pub fn new(payouter: PayouterAddr, network: Network, postgres: PgExecutorAddr) -> Self {
+    pub fn new(
+        payouter: PayouterAddr,
+        chain: ChainParams,
+        postgres: PgExecutorAddr,
+        notifier: NotifierAddr,
+    ) -> Self {
         Monitor {
             payouter,
-            network,
+            chain,
             postgres,
+            notifier,
             previous_block: None,
         }
     }
@@ -34,44 +46,48 @@ impl Actor for Monitor {
     type Context = Context<Self>;
 
     fn started(&mut self, ctx: &mut Context<Self>) {
-        let network = self.network;
+        let chain = self.chain;
 
         ctx.run_interval(Duration::new(10, 0), move |monitor, ctx| {
             let address = ctx.address();
 
-            let monitor_process = wrap_future(BlockchainStatus::find(network, &monitor.postgres))
-                .from_err::<Error>()
-                .and_then(
-                    move |status,
-                          m: &mut Monitor,
-                          _|
-                          -> Box<ActorFuture<Item = (), Error = Error, Actor = Self>> {
-                        let block_height = status.block_height;
-
-                        if let Some(ref previous_block) = m.previous_block {
-                            if block_height == *previous_block {
-                                return Box::new(fut::ok(()));
-                            }
-                        };
-
-                        Box::new(
-                            wrap_future(
-                                address
-                                    .send(ProcessBlock(block_height))
-                                    .from_err()
-                                    .and_then(|res| res.map_err(|e| Error::from(e))),
-                            )
-                            .and_then(move |_, m: &mut Monitor, _| {
-                                m.previous_block = Some(block_height);
-                                fut::ok(())
-                            }),
+            let monitor_process = wrap_future(BlockchainStatus::find(
+                chain.crypto,
+                chain.network,
+                &monitor.postgres,
+            ))
+            .from_err::<Error>()
+            .and_then(
+                move |status,
+                      m: &mut Monitor,
+                      _|
+                      -> Box<ActorFuture<Item = (), Error = Error, Actor = Self>> {
+                    let block_height = status.block_height;
+
+                    if let Some(ref previous_block) = m.previous_block {
+                        if block_height == *previous_block {
+                            return Box::new(fut::ok(()));
+                        }
+                    };
+
+                    Box::new(
+                        wrap_future(
+                            address
+                                .send(ProcessBlock(block_height))
+                                .from_err()
+                                .and_then(|res| res.map_err(|e| Error::from(e))),
                         )
-                    },
-                )
-                .map_err(|e, _, _| match e {
-                    _ => error!("{:?}", e),
-                })
-                .map(|_, _, _| ());
+                        .and_then(move |_, m: &mut Monitor, _| {
+                            m.previous_block = Some(block_height);
+                            fut::ok(())
+                        }),
+                    )
+                },
+            )
+            .map_err(|e, _, _| match e {
+                _ => error!("{:?}", e),
+            })
+            .map(|_, _, _| ());
 
             ctx.spawn(monitor_process);
         });
@@ -93,15 +109,45 @@ impl Handler<ProcessBlock> for Monitor {
         info!("Payment check before {}", block_number);
 
         let postgres = self.postgres.clone();
+        let _postgres = self.postgres.clone();
+        let __postgres = self.postgres.clone();
+        let notifier = self.notifier.clone();
         let payouter = self.payouter.clone();
+        let crypto = self.chain.crypto;
+
+        // Time-locked payout address changes which became effective are applied before any
+        // payout is sent, so payouts keep going to the old address until then.
+        let process_payouts = PayoutAddressChange::apply_due(&postgres)
+            .from_err::<Error>()
+            .and_then(move |_| {
+                Payout::find_all_confirmed(block_number, crypto, &postgres).from_err()
+            })
+            // Merchants are told about payments once they reached their confirmations, whether or
+            // not their payout is held.
+            .and_then(move |payouts| {
+                let ids = payouts
+                    .iter()
+                    .filter(|payout| payout.action == PayoutAction::Payout)
+                    .map(|payout| payout.id)
+                    .collect();
+
+                Payout::mark_confirmation_notified(ids, &__postgres)
+                    .from_err()
+                    .map(move |notified| {
+                        for payout in notified {
+                            notifier.do_send(Notify(Notification::PaymentConfirmed(payout)));
+                        }
 
-        let process_payouts = Payout::find_all_confirmed(block_number, Crypto::Btc, &postgres)
-            .from_err()
-            .map(move |payouts| stream::iter_ok(payouts))
+                        payouts
+                    })
+            })
+            // Payouts held by their store's settlement policy stay pending until a later block.
+            .and_then(move |payouts| settlement::find_due(payouts, &_postgres))
+            .map(move |due| stream::iter_ok(due))
             .flatten_stream()
-            .and_then(move |payout| {
+            .and_then(move |payouts| {
                 payouter
-                    .send(ProcessPayout(payout))
+                    .send(ProcessSettlement(payouts))
                     .from_err()
                     .and_then(|res| res.map_err(|e| Error::from(e)))
             })
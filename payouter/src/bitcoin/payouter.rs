@@ -1,49 +1,163 @@
+use std::str::FromStr;
+
 use actix::prelude::*;
-use futures::future::{self, Future, IntoFuture};
+use bigdecimal::BigDecimal;
+use futures::future::{self, Either, Future, IntoFuture};
 
 use blockchain_api_client::bitcoin::{
-    BlockchainApiClientAddr, EstimateSmartFee, SendRawTransaction, UnsignedTransaction,
+    BlockchainApiClientAddr, EstimateSmartFee, Psbt, Script, SendRawTransaction,
+    UnsignedTransaction,
 };
 use errors::Error;
 
 use core::{
-    bitcoin::{ScriptType, Transaction},
+    bitcoin::{SignedTransactionOutput, Transaction},
     db::postgres::PgExecutorAddr,
-    payment::PaymentPayload,
-    payout::{Payout, PayoutPayload},
+    payment::{Payment, PaymentPayload},
+    payout::{Payout, PayoutApproval, PayoutPayload},
     store::Store,
 };
-use hd_keyring::{HdKeyring, Wallet};
-use types::{bitcoin::Network as BtcNetwork, PaymentStatus, PayoutStatus, H256};
+use hd_keyring::{multisig, signer::RemoteSigner};
+use mailer::{Notification, NotifierAddr, Notify};
+use rustc_hex::FromHex;
+use signer::{self, PaymentKey, SigningExecutorAddr};
+use types::{bitcoin::ChainParams, currency::Crypto, PaymentStatus, PayoutStatus, H256};
+
+// Bytes of a transaction spending a P2PKH output to a single output.
+const P2PKH_TRANSACTION_SIZE: u64 = 192;
+// Bytes of each further P2PKH input of a transaction.
+const P2PKH_INPUT_SIZE: u64 = 148;
+// Bytes of a transaction besides its P2WSH input: version, counts, segwit marker, a P2WSH output
+// and lock time.
+const P2WSH_TRANSACTION_OVERHEAD: u64 = 55;
+
+// Satoshis in units of the coin.
+fn from_satoshi(satoshi: u64) -> BigDecimal {
+    BigDecimal::from_str(&format!("{}", satoshi)).unwrap()
+        / BigDecimal::from_str("100000000").unwrap()
+}
+
+// The wallet payouts are swept to and its address: a P2WSH multisig when the store has one for
+// BTC, else the first payout address.
+fn payout_destination(store: &Store, chain: &ChainParams) -> Result<(Script, String), Error> {
+    match (chain.crypto, store.btc_payout_multisig()) {
+        (Crypto::Btc, Some((threshold, public_keys))) => {
+            let public_keys = public_keys
+                .iter()
+                .map(|public_key| multisig::parse_public_key(public_key))
+                .collect::<Result<Vec<_>, _>>()?;
+            let witness_script = multisig::witness_script(threshold, &public_keys)?;
+            let address = multisig::p2wsh_address(chain, &witness_script)?;
+
+            Ok((Script(multisig::p2wsh_script_pubkey(&witness_script)), address))
+        }
+        _ => match store.bitcoin_like_payout_addresses(&chain.crypto) {
+            Some(payout_addresses) => {
+                let address = payout_addresses[0].to_string();
+
+                Ok((Script::p2pkh(address.clone()), address))
+            }
+            None => Err(Error::NoPayoutAddress),
+        },
+    }
+}
+
+// Whether the transaction spends the payout's payment output alone, and pays `payout_script`
+// alone. Payouts created before outputs were recorded only have the payment's transaction.
+fn sweeps_payment(
+    tx: &UnsignedTransaction,
+    payout: &Payout,
+    payment: &Payment,
+    payout_script: &Script,
+) -> bool {
+    let hash = payout.payment_transaction_hash.or(payment.transaction_hash);
+
+    let spends_payment = match (tx.inputs.as_slice(), hash) {
+        ([input], Some(hash)) => {
+            input.outpoint.hash == hash
+                && payout
+                    .output_index
+                    .map_or(true, |index| input.outpoint.index == index as u32)
+        }
+        _ => false,
+    };
+
+    spends_payment
+        && !tx.outputs.is_empty()
+        && tx.outputs.iter().all(|output| output.script_pubkey.0 == payout_script.0)
+}
+
+// The transaction holding the payout's payment output. Payouts created before outputs were
+// recorded only have the payment's transaction.
+fn payment_transaction_hash(payout: &Payout, payment: &Payment) -> Result<H256, Error> {
+    payout
+        .payment_transaction_hash
+        .or(payment.transaction_hash)
+        .ok_or(Error::OutputNotFound)
+}
+
+// The output the payout spends, each payout of a payment being for one of its outputs. Payouts
+// created before outputs were recorded are for the output paying the payment's address.
+fn payout_output<'a>(
+    payout: &Payout,
+    address: &str,
+    transaction: &'a Transaction,
+) -> Result<&'a SignedTransactionOutput, Error> {
+    transaction
+        .vout
+        .iter()
+        .find(|output| match payout.output_index {
+            Some(index) => output.n == index as u32,
+            None => output
+                .script
+                .addresses
+                .as_ref()
+                .map_or(false, |addresses| addresses[0] == address),
+        })
+        .ok_or(Error::OutputNotFound)
+}
 
 pub type PayouterAddr = Addr<Payouter>;
 
+// The transaction hash, network fee and destination of a sent payout.
+type Sent = (H256, BigDecimal, Option<String>);
+
 pub struct Payouter {
     pub postgres: PgExecutorAddr,
     pub blockchain_api_client: BlockchainApiClientAddr,
-    pub network: BtcNetwork,
+    pub notifier: NotifierAddr,
+    pub chain: ChainParams,
+    pub remote_signer: Option<RemoteSigner>,
+    pub signing: SigningExecutorAddr,
 }
 
 impl Payouter {
     pub fn new(
         pg_addr: PgExecutorAddr,
         blockchain_api_client: BlockchainApiClientAddr,
-        network: BtcNetwork,
+        notifier: NotifierAddr,
+        chain: ChainParams,
+        remote_signer: Option<RemoteSigner>,
+        signing: SigningExecutorAddr,
     ) -> Self {
         Payouter {
             postgres: pg_addr,
             blockchain_api_client,
-            network,
+            notifier,
+            chain,
+            remote_signer,
+            signing,
         }
     }
 
     pub fn prepare_payout(
         &self,
         payout: Payout,
-    ) -> impl Future<Item = (Wallet, Transaction, Store, f64), Error = Error> {
+    ) -> impl Future<Item = (PaymentKey, Payment, Transaction, Store, f64), Error = Error> {
         let postgres = self.postgres.clone();
         let blockchain_api_client = self.blockchain_api_client.clone();
-        let network = self.network.clone();
+        let chain = self.chain;
+        let remote_signer = self.remote_signer.clone();
 
         let store = payout.store(&postgres).from_err();
         let payment = payout.payment(&postgres).from_err();
@@ -63,91 +177,480 @@ impl Payouter {
                 future::ok((store, payment, transaction_fee))
             })
             .and_then(move |(store, payment, transaction_fee)| {
-                Transaction::find_by_hash(payment.clone().transaction_hash.unwrap(), &postgres)
-                    .from_err()
+                payment_transaction_hash(&payout, &payment)
+                    .into_future()
+                    .and_then(move |hash| Transaction::find_by_hash(hash, &postgres).from_err())
                     .and_then(move |transaction| {
-                        let mut path = store.hd_path.clone();
-
-                        path.push_str("/");
-                        path.push_str(&payment.created_at.timestamp().to_string());
-                        path.push_str("/");
-                        path.push_str(&payment.created_at.timestamp_subsec_micros().to_string());
+                        let path = payment.derivation_path(&chain.hd_path(&store.hd_path));
 
-                        HdKeyring::from_mnemonic(&path, &store.mnemonic.clone(), 0, network)
+                        PaymentKey::new(&remote_signer, &store, &payment, path, chain.network)
                             .into_future()
-                            .from_err()
-                            .and_then(move |keyring| {
-                                keyring
-                                    .get_wallet_by_index(payment.index as u32)
-                                    .into_future()
-                                    .from_err()
-                                    .and_then(move |wallet| {
-                                        future::ok((wallet, transaction, store, transaction_fee))
-                                    })
+                            .and_then(move |key| {
+                                future::ok((key, payment, transaction, store, transaction_fee))
                             })
                     })
             })
     }
 
-    pub fn payout(&self, payout: Payout) -> impl Future<Item = H256, Error = Error> {
+    // Yields the transaction hash, network fee and destination, or `None` for payments to multisig
+    // invoice addresses, whose transaction is left for the store's cosigners to complete, and for
+    // payouts of stores with manual approval, whose transaction is left for review.
+    pub fn payout(&self, payout: Payout) -> impl Future<Item = Option<Sent>, Error = Error> {
+        let postgres = self.postgres.clone();
         let blockchain_api_client = self.blockchain_api_client.clone();
+        let chain = self.chain;
+        let signing = self.signing.clone();
 
         self.prepare_payout(payout)
             .and_then(
-                move |(wallet, transaction, store, transaction_fee)| -> Box<Future<Item=H256, Error = Error>> {
-                    let payout_address = if let Some(payout_addresses) = store.btc_payout_addresses {
-                        payout_addresses[0].to_owned()
-                    } else {
-                        return Box::new(future::err(Error::NoPayoutAddress));
+                move |(key, payment, transaction, store, transaction_fee)| -> Box<Future<Item = Option<Sent>, Error = Error>> {
+                    let (payout_script, destination) = match payout_destination(&store, &chain) {
+                        Ok(payout_destination) => payout_destination,
+                        Err(e) => return Box::new(future::err(e)),
                     };
 
-                    let recepient = wallet.get_btc_address();
-
-                    let mut utxo_n = 0;
-                    for output in transaction.vout.iter() {
-                        match output.script.script_type {
-                            ScriptType::PubKeyHash => {
-                                if let Some(ref addresses) = output.script.addresses {
-                                    if addresses[0] == recepient {
-                                        utxo_n = output.n;
-                                    }
-                                }
-                            }
-                            _ => (),
-                        };
-                    }
-                    let utxo = transaction.vout[utxo_n as usize].clone();
+                    let witness_script = match payment.witness_script {
+                        Some(ref hex) => match Script::from_hex(hex) {
+                            Ok(witness_script) => Some(witness_script),
+                            Err(e) => return Box::new(future::err(Error::from(e))),
+                        },
+                        None => None,
+                    };
+
+                    let utxo = match payout_output(&payout, &payment.address, &transaction) {
+                        Ok(output) => output.clone(),
+                        Err(e) => return Box::new(future::err(e)),
+                    };
                     let value = (utxo.value * (100_000_000 as f64)) as u64;
 
                     // In satoshi
                     let tx_fee_per_byte = (transaction_fee * (100_000_000 as f64)) / 1000 as f64;
 
-                    if value <= tx_fee_per_byte as u64 * 192 {
+                    let size = match witness_script {
+                        Some(ref witness_script) => multisig::input_vsize(witness_script) as u64 + P2WSH_TRANSACTION_OVERHEAD,
+                        None => P2PKH_TRANSACTION_SIZE,
+                    };
+
+                    let fee = tx_fee_per_byte as u64 * size;
+
+                    if value <= fee {
                         info!("Insufficient funds to pay out.");
                         return Box::new(future::err(Error::InsufficientFunds));
                     }
 
-                    let mut tx = UnsignedTransaction::new(
+                    let mut tx = match UnsignedTransaction::new(
                         vec![(transaction.clone(), utxo.n)],
-                        vec![(payout_address.to_string().clone(), value - tx_fee_per_byte as u64 * 192)],
-                    );
+                        vec![(payout_script, value - fee)],
+                    ) {
+                        Ok(tx) => tx,
+                        Err(e) => return Box::new(future::err(Error::from(e))),
+                    };
+
+                    if store.manual_payout_approval && witness_script.is_none() {
+                        let psbt = Psbt::legacy(tx, &[transaction]);
+
+                        return Box::new(Payout::await_approval(payout.id, psbt.to_base64(), &postgres)
+                            .from_err()
+                            .map(|_| None));
+                    }
 
-                    tx.sign(wallet.secret_key, wallet.public_key);
-                    let raw_transaction = tx.into_raw_transaction();
+                    if let Some(witness_script) = witness_script {
+                        let mut psbt = Psbt::new(tx, vec![witness_script.to_vec()]);
+
+                        return Box::new(signer::sign(&signing, key, move |key| -> Result<_, Error> {
+                            let public_key = key.signer.public_key(&key.id)?;
+                            psbt.sign(public_key, |hash| key.signer.sign(&key.id, hash))?;
+                            Ok(psbt)
+                        })
+                        .and_then(move |psbt| {
+                            Payout::await_cosigners(payout.id, psbt.to_base64(), &postgres)
+                                .from_err()
+                        })
+                        .map(|_| None));
+                    }
 
-                    Box::new(blockchain_api_client
+                    Box::new(signer::sign(&signing, key, move |key| -> Result<_, Error> {
+                        let public_key = key.signer.public_key(&key.id)?;
+                        tx.sign(public_key, &chain, |hash| key.signer.sign(&key.id, hash))?;
+                        Ok(tx.into_raw_transaction())
+                    })
+                    .and_then(move |raw_transaction| blockchain_api_client
                         .send(SendRawTransaction(raw_transaction))
                         .from_err()
                         .and_then(move |res| res.map_err(|e| Error::from(e))))
+                        .map(move |hash| Some((hash, from_satoshi(fee), Some(destination)))))
                 },
             )
     }
+
+    // Signs and broadcasts the transaction approved for the payout, as reviewed, as long as it
+    // still sweeps the payment output to the store's payout destination.
+    pub fn pay_out_approved(
+        &self,
+        payout: Payout,
+        psbt: String,
+    ) -> impl Future<Item = Option<Sent>, Error = Error> {
+        let blockchain_api_client = self.blockchain_api_client.clone();
+        let chain = self.chain;
+        let remote_signer = self.remote_signer.clone();
+        let signing = self.signing.clone();
+
+        let store = payout.store(&self.postgres).from_err();
+        let payment = payout.payment(&self.postgres).from_err();
+
+        store
+            .join(payment)
+            .and_then(move |(store, payment)| -> Result<_, Error> {
+                let (payout_script, destination) = payout_destination(&store, &chain)?;
+                let tx = Psbt::from_base64(&psbt)?.transaction().clone();
+
+                if !sweeps_payment(&tx, &payout, &payment, &payout_script) {
+                    return Err(Error::ApprovalOutdated);
+                }
+
+                let spent: u64 = tx.inputs.iter().map(|input| input.value).sum();
+                let sent: u64 = tx.outputs.iter().map(|output| output.value).sum();
+
+                let path = payment.derivation_path(&chain.hd_path(&store.hd_path));
+                let key = PaymentKey::new(&remote_signer, &store, &payment, path, chain.network)?;
+
+                let fee = from_satoshi(spent.saturating_sub(sent));
+
+                Ok((key, tx, fee, destination))
+            })
+            .and_then(move |(key, mut tx, fee, destination)| {
+                signer::sign(&signing, key, move |key| -> Result<_, Error> {
+                    let public_key = key.signer.public_key(&key.id)?;
+                    tx.sign(public_key, &chain, |hash| key.signer.sign(&key.id, hash))?;
+                    Ok(tx.into_raw_transaction())
+                })
+                .map(move |raw_transaction| (raw_transaction, fee, destination))
+            })
+            .and_then(move |(raw_transaction, fee, destination)| {
+                blockchain_api_client
+                    .send(SendRawTransaction(raw_transaction))
+                    .from_err()
+                    .and_then(move |res| res.map_err(|e| Error::from(e)))
+                    .map(move |hash| Some((hash, fee, Some(destination))))
+            })
+    }
+
+    // Broadcasts a transaction uploaded on approval, once checked to sweep the payment output to
+    // the store's payout destination and nothing else. Its fee is what the output spent holds
+    // beyond what the transaction sends.
+    pub fn broadcast(
+        &self,
+        payout: Payout,
+        signed_transaction: String,
+    ) -> impl Future<Item = Option<Sent>, Error = Error> {
+        let postgres = self.postgres.clone();
+        let blockchain_api_client = self.blockchain_api_client.clone();
+        let chain = self.chain;
+
+        let store = payout.store(&self.postgres).from_err();
+        let payment = payout.payment(&self.postgres).from_err();
+
+        store
+            .join(payment)
+            .and_then(move |(store, payment)| -> Result<_, Error> {
+                let (payout_script, destination) = payout_destination(&store, &chain)?;
+
+                let raw_transaction: Vec<u8> = signed_transaction
+                    .from_hex()
+                    .map_err(|_| Error::SignedTransactionMismatch)?;
+                let tx = UnsignedTransaction::deserialize(&raw_transaction)
+                    .map_err(|_| Error::SignedTransactionMismatch)?;
+
+                if !sweeps_payment(&tx, &payout, &payment, &payout_script) {
+                    return Err(Error::SignedTransactionMismatch);
+                }
+
+                let outpoint = tx.inputs[0].outpoint.clone();
+                let sent: u64 = tx.outputs.iter().map(|output| output.value).sum();
+
+                Ok((raw_transaction, outpoint, sent, destination))
+            })
+            .and_then(move |(raw_transaction, outpoint, sent, destination)| {
+                Transaction::find_by_hash(outpoint.hash, &postgres)
+                    .from_err()
+                    .and_then(move |transaction| -> Result<_, Error> {
+                        let spent = match transaction
+                            .vout
+                            .iter()
+                            .find(|output| output.n == outpoint.index)
+                        {
+                            Some(output) => (output.value * (100_000_000 as f64)).round() as u64,
+                            None => return Err(Error::OutputNotFound),
+                        };
+
+                        Ok((raw_transaction, from_satoshi(spent.saturating_sub(sent)), destination))
+                    })
+            })
+            .and_then(move |(raw_transaction, fee, destination)| {
+                blockchain_api_client
+                    .send(SendRawTransaction(raw_transaction))
+                    .from_err()
+                    .and_then(move |res| res.map_err(|e| Error::from(e)))
+                    .map(move |hash| Some((hash, fee, Some(destination))))
+            })
+    }
+
+    // Sweeps the outputs of several payouts of a store into a single transaction, each input
+    // signed with the key of its payment. Yields the transaction hash, the network fee in satoshi
+    // and the destination.
+    pub fn pay_out_settlement(
+        &self,
+        store: Store,
+        payouts: Vec<(Payout, Payment)>,
+    ) -> impl Future<Item = (H256, u64, String), Error = Error> {
+        let postgres = self.postgres.clone();
+        let blockchain_api_client = self.blockchain_api_client.clone();
+        let chain = self.chain;
+        let remote_signer = self.remote_signer.clone();
+        let signing = self.signing.clone();
+
+        let transaction_fee = self
+            .blockchain_api_client
+            .send(EstimateSmartFee(10))
+            .from_err()
+            .and_then(move |res| res.map_err(|e| Error::from(e)));
+
+        let outputs = future::join_all(payouts.into_iter().map(move |(payout, payment)| {
+            let postgres = postgres.clone();
+
+            payment_transaction_hash(&payout, &payment)
+                .into_future()
+                .and_then(move |hash| Transaction::find_by_hash(hash, &postgres).from_err())
+                .map(move |transaction| (payout, payment, transaction))
+        }));
+
+        transaction_fee
+            .join(outputs)
+            .and_then(move |(transaction_fee, outputs)| -> Result<_, Error> {
+                if transaction_fee == 0 as f64 {
+                    return Err(Error::InvalidGasPrice);
+                }
+
+                let (payout_script, destination) = payout_destination(&store, &chain)?;
+
+                let mut inputs = Vec::new();
+                let mut keys = Vec::new();
+                let mut value = 0;
+
+                for (payout, payment, transaction) in outputs {
+                    let n = {
+                        let output = payout_output(&payout, &payment.address, &transaction)?;
+                        value += (output.value * (100_000_000 as f64)).round() as u64;
+                        output.n
+                    };
+
+                    let path = payment.derivation_path(&chain.hd_path(&store.hd_path));
+                    keys.push(PaymentKey::new(
+                        &remote_signer,
+                        &store,
+                        &payment,
+                        path,
+                        chain.network,
+                    )?);
+                    inputs.push((transaction, n));
+                }
+
+                // In satoshi
+                let tx_fee_per_byte = (transaction_fee * (100_000_000 as f64)) / 1000 as f64;
+                let size = P2PKH_TRANSACTION_SIZE + P2PKH_INPUT_SIZE * (inputs.len() as u64 - 1);
+                let fee = tx_fee_per_byte as u64 * size;
+
+                if value <= fee {
+                    info!("Insufficient funds to pay out.");
+                    return Err(Error::InsufficientFunds);
+                }
+
+                let tx = UnsignedTransaction::new(inputs, vec![(payout_script, value - fee)])?;
+
+                Ok((keys, tx, fee, destination))
+            })
+            .and_then(move |(keys, mut tx, fee, destination)| {
+                signer::sign(&signing, keys, move |keys| -> Result<_, Error> {
+                    for (idx, key) in keys.iter().enumerate() {
+                        let public_key = key.signer.public_key(&key.id)?;
+                        tx.sign_input(idx, public_key, &chain, |hash| {
+                            key.signer.sign(&key.id, hash)
+                        })?;
+                    }
+
+                    Ok(tx.into_raw_transaction())
+                })
+                .map(move |raw_transaction| (raw_transaction, fee, destination))
+            })
+            .and_then(move |(raw_transaction, fee, destination)| {
+                blockchain_api_client
+                    .send(SendRawTransaction(raw_transaction))
+                    .from_err()
+                    .and_then(move |res| res.map_err(|e| Error::from(e)))
+                    .map(move |hash| (hash, fee, destination))
+            })
+    }
 }
 
 impl Actor for Payouter {
     type Context = Context<Self>;
 }
 
+// The payouts of a store in a currency released together by its settlement policy, which are
+// swept in a single transaction. Payouts of stores with manual approval and to multisig invoice
+// addresses are reviewed or cosigned one by one, so they are processed on their own.
+#[derive(Message)]
+#[rtype(result = "Result<(), Error>")]
+pub struct ProcessSettlement(pub Vec<Payout>);
+
+impl Handler<ProcessSettlement> for Payouter {
+    type Result = Box<Future<Item = (), Error = Error>>;
+
+    fn handle(
+        &mut self,
+        ProcessSettlement(payouts): ProcessSettlement,
+        ctx: &mut Self::Context,
+    ) -> Self::Result {
+        let address = ctx.address();
+        let postgres = self.postgres.clone();
+
+        if payouts.is_empty() {
+            return Box::new(future::ok(()));
+        }
+
+        let store = payouts[0].store(&self.postgres).from_err();
+        let payments = future::join_all(
+            payouts
+                .iter()
+                .map(|payout| payout.payment(&postgres))
+                .collect::<Vec<_>>(),
+        )
+        .from_err();
+
+        Box::new(store.join(payments).and_then(move |(store, payments)| {
+            let (mut batched, mut single): (Vec<_>, Vec<_>) = payouts
+                .into_iter()
+                .zip(payments)
+                .partition(|&(_, ref payment)| {
+                    !store.manual_payout_approval && payment.witness_script.is_none()
+                });
+
+            if batched.len() < 2 {
+                single.append(&mut batched);
+            }
+
+            let single = single.into_iter().map({
+                let address = address.clone();
+
+                move |(payout, _)| {
+                    address
+                        .send(ProcessPayout(payout))
+                        .from_err()
+                        .and_then(|res| res.map_err(|e| Error::from(e)))
+                }
+            });
+
+            let settlement: Box<Future<Item = (), Error = Error>> = if batched.is_empty() {
+                Box::new(future::ok(()))
+            } else {
+                Box::new(
+                    address
+                        .send(PayOutSettlement(store, batched))
+                        .from_err()
+                        .and_then(|res| res.map_err(|e| Error::from(e))),
+                )
+            };
+
+            future::join_all(single).join(settlement).map(|_| ())
+        }))
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<(), Error>")]
+pub struct PayOutSettlement(pub Store, pub Vec<(Payout, Payment)>);
+
+impl Handler<PayOutSettlement> for Payouter {
+    type Result = Box<Future<Item = (), Error = Error>>;
+
+    fn handle(
+        &mut self,
+        PayOutSettlement(store, payouts): PayOutSettlement,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        let postgres = self.postgres.clone();
+        let _postgres = self.postgres.clone();
+        let notifier = self.notifier.clone();
+        let _notifier = self.notifier.clone();
+        let chain = self.chain;
+        let _payouts: Vec<Payout> = payouts.iter().map(|(payout, _)| *payout).collect();
+        let __payouts = _payouts.clone();
+
+        Box::new(
+            self.pay_out_settlement(store, payouts)
+                .and_then(move |(hash, fee, destination)| {
+                    info!("Paid out {} payouts in {}", _payouts.len(), hash);
+
+                    // The network fee is shared between the payouts, the first one taking what
+                    // doesn't divide evenly.
+                    let count = _payouts.len() as u64;
+
+                    future::join_all(_payouts.into_iter().enumerate().map(
+                        move |(i, payout)| {
+                            let fee = fee / count + if i == 0 { fee % count } else { 0 };
+                            let notifier = notifier.clone();
+
+                            let mut payout_payload = PayoutPayload::from(payout);
+                            payout_payload.transaction_hash = Some(Some(hash));
+                            payout_payload.status = Some(PayoutStatus::PaidOut);
+
+                            let mut payment_payload = PaymentPayload::new();
+                            payment_payload.status = Some(PaymentStatus::Completed);
+
+                            Payout::pay_out(
+                                payout.id,
+                                payout_payload,
+                                payment_payload,
+                                from_satoshi(fee),
+                                Some(destination.clone()),
+                                &postgres,
+                            )
+                            .from_err()
+                            .map(move |payout| {
+                                notifier.do_send(Notify(Notification::PayoutSent {
+                                    payout,
+                                    transaction_hash: hash,
+                                    explorer_url: chain.explorer_transaction_url(&hash),
+                                }))
+                            })
+                        },
+                    ))
+                    .map(|_| ())
+                })
+                .or_else(move |e| -> Self::Result {
+                    match e {
+                        Error::InsufficientFunds => Box::new(
+                            future::join_all(__payouts.into_iter().map(move |payout| {
+                                let notifier = _notifier.clone();
+
+                                let mut payload = PayoutPayload::from(payout);
+                                payload.status = Some(PayoutStatus::InsufficientFunds);
+
+                                Payout::update(payout.id, payload, &_postgres)
+                                    .from_err()
+                                    .map(move |payout| {
+                                        notifier.do_send(Notify(Notification::PayoutFailed(payout)))
+                                    })
+                            }))
+                            .map(|_| ()),
+                        ),
+                        _ => Box::new(future::err(e)),
+                    }
+                }),
+        )
+    }
+}
+
 #[derive(Message)]
 #[rtype(result = "Result<(), Error>")]
 pub struct ProcessPayout(pub Payout);
@@ -160,30 +663,79 @@ impl Handler<ProcessPayout> for Payouter {
         ProcessPayout(payout): ProcessPayout,
         ctx: &mut Self::Context,
     ) -> Self::Result {
+        let address = ctx.address();
+
         Box::new(
-            ctx.address()
-                .send(PayOut(payout))
+            Payout::find_approval(payout.id, &self.postgres)
                 .from_err()
-                .and_then(|res| res.map_err(|e| Error::from(e))),
+                .and_then(move |approval| {
+                    address
+                        .send(PayOut(payout, approval))
+                        .from_err()
+                        .and_then(|res| res.map_err(|e| Error::from(e)))
+                }),
         )
     }
 }
 
+// The approval is there for payouts of stores with manual approval which were reviewed.
 #[derive(Message)]
 #[rtype(result = "Result<(), Error>")]
-pub struct PayOut(pub Payout);
+pub struct PayOut(pub Payout, pub Option<PayoutApproval>);
 
 impl Handler<PayOut> for Payouter {
     type Result = Box<Future<Item = (), Error = Error>>;
 
-    fn handle(&mut self, PayOut(payout): PayOut, _: &mut Self::Context) -> Self::Result {
+    fn handle(
+        &mut self,
+        PayOut(payout, approval): PayOut,
+        ctx: &mut Self::Context,
+    ) -> Self::Result {
+        let address = ctx.address();
         let postgres = self.postgres.clone();
         let _postgres = self.postgres.clone();
+        let notifier = self.notifier.clone();
+        let _notifier = self.notifier.clone();
+        let chain = self.chain;
+
+        let process: Box<Future<Item = Option<Sent>, Error = Error>> = match approval {
+            Some(PayoutApproval {
+                signed_transaction: Some(signed_transaction),
+                ..
+            }) => {
+                let postgres = self.postgres.clone();
+
+                // An upload which can't be broadcast puts the payout back up for approval, so that
+                // it can be approved again.
+                Box::new(
+                    self.broadcast(payout, signed_transaction)
+                        .or_else(move |e| {
+                            Payout::reopen_approval(payout.id, &postgres)
+                                .from_err()
+                                .and_then(move |_| Err(e))
+                        }),
+                )
+            }
+            Some(PayoutApproval {
+                unsigned_transaction,
+                approved_at: Some(_),
+                ..
+            }) => Box::new(self.pay_out_approved(payout, unsigned_transaction)),
+            _ => Box::new(self.payout(payout)),
+        };
 
         Box::new(
-            self.payout(payout)
+            process
                 .from_err()
-                .and_then(move |hash| {
+                .and_then(move |sent| {
+                    let (hash, fee, destination) = match sent {
+                        Some(sent) => sent,
+                        None => {
+                            info!("Payout {} awaits cosigners or approval", payout.id);
+                            return Either::A(future::ok(()));
+                        }
+                    };
+
                     info!("Paid out {}", hash);
 
                     let mut payout_payload = PayoutPayload::from(payout);
@@ -193,17 +745,38 @@ impl Handler<PayOut> for Payouter {
                     let mut payment_payload = PaymentPayload::new();
                     payment_payload.status = Some(PaymentStatus::Completed);
 
-                    Payout::update_with_payment(
-                        payout.id,
-                        payout_payload,
-                        payment_payload,
-                        &postgres,
+                    Either::B(
+                        Payout::pay_out(
+                            payout.id,
+                            payout_payload,
+                            payment_payload,
+                            fee,
+                            destination,
+                            &postgres,
+                        )
+                        .from_err()
+                        .map(move |payout| {
+                            notifier.do_send(Notify(Notification::PayoutSent {
+                                payout,
+                                transaction_hash: hash,
+                                explorer_url: chain.explorer_transaction_url(&hash),
+                            }))
+                        }),
                     )
-                    .from_err()
                 })
-                .map(move |_| ())
                 .or_else(move |e| -> Self::Result {
                     match e {
+                        // The payout is left for review again, with a transaction that applies.
+                        Error::ApprovalOutdated => {
+                            info!("Approved payout {} no longer applies", payout.id);
+
+                            Box::new(
+                                address
+                                    .send(PayOut(payout, None))
+                                    .from_err()
+                                    .and_then(|res| res.map_err(|e| Error::from(e))),
+                            )
+                        }
                         Error::InsufficientFunds => {
                             let mut payload = PayoutPayload::from(payout);
                             payload.status = Some(PayoutStatus::InsufficientFunds);
@@ -211,7 +784,10 @@ impl Handler<PayOut> for Payouter {
                             return Box::new(
                                 Payout::update(payout.id, payload, &_postgres)
                                     .from_err()
-                                    .map(move |_| ()),
+                                    .map(move |payout| {
+                                        _notifier
+                                            .do_send(Notify(Notification::PayoutFailed(payout)))
+                                    }),
                             );
                         }
                         _ => Box::new(future::err(e)),
@@ -220,3 +796,85 @@ impl Handler<PayOut> for Payouter {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::prelude::*;
+    use uuid::Uuid;
+
+    use super::*;
+    use core::bitcoin::{ScriptType, TransactionOutputScript};
+    use types::{PayoutAction, U128};
+
+    const ADDRESS: &str = "1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2";
+
+    fn payout(payment_id: Uuid, output_index: Option<i32>) -> Payout {
+        Payout {
+            id: Uuid::new_v4(),
+            status: PayoutStatus::Pending,
+            action: PayoutAction::Payout,
+            store_id: Uuid::new_v4(),
+            payment_id,
+            typ: Crypto::Btc,
+            block_height_required: U128::from(0),
+            transaction_hash: None,
+            created_at: Utc::now(),
+            payment_transaction_hash: output_index.map(|_| H256::new()),
+            output_index,
+            confirmation_notified_at: None,
+        }
+    }
+
+    fn output(n: u32, value: f64) -> SignedTransactionOutput {
+        SignedTransactionOutput {
+            value,
+            n,
+            script: TransactionOutputScript {
+                asm: String::new(),
+                hex: String::new(),
+                req_sigs: Some(1),
+                script_type: ScriptType::PubKeyHash,
+                addresses: Some(vec![ADDRESS.to_owned()]),
+            },
+        }
+    }
+
+    #[test]
+    fn payouts_of_a_payment_spend_their_own_output() {
+        let transaction = Transaction {
+            txid: H256::new(),
+            hex: String::new(),
+            hash: H256::new(),
+            size: 0,
+            vsize: 0,
+            version: 1,
+            locktime: 0,
+            vin: Vec::new(),
+            vout: vec![output(0, 0.001), output(1, 0.002)],
+            blockhash: None,
+            confirmations: None,
+            time: None,
+            blocktime: None,
+        };
+
+        let payment_id = Uuid::new_v4();
+        let first = payout(payment_id, Some(0));
+        let second = payout(payment_id, Some(1));
+
+        let first_output = payout_output(&first, ADDRESS, &transaction).unwrap();
+        let second_output = payout_output(&second, ADDRESS, &transaction).unwrap();
+        assert_eq!(0, first_output.n);
+        assert_eq!(0.001, first_output.value);
+        assert_eq!(1, second_output.n);
+        assert_eq!(0.002, second_output.value);
+
+        // Payouts created before outputs were recorded spend the first output paying the address.
+        let legacy = payout(payment_id, None);
+        assert_eq!(0, payout_output(&legacy, ADDRESS, &transaction).unwrap().n);
+
+        match payout_output(&payout(payment_id, Some(2)), ADDRESS, &transaction) {
+            Err(Error::OutputNotFound) => (),
+            res => panic!("unexpected result {:?}", res),
+        }
+    }
+}
@@ -1,13 +1,37 @@
 use actix::prelude::*;
 
 use super::{monitor::Monitor, payouter::Payouter};
-use core::db::postgres;
 use blockchain_api_client::bitcoin::BlockchainApiClientAddr;
-use types::bitcoin::Network as BtcNetwork;
+use config::SignerConfig;
+use core::db::postgres;
+use errors::Error;
+use mailer::NotifierAddr;
+use signer::{remote_signer, SigningExecutor};
+use types::bitcoin::ChainParams;
 
-pub fn run(postgres: postgres::PgExecutorAddr, blockchain_api_client: BlockchainApiClientAddr, network: BtcNetwork) {
+pub fn run(
+    postgres: postgres::PgExecutorAddr,
+    blockchain_api_client: BlockchainApiClientAddr,
+    notifier: NotifierAddr,
+    chain: ChainParams,
+    signer_config: Option<SignerConfig>,
+) -> Result<(), Error> {
     let pg = postgres.clone();
-    let payouter = Arbiter::start(move |_| Payouter::new(pg, blockchain_api_client, network));
+    let monitor_notifier = notifier.clone();
+    let remote_signer = remote_signer(&signer_config)?;
+    let signing = SyncArbiter::start(2, || SigningExecutor);
+    let payouter = Arbiter::start(move |_| {
+        Payouter::new(
+            pg,
+            blockchain_api_client,
+            notifier,
+            chain,
+            remote_signer,
+            signing,
+        )
+    });
+
+    Arbiter::start(move |_| Monitor::new(payouter, chain, postgres, monitor_notifier));
 
-    Arbiter::start(move |_| Monitor::new(payouter, network, postgres));
+    Ok(())
 }
@@ -24,6 +24,18 @@ pub enum Error {
     InvalidGasPrice,
     #[fail(display = "insufficient funds")]
     InsufficientFunds,
+    #[fail(display = "signed transaction doesn't sweep the payment to the payout destination")]
+    SignedTransactionMismatch,
+    #[fail(display = "approved transaction no longer applies")]
+    ApprovalOutdated,
+    #[fail(display = "payment output not found")]
+    OutputNotFound,
+    #[fail(display = "invalid payment address {}", _0)]
+    InvalidPaymentAddress(String),
+    #[fail(display = "the store's seed is only held by the signer, which isn't configured")]
+    NoSigner,
+    #[fail(display = "the signer secret must not be empty")]
+    EmptySignerSecret,
 }
 
 impl From<KeyringError> for Error {
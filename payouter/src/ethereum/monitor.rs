@@ -6,25 +6,44 @@ use actix::{
 };
 use futures::{future, stream, Future, Stream};
 
-use super::payouter::{PayouterAddr, ProcessPayout};
-use core::{db::postgres::PgExecutorAddr, ethereum::BlockchainStatus, payout::Payout};
-use types::{currency::Crypto, ethereum::Network, U128};
+use super::payouter::{from_wei, PayouterAddr, ProcessPayout};
+use blockchain_api_client::{
+    errors::Error as BlockchainApiClientError,
+    ethereum::{BlockchainApiClientAddr, GetTransactionReceipt},
+};
+use core::{
+    db::postgres::PgExecutorAddr, ethereum::BlockchainStatus, ledger_entry::LedgerEntry,
+    payout::Payout, payout_address_change::PayoutAddressChange,
+};
+use types::{ethereum::Network, PayoutAction, U128};
 
 use errors::Error;
+use mailer::{Notification, NotifierAddr, Notify};
+use settlement;
 
 pub struct Monitor {
     pub payouter: PayouterAddr,
     pub network: Network,
     pub postgres: PgExecutorAddr,
+    pub blockchain_api_client: BlockchainApiClientAddr,
+    pub notifier: NotifierAddr,
     pub previous_block: Option<U128>,
 }
 
 impl Monitor {
-    pub fn new(payouter: PayouterAddr, network: Network, postgres: PgExecutorAddr) -> Self {
+    pub fn new(
+        payouter: PayouterAddr,
+        network: Network,
+        postgres: PgExecutorAddr,
+        blockchain_api_client: BlockchainApiClientAddr,
+        notifier: NotifierAddr,
+    ) -> Self {
         Monitor {
             payouter,
             network,
             postgres,
+            blockchain_api_client,
+            notifier,
             previous_block: None,
         }
     }
@@ -88,11 +107,45 @@ impl Handler<ProcessBlock> for Monitor {
         info!("Payment check before {}", block_number);
 
         let postgres = self.postgres.clone();
+        let _postgres = self.postgres.clone();
+        let __postgres = self.postgres.clone();
+        let ___postgres = self.postgres.clone();
+        let notifier = self.notifier.clone();
         let payouter = self.payouter.clone();
+        let blockchain_api_client = self.blockchain_api_client.clone();
+        let network = self.network;
+
+        // Time-locked payout address changes which became effective are applied before any
+        // payout is sent, so payouts keep going to the old address until then.
+        let process_payouts = PayoutAddressChange::apply_due(&postgres)
+            .from_err::<Error>()
+            .and_then(move |_| {
+                Payout::find_all_confirmed_eth(block_number, network, &postgres).from_err()
+            })
+            // Merchants are told about payments once they reached their confirmations, whether or
+            // not their payout is held.
+            .and_then(move |payouts| {
+                let ids = payouts
+                    .iter()
+                    .filter(|payout| payout.action == PayoutAction::Payout)
+                    .map(|payout| payout.id)
+                    .collect();
+
+                Payout::mark_confirmation_notified(ids, &__postgres)
+                    .from_err()
+                    .map(move |notified| {
+                        for payout in notified {
+                            notifier.do_send(Notify(Notification::PaymentConfirmed(payout)));
+                        }
 
-        let process_payouts = Payout::find_all_confirmed(block_number, Crypto::Eth, &postgres)
-            .from_err()
-            .map(move |payouts| stream::iter_ok(payouts))
+                        payouts
+                    })
+            })
+            // Payouts held by their store's settlement policy stay pending until a later block.
+            .and_then(move |payouts| settlement::find_due(payouts, &_postgres))
+            // Every invoice address is an account of its own, so payouts released together are
+            // still sent in a transaction each.
+            .map(move |due| stream::iter_ok(due.into_iter().flat_map(|payouts| payouts)))
             .flatten_stream()
             .and_then(move |payout| {
                 payouter
@@ -102,6 +155,43 @@ impl Handler<ProcessBlock> for Monitor {
             })
             .for_each(move |_| future::ok(()));
 
-        Box::new(process_payouts)
+        // Fees of sent payouts are posted as the fee cap of their transaction, and adjusted to what
+        // it spent once it is mined.
+        let adjust_fees = Payout::find_all_unadjusted_fees_eth(network, &self.postgres)
+            .from_err::<Error>()
+            .map(move |payouts| {
+                stream::iter_ok(
+                    payouts
+                        .into_iter()
+                        .filter_map(|payout| payout.transaction_hash.map(|hash| (payout, hash))),
+                )
+            })
+            .flatten_stream()
+            .for_each(move |(payout, hash)| {
+                let postgres = ___postgres.clone();
+
+                blockchain_api_client
+                    .send(GetTransactionReceipt(hash))
+                    .from_err::<Error>()
+                    .and_then(|res| res.map_err(|e| Error::from(e)))
+                    .and_then(move |receipt| {
+                        LedgerEntry::adjust_fee(payout, from_wei(receipt.fee()), &postgres)
+                            .from_err()
+                    })
+                    .then(move |res| {
+                        match res {
+                            // Not mined yet.
+                            Err(Error::BlockchainApiClientError(
+                                BlockchainApiClientError::EmptyResponseError,
+                            )) => (),
+                            Err(e) => error!("{:?}", e),
+                            Ok(_) => (),
+                        };
+
+                        Ok(())
+                    })
+            });
+
+        Box::new(process_payouts.and_then(move |_| adjust_fees))
     }
 }
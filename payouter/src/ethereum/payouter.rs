@@ -1,21 +1,26 @@
 use std::str::FromStr;
 
 use actix::prelude::*;
-use futures::future::{self, Future, IntoFuture};
+use bigdecimal::BigDecimal;
+use futures::future::{self, Either, Future, IntoFuture};
 
 use blockchain_api_client::ethereum::{
-    BlockchainApiClientAddr, GetGasPrice, GetTransactionCount, SendRawTransaction,
-    UnsignedTransaction,
+    BlockchainApiClientAddr, EstimateGas, Fee, GetFeeHistory, GetGasPrice, GetTransactionCount,
+    SendRawTransaction, SendRawTransactionHex, SignedTransaction, UnsignedTransaction,
 };
+use config::{EthConfig, EthTransactionType};
 use core::{
     db::postgres::PgExecutorAddr,
-    ethereum::Transaction,
-    payment::PaymentPayload,
-    payout::{Payout, PayoutPayload},
+    ethereum::Transfer,
+    payment::{Payment, PaymentPayload},
+    payout::{Payout, PayoutApproval, PayoutPayload},
     store::Store,
 };
 use errors::Error;
-use hd_keyring::{HdKeyring, Wallet};
+use hd_keyring::signer::RemoteSigner;
+use mailer::{Notification, NotifierAddr, Notify};
+use rustc_hex::FromHex;
+use signer::{self, PaymentKey, SigningExecutorAddr};
 use types::{
     bitcoin::Network as BtcNetwork, ethereum::Network as EthNetwork, PaymentStatus, PayoutAction,
     PayoutStatus, H160, H256, U128, U256,
@@ -23,92 +28,184 @@ use types::{
 
 pub type PayouterAddr = Addr<Payouter>;
 
+// The transaction hash, network fee and destination of a sent payout.
+type Sent = (H256, BigDecimal, Option<String>);
+
+const FEE_HISTORY_BLOCKS: u64 = 10;
+const FEE_HISTORY_REWARD_PERCENTILE: f64 = 50.0;
+
+// Wei in units of ether.
+pub fn from_wei(wei: U256) -> BigDecimal {
+    BigDecimal::from_str(&format!("{}", wei)).unwrap()
+        / BigDecimal::from_str("1000000000000000000").unwrap()
+}
+
+// Sweeps what the payout was credited, not the whole transaction, which can pay several addresses
+// through internal transfers. Payouts created before outputs were recorded are for the payment's
+// transaction.
+pub fn find_transfer(
+    payout: &Payout,
+    payment: &Payment,
+    postgres: &PgExecutorAddr,
+) -> impl Future<Item = Transfer, Error = Error> {
+    Transfer::find_by_output(
+        payout
+            .payment_transaction_hash
+            .or(payment.transaction_hash)
+            .unwrap(),
+        payout.output_index.unwrap_or(0),
+        postgres,
+    )
+    .from_err()
+}
+
+// Whether the transaction sends no more than the transfer, fee included, from the payment
+// address's next nonce to the store's payout address.
+fn sweeps_transfer(
+    transaction: &UnsignedTransaction,
+    nonce: U128,
+    transfer: &Transfer,
+    store: &Store,
+) -> bool {
+    let (network_fee, fee_overflow) = transaction
+        .fee
+        .max_gas_price()
+        .0
+        .overflowing_mul(transaction.gas.0);
+    let (cost, cost_overflow) = network_fee.overflowing_add(transaction.value.0);
+    let payout_address = store
+        .eth_payout_addresses
+        .as_ref()
+        .and_then(|addresses| addresses.first().cloned());
+
+    transaction.nonce == nonce
+        && Some(transaction.to) == payout_address
+        && transaction.data.is_empty()
+        && !fee_overflow
+        && !cost_overflow
+        && U256::from(cost) <= transfer.value
+}
+
 pub struct Payouter {
     pub postgres: PgExecutorAddr,
     pub blockchain_api_client: BlockchainApiClientAddr,
+    pub notifier: NotifierAddr,
     pub network: EthNetwork,
+    pub config: EthConfig,
+    pub remote_signer: Option<RemoteSigner>,
+    pub signing: SigningExecutorAddr,
 }
 
 impl Payouter {
     pub fn new(
         pg_addr: PgExecutorAddr,
         blockchain_api_client: BlockchainApiClientAddr,
-        network: EthNetwork,
+        notifier: NotifierAddr,
+        config: EthConfig,
+        remote_signer: Option<RemoteSigner>,
+        signing: SigningExecutorAddr,
     ) -> Self {
         Payouter {
             postgres: pg_addr,
             blockchain_api_client,
-            network,
+            notifier,
+            network: config.network,
+            config,
+            remote_signer,
+            signing,
+        }
+    }
+
+    pub fn get_fee(&self) -> Box<Future<Item = Fee, Error = Error>> {
+        let blockchain_api_client = self.blockchain_api_client.clone();
+
+        match self.config.transaction_type {
+            EthTransactionType::Legacy => Box::new(
+                blockchain_api_client
+                    .send(GetGasPrice)
+                    .from_err()
+                    .and_then(move |res| res.map_err(|e| Error::from(e)))
+                    .map(|gas_price| Fee::Legacy { gas_price }),
+            ),
+            EthTransactionType::Eip1559 => Box::new(
+                blockchain_api_client
+                    .send(GetFeeHistory {
+                        block_count: FEE_HISTORY_BLOCKS,
+                        reward_percentile: FEE_HISTORY_REWARD_PERCENTILE,
+                    })
+                    .from_err()
+                    .and_then(move |res| res.map_err(|e| Error::from(e)))
+                    // Payouts reserve the whole fee cap, and the part of it their transaction
+                    // doesn't spend is kept on the ledger once they're mined.
+                    .and_then(|fee_history| fee_history.suggest_fee().map_err(|e| Error::from(e))),
+            ),
         }
     }
 
     pub fn prepare_payout(
         &self,
         payout: Payout,
-    ) -> impl Future<Item = (Wallet, Transaction, Store, U256, U128), Error = Error> {
+    ) -> impl Future<Item = (PaymentKey, H160, Transfer, Store, Fee, U128), Error = Error> {
         let postgres = self.postgres.clone();
         let blockchain_api_client = self.blockchain_api_client.clone();
+        let remote_signer = self.remote_signer.clone();
 
         let store = payout.store(&postgres).from_err();
         let payment = payout.payment(&postgres).from_err();
-        let gas_price = blockchain_api_client
-            .send(GetGasPrice)
-            .from_err()
-            .and_then(move |res| res.map_err(|e| Error::from(e)));
-
-        store.join3(payment, gas_price).and_then(
-            move |(store, payment, gas_price)| -> Box<
-                Future<Item = (Wallet, Transaction, Store, U256, U128), Error = Error>,
+        let fee = self.get_fee();
+
+        store.join3(payment, fee).and_then(
+            move |(store, payment, fee)| -> Box<
+                Future<Item = (PaymentKey, H160, Transfer, Store, Fee, U128), Error = Error>,
             > {
+                let gas_price = fee.max_gas_price();
+
                 if gas_price == U256::from(0) {
                     return Box::new(future::err(Error::InvalidGasPrice));
                 }
 
-                let transaction =
-                    Transaction::find_by_hash(payment.clone().transaction_hash.unwrap(), &postgres)
-                        .from_err();
+                let transfer = find_transfer(&payout, &payment, &postgres);
+
+                let address = match H160::from_hex(&payment.address) {
+                    Ok(address) => address,
+                    Err(_) => {
+                        return Box::new(future::err(Error::InvalidPaymentAddress(
+                            payment.address.clone(),
+                        )))
+                    }
+                };
 
                 let nonce = blockchain_api_client
-                    .send(GetTransactionCount(
-                        H160::from_str(&payment.clone().address[2..]).unwrap(),
-                    ))
+                    .send(GetTransactionCount(address))
                     .from_err()
                     .and_then(move |res| res.map_err(|e| Error::from(e)));
 
-                Box::new(transaction.join(nonce).and_then(
-                    move |(transaction, nonce)| -> Box<
-                        Future<Item = (Wallet, Transaction, Store, U256, U128), Error = Error>,
+                Box::new(transfer.join(nonce).and_then(
+                    move |(transfer, nonce)| -> Box<
+                        Future<
+                            Item = (PaymentKey, H160, Transfer, Store, Fee, U128),
+                            Error = Error,
+                        >,
                     > {
-                        if transaction.value <= (gas_price * U256::from(21_000)) {
+                        if transfer.value <= (gas_price * U256::from(21_000)) {
                             info!("Insufficient funds to pay out");
                             return Box::new(future::err(Error::InsufficientFunds));
                         }
 
-                        let mut path = store.hd_path.clone();
-
-                        path.push_str("/");
-                        path.push_str(&payment.created_at.timestamp().to_string());
-                        path.push_str("/");
-                        path.push_str(&payment.created_at.timestamp_subsec_micros().to_string());
+                        let path = payment.derivation_path(&store.hd_path);
 
                         Box::new(
-                            HdKeyring::from_mnemonic(
-                                &path,
-                                &store.mnemonic.clone(),
-                                0,
+                            PaymentKey::new(
+                                &remote_signer,
+                                &store,
+                                &payment,
+                                path,
                                 // Dummy
                                 BtcNetwork::Test,
                             )
                             .into_future()
-                            .from_err()
-                            .and_then(move |keyring| {
-                                keyring
-                                    .get_wallet_by_index(payment.index as u32)
-                                    .into_future()
-                                    .from_err()
-                                    .and_then(move |wallet| {
-                                        future::ok((wallet, transaction, store, gas_price, nonce))
-                                    })
+                            .and_then(move |key| {
+                                future::ok((key, address, transfer, store, fee, nonce))
                             }),
                         )
                     },
@@ -117,75 +214,260 @@ impl Payouter {
         )
     }
 
-    pub fn payout(&self, payout: Payout) -> impl Future<Item = H256, Error = Error> {
+    // Yields the transaction hash, network fee and destination, or `None` for payouts of stores
+    // with manual approval, the unsigned transaction being left for review.
+    pub fn payout(&self, payout: Payout) -> impl Future<Item = Option<Sent>, Error = Error> {
         let chain_id = self.network.chain_id();
+        let postgres = self.postgres.clone();
         let blockchain_api_client = self.blockchain_api_client.clone();
+        let _blockchain_api_client = self.blockchain_api_client.clone();
+        let signing = self.signing.clone();
 
         self.prepare_payout(payout)
-            .and_then(move |(wallet, transaction, store, gas_price, nonce)| {
+            .and_then(move |(key, address, transfer, store, fee, nonce)| {
+                let review = store.manual_payout_approval;
+
                 match store.eth_payout_addresses {
-                    Some(payout_addresses) => {
-                        future::ok((wallet, transaction, gas_price, nonce, payout_addresses))
+                    Some(addresses) => {
+                        future::ok((key, address, transfer, fee, nonce, addresses[0], review))
                     }
                     None => future::err(Error::NoPayoutAddress),
                 }
             })
+            .and_then(move |(key, from, transfer, fee, nonce, to, review)| {
+                // Payout addresses may be contract wallets such as a Safe, which need more gas to
+                // receive than a plain transfer.
+                _blockchain_api_client
+                    .send(EstimateGas {
+                        from,
+                        to,
+                        value: transfer.value,
+                    })
+                    .from_err()
+                    .and_then(move |res| res.map_err(|e| Error::from(e)))
+                    .and_then(move |gas| {
+                        let gas = if gas < U256::from(21_000) {
+                            U256::from(21_000)
+                        } else {
+                            gas
+                        };
+
+                        if transfer.value <= fee.max_gas_price() * gas {
+                            info!("Insufficient funds to pay out");
+                            return future::err(Error::InsufficientFunds);
+                        }
+
+                        let value = transfer.value - fee.max_gas_price() * gas;
+
+                        let raw_transaction = UnsignedTransaction {
+                            nonce,
+                            fee,
+                            gas,
+                            to,
+                            value,
+                            data: b"".to_vec(),
+                        };
+
+                        future::ok((key, raw_transaction, review))
+                    })
+            })
+            .and_then(move |(key, raw_transaction, review)| {
+                if review {
+                    let unsigned_transaction = raw_transaction.unsigned_rlp_encode(chain_id);
+
+                    return Either::A(
+                        Payout::await_approval(payout.id, unsigned_transaction, &postgres)
+                            .from_err()
+                            .map(|_| None),
+                    );
+                }
+
+                let network_fee =
+                    from_wei(raw_transaction.fee.max_gas_price() * raw_transaction.gas);
+                let destination = raw_transaction.to.hex();
+
+                Either::B(
+                    signer::sign(&signing, key, move |key| {
+                        raw_transaction
+                            .sign(chain_id, |hash| key.signer.sign(&key.id, hash))
+                            .map_err(|e| Error::from(e))
+                    })
+                    .and_then(move |signed_transaction| {
+                        blockchain_api_client
+                            .send(SendRawTransaction(signed_transaction))
+                            .from_err()
+                            .and_then(move |res| res.map_err(|e| Error::from(e)))
+                    })
+                    .map(move |hash| Some((hash, network_fee, Some(destination)))),
+                )
+            })
+    }
+
+    // The payment, its address and next nonce, the transfer swept and the store, which
+    // transactions reviewed or uploaded on approval are checked against.
+    fn prepare_approved(
+        &self,
+        payout: Payout,
+    ) -> impl Future<Item = (Payment, H160, U128, Transfer, Store), Error = Error> {
+        let postgres = self.postgres.clone();
+        let blockchain_api_client = self.blockchain_api_client.clone();
+
+        let store = payout.store(&postgres).from_err();
+        let payment = payout.payment(&postgres).from_err();
+
+        store.join(payment).and_then(move |(store, payment)| {
+            H160::from_hex(&payment.address)
+                .map_err(|_| Error::InvalidPaymentAddress(payment.address.clone()))
+                .into_future()
+                .and_then(move |address| {
+                    let transfer = find_transfer(&payout, &payment, &postgres);
+                    let nonce = blockchain_api_client
+                        .send(GetTransactionCount(address))
+                        .from_err()
+                        .and_then(move |res| res.map_err(|e| Error::from(e)));
+
+                    transfer
+                        .join(nonce)
+                        .map(move |(transfer, nonce)| (payment, address, nonce, transfer, store))
+                })
+        })
+    }
+
+    // Signs and broadcasts the transaction approved for the payout, as reviewed, as long as it
+    // still sweeps the transfer to the store's payout address.
+    pub fn pay_out_approved(
+        &self,
+        payout: Payout,
+        unsigned_transaction: String,
+    ) -> impl Future<Item = Option<Sent>, Error = Error> {
+        let chain_id = self.network.chain_id();
+        let blockchain_api_client = self.blockchain_api_client.clone();
+        let remote_signer = self.remote_signer.clone();
+        let signing = self.signing.clone();
+
+        self.prepare_approved(payout)
             .and_then(
-                move |(wallet, transaction, gas_price, nonce, payout_addresses)| {
-                    let value = transaction.value - gas_price * U256::from(21_000);
-
-                    let raw_transaction = UnsignedTransaction {
-                        nonce,
-                        gas_price,
-                        gas: U256::from(21_000),
-                        to: payout_addresses[0],
-                        value,
-                        data: b"".to_vec(),
-                    };
+                move |(payment, _, nonce, transfer, store)| -> Result<_, Error> {
+                    let payload: Vec<u8> = unsigned_transaction
+                        .from_hex()
+                        .map_err(|_| Error::ApprovalOutdated)?;
+                    let (raw_transaction, transaction_chain_id) =
+                        UnsignedTransaction::unsigned_rlp_decode(&payload)
+                            .map_err(|_| Error::ApprovalOutdated)?;
+
+                    if transaction_chain_id != chain_id
+                        || !sweeps_transfer(&raw_transaction, nonce, &transfer, &store)
+                    {
+                        return Err(Error::ApprovalOutdated);
+                    }
+
+                    let path = payment.derivation_path(&store.hd_path);
+                    // Dummy
+                    let key =
+                        PaymentKey::new(&remote_signer, &store, &payment, path, BtcNetwork::Test)?;
 
+                    let network_fee =
+                        from_wei(raw_transaction.fee.max_gas_price() * raw_transaction.gas);
+                    let destination = raw_transaction.to.hex();
+
+                    Ok((key, raw_transaction, network_fee, destination))
+                },
+            )
+            .and_then(move |(key, raw_transaction, network_fee, destination)| {
+                signer::sign(&signing, key, move |key| {
                     raw_transaction
-                        .sign(wallet.secret_key, chain_id)
-                        .into_future()
-                        .from_err()
-                        .and_then(move |signed_transaction| {
-                            blockchain_api_client
-                                .send(SendRawTransaction(signed_transaction))
-                                .from_err()
-                                .and_then(move |res| res.map_err(|e| Error::from(e)))
-                        })
+                        .sign(chain_id, |hash| key.signer.sign(&key.id, hash))
+                        .map_err(|e| Error::from(e))
+                })
+                .map(move |signed_transaction| (signed_transaction, network_fee, destination))
+            })
+            .and_then(move |(signed_transaction, network_fee, destination)| {
+                blockchain_api_client
+                    .send(SendRawTransaction(signed_transaction))
+                    .from_err()
+                    .and_then(move |res| res.map_err(|e| Error::from(e)))
+                    .map(move |hash| Some((hash, network_fee, Some(destination))))
+            })
+    }
+
+    // Broadcasts a transaction signed outside of the payouter, as uploaded on approval, once
+    // checked to be signed by the payment address and to sweep the transfer to the store's
+    // payout address.
+    pub fn broadcast(
+        &self,
+        payout: Payout,
+        signed_transaction: String,
+    ) -> impl Future<Item = Option<Sent>, Error = Error> {
+        let chain_id = self.network.chain_id();
+        let blockchain_api_client = self.blockchain_api_client.clone();
+
+        self.prepare_approved(payout)
+            .and_then(
+                move |(_, address, nonce, transfer, store)| -> Result<_, Error> {
+                    let raw_transaction: Vec<u8> = signed_transaction
+                        .from_hex()
+                        .map_err(|_| Error::SignedTransactionMismatch)?;
+                    let decoded = SignedTransaction::rlp_decode(&raw_transaction)
+                        .map_err(|_| Error::SignedTransactionMismatch)?;
+
+                    if decoded.chain_id() != chain_id
+                        || decoded.sender().ok() != Some(address)
+                        || !sweeps_transfer(decoded.transaction(), nonce, &transfer, &store)
+                    {
+                        return Err(Error::SignedTransactionMismatch);
+                    }
+
+                    let transaction = decoded.transaction();
+                    let network_fee = from_wei(transaction.fee.max_gas_price() * transaction.gas);
+
+                    Ok((signed_transaction, network_fee, transaction.to.hex()))
                 },
             )
+            .and_then(move |(signed_transaction, network_fee, destination)| {
+                blockchain_api_client
+                    .send(SendRawTransactionHex(signed_transaction))
+                    .from_err()
+                    .and_then(move |res| res.map_err(|e| Error::from(e)))
+                    .map(move |hash| Some((hash, network_fee, Some(destination))))
+            })
     }
 
-    pub fn refund(&self, payout: Payout) -> impl Future<Item = H256, Error = Error> {
+    // Yields the transaction hash, network fee and the address refunded.
+    pub fn refund(
+        &self,
+        payout: Payout,
+    ) -> impl Future<Item = (H256, BigDecimal, String), Error = Error> {
         let chain_id = self.network.chain_id();
         let blockchain_api_client = self.blockchain_api_client.clone();
+        let signing = self.signing.clone();
 
         self.prepare_payout(payout)
-            .and_then(move |(wallet, transaction, _, gas_price, nonce)| {
-                let value = transaction.value - gas_price * U256::from(21_000);
+            .and_then(move |(key, _, transfer, _, fee, nonce)| {
+                let network_fee = fee.max_gas_price() * U256::from(21_000);
+                let value = transfer.value - network_fee;
+                let destination = transfer.from_address.hex();
 
                 let raw_transaction = UnsignedTransaction {
                     nonce,
-                    gas_price,
+                    fee,
                     gas: U256::from(21_000),
-                    to: transaction.from_address,
+                    to: transfer.from_address,
                     value,
                     data: b"".to_vec(),
                 };
 
-                Box::new(
+                signer::sign(&signing, key, move |key| {
                     raw_transaction
-                        .sign(wallet.secret_key, chain_id)
-                        .into_future()
+                        .sign(chain_id, |hash| key.signer.sign(&key.id, hash))
+                        .map_err(|e| Error::from(e))
+                })
+                .and_then(move |signed_transaction| {
+                    blockchain_api_client
+                        .send(SendRawTransaction(signed_transaction))
                         .from_err()
-                        .and_then(move |signed_transaction| {
-                            blockchain_api_client
-                                .send(SendRawTransaction(signed_transaction))
-                                .from_err()
-                                .and_then(move |res| res.map_err(|e| Error::from(e)))
-                        }),
-                )
+                        .and_then(move |res| res.map_err(|e| Error::from(e)))
+                })
+                .map(move |hash| (hash, from_wei(network_fee), destination))
             })
     }
 }
@@ -208,13 +490,18 @@ impl Handler<ProcessPayout> for Payouter {
     ) -> Self::Result {
         let address = ctx.address();
         let postgres = self.postgres.clone();
+        let notifier = self.notifier.clone();
 
         let process: Self::Result = match payout.action {
             PayoutAction::Payout => Box::new(
-                address
-                    .send(PayOut(payout))
+                Payout::find_approval(payout.id, &postgres)
                     .from_err()
-                    .and_then(|res| res.map_err(|e| Error::from(e))),
+                    .and_then(move |approval| {
+                        address
+                            .send(PayOut(payout, approval))
+                            .from_err()
+                            .and_then(|res| res.map_err(|e| Error::from(e)))
+                    }),
             ),
             PayoutAction::Refund => Box::new(
                 address
@@ -233,7 +520,11 @@ impl Handler<ProcessPayout> for Payouter {
                     return Box::new(
                         Payout::update(payout.id, payload, &postgres)
                             .from_err()
-                            .map(move |_| ()),
+                            .map(move |payout| {
+                                if payout.action == PayoutAction::Payout {
+                                    notifier.do_send(Notify(Notification::PayoutFailed(payout)));
+                                }
+                            }),
                     );
                 }
                 _ => Box::new(future::err(e)),
@@ -242,17 +533,79 @@ impl Handler<ProcessPayout> for Payouter {
     }
 }
 
+// The approval is there for payouts of stores with manual approval which were reviewed.
 #[derive(Message)]
 #[rtype(result = "Result<(), Error>")]
-pub struct PayOut(pub Payout);
+pub struct PayOut(pub Payout, pub Option<PayoutApproval>);
 
 impl Handler<PayOut> for Payouter {
     type Result = Box<Future<Item = (), Error = Error>>;
 
-    fn handle(&mut self, PayOut(payout): PayOut, _: &mut Self::Context) -> Self::Result {
+    fn handle(
+        &mut self,
+        PayOut(payout, approval): PayOut,
+        ctx: &mut Self::Context,
+    ) -> Self::Result {
+        let address = ctx.address();
         let postgres = self.postgres.clone();
+        let notifier = self.notifier.clone();
+        let config = self.config.clone();
+
+        let sent: Box<Future<Item = Option<Sent>, Error = Error>> = match approval {
+            Some(PayoutApproval {
+                signed_transaction: Some(signed_transaction),
+                ..
+            }) => {
+                let postgres = self.postgres.clone();
+
+                // An upload which can't be broadcast puts the payout back up for approval, so that
+                // it can be approved again.
+                Box::new(
+                    self.broadcast(payout, signed_transaction)
+                        .or_else(move |e| {
+                            Payout::reopen_approval(payout.id, &postgres)
+                                .from_err()
+                                .and_then(move |_| Err(e))
+                        }),
+                )
+            }
+            Some(PayoutApproval {
+                unsigned_transaction,
+                approved_at: Some(_),
+                ..
+            }) => Box::new(self.pay_out_approved(payout, unsigned_transaction)),
+            _ => Box::new(self.payout(payout)),
+        };
+
+        // An approved transaction which no longer applies is replaced by one left for review.
+        let sent = sent.or_else(
+            move |e| -> Box<Future<Item = Option<Sent>, Error = Error>> {
+                match e {
+                    Error::ApprovalOutdated => {
+                        info!("Approved payout {} no longer applies", payout.id);
+
+                        Box::new(
+                            address
+                                .send(PayOut(payout, None))
+                                .from_err()
+                                .and_then(|res| res.map_err(|e| Error::from(e)))
+                                .map(|_| None),
+                        )
+                    }
+                    _ => Box::new(future::err(e)),
+                }
+            },
+        );
+
+        Box::new(sent.and_then(move |paid_out| {
+            let (hash, fee, destination) = match paid_out {
+                Some(paid_out) => paid_out,
+                None => {
+                    info!("Payout {} awaits approval", payout.id);
+                    return Either::A(future::ok(()));
+                }
+            };
 
-        Box::new(self.payout(payout).from_err().and_then(move |hash| {
             info!("Paid out {}", hash.hex());
 
             let mut payout_payload = PayoutPayload::from(payout);
@@ -262,9 +615,23 @@ impl Handler<PayOut> for Payouter {
             let mut payment_payload = PaymentPayload::new();
             payment_payload.status = Some(PaymentStatus::Completed);
 
-            Payout::update_with_payment(payout.id, payout_payload, payment_payload, &postgres)
+            Either::B(
+                Payout::pay_out(
+                    payout.id,
+                    payout_payload,
+                    payment_payload,
+                    fee,
+                    destination,
+                    &postgres,
+                )
                 .from_err()
-                .map(move |_| ())
+                .map(move |payout| {
+                    notifier.do_send(Notify(Notification::PayoutSent {
+                        payout,
+                        transaction_hash: hash,
+                        explorer_url: config.explorer_transaction_url(&hash),
+                    }))
+                })
                 .or_else(move |e| -> Box<Future<Item = (), Error = Error>> {
                     match e {
                         // If payout address doesn't exist for the store, change payout object's action to Refund.
@@ -280,7 +647,8 @@ impl Handler<PayOut> for Payouter {
                         }
                         _ => Box::new(future::err(e)),
                     }
-                })
+                }),
+            )
         }))
     }
 }
@@ -295,15 +663,19 @@ impl Handler<Refund> for Payouter {
     fn handle(&mut self, Refund(payout): Refund, _: &mut Self::Context) -> Self::Result {
         let postgres = self.postgres.clone();
 
-        Box::new(self.refund(payout).from_err().and_then(move |hash| {
-            info!("Refunded {}", hash.hex());
-            let mut payload = PayoutPayload::from(payout);
-            payload.transaction_hash = Some(Some(hash));
-            payload.status = Some(PayoutStatus::Refunded);
-
-            Payout::update(payout.id, payload, &postgres)
+        Box::new(
+            self.refund(payout)
                 .from_err()
-                .map(move |_| ())
-        }))
+                .and_then(move |(hash, fee, destination)| {
+                    info!("Refunded {}", hash.hex());
+                    let mut payload = PayoutPayload::from(payout);
+                    payload.transaction_hash = Some(Some(hash));
+                    payload.status = Some(PayoutStatus::Refunded);
+
+                    Payout::refund(payout.id, payload, fee, Some(destination), &postgres)
+                        .from_err()
+                        .map(move |_| ())
+                }),
+        )
     }
 }
@@ -1,13 +1,46 @@
 use actix::prelude::*;
 
 use super::{monitor::Monitor, payouter::Payouter};
-use core::db::postgres;
 use blockchain_api_client::ethereum::BlockchainApiClientAddr;
-use types::ethereum::Network as EthNetwork;
+use config::{EthConfig, SignerConfig};
+use core::db::postgres;
+use errors::Error;
+use mailer::NotifierAddr;
+use signer::{remote_signer, SigningExecutor};
 
-pub fn run(postgres: postgres::PgExecutorAddr, blockchain_api_client: BlockchainApiClientAddr, network: EthNetwork) {
+pub fn run(
+    postgres: postgres::PgExecutorAddr,
+    blockchain_api_client: BlockchainApiClientAddr,
+    notifier: NotifierAddr,
+    config: EthConfig,
+    signer_config: Option<SignerConfig>,
+) -> Result<(), Error> {
+    let network = config.network;
     let pg = postgres.clone();
-    let payouter = Arbiter::start(move |_| Payouter::new(pg, blockchain_api_client, network));
+    let monitor_blockchain_api_client = blockchain_api_client.clone();
+    let monitor_notifier = notifier.clone();
+    let remote_signer = remote_signer(&signer_config)?;
+    let signing = SyncArbiter::start(2, || SigningExecutor);
+    let payouter = Arbiter::start(move |_| {
+        Payouter::new(
+            pg,
+            blockchain_api_client,
+            notifier,
+            config,
+            remote_signer,
+            signing,
+        )
+    });
+
+    Arbiter::start(move |_| {
+        Monitor::new(
+            payouter,
+            network,
+            postgres,
+            monitor_blockchain_api_client,
+            monitor_notifier,
+        )
+    });
 
-    Arbiter::start(move |_| Monitor::new(payouter, network, postgres));
+    Ok(())
 }
@@ -2,23 +2,30 @@
 
 extern crate actix;
 extern crate actix_web;
+extern crate bigdecimal;
 extern crate byteorder;
+extern crate chrono;
 #[macro_use]
 extern crate failure;
 extern crate futures;
 #[macro_use]
 extern crate log;
 extern crate env_logger;
+extern crate rustc_hex;
 extern crate serde;
 extern crate serde_json;
 extern crate tokio;
+extern crate uuid;
 
 extern crate config;
 extern crate core;
 extern crate hd_keyring;
+extern crate mailer;
 extern crate blockchain_api_client;
 extern crate types;
 
 pub mod bitcoin;
 pub mod errors;
 pub mod ethereum;
+mod settlement;
+pub mod signer;
@@ -0,0 +1,332 @@
+use std::{collections::HashMap, str::FromStr};
+
+use bigdecimal::{BigDecimal, Zero};
+use chrono::{prelude::*, Duration};
+
+use futures::{future, Future};
+
+use core::{
+    bitcoin::Transaction, db::postgres::PgExecutorAddr, payment::Payment, payout::Payout,
+    store::Store,
+};
+use errors::Error;
+use ethereum::payouter::{find_transfer, from_wei};
+use types::{currency::Crypto, PayoutAction, SettlementInterval};
+
+// The latest scheduled settlement of a store up to `now`, or `None` when payouts are sent as soon
+// as they are confirmed.
+fn last_settlement_at(
+    interval: Option<SettlementInterval>,
+    weekday: i32,
+    hour: i32,
+    now: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    let interval = interval?;
+    let today = now.date().and_hms(hour as u32, 0, 0);
+
+    let (settlement, period) = match interval {
+        SettlementInterval::Daily => (today, Duration::days(1)),
+        SettlementInterval::Weekly => {
+            let days =
+                (i64::from(now.weekday().num_days_from_monday()) - i64::from(weekday) + 7) % 7;
+
+            (today - Duration::days(days), Duration::days(7))
+        }
+    };
+
+    if settlement > now {
+        Some(settlement - period)
+    } else {
+        Some(settlement)
+    }
+}
+
+// What a store's settlement policy holds its payouts in a currency to.
+pub struct Policy {
+    pub interval: Option<SettlementInterval>,
+    pub weekday: i32,
+    pub hour: i32,
+    pub minimum: Option<BigDecimal>,
+}
+
+impl Policy {
+    pub fn new(store: &Store, crypto: &Crypto) -> Self {
+        Policy {
+            interval: store.settlement_interval,
+            weekday: store.settlement_weekday,
+            hour: store.settlement_hour,
+            minimum: store.payout_minimum(crypto),
+        }
+    }
+}
+
+// Filters confirmed payouts down to the ones their store's settlement policy lets go now. Payouts
+// of payments made after the last scheduled settlement wait for the next one, and the rest of a
+// store's payouts in a currency are only sent once the total they credited reaches the store's
+// minimum. Refunds and reviewed payouts are never held.
+//
+// Due payouts come in groups which are sent together: the released payouts of a store in a
+// currency, or a refund or reviewed payout on its own.
+pub fn due(
+    payouts: Vec<(Payout, BigDecimal, Policy, bool)>,
+    now: DateTime<Utc>,
+) -> Vec<Vec<Payout>> {
+    let mut due = Vec::new();
+    let mut held = HashMap::new();
+
+    for (payout, amount, policy, reviewed) in payouts {
+        if payout.action == PayoutAction::Refund || reviewed {
+            due.push(vec![payout]);
+            continue;
+        }
+
+        let settlement = last_settlement_at(policy.interval, policy.weekday, policy.hour, now);
+
+        if settlement.map_or(false, |settlement| payout.created_at > settlement) {
+            continue;
+        }
+
+        let entry = held
+            .entry((payout.store_id, payout.typ))
+            .or_insert_with(|| (policy.minimum, BigDecimal::zero(), Vec::new()));
+
+        entry.1 = entry.1.clone() + amount;
+        entry.2.push(payout);
+    }
+
+    for (_, (minimum, total, payouts)) in held {
+        match minimum {
+            Some(ref minimum) if total < *minimum => {
+                info!(
+                    "Holding {} payouts below the minimum of {}",
+                    payouts.len(),
+                    minimum
+                );
+            }
+            _ => due.push(payouts),
+        }
+    }
+
+    due
+}
+
+// The amount a payout credits: the value of the output it was created for, a payment paid with
+// several outputs having a payout per output. Payouts created before outputs were recorded are
+// for the payment's single output.
+fn payout_amount(
+    payout: Payout,
+    payment: &Payment,
+    postgres: &PgExecutorAddr,
+) -> Box<Future<Item = BigDecimal, Error = Error>> {
+    let amount_paid = payment.amount_paid.clone().unwrap_or_else(BigDecimal::zero);
+
+    match (
+        payout.typ,
+        payout.payment_transaction_hash,
+        payout.output_index,
+    ) {
+        (Crypto::Eth, _, _) => Box::new(
+            find_transfer(&payout, payment, postgres).map(|transfer| from_wei(transfer.value)),
+        ),
+        (_, Some(hash), Some(output_index)) => Box::new(
+            Transaction::find_by_hash(hash, postgres)
+                .from_err()
+                .map(move |transaction| {
+                    transaction
+                        .vout
+                        .iter()
+                        .find(|output| output.n == output_index as u32)
+                        .map(|output| BigDecimal::from_str(&format!("{}", output.value)).unwrap())
+                        .unwrap_or(amount_paid)
+                }),
+        ),
+        _ => Box::new(future::ok(amount_paid)),
+    }
+}
+
+// Loads what the settlement policies of confirmed payouts' stores need to tell which are due.
+pub fn find_due(
+    payouts: Vec<Payout>,
+    postgres: &PgExecutorAddr,
+) -> impl Future<Item = Vec<Vec<Payout>>, Error = Error> {
+    let postgres = postgres.clone();
+
+    future::join_all(payouts.into_iter().map(move |payout| {
+        let postgres = postgres.clone();
+
+        payout
+            .payment(&postgres)
+            .from_err()
+            .and_then(move |payment| payout_amount(payout, &payment, &postgres))
+            .join3(
+                payout.store(&postgres).from_err(),
+                Payout::find_approval(payout.id, &postgres).from_err(),
+            )
+            .map(move |(amount, store, approval)| {
+                let policy = Policy::new(&store, &payout.typ);
+
+                (payout, amount, policy, approval.is_some())
+            })
+    }))
+    .map(|payouts| due(payouts, Utc::now()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use types::{PayoutStatus, U128};
+    use uuid::Uuid;
+
+    fn payout(store_id: Uuid, action: PayoutAction, created_at: DateTime<Utc>) -> Payout {
+        Payout {
+            id: Uuid::new_v4(),
+            status: PayoutStatus::Pending,
+            action,
+            store_id,
+            payment_id: Uuid::new_v4(),
+            typ: Crypto::Btc,
+            block_height_required: U128::from(0),
+            transaction_hash: None,
+            created_at,
+            payment_transaction_hash: None,
+            output_index: None,
+            confirmation_notified_at: None,
+        }
+    }
+
+    fn policy(interval: Option<SettlementInterval>, minimum: Option<&str>) -> Policy {
+        Policy {
+            interval,
+            weekday: 0,
+            hour: 9,
+            minimum: minimum.map(|minimum| BigDecimal::from_str(minimum).unwrap()),
+        }
+    }
+
+    fn amount(amount: &str) -> BigDecimal {
+        BigDecimal::from_str(amount).unwrap()
+    }
+
+    #[test]
+    fn test_due_minimum() {
+        let now = Utc.ymd(2019, 6, 12).and_hms(10, 30, 0);
+        let created_at = now - Duration::hours(1);
+        let store_id = Uuid::new_v4();
+
+        // Held while the outputs credited stay below the minimum, even when the payments' amounts
+        // would reach it.
+        let first = payout(store_id, PayoutAction::Payout, created_at);
+        let second = payout(store_id, PayoutAction::Payout, created_at);
+        let released = due(
+            vec![
+                (first, amount("0.004"), policy(None, Some("0.01")), false),
+                (second, amount("0.005"), policy(None, Some("0.01")), false),
+            ],
+            now,
+        );
+        assert!(released.is_empty());
+
+        // Released together once the total reaches it.
+        let third = payout(store_id, PayoutAction::Payout, created_at);
+        let released = due(
+            vec![
+                (first, amount("0.004"), policy(None, Some("0.01")), false),
+                (second, amount("0.005"), policy(None, Some("0.01")), false),
+                (third, amount("0.001"), policy(None, Some("0.01")), false),
+            ],
+            now,
+        );
+        assert_eq!(released.len(), 1);
+        assert_eq!(released[0].len(), 3);
+
+        // Refunds and reviewed payouts aren't held.
+        let refund = payout(store_id, PayoutAction::Refund, created_at);
+        let released = due(
+            vec![
+                (refund, amount("0.001"), policy(None, Some("0.01")), false),
+                (first, amount("0.001"), policy(None, Some("0.01")), true),
+            ],
+            now,
+        );
+        assert_eq!(
+            released
+                .iter()
+                .map(|payouts| payouts.iter().map(|payout| payout.id).collect::<Vec<_>>())
+                .collect::<Vec<_>>(),
+            vec![vec![refund.id], vec![first.id]]
+        );
+    }
+
+    #[test]
+    fn test_due_schedule() {
+        // A Wednesday, after the daily settlement at 9:00.
+        let now = Utc.ymd(2019, 6, 12).and_hms(10, 30, 0);
+        let settlement = Utc.ymd(2019, 6, 12).and_hms(9, 0, 0);
+        let store_id = Uuid::new_v4();
+        let daily = || policy(Some(SettlementInterval::Daily), None);
+
+        let before = payout(
+            store_id,
+            PayoutAction::Payout,
+            settlement - Duration::seconds(1),
+        );
+        let at = payout(store_id, PayoutAction::Payout, settlement);
+        let after = payout(
+            store_id,
+            PayoutAction::Payout,
+            settlement + Duration::seconds(1),
+        );
+
+        let released = due(
+            vec![
+                (before, amount("0.1"), daily(), false),
+                (at, amount("0.1"), daily(), false),
+                (after, amount("0.1"), daily(), false),
+            ],
+            now,
+        );
+
+        // Payouts up to the settlement go out with it, later ones wait for the next.
+        assert_eq!(released.len(), 1);
+        let mut ids = released[0]
+            .iter()
+            .map(|payout| payout.id)
+            .collect::<Vec<_>>();
+        ids.sort();
+        let mut expected = vec![before.id, at.id];
+        expected.sort();
+        assert_eq!(ids, expected);
+
+        // Payouts of stores without a schedule go out right away.
+        let released = due(vec![(after, amount("0.1"), policy(None, None), false)], now);
+        assert_eq!(released.len(), 1);
+    }
+
+    #[test]
+    fn test_last_settlement_at() {
+        // A Wednesday.
+        let now = Utc.ymd(2019, 6, 12).and_hms(10, 30, 0);
+
+        assert_eq!(last_settlement_at(None, 0, 0, now), None);
+        assert_eq!(
+            last_settlement_at(Some(SettlementInterval::Daily), 0, 9, now),
+            Some(Utc.ymd(2019, 6, 12).and_hms(9, 0, 0))
+        );
+        assert_eq!(
+            last_settlement_at(Some(SettlementInterval::Daily), 0, 11, now),
+            Some(Utc.ymd(2019, 6, 11).and_hms(11, 0, 0))
+        );
+        assert_eq!(
+            last_settlement_at(Some(SettlementInterval::Weekly), 0, 0, now),
+            Some(Utc.ymd(2019, 6, 10).and_hms(0, 0, 0))
+        );
+        assert_eq!(
+            last_settlement_at(Some(SettlementInterval::Weekly), 2, 11, now),
+            Some(Utc.ymd(2019, 6, 5).and_hms(11, 0, 0))
+        );
+        assert_eq!(
+            last_settlement_at(Some(SettlementInterval::Weekly), 4, 0, now),
+            Some(Utc.ymd(2019, 6, 7).and_hms(0, 0, 0))
+        );
+    }
+}
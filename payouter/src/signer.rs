@@ -0,0 +1,112 @@
+use actix::prelude::*;
+use futures::Future;
+
+use config::SignerConfig;
+use core::{payment::Payment, store::Store};
+use errors::Error;
+use hd_keyring::signer::{InMemorySigner, KeyId, RemoteSigner, Signer, SignerAddress};
+use types::bitcoin::Network as BtcNetwork;
+
+pub fn remote_signer(config: &Option<SignerConfig>) -> Result<Option<RemoteSigner>, Error> {
+    let config = match *config {
+        Some(ref config) => config,
+        None => return Ok(None),
+    };
+
+    let address: SignerAddress = config.address.parse()?;
+
+    if config.secret.is_empty() {
+        return Err(Error::EmptySignerSecret);
+    }
+
+    Ok(Some(RemoteSigner::new(address, &config.secret)))
+}
+
+// The key a payment was paid to, and what signs with it.
+pub struct PaymentKey {
+    pub signer: Box<Signer + Send>,
+    pub id: KeyId,
+}
+
+impl PaymentKey {
+    // Without a signer process, the key is derived in-process from the store's mnemonic, as kept
+    // for stores created without one.
+    pub fn new(
+        remote_signer: &Option<RemoteSigner>,
+        store: &Store,
+        payment: &Payment,
+        hd_path: String,
+        btc_network: BtcNetwork,
+    ) -> Result<Self, Error> {
+        let store_id = store.id.to_string();
+
+        let signer: Box<Signer + Send> = match *remote_signer {
+            Some(ref remote_signer) => Box::new(remote_signer.clone()),
+            None => {
+                let mnemonic = store.mnemonic.as_ref().ok_or(Error::NoSigner)?;
+                let passphrase = store.mnemonic_passphrase.clone().unwrap_or_default();
+
+                let mut signer = InMemorySigner::new(btc_network);
+                signer.add_store(&store_id, mnemonic, &passphrase)?;
+                Box::new(signer)
+            }
+        };
+
+        Ok(PaymentKey {
+            signer,
+            id: KeyId {
+                store_id,
+                hd_path,
+                index: payment.index as u32,
+            },
+        })
+    }
+}
+
+pub type SigningExecutorAddr = Addr<SigningExecutor>;
+
+// Signs with payment keys on threads of its own, as a remote signer is waited on over a socket
+// which would otherwise block the payouters.
+pub struct SigningExecutor;
+
+impl Actor for SigningExecutor {
+    type Context = SyncContext<Self>;
+}
+
+// Runs `F` with the payment key, or keys, yielding what it signed.
+pub struct Sign<K, F>(pub K, pub F);
+
+impl<K, F, T> Message for Sign<K, F>
+where
+    K: Send + 'static,
+    F: FnOnce(&K) -> Result<T, Error> + Send + 'static,
+    T: Send + 'static,
+{
+    type Result = Result<T, Error>;
+}
+
+impl<K, F, T> Handler<Sign<K, F>> for SigningExecutor
+where
+    K: Send + 'static,
+    F: FnOnce(&K) -> Result<T, Error> + Send + 'static,
+    T: Send + 'static,
+{
+    type Result = Result<T, Error>;
+
+    fn handle(&mut self, Sign(key, sign): Sign<K, F>, _: &mut Self::Context) -> Self::Result {
+        sign(&key)
+    }
+}
+
+pub fn sign<K, F, T>(
+    signing: &SigningExecutorAddr,
+    key: K,
+    sign: F,
+) -> impl Future<Item = T, Error = Error>
+where
+    K: Send + 'static,
+    F: FnOnce(&K) -> Result<T, Error> + Send + 'static,
+    T: Send + 'static,
+{
+    signing.send(Sign(key, sign)).from_err().and_then(|res| res)
+}
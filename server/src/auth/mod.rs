@@ -1,8 +1,11 @@
+use std::net::IpAddr;
+
 use actix_web::{error, Error as ActixError, FromRequest, HttpMessage, HttpRequest};
 use base64::decode;
 use chrono::prelude::*;
 use futures::future::{err, Future};
 use jwt;
+use ring::constant_time::verify_slices_are_equal;
 use uuid::Uuid;
 
 use core::client_token::ClientToken;
@@ -97,6 +100,7 @@ impl FromRequest<AppState> for AuthClient {
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct AuthUser {
     pub id: Uuid,
+    pub session_id: Option<Uuid>,
 }
 
 impl FromRequest<AppState> for AuthUser {
@@ -113,6 +117,109 @@ impl FromRequest<AppState> for AuthUser {
     }
 }
 
+// The address of the client. `X-Forwarded-For` is only taken into account when the request came
+// through the `trusted_proxies` of the server configuration, since anyone can set it.
+#[derive(Debug, Clone)]
+pub struct RemoteIp(pub Option<String>);
+
+impl FromRequest<AppState> for RemoteIp {
+    type Config = ();
+    type Result = Result<RemoteIp, ActixError>;
+
+    fn from_request(req: &HttpRequest<AppState>, _cfg: &Self::Config) -> Self::Result {
+        let remote = client_ip(req, &req.state().config.trusted_proxies);
+
+        Ok(RemoteIp(remote.map(|remote| remote.to_string())))
+    }
+}
+
+// The address of the client of the request, unknown when it didn't come over TCP.
+pub fn client_ip<S>(req: &HttpRequest<S>, trusted_proxies: &[IpAddr]) -> Option<IpAddr> {
+    let peer = req.peer_addr()?.ip();
+
+    let forwarded_for: Vec<&str> = req
+        .headers()
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|header| header.to_str().ok())
+        .collect();
+
+    Some(remote_ip(peer, &forwarded_for.join(","), trusted_proxies))
+}
+
+// Each proxy appends the address it got the request from to `X-Forwarded-For`, so the client is
+// the last address which isn't one of a trusted proxy. An address which doesn't parse could be
+// made up by the client, and ends the search.
+fn remote_ip(peer: IpAddr, forwarded_for: &str, trusted_proxies: &[IpAddr]) -> IpAddr {
+    let mut remote = peer;
+
+    for addr in forwarded_for.rsplit(',') {
+        if !trusted_proxies.contains(&remote) {
+            break;
+        }
+
+        remote = match addr.trim().parse() {
+            Ok(addr) => addr,
+            Err(_) => break,
+        };
+    }
+
+    remote
+}
+
+#[derive(Debug, Clone)]
+pub struct UserAgent(pub Option<String>);
+
+impl FromRequest<AppState> for UserAgent {
+    type Config = ();
+    type Result = Result<UserAgent, ActixError>;
+
+    fn from_request(req: &HttpRequest<AppState>, _cfg: &Self::Config) -> Self::Result {
+        let user_agent = req
+            .headers()
+            .get("user-agent")
+            .and_then(|user_agent| user_agent.to_str().ok())
+            .map(|user_agent| user_agent.to_string());
+
+        Ok(UserAgent(user_agent))
+    }
+}
+
+// An operator of the instance, authenticated with the `operator_token` of the server configuration.
+#[derive(Debug, Clone)]
+pub struct AuthOperator;
+
+impl FromRequest<AppState> for AuthOperator {
+    type Config = ();
+    type Result = Result<AuthOperator, ActixError>;
+
+    fn from_request(req: &HttpRequest<AppState>, _cfg: &Self::Config) -> Self::Result {
+        let operator_token = match req.state().config.operator_token {
+            Some(ref operator_token) => operator_token.clone(),
+            None => return Err(error::ErrorNotFound("not found")),
+        };
+
+        let auth_header = match req.headers().get("authorization") {
+            Some(auth_header) => auth_header,
+            None => return Err(error::ErrorUnauthorized("invalid authorization token")),
+        };
+
+        let auth_header_parts: Vec<_> = auth_header
+            .to_str()
+            .map_err(|_| error::ErrorUnauthorized("invalid authorization token"))?
+            .split_whitespace()
+            .collect();
+
+        if auth_header_parts.len() != 2 || auth_header_parts[0].to_lowercase() != "bearer" {
+            return Err(error::ErrorUnauthorized("invalid authorization token"));
+        }
+
+        verify_slices_are_equal(auth_header_parts[1].as_bytes(), operator_token.as_bytes())
+            .map(|_| AuthOperator)
+            .map_err(|_| error::ErrorUnauthorized("invalid authorization token"))
+    }
+}
+
 impl FromRequest<AppState> for ClientToken {
     type Config = ();
     type Result = Box<Future<Item = ClientToken, Error = ActixError>>;
@@ -171,3 +278,45 @@ impl FromRequest<AppState> for ClientToken {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(addr: &str) -> IpAddr {
+        addr.parse().unwrap()
+    }
+
+    #[test]
+    fn forwarded_addresses_are_ignored_from_untrusted_peers() {
+        let remote = remote_ip(ip("203.0.113.7"), "198.51.100.1", &[ip("127.0.0.1")]);
+
+        assert_eq!(remote, ip("203.0.113.7"));
+    }
+
+    #[test]
+    fn the_client_is_the_last_address_before_the_trusted_proxies() {
+        let trusted_proxies = &[ip("127.0.0.1"), ip("10.0.0.2")];
+
+        assert_eq!(
+            remote_ip(ip("127.0.0.1"), "198.51.100.1", trusted_proxies),
+            ip("198.51.100.1")
+        );
+        assert_eq!(
+            remote_ip(
+                ip("127.0.0.1"),
+                "192.0.2.9, 198.51.100.1, 10.0.0.2",
+                trusted_proxies
+            ),
+            ip("198.51.100.1")
+        );
+        assert_eq!(
+            remote_ip(ip("127.0.0.1"), "unknown, 10.0.0.2", trusted_proxies),
+            ip("10.0.0.2")
+        );
+        assert_eq!(
+            remote_ip(ip("127.0.0.1"), "", trusted_proxies),
+            ip("127.0.0.1")
+        );
+    }
+}
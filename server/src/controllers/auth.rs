@@ -1,17 +1,22 @@
-use actix_web::{Json, Path, State};
+use actix_web::{Json, Path, Query, State};
 use futures::future::{err, Future};
 use serde_json::Value;
 use uuid::Uuid;
 
-use auth::AuthUser;
+use auth::{AuthUser, RemoteIp, UserAgent};
 use core::user::UserPayload;
 use services::{self, Error};
 use state::AppState;
+use types::Locale;
+
+const LIMIT: i64 = 15;
+const OFFSET: i64 = 0;
 
 #[derive(Debug, Deserialize)]
 pub struct RegistrationParams {
     pub email: String,
     pub password: String,
+    pub locale: Option<Locale>,
 }
 
 pub fn registration(
@@ -30,6 +35,7 @@ pub fn registration(
     let mut payload = UserPayload::new();
     payload.email = Some(params.email);
     payload.password = Some(params.password);
+    payload.locale = Some(params.locale.unwrap_or_default());
 
     Box::new(
         services::users::register(
@@ -50,18 +56,32 @@ pub struct LoginParams {
 }
 
 pub fn authentication(
-    (state, params): (State<AppState>, Json<LoginParams>),
+    (state, params, remote_ip, user_agent): (
+        State<AppState>,
+        Json<LoginParams>,
+        RemoteIp,
+        UserAgent,
+    ),
 ) -> impl Future<Item = Json<Value>, Error = Error> {
     let params = params.into_inner();
 
     services::users::authenticate(
         params.email,
         params.password,
+        remote_ip.0,
+        user_agent.0,
+        state.rate_limiter.clone(),
         &state.postgres,
         state.jwt_private.clone(),
     )
     .then(|res| {
-        res.and_then(|(token, user)| Ok(Json(json!({ "token": token, "user": user.export() }))))
+        res.and_then(|(token, refresh_token, user)| {
+            Ok(Json(json!({
+                "token": token,
+                "refresh_token": refresh_token,
+                "user": user.export(),
+            })))
+        })
     })
 }
 
@@ -71,15 +91,31 @@ pub struct ActivationParams {
 }
 
 pub fn activation(
-    (state, params): (State<AppState>, Json<ActivationParams>),
+    (state, params, remote_ip, user_agent): (
+        State<AppState>,
+        Json<ActivationParams>,
+        RemoteIp,
+        UserAgent,
+    ),
 ) -> impl Future<Item = Json<Value>, Error = Error> {
     let params = params.into_inner();
 
-    services::users::activate(params.token, &state.postgres, state.jwt_private.clone()).then(
-        |res| {
-            res.and_then(|(token, user)| Ok(Json(json!({ "token": token, "user": user.export() }))))
-        },
+    services::users::activate(
+        params.token,
+        remote_ip.0,
+        user_agent.0,
+        &state.postgres,
+        state.jwt_private.clone(),
     )
+    .then(|res| {
+        res.and_then(|(token, refresh_token, user)| {
+            Ok(Json(json!({
+                "token": token,
+                "refresh_token": refresh_token,
+                "user": user.export(),
+            })))
+        })
+    })
 }
 
 #[derive(Deserialize)]
@@ -88,12 +124,13 @@ pub struct ResetPasswordParams {
 }
 
 pub fn reset_password(
-    (state, params): (State<AppState>, Json<ResetPasswordParams>),
+    (state, params, remote_ip): (State<AppState>, Json<ResetPasswordParams>, RemoteIp),
 ) -> impl Future<Item = Json<Value>, Error = Error> {
     let params = params.into_inner();
 
     services::users::reset_password(
         params.email,
+        remote_ip.0,
         state.mailer.clone(),
         &state.postgres,
         state.config.web_client_url.clone(),
@@ -109,21 +146,133 @@ pub struct ChangePasswordParams {
 }
 
 pub fn change_password(
-    (state, params): (State<AppState>, Json<ChangePasswordParams>),
+    (state, params, remote_ip, user_agent): (
+        State<AppState>,
+        Json<ChangePasswordParams>,
+        RemoteIp,
+        UserAgent,
+    ),
 ) -> impl Future<Item = Json<Value>, Error = Error> {
     let params = params.into_inner();
 
     services::users::change_password(
         params.token,
         params.password,
+        remote_ip.0,
+        user_agent.0,
         &state.postgres,
         state.jwt_private.clone(),
     )
     .then(|res| {
-        res.and_then(|(token, user)| Ok(Json(json!({ "token": token, "user": user.export() }))))
+        res.and_then(|(token, refresh_token, user)| {
+            Ok(Json(json!({
+                "token": token,
+                "refresh_token": refresh_token,
+                "user": user.export(),
+            })))
+        })
     })
 }
 
+#[derive(Deserialize)]
+pub struct RefreshTokenParams {
+    pub refresh_token: String,
+}
+
+pub fn refresh_token(
+    (state, params): (State<AppState>, Json<RefreshTokenParams>),
+) -> impl Future<Item = Json<Value>, Error = Error> {
+    let params = params.into_inner();
+
+    services::sessions::refresh(
+        params.refresh_token,
+        &state.postgres,
+        state.jwt_private.clone(),
+    )
+    .then(|res| {
+        res.and_then(|(token, refresh_token)| {
+            Ok(Json(json!({
+                "token": token,
+                "refresh_token": refresh_token,
+            })))
+        })
+    })
+}
+
+pub fn logout(
+    (state, user): (State<AppState>, AuthUser),
+) -> Box<Future<Item = Json<Value>, Error = Error>> {
+    let session_id = match user.session_id {
+        Some(session_id) => session_id,
+        None => return Box::new(err(Error::BadRequest("token has no session"))),
+    };
+
+    Box::new(
+        services::sessions::revoke(session_id, user.id, &state.postgres)
+            .then(|res| res.and_then(|revoked| Ok(Json(json!({ "revoked": revoked }))))),
+    )
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListParams {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+pub fn sessions(
+    (state, params, user): (State<AppState>, Query<ListParams>, AuthUser),
+) -> impl Future<Item = Json<Value>, Error = Error> {
+    let mut limit = LIMIT;
+    let mut offset = OFFSET;
+
+    if let Some(_limit) = params.limit {
+        if _limit < LIMIT {
+            limit = _limit;
+        }
+    };
+
+    if let Some(_offset) = params.offset {
+        offset = _offset;
+    };
+
+    let current_session_id = user.session_id;
+
+    services::sessions::find_by_user(user.id, limit, offset, &state.postgres).then(move |res| {
+        res.and_then(|sessions| {
+            let exported: Vec<Value> = sessions
+                .into_iter()
+                .map(|session| {
+                    let mut exported = session.export();
+                    exported["current"] = json!(Some(session.id) == current_session_id);
+                    exported
+                })
+                .collect();
+
+            Ok(Json(json!({
+                "sessions": exported,
+                "limit": limit,
+                "offset": offset,
+            })))
+        })
+    })
+}
+
+pub fn revoke_session(
+    (state, path, user): (State<AppState>, Path<Uuid>, AuthUser),
+) -> impl Future<Item = Json<Value>, Error = Error> {
+    let id = path.into_inner();
+
+    services::sessions::revoke(id, user.id, &state.postgres)
+        .then(|res| res.and_then(|revoked| Ok(Json(json!({ "revoked": revoked })))))
+}
+
+pub fn revoke_all_sessions(
+    (state, user): (State<AppState>, AuthUser),
+) -> impl Future<Item = Json<Value>, Error = Error> {
+    services::sessions::revoke_all(user.id, &state.postgres)
+        .then(|res| res.and_then(|revoked| Ok(Json(json!({ "revoked": revoked })))))
+}
+
 pub fn profile(
     (state, user): (State<AppState>, AuthUser),
 ) -> impl Future<Item = Json<Value>, Error = Error> {
@@ -132,7 +281,7 @@ pub fn profile(
 }
 
 pub fn delete(
-    (state, path, user): (State<AppState>, Path<Uuid>, AuthUser),
+    (state, path, user, remote_ip): (State<AppState>, Path<Uuid>, AuthUser, RemoteIp),
 ) -> Box<Future<Item = Json<Value>, Error = Error>> {
     let id = path.into_inner();
 
@@ -141,7 +290,7 @@ pub fn delete(
     }
 
     Box::new(
-        services::users::delete(user.id, &state.postgres)
+        services::users::delete(user.id, remote_ip.0, &state.postgres)
             .then(|res| res.and_then(|deleted| Ok(Json(json!({ "deleted": deleted }))))),
     )
 }
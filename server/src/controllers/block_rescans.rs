@@ -0,0 +1,72 @@
+use actix_web::{Json, Path, State};
+use futures::future::{err, Future};
+use serde_json::Value;
+use uuid::Uuid;
+
+use auth::AuthOperator;
+use core::block_rescan::BlockRescanPayload;
+use services::{self, Error};
+use state::AppState;
+use types::{currency::Crypto, ethereum::Network as EthNetwork, U128};
+
+#[derive(Debug, Deserialize)]
+pub struct CreateParams {
+    pub crypto: Crypto,
+    // EVM chain id to rescan. Defaults to the first configured EVM network.
+    pub chain_id: Option<u64>,
+    pub from_block: u64,
+    pub to_block: u64,
+}
+
+pub fn create(
+    (state, _, params): (State<AppState>, AuthOperator, Json<CreateParams>),
+) -> Box<Future<Item = Json<Value>, Error = Error>> {
+    let params = params.into_inner();
+
+    if params.crypto == Crypto::BtcLightning {
+        return Box::new(err(Error::BadRequest(
+            "lightning payments aren't processed from blocks",
+        )));
+    }
+
+    if !state.supports(&params.crypto) {
+        return Box::new(err(Error::CurrencyNotSupported));
+    }
+
+    if params.from_block > params.to_block {
+        return Box::new(err(Error::BadRequest(
+            "from_block must not be greater than to_block",
+        )));
+    }
+
+    let mut payload = BlockRescanPayload::new();
+    payload.crypto = Some(params.crypto);
+    payload.from_block = Some(U128::from(params.from_block));
+    payload.to_block = Some(U128::from(params.to_block));
+
+    match params.crypto {
+        Crypto::Eth => match state.evm_config(params.chain_id.map(EthNetwork)) {
+            Some(eth_config) => payload.eth_network = Some(Some(eth_config.network)),
+            None => return Box::new(err(Error::CurrencyNotSupported)),
+        },
+        _ => {
+            if params.chain_id.is_some() {
+                return Box::new(err(Error::BadRequest(
+                    "chain_id is only available for ethereum",
+                )));
+            }
+        }
+    }
+
+    Box::new(
+        services::block_rescans::create(payload, &state.postgres)
+            .map(|rescan| Json(rescan.export())),
+    )
+}
+
+pub fn get(
+    (state, path, _): (State<AppState>, Path<Uuid>, AuthOperator),
+) -> impl Future<Item = Json<Value>, Error = Error> {
+    services::block_rescans::get(path.into_inner(), &state.postgres)
+        .map(|rescan| Json(rescan.export()))
+}
@@ -3,7 +3,7 @@ use futures::future::{Future, IntoFuture};
 use serde_json::Value;
 use uuid::Uuid;
 
-use auth::AuthUser;
+use auth::{AuthUser, RemoteIp};
 use core::{client_token::ClientTokenPayload, store::Store};
 use state::AppState;
 use services::{self, Error};
@@ -29,7 +29,7 @@ fn validate_store_owner(store: &Store, user: &AuthUser) -> Result<bool, Error> {
 }
 
 pub fn create(
-    (state, user, params): (State<AppState>, AuthUser, Json<CreateParams>),
+    (state, user, params, remote_ip): (State<AppState>, AuthUser, Json<CreateParams>, RemoteIp),
 ) -> impl Future<Item = Json<Value>, Error = Error> {
     let mut params = params.into_inner();
 
@@ -51,7 +51,7 @@ pub fn create(
                     typ: params.typ,
                 };
 
-                services::client_tokens::create(payload, &state.postgres)
+                services::client_tokens::create(payload, user.id, remote_ip.0, &state.postgres)
                     .then(|res| res.and_then(|client_token| Ok(Json(client_token.export()))))
             })
     })
@@ -117,7 +117,7 @@ pub fn get(
 }
 
 pub fn delete(
-    (state, path, user): (State<AppState>, Path<Uuid>, AuthUser),
+    (state, path, user, remote_ip): (State<AppState>, Path<Uuid>, AuthUser, RemoteIp),
 ) -> impl Future<Item = Json<Value>, Error = Error> {
     let id = path.into_inner();
 
@@ -126,7 +126,7 @@ pub fn delete(
             validate_store_owner(&store, &user)
                 .into_future()
                 .and_then(move |_| {
-                    services::client_tokens::delete(id, &state.postgres)
+                    services::client_tokens::delete(id, user.id, remote_ip.0, &state.postgres)
                         .then(|res| res.and_then(|res| Ok(Json(json!({ "deleted": res })))))
                 })
         })
@@ -0,0 +1,148 @@
+use actix_web::{http::header::CONTENT_DISPOSITION, HttpResponse, Path, Query, State};
+use bytes::Bytes;
+use chrono::prelude::*;
+use futures::{
+    future::{Either, Future, IntoFuture},
+    Stream,
+};
+use uuid::Uuid;
+
+use auth::{AuthUser, RemoteIp};
+use core::{export, store::Store};
+use services::{self, Error};
+use state::AppState;
+use types::{ExportFormat, ExportKind};
+
+fn validate_store_owner(store: &Store, user: &AuthUser) -> Result<bool, Error> {
+    if store.owner_id != user.id {
+        return Err(Error::InvalidRequestAccount);
+    }
+
+    Ok(true)
+}
+
+fn attachment(file_name: String) -> String {
+    format!("attachment; filename=\"{}\"", file_name)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExportParams {
+    pub kind: ExportKind,
+    pub format: Option<ExportFormat>,
+    // Unix timestamps bounding the creation times of what is exported, `to` being excluded.
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    // Generates the export in the background and emails a download link instead of streaming it.
+    pub email: Option<bool>,
+}
+
+impl ExportParams {
+    fn validate(&self) -> Result<(Option<DateTime<Utc>>, Option<DateTime<Utc>>), Error> {
+        let timestamp = |seconds: Option<i64>| match seconds {
+            Some(seconds) => Utc
+                .timestamp_opt(seconds, 0)
+                .single()
+                .map(Some)
+                .ok_or(Error::BadRequest("invalid timestamp")),
+            None => Ok(None),
+        };
+
+        let from = timestamp(self.from)?;
+        let to = timestamp(self.to)?;
+
+        if let (Some(from), Some(to)) = (from, to) {
+            if from >= to {
+                return Err(Error::BadRequest("from must be before to"));
+            }
+        }
+
+        Ok((from, to))
+    }
+}
+
+pub fn get(
+    (state, path, params, user, remote_ip): (
+        State<AppState>,
+        Path<Uuid>,
+        Query<ExportParams>,
+        AuthUser,
+        RemoteIp,
+    ),
+) -> impl Future<Item = HttpResponse, Error = Error> {
+    let id = path.into_inner();
+    let params = params.into_inner();
+    let kind = params.kind;
+    let format = params.format.unwrap_or(ExportFormat::Csv);
+    let email = params.email.unwrap_or(false);
+
+    params.validate().into_future().and_then(move |(from, to)| {
+        services::stores::get(id, &state.postgres).and_then(move |store| {
+            validate_store_owner(&store, &user)
+                .into_future()
+                .and_then(move |_| {
+                    if email {
+                        return Either::A(
+                            services::exports::request(
+                                store,
+                                kind,
+                                format,
+                                from,
+                                to,
+                                user.id,
+                                remote_ip.0,
+                                state.mailer.clone(),
+                                &state.postgres,
+                                state.config.export_dir.clone(),
+                                state.config.web_client_url.clone(),
+                                state.config.mail_sender.clone(),
+                            )
+                            .map(|export| HttpResponse::Accepted().json(export.export())),
+                        );
+                    }
+
+                    let file_name = export::file_name(kind, format, Utc::now());
+
+                    Either::B(
+                        services::exports::stream(
+                            store.id,
+                            kind,
+                            format,
+                            from,
+                            to,
+                            user.id,
+                            remote_ip.0,
+                            &state.postgres,
+                        )
+                        .map(move |rows| {
+                            HttpResponse::Ok()
+                                .content_type(format.content_type())
+                                .header(CONTENT_DISPOSITION, attachment(file_name))
+                                .streaming(rows.map(Bytes::from))
+                        }),
+                    )
+                })
+        })
+    })
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DownloadParams {
+    pub token: Uuid,
+}
+
+pub fn download(
+    (state, path, params): (State<AppState>, Path<Uuid>, Query<DownloadParams>),
+) -> impl Future<Item = HttpResponse, Error = Error> {
+    services::exports::download(
+        path.into_inner(),
+        params.token,
+        state.config.export_dir.clone(),
+        &state.postgres,
+    )
+    .map(|(export, chunks)| {
+        HttpResponse::Ok()
+            .content_type(export.format.content_type())
+            .header(CONTENT_DISPOSITION, attachment(export.file_name()))
+            .streaming(chunks.map(Bytes::from))
+    })
+}
@@ -1,6 +1,11 @@
 pub mod auth;
+pub mod block_rescans;
 pub mod client_tokens;
+pub mod exports;
+pub mod notification_preferences;
 pub mod payments;
+pub mod payout_address_changes;
+pub mod payout_approvals;
 pub mod root;
 pub mod stores;
 pub mod vouchers;
@@ -0,0 +1,89 @@
+use actix_web::{Json, Path, State};
+use futures::future::{err, Future, IntoFuture};
+use serde_json::Value;
+use uuid::Uuid;
+
+use auth::{AuthUser, RemoteIp};
+use core::{notification_preference::NotificationPreferencePayload, store::Store};
+use services::{self, Error};
+use state::AppState;
+
+fn validate_store_owner(store: &Store, user: &AuthUser) -> Result<bool, Error> {
+    if store.owner_id != user.id {
+        return Err(Error::InvalidRequestAccount);
+    }
+
+    Ok(true)
+}
+
+pub fn get(
+    (state, path, user): (State<AppState>, Path<Uuid>, AuthUser),
+) -> impl Future<Item = Json<Value>, Error = Error> {
+    let store_id = path.into_inner();
+
+    services::stores::get(store_id, &state.postgres).and_then(move |store| {
+        validate_store_owner(&store, &user)
+            .into_future()
+            .and_then(move |_| {
+                services::notification_preferences::get(store.id, &state.postgres)
+                    .then(|res| res.and_then(|preference| Ok(Json(preference.export()))))
+            })
+    })
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PatchParams {
+    // An empty string sends notifications to the store owner again.
+    pub email: Option<String>,
+    pub payment_confirmed: Option<bool>,
+    pub insufficient_amount: Option<bool>,
+    pub payout_sent: Option<bool>,
+    pub payout_failed: Option<bool>,
+}
+
+pub fn patch(
+    (state, path, params, user, remote_ip): (
+        State<AppState>,
+        Path<Uuid>,
+        Json<PatchParams>,
+        AuthUser,
+        RemoteIp,
+    ),
+) -> Box<Future<Item = Json<Value>, Error = Error>> {
+    let store_id = path.into_inner();
+    let params = params.into_inner();
+
+    let mut payload = NotificationPreferencePayload::new();
+
+    if let Some(email) = params.email {
+        if email.len() == 0 {
+            payload.email = Some(None);
+        } else if !email.contains("@") {
+            return Box::new(err(Error::BadRequest("invalid email")));
+        } else {
+            payload.email = Some(Some(email));
+        }
+    }
+
+    payload.payment_confirmed = params.payment_confirmed;
+    payload.insufficient_amount = params.insufficient_amount;
+    payload.payout_sent = params.payout_sent;
+    payload.payout_failed = params.payout_failed;
+
+    Box::new(
+        services::stores::get(store_id, &state.postgres).and_then(move |store| {
+            validate_store_owner(&store, &user)
+                .into_future()
+                .and_then(move |_| {
+                    services::notification_preferences::update(
+                        store.id,
+                        payload,
+                        user.id,
+                        remote_ip.0,
+                        &state.postgres,
+                    )
+                    .then(|res| res.and_then(|preference| Ok(Json(preference.export()))))
+                })
+        }),
+    )
+}
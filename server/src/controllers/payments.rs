@@ -1,3 +1,5 @@
+use std::cmp;
+
 use actix_web::{Json, Path, State};
 use bigdecimal::BigDecimal;
 use futures::future::{self, err, ok, Future, IntoFuture};
@@ -15,6 +17,7 @@ use services::{self, Error};
 use state::AppState;
 use types::{
     currency::{Crypto, Fiat},
+    ethereum::Network as EthNetwork,
     PaymentStatus, U128,
 };
 
@@ -24,6 +27,8 @@ pub struct CreateParams {
     pub fiat: Fiat,
     pub price: BigDecimal,
     pub identifier: Option<String>,
+    // EVM chain id to accept the payment on. Defaults to the first configured EVM network.
+    pub chain_id: Option<u64>,
 }
 
 pub fn create(
@@ -63,23 +68,39 @@ pub fn create(
                 }
 
                 let min_charge;
+                let symbol;
 
                 match params.crypto {
-                    Crypto::Btc => {
-                        payload.confirmations_required = store.btc_confirmations_required;
-                        payload.btc_network = state
-                            .clone()
-                            .btc_config
-                            .map_or(None, |config| Some(config.network));
-                        min_charge = state.clone().btc_config.unwrap().min_charge;
-                    }
                     Crypto::Eth => {
-                        payload.confirmations_required = store.eth_confirmations_required;
-                        payload.eth_network = state
-                            .clone()
-                            .eth_config
-                            .map_or(None, |config| Some(config.network));
-                        min_charge = state.clone().eth_config.unwrap().min_charge;
+                        let eth_config = match state.evm_config(params.chain_id.map(EthNetwork)) {
+                            Some(eth_config) => eth_config,
+                            None => return Box::new(err(Error::CurrencyNotSupported)),
+                        };
+
+                        payload.confirmations_required = Some(cmp::max(
+                            store.eth_confirmations_required.unwrap_or(0),
+                            eth_config.confirmations_required,
+                        ));
+                        payload.eth_network = Some(eth_config.network);
+                        min_charge = eth_config.min_charge;
+                        symbol = eth_config.symbol;
+                    }
+                    Crypto::BtcLightning => {
+                        let lightning_config = state.lightning_config.clone().unwrap();
+
+                        payload.confirmations_required =
+                            store.confirmations_required(&params.crypto);
+                        payload.btc_network = Some(lightning_config.network);
+                        min_charge = lightning_config.min_charge;
+                        symbol = Crypto::Btc.to_string();
+                    }
+                    crypto => {
+                        let btc_config = state.bitcoin_like_config(&crypto).unwrap();
+
+                        payload.confirmations_required = store.confirmations_required(&crypto);
+                        payload.btc_network = Some(btc_config.network);
+                        min_charge = btc_config.min_charge;
+                        symbol = crypto.to_string();
                     }
                 }
 
@@ -89,7 +110,9 @@ pub fn create(
                         &store,
                         &state.postgres,
                         min_charge,
+                        symbol,
                         state.currency_api_client.clone(),
+                        state.lightning_client.clone(),
                     )
                     .and_then(move |payment| {
                         JWTPayload::new(None, Some(auth_client), payment.expires_at)
@@ -136,16 +159,22 @@ pub fn get_status(
         .and_then(move |payment| {
             let block_height_future: Box<Future<Item = U128, Error = Error>> = match payment.crypto
             {
-                Crypto::Btc => Box::new(
-                    BtcBlockchainStatus::find(payment.btc_network.unwrap(), &state.postgres)
-                        .from_err()
-                        .map(move |status| status.block_height),
-                ),
+                // Lightning payments don't wait for confirmations.
+                Crypto::BtcLightning => Box::new(future::ok(U128::from(0))),
                 Crypto::Eth => Box::new(
                     EthBlockchainStatus::find(payment.eth_network.unwrap(), &state.postgres)
                         .from_err()
                         .map(move |status| status.block_height),
                 ),
+                crypto => Box::new(
+                    BtcBlockchainStatus::find(
+                        crypto,
+                        payment.btc_network.unwrap(),
+                        &state.postgres,
+                    )
+                    .from_err()
+                    .map(move |status| status.block_height),
+                ),
             };
 
             block_height_future.and_then(move |block_height| {
@@ -0,0 +1,131 @@
+use actix_web::{Json, Path, Query, State};
+use chrono::Duration;
+use futures::future::{Future, IntoFuture};
+use serde_json::Value;
+use uuid::Uuid;
+
+use auth::{AuthUser, RemoteIp};
+use core::store::Store;
+use services::{self, Error};
+use state::AppState;
+
+const LIMIT: i64 = 15;
+const OFFSET: i64 = 0;
+
+fn validate_store_owner(store: &Store, user: &AuthUser) -> Result<bool, Error> {
+    if store.owner_id != user.id {
+        return Err(Error::InvalidRequestAccount);
+    }
+
+    Ok(true)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListParams {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+pub fn list(
+    (state, path, params, user): (State<AppState>, Path<Uuid>, Query<ListParams>, AuthUser),
+) -> impl Future<Item = Json<Value>, Error = Error> {
+    let store_id = path.into_inner();
+    let mut limit = LIMIT;
+    let mut offset = OFFSET;
+
+    if let Some(_limit) = params.limit {
+        if _limit < LIMIT {
+            limit = _limit;
+        }
+    };
+
+    if let Some(_offset) = params.offset {
+        offset = _offset;
+    };
+
+    services::stores::get(store_id, &state.postgres).and_then(move |store| {
+        validate_store_owner(&store, &user)
+            .into_future()
+            .and_then(move |_| {
+                services::payout_address_changes::find_by_store(
+                    store.id,
+                    limit,
+                    offset,
+                    &state.postgres,
+                )
+                .then(move |res| {
+                    res.and_then(|changes| {
+                        let exported: Vec<Value> =
+                            changes.into_iter().map(|change| change.export()).collect();
+
+                        Ok(Json(json!({
+                            "payout_address_changes": exported,
+                            "limit": limit,
+                            "offset": offset,
+                        })))
+                    })
+                })
+            })
+    })
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConfirmParams {
+    pub token: Uuid,
+}
+
+pub fn confirm(
+    (state, params, remote_ip): (State<AppState>, Json<ConfirmParams>, RemoteIp),
+) -> impl Future<Item = Json<Value>, Error = Error> {
+    let params = params.into_inner();
+
+    let time_lock = Duration::hours(state.config.payout_address_time_lock_hours.unwrap_or(0));
+
+    services::payout_address_changes::confirm(
+        params.token,
+        time_lock,
+        remote_ip.0,
+        state.mailer.clone(),
+        &state.postgres,
+        state.config.web_client_url.clone(),
+        state.config.mail_sender.clone(),
+    )
+    .then(|res| res.and_then(|change| Ok(Json(change.export()))))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CancelParams {
+    pub token: Uuid,
+}
+
+// The link of the emails cancels the change without signing in.
+pub fn cancel_by_token(
+    (state, params, remote_ip): (State<AppState>, Json<CancelParams>, RemoteIp),
+) -> impl Future<Item = Json<Value>, Error = Error> {
+    let params = params.into_inner();
+
+    services::payout_address_changes::cancel_by_token(params.token, remote_ip.0, &state.postgres)
+        .then(|res| res.and_then(|change| Ok(Json(change.export()))))
+}
+
+pub fn cancel(
+    (state, path, user, remote_ip): (State<AppState>, Path<Uuid>, AuthUser, RemoteIp),
+) -> impl Future<Item = Json<Value>, Error = Error> {
+    let id = path.into_inner();
+
+    services::payout_address_changes::get(id, &state.postgres).and_then(move |change| {
+        services::stores::get(change.store_id, &state.postgres).and_then(move |store| {
+            validate_store_owner(&store, &user)
+                .into_future()
+                .and_then(move |_| {
+                    services::payout_address_changes::cancel(
+                        id,
+                        user.id,
+                        remote_ip.0,
+                        &state.postgres,
+                    )
+                    .then(|res| res.and_then(|change| Ok(Json(change.export()))))
+                })
+        })
+    })
+}
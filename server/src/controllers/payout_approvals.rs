@@ -0,0 +1,99 @@
+use actix_web::{Json, Path, State};
+use futures::future::{self, Future, IntoFuture};
+use rustc_hex::FromHex;
+use serde_json::Value;
+use uuid::Uuid;
+
+use auth::{AuthUser, RemoteIp};
+use core::store::Store;
+use services::{self, Error};
+use state::AppState;
+
+fn validate_store_owner(store: &Store, user: &AuthUser) -> Result<bool, Error> {
+    if store.owner_id != user.id {
+        return Err(Error::InvalidRequestAccount);
+    }
+
+    Ok(true)
+}
+
+pub fn list(
+    (state, path, user): (State<AppState>, Path<Uuid>, AuthUser),
+) -> impl Future<Item = Json<Value>, Error = Error> {
+    let store_id = path.into_inner();
+
+    services::stores::get(store_id, &state.postgres).and_then(move |store| {
+        validate_store_owner(&store, &user)
+            .into_future()
+            .and_then(move |_| {
+                services::payout_approvals::find_by_store(store.id, &state.postgres).then(|res| {
+                    res.and_then(|approvals| {
+                        let exported: Vec<Value> = approvals
+                            .into_iter()
+                            .map(|(payout, approval)| {
+                                json!({
+                                    "payout_id": payout.id,
+                                    "payment_id": payout.payment_id,
+                                    "currency": payout.typ,
+                                    "unsigned_transaction": approval.unsigned_transaction,
+                                    "created_at": approval.created_at.timestamp(),
+                                })
+                            })
+                            .collect();
+
+                        Ok(Json(json!({ "payout_approvals": exported })))
+                    })
+                })
+            })
+    })
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ApproveParams {
+    // A hex encoded transaction signed outside of Finch, broadcast instead of signing one.
+    pub signed_transaction: Option<String>,
+}
+
+pub fn approve(
+    (state, path, params, user, remote_ip): (
+        State<AppState>,
+        Path<Uuid>,
+        Json<ApproveParams>,
+        AuthUser,
+        RemoteIp,
+    ),
+) -> Box<Future<Item = Json<Value>, Error = Error>> {
+    let id = path.into_inner();
+    let params = params.into_inner();
+
+    let signed_transaction = params
+        .signed_transaction
+        .map(|transaction| transaction.trim_start_matches("0x").to_string());
+
+    if let Some(ref transaction) = signed_transaction {
+        if transaction.from_hex::<Vec<u8>>().is_err() {
+            return Box::new(future::err(Error::BadRequest(
+                "signed transaction is not valid hex",
+            )));
+        }
+    }
+
+    Box::new(
+        services::payout_approvals::get_payout(id, &state.postgres).and_then(move |payout| {
+            services::stores::get(payout.store_id, &state.postgres).and_then(move |store| {
+                validate_store_owner(&store, &user)
+                    .into_future()
+                    .and_then(move |_| {
+                        services::payout_approvals::approve(
+                            payout.id,
+                            signed_transaction,
+                            user.id,
+                            remote_ip.0,
+                            &state.postgres,
+                        )
+                        .then(|res| res.and_then(|payout| Ok(Json(json!(payout)))))
+                    })
+            })
+        }),
+    )
+}
@@ -1,15 +1,21 @@
 use actix_web::{Json, Path, Query, State};
-use futures::future::{Future, IntoFuture};
-use serde_json::Value;
+use bigdecimal::{BigDecimal, Zero};
+use futures::future::{self, Future, IntoFuture};
+use serde_json::{self, Value};
 use uuid::Uuid;
 
-use auth::AuthUser;
-use core::store::{Store, StorePayload};
+use auth::{AuthUser, RemoteIp};
+use core::{
+    payout_address_change::PayoutAddressChangePayload,
+    store::{Store, StorePayload},
+};
+use hd_keyring::multisig;
 use services::{self, Error};
 use state::AppState;
 use types::{
     bitcoin::{Address as BtcAddress, Network as BtcNetwork},
-    H160,
+    currency::Crypto,
+    SettlementInterval, H160,
 };
 
 const LIMIT: i64 = 15;
@@ -19,6 +25,9 @@ const OFFSET: i64 = 0;
 pub struct CreateParams {
     pub name: String,
     pub description: String,
+    // Imports an existing wallet instead of generating one.
+    pub mnemonic: Option<String>,
+    pub mnemonic_passphrase: Option<String>,
 }
 
 pub fn create(
@@ -33,17 +42,34 @@ pub fn create(
     let mut payload = StorePayload::new();
     payload.name = Some(params.name);
     payload.description = Some(params.description);
+    payload.mnemonic = params.mnemonic.map(Some);
+    payload.mnemonic_passphrase = params.mnemonic_passphrase.map(Some);
     payload.owner_id = Some(user.id);
     payload.eth_confirmations_required = Some(Some(1));
     payload.btc_confirmations_required = Some(Some(1));
+    payload.ltc_confirmations_required = Some(Some(1));
+    payload.bch_confirmations_required = Some(Some(1));
+    payload.doge_confirmations_required = Some(Some(1));
 
     let btc_network = state
         .clone()
         .btc_config
         .map_or(BtcNetwork::Test, |config| config.network);
 
-    services::stores::create(payload, btc_network, &state.postgres)
-        .then(|res| res.and_then(|store| Ok(Json(store.export()))))
+    // With a signer, seeds are only held by it and generated ones are shown once to be handed over.
+    let holds_seed = state.signer_config.is_none();
+
+    services::stores::create(payload, btc_network, holds_seed, &state.postgres).then(|res| {
+        res.and_then(|(store, mnemonic)| {
+            let mut exported = store.export();
+
+            if let Some(mnemonic) = mnemonic {
+                exported["mnemonic"] = json!(mnemonic);
+            }
+
+            Ok(Json(exported))
+        })
+    })
 }
 
 #[derive(Debug, Deserialize)]
@@ -54,6 +80,138 @@ pub struct PatchParams {
     pub eth_confirmations_required: Option<i32>,
     pub btc_payout_addresses: Option<Vec<BtcAddress>>,
     pub btc_confirmations_required: Option<i32>,
+    pub ltc_payout_addresses: Option<Vec<BtcAddress>>,
+    pub ltc_confirmations_required: Option<i32>,
+    pub bch_payout_addresses: Option<Vec<BtcAddress>>,
+    pub bch_confirmations_required: Option<i32>,
+    pub doge_payout_addresses: Option<Vec<BtcAddress>>,
+    pub doge_confirmations_required: Option<i32>,
+    pub btc_payout_multisig: Option<PayoutMultisigParams>,
+    pub btc_invoice_multisig: Option<InvoiceMultisigParams>,
+    pub manual_payout_approval: Option<bool>,
+    // A minimum of 0 removes it.
+    pub btc_payout_minimum: Option<BigDecimal>,
+    pub ltc_payout_minimum: Option<BigDecimal>,
+    pub bch_payout_minimum: Option<BigDecimal>,
+    pub doge_payout_minimum: Option<BigDecimal>,
+    pub eth_payout_minimum: Option<BigDecimal>,
+    pub settlement_interval: Option<SettlementIntervalParams>,
+    pub settlement_weekday: Option<i32>,
+    pub settlement_hour: Option<i32>,
+    pub btc_lightning_enabled: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SettlementIntervalParams {
+    // Payouts are sent as soon as they are confirmed.
+    Immediate,
+    Daily,
+    Weekly,
+}
+
+// A wallet payouts are swept to, from hex encoded public keys.
+#[derive(Debug, Deserialize)]
+pub struct PayoutMultisigParams {
+    pub threshold: i32,
+    pub public_keys: Vec<String>,
+}
+
+// Cosigners of invoice addresses along with Finch, from the xpubs of their receive chains.
+#[derive(Debug, Deserialize)]
+pub struct InvoiceMultisigParams {
+    pub threshold: i32,
+    pub xpubs: Vec<String>,
+}
+
+// Addresses are valid base58 on any supported chain, so they are checked against the configured one.
+fn validate_payout_addresses(
+    state: &AppState,
+    crypto: Crypto,
+    addresses: &Option<Vec<BtcAddress>>,
+) -> Result<bool, Error> {
+    if let (Some(addresses), Some(config)) = (addresses, state.bitcoin_like_config(&crypto)) {
+        let chain = config.chain(crypto);
+
+        if addresses
+            .iter()
+            .any(|address| !address.is_valid_for(&chain))
+        {
+            return Err(Error::BadRequest(
+                "payout address is not valid for the currency",
+            ));
+        }
+    }
+
+    Ok(true)
+}
+
+fn validate_multisig(params: &PatchParams) -> Result<bool, Error> {
+    if let Some(ref wallet) = params.btc_payout_multisig {
+        if params.btc_payout_addresses.is_some() {
+            return Err(Error::BadRequest(
+                "payout addresses and a payout multisig can't both be set",
+            ));
+        }
+
+        let public_keys = wallet
+            .public_keys
+            .iter()
+            .map(|public_key| multisig::parse_public_key(public_key))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| Error::BadRequest("payout multisig public key is not valid"))?;
+
+        if wallet.threshold < 1
+            || multisig::witness_script(wallet.threshold as usize, &public_keys).is_err()
+        {
+            return Err(Error::BadRequest("payout multisig is not valid"));
+        }
+    }
+
+    if let Some(ref wallet) = params.btc_invoice_multisig {
+        // Finch holds one more key than the cosigners.
+        let keys = wallet.xpubs.len() + 1;
+
+        if wallet.threshold < 1
+            || wallet.threshold as usize > keys
+            || keys > multisig::MAX_KEYS
+            || multisig::derive_public_keys(&wallet.xpubs, 1).is_err()
+        {
+            return Err(Error::BadRequest("invoice multisig is not valid"));
+        }
+    }
+
+    Ok(true)
+}
+
+fn validate_settlement(params: &PatchParams) -> Result<bool, Error> {
+    for minimum in &[
+        &params.btc_payout_minimum,
+        &params.ltc_payout_minimum,
+        &params.bch_payout_minimum,
+        &params.doge_payout_minimum,
+        &params.eth_payout_minimum,
+    ] {
+        if let Some(minimum) = minimum {
+            if *minimum < BigDecimal::zero() {
+                return Err(Error::BadRequest("payout minimum can't be negative"));
+            }
+        }
+    }
+
+    if let Some(weekday) = params.settlement_weekday {
+        if weekday < 0 || weekday > 6 {
+            return Err(Error::BadRequest("settlement weekday must be from 0 to 6"));
+        }
+    }
+
+    if let Some(hour) = params.settlement_hour {
+        if hour < 0 || hour > 23 {
+            return Err(Error::BadRequest("settlement hour must be from 0 to 23"));
+        }
+    }
+
+    Ok(true)
 }
 
 fn validate_store_owner(store: &Store, user: &AuthUser) -> Result<bool, Error> {
@@ -65,7 +223,13 @@ fn validate_store_owner(store: &Store, user: &AuthUser) -> Result<bool, Error> {
 }
 
 pub fn patch(
-    (state, path, params, user): (State<AppState>, Path<Uuid>, Json<PatchParams>, AuthUser),
+    (state, path, params, user, remote_ip): (
+        State<AppState>,
+        Path<Uuid>,
+        Json<PatchParams>,
+        AuthUser,
+        RemoteIp,
+    ),
 ) -> Box<Future<Item = Json<Value>, Error = Error>> {
     let id = path.into_inner();
     let mut params = params.into_inner();
@@ -74,6 +238,25 @@ pub fn patch(
         params.name = Some(String::from("My Store"));
     }
 
+    for (crypto, addresses) in &[
+        (Crypto::Btc, &params.btc_payout_addresses),
+        (Crypto::Ltc, &params.ltc_payout_addresses),
+        (Crypto::Bch, &params.bch_payout_addresses),
+        (Crypto::Doge, &params.doge_payout_addresses),
+    ] {
+        if let Err(e) = validate_payout_addresses(&state, *crypto, addresses) {
+            return Box::new(future::err(e));
+        }
+    }
+
+    if let Err(e) = validate_multisig(&params) {
+        return Box::new(future::err(e));
+    }
+
+    if let Err(e) = validate_settlement(&params) {
+        return Box::new(future::err(e));
+    }
+
     Box::new(
         services::stores::get(id, &state.postgres).and_then(move |store| {
             validate_store_owner(&store, &user)
@@ -89,24 +272,139 @@ pub fn patch(
                         payload.description = Some(description);
                     }
 
-                    if let Some(eth_payout_addresses) = params.eth_payout_addresses {
-                        payload.eth_payout_addresses = Some(Some(eth_payout_addresses));
-                    }
-
                     if let Some(eth_confirmations_required) = params.eth_confirmations_required {
                         payload.eth_confirmations_required = Some(Some(eth_confirmations_required));
                     }
 
-                    if let Some(btc_payout_address) = params.btc_payout_addresses {
-                        payload.btc_payout_addresses = Some(Some(btc_payout_address));
-                    }
-
                     if let Some(btc_confirmations_required) = params.btc_confirmations_required {
                         payload.btc_confirmations_required = Some(Some(btc_confirmations_required));
                     }
 
-                    services::stores::patch(id, payload, &state.postgres)
-                        .then(|res| res.and_then(|store| Ok(Json(store.export()))))
+                    if let Some(ltc_confirmations_required) = params.ltc_confirmations_required {
+                        payload.ltc_confirmations_required = Some(Some(ltc_confirmations_required));
+                    }
+
+                    if let Some(bch_confirmations_required) = params.bch_confirmations_required {
+                        payload.bch_confirmations_required = Some(Some(bch_confirmations_required));
+                    }
+
+                    if let Some(doge_confirmations_required) = params.doge_confirmations_required {
+                        payload.doge_confirmations_required =
+                            Some(Some(doge_confirmations_required));
+                    }
+
+                    // Turning manual approval off is confirmed by email along with payout
+                    // address changes, turning it on takes effect right away.
+                    let disables_approval = store.manual_payout_approval
+                        && params.manual_payout_approval == Some(false);
+
+                    if params.manual_payout_approval == Some(true) {
+                        payload.manual_payout_approval = Some(true);
+                    }
+
+                    for (minimum, payload_minimum) in vec![
+                        (params.btc_payout_minimum, &mut payload.btc_payout_minimum),
+                        (params.ltc_payout_minimum, &mut payload.ltc_payout_minimum),
+                        (params.bch_payout_minimum, &mut payload.bch_payout_minimum),
+                        (params.doge_payout_minimum, &mut payload.doge_payout_minimum),
+                        (params.eth_payout_minimum, &mut payload.eth_payout_minimum),
+                    ] {
+                        if let Some(minimum) = minimum {
+                            *payload_minimum = Some(if minimum.is_zero() {
+                                None
+                            } else {
+                                Some(minimum)
+                            });
+                        }
+                    }
+
+                    if let Some(interval) = params.settlement_interval {
+                        payload.settlement_interval = Some(match interval {
+                            SettlementIntervalParams::Immediate => None,
+                            SettlementIntervalParams::Daily => Some(SettlementInterval::Daily),
+                            SettlementIntervalParams::Weekly => Some(SettlementInterval::Weekly),
+                        });
+                    }
+
+                    if let Some(settlement_weekday) = params.settlement_weekday {
+                        payload.settlement_weekday = Some(settlement_weekday);
+                    }
+
+                    if let Some(settlement_hour) = params.settlement_hour {
+                        payload.settlement_hour = Some(settlement_hour);
+                    }
+
+                    if let Some(btc_lightning_enabled) = params.btc_lightning_enabled {
+                        payload.btc_lightning_enabled = Some(btc_lightning_enabled);
+                    }
+
+                    // Payout addresses and multisig keys are only changed after the owner
+                    // confirms it by email.
+                    let has_payout_addresses = params.eth_payout_addresses.is_some()
+                        || params.btc_payout_addresses.is_some()
+                        || params.ltc_payout_addresses.is_some()
+                        || params.bch_payout_addresses.is_some()
+                        || params.doge_payout_addresses.is_some()
+                        || params.btc_payout_multisig.is_some()
+                        || params.btc_invoice_multisig.is_some()
+                        || disables_approval;
+
+                    let mut change_payload = PayoutAddressChangePayload::new();
+                    change_payload.eth_payout_addresses = Some(params.eth_payout_addresses);
+                    change_payload.btc_payout_addresses = Some(params.btc_payout_addresses);
+                    change_payload.ltc_payout_addresses = Some(params.ltc_payout_addresses);
+                    change_payload.bch_payout_addresses = Some(params.bch_payout_addresses);
+                    change_payload.doge_payout_addresses = Some(params.doge_payout_addresses);
+
+                    if let Some(wallet) = params.btc_payout_multisig {
+                        change_payload.btc_payout_multisig_threshold = Some(Some(wallet.threshold));
+                        change_payload.btc_payout_multisig_public_keys =
+                            Some(Some(wallet.public_keys));
+                    }
+
+                    if let Some(wallet) = params.btc_invoice_multisig {
+                        change_payload.btc_invoice_multisig_threshold =
+                            Some(Some(wallet.threshold));
+                        change_payload.btc_invoice_multisig_xpubs = Some(Some(wallet.xpubs));
+                    }
+
+                    if disables_approval {
+                        change_payload.manual_payout_approval = Some(Some(false));
+                    }
+
+                    services::stores::patch(
+                        id,
+                        payload,
+                        user.id,
+                        remote_ip.0.clone(),
+                        &state.postgres,
+                    )
+                    .and_then(
+                        move |store| -> Box<Future<Item = Json<Value>, Error = Error>> {
+                            if !has_payout_addresses {
+                                return Box::new(future::ok(Json(store.export())));
+                            }
+
+                            let mut exported = store.export();
+
+                            Box::new(
+                                services::payout_address_changes::request(
+                                    store,
+                                    change_payload,
+                                    user.id,
+                                    remote_ip.0,
+                                    state.mailer.clone(),
+                                    &state.postgres,
+                                    state.config.web_client_url.clone(),
+                                    state.config.mail_sender.clone(),
+                                )
+                                .map(move |change| {
+                                    exported["pending_payout_address_change"] = change.export();
+                                    Json(exported)
+                                }),
+                            )
+                        },
+                    )
                 })
         }),
     )
@@ -163,7 +461,7 @@ pub fn get(
 }
 
 pub fn delete(
-    (state, path, user): (State<AppState>, Path<Uuid>, AuthUser),
+    (state, path, user, remote_ip): (State<AppState>, Path<Uuid>, AuthUser, RemoteIp),
 ) -> impl Future<Item = Json<Value>, Error = Error> {
     let id = path.into_inner();
 
@@ -171,8 +469,186 @@ pub fn delete(
         validate_store_owner(&store, &user)
             .into_future()
             .and_then(move |_| {
-                services::stores::delete(id, &state.postgres)
+                services::stores::delete(id, user.id, remote_ip.0, &state.postgres)
                     .then(|res| res.and_then(|res| Ok(Json(json!({ "deleted": res })))))
             })
     })
 }
+
+#[derive(Debug, Deserialize)]
+pub struct ExportWalletParams {
+    pub password: String,
+}
+
+pub fn export_wallet(
+    (state, path, params, user, remote_ip): (
+        State<AppState>,
+        Path<Uuid>,
+        Json<ExportWalletParams>,
+        AuthUser,
+        RemoteIp,
+    ),
+) -> impl Future<Item = Json<Value>, Error = Error> {
+    let id = path.into_inner();
+    let password = params.into_inner().password;
+
+    let btc_network = state
+        .clone()
+        .btc_config
+        .map_or(BtcNetwork::Test, |config| config.network);
+
+    services::stores::get(id, &state.postgres).and_then(move |store| {
+        validate_store_owner(&store, &user)
+            .into_future()
+            .and_then(move |_| {
+                services::users::confirm_password(
+                    user.id,
+                    password,
+                    state.rate_limiter.clone(),
+                    &state.postgres,
+                )
+                .and_then(move |_| {
+                    services::stores::export_wallet(
+                        store,
+                        btc_network,
+                        user.id,
+                        remote_ip.0,
+                        &state.postgres,
+                    )
+                })
+                .and_then(|export| serde_json::to_value(export).map_err(|e| Error::from(e)))
+                .map(|exported| Json(exported))
+            })
+    })
+}
+
+pub fn audit_events(
+    (state, path, params, user): (State<AppState>, Path<Uuid>, Query<ListParams>, AuthUser),
+) -> impl Future<Item = Json<Value>, Error = Error> {
+    let id = path.into_inner();
+    let mut limit = LIMIT;
+    let mut offset = OFFSET;
+
+    if let Some(_limit) = params.limit {
+        if _limit < LIMIT {
+            limit = _limit;
+        }
+    };
+
+    if let Some(_offset) = params.offset {
+        offset = _offset;
+    };
+
+    services::stores::get(id, &state.postgres).and_then(move |store| {
+        validate_store_owner(&store, &user)
+            .into_future()
+            .and_then(move |_| {
+                services::audit_events::find_by_store(store.id, limit, offset, &state.postgres)
+                    .then(move |res| {
+                        res.and_then(|audit_events| {
+                            let exported: Vec<Value> = audit_events
+                                .into_iter()
+                                .map(|audit_event| audit_event.export())
+                                .collect();
+
+                            Ok(Json(json!({
+                                "audit_events": exported,
+                                "limit": limit,
+                                "offset": offset,
+                            })))
+                        })
+                    })
+            })
+    })
+}
+
+pub fn ledger_entries(
+    (state, path, params, user): (State<AppState>, Path<Uuid>, Query<ListParams>, AuthUser),
+) -> impl Future<Item = Json<Value>, Error = Error> {
+    let id = path.into_inner();
+    let mut limit = LIMIT;
+    let mut offset = OFFSET;
+
+    if let Some(_limit) = params.limit {
+        if _limit < LIMIT {
+            limit = _limit;
+        }
+    };
+
+    if let Some(_offset) = params.offset {
+        offset = _offset;
+    };
+
+    services::stores::get(id, &state.postgres).and_then(move |store| {
+        validate_store_owner(&store, &user)
+            .into_future()
+            .and_then(move |_| {
+                services::ledger_entries::find_by_store(store.id, limit, offset, &state.postgres)
+                    .then(move |res| {
+                        res.and_then(|entries| {
+                            let exported: Vec<Value> =
+                                entries.into_iter().map(|entry| entry.export()).collect();
+
+                            Ok(Json(json!({
+                                "ledger_entries": exported,
+                                "limit": limit,
+                                "offset": offset,
+                            })))
+                        })
+                    })
+            })
+    })
+}
+
+// What the store earned, paid in fees and has pending per currency, according to its ledger.
+pub fn balances(
+    (state, path, user): (State<AppState>, Path<Uuid>, AuthUser),
+) -> impl Future<Item = Json<Value>, Error = Error> {
+    let id = path.into_inner();
+
+    services::stores::get(id, &state.postgres).and_then(move |store| {
+        validate_store_owner(&store, &user)
+            .into_future()
+            .and_then(move |_| {
+                services::ledger_entries::find_balances(store.id, &state.postgres).then(|res| {
+                    res.and_then(|(balances, discrepancies)| {
+                        Ok(Json(json!({
+                            "balances": balances,
+                            "discrepancies": discrepancies,
+                        })))
+                    })
+                })
+            })
+    })
+}
+
+// Payouts of multisig invoice addresses Finch signed its part of, for cosigners to complete.
+pub fn psbts(
+    (state, path, user): (State<AppState>, Path<Uuid>, AuthUser),
+) -> impl Future<Item = Json<Value>, Error = Error> {
+    let id = path.into_inner();
+
+    services::stores::get(id, &state.postgres).and_then(move |store| {
+        validate_store_owner(&store, &user)
+            .into_future()
+            .and_then(move |_| {
+                services::stores::find_psbts(store.id, &state.postgres).then(|res| {
+                    res.and_then(|psbts| {
+                        let exported: Vec<Value> = psbts
+                            .into_iter()
+                            .map(|(payout, psbt)| {
+                                json!({
+                                    "payout_id": payout.id,
+                                    "payment_id": payout.payment_id,
+                                    "psbt": psbt.psbt,
+                                    "created_at": psbt.created_at.timestamp(),
+                                })
+                            })
+                            .collect();
+
+                        Ok(Json(json!({ "psbts": exported })))
+                    })
+                })
+            })
+    })
+}
@@ -4,6 +4,7 @@ extern crate actix;
 extern crate actix_web;
 extern crate base64;
 extern crate bigdecimal;
+extern crate bytes;
 extern crate chrono;
 extern crate data_encoding;
 extern crate diesel;
@@ -12,11 +13,8 @@ extern crate failure;
 extern crate env_logger;
 extern crate futures;
 extern crate jsonwebtoken as jwt;
-extern crate lettre;
-extern crate lettre_email;
+#[macro_use]
 extern crate log;
-extern crate native_tls;
-extern crate num_cpus;
 extern crate openssl;
 extern crate ring;
 extern crate rustc_hex;
@@ -32,11 +30,13 @@ extern crate config;
 extern crate core;
 extern crate currency_api_client;
 extern crate hd_keyring;
+extern crate lightning_api_client;
+extern crate mailer;
 extern crate types;
 
 mod auth;
 mod controllers;
-mod mailer;
+mod rate_limiter;
 mod services;
 mod state;
 
@@ -48,24 +48,33 @@ use actix_web::{http, middleware, server, App};
 use config::Config;
 use core::db::postgres;
 use currency_api_client::Client as CurrencyApiClient;
-use mailer::Mailer;
-
-pub fn run(postgres: postgres::PgExecutorAddr, config: Config) {
-    let smtp_config = config.smtp.clone();
-    let mailer = SyncArbiter::start(num_cpus::get() * 1, move || {
-        Mailer(mailer::init_mailer(
-            smtp_config.host.clone(),
-            smtp_config.port.clone(),
-            smtp_config.user.clone(),
-            smtp_config.pass.clone(),
-        ))
-    });
+use lightning_api_client::LightningClientAddr;
+use mailer::MailerAddr;
+use rate_limiter::{RateLimit, RateLimiter};
 
+pub fn run(
+    postgres: postgres::PgExecutorAddr,
+    mailer: MailerAddr,
+    lightning_client: Option<LightningClientAddr>,
+    config: Config,
+) {
     let currency_api = config.server.currency_api.clone();
     let currency_api_key = config.server.currency_api_key.clone();
     let currency_api_client =
         Arbiter::start(move |_| CurrencyApiClient::new(&currency_api, &currency_api_key));
 
+    let rate_limiter = RateLimiter::new(
+        config.server.rate_limit.clone(),
+        config.server.trusted_proxies.clone(),
+    );
+
+    let sweeper_postgres = postgres.clone();
+    let export_dir = config.server.export_dir.clone();
+    Arbiter::start(move |_| services::exports::Sweeper::new(sweeper_postgres, export_dir));
+
+    let applier_postgres = postgres.clone();
+    Arbiter::start(move |_| services::payout_address_changes::Applier::new(applier_postgres));
+
     let host = config.server.host.clone();
     let port = config.server.port.clone();
 
@@ -79,10 +88,18 @@ pub fn run(postgres: postgres::PgExecutorAddr, config: Config) {
             jwt_private: fs::read(config.server.private_key_path.clone())
                 .expect("failed to open the private key file"),
             btc_config: config.bitcoin.clone(),
-            eth_config: config.ethereum.clone(),
+            ltc_config: config.litecoin.clone(),
+            bch_config: config.bitcoin_cash.clone(),
+            doge_config: config.dogecoin.clone(),
+            evm_configs: config.evm_networks(),
+            lightning_config: config.lightning.clone(),
+            lightning_client: lightning_client.clone(),
+            signer_config: config.signer.clone(),
             currency_api_client: currency_api_client.clone(),
+            rate_limiter: rate_limiter.clone(),
         })
         .middleware(middleware::Logger::default())
+        .middleware(RateLimit(rate_limiter.clone()))
         .configure(|app| {
             middleware::cors::Cors::for_app(app)
                 .max_age(3600)
@@ -101,6 +118,24 @@ pub fn run(postgres: postgres::PgExecutorAddr, config: Config) {
                     r.method(http::Method::POST)
                         .with_async(controllers::auth::authentication);
                 })
+                .resource("/refresh_token", |r| {
+                    r.method(http::Method::POST)
+                        .with_async(controllers::auth::refresh_token);
+                })
+                .resource("/logout", |r| {
+                    r.method(http::Method::POST)
+                        .with_async(controllers::auth::logout);
+                })
+                .resource("/sessions", |r| {
+                    r.method(http::Method::GET)
+                        .with_async(controllers::auth::sessions);
+                    r.method(http::Method::DELETE)
+                        .with_async(controllers::auth::revoke_all_sessions);
+                })
+                .resource("/sessions/{id}", |r| {
+                    r.method(http::Method::DELETE)
+                        .with_async(controllers::auth::revoke_session);
+                })
                 .resource("/reset_password", |r| {
                     r.method(http::Method::POST)
                         .with_async(controllers::auth::reset_password);
@@ -143,6 +178,72 @@ pub fn run(postgres: postgres::PgExecutorAddr, config: Config) {
                     r.method(http::Method::DELETE)
                         .with_async(controllers::stores::delete);
                 })
+                .resource("/stores/{id}/audit_events", |r| {
+                    r.method(http::Method::GET)
+                        .with_async(controllers::stores::audit_events);
+                })
+                .resource("/stores/{id}/ledger_entries", |r| {
+                    r.method(http::Method::GET)
+                        .with_async(controllers::stores::ledger_entries);
+                })
+                .resource("/stores/{id}/balances", |r| {
+                    r.method(http::Method::GET)
+                        .with_async(controllers::stores::balances);
+                })
+                .resource("/stores/{id}/exports", |r| {
+                    r.method(http::Method::GET)
+                        .with_async(controllers::exports::get);
+                })
+                .resource("/stores/{id}/psbts", |r| {
+                    r.method(http::Method::GET)
+                        .with_async(controllers::stores::psbts);
+                })
+                .resource("/stores/{id}/wallet_export", |r| {
+                    r.method(http::Method::POST)
+                        .with_async(controllers::stores::export_wallet);
+                })
+                .resource("/stores/{id}/notification_preferences", |r| {
+                    r.method(http::Method::GET)
+                        .with_async(controllers::notification_preferences::get);
+                    r.method(http::Method::PATCH)
+                        .with_async(controllers::notification_preferences::patch);
+                })
+                .resource("/stores/{id}/payout_address_changes", |r| {
+                    r.method(http::Method::GET)
+                        .with_async(controllers::payout_address_changes::list);
+                })
+                .resource("/stores/{id}/payout_approvals", |r| {
+                    r.method(http::Method::GET)
+                        .with_async(controllers::payout_approvals::list);
+                })
+                .resource("/payout_address_changes/confirm", |r| {
+                    r.method(http::Method::POST)
+                        .with_async(controllers::payout_address_changes::confirm);
+                })
+                .resource("/payout_address_changes/cancel", |r| {
+                    r.method(http::Method::POST)
+                        .with_async(controllers::payout_address_changes::cancel_by_token);
+                })
+                .resource("/payout_address_changes/{id}/cancel", |r| {
+                    r.method(http::Method::POST)
+                        .with_async(controllers::payout_address_changes::cancel);
+                })
+                .resource("/exports/{id}/download", |r| {
+                    r.method(http::Method::GET)
+                        .with_async(controllers::exports::download);
+                })
+                .resource("/payouts/{id}/approve", |r| {
+                    r.method(http::Method::POST)
+                        .with_async(controllers::payout_approvals::approve);
+                })
+                .resource("/block_rescans", |r| {
+                    r.method(http::Method::POST)
+                        .with_async(controllers::block_rescans::create);
+                })
+                .resource("/block_rescans/{id}", |r| {
+                    r.method(http::Method::GET)
+                        .with_async(controllers::block_rescans::get);
+                })
                 .resource("/payments", |r| {
                     r.method(http::Method::POST)
                         .with_async(controllers::payments::create);
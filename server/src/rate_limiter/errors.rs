@@ -0,0 +1,23 @@
+use actix::MailboxError;
+
+use core::db::Error as DbError;
+
+#[derive(Debug, Fail)]
+pub enum Error {
+    #[fail(display = "{}", _0)]
+    DbError(#[cause] DbError),
+    #[fail(display = "{}", _0)]
+    MailboxError(#[cause] MailboxError),
+}
+
+impl From<DbError> for Error {
+    fn from(e: DbError) -> Error {
+        Error::DbError(e)
+    }
+}
+
+impl From<MailboxError> for Error {
+    fn from(e: MailboxError) -> Error {
+        Error::MailboxError(e)
+    }
+}
@@ -0,0 +1,279 @@
+mod errors;
+pub use self::errors::Error;
+
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    time::{Duration, Instant},
+};
+
+use actix::prelude::*;
+use actix_web::{
+    http::{self, Method},
+    middleware::{Middleware, Started},
+    Body, HttpRequest, HttpResponse, Result as ActixResult,
+};
+use data_encoding::HEXLOWER;
+use futures::future::{self, Future};
+use ring::digest;
+
+use auth;
+use config::{RateLimitBackend, RateLimitConfig};
+use core::db::{
+    redis::{self, Delete, GetCount, Incr, RedisExecutor, RedisExecutorAddr},
+    Error as DbError,
+};
+
+// Fixed-window counters for single-node deployments. Counters live in the process, so
+// they are reset on restart and not shared between instances.
+#[derive(Default)]
+pub struct MemoryStore {
+    counters: HashMap<String, (u64, Instant)>,
+}
+
+impl MemoryStore {
+    fn count(&self, key: &str) -> u64 {
+        match self.counters.get(key) {
+            Some(&(count, expires_at)) if expires_at > Instant::now() => count,
+            _ => 0,
+        }
+    }
+}
+
+impl Actor for MemoryStore {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Context<Self>) {
+        ctx.run_interval(Duration::new(60, 0), |store, _| {
+            let now = Instant::now();
+
+            store
+                .counters
+                .retain(|_, &mut (_, expires_at)| expires_at > now);
+        });
+    }
+}
+
+impl Handler<Incr> for MemoryStore {
+    type Result = Result<u64, DbError>;
+
+    fn handle(&mut self, Incr { key, expires_in }: Incr, _: &mut Self::Context) -> Self::Result {
+        let count = self.count(&key) + 1;
+
+        let expires_at = match self.counters.get(&key) {
+            Some(&(_, expires_at)) if count > 1 => expires_at,
+            _ => Instant::now() + Duration::new(expires_in, 0),
+        };
+
+        self.counters.insert(key, (count, expires_at));
+
+        Ok(count)
+    }
+}
+
+impl Handler<GetCount> for MemoryStore {
+    type Result = Result<u64, DbError>;
+
+    fn handle(&mut self, GetCount(key): GetCount, _: &mut Self::Context) -> Self::Result {
+        Ok(self.count(&key))
+    }
+}
+
+impl Handler<Delete> for MemoryStore {
+    type Result = Result<(), DbError>;
+
+    fn handle(&mut self, Delete(key): Delete, _: &mut Self::Context) -> Self::Result {
+        self.counters.remove(&key);
+
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+enum Backend {
+    Memory(Addr<MemoryStore>),
+    Redis(RedisExecutorAddr),
+}
+
+#[derive(Clone)]
+pub struct RateLimiter {
+    backend: Backend,
+    config: RateLimitConfig,
+    trusted_proxies: Vec<IpAddr>,
+}
+
+impl RateLimiter {
+    // The Redis URL is checked along with the rest of the configuration at startup, see
+    // `ServerConfig::validate`.
+    pub fn new(config: RateLimitConfig, trusted_proxies: Vec<IpAddr>) -> Self {
+        let backend = match (config.backend, config.redis_url.as_ref()) {
+            (RateLimitBackend::Redis, Some(redis_url)) => {
+                let pool = redis::init_pool(redis_url);
+
+                Backend::Redis(SyncArbiter::start(2, move || RedisExecutor(pool.clone())))
+            }
+            _ => Backend::Memory(MemoryStore::default().start()),
+        };
+
+        RateLimiter {
+            backend,
+            config,
+            trusted_proxies,
+        }
+    }
+
+    fn incr(&self, key: String, expires_in: u64) -> Box<Future<Item = u64, Error = Error>> {
+        let msg = Incr { key, expires_in };
+
+        match self.backend {
+            Backend::Memory(ref addr) => Box::new(
+                addr.send(msg)
+                    .from_err()
+                    .and_then(|res| res.map_err(|e| Error::from(e))),
+            ),
+            Backend::Redis(ref addr) => Box::new(
+                addr.send(msg)
+                    .from_err()
+                    .and_then(|res| res.map_err(|e| Error::from(e))),
+            ),
+        }
+    }
+
+    fn get_count(&self, key: String) -> Box<Future<Item = u64, Error = Error>> {
+        match self.backend {
+            Backend::Memory(ref addr) => Box::new(
+                addr.send(GetCount(key))
+                    .from_err()
+                    .and_then(|res| res.map_err(|e| Error::from(e))),
+            ),
+            Backend::Redis(ref addr) => Box::new(
+                addr.send(GetCount(key))
+                    .from_err()
+                    .and_then(|res| res.map_err(|e| Error::from(e))),
+            ),
+        }
+    }
+
+    fn delete(&self, key: String) -> Box<Future<Item = (), Error = Error>> {
+        match self.backend {
+            Backend::Memory(ref addr) => Box::new(
+                addr.send(Delete(key))
+                    .from_err()
+                    .and_then(|res| res.map_err(|e| Error::from(e))),
+            ),
+            Backend::Redis(ref addr) => Box::new(
+                addr.send(Delete(key))
+                    .from_err()
+                    .and_then(|res| res.map_err(|e| Error::from(e))),
+            ),
+        }
+    }
+
+    // Failed logins are counted per account, whichever addresses they come from, so that guessing
+    // a password from many addresses still locks the account. Requests of a single address are
+    // limited by the rule of `/login`.
+    fn failed_logins_key(email: &str) -> String {
+        format!("failed_logins:{}", email.to_lowercase())
+    }
+
+    pub fn is_locked_out(&self, email: &str) -> impl Future<Item = bool, Error = Error> {
+        let max_failed_logins = self.config.max_failed_logins;
+
+        self.get_count(RateLimiter::failed_logins_key(email))
+            .map(move |count| count >= max_failed_logins)
+    }
+
+    pub fn record_failed_login(&self, email: &str) -> impl Future<Item = (), Error = Error> {
+        self.incr(
+            RateLimiter::failed_logins_key(email),
+            self.config.lockout_secs,
+        )
+        .map(|_| ())
+    }
+
+    pub fn clear_failed_logins(&self, email: &str) -> impl Future<Item = (), Error = Error> {
+        self.delete(RateLimiter::failed_logins_key(email))
+    }
+}
+
+// Limits requests to the configured paths per client IP and, when an authorization header is
+// sent, per token. The token is hashed so it never ends up in the backing store.
+pub struct RateLimit(pub RateLimiter);
+
+impl<S> Middleware<S> for RateLimit {
+    fn start(&self, req: &HttpRequest<S>) -> ActixResult<Started> {
+        if *req.method() == Method::OPTIONS {
+            return Ok(Started::Done);
+        }
+
+        let rule = match self
+            .0
+            .config
+            .rules
+            .iter()
+            .find(|rule| rule.path == req.path())
+        {
+            Some(rule) => rule.clone(),
+            None => return Ok(Started::Done),
+        };
+
+        let mut checks = Vec::new();
+
+        if let (Some(per_ip), Some(ip)) =
+            (rule.per_ip, auth::client_ip(req, &self.0.trusted_proxies))
+        {
+            checks.push((format!("rate_limit:{}:ip:{}", rule.path, ip), per_ip));
+        }
+
+        let token = req
+            .headers()
+            .get("authorization")
+            .and_then(|token| token.to_str().ok());
+
+        if let (Some(per_token), Some(token)) = (rule.per_token, token) {
+            let token_hash =
+                HEXLOWER.encode(digest::digest(&digest::SHA256, token.as_bytes()).as_ref());
+
+            checks.push((
+                format!("rate_limit:{}:token:{}", rule.path, token_hash),
+                per_token,
+            ));
+        }
+
+        if checks.is_empty() {
+            return Ok(Started::Done);
+        }
+
+        let window_secs = rule.window_secs;
+        let rate_limiter = self.0.clone();
+
+        let hits = checks.into_iter().map(move |(key, limit)| {
+            rate_limiter
+                .incr(key, window_secs)
+                .map(move |count| count > limit)
+        });
+
+        Ok(Started::Future(Box::new(future::join_all(hits).then(
+            move |res| match res {
+                Ok(exceeded) => {
+                    if !exceeded.into_iter().any(|exceeded| exceeded) {
+                        return Ok(None);
+                    }
+
+                    let message = format!("{}", json!({ "message": "too many requests" }));
+
+                    Ok(Some(
+                        HttpResponse::build(http::StatusCode::TOO_MANY_REQUESTS)
+                            .header("Retry-After", window_secs.to_string())
+                            .body(Body::from(message)),
+                    ))
+                }
+                // Requests are let through when the backing store is unavailable.
+                Err(e) => {
+                    error!("{:?}", e);
+                    Ok(None)
+                }
+            },
+        ))))
+    }
+}
@@ -0,0 +1,41 @@
+use futures::future::Future;
+use uuid::Uuid;
+
+use core::{
+    audit_event::{AuditEvent, AuditEventPayload},
+    db::{
+        postgres::{PgExecutorAddr, PooledConnection},
+        Error as DbError,
+    },
+};
+use services::Error;
+
+pub fn record(
+    payload: AuditEventPayload,
+    postgres: &PgExecutorAddr,
+) -> impl Future<Item = AuditEvent, Error = Error> {
+    AuditEvent::insert(payload, postgres).from_err()
+}
+
+// For events about a mutation, which are recorded in its transaction.
+pub fn audited<F, E, T>(
+    mutate: F,
+    event: E,
+    postgres: &PgExecutorAddr,
+) -> impl Future<Item = T, Error = Error>
+where
+    F: FnOnce(&PooledConnection) -> Result<T, DbError> + Send + 'static,
+    E: FnOnce(&T) -> AuditEventPayload + Send + 'static,
+    T: Send + 'static,
+{
+    AuditEvent::audited(mutate, event, postgres).from_err()
+}
+
+pub fn find_by_store(
+    store_id: Uuid,
+    limit: i64,
+    offset: i64,
+    postgres: &PgExecutorAddr,
+) -> impl Future<Item = Vec<AuditEvent>, Error = Error> {
+    AuditEvent::find_by_store(store_id, limit, offset, postgres).from_err()
+}
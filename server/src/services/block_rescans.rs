@@ -0,0 +1,19 @@
+use futures::future::Future;
+use uuid::Uuid;
+
+use core::{
+    block_rescan::{BlockRescan, BlockRescanPayload},
+    db::postgres::PgExecutorAddr,
+};
+use services::Error;
+
+pub fn create(
+    payload: BlockRescanPayload,
+    postgres: &PgExecutorAddr,
+) -> impl Future<Item = BlockRescan, Error = Error> {
+    BlockRescan::insert(payload, postgres).from_err()
+}
+
+pub fn get(id: Uuid, postgres: &PgExecutorAddr) -> impl Future<Item = BlockRescan, Error = Error> {
+    BlockRescan::find_by_id(id, postgres).from_err()
+}
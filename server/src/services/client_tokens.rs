@@ -1,17 +1,45 @@
 use futures::future::Future;
 use uuid::Uuid;
 
+use serde_json::Value;
+
 use core::{
+    audit_event::AuditEventPayload,
     client_token::{ClientToken, ClientTokenPayload},
-    db::postgres::PgExecutorAddr,
+    db::{client_tokens, postgres::PgExecutorAddr},
 };
-use services::Error;
+use services::{self, Error};
+use types::AuditAction;
+
+// The token itself is a credential, so it never ends up in the audit log.
+fn audit_values(client_token: &ClientToken) -> Value {
+    json!({
+        "id": client_token.id,
+        "name": client_token.name,
+        "domain": client_token.domain,
+        "typ": client_token.typ,
+    })
+}
 
 pub fn create(
-    payload: ClientTokenPayload,
+    mut payload: ClientTokenPayload,
+    actor_id: Uuid,
+    ip_address: Option<String>,
     postgres: &PgExecutorAddr,
 ) -> impl Future<Item = ClientToken, Error = Error> {
-    ClientToken::insert(payload, postgres).from_err()
+    payload.set_created_at();
+
+    services::audit_events::audited(
+        move |conn| client_tokens::insert(payload, conn),
+        move |client_token| {
+            let mut event =
+                AuditEventPayload::new(AuditAction::ClientTokenCreated, Some(actor_id), ip_address);
+            event.store_id = Some(client_token.store_id);
+            event.new_values = Some(audit_values(client_token));
+            event
+        },
+        postgres,
+    )
 }
 
 pub fn get_by_token_and_domain(
@@ -35,6 +63,30 @@ pub fn get(id: Uuid, postgres: &PgExecutorAddr) -> impl Future<Item = ClientToke
     ClientToken::find_by_id(id, postgres).from_err()
 }
 
-pub fn delete(id: Uuid, postgres: &PgExecutorAddr) -> impl Future<Item = usize, Error = Error> {
-    ClientToken::delete(id, postgres).from_err()
+pub fn delete(
+    id: Uuid,
+    actor_id: Uuid,
+    ip_address: Option<String>,
+    postgres: &PgExecutorAddr,
+) -> impl Future<Item = usize, Error = Error> {
+    let postgres = postgres.clone();
+
+    ClientToken::find_by_id(id, &postgres)
+        .from_err()
+        .and_then(move |client_token| {
+            services::audit_events::audited(
+                move |conn| client_tokens::delete(id, conn),
+                move |_| {
+                    let mut event = AuditEventPayload::new(
+                        AuditAction::ClientTokenDeleted,
+                        Some(actor_id),
+                        ip_address,
+                    );
+                    event.store_id = Some(client_token.store_id);
+                    event.old_values = Some(audit_values(&client_token));
+                    event
+                },
+                &postgres,
+            )
+        })
 }
@@ -1,3 +1,5 @@
+use std::io::Error as IoError;
+
 use actix::MailboxError;
 use actix_web::{client::SendRequestError, error, http, Body, HttpResponse};
 use bigdecimal::BigDecimal;
@@ -12,8 +14,9 @@ use serde_json::Error as SerdeError;
 use core::{db::Error as DbError, ModelError};
 use currency_api_client::Error as CurrencyApiClientError;
 use hd_keyring::Error as KeyringError;
+use lightning_api_client::Error as LightningApiClientError;
 use mailer::Error as MailerError;
-use types::currency::Crypto;
+use rate_limiter::Error as RateLimiterError;
 
 #[derive(Debug, Fail)]
 pub enum Error {
@@ -24,6 +27,8 @@ pub enum Error {
     #[fail(display = "{}", _0)]
     KeyringError(#[cause] KeyringError),
     #[fail(display = "{}", _0)]
+    LightningApiClientError(#[cause] LightningApiClientError),
+    #[fail(display = "{}", _0)]
     DecodeError(#[cause] DecodeError),
     #[fail(display = "{}", _0)]
     JwtError(#[cause] JwtError),
@@ -52,6 +57,12 @@ pub enum Error {
     #[fail(display = "{}", _0)]
     MailerError(#[cause] MailerError),
     #[fail(display = "{}", _0)]
+    RateLimiterError(#[cause] RateLimiterError),
+    #[fail(display = "{}", _0)]
+    IoError(#[cause] IoError),
+    #[fail(display = "too many failed login attempts, please try again later")]
+    AccountLocked,
+    #[fail(display = "{}", _0)]
     BadRequest(&'static str),
     #[fail(display = "internal server error")]
     InternalServerError,
@@ -59,7 +70,7 @@ pub enum Error {
         display = "charge amount is too low. It has to be at least {} {}",
         min, unit
     )]
-    ChargeAmountTooLow { min: BigDecimal, unit: Crypto },
+    ChargeAmountTooLow { min: BigDecimal, unit: String },
 }
 
 impl error::ResponseError for Error {
@@ -77,6 +88,9 @@ impl error::ResponseError for Error {
                 HttpResponse::build(http::StatusCode::FORBIDDEN).body(Body::from(user_err_message))
             }
 
+            Error::AccountLocked => HttpResponse::build(http::StatusCode::TOO_MANY_REQUESTS)
+                .body(Body::from(user_err_message)),
+
             Error::PaymentNotConfirmed => {
                 HttpResponse::build(http::StatusCode::NOT_FOUND).body(Body::from(user_err_message))
             }
@@ -127,6 +141,12 @@ impl From<KeyringError> for Error {
     }
 }
 
+impl From<LightningApiClientError> for Error {
+    fn from(e: LightningApiClientError) -> Error {
+        Error::LightningApiClientError(e)
+    }
+}
+
 impl From<DecodeError> for Error {
     fn from(e: DecodeError) -> Error {
         Error::DecodeError(e)
@@ -186,3 +206,15 @@ impl From<MailerError> for Error {
         Error::MailerError(e)
     }
 }
+
+impl From<RateLimiterError> for Error {
+    fn from(e: RateLimiterError) -> Error {
+        Error::RateLimiterError(e)
+    }
+}
+
+impl From<IoError> for Error {
+    fn from(e: IoError) -> Error {
+        Error::IoError(e)
+    }
+}
@@ -0,0 +1,631 @@
+use std::{
+    fs::{self, File},
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use actix::prelude::*;
+use bigdecimal::{BigDecimal, Zero};
+use chrono::{prelude::*, Duration as ChronoDuration};
+use futures::{
+    future::{Future, IntoFuture},
+    stream::{self, Stream},
+};
+use serde_json::{Map, Value};
+use uuid::Uuid;
+
+use core::{
+    audit_event::AuditEventPayload,
+    db::{exports, postgres::PgExecutorAddr},
+    export::{Export, ExportPayload},
+    ledger_entry::LedgerEntry,
+    payment::Payment,
+    payout::Payout,
+    store::Store,
+    user::User,
+};
+use mailer::{MailerAddr, SendMail, Template};
+use services::{self, Error};
+use types::{AuditAction, ExportFormat, ExportKind, LedgerAccount};
+
+// Rows fetched at a time, so that large ranges are streamed rather than loaded at once.
+const PAGE_SIZE: i64 = 500;
+
+const DOWNLOAD_CHUNK_SIZE: usize = 65536;
+
+// Exports still generating after this long are taken to be abandoned.
+const GENERATION_TIMEOUT_MINS: i64 = 60;
+
+const SWEEP_INTERVAL_SECS: u64 = 600;
+
+const PAYMENT_COLUMNS: &[&str] = &[
+    "id",
+    "identifier",
+    "status",
+    "currency",
+    "address",
+    "fiat",
+    "price",
+    "charge",
+    "amount_paid",
+    "transaction_hashes",
+    "created_at",
+    "paid_at",
+    "expires_at",
+];
+
+const PAYOUT_COLUMNS: &[&str] = &[
+    "id",
+    "payment_id",
+    "status",
+    "action",
+    "currency",
+    "payment_transaction_hash",
+    "output_index",
+    "transaction_hash",
+    "amount",
+    "network_fee",
+    "destination",
+    "created_at",
+    "sent_at",
+];
+
+type Record = Vec<Option<String>>;
+
+// Creation time and id of the last record of a page, which the next page follows.
+type Cursor = (DateTime<Utc>, Uuid);
+
+fn columns(kind: ExportKind) -> &'static [&'static str] {
+    match kind {
+        ExportKind::Payments => PAYMENT_COLUMNS,
+        ExportKind::Payouts => PAYOUT_COLUMNS,
+    }
+}
+
+// A payment credited by several outputs lists the transaction of each, separated by spaces.
+fn payment_record(payment: &Payment, credits: &[Payout]) -> Record {
+    let mut hashes: Vec<String> = Vec::new();
+
+    for payout in credits
+        .iter()
+        .filter(|payout| payout.payment_id == payment.id)
+    {
+        if let Some(hash) = payout.payment_transaction_hash {
+            let hash = hash.to_string();
+
+            if !hashes.contains(&hash) {
+                hashes.push(hash);
+            }
+        }
+    }
+
+    // Payments credited before outputs were recorded only know their latest transaction.
+    if hashes.is_empty() {
+        hashes.extend(payment.transaction_hash.map(|hash| hash.to_string()));
+    }
+
+    vec![
+        Some(payment.id.to_string()),
+        payment.identifier.clone(),
+        Some(payment.status.to_string()),
+        Some(payment.crypto.to_string()),
+        Some(payment.address.clone()),
+        Some(payment.fiat.to_string()),
+        Some(payment.price.to_string()),
+        Some(payment.charge.to_string()),
+        payment
+            .amount_paid
+            .as_ref()
+            .map(|amount_paid| amount_paid.to_string()),
+        if hashes.is_empty() {
+            None
+        } else {
+            Some(hashes.join(" "))
+        },
+        Some(payment.created_at.to_rfc3339()),
+        payment.paid_at.map(|paid_at| paid_at.to_rfc3339()),
+        Some(payment.expires_at.to_rfc3339()),
+    ]
+}
+
+// The amount sent, fee and destination of a payout come from the postings of its settlement.
+fn payout_record(payout: &Payout, settlements: &[LedgerEntry]) -> Record {
+    let posting = |accounts: &[LedgerAccount]| {
+        settlements
+            .iter()
+            .find(|entry| entry.payout_id == payout.id && accounts.contains(&entry.account))
+    };
+    let sent = posting(&[LedgerAccount::Settled, LedgerAccount::Refunded]);
+    // Ethereum fees are adjusted to what the transaction spent once it is mined.
+    let fee = sent.map(|_| {
+        settlements
+            .iter()
+            .filter(|entry| {
+                entry.payout_id == payout.id && entry.account == LedgerAccount::NetworkFees
+            })
+            .fold(BigDecimal::zero(), |fee, entry| fee + entry.amount.clone())
+    });
+
+    vec![
+        Some(payout.id.to_string()),
+        Some(payout.payment_id.to_string()),
+        Some(payout.status.to_string()),
+        Some(payout.action.to_string()),
+        Some(payout.typ.to_string()),
+        payout.payment_transaction_hash.map(|hash| hash.to_string()),
+        payout.output_index.map(|index| index.to_string()),
+        payout.transaction_hash.map(|hash| hash.to_string()),
+        sent.map(|entry| entry.amount.to_string()),
+        fee.map(|fee| fee.to_string()),
+        sent.and_then(|entry| entry.destination.clone()),
+        Some(payout.created_at.to_rfc3339()),
+        sent.map(|entry| entry.created_at.to_rfc3339()),
+    ]
+}
+
+fn records(
+    store_id: Uuid,
+    kind: ExportKind,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    after: Option<Cursor>,
+    postgres: &PgExecutorAddr,
+) -> Box<Future<Item = (Vec<Record>, Option<Cursor>), Error = Error>> {
+    let postgres = postgres.clone();
+
+    match kind {
+        ExportKind::Payments => Box::new(
+            Payment::find_by_store_in_range(store_id, from, to, PAGE_SIZE, after, &postgres)
+                .from_err()
+                .and_then(move |payments| {
+                    let payment_ids = payments.iter().map(|payment| payment.id).collect();
+
+                    Payout::find_all_by_payments(payment_ids, &postgres)
+                        .from_err()
+                        .map(move |credits| {
+                            let last = payments
+                                .last()
+                                .map(|payment| (payment.created_at, payment.id));
+                            let records: Vec<Record> = payments
+                                .iter()
+                                .map(|payment| payment_record(payment, &credits))
+                                .collect();
+
+                            (records, last)
+                        })
+                }),
+        ),
+        ExportKind::Payouts => Box::new(
+            Payout::find_by_store_in_range(store_id, from, to, PAGE_SIZE, after, &postgres)
+                .from_err()
+                .and_then(move |payouts| {
+                    let payout_ids = payouts.iter().map(|payout| payout.id).collect();
+
+                    LedgerEntry::find_settlements(payout_ids, &postgres)
+                        .from_err()
+                        .map(move |settlements| {
+                            let last = payouts.last().map(|payout| (payout.created_at, payout.id));
+                            let records: Vec<Record> = payouts
+                                .iter()
+                                .map(|payout| payout_record(payout, &settlements))
+                                .collect();
+
+                            (records, last)
+                        })
+                }),
+        ),
+    }
+}
+
+// Quotes fields as RFC 4180 asks, and keeps spreadsheets from reading text such as identifiers
+// as formulas.
+fn csv_field(value: &str) -> String {
+    let value = match value.chars().next() {
+        Some('=') | Some('+') | Some('-') | Some('@') => format!("'{}", value),
+        _ => String::from(value),
+    };
+
+    if value.contains(|c| c == ',' || c == '"' || c == '\n' || c == '\r') {
+        format!("\"{}\"", value.replace("\"", "\"\""))
+    } else {
+        value
+    }
+}
+
+fn header(format: ExportFormat, columns: &[&str]) -> String {
+    match format {
+        ExportFormat::Csv => format!("{}\r\n", columns.join(",")),
+        ExportFormat::Json => String::from("["),
+    }
+}
+
+fn footer(format: ExportFormat) -> String {
+    match format {
+        ExportFormat::Csv => String::new(),
+        ExportFormat::Json => String::from("]\n"),
+    }
+}
+
+// Renders a page of records, `continued` telling whether records were rendered before it.
+fn render(format: ExportFormat, columns: &[&str], records: &[Record], continued: bool) -> String {
+    match format {
+        ExportFormat::Csv => records
+            .iter()
+            .map(|record| {
+                let fields: Vec<String> = record
+                    .iter()
+                    .map(|field| {
+                        field
+                            .as_ref()
+                            .map_or(String::new(), |field| csv_field(field))
+                    })
+                    .collect();
+
+                format!("{}\r\n", fields.join(","))
+            })
+            .collect(),
+        ExportFormat::Json => {
+            let objects: Vec<String> = records
+                .iter()
+                .map(|record| {
+                    let mut object = Map::new();
+
+                    for (column, field) in columns.iter().zip(record.iter()) {
+                        let value = field.clone().map_or(Value::Null, Value::String);
+                        object.insert(column.to_string(), value);
+                    }
+
+                    Value::Object(object).to_string()
+                })
+                .collect();
+
+            match (continued, objects.is_empty()) {
+                (true, false) => format!(",\n{}", objects.join(",\n")),
+                (false, false) => format!("\n{}", objects.join(",\n")),
+                (_, true) => String::new(),
+            }
+        }
+    }
+}
+
+// The export, oldest first, as chunks of at most a page of records.
+fn rows(
+    store_id: Uuid,
+    kind: ExportKind,
+    format: ExportFormat,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    postgres: &PgExecutorAddr,
+) -> Box<Stream<Item = String, Error = Error>> {
+    let postgres = postgres.clone();
+    let columns = columns(kind);
+
+    // Pages follow the last record of the previous one rather than skipping an offset, so that
+    // later pages cost as much as the first and records created meanwhile don't shift them.
+    let pages = stream::unfold(Some(None), move |after: Option<Option<Cursor>>| {
+        let postgres = postgres.clone();
+
+        after.map(move |after| {
+            records(store_id, kind, from, to, after, &postgres).map(move |(records, last)| {
+                let next = if records.len() < PAGE_SIZE as usize {
+                    None
+                } else {
+                    Some(last)
+                };
+
+                (render(format, columns, &records, after.is_some()), next)
+            })
+        })
+    });
+
+    Box::new(
+        stream::once(Ok(header(format, columns)))
+            .chain(pages)
+            .chain(stream::once(Ok(footer(format)))),
+    )
+}
+
+fn export_event(
+    store_id: Uuid,
+    kind: ExportKind,
+    format: ExportFormat,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    actor_id: Uuid,
+    ip_address: Option<String>,
+) -> AuditEventPayload {
+    let mut event = AuditEventPayload::new(AuditAction::DataExported, Some(actor_id), ip_address);
+    event.store_id = Some(store_id);
+    event.new_values = Some(json!({
+        "kind": kind,
+        "format": format,
+        "from": from.map(|from| from.timestamp()),
+        "to": to.map(|to| to.timestamp()),
+    }));
+    event
+}
+
+pub fn stream(
+    store_id: Uuid,
+    kind: ExportKind,
+    format: ExportFormat,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    actor_id: Uuid,
+    ip_address: Option<String>,
+    postgres: &PgExecutorAddr,
+) -> impl Future<Item = Box<Stream<Item = String, Error = Error>>, Error = Error> {
+    let postgres = postgres.clone();
+
+    let event = export_event(store_id, kind, format, from, to, actor_id, ip_address);
+
+    services::audit_events::record(event, &postgres)
+        .map(move |_| rows(store_id, kind, format, from, to, &postgres))
+}
+
+// A completed export's file.
+fn path(export_dir: &str, id: Uuid) -> PathBuf {
+    Path::new(export_dir).join(id.to_string())
+}
+
+// Exports are written under this name until complete, so that a file named after an export is
+// always whole.
+fn partial_path(export_dir: &str, id: Uuid) -> PathBuf {
+    path(export_dir, id).with_extension("part")
+}
+
+fn write(
+    export: &Export,
+    export_dir: String,
+    postgres: &PgExecutorAddr,
+) -> impl Future<Item = (), Error = Error> {
+    let id = export.id;
+    let rows = rows(
+        export.store_id,
+        export.kind,
+        export.format,
+        export.from_at,
+        export.to_at,
+        postgres,
+    );
+
+    fs::create_dir_all(&export_dir)
+        .and_then(|_| File::create(partial_path(&export_dir, id)))
+        .map_err(|e| Error::from(e))
+        .into_future()
+        .and_then(move |file| {
+            rows.fold(file, |mut file, chunk| {
+                file.write_all(chunk.as_bytes())
+                    .map(|_| file)
+                    .map_err(|e| Error::from(e))
+            })
+        })
+        .and_then(move |file| {
+            file.sync_all()
+                .and_then(|_| fs::rename(partial_path(&export_dir, id), path(&export_dir, id)))
+                .map_err(|e| Error::from(e))
+        })
+}
+
+fn generate(
+    store: Store,
+    export: Export,
+    mailer: MailerAddr,
+    postgres: &PgExecutorAddr,
+    export_dir: String,
+    web_client_url: String,
+    mail_sender: String,
+) -> impl Future<Item = (), Error = Error> {
+    let postgres = postgres.clone();
+    let _postgres = postgres.clone();
+    let id = export.id;
+    let requester = User::find_by_id(export.requested_by, &postgres).from_err();
+
+    write(&export, export_dir.clone(), &postgres)
+        .and_then(move |_| Export::complete(id, &postgres).from_err())
+        // Failed exports are marked as such rather than left to be waited on.
+        .or_else(move |e| {
+            let _ = fs::remove_file(partial_path(&export_dir, id));
+
+            Export::fail(id, &_postgres).then(move |_| Err::<Export, Error>(e))
+        })
+        .join(requester)
+        .and_then(move |(export, requester)| {
+            let template = Template::ExportReady {
+                store_name: store.name,
+                url: format!(
+                    "{}/download_export?id={}&token={}",
+                    web_client_url, export.id, export.download_token
+                ),
+            };
+
+            mailer
+                .send(SendMail::from_template(
+                    &template,
+                    requester.locale,
+                    mail_sender,
+                    requester.email,
+                ))
+                .from_err()
+                .and_then(move |res| res.map_err(|e| Error::from(e)))
+        })
+}
+
+// Records the export and generates it in the background, its download link being emailed to the
+// requester once done.
+pub fn request(
+    store: Store,
+    kind: ExportKind,
+    format: ExportFormat,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    actor_id: Uuid,
+    ip_address: Option<String>,
+    mailer: MailerAddr,
+    postgres: &PgExecutorAddr,
+    export_dir: String,
+    web_client_url: String,
+    mail_sender: String,
+) -> impl Future<Item = Export, Error = Error> {
+    let postgres = postgres.clone();
+
+    let mut payload = ExportPayload::new();
+    payload.store_id = Some(store.id);
+    payload.requested_by = Some(actor_id);
+    payload.kind = Some(kind);
+    payload.format = Some(format);
+    payload.from_at = Some(from);
+    payload.to_at = Some(to);
+    payload.set_created_at();
+    payload.set_expires_at();
+
+    let event = export_event(store.id, kind, format, from, to, actor_id, ip_address);
+
+    services::audit_events::audited(
+        move |conn| exports::insert(payload, conn),
+        move |_| event,
+        &postgres,
+    )
+    .map(move |export| {
+        let id = export.id;
+        let generation = generate(
+            store,
+            export.clone(),
+            mailer,
+            &postgres,
+            export_dir,
+            web_client_url,
+            mail_sender,
+        )
+        .map_err(move |e| error!("failed to generate export {}: {:?}", id, e));
+
+        Arbiter::spawn(generation);
+
+        export
+    })
+}
+
+// Reads a file a chunk at a time.
+fn chunks(file: File) -> Box<Stream<Item = Vec<u8>, Error = Error>> {
+    Box::new(stream::unfold(file, |mut file| {
+        let mut chunk = vec![0; DOWNLOAD_CHUNK_SIZE];
+
+        match file.read(&mut chunk) {
+            Ok(0) => None,
+            Ok(read) => {
+                chunk.truncate(read);
+                Some(Ok((chunk, file)))
+            }
+            Err(e) => Some(Err(Error::from(e))),
+        }
+    }))
+}
+
+pub fn download(
+    id: Uuid,
+    download_token: Uuid,
+    export_dir: String,
+    postgres: &PgExecutorAddr,
+) -> impl Future<Item = (Export, Box<Stream<Item = Vec<u8>, Error = Error>>), Error = Error> {
+    Export::find_by_id(id, postgres)
+        .from_err()
+        .and_then(move |export| {
+            if export.download_token != download_token || export.is_expired() {
+                return Err(Error::BadRequest("invalid or expired download link"));
+            }
+
+            if export.failed_at.is_some() {
+                return Err(Error::BadRequest("export failed, please request it again"));
+            }
+
+            if export.completed_at.is_none() {
+                return Err(Error::BadRequest("export is not ready yet"));
+            }
+
+            let file = File::open(path(&export_dir, export.id))?;
+
+            Ok((export, chunks(file)))
+        })
+}
+
+// Marks exports whose generation was abandoned, by a server which stopped meanwhile, as failed,
+// and deletes expired exports along with their files.
+pub struct Sweeper {
+    postgres: PgExecutorAddr,
+    export_dir: String,
+}
+
+impl Sweeper {
+    pub fn new(postgres: PgExecutorAddr, export_dir: String) -> Self {
+        Sweeper {
+            postgres,
+            export_dir,
+        }
+    }
+}
+
+impl Actor for Sweeper {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        ctx.run_interval(Duration::from_secs(SWEEP_INTERVAL_SECS), |sweeper, _| {
+            let started_before = Utc::now() - ChronoDuration::minutes(GENERATION_TIMEOUT_MINS);
+            let export_dir = sweeper.export_dir.clone();
+            let _export_dir = sweeper.export_dir.clone();
+
+            let stale = Export::fail_stale(started_before, &sweeper.postgres).map(move |exports| {
+                for export in exports {
+                    warn!("export {} was abandoned", export.id);
+                    let _ = fs::remove_file(partial_path(&export_dir, export.id));
+                }
+            });
+            let expired = Export::delete_expired(&sweeper.postgres).map(move |exports| {
+                for export in exports {
+                    let _ = fs::remove_file(path(&_export_dir, export.id));
+                }
+            });
+
+            Arbiter::spawn(
+                stale
+                    .join(expired)
+                    .map(|_| ())
+                    .map_err(|e| error!("failed to sweep exports: {:?}", e)),
+            );
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_fields_are_quoted_and_kept_from_formulas() {
+        assert_eq!(csv_field("order 42"), "order 42");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("=1+1"), "'=1+1");
+        assert_eq!(csv_field("@x,y"), "\"'@x,y\"");
+    }
+
+    #[test]
+    fn json_pages_form_an_array() {
+        let columns = &["id", "identifier"];
+        let first = vec![vec![Some(String::from("1")), None]];
+        let second = vec![vec![Some(String::from("2")), Some(String::from("b"))]];
+
+        let json = vec![
+            header(ExportFormat::Json, columns),
+            render(ExportFormat::Json, columns, &first, false),
+            render(ExportFormat::Json, columns, &second, true),
+            render(ExportFormat::Json, columns, &[], true),
+            footer(ExportFormat::Json),
+        ]
+        .concat();
+
+        let value: Value = ::serde_json::from_str(&json).unwrap();
+        assert_eq!(value[0]["identifier"], Value::Null);
+        assert_eq!(value[1]["identifier"], "b");
+    }
+}
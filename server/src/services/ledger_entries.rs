@@ -0,0 +1,27 @@
+use futures::future::Future;
+use uuid::Uuid;
+
+use core::{
+    db::postgres::PgExecutorAddr,
+    ledger_entry::{Balance, Discrepancy, LedgerEntry},
+};
+use services::Error;
+
+pub fn find_by_store(
+    store_id: Uuid,
+    limit: i64,
+    offset: i64,
+    postgres: &PgExecutorAddr,
+) -> impl Future<Item = Vec<LedgerEntry>, Error = Error> {
+    LedgerEntry::find_by_store(store_id, limit, offset, postgres).from_err()
+}
+
+// The balances come with what doesn't agree with payments and payouts, if anything.
+pub fn find_balances(
+    store_id: Uuid,
+    postgres: &PgExecutorAddr,
+) -> impl Future<Item = (Vec<Balance>, Vec<Discrepancy>), Error = Error> {
+    LedgerEntry::find_balances_by_store(store_id, postgres)
+        .join(LedgerEntry::check(store_id, postgres))
+        .from_err()
+}
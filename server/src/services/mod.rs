@@ -1,8 +1,16 @@
 mod errors;
 
 pub use self::errors::Error;
+pub mod audit_events;
+pub mod block_rescans;
 pub mod client_tokens;
+pub mod exports;
+pub mod ledger_entries;
+pub mod notification_preferences;
 pub mod payments;
+pub mod payout_address_changes;
+pub mod payout_approvals;
+pub mod sessions;
 pub mod stores;
 pub mod users;
 pub mod vouchers;
@@ -0,0 +1,50 @@
+use futures::future::Future;
+use uuid::Uuid;
+
+use core::{
+    audit_event::AuditEventPayload,
+    db::{notification_preferences, postgres::PgExecutorAddr},
+    notification_preference::{NotificationPreference, NotificationPreferencePayload},
+};
+use services::{self, Error};
+use types::AuditAction;
+
+pub fn get(
+    store_id: Uuid,
+    postgres: &PgExecutorAddr,
+) -> impl Future<Item = NotificationPreference, Error = Error> {
+    NotificationPreference::find_by_store(store_id, postgres).from_err()
+}
+
+pub fn update(
+    store_id: Uuid,
+    mut payload: NotificationPreferencePayload,
+    actor_id: Uuid,
+    ip_address: Option<String>,
+    postgres: &PgExecutorAddr,
+) -> impl Future<Item = NotificationPreference, Error = Error> {
+    let postgres = postgres.clone();
+
+    payload.store_id = Some(store_id);
+    payload.set_updated_at();
+
+    NotificationPreference::find_by_store(store_id, &postgres)
+        .from_err()
+        .and_then(move |old_preference| {
+            services::audit_events::audited(
+                move |conn| notification_preferences::upsert(payload, conn),
+                move |preference| {
+                    let mut event = AuditEventPayload::new(
+                        AuditAction::NotificationPreferencesUpdated,
+                        Some(actor_id),
+                        ip_address,
+                    );
+                    event.store_id = Some(store_id);
+                    event.old_values = Some(old_preference.export());
+                    event.new_values = Some(preference.export());
+                    event
+                },
+                &postgres,
+            )
+        })
+}
@@ -1,86 +1,271 @@
+use std::str::FromStr;
+
 use bigdecimal::BigDecimal;
-use futures::future::{self, Future, IntoFuture};
+use futures::future::{self, Either, Future};
+use rustc_hex::ToHex;
 use uuid::Uuid;
 
 use core::{
     db::postgres::PgExecutorAddr,
-    payment::{Payment, PaymentPayload},
+    payment::{Payment, PaymentPayload, EXPIRES_IN},
     store::Store,
 };
 use currency_api_client::{CurrencyApiClientAddr, GetRate};
-use hd_keyring::HdKeyring;
+use hd_keyring::{multisig, Error as KeyringError, HdKeyring, XpubAccount};
+use lightning_api_client::{CreateInvoice, LightningClientAddr};
 use services::Error;
-use types::{bitcoin::Network as BtcNetwork, currency::Crypto, PaymentStatus};
+use types::{
+    bitcoin::{ChainParams, Network as BtcNetwork, MSAT_PER_BTC},
+    currency::Crypto,
+    ethereum::BIP44_HD_PATH,
+    PaymentStatus,
+};
 
 const BTC_SCALE: i64 = 8;
 const ETH_SCALE: i64 = 6;
 
 pub fn create(
+    payload: PaymentPayload,
+    store: &Store,
+    postgres: &PgExecutorAddr,
+    min_charge: Option<BigDecimal>,
+    symbol: String,
+    currency_api_client: CurrencyApiClientAddr,
+    lightning_client: Option<LightningClientAddr>,
+) -> Box<Future<Item = Payment, Error = Error>> {
+    match (payload.crypto.unwrap(), lightning_client) {
+        (Crypto::BtcLightning, Some(lightning_client)) => Box::new(create_with_invoice(
+            payload,
+            store,
+            postgres,
+            min_charge,
+            symbol,
+            currency_api_client,
+            lightning_client,
+        )),
+        _ => Box::new(create_with_address(
+            payload,
+            store,
+            postgres,
+            min_charge,
+            symbol,
+            currency_api_client,
+        )),
+    }
+}
+
+fn create_with_address(
     mut payload: PaymentPayload,
     store: &Store,
     postgres: &PgExecutorAddr,
     min_charge: Option<BigDecimal>,
+    symbol: String,
     currency_api_client: CurrencyApiClientAddr,
 ) -> impl Future<Item = Payment, Error = Error> {
     let postgres = postgres.clone();
-    let store = store.to_owned();
-
-    let index: u32 = 1;
+    let store_id = store.id;
+    let mnemonic = store.mnemonic.clone();
+    let passphrase = store.mnemonic_passphrase.clone().unwrap_or_default();
+    let invoice_multisig = match payload.crypto {
+        Some(Crypto::Btc) => store.btc_invoice_multisig(),
+        _ => None,
+    };
 
-    payload.index = Some(index as i32);
     payload.status = Some(PaymentStatus::Pending);
     payload.set_created_at();
 
-    let mut path = store.hd_path.clone();
-
-    let created_at = payload.created_at.unwrap();
-
-    path.push_str("/");
-    path.push_str(&created_at.timestamp().to_string());
-    path.push_str("/");
-    path.push_str(&created_at.timestamp_subsec_micros().to_string());
-
-    HdKeyring::from_mnemonic(
-        &path,
-        &store.mnemonic.clone(),
-        0,
-        payload.btc_network.unwrap_or(BtcNetwork::Test),
-    )
-    .into_future()
-    .from_err()
-    .and_then(move |keyring| keyring.get_wallet_by_index(index).into_future().from_err())
-    .and_then(move |wallet| {
-        currency_api_client
-            .send(GetRate {
-                from: payload.fiat.unwrap(),
-                to: payload.crypto.unwrap(),
-            })
+    let btc_network = payload.btc_network.unwrap_or(BtcNetwork::Test);
+
+    let hd_path = match ChainParams::new(payload.crypto.unwrap(), btc_network) {
+        Some(chain) => chain.bip44_hd_path(),
+        None => BIP44_HD_PATH.to_owned(),
+    };
+
+    let account = find_account(
+        store_id,
+        hd_path.clone(),
+        mnemonic,
+        passphrase,
+        btc_network,
+        &postgres,
+    );
+    let charge = get_charge(&payload, symbol, min_charge, currency_api_client);
+
+    // Only invoices that passed validation take up an address index.
+    account.join(charge).and_then(move |(account, charge)| {
+        Store::next_address_index(store_id, hd_path.clone(), &postgres)
             .from_err()
-            .and_then(move |res| res.map_err(|e| Error::from(e)))
-            .and_then(move |rate| -> Box<Future<Item = Payment, Error = Error>> {
-                let charge = match payload.crypto.unwrap() {
-                    Crypto::Btc => payload.clone().price.unwrap() * rate.with_scale(BTC_SCALE),
-                    Crypto::Eth => payload.clone().price.unwrap() * rate.with_scale(ETH_SCALE),
-                };
-
-                if let Some(min_charge) = min_charge {
-                    if charge < min_charge {
-                        return Box::new(future::err(Error::ChargeAmountTooLow {
-                            min: min_charge,
-                            unit: payload.crypto.unwrap(),
-                        }));
+            .and_then(move |index| -> Result<PaymentPayload, Error> {
+                payload.charge = Some(charge);
+                payload.index = Some(index as i32);
+                payload.hd_path = Some(hd_path);
+
+                match invoice_multisig {
+                    Some((threshold, xpubs)) => {
+                        let chain = ChainParams::new(Crypto::Btc, btc_network).unwrap();
+                        let (address, witness_script) =
+                            multisig_address(&account, threshold, &xpubs, index, &chain)?;
+
+                        payload.address = Some(address);
+                        payload.witness_script = Some(witness_script);
+                    }
+                    None => {
+                        payload.address = Some(account.address(index, &payload.crypto.unwrap())?);
                     }
                 }
 
-                payload.charge = Some(charge);
+                Ok(payload)
+            })
+            .and_then(move |payload| Payment::insert(payload, &postgres).from_err())
+    })
+}
 
-                payload.address = Some(wallet.get_address(&payload.crypto.unwrap()));
+// The store's account at `hd_path`, known by its xpub. Stores created before xpubs were recorded
+// still hold their seed, which the missing xpub is derived from once and kept.
+fn find_account(
+    store_id: Uuid,
+    hd_path: String,
+    mnemonic: Option<String>,
+    passphrase: String,
+    btc_network: BtcNetwork,
+    postgres: &PgExecutorAddr,
+) -> impl Future<Item = XpubAccount, Error = Error> {
+    let postgres = postgres.clone();
 
-                Box::new(Payment::insert(payload, &postgres).from_err())
-            })
+    Store::find_xpub(store_id, hd_path.clone(), &postgres)
+        .from_err()
+        .and_then(move |xpub| -> Box<Future<Item = String, Error = Error>> {
+            match (xpub, mnemonic) {
+                (Some(xpub), _) => Box::new(future::ok(xpub)),
+                (None, Some(mnemonic)) => {
+                    let xpub = match HdKeyring::from_mnemonic(
+                        &hd_path,
+                        &mnemonic,
+                        &passphrase,
+                        0,
+                        btc_network,
+                    ) {
+                        Ok(keyring) => keyring.xpub(),
+                        Err(e) => return Box::new(future::err(Error::from(e))),
+                    };
+
+                    Box::new(
+                        Store::insert_xpub(store_id, hd_path, xpub.clone(), &postgres)
+                            .from_err()
+                            .map(move |_| xpub),
+                    )
+                }
+                (None, None) => Box::new(future::err(Error::BadRequest(
+                    "the store has no key for this currency",
+                ))),
+            }
+        })
+        .and_then(move |xpub| {
+            XpubAccount::from_xpub(&xpub, btc_network).map_err(|e| Error::from(e))
+        })
+}
+
+// Addresses of stores with invoice cosigners are spent with their keys and Finch's at the payment's
+// index. Yields the address and the hex encoded witness script.
+fn multisig_address(
+    account: &XpubAccount,
+    threshold: usize,
+    xpubs: &[String],
+    index: u32,
+    chain: &ChainParams,
+) -> Result<(String, String), KeyringError> {
+    let mut public_keys = multisig::derive_public_keys(xpubs, index)?;
+    public_keys.push(account.public_key(index)?);
+
+    let witness_script = multisig::witness_script(threshold, &public_keys)?;
+    let address = multisig::p2wsh_address(chain, &witness_script)?;
+
+    Ok((address, witness_script.to_hex()))
+}
+
+// Lightning payments are paid to an invoice of the operator's node instead of a derived address.
+fn create_with_invoice(
+    mut payload: PaymentPayload,
+    store: &Store,
+    postgres: &PgExecutorAddr,
+    min_charge: Option<BigDecimal>,
+    symbol: String,
+    currency_api_client: CurrencyApiClientAddr,
+    lightning_client: LightningClientAddr,
+) -> impl Future<Item = Payment, Error = Error> {
+    let postgres = postgres.clone();
+    let description = store.name.clone();
+
+    payload.index = Some(0);
+    payload.status = Some(PaymentStatus::Pending);
+    payload.set_created_at();
+
+    get_charge(&payload, symbol, min_charge, currency_api_client).and_then(move |charge| {
+        let amount_msat = match (charge.clone() * BigDecimal::from_str(MSAT_PER_BTC).unwrap())
+            .with_scale(0)
+            .to_string()
+            .parse::<u64>()
+        {
+            Ok(amount_msat) => amount_msat,
+            Err(_) => return Either::A(future::err(Error::InternalServerError)),
+        };
+
+        Either::B(
+            lightning_client
+                .send(CreateInvoice {
+                    label: Uuid::new_v4().to_string(),
+                    amount_msat,
+                    description,
+                    expiry: EXPIRES_IN as u64,
+                })
+                .from_err()
+                .and_then(|res| res.map_err(|e| Error::from(e)))
+                .and_then(move |invoice| {
+                    payload.charge = Some(charge);
+                    payload.address = Some(invoice.payment_request);
+                    payload.ln_payment_hash = Some(invoice.payment_hash);
+
+                    Payment::insert(payload, &postgres).from_err()
+                }),
+        )
     })
 }
 
+fn get_charge(
+    payload: &PaymentPayload,
+    symbol: String,
+    min_charge: Option<BigDecimal>,
+    currency_api_client: CurrencyApiClientAddr,
+) -> impl Future<Item = BigDecimal, Error = Error> {
+    let crypto = payload.crypto.unwrap();
+    let price = payload.price.clone().unwrap();
+
+    currency_api_client
+        .send(GetRate {
+            from: payload.fiat.unwrap(),
+            to: symbol.clone(),
+        })
+        .from_err()
+        .and_then(move |res| res.map_err(|e| Error::from(e)))
+        .and_then(move |rate| {
+            let charge = match crypto {
+                Crypto::Eth => price * rate.with_scale(ETH_SCALE),
+                _ => price * rate.with_scale(BTC_SCALE),
+            };
+
+            if let Some(min_charge) = min_charge {
+                if charge < min_charge {
+                    return Err(Error::ChargeAmountTooLow {
+                        min: min_charge,
+                        unit: symbol,
+                    });
+                }
+            }
+
+            Ok(charge)
+        })
+}
+
 pub fn get(id: Uuid, postgres: &PgExecutorAddr) -> impl Future<Item = Payment, Error = Error> {
     Payment::find_by_id(id, postgres).from_err()
 }
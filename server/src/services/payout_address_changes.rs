@@ -0,0 +1,258 @@
+use std::time::Duration as StdDuration;
+
+use actix::prelude::*;
+use chrono::Duration;
+use futures::future::{err, Future};
+use uuid::Uuid;
+
+use core::{
+    audit_event::AuditEventPayload,
+    db::{payout_address_changes, postgres::PgExecutorAddr},
+    payout_address_change::{PayoutAddressChange, PayoutAddressChangePayload},
+    store::Store,
+    user::User,
+};
+use mailer::{MailerAddr, SendMail, Template};
+use services::{self, Error};
+use types::{AuditAction, Locale};
+
+// How often changes whose time-lock ran out are applied, besides before payouts.
+const APPLY_INTERVAL_SECS: u64 = 60;
+
+fn cancel_url(web_client_url: &str, change: &PayoutAddressChange) -> String {
+    format!(
+        "{}/cancel_payout_address_change?token={}",
+        web_client_url, change.cancellation_token
+    )
+}
+
+fn send_mail(
+    template: Template,
+    locale: Locale,
+    email: String,
+    mailer: MailerAddr,
+    mail_sender: String,
+) -> impl Future<Item = (), Error = Error> {
+    mailer
+        .send(SendMail::from_template(
+            &template,
+            locale,
+            mail_sender,
+            email,
+        ))
+        .from_err()
+        .and_then(move |res| res.map_err(|e| Error::from(e)))
+}
+
+pub fn request(
+    store: Store,
+    mut payload: PayoutAddressChangePayload,
+    actor_id: Uuid,
+    ip_address: Option<String>,
+    mailer: MailerAddr,
+    postgres: &PgExecutorAddr,
+    web_client_url: String,
+    mail_sender: String,
+) -> impl Future<Item = PayoutAddressChange, Error = Error> {
+    let postgres = postgres.clone();
+
+    payload.store_id = Some(store.id);
+    payload.set_created_at();
+    payload.set_tokens();
+
+    User::find_by_id(store.owner_id, &postgres)
+        .from_err()
+        .and_then(move |owner| {
+            let store_name = store.name.clone();
+            let _ip_address = ip_address.clone();
+
+            services::audit_events::audited(
+                move |conn| payout_address_changes::insert(payload, conn),
+                move |change| {
+                    let mut event = AuditEventPayload::new(
+                        AuditAction::PayoutAddressChangeRequested,
+                        Some(actor_id),
+                        ip_address,
+                    );
+                    event.store_id = Some(change.store_id);
+                    event.old_values = Some(json!({
+                        "eth_payout_addresses": store.eth_payout_addresses,
+                        "btc_payout_addresses": store.btc_payout_addresses,
+                        "ltc_payout_addresses": store.ltc_payout_addresses,
+                        "bch_payout_addresses": store.bch_payout_addresses,
+                        "doge_payout_addresses": store.doge_payout_addresses,
+                        "btc_payout_multisig_threshold": store.btc_payout_multisig_threshold,
+                        "btc_payout_multisig_public_keys": store.btc_payout_multisig_public_keys,
+                        "btc_invoice_multisig_threshold": store.btc_invoice_multisig_threshold,
+                        "btc_invoice_multisig_xpubs": store.btc_invoice_multisig_xpubs,
+                        "manual_payout_approval": store.manual_payout_approval,
+                    }));
+                    event.new_values = Some(change.export());
+                    event
+                },
+                &postgres,
+            )
+            .and_then(move |change| {
+                let change_id = change.id;
+
+                let template = Template::PayoutAddressChange {
+                    store_name,
+                    url: format!(
+                        "{}/confirm_payout_addresses?token={}",
+                        web_client_url, change.confirmation_token
+                    ),
+                    cancel_url: cancel_url(&web_client_url, &change),
+                };
+
+                send_mail(template, owner.locale, owner.email, mailer, mail_sender)
+                    .then(move |res| res.and_then(|_| Ok(change)))
+                    .or_else(move |e| {
+                        // A change nobody was told about can't be confirmed.
+                        cancel_audited(change_id, None, _ip_address, &postgres).and_then(|_| err(e))
+                    })
+            })
+        })
+}
+
+// The owner is told about the confirmation, with a link to cancel the change, before its
+// time-lock starts. A change the owner couldn't be told about is cancelled instead.
+pub fn confirm(
+    token: Uuid,
+    time_lock: Duration,
+    ip_address: Option<String>,
+    mailer: MailerAddr,
+    postgres: &PgExecutorAddr,
+    web_client_url: String,
+    mail_sender: String,
+) -> impl Future<Item = PayoutAddressChange, Error = Error> {
+    let postgres = postgres.clone();
+    let _ip_address = ip_address.clone();
+
+    services::audit_events::audited(
+        move |conn| payout_address_changes::confirm(token, conn),
+        move |change| {
+            let mut event =
+                AuditEventPayload::new(AuditAction::PayoutAddressChangeConfirmed, None, ip_address);
+            event.store_id = Some(change.store_id);
+            event.new_values = Some(change.export());
+            event
+        },
+        &postgres,
+    )
+    .and_then(move |change| {
+        let change_id = change.id;
+        let _postgres = postgres.clone();
+        let __postgres = postgres.clone();
+
+        Store::find_by_id(change.store_id, &postgres)
+            .and_then(move |store| {
+                User::find_by_id(store.owner_id, &_postgres).map(move |owner| (store, owner))
+            })
+            .from_err()
+            .and_then(move |(store, owner)| {
+                let template = Template::PayoutAddressChangeConfirmed {
+                    store_name: store.name,
+                    cancel_url: cancel_url(&web_client_url, &change),
+                };
+
+                send_mail(template, owner.locale, owner.email, mailer, mail_sender)
+            })
+            .and_then(move |_| {
+                PayoutAddressChange::schedule(change_id, time_lock, &__postgres).from_err()
+            })
+            .or_else(move |e| {
+                cancel_audited(change_id, None, _ip_address, &postgres).and_then(|_| err(e))
+            })
+    })
+}
+
+pub fn cancel(
+    id: Uuid,
+    actor_id: Uuid,
+    ip_address: Option<String>,
+    postgres: &PgExecutorAddr,
+) -> impl Future<Item = PayoutAddressChange, Error = Error> {
+    cancel_audited(id, Some(actor_id), ip_address, postgres)
+}
+
+// Cancels the change through the link emailed to the owner, who may not be able to sign in.
+pub fn cancel_by_token(
+    token: Uuid,
+    ip_address: Option<String>,
+    postgres: &PgExecutorAddr,
+) -> impl Future<Item = PayoutAddressChange, Error = Error> {
+    services::audit_events::audited(
+        move |conn| payout_address_changes::cancel_by_token(token, conn),
+        move |change| {
+            let mut event =
+                AuditEventPayload::new(AuditAction::PayoutAddressChangeCancelled, None, ip_address);
+            event.store_id = Some(change.store_id);
+            event.old_values = Some(change.export());
+            event
+        },
+        postgres,
+    )
+}
+
+fn cancel_audited(
+    id: Uuid,
+    actor_id: Option<Uuid>,
+    ip_address: Option<String>,
+    postgres: &PgExecutorAddr,
+) -> impl Future<Item = PayoutAddressChange, Error = Error> {
+    services::audit_events::audited(
+        move |conn| payout_address_changes::cancel(id, conn),
+        move |change| {
+            let mut event = AuditEventPayload::new(
+                AuditAction::PayoutAddressChangeCancelled,
+                actor_id,
+                ip_address,
+            );
+            event.store_id = Some(change.store_id);
+            event.old_values = Some(change.export());
+            event
+        },
+        postgres,
+    )
+}
+
+pub fn get(
+    id: Uuid,
+    postgres: &PgExecutorAddr,
+) -> impl Future<Item = PayoutAddressChange, Error = Error> {
+    PayoutAddressChange::find_by_id(id, postgres).from_err()
+}
+
+pub fn find_by_store(
+    store_id: Uuid,
+    limit: i64,
+    offset: i64,
+    postgres: &PgExecutorAddr,
+) -> impl Future<Item = Vec<PayoutAddressChange>, Error = Error> {
+    PayoutAddressChange::find_by_store(store_id, limit, offset, postgres).from_err()
+}
+
+// Applies changes whose time-lock ran out, so that stores show them even while no payout is due.
+pub struct Applier {
+    postgres: PgExecutorAddr,
+}
+
+impl Applier {
+    pub fn new(postgres: PgExecutorAddr) -> Self {
+        Applier { postgres }
+    }
+}
+
+impl Actor for Applier {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        ctx.run_interval(StdDuration::from_secs(APPLY_INTERVAL_SECS), |applier, _| {
+            Arbiter::spawn(
+                PayoutAddressChange::apply_due(&applier.postgres)
+                    .map(|_| ())
+                    .map_err(|e| error!("failed to apply payout address changes: {:?}", e)),
+            );
+        });
+    }
+}
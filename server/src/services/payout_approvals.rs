@@ -0,0 +1,52 @@
+use futures::future::Future;
+use uuid::Uuid;
+
+use core::{
+    audit_event::AuditEventPayload,
+    db::{payouts, postgres::PgExecutorAddr},
+    payout::{Payout, PayoutApproval},
+};
+use services::{self, Error};
+use types::AuditAction;
+
+pub fn get_payout(
+    id: Uuid,
+    postgres: &PgExecutorAddr,
+) -> impl Future<Item = Payout, Error = Error> {
+    Payout::find_by_id(id, postgres).from_err()
+}
+
+pub fn find_by_store(
+    store_id: Uuid,
+    postgres: &PgExecutorAddr,
+) -> impl Future<Item = Vec<(Payout, PayoutApproval)>, Error = Error> {
+    Payout::find_all_awaiting_approval_by_store(store_id, postgres).from_err()
+}
+
+// The payouter picks the payout up again on the next block, broadcasting the signed transaction
+// if one was uploaded.
+pub fn approve(
+    id: Uuid,
+    signed_transaction: Option<String>,
+    actor_id: Uuid,
+    ip_address: Option<String>,
+    postgres: &PgExecutorAddr,
+) -> impl Future<Item = Payout, Error = Error> {
+    let _signed_transaction = signed_transaction.clone();
+
+    services::audit_events::audited(
+        move |conn| payouts::approve(id, actor_id, _signed_transaction, conn),
+        move |payout| {
+            let mut event =
+                AuditEventPayload::new(AuditAction::PayoutApproved, Some(actor_id), ip_address);
+            event.store_id = Some(payout.store_id);
+            event.new_values = Some(json!({
+                "payout_id": payout.id,
+                "payment_id": payout.payment_id,
+                "signed_transaction": signed_transaction,
+            }));
+            event
+        },
+        postgres,
+    )
+}
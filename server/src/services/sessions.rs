@@ -0,0 +1,152 @@
+use chrono::{prelude::*, Duration};
+use data_encoding::BASE64;
+use futures::future::{Future, IntoFuture};
+use ring::{digest, rand, rand::SecureRandom};
+use uuid::Uuid;
+
+use auth::{AuthUser, JWTPayload};
+use core::{
+    db::postgres::PgExecutorAddr,
+    session::{Session, SessionPayload},
+};
+use services::Error;
+use types::PrivateKey;
+
+const ACCESS_TOKEN_TTL_MINUTES: i64 = 15;
+const REFRESH_TOKEN_TTL_DAYS: i64 = 30;
+const REFRESH_TOKEN_LEN: usize = 32;
+
+// Only the digest of a refresh token is stored, so a leaked sessions table can't be replayed.
+fn hash_refresh_token(refresh_token: &str) -> String {
+    BASE64.encode(digest::digest(&digest::SHA256, refresh_token.as_bytes()).as_ref())
+}
+
+fn generate_refresh_token() -> Result<String, Error> {
+    let rng = rand::SystemRandom::new();
+    let mut refresh_token = [0u8; REFRESH_TOKEN_LEN];
+
+    rng.fill(&mut refresh_token)
+        .map_err(|_| Error::InternalServerError)?;
+
+    Ok(BASE64.encode(&refresh_token))
+}
+
+fn encode_access_token(
+    user_id: Uuid,
+    session_id: Uuid,
+    jwt_private: &PrivateKey,
+) -> Result<String, Error> {
+    let expires_at = Utc::now() + Duration::minutes(ACCESS_TOKEN_TTL_MINUTES);
+
+    JWTPayload::new(
+        Some(AuthUser {
+            id: user_id,
+            session_id: Some(session_id),
+        }),
+        None,
+        expires_at,
+    )
+    .encode(jwt_private)
+    .map_err(|e| Error::from(e))
+}
+
+// Returns a short-lived access token and the refresh token of the new session.
+pub fn create(
+    user_id: Uuid,
+    ip_address: Option<String>,
+    user_agent: Option<String>,
+    postgres: &PgExecutorAddr,
+    jwt_private: PrivateKey,
+) -> impl Future<Item = (String, String), Error = Error> {
+    let postgres = postgres.clone();
+
+    generate_refresh_token()
+        .into_future()
+        .and_then(move |refresh_token| {
+            let mut payload = SessionPayload::new();
+            payload.user_id = Some(user_id);
+            payload.refresh_token_hash = Some(hash_refresh_token(&refresh_token));
+            payload.ip_address = Some(ip_address);
+            payload.user_agent = Some(user_agent);
+            payload.expires_at = Some(Utc::now() + Duration::days(REFRESH_TOKEN_TTL_DAYS));
+
+            Session::insert(payload, &postgres)
+                .from_err()
+                .and_then(move |session| {
+                    encode_access_token(user_id, session.id, &jwt_private)
+                        .map(|access_token| (access_token, refresh_token))
+                })
+        })
+}
+
+pub fn refresh(
+    refresh_token: String,
+    postgres: &PgExecutorAddr,
+    jwt_private: PrivateKey,
+) -> impl Future<Item = (String, String), Error = Error> {
+    let postgres = postgres.clone();
+
+    let old_refresh_token_hash = hash_refresh_token(&refresh_token);
+    let superseded_refresh_token_hash = old_refresh_token_hash.clone();
+    let postgres_revoke = postgres.clone();
+
+    Session::find_active_by_refresh_token_hash(old_refresh_token_hash.clone(), &postgres)
+        .from_err()
+        .or_else(move |e: Error| {
+            // A token which was already rotated out is presented again, so a copy of it leaked.
+            // The session it was replaced in is revoked, locking out whoever holds the copy.
+            Session::revoke_by_superseded_refresh_token_hash(
+                superseded_refresh_token_hash,
+                &postgres_revoke,
+            )
+            .from_err()
+            .and_then(move |revoked| match revoked {
+                0 => Err(e),
+                _ => Err(Error::BadRequest("refresh token was already used")),
+            })
+        })
+        .and_then(move |session| {
+            generate_refresh_token()
+                .into_future()
+                .and_then(move |refresh_token| {
+                    Session::rotate(
+                        session.id,
+                        old_refresh_token_hash,
+                        hash_refresh_token(&refresh_token),
+                        &postgres,
+                    )
+                    .from_err()
+                    .and_then(move |session| match session {
+                        Some(session) => {
+                            encode_access_token(session.user_id, session.id, &jwt_private)
+                                .map(|access_token| (access_token, refresh_token))
+                        }
+                        None => Err(Error::BadRequest("refresh token was already used")),
+                    })
+                })
+        })
+}
+
+pub fn find_by_user(
+    user_id: Uuid,
+    limit: i64,
+    offset: i64,
+    postgres: &PgExecutorAddr,
+) -> impl Future<Item = Vec<Session>, Error = Error> {
+    Session::find_active_by_user(user_id, limit, offset, postgres).from_err()
+}
+
+pub fn revoke(
+    id: Uuid,
+    user_id: Uuid,
+    postgres: &PgExecutorAddr,
+) -> impl Future<Item = usize, Error = Error> {
+    Session::revoke(id, user_id, postgres).from_err()
+}
+
+pub fn revoke_all(
+    user_id: Uuid,
+    postgres: &PgExecutorAddr,
+) -> impl Future<Item = usize, Error = Error> {
+    Session::revoke_by_user(user_id, postgres).from_err()
+}
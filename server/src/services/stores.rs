@@ -3,12 +3,22 @@ use openssl::rsa::Rsa;
 use uuid::Uuid;
 
 use core::{
-    db::postgres::PgExecutorAddr,
+    audit_event::AuditEventPayload,
+    db::{postgres::PgExecutorAddr, stores},
+    payment::Payment,
+    payout::{Payout, PayoutPsbt},
     store::{Store, StorePayload},
 };
-use hd_keyring::HdKeyring;
-use services::Error;
-use types::{bitcoin::Network as BtcNetwork, PrivateKey, PublicKey};
+use hd_keyring::{
+    recovery::{self, ExportedAccount, ExportedPayment, WalletExport},
+    Error as KeyringError, HdKeyring,
+};
+use services::{self, Error};
+use types::{
+    bitcoin::{ChainParams, Network as BtcNetwork},
+    ethereum::BIP44_HD_PATH,
+    AuditAction, PrivateKey, PublicKey,
+};
 
 fn generate_rsa() -> Result<(PrivateKey, PublicKey), Error> {
     let rsa = Rsa::generate(2048)?;
@@ -18,39 +28,126 @@ fn generate_rsa() -> Result<(PrivateKey, PublicKey), Error> {
     Ok((private_key, public_key))
 }
 
+// Extended public keys of the accounts new payments are derived under, by hd path.
+fn account_xpubs(
+    mnemonic: &str,
+    passphrase: &str,
+    btc_network: BtcNetwork,
+) -> Result<Vec<(String, String)>, KeyringError> {
+    let mut hd_paths = vec![BIP44_HD_PATH.to_owned()];
+
+    for chain in ChainParams::all() {
+        let hd_path = chain.bip44_hd_path();
+
+        if !hd_paths.contains(&hd_path) {
+            hd_paths.push(hd_path);
+        }
+    }
+
+    hd_paths
+        .into_iter()
+        .map(|hd_path| {
+            HdKeyring::from_mnemonic(&hd_path, mnemonic, passphrase, 0, btc_network)
+                .map(|keyring| (hd_path, keyring.xpub()))
+        })
+        .collect()
+}
+
+// Yields the store, along with the mnemonic of a generated wallet when `holds_seed` is false: the
+// seed is then only kept by the signer, which the operator has to give it to.
 pub fn create(
     mut payload: StorePayload,
     btc_network: BtcNetwork,
+    holds_seed: bool,
     postgres: &PgExecutorAddr,
-) -> impl Future<Item = Store, Error = Error> {
+) -> impl Future<Item = (Store, Option<String>), Error = Error> {
     let postgres = postgres.clone();
 
     let kay_pair = generate_rsa().into_future();
 
-    let keyring = HdKeyring::new("m/44'/60'/0'/0", 1, btc_network)
-        .into_future()
-        .from_err();
+    let passphrase = payload
+        .mnemonic_passphrase
+        .clone()
+        .and_then(|passphrase| passphrase)
+        .unwrap_or_default();
+    let imported = payload.mnemonic.clone().and_then(|mnemonic| mnemonic);
+
+    // Existing wallets are imported by giving their mnemonic.
+    let keyring = match imported {
+        Some(ref mnemonic) => {
+            HdKeyring::from_mnemonic("m/44'/60'/0'/0", mnemonic, &passphrase, 1, btc_network)
+                .map_err(|_| Error::BadRequest("invalid mnemonic"))
+        }
+        None => HdKeyring::new("m/44'/60'/0'/0", &passphrase, 1, btc_network)
+            .map_err(|e| Error::from(e)),
+    }
+    .into_future();
 
     kay_pair
         .join(keyring)
         .and_then(move |((private_key, public_key), keyring)| {
-            payload.mnemonic = Some(keyring.mnemonic.phrase());
+            let mnemonic = keyring.mnemonic.phrase();
+            let xpubs = account_xpubs(&mnemonic, &passphrase, btc_network);
+
+            if holds_seed {
+                payload.mnemonic = Some(Some(mnemonic.clone()));
+                payload.mnemonic_passphrase = Some(Some(passphrase));
+            } else {
+                payload.mnemonic = Some(None);
+                payload.mnemonic_passphrase = Some(None);
+            }
+
             payload.hd_path = Some(keyring.hd_path.to_string());
             payload.private_key = Some(private_key);
             payload.public_key = Some(public_key);
 
-            Store::insert(payload, &postgres).from_err()
+            // Imported mnemonics are already known to the merchant.
+            let revealed = match (holds_seed, imported) {
+                (false, None) => Some(mnemonic),
+                _ => None,
+            };
+
+            xpubs
+                .map(move |xpubs| (payload, xpubs, revealed))
+                .map_err(|e| Error::from(e))
+        })
+        .and_then(move |(payload, xpubs, revealed)| {
+            Store::insert(payload, xpubs, &postgres)
+                .from_err()
+                .map(move |store| (store, revealed))
         })
 }
 
 pub fn patch(
     id: Uuid,
-    payload: StorePayload,
+    mut payload: StorePayload,
+    actor_id: Uuid,
+    ip_address: Option<String>,
     postgres: &PgExecutorAddr,
 ) -> impl Future<Item = Store, Error = Error> {
     let postgres = postgres.clone();
 
-    Store::update(id, payload, &postgres).from_err()
+    payload.set_updated_at();
+
+    Store::find_by_id(id, &postgres)
+        .from_err()
+        .and_then(move |old_store| {
+            services::audit_events::audited(
+                move |conn| stores::update(id, payload, conn),
+                move |store| {
+                    let mut event = AuditEventPayload::new(
+                        AuditAction::StoreUpdated,
+                        Some(actor_id),
+                        ip_address,
+                    );
+                    event.store_id = Some(store.id);
+                    event.old_values = Some(old_store.export_settings());
+                    event.new_values = Some(store.export_settings());
+                    event
+                },
+                &postgres,
+            )
+        })
 }
 
 pub fn find_by_owner(
@@ -66,6 +163,128 @@ pub fn get(id: Uuid, postgres: &PgExecutorAddr) -> impl Future<Item = Store, Err
     Store::find_by_id(id, postgres).from_err()
 }
 
-pub fn delete(id: Uuid, postgres: &PgExecutorAddr) -> impl Future<Item = usize, Error = Error> {
-    Store::soft_delete(id, postgres).from_err()
+pub fn find_psbts(
+    id: Uuid,
+    postgres: &PgExecutorAddr,
+) -> impl Future<Item = Vec<(Payout, PayoutPsbt)>, Error = Error> {
+    Payout::find_all_awaiting_cosigners_by_store(id, postgres).from_err()
+}
+
+pub fn delete(
+    id: Uuid,
+    actor_id: Uuid,
+    ip_address: Option<String>,
+    postgres: &PgExecutorAddr,
+) -> impl Future<Item = usize, Error = Error> {
+    let postgres = postgres.clone();
+
+    Store::find_by_id(id, &postgres)
+        .from_err()
+        .and_then(move |store| {
+            services::audit_events::audited(
+                move |conn| stores::soft_delete(id, conn),
+                move |_| {
+                    let mut event = AuditEventPayload::new(
+                        AuditAction::StoreDeleted,
+                        Some(actor_id),
+                        ip_address,
+                    );
+                    event.store_id = Some(store.id);
+                    event.old_values = Some(store.export_settings());
+                    event
+                },
+                &postgres,
+            )
+        })
+}
+
+// Everything needed to recover the store's funds without Finch. See `hd_keyring::recovery`.
+pub fn export_wallet(
+    store: Store,
+    btc_network: BtcNetwork,
+    actor_id: Uuid,
+    ip_address: Option<String>,
+    postgres: &PgExecutorAddr,
+) -> impl Future<Item = WalletExport, Error = Error> {
+    let postgres = postgres.clone();
+
+    Payment::find_all_with_address_by_store(store.id, &postgres)
+        .from_err()
+        .and_then(move |payments| -> Result<(Store, WalletExport), Error> {
+            let mnemonic = store.mnemonic.clone().ok_or(Error::BadRequest(
+                "the store's seed is only held by the signer",
+            ))?;
+            let passphrase = store.mnemonic_passphrase.clone().unwrap_or_default();
+
+            // Accounts of sequentially derived payments. Legacy ones each had their own.
+            let mut hd_paths = vec![String::from("m")];
+
+            for payment in payments.iter() {
+                if let Some(ref hd_path) = payment.hd_path {
+                    if !hd_paths.contains(hd_path) {
+                        hd_paths.push(hd_path.clone());
+                    }
+                }
+            }
+
+            let accounts = hd_paths
+                .into_iter()
+                .map(|hd_path| {
+                    let paid_indexes = payments
+                        .iter()
+                        .filter(|payment| {
+                            payment.hd_path.as_ref() == Some(&hd_path)
+                                && (payment.amount_paid.is_some()
+                                    || payment.transaction_hash.is_some())
+                        })
+                        .map(|payment| payment.index as u32)
+                        .collect::<Vec<u32>>();
+
+                    HdKeyring::from_mnemonic(&hd_path, &mnemonic, &passphrase, 0, btc_network).map(
+                        |keyring| ExportedAccount {
+                            xprv: keyring.xprv(),
+                            gap_limit: recovery::gap_limit(&paid_indexes),
+                            hd_path,
+                        },
+                    )
+                })
+                .collect::<Result<Vec<ExportedAccount>, _>>()?;
+
+            let payments = payments
+                .into_iter()
+                .map(|payment| {
+                    let payment_btc_network = payment.btc_network.unwrap_or(BtcNetwork::Test);
+
+                    let hd_path = match ChainParams::new(payment.crypto, payment_btc_network) {
+                        Some(chain) => payment.derivation_path(&chain.hd_path(&store.hd_path)),
+                        None => payment.derivation_path(&store.hd_path),
+                    };
+
+                    ExportedPayment {
+                        id: payment.id.to_string(),
+                        crypto: payment.crypto,
+                        btc_network: payment_btc_network,
+                        address: payment.address,
+                        hd_path,
+                        index: payment.index as u32,
+                    }
+                })
+                .collect();
+
+            let export = WalletExport {
+                mnemonic,
+                passphrase,
+                accounts,
+                payments,
+            };
+
+            Ok((store, export))
+        })
+        .and_then(move |(store, export)| {
+            let mut event =
+                AuditEventPayload::new(AuditAction::WalletExported, Some(actor_id), ip_address);
+            event.store_id = Some(store.id);
+
+            services::audit_events::record(event, &postgres).map(move |_| export)
+        })
 }
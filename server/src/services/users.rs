@@ -1,17 +1,17 @@
-use chrono::{prelude::*, Duration};
 use data_encoding::BASE64;
-use futures::future::{err, Future, IntoFuture};
+use futures::future::{err, Future};
 use ring::{digest, pbkdf2, rand, rand::SecureRandom};
 use uuid::Uuid;
 
-use auth::{AuthUser, JWTPayload};
 use core::{
-    db::postgres::PgExecutorAddr,
+    audit_event::AuditEventPayload,
+    db::{postgres::PgExecutorAddr, users},
     user::{User, UserPayload},
 };
-use mailer::{MailerAddr, SendMail};
-use services::Error;
-use types::PrivateKey;
+use mailer::{MailerAddr, SendMail, Template};
+use rate_limiter::RateLimiter;
+use services::{self, Error};
+use types::{AuditAction, PrivateKey};
 
 const CREDENTIAL_LEN: usize = digest::SHA512_OUTPUT_LEN;
 const N_ITER: u32 = 100_000;
@@ -50,29 +50,20 @@ pub fn register(
                 .and_then(move |user| {
                     let user_id = user.id;
 
-                    let url = format!(
-                        "{}/activation?token={}",
-                        web_client_url, user.verification_token
-                    );
-
-                    let html = format!(
-                        "Please click the following link to activate your account: <a href=\"{}\">{}</a>.",
-                        url, url
-                    );
-
-                    let text = format!(
-                        "Please click the following link to activate your account: {}",
-                        url
-                    );
+                    let template = Template::Activation {
+                        url: format!(
+                            "{}/activation?token={}",
+                            web_client_url, user.verification_token
+                        ),
+                    };
 
                     mailer
-                        .send(SendMail {
-                            subject: String::from("Please activate your account."),
-                            from: mail_sender,
-                            to: user.email.clone(),
-                            html,
-                            text,
-                        })
+                        .send(SendMail::from_template(
+                            &template,
+                            user.locale,
+                            mail_sender,
+                            user.email.clone(),
+                        ))
                         .from_err()
                         .and_then(move |res| res.map_err(|e| Error::from(e)))
                         .then(move |res| res.and_then(|_| Ok(user)))
@@ -89,64 +80,133 @@ pub fn register(
 pub fn authenticate(
     email: String,
     password: String,
+    ip_address: Option<String>,
+    user_agent: Option<String>,
+    rate_limiter: RateLimiter,
     postgres: &PgExecutorAddr,
     jwt_private: PrivateKey,
-) -> impl Future<Item = (String, User), Error = Error> {
-    User::find_by_email(email, postgres)
+) -> impl Future<Item = (String, String, User), Error = Error> {
+    let postgres = postgres.clone();
+
+    rate_limiter
+        .is_locked_out(&email)
         .from_err()
+        .and_then(|locked_out| {
+            if locked_out {
+                return Err(Error::AccountLocked);
+            }
+
+            Ok(())
+        })
+        .and_then({
+            let email = email.clone();
+            let postgres = postgres.clone();
+
+            move |_| {
+                User::find_by_email(email, &postgres)
+                    .from_err()
+                    .and_then(move |user| verify_password(&user, &password).map(move |_| user))
+            }
+        })
+        .or_else({
+            let rate_limiter = rate_limiter.clone();
+            let email = email.clone();
+
+            move |e| -> Box<Future<Item = User, Error = Error>> {
+                match e {
+                    Error::IncorrectPassword => Box::new(
+                        rate_limiter
+                            .record_failed_login(&email)
+                            .from_err()
+                            .and_then(|_| err(Error::IncorrectPassword)),
+                    ),
+                    e => Box::new(err(e)),
+                }
+            }
+        })
         .and_then(move |user| {
-            let salt = BASE64
-                .decode(&user.salt.as_bytes())
-                .map_err(|e| Error::from(e))
-                .into_future();
-
-            let password_hash = BASE64
-                .decode(&user.password.as_bytes())
-                .map_err(|e| Error::from(e))
-                .into_future();
-
-            salt.join(password_hash)
-                .and_then(move |(salt, password_hash)| {
-                    pbkdf2::verify(
-                        &digest::SHA512,
-                        N_ITER,
-                        &salt,
-                        password.as_bytes(),
-                        &password_hash,
-                    )
-                    .map_err(|_| Error::IncorrectPassword)
-                    .into_future()
-                })
-                .and_then(move |_| {
-                    let expires_at = Utc::now() + Duration::days(1);
+            rate_limiter
+                .clear_failed_logins(&email)
+                .from_err()
+                .map(move |_| user)
+        })
+        .and_then(move |user| {
+            services::sessions::create(user.id, ip_address, user_agent, &postgres, jwt_private)
+                .map(move |(token, refresh_token)| (token, refresh_token, user))
+        })
+}
 
-                    JWTPayload::new(Some(AuthUser { id: user.id }), None, expires_at)
-                        .encode(&jwt_private)
-                        .map_err(|e| Error::from(e))
-                        .and_then(|token| Ok((token, user)))
-                })
+fn verify_password(user: &User, password: &str) -> Result<(), Error> {
+    let salt = BASE64.decode(&user.salt.as_bytes())?;
+    let password_hash = BASE64.decode(&user.password.as_bytes())?;
+
+    pbkdf2::verify(
+        &digest::SHA512,
+        N_ITER,
+        &salt,
+        password.as_bytes(),
+        &password_hash,
+    )
+    .map_err(|_| Error::IncorrectPassword)
+}
+
+// Asks a signed in user for their password again before a sensitive operation. Failures count
+// towards the same lockout as logins.
+pub fn confirm_password(
+    id: Uuid,
+    password: String,
+    rate_limiter: RateLimiter,
+    postgres: &PgExecutorAddr,
+) -> impl Future<Item = User, Error = Error> {
+    User::find_by_id(id, postgres)
+        .from_err()
+        .and_then(move |user| {
+            rate_limiter.is_locked_out(&user.email).from_err().and_then(
+                move |locked_out| -> Box<Future<Item = User, Error = Error>> {
+                    if locked_out {
+                        return Box::new(err(Error::AccountLocked));
+                    }
+
+                    match verify_password(&user, &password) {
+                        Ok(_) => Box::new(
+                            rate_limiter
+                                .clear_failed_logins(&user.email)
+                                .from_err()
+                                .map(move |_| user),
+                        ),
+                        Err(Error::IncorrectPassword) => Box::new(
+                            rate_limiter
+                                .record_failed_login(&user.email)
+                                .from_err()
+                                .and_then(|_| err(Error::IncorrectPassword)),
+                        ),
+                        Err(e) => Box::new(err(e)),
+                    }
+                },
+            )
         })
 }
 
 pub fn activate(
     token: Uuid,
+    ip_address: Option<String>,
+    user_agent: Option<String>,
     postgres: &PgExecutorAddr,
     jwt_private: PrivateKey,
-) -> impl Future<Item = (String, User), Error = Error> {
-    User::activate(token, postgres)
+) -> impl Future<Item = (String, String, User), Error = Error> {
+    let postgres = postgres.clone();
+
+    User::activate(token, &postgres)
         .from_err()
         .and_then(move |user| {
-            let expires_at = Utc::now() + Duration::days(1);
-
-            JWTPayload::new(Some(AuthUser { id: user.id }), None, expires_at)
-                .encode(&jwt_private)
-                .map_err(|e| Error::from(e))
-                .and_then(|token| Ok((token, user)))
+            services::sessions::create(user.id, ip_address, user_agent, &postgres, jwt_private)
+                .map(move |(token, refresh_token)| (token, refresh_token, user))
         })
 }
 
 pub fn reset_password(
     email: String,
+    ip_address: Option<String>,
     mailer: MailerAddr,
     postgres: &PgExecutorAddr,
     web_client_url: String,
@@ -162,55 +222,59 @@ pub fn reset_password(
             let mut payload = UserPayload::from(user.clone());
 
             payload.set_reset_token();
-            User::update(user.id, payload, &postgres)
-                .from_err()
-                .and_then(move |user| {
-                    let user_id = user.id;
+            payload.set_updated_at();
 
-                    let url = format!(
+            let user_id = user.id;
+
+            services::audit_events::audited(
+                move |conn| users::update(user_id, payload, conn),
+                move |user| {
+                    AuditEventPayload::new(
+                        AuditAction::PasswordResetRequested,
+                        Some(user.id),
+                        ip_address,
+                    )
+                },
+                &postgres,
+            )
+            .and_then(move |user| {
+                let template = Template::ResetPassword {
+                    url: format!(
                         "{}/reset_password?token={}",
                         web_client_url,
                         user.reset_token.unwrap()
-                    );
-
-                    let html = format!(
-                        "Please click the following link to reset your password: <a href=\"{}\">{}</a>.",
-                        url, url
-                    );
-
-                    let text = format!(
-                        "Please click the following link to reset your password: {}",
-                        url
-                    );
-
-                    mailer
-                        .send(SendMail {
-                            subject: String::from("Please reset your password."),
-                            from: mail_sender,
-                            to: user.email.clone(),
-                            html,
-                            text,
-                        })
-                        .from_err()
-                        .and_then(move |res| res.map_err(|e| Error::from(e)))
-                        .then(move |res| res.and_then(|_| Ok(user)))
-                        .from_err()
-                        .or_else(move |e| {
-                            User::delete(user_id, &postgres)
-                                .from_err()
-                                .and_then(|_| err(e))
-                        })
-                })
-                .map(|_| true)
+                    ),
+                };
+
+                mailer
+                    .send(SendMail::from_template(
+                        &template,
+                        user.locale,
+                        mail_sender,
+                        user.email.clone(),
+                    ))
+                    .from_err()
+                    .and_then(move |res| res.map_err(|e| Error::from(e)))
+                    .then(move |res| res.and_then(|_| Ok(user)))
+                    .from_err()
+                    .or_else(move |e| {
+                        User::delete(user_id, &postgres)
+                            .from_err()
+                            .and_then(|_| err(e))
+                    })
+            })
+            .map(|_| true)
         })
 }
 
 pub fn change_password(
     token: Uuid,
     password: String,
+    ip_address: Option<String>,
+    user_agent: Option<String>,
     postgres: &PgExecutorAddr,
     jwt_private: PrivateKey,
-) -> impl Future<Item = (String, User), Error = Error> {
+) -> impl Future<Item = (String, String, User), Error = Error> {
     let postgres = postgres.clone();
 
     User::find_by_reset_token(token, &postgres)
@@ -235,16 +299,31 @@ pub fn change_password(
             payload.password = Some(BASE64.encode(&pbkdf2_hash));
             payload.salt = Some(BASE64.encode(&salt));
 
-            User::update(user.id, payload, &postgres)
-                .from_err()
-                .and_then(move |user| {
-                    let expires_at = Utc::now() + Duration::days(1);
-
-                    JWTPayload::new(Some(AuthUser { id: user.id }), None, expires_at)
-                        .encode(&jwt_private)
-                        .map_err(|e| Error::from(e))
-                        .and_then(|token| Ok((token, user)))
+            payload.set_updated_at();
+
+            let user_id = user.id;
+            let _ip_address = ip_address.clone();
+
+            services::audit_events::audited(
+                move |conn| users::update(user_id, payload, conn),
+                move |user| {
+                    AuditEventPayload::new(AuditAction::PasswordChanged, Some(user.id), _ip_address)
+                },
+                &postgres,
+            )
+            .and_then(move |user| {
+                // Every existing session is revoked, so a stolen refresh token stops working.
+                services::sessions::revoke_all(user_id, &postgres).and_then(move |_| {
+                    services::sessions::create(
+                        user.id,
+                        ip_address,
+                        user_agent,
+                        &postgres,
+                        jwt_private,
+                    )
+                    .map(move |(token, refresh_token)| (token, refresh_token, user))
                 })
+            })
         })
 }
 
@@ -252,6 +331,25 @@ pub fn get(id: Uuid, postgres: &PgExecutorAddr) -> impl Future<Item = User, Erro
     User::find_by_id(id, postgres).from_err()
 }
 
-pub fn delete(id: Uuid, postgres: &PgExecutorAddr) -> impl Future<Item = usize, Error = Error> {
-    User::delete(id, postgres).from_err()
+pub fn delete(
+    id: Uuid,
+    ip_address: Option<String>,
+    postgres: &PgExecutorAddr,
+) -> impl Future<Item = usize, Error = Error> {
+    let postgres = postgres.clone();
+
+    User::find_by_id(id, &postgres)
+        .from_err()
+        .and_then(move |user| {
+            services::audit_events::audited(
+                move |conn| users::delete(id, conn),
+                move |_| {
+                    let mut event =
+                        AuditEventPayload::new(AuditAction::UserDeleted, Some(id), ip_address);
+                    event.old_values = Some(json!({ "email": user.email }));
+                    event
+                },
+                &postgres,
+            )
+        })
 }
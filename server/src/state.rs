@@ -1,8 +1,10 @@
-use config::{BtcConfig, EthConfig, ServerConfig};
+use config::{BtcConfig, EthConfig, LightningConfig, ServerConfig, SignerConfig};
 use core::db::postgres::PgExecutorAddr;
 use currency_api_client::CurrencyApiClientAddr;
+use lightning_api_client::LightningClientAddr;
 use mailer::MailerAddr;
-use types::{currency::Crypto, PrivateKey, PublicKey};
+use rate_limiter::RateLimiter;
+use types::{currency::Crypto, ethereum::Network as EthNetwork, PrivateKey, PublicKey};
 
 #[derive(Clone)]
 pub struct AppState {
@@ -12,15 +14,45 @@ pub struct AppState {
     pub jwt_public: PublicKey,
     pub jwt_private: PrivateKey,
     pub btc_config: Option<BtcConfig>,
-    pub eth_config: Option<EthConfig>,
+    pub ltc_config: Option<BtcConfig>,
+    pub bch_config: Option<BtcConfig>,
+    pub doge_config: Option<BtcConfig>,
+    pub evm_configs: Vec<EthConfig>,
+    pub lightning_config: Option<LightningConfig>,
+    pub lightning_client: Option<LightningClientAddr>,
+    pub signer_config: Option<SignerConfig>,
     pub currency_api_client: CurrencyApiClientAddr,
+    pub rate_limiter: RateLimiter,
 }
 
 impl AppState {
     pub fn supports(&self, crypto: &Crypto) -> bool {
         match crypto {
-            Crypto::Btc => self.btc_config.is_some(),
-            Crypto::Eth => self.eth_config.is_some(),
+            Crypto::Eth => !self.evm_configs.is_empty(),
+            Crypto::BtcLightning => self.lightning_client.is_some(),
+            _ => self.bitcoin_like_config(crypto).is_some(),
+        }
+    }
+
+    pub fn bitcoin_like_config(&self, crypto: &Crypto) -> Option<BtcConfig> {
+        match crypto {
+            Crypto::Btc => self.btc_config.clone(),
+            Crypto::Ltc => self.ltc_config.clone(),
+            Crypto::Bch => self.bch_config.clone(),
+            Crypto::Doge => self.doge_config.clone(),
+            Crypto::Eth | Crypto::BtcLightning => None,
+        }
+    }
+
+    // Payments without an explicit chain id go to the first configured EVM network.
+    pub fn evm_config(&self, network: Option<EthNetwork>) -> Option<EthConfig> {
+        match network {
+            Some(network) => self
+                .evm_configs
+                .iter()
+                .find(|config| config.network == network)
+                .cloned(),
+            None => self.evm_configs.first().cloned(),
         }
     }
 }
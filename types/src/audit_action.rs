@@ -0,0 +1,93 @@
+use std::{fmt, io::Write};
+
+use diesel::{
+    deserialize::{self, FromSql},
+    pg::Pg,
+    serialize::{self, Output, ToSql},
+    sql_types::Text,
+    types::VarChar,
+};
+
+#[derive(
+    FromSqlRow, AsExpression, Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, Hash,
+)]
+#[serde(rename_all = "snake_case")]
+#[sql_type = "VarChar"]
+pub enum AuditAction {
+    StoreUpdated,
+    StoreDeleted,
+    ClientTokenCreated,
+    ClientTokenDeleted,
+    PasswordResetRequested,
+    PasswordChanged,
+    UserDeleted,
+    PayoutAddressChangeRequested,
+    PayoutAddressChangeConfirmed,
+    PayoutAddressChangeCancelled,
+    NotificationPreferencesUpdated,
+    PaymentDiscrepancyDetected,
+    WalletExported,
+    PayoutApproved,
+    DataExported,
+}
+
+impl AuditAction {
+    pub fn to_str(&self) -> &str {
+        match *self {
+            AuditAction::StoreUpdated => "store_updated",
+            AuditAction::StoreDeleted => "store_deleted",
+            AuditAction::ClientTokenCreated => "client_token_created",
+            AuditAction::ClientTokenDeleted => "client_token_deleted",
+            AuditAction::PasswordResetRequested => "password_reset_requested",
+            AuditAction::PasswordChanged => "password_changed",
+            AuditAction::UserDeleted => "user_deleted",
+            AuditAction::PayoutAddressChangeRequested => "payout_address_change_requested",
+            AuditAction::PayoutAddressChangeConfirmed => "payout_address_change_confirmed",
+            AuditAction::PayoutAddressChangeCancelled => "payout_address_change_cancelled",
+            AuditAction::NotificationPreferencesUpdated => "notification_preferences_updated",
+            AuditAction::PaymentDiscrepancyDetected => "payment_discrepancy_detected",
+            AuditAction::WalletExported => "wallet_exported",
+            AuditAction::PayoutApproved => "payout_approved",
+            AuditAction::DataExported => "data_exported",
+        }
+    }
+}
+
+impl fmt::Display for AuditAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_str())
+    }
+}
+
+impl ToSql<Text, Pg> for AuditAction {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        let text = self.to_str();
+
+        ToSql::<Text, Pg>::to_sql(&text, out)
+    }
+}
+
+impl FromSql<Text, Pg> for AuditAction {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        let text: String = FromSql::<Text, Pg>::from_sql(bytes)?;
+
+        match text.as_ref() {
+            "store_updated" => Ok(AuditAction::StoreUpdated),
+            "store_deleted" => Ok(AuditAction::StoreDeleted),
+            "client_token_created" => Ok(AuditAction::ClientTokenCreated),
+            "client_token_deleted" => Ok(AuditAction::ClientTokenDeleted),
+            "password_reset_requested" => Ok(AuditAction::PasswordResetRequested),
+            "password_changed" => Ok(AuditAction::PasswordChanged),
+            "user_deleted" => Ok(AuditAction::UserDeleted),
+            "payout_address_change_requested" => Ok(AuditAction::PayoutAddressChangeRequested),
+            "payout_address_change_confirmed" => Ok(AuditAction::PayoutAddressChangeConfirmed),
+            "payout_address_change_cancelled" => Ok(AuditAction::PayoutAddressChangeCancelled),
+            "notification_preferences_updated" => Ok(AuditAction::NotificationPreferencesUpdated),
+            "payment_discrepancy_detected" => Ok(AuditAction::PaymentDiscrepancyDetected),
+            "wallet_exported" => Ok(AuditAction::WalletExported),
+            "payout_approved" => Ok(AuditAction::PayoutApproved),
+            "data_exported" => Ok(AuditAction::DataExported),
+            v => Err(format!("unknown value {} for AuditAction found", v).into()),
+        }
+    }
+}
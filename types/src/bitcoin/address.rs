@@ -13,7 +13,7 @@ use diesel::{
 };
 use rust_base58::FromBase58;
 
-use bitcoin::network::Network;
+use bitcoin::chain::ChainParams;
 use h256::H256;
 
 pub enum AddressType {
@@ -25,20 +25,16 @@ pub enum AddressType {
 pub struct Address(String);
 
 impl Address {
-    fn address_type(&self) -> AddressType {
-        match self.chars().next().unwrap() {
-            '1' | 'm' | 'n' => AddressType::P2PKH,
-            '3' | '2' => AddressType::P2SH,
-            _ => panic!("invalid bitcion address found"),
-        }
+    pub fn version(&self) -> Result<u8, String> {
+        let raw = self.from_base58().map_err(|e| format!("{:?}", e))?;
+
+        raw.first()
+            .cloned()
+            .ok_or_else(|| String::from("empty bitcoin address"))
     }
 
-    fn network(&self) -> Network {
-        match self.chars().next().unwrap() {
-            'm' | 'n' => Network::Mainnet,
-            '2' => Network::Test,
-            _ => panic!("invalid bitcoin address found"),
-        }
+    pub fn is_valid_for(&self, chain: &ChainParams) -> bool {
+        self.version() == Ok(chain.p2pkh_version)
     }
 }
 
@@ -57,10 +53,12 @@ impl FromStr for Address {
         }
 
         // Only support P2PKH for now.
-        match s.chars().next().unwrap() {
-            '1' | 'm' | 'n' => (),
-            _ => return Err(String::from("address type not supported")),
-        };
+        if !ChainParams::all()
+            .iter()
+            .any(|chain| chain.p2pkh_version == raw[0])
+        {
+            return Err(String::from("address type not supported"));
+        }
 
         Ok(Address(s.to_owned()))
     }
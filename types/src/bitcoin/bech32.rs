@@ -0,0 +1,92 @@
+// Segwit addresses (BIP173), for version 0 witness programs.
+
+const CHARSET: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+const CHECKSUM_LENGTH: usize = 6;
+
+pub fn encode_segwit_address(hrp: &str, program: &[u8]) -> String {
+    let mut data = vec![0];
+    data.extend(to_base32(program));
+
+    let mut values = hrp_expand(hrp);
+    values.extend_from_slice(&data);
+    values.extend_from_slice(&[0; CHECKSUM_LENGTH]);
+    let checksum = polymod(&values) ^ 1;
+
+    for i in 0..CHECKSUM_LENGTH {
+        data.push(((checksum >> (5 * (5 - i))) & 31) as u8);
+    }
+
+    let mut address = format!("{}1", hrp);
+    address.extend(data.iter().map(|d| CHARSET[*d as usize] as char));
+    address
+}
+
+fn polymod(values: &[u8]) -> u32 {
+    let mut checksum: u32 = 1;
+
+    for value in values {
+        let top = checksum >> 25;
+        checksum = (checksum & 0x1ffffff) << 5 ^ u32::from(*value);
+
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                checksum ^= generator;
+            }
+        }
+    }
+
+    checksum
+}
+
+fn hrp_expand(hrp: &str) -> Vec<u8> {
+    let mut values: Vec<u8> = hrp.bytes().map(|b| b >> 5).collect();
+    values.push(0);
+    values.extend(hrp.bytes().map(|b| b & 31));
+    values
+}
+
+// Regroups bytes into the 5 bit values bech32 characters stand for, padding the last one.
+fn to_base32(data: &[u8]) -> Vec<u8> {
+    let mut acc: u32 = 0;
+    let mut bits = 0;
+    let mut converted = Vec::new();
+
+    for value in data {
+        acc = (acc << 8) | u32::from(*value);
+        bits += 8;
+
+        while bits >= 5 {
+            bits -= 5;
+            converted.push(((acc >> bits) & 31) as u8);
+        }
+    }
+
+    if bits > 0 {
+        converted.push(((acc << (5 - bits)) & 31) as u8);
+    }
+
+    converted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustc_hex::FromHex;
+
+    #[test]
+    fn encode_p2wsh_addresses() {
+        let program = "1863143c14c5166804bd19203356da136c985678cd4d27a1b8c6329604903262"
+            .from_hex()
+            .unwrap();
+
+        assert_eq!(
+            "bc1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3qccfmv3",
+            encode_segwit_address("bc", &program)
+        );
+        assert_eq!(
+            "tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7",
+            encode_segwit_address("tb", &program)
+        );
+    }
+}
@@ -0,0 +1,204 @@
+// Bitcoin Cash addresses (CashAddr), which nodes return for outputs unless run with
+// -usecashaddr=0. Payments are stored with legacy addresses, which cashaddrs map to one to one.
+
+use rust_base58::ToBase58;
+
+use bitcoin::chain::ChainParams;
+use h256::H256;
+
+const CHARSET: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const GENERATOR: [u64; 5] = [
+    0x98f2bc8e61,
+    0x79b76d99e2,
+    0xf33e5fb3c4,
+    0xae2eabe2a8,
+    0x1e4f43e470,
+];
+const CHECKSUM_LENGTH: usize = 8;
+
+const TYPE_P2PKH: u8 = 0;
+const TYPE_P2SH: u8 = 8;
+
+// The legacy address of a cashaddr of the chain, with or without its prefix.
+pub fn to_legacy(chain: &ChainParams, address: &str) -> Result<String, String> {
+    let prefix = chain
+        .cashaddr_prefix
+        .ok_or_else(|| String::from("chain without cashaddr addresses"))?;
+
+    let (version, hash) = decode(prefix, address)?;
+
+    let version = match version {
+        TYPE_P2PKH => chain.p2pkh_version,
+        TYPE_P2SH => chain.p2sh_version,
+        _ => return Err(String::from("address type not supported")),
+    };
+
+    let mut raw = vec![version];
+    raw.extend_from_slice(&hash);
+    let checksum = H256::from_data(&raw);
+    raw.extend_from_slice(&checksum[0..4]);
+
+    Ok(raw.to_base58())
+}
+
+// The address the way payments are stored. Anything but a cashaddr is returned as it is.
+pub fn legacy_address(chain: &ChainParams, address: &str) -> String {
+    match chain.cashaddr_prefix {
+        Some(_) => to_legacy(chain, address).unwrap_or_else(|_| address.to_owned()),
+        None => address.to_owned(),
+    }
+}
+
+// The version byte and the 160 bit hash of the address.
+fn decode(prefix: &str, address: &str) -> Result<(u8, Vec<u8>), String> {
+    if address.to_lowercase() != address && address.to_uppercase() != address {
+        return Err(String::from("mixed case address"));
+    }
+
+    let address = address.to_lowercase();
+    let payload = match address.find(':') {
+        Some(separator) if &address[..separator] == prefix => &address[separator + 1..],
+        Some(_) => return Err(String::from("address of another chain")),
+        None => &address[..],
+    };
+
+    let values = payload
+        .bytes()
+        .map(|c| {
+            CHARSET
+                .iter()
+                .position(|d| *d == c)
+                .map(|value| value as u8)
+                .ok_or_else(|| String::from("invalid cashaddr character"))
+        })
+        .collect::<Result<Vec<u8>, String>>()?;
+
+    if values.len() <= CHECKSUM_LENGTH {
+        return Err(String::from("invalid cashaddr length"));
+    }
+
+    let mut checked = prefix_expand(prefix);
+    checked.extend_from_slice(&values);
+
+    if polymod(&checked) != 0 {
+        return Err(String::from("invalid cashaddr checksum"));
+    }
+
+    let data = from_base32(&values[..values.len() - CHECKSUM_LENGTH])?;
+
+    // Only 160 bit hashes, the size bits of the version being 0, are in use.
+    if data.len() != 21 || data[0] & 0x87 != 0 {
+        return Err(String::from("invalid cashaddr hash size"));
+    }
+
+    Ok((data[0], data[1..].to_vec()))
+}
+
+fn polymod(values: &[u8]) -> u64 {
+    let mut checksum: u64 = 1;
+
+    for value in values {
+        let top = checksum >> 35;
+        checksum = ((checksum & 0x07_ffff_ffff) << 5) ^ u64::from(*value);
+
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                checksum ^= generator;
+            }
+        }
+    }
+
+    checksum ^ 1
+}
+
+fn prefix_expand(prefix: &str) -> Vec<u8> {
+    let mut values: Vec<u8> = prefix.bytes().map(|b| b & 31).collect();
+    values.push(0);
+    values
+}
+
+// Regroups 5 bit values into bytes. What is left over has to be zero padding.
+fn from_base32(values: &[u8]) -> Result<Vec<u8>, String> {
+    let mut acc: u32 = 0;
+    let mut bits = 0;
+    let mut converted = Vec::new();
+
+    for value in values {
+        acc = (acc << 5) | u32::from(*value);
+        bits += 5;
+
+        if bits >= 8 {
+            bits -= 8;
+            converted.push(((acc >> bits) & 255) as u8);
+        }
+    }
+
+    if bits >= 5 || acc & ((1 << bits) - 1) != 0 {
+        return Err(String::from("invalid cashaddr padding"));
+    }
+
+    Ok(converted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::network::Network;
+    use currency::Crypto;
+
+    #[test]
+    fn cashaddrs_map_to_legacy_addresses() {
+        let chain = ChainParams::new(Crypto::Bch, Network::Mainnet).unwrap();
+
+        let vectors = [
+            (
+                "bitcoincash:qpm2qsznhks23z7629mms6s4cwef74vcwvy22gdx6a",
+                "1BpEi6DfDAUFd7GtittLSdBeYJvcoaVggu",
+            ),
+            (
+                "bitcoincash:qr95sy3j9xwd2ap32xkykttr4cvcu7as4y0qverfuy",
+                "1KXrWXciRDZUpQwQmuM1DbwsKDLYAYsVLR",
+            ),
+            (
+                "qqq3728yw0y47sqn6l2na30mcw6zm78dzqre909m2r",
+                "16w1D5WRVKJuZUsSRzdLp9w3YGcgoxDXb",
+            ),
+            (
+                "bitcoincash:ppm2qsznhks23z7629mms6s4cwef74vcwvn0h829pq",
+                "3CWFddi6m4ndiGyKqzYvsFYagqDLPVMTzC",
+            ),
+            (
+                "BITCOINCASH:PR95SY3J9XWD2AP32XKYKTTR4CVCU7AS4YC93KY28E",
+                "3LDsS579y7sruadqu11beEJoTjdFiFCdX4",
+            ),
+            (
+                "bitcoincash:pqq3728yw0y47sqn6l2na30mcw6zm78dzq5ucqzc37",
+                "31nwvkZwyPdgzjBJZXfDmSWsC4ZLKpYyUw",
+            ),
+        ];
+
+        for (cashaddr, legacy) in vectors.iter() {
+            assert_eq!(to_legacy(&chain, cashaddr).unwrap(), *legacy);
+        }
+    }
+
+    #[test]
+    fn invalid_cashaddrs_are_rejected() {
+        let chain = ChainParams::new(Crypto::Bch, Network::Mainnet).unwrap();
+
+        let invalid = [
+            // Checksum
+            "bitcoincash:qpm2qsznhks23z7629mms6s4cwef74vcwvy22gdx6c",
+            // Prefix
+            "bchtest:qpm2qsznhks23z7629mms6s4cwef74vcwvy22gdx6a",
+            // Mixed case
+            "bitcoincash:Qpm2qsznhks23z7629mms6s4cwef74vcwvy22gdx6a",
+            // Legacy
+            "1BpEi6DfDAUFd7GtittLSdBeYJvcoaVggu",
+        ];
+
+        for address in invalid.iter() {
+            assert!(to_legacy(&chain, address).is_err());
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use bitcoin::network::Network;
+use currency::Crypto;
+use h256::H256;
+
+const SIGHASH_ALL: u32 = 0x01;
+const SIGHASH_FORKID: u32 = 0x40;
+
+// Parameters which tell bitcoin-like chains apart while they share the bitcoind JSON-RPC stack.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChainParams {
+    pub crypto: Crypto,
+    pub network: Network,
+    pub p2pkh_version: u8,
+    pub p2sh_version: u8,
+    pub bip44_coin_type: u32,
+    // Human readable part of segwit addresses, for chains with segwit.
+    pub bech32_hrp: Option<&'static str>,
+    // Set for chains which replay-protect signatures with SIGHASH_FORKID (Bitcoin Cash).
+    pub fork_id: Option<u32>,
+    // Prefix of cashaddr addresses, for chains whose nodes return them (Bitcoin Cash).
+    pub cashaddr_prefix: Option<&'static str>,
+}
+
+impl ChainParams {
+    pub fn new(crypto: Crypto, network: Network) -> Option<Self> {
+        let (p2pkh_version, p2sh_version, bip44_coin_type, bech32_hrp, fork_id, cashaddr_prefix) =
+            match (crypto, network) {
+                (Crypto::Btc, Network::Mainnet) => (0, 5, 0, Some("bc"), None, None),
+                (Crypto::Btc, Network::Test) => (111, 196, 1, Some("tb"), None, None),
+                (Crypto::Ltc, Network::Mainnet) => (48, 50, 2, Some("ltc"), None, None),
+                (Crypto::Ltc, Network::Test) => (111, 58, 1, Some("tltc"), None, None),
+                (Crypto::Bch, Network::Mainnet) => (0, 5, 145, None, Some(0), Some("bitcoincash")),
+                (Crypto::Bch, Network::Test) => (111, 196, 1, None, Some(0), Some("bchtest")),
+                (Crypto::Doge, Network::Mainnet) => (30, 22, 3, None, None, None),
+                (Crypto::Doge, Network::Test) => (113, 196, 1, None, None, None),
+                (Crypto::Eth, _) | (Crypto::BtcLightning, _) => return None,
+            };
+
+        Some(ChainParams {
+            crypto,
+            network,
+            p2pkh_version,
+            p2sh_version,
+            bip44_coin_type,
+            bech32_hrp,
+            fork_id,
+            cashaddr_prefix,
+        })
+    }
+
+    pub fn all() -> Vec<Self> {
+        let mut chains = Vec::new();
+
+        for crypto in &[Crypto::Btc, Crypto::Ltc, Crypto::Bch, Crypto::Doge] {
+            for network in &[Network::Mainnet, Network::Test] {
+                chains.extend(ChainParams::new(*crypto, *network));
+            }
+        }
+
+        chains
+    }
+
+    pub fn sighash_type(&self) -> u32 {
+        match self.fork_id {
+            Some(fork_id) => SIGHASH_ALL | SIGHASH_FORKID | (fork_id << 8),
+            None => SIGHASH_ALL,
+        }
+    }
+
+    // Account of payments derived under their creation time. Bitcoin kept the store's own hd path.
+    pub fn hd_path(&self, store_hd_path: &str) -> String {
+        match self.crypto {
+            Crypto::Btc => store_hd_path.to_owned(),
+            _ => format!("m/44'/{}'/0'/0", self.bip44_coin_type),
+        }
+    }
+
+    // Account of new payments, so that their addresses can be recovered by BIP44 wallets.
+    pub fn bip44_hd_path(&self) -> String {
+        format!("m/44'/{}'/0'/0", self.bip44_coin_type)
+    }
+
+    pub fn explorer_transaction_url(&self, hash: &H256) -> String {
+        match (self.crypto, self.network) {
+            (Crypto::Ltc, Network::Mainnet) => format!("https://sochain.com/tx/LTC/{}", hash),
+            (Crypto::Ltc, Network::Test) => format!("https://sochain.com/tx/LTCTEST/{}", hash),
+            (Crypto::Bch, Network::Mainnet) => {
+                format!("https://explorer.bitcoin.com/bch/tx/{}", hash)
+            }
+            (Crypto::Bch, Network::Test) => {
+                format!("https://explorer.bitcoin.com/tbch/tx/{}", hash)
+            }
+            (Crypto::Doge, Network::Mainnet) => format!("https://sochain.com/tx/DOGE/{}", hash),
+            (Crypto::Doge, Network::Test) => format!("https://sochain.com/tx/DOGETEST/{}", hash),
+            _ => self.network.explorer_transaction_url(hash),
+        }
+    }
+}
@@ -1,7 +1,14 @@
 pub mod address;
+pub mod bech32;
+pub mod cashaddr;
+pub mod chain;
 pub mod network;
 pub mod var_int;
 
 pub use self::address::Address;
+pub use self::chain::ChainParams;
 pub use self::network::Network;
 pub use self::var_int::VarInt;
+
+// Lightning amounts are in millisatoshis.
+pub const MSAT_PER_BTC: &str = "100000000000";
@@ -7,6 +7,8 @@ use diesel::{
     types::VarChar,
 };
 
+use h256::H256;
+
 #[derive(FromSqlRow, AsExpression, Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Debug)]
 #[serde(rename_all = "lowercase")]
 #[sql_type = "VarChar"]
@@ -22,6 +24,13 @@ impl Network {
             Network::Test => "test",
         }
     }
+
+    pub fn explorer_transaction_url(&self, hash: &H256) -> String {
+        match *self {
+            Network::Mainnet => format!("https://blockstream.info/tx/{}", hash),
+            Network::Test => format!("https://blockstream.info/testnet/tx/{}", hash),
+        }
+    }
 }
 
 impl fmt::Display for Network {
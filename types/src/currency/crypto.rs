@@ -15,6 +15,11 @@ use diesel::{
 pub enum Crypto {
     Btc,
     Eth,
+    Ltc,
+    Bch,
+    Doge,
+    // Bitcoin paid through the Lightning Network.
+    BtcLightning,
 }
 
 impl Crypto {
@@ -22,6 +27,10 @@ impl Crypto {
         match *self {
             Crypto::Btc => "btc",
             Crypto::Eth => "eth",
+            Crypto::Ltc => "ltc",
+            Crypto::Bch => "bch",
+            Crypto::Doge => "doge",
+            Crypto::BtcLightning => "btc_lightning",
         }
     }
 }
@@ -55,6 +64,10 @@ impl FromStr for Crypto {
         match s.as_ref() {
             "btc" => Ok(Crypto::Btc),
             "eth" => Ok(Crypto::Eth),
+            "ltc" => Ok(Crypto::Ltc),
+            "bch" => Ok(Crypto::Bch),
+            "doge" => Ok(Crypto::Doge),
+            "btc_lightning" => Ok(Crypto::BtcLightning),
             _ => Err(String::from("invalid value for crypto")),
         }
     }
@@ -1,3 +1,6 @@
 pub mod network;
 
-pub use self::network::Network;
\ No newline at end of file
+pub use self::network::Network;
+
+// EVM networks share the Ethereum account, like wallets do.
+pub const BIP44_HD_PATH: &str = "m/44'/60'/0'/0";
@@ -7,39 +7,46 @@ use diesel::{
     types::VarChar,
 };
 
-#[derive(FromSqlRow, AsExpression, Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Debug)]
-#[serde(rename_all = "lowercase")]
+use h256::H256;
+
+// An EVM network, identified by its EIP-155 chain id.
+#[derive(FromSqlRow, AsExpression, Serialize, Copy, Clone, PartialEq, Eq, Hash, Debug)]
 #[sql_type = "VarChar"]
-pub enum Network {
-    Mainnet,
-    Ropsten,
-}
+pub struct Network(pub u64);
 
 impl Network {
     pub fn chain_id(&self) -> u64 {
-        match self {
-            Network::Mainnet => 1,
-            Network::Ropsten => 3,
-        }
+        self.0
     }
 
-    pub fn to_str(&self) -> &str {
-        match *self {
-            Network::Mainnet => "mainnet",
-            Network::Ropsten => "ropsten",
+    // Block explorers of well-known networks. Others have to be configured.
+    pub fn default_explorer_url(&self) -> Option<&str> {
+        match self.0 {
+            1 => Some("https://etherscan.io"),
+            3 => Some("https://ropsten.etherscan.io"),
+            5 => Some("https://goerli.etherscan.io"),
+            56 => Some("https://bscscan.com"),
+            137 => Some("https://polygonscan.com"),
+            42161 => Some("https://arbiscan.io"),
+            11155111 => Some("https://sepolia.etherscan.io"),
+            _ => None,
         }
     }
+
+    pub fn explorer_transaction_url(&self, explorer_url: &str, hash: &H256) -> String {
+        format!("{}/tx/{}", explorer_url.trim_end_matches('/'), hash.hex())
+    }
 }
 
 impl fmt::Display for Network {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.to_str())
+        write!(f, "{}", self.0)
     }
 }
 
 impl ToSql<VarChar, Pg> for Network {
     fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
-        let text = self.to_str();
+        let text = self.to_string();
 
         ToSql::<VarChar, Pg>::to_sql(&text, out)
     }
@@ -57,10 +64,59 @@ impl FromStr for Network {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Network, Self::Err> {
+        // Names used before networks were identified by chain id.
         match s.as_ref() {
-            "mainnet" => Ok(Network::Mainnet),
-            "ropsten" => Ok(Network::Ropsten),
-            _ => Err(String::from("invalid value for ethereum network")),
+            "mainnet" => Ok(Network(1)),
+            "ropsten" => Ok(Network(3)),
+            _ => s
+                .parse::<u64>()
+                .map(Network)
+                .map_err(|_| String::from("invalid value for ethereum network")),
+        }
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Network {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use std::fmt::Formatter;
+
+        struct Visitor;
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = Network;
+
+            fn expecting(&self, formatter: &mut Formatter) -> fmt::Result {
+                formatter.write_str("chain id or ethereum network name")
+            }
+
+            fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                Ok(Network(v))
+            }
+
+            fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                if v < 0 {
+                    return Err(E::custom("chain id must be positive"));
+                }
+
+                Ok(Network(v as u64))
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                Network::from_str(v).map_err(E::custom)
+            }
         }
+
+        deserializer.deserialize_any(Visitor)
     }
 }
@@ -0,0 +1,52 @@
+use std::io::Write;
+
+use diesel::{
+    deserialize::{self, FromSql},
+    pg::Pg,
+    serialize::{self, Output, ToSql},
+    sql_types::Text,
+    types::VarChar,
+};
+
+#[derive(FromSqlRow, AsExpression, Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+#[sql_type = "VarChar"]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+impl ExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match *self {
+            ExportFormat::Csv => "text/csv",
+            ExportFormat::Json => "application/json",
+        }
+    }
+
+    pub fn to_str(&self) -> &str {
+        match *self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+        }
+    }
+}
+
+impl ToSql<Text, Pg> for ExportFormat {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        ToSql::<Text, Pg>::to_sql(&self.to_str(), out)
+    }
+}
+
+impl FromSql<Text, Pg> for ExportFormat {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        let text: String = FromSql::<Text, Pg>::from_sql(bytes)
+            .map_err(|_| String::from("failed to convert to text"))?;
+
+        match text.as_ref() {
+            "csv" => Ok(ExportFormat::Csv),
+            "json" => Ok(ExportFormat::Json),
+            v => Err(format!("unknown value {} for ExportFormat found", v).into()),
+        }
+    }
+}
@@ -0,0 +1,46 @@
+use std::io::Write;
+
+use diesel::{
+    deserialize::{self, FromSql},
+    pg::Pg,
+    serialize::{self, Output, ToSql},
+    sql_types::Text,
+    types::VarChar,
+};
+
+// What an accounting export lists.
+#[derive(FromSqlRow, AsExpression, Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+#[sql_type = "VarChar"]
+pub enum ExportKind {
+    Payments,
+    Payouts,
+}
+
+impl ExportKind {
+    pub fn to_str(&self) -> &str {
+        match *self {
+            ExportKind::Payments => "payments",
+            ExportKind::Payouts => "payouts",
+        }
+    }
+}
+
+impl ToSql<Text, Pg> for ExportKind {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        ToSql::<Text, Pg>::to_sql(&self.to_str(), out)
+    }
+}
+
+impl FromSql<Text, Pg> for ExportKind {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        let text: String = FromSql::<Text, Pg>::from_sql(bytes)
+            .map_err(|_| String::from("failed to convert to text"))?;
+
+        match text.as_ref() {
+            "payments" => Ok(ExportKind::Payments),
+            "payouts" => Ok(ExportKind::Payouts),
+            v => Err(format!("unknown value {} for ExportKind found", v).into()),
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use std::io::Write;
+
+use diesel::{
+    deserialize::{self, FromSql},
+    pg::Pg,
+    serialize::{self, Output, ToSql},
+    sql_types::Text,
+    types::VarChar,
+};
+
+// Accounts of a store's ledger, debits being positive amounts and credits negative ones.
+#[derive(
+    FromSqlRow, AsExpression, Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, Hash,
+)]
+#[serde(rename_all = "snake_case")]
+#[sql_type = "VarChar"]
+pub enum LedgerAccount {
+    // What customers paid, credited as payments are.
+    Payments,
+    // Held on Finch's addresses until paid out or refunded.
+    Pending,
+    // Sent to the store's payout addresses.
+    Settled,
+    // Sent back to customers.
+    Refunded,
+    // Spent on transaction fees of payouts and refunds.
+    NetworkFees,
+    // Left on Finch's addresses by transactions which spent less than their fee cap.
+    Unspent,
+}
+
+impl ToSql<Text, Pg> for LedgerAccount {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        let text = match *self {
+            LedgerAccount::Payments => "payments",
+            LedgerAccount::Pending => "pending",
+            LedgerAccount::Settled => "settled",
+            LedgerAccount::Refunded => "refunded",
+            LedgerAccount::NetworkFees => "network_fees",
+            LedgerAccount::Unspent => "unspent",
+        };
+
+        ToSql::<Text, Pg>::to_sql(&text, out)
+    }
+}
+
+impl FromSql<Text, Pg> for LedgerAccount {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        let text: String = FromSql::<Text, Pg>::from_sql(bytes)
+            .map_err(|_| String::from("failed to convert to text"))?;
+
+        match text.as_ref() {
+            "payments" => Ok(LedgerAccount::Payments),
+            "pending" => Ok(LedgerAccount::Pending),
+            "settled" => Ok(LedgerAccount::Settled),
+            "refunded" => Ok(LedgerAccount::Refunded),
+            "network_fees" => Ok(LedgerAccount::NetworkFees),
+            "unspent" => Ok(LedgerAccount::Unspent),
+            v => Err(format!("unknown value {} for LedgerAccount found", v).into()),
+        }
+    }
+}
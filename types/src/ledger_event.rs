@@ -0,0 +1,52 @@
+use std::io::Write;
+
+use diesel::{
+    deserialize::{self, FromSql},
+    pg::Pg,
+    serialize::{self, Output, ToSql},
+    sql_types::Text,
+    types::VarChar,
+};
+
+// What a set of postings records, each payout having at most one of each.
+#[derive(
+    FromSqlRow, AsExpression, Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, Hash,
+)]
+#[serde(rename_all = "snake_case")]
+#[sql_type = "VarChar"]
+pub enum LedgerEvent {
+    // A payment output was credited.
+    Credit,
+    Payout,
+    Refund,
+    // The network fee of a sent payout or refund turned out lower than the one posted.
+    FeeAdjustment,
+}
+
+impl ToSql<Text, Pg> for LedgerEvent {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        let text = match *self {
+            LedgerEvent::Credit => "credit",
+            LedgerEvent::Payout => "payout",
+            LedgerEvent::Refund => "refund",
+            LedgerEvent::FeeAdjustment => "fee_adjustment",
+        };
+
+        ToSql::<Text, Pg>::to_sql(&text, out)
+    }
+}
+
+impl FromSql<Text, Pg> for LedgerEvent {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        let text: String = FromSql::<Text, Pg>::from_sql(bytes)
+            .map_err(|_| String::from("failed to convert to text"))?;
+
+        match text.as_ref() {
+            "credit" => Ok(LedgerEvent::Credit),
+            "payout" => Ok(LedgerEvent::Payout),
+            "refund" => Ok(LedgerEvent::Refund),
+            "fee_adjustment" => Ok(LedgerEvent::FeeAdjustment),
+            v => Err(format!("unknown value {} for LedgerEvent found", v).into()),
+        }
+    }
+}
@@ -24,26 +24,42 @@ extern crate sha2;
 #[macro_use]
 extern crate uint;
 
+mod audit_action;
 pub mod bitcoin;
 mod clients;
 pub mod currency;
 pub mod ethereum;
+mod export_format;
+mod export_kind;
 mod h160;
 mod h256;
+mod ledger_account;
+mod ledger_event;
+mod locale;
 mod payment_status;
 mod payout_actions;
 mod payout_status;
+mod rescan_status;
+mod settlement_interval;
 mod u128;
 mod u256;
 
 pub type PrivateKey = Vec<u8>;
 pub type PublicKey = Vec<u8>;
 
+pub use self::audit_action::AuditAction;
 pub use self::clients::Client;
+pub use self::export_format::ExportFormat;
+pub use self::export_kind::ExportKind;
 pub use self::h160::H160;
 pub use self::h256::H256;
+pub use self::ledger_account::LedgerAccount;
+pub use self::ledger_event::LedgerEvent;
+pub use self::locale::Locale;
 pub use self::payment_status::PaymentStatus;
 pub use self::payout_actions::PayoutAction;
 pub use self::payout_status::PayoutStatus;
+pub use self::rescan_status::RescanStatus;
+pub use self::settlement_interval::SettlementInterval;
 pub use self::u128::U128;
 pub use self::u256::U256;
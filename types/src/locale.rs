@@ -0,0 +1,68 @@
+use std::{fmt, io::Write, str::FromStr};
+
+use diesel::{
+    deserialize::{self, FromSql},
+    pg::Pg,
+    serialize::{self, Output, ToSql},
+    sql_types::Text,
+    types::VarChar,
+};
+
+#[derive(
+    FromSqlRow, AsExpression, Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, Hash,
+)]
+#[serde(rename_all = "snake_case")]
+#[sql_type = "VarChar"]
+pub enum Locale {
+    En,
+    Ja,
+}
+
+impl Locale {
+    pub fn to_str(&self) -> &str {
+        match *self {
+            Locale::En => "en",
+            Locale::Ja => "ja",
+        }
+    }
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        Locale::En
+    }
+}
+
+impl FromStr for Locale {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "en" => Ok(Locale::En),
+            "ja" => Ok(Locale::Ja),
+            _ => Err(format!("unknown locale {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for Locale {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_str())
+    }
+}
+
+impl ToSql<Text, Pg> for Locale {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        let text = self.to_str();
+
+        ToSql::<Text, Pg>::to_sql(&text, out)
+    }
+}
+
+impl FromSql<Text, Pg> for Locale {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        let text: String = FromSql::<Text, Pg>::from_sql(bytes)?;
+
+        text.parse::<Locale>().map_err(|e| e.into())
+    }
+}
@@ -16,6 +16,10 @@ pub enum PayoutStatus {
     PaidOut,
     Refunded,
     InsufficientFunds,
+    // Partially signed by Finch, waiting for the other keys of a multisig invoice address.
+    AwaitingCosigners,
+    // Left unsigned for review, in stores with manual payout approval.
+    AwaitingApproval,
 }
 
 impl fmt::Display for PayoutStatus {
@@ -28,6 +32,8 @@ impl fmt::Display for PayoutStatus {
                 PayoutStatus::PaidOut => "paid_out",
                 PayoutStatus::Refunded => "refunded",
                 PayoutStatus::InsufficientFunds => "insufficient_funds",
+                PayoutStatus::AwaitingCosigners => "awaiting_cosigners",
+                PayoutStatus::AwaitingApproval => "awaiting_approval",
             }
         )
     }
@@ -40,6 +46,8 @@ impl ToSql<Text, Pg> for PayoutStatus {
             PayoutStatus::PaidOut => "paid_out",
             PayoutStatus::Refunded => "refunded",
             PayoutStatus::InsufficientFunds => "insufficient_funds",
+            PayoutStatus::AwaitingCosigners => "awaiting_cosigners",
+            PayoutStatus::AwaitingApproval => "awaiting_approval",
         };
 
         ToSql::<Text, Pg>::to_sql(&text, out)
@@ -56,6 +64,8 @@ impl FromSql<Text, Pg> for PayoutStatus {
             "paid_out" => Ok(PayoutStatus::PaidOut),
             "refunded" => Ok(PayoutStatus::Refunded),
             "insufficient_funds" => Ok(PayoutStatus::InsufficientFunds),
+            "awaiting_cosigners" => Ok(PayoutStatus::AwaitingCosigners),
+            "awaiting_approval" => Ok(PayoutStatus::AwaitingApproval),
             v => Err(format!("unknown value {} for Currency found", v).into()),
         }
     }
@@ -0,0 +1,58 @@
+use std::{fmt, io::Write};
+
+use diesel::{
+    deserialize::{self, FromSql},
+    pg::Pg,
+    serialize::{self, Output, ToSql},
+    sql_types::Text,
+    types::VarChar,
+};
+
+#[derive(FromSqlRow, AsExpression, Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+#[sql_type = "VarChar"]
+pub enum RescanStatus {
+    Pending,
+    Running,
+    Completed,
+    Failed,
+}
+
+impl RescanStatus {
+    pub fn to_str(&self) -> &str {
+        match *self {
+            RescanStatus::Pending => "pending",
+            RescanStatus::Running => "running",
+            RescanStatus::Completed => "completed",
+            RescanStatus::Failed => "failed",
+        }
+    }
+}
+
+impl fmt::Display for RescanStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_str())
+    }
+}
+
+impl ToSql<Text, Pg> for RescanStatus {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        let text = self.to_str();
+
+        ToSql::<Text, Pg>::to_sql(&text, out)
+    }
+}
+
+impl FromSql<Text, Pg> for RescanStatus {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        let text: String = FromSql::<Text, Pg>::from_sql(bytes)?;
+
+        match text.as_ref() {
+            "pending" => Ok(RescanStatus::Pending),
+            "running" => Ok(RescanStatus::Running),
+            "completed" => Ok(RescanStatus::Completed),
+            "failed" => Ok(RescanStatus::Failed),
+            v => Err(format!("unknown value {} for RescanStatus found", v).into()),
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use std::io::Write;
+
+use diesel::{
+    deserialize::{self, FromSql},
+    pg::Pg,
+    serialize::{self, Output, ToSql},
+    sql_types::Text,
+    types::VarChar,
+};
+
+// How often a store's confirmed payouts are sent, at the hour of the day set on the store.
+#[derive(FromSqlRow, AsExpression, Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+#[sql_type = "VarChar"]
+pub enum SettlementInterval {
+    Daily,
+    // On the weekday set on the store.
+    Weekly,
+}
+
+impl ToSql<Text, Pg> for SettlementInterval {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        let text = match *self {
+            SettlementInterval::Daily => "daily",
+            SettlementInterval::Weekly => "weekly",
+        };
+
+        ToSql::<Text, Pg>::to_sql(&text, out)
+    }
+}
+
+impl FromSql<Text, Pg> for SettlementInterval {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        let text: String = FromSql::<Text, Pg>::from_sql(bytes)
+            .map_err(|_| String::from("failed to convert to text"))?;
+
+        match text.as_ref() {
+            "daily" => Ok(SettlementInterval::Daily),
+            "weekly" => Ok(SettlementInterval::Weekly),
+            v => Err(format!("unknown value {} for SettlementInterval found", v).into()),
+        }
+    }
+}